@@ -0,0 +1,50 @@
+// 分片幂等存储的并发吞吐：16 个线程各执行 100_000 次 record + seen，比较不同分片数
+// 运行：cargo bench -p distributed --bench idempotency_sharding
+use distributed::storage::ShardedIdempotency;
+use std::time::{Duration, Instant};
+
+const THREADS: u64 = 16;
+const OPS_PER_THREAD: u64 = 100_000;
+
+fn run<const N: usize>() -> Duration {
+    let store = ShardedIdempotency::<u64, N>::new();
+    let start = Instant::now();
+    std::thread::scope(|s| {
+        for t in 0..THREADS {
+            let store = &store;
+            s.spawn(move || {
+                for i in 0..OPS_PER_THREAD {
+                    let id = t * OPS_PER_THREAD + i;
+                    store.record(id);
+                    assert!(store.seen(&id));
+                }
+            });
+        }
+    });
+    let elapsed = start.elapsed();
+    assert_eq!(store.len() as u64, THREADS * OPS_PER_THREAD);
+    elapsed
+}
+
+fn report(shards: usize, elapsed: Duration, baseline: Duration) {
+    let ops = (THREADS * OPS_PER_THREAD * 2) as f64;
+    println!(
+        "shards={shards:<4} {elapsed:>12.2?} {:>12.0} ops/s  speedup x{:.2}",
+        ops / elapsed.as_secs_f64(),
+        baseline.as_secs_f64() / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    println!(
+        "{THREADS} threads x {OPS_PER_THREAD} record+seen, {} cpus",
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    );
+    let baseline = run::<1>();
+    report(1, baseline, baseline);
+    report(2, run::<2>(), baseline);
+    report(4, run::<4>(), baseline);
+    report(8, run::<8>(), baseline);
+    report(16, run::<16>(), baseline);
+    report(256, run::<256>(), baseline);
+}
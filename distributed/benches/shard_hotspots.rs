@@ -0,0 +1,76 @@
+// Zipf 分布下的分片热点检测：100 万次请求按 Zipf(θ=1.0) 落到 100 个分片，8 个线程并发记录
+// 运行：cargo bench -p distributed --bench shard_hotspots
+use distributed::core::{ClusterTopology, ShardId, ShardStats};
+use std::time::Instant;
+
+const SHARDS: u64 = 100;
+const REQUESTS: u64 = 1_000_000;
+const THREADS: u64 = 8;
+const THETA: f64 = 1.0;
+const WRITE_EVERY: u64 = 10;
+
+/// SplitMix64，序列由种子决定
+struct Rng(u64);
+
+impl Rng {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// 第 `i` 个分片（从 0 起）的访问概率正比于 `1 / (i + 1)^θ` 的累积分布
+fn zipf_cdf() -> Vec<f64> {
+    let mut cdf: Vec<f64> = (1..=SHARDS)
+        .scan(0.0, |sum, rank| {
+            *sum += 1.0 / (rank as f64).powf(THETA);
+            Some(*sum)
+        })
+        .collect();
+    let total = cdf[cdf.len() - 1];
+    cdf.iter_mut().for_each(|c| *c /= total);
+    cdf
+}
+
+fn main() {
+    let stats = ShardStats::for_topology(&ClusterTopology::new(SHARDS));
+    let cdf = zipf_cdf();
+    let start = Instant::now();
+    std::thread::scope(|s| {
+        for t in 0..THREADS {
+            let (stats, cdf) = (&stats, &cdf);
+            s.spawn(move || {
+                let mut rng = Rng(t);
+                for i in 0..REQUESTS / THREADS {
+                    let u = rng.next_f64();
+                    let rank = cdf.partition_point(|c| *c <= u);
+                    let shard = ShardId(rank.min(cdf.len() - 1) as u64);
+                    if i % WRITE_EVERY == 0 {
+                        stats.record_write(shard, 512);
+                    } else {
+                        stats.record_read(shard, 128);
+                    }
+                }
+            });
+        }
+    });
+    let elapsed = start.elapsed();
+    assert_eq!(stats.total_ops(), REQUESTS);
+
+    let top_n = (SHARDS / 10) as usize;
+    let hottest = stats.hottest_shards(top_n);
+    let hot_ops: u64 = hottest.iter().map(|(_, ops)| ops).sum();
+    let share = hot_ops as f64 / REQUESTS as f64;
+    println!(
+        "{REQUESTS} requests over {SHARDS} shards in {elapsed:.2?} ({:.0} ops/s)",
+        REQUESTS as f64 / elapsed.as_secs_f64()
+    );
+    println!("top {top_n} shards: {:.1}% of traffic", share * 100.0);
+    for (shard, ops) in &hottest {
+        println!("  shard {:>3}: {ops}", shard.0);
+    }
+    assert!(share > 0.5, "top 10% of shards received only {share:.3}");
+}
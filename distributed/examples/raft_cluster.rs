@@ -0,0 +1,149 @@
+//! 三节点 Raft 集群演示（transport-grpc 特性）
+//!
+//! 运行：`cargo run -p distributed --example raft_cluster --features transport-grpc -- 7001 7002 7003`
+//!
+//! 流程：在本机三个端口启动节点并等待选出领导者；经管理 RPC `Propose` 提交命令并打印各节点的
+//! 提交进度；随后停掉一个跟随者继续提交，再以空日志重启它，观察领导者补齐其日志。
+
+use distributed::consensus::MinimalRaft;
+use distributed::consensus::raft_grpc::{GrpcRaftTransport, RaftGrpcNode};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::TcpListenerStream;
+
+struct Running {
+    node: RaftGrpcNode,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Running {
+    fn kill(self) {
+        self.node.shutdown();
+        for task in self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn spawn_node(
+    id: &str,
+    port: u16,
+    cluster: &[(String, String)],
+) -> Result<Running, Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    let mut raft = MinimalRaft::new();
+    let (name, mut applied) = (id.to_string(), 0u64);
+    raft.set_apply(Box::new(move |cmd: &Vec<u8>| {
+        applied += 1;
+        println!(
+            "  [{name}] apply #{applied}: {}",
+            String::from_utf8_lossy(cmd)
+        );
+    }));
+    let peers = cluster.iter().filter(|(peer, _)| peer != id).cloned();
+    let node = RaftGrpcNode::new(id, raft, peers);
+    let service = node.server();
+    let server = tokio::spawn(async move {
+        let _ = tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await;
+    });
+    let driver = node.start();
+    Ok(Running {
+        node,
+        tasks: vec![server, driver],
+    })
+}
+
+/// 经管理 RPC 提交命令：先问任一节点，按领导者提示重定向
+async fn propose(
+    admin: &GrpcRaftTransport,
+    ids: &[String],
+    command: &str,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let mut target = ids[0].clone();
+    for _ in 0..20 {
+        match admin.propose(&target, command.as_bytes().to_vec()).await {
+            Ok(reply) if reply.accepted => return Ok(reply.index),
+            Ok(reply) => {
+                if let Some(leader) = reply.leader {
+                    target = leader;
+                    continue;
+                }
+            }
+            Err(_) => {
+                let next = (ids.iter().position(|id| *id == target).unwrap_or(0) + 1) % ids.len();
+                target = ids[next].clone();
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Err(format!("no leader accepted {command}").into())
+}
+
+async fn print_progress(admin: &GrpcRaftTransport, ids: &[String]) {
+    for id in ids {
+        match admin.status(id).await {
+            Ok(s) => println!(
+                "  {id}: {:<9} term={} commit={} last_log={}",
+                s.state, s.term, s.commit_index, s.last_log_index
+            ),
+            Err(e) => println!("  {id}: unreachable ({e})"),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut ports: Vec<u16> = std::env::args()
+        .skip(1)
+        .map(|a| a.parse())
+        .collect::<Result<_, _>>()?;
+    if ports.is_empty() {
+        ports = vec![7001, 7002, 7003];
+    }
+    let cluster: Vec<(String, String)> = ports
+        .iter()
+        .enumerate()
+        .map(|(i, port)| (format!("n{}", i + 1), format!("http://127.0.0.1:{port}")))
+        .collect();
+    let ids: Vec<String> = cluster.iter().map(|(id, _)| id.clone()).collect();
+
+    println!("🚀 启动 {} 个节点", ids.len());
+    let mut nodes = Vec::new();
+    for (id, port) in ids.iter().zip(&ports) {
+        nodes.push(Some(spawn_node(id, *port, &cluster).await?));
+    }
+    let admin = GrpcRaftTransport::new(cluster.clone());
+
+    let index = propose(&admin, &ids, "set x=1").await?;
+    println!("\n✅ 领导者已接受命令，日志索引 {index}");
+    for cmd in ["set y=2", "set z=3"] {
+        propose(&admin, &ids, cmd).await?;
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    print_progress(&admin, &ids).await;
+
+    // 停掉一个跟随者
+    let leader = admin.status(&ids[0]).await?.leader.unwrap_or_default();
+    let victim = ids.iter().position(|id| *id != leader).unwrap();
+    println!("\n💥 停止 {}", ids[victim]);
+    nodes[victim].take().unwrap().kill();
+    for cmd in ["set x=10", "set y=20"] {
+        propose(&admin, &ids, cmd).await?;
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    print_progress(&admin, &ids).await;
+
+    // 以空日志重启：领导者回退 next_index 补齐
+    println!("\n🔄 重启 {}（空日志）", ids[victim]);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    nodes[victim] = Some(spawn_node(&ids[victim], ports[victim], &cluster).await?);
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    print_progress(&admin, &ids).await;
+
+    println!("\n🎉 演示结束");
+    Ok(())
+}
@@ -0,0 +1,310 @@
+//! 集群状态检查（admin）
+//!
+//! 设计意图：
+//! - 回答"本节点眼中的集群是什么样子"：`ClusterInspector` 聚合对哈希环、SWIM 会籍视图、
+//!   本地复制器与熔断器的引用，以及各 Raft 组的指标，`snapshot` 生成一份可序列化的
+//!   `ClusterSnapshot`。检查只读取当前状态，不触发探测或状态迁移。
+//! - 登记了 `HotspotDetector` 时，快照附带其滑动窗口热点报告（最热的键与节点）；登记了
+//!   `ConvergenceProbe` 时附带各副本的传播延迟汇总。
+//! - `diff` 比较两份快照，按分区（ring / members / replication / circuit_breakers / raft）
+//!   列出新增、移除与变化的条目，用于对比两个节点的视图或同一节点前后两个时刻；热点报告与
+//!   收敛延迟随负载持续变化，不参与比较。
+//!
+//! 不变量（草图）：
+//! - 环中各节点的 `ownership` 之和为 1（环非空时）；`vnodes` 不超过 `vnodes_per_node`。
+//! - `diff(a, a)` 为空；`diff(a, b)` 中的新增与 `diff(b, a)` 中的移除一一对应。
+
+use crate::consensus::raft::{MinimalRaft, RaftMetrics};
+use crate::monitoring::convergence::{ConvergenceProbe, ConvergenceSnapshot};
+use crate::partitioning::{HotspotDetector, HotspotReport};
+use crate::security::{CircuitBreaker, CircuitState};
+use crate::storage::IdempotencyKey;
+use crate::storage::replication::LocalReplicator;
+use crate::swim::{MembershipView, SwimMemberState};
+use crate::topology::ConsistentHashRing;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 快照所读取的组件（均可缺省）
+pub struct ClusterInspector<'a, ID = IdempotencyKey> {
+    node_id: String,
+    ring: Option<&'a ConsistentHashRing>,
+    membership: Option<&'a MembershipView>,
+    replicator: Option<&'a LocalReplicator<ID>>,
+    circuit_breakers: Vec<(String, &'a CircuitBreaker)>,
+    raft: BTreeMap<String, RaftMetrics>,
+    hotspots: Option<&'a HotspotDetector>,
+    convergence: Option<&'a ConvergenceProbe>,
+}
+
+impl<'a, ID> ClusterInspector<'a, ID> {
+    pub fn new(node_id: impl Into<String>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            ring: None,
+            membership: None,
+            replicator: None,
+            circuit_breakers: Vec::new(),
+            raft: BTreeMap::new(),
+            hotspots: None,
+            convergence: None,
+        }
+    }
+
+    pub fn with_ring(mut self, ring: &'a ConsistentHashRing) -> Self {
+        self.ring = Some(ring);
+        self
+    }
+
+    pub fn with_membership(mut self, membership: &'a MembershipView) -> Self {
+        self.membership = Some(membership);
+        self
+    }
+
+    pub fn with_replicator(mut self, replicator: &'a LocalReplicator<ID>) -> Self {
+        self.replicator = Some(replicator);
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, name: impl Into<String>, cb: &'a CircuitBreaker) -> Self {
+        self.circuit_breakers.push((name.into(), cb));
+        self
+    }
+
+    /// 登记一个 Raft 组；指标在登记时读取
+    pub fn with_raft<E>(mut self, group: impl Into<String>, raft: &MinimalRaft<E>) -> Self {
+        self.raft.insert(group.into(), raft.metrics());
+        self
+    }
+
+    pub fn with_hotspots(mut self, detector: &'a HotspotDetector) -> Self {
+        self.hotspots = Some(detector);
+        self
+    }
+
+    pub fn with_convergence(mut self, probe: &'a ConvergenceProbe) -> Self {
+        self.convergence = Some(probe);
+        self
+    }
+
+    pub fn snapshot(&self) -> ClusterSnapshot {
+        let ring = self.ring.map(|ring| {
+            let counts = ring.vnode_counts();
+            let nodes = ring
+                .ownership()
+                .into_iter()
+                .map(|(node, ownership)| {
+                    let vnodes = counts.get(&node).copied().unwrap_or(0);
+                    (node, RingNode { vnodes, ownership })
+                })
+                .collect();
+            RingSnapshot {
+                vnodes_per_node: ring.vnodes_per_node(),
+                nodes,
+            }
+        });
+
+        let members = self
+            .membership
+            .iter()
+            .flat_map(|view| view.members.iter())
+            .map(|(node, info)| {
+                let member = MemberSnapshot {
+                    state: info.state,
+                    incarnation: info.incarnation,
+                };
+                (node.clone(), member)
+            })
+            .collect();
+
+        let replication = self
+            .replicator
+            .map(|r| {
+                r.nodes
+                    .iter()
+                    .map(|n| {
+                        let health = ReplicaHealth {
+                            ack_rate: r.ack_rate(n),
+                            lag: r.lag(n),
+                        };
+                        (n.clone(), health)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let circuit_breakers = self
+            .circuit_breakers
+            .iter()
+            .map(|(name, cb)| (name.clone(), cb.state()))
+            .collect();
+
+        ClusterSnapshot {
+            node_id: self.node_id.clone(),
+            taken_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            ring,
+            members,
+            replication,
+            circuit_breakers,
+            raft: self.raft.clone(),
+            hotspots: self.hotspots.map(HotspotDetector::report),
+            convergence: self.convergence.map(ConvergenceProbe::snapshot),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RingNode {
+    pub vnodes: usize,
+    /// 拥有的哈希空间占比
+    pub ownership: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RingSnapshot {
+    pub vnodes_per_node: u32,
+    pub nodes: BTreeMap<String, RingNode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemberSnapshot {
+    pub state: SwimMemberState,
+    pub incarnation: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplicaHealth {
+    /// 确认复制请求的比例；尚未复制过为 `None`
+    pub ack_rate: Option<f64>,
+    /// 落后的写入数
+    pub lag: u64,
+}
+
+/// 某一时刻本节点眼中的集群状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterSnapshot {
+    pub node_id: String,
+    /// Unix 毫秒
+    pub taken_at_ms: u64,
+    pub ring: Option<RingSnapshot>,
+    pub members: BTreeMap<String, MemberSnapshot>,
+    pub replication: BTreeMap<String, ReplicaHealth>,
+    pub circuit_breakers: BTreeMap<String, CircuitState>,
+    pub raft: BTreeMap<String, RaftMetrics>,
+    pub hotspots: Option<HotspotReport>,
+    #[serde(default)]
+    pub convergence: Option<ConvergenceSnapshot>,
+}
+
+impl ClusterSnapshot {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("cluster snapshot is serializable")
+    }
+
+    /// 本快照到 `newer` 的变化
+    pub fn diff(&self, newer: &ClusterSnapshot) -> Vec<SnapshotChange> {
+        diff(self, newer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SnapshotChange {
+    Added {
+        section: String,
+        key: String,
+        value: serde_json::Value,
+    },
+    Removed {
+        section: String,
+        key: String,
+        value: serde_json::Value,
+    },
+    Changed {
+        section: String,
+        key: String,
+        from: serde_json::Value,
+        to: serde_json::Value,
+    },
+}
+
+impl SnapshotChange {
+    pub fn section(&self) -> &str {
+        match self {
+            SnapshotChange::Added { section, .. }
+            | SnapshotChange::Removed { section, .. }
+            | SnapshotChange::Changed { section, .. } => section,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            SnapshotChange::Added { key, .. }
+            | SnapshotChange::Removed { key, .. }
+            | SnapshotChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// 按分区比较两份快照，结果按 (分区, 键) 排序；`node_id`、`taken_at_ms`、`hotspots` 与
+/// `convergence` 不参与比较
+pub fn diff(old: &ClusterSnapshot, new: &ClusterSnapshot) -> Vec<SnapshotChange> {
+    let ring_nodes =
+        |s: &ClusterSnapshot| s.ring.as_ref().map(|r| r.nodes.clone()).unwrap_or_default();
+    let mut changes = Vec::new();
+    diff_section("ring", &ring_nodes(old), &ring_nodes(new), &mut changes);
+    diff_section("members", &old.members, &new.members, &mut changes);
+    diff_section(
+        "replication",
+        &old.replication,
+        &new.replication,
+        &mut changes,
+    );
+    diff_section(
+        "circuit_breakers",
+        &old.circuit_breakers,
+        &new.circuit_breakers,
+        &mut changes,
+    );
+    diff_section("raft", &old.raft, &new.raft, &mut changes);
+    changes.sort_by(|a, b| (a.section(), a.key()).cmp(&(b.section(), b.key())));
+    changes
+}
+
+fn diff_section<V: Serialize + PartialEq>(
+    section: &str,
+    old: &BTreeMap<String, V>,
+    new: &BTreeMap<String, V>,
+    changes: &mut Vec<SnapshotChange>,
+) {
+    let json = |v: &V| serde_json::to_value(v).expect("snapshot entries are serializable");
+    for (key, before) in old {
+        match new.get(key) {
+            None => changes.push(SnapshotChange::Removed {
+                section: section.to_string(),
+                key: key.clone(),
+                value: json(before),
+            }),
+            Some(after) if after != before => changes.push(SnapshotChange::Changed {
+                section: section.to_string(),
+                key: key.clone(),
+                from: json(before),
+                to: json(after),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, after) in new {
+        if !old.contains_key(key) {
+            changes.push(SnapshotChange::Added {
+                section: section.to_string(),
+                key: key.clone(),
+                value: json(after),
+            });
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! 会籍与配置变更的审计日志
+//!
+//! 设计意图：
+//! - `ChangeLog` 记录谁在何时改变了集群：成员加入、排空、下线、SWIM 确认故障，以及动态
+//!   配置更新。条目只追加，每条包含前一条的哈希（哈希链），经 WAL（`FileLogStorage`）持久化。
+//! - 条目哈希为 SHA-256(前一条哈希 ‖ 序号、时间、操作者与事件的 JSON)，配置值在记录时即
+//!   渲染为 JSON 文本，重新计算哈希不依赖 `HashMap` 的迭代顺序。
+//! - 成员变更经 `join` / `decommission` 完成并记录（成员视图未变化时不记录），`drain` 与
+//!   `observe_swim` 只记录；配置更新经 `watch_config` 订阅 `ConfigManager`，按键比较前后快照。
+//! - `verify_chain` 从头校验序号、链接与哈希，返回第一个断开的位置；`entries_since` 供导出。
+//!
+//! 不变量（草图）：
+//! - 序号从 1 连续递增；第 1 条的 `prev_hash` 为全零，其余等于前一条的 `hash`。
+//! - 先写 WAL 再进入内存：写入失败的事件不会出现在 `entries` 中。
+
+use crate::codec::JsonCodec;
+use crate::config_management::{ConfigManager, ConfigValue};
+use crate::core::errors::DistributedError;
+use crate::core::membership::{ClusterEpoch, ClusterMembership, ClusterNodeId};
+use crate::storage::{FileLogStorage, LogStorage};
+use crate::swim::{SwimEvent, SwimMemberState};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// 第 1 条的 `prev_hash`
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// 被审计的变更
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    Joined {
+        node: String,
+        epoch: u64,
+    },
+    Draining {
+        node: String,
+    },
+    Decommissioned {
+        node: String,
+        epoch: u64,
+    },
+    /// SWIM 确认节点故障
+    ConfirmedDead {
+        node: String,
+        incarnation: u64,
+    },
+    /// 键 → 新值的 JSON 文本；`None` 表示键被删除
+    ConfigUpdated {
+        version: u64,
+        changes: BTreeMap<String, Option<String>>,
+    },
+}
+
+/// 哈希链中的一条
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeEntry {
+    pub seq: u64,
+    /// 记录时间（Unix 毫秒）
+    pub at_ms: u64,
+    pub actor: String,
+    pub event: ChangeEvent,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl ChangeEntry {
+    /// 按内容与 `prev_hash` 重新计算哈希
+    pub fn compute_hash(&self) -> String {
+        #[derive(Serialize)]
+        struct Hashed<'a> {
+            seq: u64,
+            at_ms: u64,
+            actor: &'a str,
+            event: &'a ChangeEvent,
+        }
+        let body = serde_json::to_vec(&Hashed {
+            seq: self.seq,
+            at_ms: self.at_ms,
+            actor: &self.actor,
+            event: &self.event,
+        })
+        .expect("change entry serializes");
+        let mut hasher = Sha256::new();
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(&body);
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+}
+
+/// `verify_chain` 找到的第一个断点
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ChainError {
+    #[error("entry {seq}: expected sequence {expected}")]
+    SequenceGap { seq: u64, expected: u64 },
+    #[error("entry {seq}: prev_hash does not match the preceding entry")]
+    BrokenLink { seq: u64 },
+    #[error("entry {seq}: hash does not match its contents")]
+    HashMismatch { seq: u64 },
+}
+
+impl ChainError {
+    /// 断点所在条目的序号
+    pub fn seq(&self) -> u64 {
+        match self {
+            ChainError::SequenceGap { seq, .. }
+            | ChainError::BrokenLink { seq }
+            | ChainError::HashMismatch { seq } => *seq,
+        }
+    }
+}
+
+/// 只追加、哈希链接的变更日志
+pub struct ChangeLog {
+    wal: Option<FileLogStorage<JsonCodec, ChangeEntry>>,
+    entries: Vec<ChangeEntry>,
+}
+
+impl Default for ChangeLog {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl ChangeLog {
+    /// 不持久化的日志
+    pub fn in_memory() -> Self {
+        Self {
+            wal: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// 打开 `path` 处的 WAL 并载入已有条目；载入时不校验，需要时调用 `verify_chain`
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, DistributedError> {
+        let wal = FileLogStorage::new(path.into(), JsonCodec);
+        let entries = wal.read_all()?;
+        Ok(Self {
+            wal: Some(wal),
+            entries,
+        })
+    }
+
+    pub fn entries(&self) -> &[ChangeEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 最新条目的哈希；空日志为 `GENESIS_HASH`
+    pub fn head_hash(&self) -> &str {
+        self.entries
+            .last()
+            .map_or(GENESIS_HASH, |e| e.hash.as_str())
+    }
+
+    /// 序号大于 `seq` 的条目，供增量导出（`seq = 0` 导出全部）
+    pub fn entries_since(&self, seq: u64) -> Vec<ChangeEntry> {
+        let start = self.entries.partition_point(|e| e.seq <= seq);
+        self.entries[start..].to_vec()
+    }
+
+    /// 追加一条记录并返回其序号
+    pub fn record(
+        &mut self,
+        actor: impl Into<String>,
+        event: ChangeEvent,
+    ) -> Result<u64, DistributedError> {
+        let mut entry = ChangeEntry {
+            seq: self.entries.last().map_or(1, |e| e.seq + 1),
+            at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            actor: actor.into(),
+            event,
+            prev_hash: self.head_hash().to_string(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+        if let Some(wal) = &mut self.wal {
+            wal.append(entry.clone())?;
+        }
+        let seq = entry.seq;
+        self.entries.push(entry);
+        Ok(seq)
+    }
+
+    /// 从头校验序号、链接与哈希，返回第一个断点
+    pub fn verify_chain(&self) -> Result<(), ChainError> {
+        let mut prev = GENESIS_HASH;
+        for (i, entry) in self.entries.iter().enumerate() {
+            let expected = i as u64 + 1;
+            if entry.seq != expected {
+                return Err(ChainError::SequenceGap {
+                    seq: entry.seq,
+                    expected,
+                });
+            }
+            if entry.prev_hash != prev {
+                return Err(ChainError::BrokenLink { seq: entry.seq });
+            }
+            if entry.compute_hash() != entry.hash {
+                return Err(ChainError::HashMismatch { seq: entry.seq });
+            }
+            prev = &entry.hash;
+        }
+        Ok(())
+    }
+
+    /// 节点加入 `membership`；成员视图变化时记录 `Joined`
+    pub fn join(
+        &mut self,
+        actor: impl Into<String>,
+        membership: &mut ClusterMembership,
+        node: ClusterNodeId,
+    ) -> Result<ClusterEpoch, DistributedError> {
+        let before = membership.current_epoch();
+        let name = node.as_str().to_string();
+        let epoch = membership.join(node);
+        if epoch != before {
+            self.record(
+                actor,
+                ChangeEvent::Joined {
+                    node: name,
+                    epoch: epoch.0,
+                },
+            )?;
+        }
+        Ok(epoch)
+    }
+
+    /// 节点开始排空（迁出数据、停止接收新请求）；仍是成员
+    pub fn drain(
+        &mut self,
+        actor: impl Into<String>,
+        node: &ClusterNodeId,
+    ) -> Result<u64, DistributedError> {
+        self.record(
+            actor,
+            ChangeEvent::Draining {
+                node: node.as_str().to_string(),
+            },
+        )
+    }
+
+    /// 节点从 `membership` 下线；成员视图变化时记录 `Decommissioned`
+    pub fn decommission(
+        &mut self,
+        actor: impl Into<String>,
+        membership: &mut ClusterMembership,
+        node: &ClusterNodeId,
+    ) -> Result<ClusterEpoch, DistributedError> {
+        let before = membership.current_epoch();
+        let epoch = membership.leave(node);
+        if epoch != before {
+            self.record(
+                actor,
+                ChangeEvent::Decommissioned {
+                    node: node.as_str().to_string(),
+                    epoch: epoch.0,
+                },
+            )?;
+        }
+        Ok(epoch)
+    }
+
+    /// SWIM 事件为 `Faulty` 时记录 `ConfirmedDead`，返回其序号
+    pub fn observe_swim(
+        &mut self,
+        actor: impl Into<String>,
+        event: &SwimEvent,
+    ) -> Result<Option<u64>, DistributedError> {
+        if event.state != SwimMemberState::Faulty {
+            return Ok(None);
+        }
+        self.record(
+            actor,
+            ChangeEvent::ConfirmedDead {
+                node: event.node_id.clone(),
+                incarnation: event.incarnation,
+            },
+        )
+        .map(Some)
+    }
+
+    /// 订阅 `manager` 的配置变更，每次通知记录与上一快照相比新增、修改与删除的键；
+    /// 写入失败的变更被丢弃（订阅回调无法返回错误）
+    pub fn watch_config(
+        log: &Arc<Mutex<ChangeLog>>,
+        manager: &mut ConfigManager,
+        actor: impl Into<String>,
+    ) {
+        let log = Arc::downgrade(log);
+        let actor = actor.into();
+        let previous = Mutex::new(manager.snapshot().values);
+        manager.subscribe(move |snapshot| {
+            let mut previous = previous.lock().unwrap();
+            let changes = config_changes(&previous, &snapshot.values);
+            *previous = snapshot.values.clone();
+            if changes.is_empty() {
+                return;
+            }
+            if let Some(log) = log.upgrade() {
+                let event = ChangeEvent::ConfigUpdated {
+                    version: snapshot.version,
+                    changes,
+                };
+                let _ = log.lock().unwrap().record(actor.clone(), event);
+            }
+        });
+    }
+}
+
+fn config_changes(
+    before: &HashMap<String, ConfigValue>,
+    after: &HashMap<String, ConfigValue>,
+) -> BTreeMap<String, Option<String>> {
+    let render =
+        |value: &ConfigValue| serde_json::to_string(value).expect("config value serializes");
+    let mut changes: BTreeMap<_, _> = after
+        .iter()
+        .filter(|(key, value)| before.get(*key) != Some(*value))
+        .map(|(key, value)| (key.clone(), Some(render(value))))
+        .collect();
+    changes.extend(
+        before
+            .keys()
+            .filter(|key| !after.contains_key(*key))
+            .map(|key| (key.clone(), None)),
+    );
+    changes
+}
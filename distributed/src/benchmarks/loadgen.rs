@@ -0,0 +1,724 @@
+//! 端到端负载生成
+//!
+//! 设计意图：
+//! - criterion 基准只覆盖单个组件；`LoadGenerator` 以可配置的工作负载驱动任意
+//!   `ReplicatedKv`，评估复制 KV 的端到端表现：读写比例、键分布（均匀 / Zipfian）、
+//!   值大小、并发客户端数，以及经 `TokenBucket` 限速的目标 QPS。
+//! - `InMemoryReplicatedKv` 是进程内的复制 KV：每条副本消息经 `ChaosInjector` 模拟的内存
+//!   传输（延迟、丢弃、分区），写入按 `MajorityQuorum` 判定成功，读取收集足够的副本应答
+//!   并取最高版本。提交的写入同时按版本记入 `MvccKv`，供一致的只读快照使用。
+//! - watch 历史可交给 `WatchLog`（如 `WalWatchLog`）保存：接管同一日志的新实例从日志中的最大
+//!   版本继续分配版本，相当于领导者切换后新领导者在已提交的日志之上继续写入。
+//! - `LoadReport` 汇总吞吐、HDR 风格直方图（按 2 的幂分段、段内 128 个线性子桶，相对误差
+//!   不超过 1/128）给出的 p50/p99/p999 延迟，以及按 `DistributedError` 变体的错误分布；
+//!   可输出 CSV 与 JSON 供回归跟踪。
+//!
+//! 不变量（草图）：
+//! - `ops == successes + failures == reads + writes`；错误分布之和等于 `failures`。
+//! - 同一种子下每个客户端的键与操作序列确定（延迟与限速取决于真实时钟）。
+
+use crate::chaos::{ChaosConfig, ChaosInjector};
+use crate::consistency::ConsistencyLevel;
+use crate::core::ClusterNodeId;
+use crate::core::errors::DistributedError;
+use crate::partitioning::HotspotDetector;
+use crate::security::TokenBucket;
+use crate::storage::cached_kv::{ApplyEvent, ApplyListener, ReplicatedKv};
+use crate::storage::mvcc::{KvSnapshot, MvccKv};
+use crate::storage::replication::{MajorityQuorum, QuorumPolicy};
+use crate::storage::watch::{KeyPrefix, KvEvent, WatchError, WatchHub, WatchLog, WatchStream};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ---------------- 进程内复制 KV ----------------
+
+struct Replica<K, V> {
+    id: ClusterNodeId,
+    /// 键 → (版本, 值)；`None` 为删除墓碑
+    data: Mutex<HashMap<K, (u64, Option<V>)>>,
+}
+
+/// 经模拟内存传输复制到多个副本的 KV
+pub struct InMemoryReplicatedKv<K, V> {
+    replicas: Vec<Replica<K, V>>,
+    chaos: RwLock<ChaosInjector>,
+    version: AtomicU64,
+    listeners: Mutex<Vec<ApplyListener<K>>>,
+    hotspots: Option<Arc<HotspotDetector<K>>>,
+    watch: WatchHub<K, V>,
+    mvcc: Arc<MvccKv<K, V>>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> InMemoryReplicatedKv<K, V> {
+    pub fn new(replicas: usize) -> Self {
+        Self {
+            replicas: (0..replicas.max(1))
+                .map(|_| Replica {
+                    id: ClusterNodeId::generate(),
+                    data: Mutex::new(HashMap::new()),
+                })
+                .collect(),
+            chaos: RwLock::new(ChaosInjector::new(ChaosConfig::default())),
+            version: AtomicU64::new(0),
+            listeners: Mutex::new(Vec::new()),
+            hotspots: None,
+            watch: WatchHub::new(),
+            mvcc: Arc::new(MvccKv::new()),
+        }
+    }
+
+    /// 快照 GC 至少保留最近 `versions` 个版本范围内的历史
+    pub fn with_snapshot_retention(mut self, versions: u64) -> Self {
+        self.mvcc = Arc::new(MvccKv::new().with_retention(versions));
+        self
+    }
+
+    /// 已提交写入的多版本视图
+    pub fn mvcc(&self) -> &Arc<MvccKv<K, V>> {
+        &self.mvcc
+    }
+
+    /// 回收活跃快照与保留窗口都不再需要的版本，返回回收的版本数
+    pub fn gc_versions(&self) -> usize {
+        self.mvcc.gc()
+    }
+
+    /// 每个 watch 订阅的缓冲容量，溢出即取消该订阅
+    pub fn with_watch_buffer(mut self, events: usize) -> Self {
+        self.watch = self.watch.with_buffer(events);
+        self
+    }
+
+    /// watch 可回放的历史事件数
+    pub fn with_watch_history(mut self, events: usize) -> Self {
+        self.watch = self.watch.with_history_limit(events);
+        self
+    }
+
+    /// 以 `log` 保存 watch 历史；版本从日志中已有的最大版本之后继续分配
+    pub fn with_watch_log(mut self, log: Arc<dyn WatchLog<K, V>>) -> Self {
+        *self.version.get_mut() = (*self.version.get_mut()).max(log.last_version());
+        self.watch = self.watch.with_log(log);
+        self
+    }
+
+    /// 压缩版本小于 `before` 的 watch 历史
+    pub fn compact_watch_history(&self, before: u64) -> Result<(), DistributedError> {
+        self.watch.compact(before)
+    }
+
+    pub fn watch_hub(&self) -> &WatchHub<K, V> {
+        &self.watch
+    }
+
+    /// 读写路径上的每次访问记入热点检测器
+    pub fn with_hotspots(mut self, detector: Arc<HotspotDetector<K>>) -> Self {
+        self.hotspots = Some(detector);
+        self
+    }
+
+    fn track(&self, key: &K) {
+        if let Some(detector) = &self.hotspots {
+            detector.record(key, None);
+        }
+    }
+
+    /// 副本消息的故障注入；分区按副本 ID 的文本形式匹配
+    pub fn with_chaos(self, config: ChaosConfig) -> Self {
+        self.set_chaos(config);
+        self
+    }
+
+    pub fn set_chaos(&self, config: ChaosConfig) {
+        self.chaos.write().unwrap().update(config);
+    }
+
+    pub fn replica_ids(&self) -> Vec<ClusterNodeId> {
+        self.replicas.iter().map(|r| r.id.clone()).collect()
+    }
+
+    /// 本次消息能否送达副本
+    fn reachable(chaos: &ChaosInjector, replica: &Replica<K, V>) -> bool {
+        !chaos.is_partitioned_with(replica.id.as_str()) && !chaos.should_drop()
+    }
+
+    fn write(
+        &self,
+        key: K,
+        value: Option<V>,
+        level: ConsistencyLevel,
+    ) -> Result<(), DistributedError> {
+        self.track(&key);
+        let chaos = self.chaos.read().unwrap().clone();
+        // 扇出并发进行，整次写入只计一次传输延迟
+        chaos.inject_latency();
+        // 版本分配与副本应用在 watch 发布锁内进行，订阅者按版本顺序收到事件
+        self.watch.publish_with(|| {
+            let version = self.version.fetch_add(1, Ordering::Relaxed) + 1;
+            let mut acks = 0;
+            for replica in &self.replicas {
+                if !Self::reachable(&chaos, replica) {
+                    continue;
+                }
+                let mut data = replica.data.lock().unwrap();
+                let entry = data.entry(key.clone()).or_insert((0, None));
+                if entry.0 < version {
+                    *entry = (version, value.clone());
+                }
+                acks += 1;
+            }
+            let need = MajorityQuorum::required_acks(self.replicas.len(), level);
+            if acks < need {
+                return Err(DistributedError::Network(format!("acks {acks}/{need}")));
+            }
+            self.mvcc.apply(key.clone(), value.clone(), version);
+            let key = key.clone();
+            Ok(match value {
+                Some(value) => KvEvent::Put {
+                    key,
+                    value,
+                    version,
+                },
+                None => KvEvent::Delete { key, version },
+            })
+        })?;
+        let event = ApplyEvent {
+            key,
+            origin: self.replicas[0].id.to_string(),
+        };
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(&event);
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> ReplicatedKv<K, V> for InMemoryReplicatedKv<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    /// 收集 `required_acks` 个副本的应答，返回其中版本最高的值
+    fn get(&self, key: &K, level: ConsistencyLevel) -> Result<Option<V>, DistributedError> {
+        self.track(key);
+        let chaos = self.chaos.read().unwrap().clone();
+        chaos.inject_latency();
+        let need = MajorityQuorum::required_acks(self.replicas.len(), level);
+        let mut responses = 0;
+        let mut latest: Option<(u64, Option<V>)> = None;
+        for replica in &self.replicas {
+            if responses == need {
+                break;
+            }
+            if !Self::reachable(&chaos, replica) {
+                continue;
+            }
+            responses += 1;
+            if let Some((version, value)) = replica.data.lock().unwrap().get(key)
+                && latest.as_ref().is_none_or(|(v, _)| v < version)
+            {
+                latest = Some((*version, value.clone()));
+            }
+        }
+        if responses < need {
+            return Err(DistributedError::Network(format!(
+                "read acks {responses}/{need}"
+            )));
+        }
+        Ok(latest.and_then(|(_, value)| value))
+    }
+
+    fn put(&self, key: K, value: V, level: ConsistencyLevel) -> Result<(), DistributedError> {
+        self.write(key, Some(value), level)
+    }
+
+    fn delete(&self, key: &K, level: ConsistencyLevel) -> Result<(), DistributedError> {
+        self.write(key.clone(), None, level)
+    }
+
+    fn subscribe(&self, listener: ApplyListener<K>) {
+        self.listeners.lock().unwrap().push(listener);
+    }
+
+    fn watch_from(&self, prefix: K, from: Option<u64>) -> Result<WatchStream<K, V>, WatchError>
+    where
+        K: KeyPrefix,
+    {
+        self.watch.watch(prefix, from)
+    }
+
+    fn snapshot(&self) -> Result<KvSnapshot<K, V>, DistributedError> {
+        Ok(self.mvcc.snapshot())
+    }
+
+    fn snapshot_at(&self, version: u64) -> Result<KvSnapshot<K, V>, DistributedError> {
+        self.mvcc.snapshot_at(version)
+    }
+}
+
+// ---------------- 工作负载 ----------------
+
+/// 键的访问分布
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyDistribution {
+    Uniform,
+    /// 第 `i` 个键（从 0 起）的访问概率正比于 `1 / (i + 1)^theta`
+    Zipfian {
+        theta: f64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkloadConfig {
+    pub duration: Duration,
+    /// 读操作占比（0.0~1.0），其余为写
+    pub read_ratio: f64,
+    pub key_count: usize,
+    pub distribution: KeyDistribution,
+    pub value_size: usize,
+    /// 所有客户端合计的目标 QPS；`None` 不限速
+    pub target_qps: Option<u64>,
+    /// 并发客户端（线程）数
+    pub clients: usize,
+    pub read_level: ConsistencyLevel,
+    pub write_level: ConsistencyLevel,
+    pub seed: u64,
+}
+
+impl Default for WorkloadConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(10),
+            read_ratio: 0.9,
+            key_count: 10_000,
+            distribution: KeyDistribution::Uniform,
+            value_size: 128,
+            target_qps: None,
+            clients: 1,
+            read_level: ConsistencyLevel::Eventual,
+            write_level: ConsistencyLevel::Quorum,
+            seed: 42,
+        }
+    }
+}
+
+impl WorkloadConfig {
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn with_read_ratio(mut self, read_ratio: f64) -> Self {
+        self.read_ratio = read_ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_keys(mut self, key_count: usize, distribution: KeyDistribution) -> Self {
+        self.key_count = key_count.max(1);
+        self.distribution = distribution;
+        self
+    }
+
+    pub fn with_value_size(mut self, value_size: usize) -> Self {
+        self.value_size = value_size;
+        self
+    }
+
+    pub fn with_target_qps(mut self, target_qps: Option<u64>) -> Self {
+        self.target_qps = target_qps.filter(|qps| *qps > 0);
+        self
+    }
+
+    pub fn with_clients(mut self, clients: usize) -> Self {
+        self.clients = clients.max(1);
+        self
+    }
+
+    pub fn with_levels(mut self, read: ConsistencyLevel, write: ConsistencyLevel) -> Self {
+        self.read_level = read;
+        self.write_level = write;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// SplitMix64：每个客户端一个，序列由种子决定
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// [0, 1) 上的均匀分布
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// 按分布抽取键的下标；Zipfian 预先计算累积分布后二分查找
+enum KeyChooser {
+    Uniform(usize),
+    Zipfian(Vec<f64>),
+}
+
+impl KeyChooser {
+    fn new(key_count: usize, distribution: KeyDistribution) -> Self {
+        match distribution {
+            KeyDistribution::Uniform => KeyChooser::Uniform(key_count),
+            KeyDistribution::Zipfian { theta } => {
+                let mut cdf: Vec<f64> = (1..=key_count)
+                    .scan(0.0, |sum, rank| {
+                        *sum += 1.0 / (rank as f64).powf(theta);
+                        Some(*sum)
+                    })
+                    .collect();
+                let total = cdf.last().copied().unwrap_or(1.0);
+                cdf.iter_mut().for_each(|c| *c /= total);
+                KeyChooser::Zipfian(cdf)
+            }
+        }
+    }
+
+    fn next(&self, rng: &mut Rng) -> usize {
+        match self {
+            KeyChooser::Uniform(count) => (rng.next_u64() % *count as u64) as usize,
+            KeyChooser::Zipfian(cdf) => {
+                let u = rng.next_f64();
+                cdf.partition_point(|c| *c <= u).min(cdf.len() - 1)
+            }
+        }
+    }
+}
+
+/// 第 `index` 个键
+pub fn key_name(index: usize) -> String {
+    format!("key-{index:08}")
+}
+
+// ---------------- 延迟直方图 ----------------
+
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// HDR 风格的延迟直方图（纳秒）：小于 128ns 精确计数，其上每个 2 的幂区间分 128 个线性子桶
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    total: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; Self::index(u64::MAX) + 1],
+            total: 0,
+            min: u64::MAX,
+            max: 0,
+            sum: 0,
+        }
+    }
+
+    fn index(value: u64) -> usize {
+        if value < SUB_BUCKETS {
+            return value as usize;
+        }
+        let msb = 63 - value.leading_zeros();
+        let shift = msb - SUB_BUCKET_BITS;
+        ((shift as u64 + 1) * SUB_BUCKETS + ((value >> shift) - SUB_BUCKETS)) as usize
+    }
+
+    /// 桶内的最大值
+    fn upper_bound(index: usize) -> u64 {
+        let index = index as u64;
+        if index < 2 * SUB_BUCKETS {
+            return index;
+        }
+        let shift = index / SUB_BUCKETS - 1;
+        let sub = index % SUB_BUCKETS + SUB_BUCKETS;
+        ((sub + 1) << shift) - 1
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.counts[Self::index(nanos)] += 1;
+        self.total += 1;
+        self.min = self.min.min(nanos);
+        self.max = self.max.max(nanos);
+        self.sum += nanos as u128;
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.total += other.total;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.total
+    }
+
+    pub fn min(&self) -> Duration {
+        Duration::from_nanos(if self.total == 0 { 0 } else { self.min })
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max)
+    }
+
+    pub fn mean(&self) -> Duration {
+        match self.total {
+            0 => Duration::ZERO,
+            n => Duration::from_nanos((self.sum / n as u128) as u64),
+        }
+    }
+
+    /// 分位数（`q` 取 0.0~1.0）：不小于 `q` 比例样本的最小桶上界，且不超过最大样本
+    pub fn percentile(&self, q: f64) -> Duration {
+        if self.total == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_nanos(Self::upper_bound(index).min(self.max));
+            }
+        }
+        self.max()
+    }
+}
+
+// ---------------- 报告 ----------------
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    pub elapsed: Duration,
+    pub ops: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub reads: u64,
+    pub writes: u64,
+    /// 全部操作（含失败）的延迟
+    pub latency: LatencyHistogram,
+    /// `DistributedError::kind` → 次数
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl LoadReport {
+    /// 每秒完成的操作数
+    pub fn throughput(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.ops as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    pub fn p50(&self) -> Duration {
+        self.latency.percentile(0.50)
+    }
+
+    pub fn p99(&self) -> Duration {
+        self.latency.percentile(0.99)
+    }
+
+    pub fn p999(&self) -> Duration {
+        self.latency.percentile(0.999)
+    }
+
+    /// 计数之间的恒等关系成立
+    pub fn is_consistent(&self) -> bool {
+        self.ops == self.successes + self.failures
+            && self.ops == self.reads + self.writes
+            && self.ops == self.latency.count()
+            && self.failures == self.errors.values().sum::<u64>()
+    }
+
+    fn merge(&mut self, other: LoadReport) {
+        self.ops += other.ops;
+        self.successes += other.successes;
+        self.failures += other.failures;
+        self.reads += other.reads;
+        self.writes += other.writes;
+        self.latency.merge(&other.latency);
+        for (kind, count) in other.errors {
+            *self.errors.entry(kind).or_insert(0) += count;
+        }
+    }
+
+    pub const CSV_HEADER: &'static str = "elapsed_s,ops,successes,failures,reads,writes,\
+        throughput_ops_s,p50_us,p99_us,p999_us,max_us,errors";
+
+    /// 一行 CSV，列与 `CSV_HEADER` 对应；错误分布写作 `Kind=n;Kind=n`
+    pub fn to_csv_row(&self) -> String {
+        let micros = |d: Duration| d.as_micros();
+        let errors: Vec<String> = self
+            .errors
+            .iter()
+            .map(|(kind, count)| format!("{kind}={count}"))
+            .collect();
+        format!(
+            "{:.3},{},{},{},{},{},{:.1},{},{},{},{},{}",
+            self.elapsed.as_secs_f64(),
+            self.ops,
+            self.successes,
+            self.failures,
+            self.reads,
+            self.writes,
+            self.throughput(),
+            micros(self.p50()),
+            micros(self.p99()),
+            micros(self.p999()),
+            micros(self.latency.max()),
+            errors.join(";"),
+        )
+    }
+
+    /// 表头加一行数据
+    pub fn to_csv(&self) -> String {
+        format!("{}\n{}\n", Self::CSV_HEADER, self.to_csv_row())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let micros = |d: Duration| d.as_micros() as u64;
+        serde_json::json!({
+            "elapsed_s": self.elapsed.as_secs_f64(),
+            "ops": self.ops,
+            "successes": self.successes,
+            "failures": self.failures,
+            "reads": self.reads,
+            "writes": self.writes,
+            "throughput_ops_s": self.throughput(),
+            "latency_us": {
+                "min": micros(self.latency.min()),
+                "mean": micros(self.latency.mean()),
+                "p50": micros(self.p50()),
+                "p99": micros(self.p99()),
+                "p999": micros(self.p999()),
+                "max": micros(self.latency.max()),
+            },
+            "errors": self.errors,
+        })
+    }
+}
+
+// ---------------- 负载生成器 ----------------
+
+pub struct LoadGenerator {
+    config: WorkloadConfig,
+}
+
+impl LoadGenerator {
+    pub fn new(config: WorkloadConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &WorkloadConfig {
+        &self.config
+    }
+
+    /// 以 `clients` 个线程运行 `duration`，阻塞直至结束
+    pub fn run<S: ReplicatedKv<String, Vec<u8>>>(&self, kv: &S) -> LoadReport {
+        let config = &self.config;
+        let chooser = KeyChooser::new(config.key_count, config.distribution);
+        // 突发上限为 100ms 的配额，避免开始时的满桶造成尖峰
+        let limiter = config
+            .target_qps
+            .map(|qps| Mutex::new(TokenBucket::new((qps / 10).max(1), qps)));
+        let start = Instant::now();
+        let deadline = start + config.duration;
+
+        let mut report = thread::scope(|scope| {
+            let clients: Vec<_> = (0..config.clients as u64)
+                .map(|client| {
+                    let (chooser, limiter) = (&chooser, limiter.as_ref());
+                    scope.spawn(move || {
+                        let rng = Rng(config.seed.wrapping_add(client));
+                        run_client(config, kv, chooser, limiter, rng, deadline)
+                    })
+                })
+                .collect();
+            clients
+                .into_iter()
+                .map(|client| client.join().expect("load client panicked"))
+                .fold(LoadReport::default(), |mut total, report| {
+                    total.merge(report);
+                    total
+                })
+        });
+        report.elapsed = start.elapsed();
+        report
+    }
+}
+
+fn run_client<S: ReplicatedKv<String, Vec<u8>>>(
+    config: &WorkloadConfig,
+    kv: &S,
+    chooser: &KeyChooser,
+    limiter: Option<&Mutex<TokenBucket>>,
+    mut rng: Rng,
+    deadline: Instant,
+) -> LoadReport {
+    let mut report = LoadReport::default();
+    let pause = config
+        .target_qps
+        .map(|qps| Duration::from_secs_f64(1.0 / qps as f64).min(Duration::from_millis(1)));
+    while Instant::now() < deadline {
+        if let (Some(limiter), Some(pause)) = (limiter, pause)
+            && !limiter.lock().unwrap().allow()
+        {
+            thread::sleep(pause);
+            continue;
+        }
+        let key = key_name(chooser.next(&mut rng));
+        let read = rng.next_f64() < config.read_ratio;
+        let began = Instant::now();
+        let result = if read {
+            kv.get(&key, config.read_level).map(|_| ())
+        } else {
+            let value = vec![(rng.next_u64() & 0xff) as u8; config.value_size];
+            kv.put(key, value, config.write_level)
+        };
+        report.latency.record(began.elapsed());
+        report.ops += 1;
+        if read {
+            report.reads += 1;
+        } else {
+            report.writes += 1;
+        }
+        match result {
+            Ok(()) => report.successes += 1,
+            Err(e) => {
+                report.failures += 1;
+                *report.errors.entry(e.kind().to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+    report
+}
@@ -2,5 +2,6 @@
 //! 
 //! 提供各种分布式系统组件的性能基准测试
 
+pub mod loadgen;
 pub mod lock_performance;
 pub mod network_performance;
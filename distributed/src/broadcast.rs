@@ -0,0 +1,491 @@
+//! 组播（broadcast）
+//!
+//! 设计意图：
+//! - 两种语义共用复制传输 `ReplicationTransport`：消息 ID 放在 `idempotency_key`，
+//!   整条 `BroadcastMessage` 以 JSON 编码放在 `payload`，节点侧经 `receive` 交付。
+//! - `BestEffortBroadcast`：向当前成员各发送一次，不重试也不转发；发送方中途崩溃时
+//!   只有已送达的节点交付。
+//! - `ReliableBroadcast`：节点首次交付时立即向其余成员转发（gossip），并对未确认的
+//!   成员周期性重传，直到确认或达到 `max_attempts`；只要有一个存活节点收到消息，
+//!   即使发送方在部分送达后崩溃，消息也会到达全部存活节点。
+//! - 交付回调按主题注册；按消息 ID 经 `IdempotencyStore` 去重。
+//!
+//! 不变量（草图）：
+//! - 同一节点对同一消息 ID 至多交付一次：检查与记录在同一把锁内完成。
+//! - 重复到达的消息同样视为成功接收（`applied = true`），发送方据此停止重传。
+//! - 消息 ID 为 `"{origin}-{seq}"`；序号不持久化，节点重启后应换用新的节点 ID。
+
+use crate::consistency::ConsistencyLevel;
+use crate::core::errors::DistributedError;
+use crate::network::Deadline;
+use crate::storage::replication::{Priority, ReplicateAck, ReplicateRequest, ReplicationTransport};
+use crate::storage::{IdempotencyStore, InMemoryIdempotency};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// 一条组播消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BroadcastMessage {
+    pub id: String,
+    pub topic: String,
+    /// 最初发出该消息的节点
+    pub origin: String,
+    pub payload: Vec<u8>,
+}
+
+/// 按主题注册的交付回调
+pub type DeliveryCallback = Arc<dyn Fn(&BroadcastMessage) + Send + Sync>;
+
+/// 一次发送（或转发、重传）的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BroadcastReport {
+    pub message_id: String,
+    /// 已确认的节点
+    pub delivered: Vec<String>,
+    /// 发送失败或未确认的节点
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BroadcastConfig {
+    /// 单次发送的时间预算
+    pub timeout: Duration,
+    /// `start` 驱动的重传间隔
+    pub retransmit_interval: Duration,
+    /// 每条消息的最大发送轮数（含首轮），之后放弃未确认的成员
+    pub max_attempts: u32,
+}
+
+impl Default for BroadcastConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            retransmit_interval: Duration::from_millis(200),
+            max_attempts: 10,
+        }
+    }
+}
+
+/// 两种组播共享的节点端：成员、去重与回调
+struct Endpoint<T> {
+    node_id: String,
+    transport: Arc<T>,
+    members: RwLock<Vec<String>>,
+    idempotency: Mutex<Box<dyn IdempotencyStore<String> + Send>>,
+    callbacks: RwLock<HashMap<String, Vec<DeliveryCallback>>>,
+    seq: AtomicU64,
+}
+
+impl<T: ReplicationTransport> Endpoint<T> {
+    fn new(node_id: String, transport: T, members: Vec<String>) -> Self {
+        Self {
+            node_id,
+            transport: Arc::new(transport),
+            members: RwLock::new(members),
+            idempotency: Mutex::new(Box::new(InMemoryIdempotency::default())),
+            callbacks: RwLock::new(HashMap::new()),
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    fn next_message(&self, topic: &str, payload: Vec<u8>) -> BroadcastMessage {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        BroadcastMessage {
+            id: format!("{}-{seq}", self.node_id),
+            topic: topic.to_string(),
+            origin: self.node_id.clone(),
+            payload,
+        }
+    }
+
+    /// 除自身与 `exclude` 外的当前成员
+    fn peers(&self, exclude: &str) -> Vec<String> {
+        self.members
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|m| **m != self.node_id && *m != exclude)
+            .cloned()
+            .collect()
+    }
+
+    fn subscribe(&self, topic: &str, callback: DeliveryCallback) {
+        self.callbacks
+            .write()
+            .unwrap()
+            .entry(topic.to_string())
+            .or_default()
+            .push(callback);
+    }
+
+    /// 首次见到该消息时执行回调并返回 true；重复消息返回 false
+    fn deliver(&self, message: &BroadcastMessage) -> bool {
+        {
+            let mut store = self.idempotency.lock().unwrap();
+            if store.seen(&message.id) {
+                return false;
+            }
+            store.record(message.id.clone());
+        }
+        let callbacks = self
+            .callbacks
+            .read()
+            .unwrap()
+            .get(&message.topic)
+            .cloned()
+            .unwrap_or_default();
+        for callback in callbacks {
+            callback(message);
+        }
+        true
+    }
+
+    fn encode(message: &BroadcastMessage) -> Result<ReplicateRequest, DistributedError> {
+        let payload = serde_json::to_vec(message)
+            .map_err(|e| DistributedError::InvalidState(format!("encode broadcast: {e}")))?;
+        Ok(ReplicateRequest {
+            idempotency_key: message.id.clone(),
+            payload,
+            level: ConsistencyLevel::Eventual,
+            priority: Priority::Normal,
+        })
+    }
+
+    fn decode(request: &ReplicateRequest) -> Result<BroadcastMessage, DistributedError> {
+        let message: BroadcastMessage = serde_json::from_slice(&request.payload)
+            .map_err(|e| DistributedError::InvalidState(format!("decode broadcast: {e}")))?;
+        if message.id != request.idempotency_key {
+            return Err(DistributedError::InvalidState(format!(
+                "broadcast id {} does not match idempotency key {}",
+                message.id, request.idempotency_key
+            )));
+        }
+        Ok(message)
+    }
+
+    /// 并发发送到 `targets`，每个节点一次
+    async fn send_all(
+        &self,
+        message: &BroadcastMessage,
+        targets: &[String],
+        timeout: Duration,
+    ) -> Result<BroadcastReport, DistributedError> {
+        let request = Self::encode(message)?;
+        let deadline = Deadline::after(timeout);
+        let mut tasks = tokio::task::JoinSet::new();
+        for node in targets {
+            let transport = self.transport.clone();
+            let node = node.clone();
+            let request = request.clone();
+            tasks.spawn(async move {
+                match transport.send(&node, request, deadline).await {
+                    Ok(ack) => ack,
+                    Err(e) => ReplicateAck {
+                        applied: false,
+                        node_id: node,
+                        error: Some(e.to_string()),
+                    },
+                }
+            });
+        }
+
+        let mut report = BroadcastReport {
+            message_id: message.id.clone(),
+            ..BroadcastReport::default()
+        };
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok(ack) = joined {
+                if ack.applied {
+                    report.delivered.push(ack.node_id);
+                } else {
+                    report.failed.push(ack.node_id);
+                }
+            }
+        }
+        let order = |n: &String| targets.iter().position(|t| t == n);
+        report.delivered.sort_by_key(order);
+        report.failed.sort_by_key(order);
+        Ok(report)
+    }
+}
+
+/// 尽力而为组播：每个成员发送一次
+pub struct BestEffortBroadcast<T> {
+    endpoint: Arc<Endpoint<T>>,
+    timeout: Duration,
+}
+
+impl<T> Clone for BestEffortBroadcast<T> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoint: self.endpoint.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<T: ReplicationTransport> BestEffortBroadcast<T> {
+    /// `members` 可包含自身，发送时会被跳过
+    pub fn new(node_id: impl Into<String>, transport: T, members: Vec<String>) -> Self {
+        Self {
+            endpoint: Arc::new(Endpoint::new(node_id.into(), transport, members)),
+            timeout: BroadcastConfig::default().timeout,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_idempotency(self, store: Box<dyn IdempotencyStore<String> + Send>) -> Self {
+        *self.endpoint.idempotency.lock().unwrap() = store;
+        self
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.endpoint.node_id
+    }
+
+    pub fn set_members(&self, members: Vec<String>) {
+        *self.endpoint.members.write().unwrap() = members;
+    }
+
+    /// 注册某主题的交付回调
+    pub fn subscribe(
+        &self,
+        topic: &str,
+        callback: impl Fn(&BroadcastMessage) + Send + Sync + 'static,
+    ) {
+        self.endpoint.subscribe(topic, Arc::new(callback));
+    }
+
+    /// 本地交付后向其余成员各发送一次
+    pub async fn broadcast(
+        &self,
+        topic: &str,
+        payload: Vec<u8>,
+    ) -> Result<BroadcastReport, DistributedError> {
+        let message = self.endpoint.next_message(topic, payload);
+        self.endpoint.deliver(&message);
+        let targets = self.endpoint.peers(&message.origin);
+        self.endpoint
+            .send_all(&message, &targets, self.timeout)
+            .await
+    }
+
+    /// 节点侧接收；返回是否为首次交付
+    pub fn receive(&self, request: &ReplicateRequest) -> Result<bool, DistributedError> {
+        let message = Endpoint::<T>::decode(request)?;
+        Ok(self.endpoint.deliver(&message))
+    }
+}
+
+struct Pending {
+    message: BroadcastMessage,
+    unacked: HashSet<String>,
+    attempts: u32,
+}
+
+struct ReliableInner<T> {
+    endpoint: Endpoint<T>,
+    config: BroadcastConfig,
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+/// 可靠组播：重传直到确认，首次交付时向其余成员转发
+pub struct ReliableBroadcast<T> {
+    inner: Arc<ReliableInner<T>>,
+}
+
+impl<T> Clone for ReliableBroadcast<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: ReplicationTransport> ReliableBroadcast<T> {
+    /// `members` 可包含自身，发送时会被跳过
+    pub fn new(node_id: impl Into<String>, transport: T, members: Vec<String>) -> Self {
+        Self {
+            inner: Arc::new(ReliableInner {
+                endpoint: Endpoint::new(node_id.into(), transport, members),
+                config: BroadcastConfig::default(),
+                pending: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// 仅在共享（clone）之前调用有效
+    pub fn with_config(mut self, config: BroadcastConfig) -> Self {
+        if let Some(inner) = Arc::get_mut(&mut self.inner) {
+            inner.config = config;
+        }
+        self
+    }
+
+    pub fn with_idempotency(self, store: Box<dyn IdempotencyStore<String> + Send>) -> Self {
+        *self.inner.endpoint.idempotency.lock().unwrap() = store;
+        self
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.inner.endpoint.node_id
+    }
+
+    pub fn set_members(&self, members: Vec<String>) {
+        *self.inner.endpoint.members.write().unwrap() = members;
+    }
+
+    /// 注册某主题的交付回调
+    pub fn subscribe(
+        &self,
+        topic: &str,
+        callback: impl Fn(&BroadcastMessage) + Send + Sync + 'static,
+    ) {
+        self.inner.endpoint.subscribe(topic, Arc::new(callback));
+    }
+
+    /// 本地交付后发送给其余成员；未确认的成员留给后续重传
+    pub async fn broadcast(
+        &self,
+        topic: &str,
+        payload: Vec<u8>,
+    ) -> Result<BroadcastReport, DistributedError> {
+        let message = self.inner.endpoint.next_message(topic, payload);
+        self.inner.endpoint.deliver(&message);
+        self.disseminate(message).await
+    }
+
+    /// 节点侧接收；首次交付时在后台向除来源外的成员转发。返回是否为首次交付
+    pub fn receive(&self, request: &ReplicateRequest) -> Result<bool, DistributedError> {
+        let message = Endpoint::<T>::decode(request)?;
+        if !self.inner.endpoint.deliver(&message) {
+            return Ok(false);
+        }
+        let this = self.clone();
+        tokio::spawn(async move {
+            let _ = this.disseminate(message).await;
+        });
+        Ok(true)
+    }
+
+    /// 尚有成员未确认的消息数
+    pub fn pending(&self) -> usize {
+        self.inner.pending.lock().unwrap().len()
+    }
+
+    /// 对全部待确认消息重传一轮；返回本轮重传的消息数
+    pub async fn retransmit_pending(&self) -> usize {
+        let due: Vec<(BroadcastMessage, Vec<String>)> = {
+            let mut pending = self.inner.pending.lock().unwrap();
+            pending
+                .values_mut()
+                .map(|p| {
+                    p.attempts += 1;
+                    (p.message.clone(), p.unacked.iter().cloned().collect())
+                })
+                .collect()
+        };
+        for (message, targets) in &due {
+            if let Ok(report) = self
+                .inner
+                .endpoint
+                .send_all(message, targets, self.inner.config.timeout)
+                .await
+            {
+                self.settle(&report);
+            }
+        }
+        due.len()
+    }
+
+    /// 后台按 `retransmit_interval` 驱动重传
+    pub fn start(&self) -> tokio::task::JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(this.inner.config.retransmit_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                this.retransmit_pending().await;
+            }
+        })
+    }
+
+    async fn disseminate(
+        &self,
+        message: BroadcastMessage,
+    ) -> Result<BroadcastReport, DistributedError> {
+        let targets = self.inner.endpoint.peers(&message.origin);
+        if targets.is_empty() {
+            return Ok(BroadcastReport {
+                message_id: message.id,
+                ..BroadcastReport::default()
+            });
+        }
+        self.inner.pending.lock().unwrap().insert(
+            message.id.clone(),
+            Pending {
+                message: message.clone(),
+                unacked: targets.iter().cloned().collect(),
+                attempts: 1,
+            },
+        );
+        let report = self
+            .inner
+            .endpoint
+            .send_all(&message, &targets, self.inner.config.timeout)
+            .await?;
+        self.settle(&report);
+        Ok(report)
+    }
+
+    /// 移除已确认的成员；全部确认或轮数用尽的消息不再重传
+    fn settle(&self, report: &BroadcastReport) {
+        let mut pending = self.inner.pending.lock().unwrap();
+        if let Some(p) = pending.get_mut(&report.message_id) {
+            for node in &report.delivered {
+                p.unacked.remove(node);
+            }
+            if p.unacked.is_empty() || p.attempts >= self.inner.config.max_attempts {
+                pending.remove(&report.message_id);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "transport-grpc")]
+mod grpc {
+    use super::{BestEffortBroadcast, ReliableBroadcast};
+    use crate::core::errors::DistributedError;
+    use crate::network::grpc_replication::NodeHandler;
+    use crate::storage::replication::{ReplicateRequest, ReplicationTransport};
+    use std::future::Future;
+
+    /// 挂到 `ReplicationServer` 上即可经 gRPC 接收组播
+    impl<T: ReplicationTransport> NodeHandler for BestEffortBroadcast<T> {
+        fn apply(
+            &self,
+            request: ReplicateRequest,
+        ) -> impl Future<Output = Result<(), DistributedError>> + Send {
+            let result = self.receive(&request).map(|_| ());
+            async move { result }
+        }
+    }
+
+    impl<T: ReplicationTransport> NodeHandler for ReliableBroadcast<T> {
+        fn apply(
+            &self,
+            request: ReplicateRequest,
+        ) -> impl Future<Output = Result<(), DistributedError>> + Send {
+            let result = self.receive(&request).map(|_| ());
+            async move { result }
+        }
+    }
+}
@@ -7,6 +7,23 @@
 //! 兼容性与演进（草图）：
 //! - 引入版本前缀或模式以实现向后兼容；避免破坏性变更。
 //! - 对于日志/快照等持久化格式，应记录 schema 版本与校验和。
+//!
+//! 版本化命令（`UpcastingCodec`）：
+//! - 滚动升级期间新旧节点混跑，双方须能解码对方的命令格式。线上格式为 4 字节大端
+//!   schema 版本前缀 + 该版本形状的 JSON 体；版本从 1 开始，0 保留给未版本化的负载。
+//! - 注册表按版本登记迁移 `(version, upcast, downcast)`：`upcast` 把 `version - 1` 的
+//!   JSON 形状迁移到 `version`，`downcast` 反之。解码时从线上版本逐级上迁到当前版本再
+//!   反序列化为内存类型；`encode_for_version(v)` 从当前版本逐级下迁，供混合版本集群中
+//!   向旧节点发送。
+//! - 高于当前版本（未来版本）的负载以 `CodecError::UnsupportedVersion` 拒绝并携带所见版本。
+//! - `SchemaTranscoder` 把已编码的负载改写为指定版本，供传输层按连接协商的版本发送。
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use thiserror::Error;
+
 pub trait BinaryCodec<T> {
     fn encode(&self, value: &T) -> Vec<u8>;
     fn decode(&self, bytes: &[u8]) -> Option<T>;
@@ -37,3 +54,209 @@ impl BinaryCodec<String> for StringUtf8Codec {
         std::str::from_utf8(bytes).ok().map(|s| s.to_string())
     }
 }
+
+/// 基于 serde 的 JSON 编解码器，用于持久化结构化记录
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonCodec;
+
+impl<T: serde::Serialize + serde::de::DeserializeOwned> BinaryCodec<T> for JsonCodec {
+    fn encode(&self, value: &T) -> Vec<u8> {
+        serde_json::to_vec(value).expect("serializable value")
+    }
+    fn decode(&self, bytes: &[u8]) -> Option<T> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+/// CRC-32（IEEE 802.3，反射多项式 `0xEDB88320`），用于持久化帧的完整性校验
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// 版本化编解码错误
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CodecError {
+    #[error("unsupported schema version {version} (supported 1..={current})")]
+    UnsupportedVersion { version: u32, current: u32 },
+    #[error("malformed payload: {0}")]
+    Malformed(String),
+    #[error("migration to schema version {version} failed: {reason}")]
+    Migration { version: u32, reason: String },
+}
+
+/// 单步迁移：输入为相邻版本的 JSON 形状
+pub type MigrateFn = Arc<dyn Fn(Value) -> Result<Value, String> + Send + Sync>;
+
+struct Migration {
+    upcast: MigrateFn,
+    downcast: MigrateFn,
+}
+
+/// 带 schema 版本前缀、按注册表逐级迁移的编解码器（见模块文档）
+pub struct UpcastingCodec<T> {
+    /// 下标 i 处为迁移到版本 i + 2 的一步
+    migrations: Vec<Migration>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for UpcastingCodec<T> {
+    fn clone(&self) -> Self {
+        Self {
+            migrations: self
+                .migrations
+                .iter()
+                .map(|m| Migration {
+                    upcast: m.upcast.clone(),
+                    downcast: m.downcast.clone(),
+                })
+                .collect(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for UpcastingCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> UpcastingCodec<T> {
+    /// 只有版本 1 的编解码器
+    pub fn new() -> Self {
+        Self {
+            migrations: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// 登记到 `version` 的迁移；版本须从 2 起按顺序登记，登记后当前版本即为 `version`
+    pub fn with_migration<U, D>(mut self, version: u32, upcast: U, downcast: D) -> Self
+    where
+        U: Fn(Value) -> Result<Value, String> + Send + Sync + 'static,
+        D: Fn(Value) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        assert_eq!(
+            version,
+            self.current_version() + 1,
+            "schema migrations must be registered in order"
+        );
+        self.migrations.push(Migration {
+            upcast: Arc::new(upcast),
+            downcast: Arc::new(downcast),
+        });
+        self
+    }
+
+    /// 内存类型对应的 schema 版本
+    pub fn current_version(&self) -> u32 {
+        self.migrations.len() as u32 + 1
+    }
+
+    /// 读取负载的 schema 版本前缀
+    pub fn wire_version(bytes: &[u8]) -> Result<u32, CodecError> {
+        let prefix: [u8; 4] = bytes
+            .get(..4)
+            .and_then(|p| p.try_into().ok())
+            .ok_or_else(|| CodecError::Malformed("missing schema version prefix".into()))?;
+        Ok(u32::from_be_bytes(prefix))
+    }
+
+    fn check_version(&self, version: u32) -> Result<(), CodecError> {
+        if version == 0 || version > self.current_version() {
+            return Err(CodecError::UnsupportedVersion {
+                version,
+                current: self.current_version(),
+            });
+        }
+        Ok(())
+    }
+
+    fn migration(&self, version: u32) -> &Migration {
+        &self.migrations[version as usize - 2]
+    }
+
+    /// 把 `version` 形状的 JSON 迁移到 `target` 形状
+    fn migrate(&self, mut value: Value, version: u32, target: u32) -> Result<Value, CodecError> {
+        let failed = |version, reason| CodecError::Migration { version, reason };
+        for v in version + 1..=target {
+            value = (self.migration(v).upcast)(value).map_err(|e| failed(v, e))?;
+        }
+        for v in (target + 1..=version).rev() {
+            value = (self.migration(v).downcast)(value).map_err(|e| failed(v - 1, e))?;
+        }
+        Ok(value)
+    }
+
+    fn frame(version: u32, value: &Value) -> Result<Vec<u8>, CodecError> {
+        let mut out = version.to_be_bytes().to_vec();
+        serde_json::to_writer(&mut out, value).map_err(|e| CodecError::Malformed(e.to_string()))?;
+        Ok(out)
+    }
+
+    fn unframe(&self, bytes: &[u8]) -> Result<(u32, Value), CodecError> {
+        let version = Self::wire_version(bytes)?;
+        self.check_version(version)?;
+        let value = serde_json::from_slice(&bytes[4..])
+            .map_err(|e| CodecError::Malformed(e.to_string()))?;
+        Ok((version, value))
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> UpcastingCodec<T> {
+    /// 以旧的线上版本 `version` 编码，供尚未升级的节点解码
+    pub fn encode_for_version(&self, value: &T, version: u32) -> Result<Vec<u8>, CodecError> {
+        self.check_version(version)?;
+        let json = serde_json::to_value(value).map_err(|e| CodecError::Malformed(e.to_string()))?;
+        let json = self.migrate(json, self.current_version(), version)?;
+        Self::frame(version, &json)
+    }
+
+    /// 解码任意已知版本的负载，逐级上迁到当前版本
+    pub fn try_decode(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        let (version, json) = self.unframe(bytes)?;
+        let json = self.migrate(json, version, self.current_version())?;
+        serde_json::from_value(json).map_err(|e| CodecError::Malformed(e.to_string()))
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> BinaryCodec<T> for UpcastingCodec<T> {
+    fn encode(&self, value: &T) -> Vec<u8> {
+        self.encode_for_version(value, self.current_version())
+            .expect("serializable value")
+    }
+    fn decode(&self, bytes: &[u8]) -> Option<T> {
+        self.try_decode(bytes).ok()
+    }
+}
+
+/// 把已编码的负载改写为指定 schema 版本，供传输层按对端协商的版本发送
+pub trait SchemaTranscoder: Send + Sync {
+    /// 本节点支持的最高版本
+    fn current_version(&self) -> u32;
+    /// 把带版本前缀的负载改写为 `version`；已是该版本时原样返回
+    fn transcode(&self, bytes: &[u8], version: u32) -> Result<Vec<u8>, CodecError>;
+}
+
+impl<T> SchemaTranscoder for UpcastingCodec<T> {
+    fn current_version(&self) -> u32 {
+        UpcastingCodec::current_version(self)
+    }
+
+    fn transcode(&self, bytes: &[u8], version: u32) -> Result<Vec<u8>, CodecError> {
+        self.check_version(version)?;
+        let (from, json) = self.unframe(bytes)?;
+        if from == version {
+            return Ok(bytes.to_vec());
+        }
+        let json = self.migrate(json, from, version)?;
+        Self::frame(version, &json)
+    }
+}
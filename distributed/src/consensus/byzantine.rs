@@ -15,9 +15,14 @@
 //!
 //! 参考文献见 `consensus::mod` 顶部列表（Castro & Liskov, 1999 等）。
 
+use super::{ConsensusNode, ConsensusRole, ProposalId};
 use crate::consistency::ConsistencyLevel;
+use crate::core::errors::DistributedError;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
 use std::time::{Duration, SystemTime};
 
 /// 拜占庭容错节点状态
@@ -79,6 +84,8 @@ pub enum ByzantineMessage {
         view_change_certificates: Vec<ViewChangeCertificate>,
         timestamp: SystemTime,
     },
+    /// 检查点消息
+    Checkpoint(CheckpointMsg),
 }
 
 /// 准备证书
@@ -97,6 +104,179 @@ pub struct ViewChangeCertificate {
     pub view_change_messages: Vec<ByzantineMessage>,
 }
 
+/// 检查点消息：副本执行到 `seq` 后对状态摘要的声明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointMsg {
+    pub seq: u64,
+    pub digest: String,
+    pub node_id: String,
+}
+
+/// 稳定检查点：已收集到 2f+1 个摘要一致的检查点消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StableCheckpoint {
+    pub seq: u64,
+    pub digest: String,
+    pub proof: Vec<CheckpointMsg>,
+}
+
+/// 按序列号组织的协议消息日志
+#[derive(Debug, Clone, Default)]
+pub struct MessageLog {
+    entries: BTreeMap<u64, Vec<ByzantineMessage>>,
+}
+
+impl MessageLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn append(&mut self, seq: u64, message: ByzantineMessage) {
+        self.entries.entry(seq).or_default().push(message);
+    }
+
+    /// 日志中的序列号个数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 日志中的消息总数
+    pub fn message_count(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    /// 丢弃 `seq` 及之前的全部消息，返回丢弃的序列号个数
+    pub fn truncate_through(&mut self, seq: u64) -> usize {
+        // seq == u64::MAX 时没有更大的序列号可保留
+        let kept = match seq.checked_add(1) {
+            Some(next) => self.entries.split_off(&next),
+            None => BTreeMap::new(),
+        };
+        let removed = self.entries.len();
+        self.entries = kept;
+        removed
+    }
+
+    /// `seq` 之后（不含）的全部消息，按序列号排序
+    pub fn messages_after(&self, seq: u64) -> Vec<ByzantineMessage> {
+        self.entries_after(seq).into_iter().map(|(_, m)| m).collect()
+    }
+
+    /// `seq` 之后（不含）的全部消息及其序列号
+    pub fn entries_after(&self, seq: u64) -> Vec<(u64, ByzantineMessage)> {
+        let Some(next) = seq.checked_add(1) else {
+            return Vec::new();
+        };
+        self.entries
+            .range(next..)
+            .flat_map(|(s, msgs)| msgs.iter().map(move |m| (*s, m.clone())))
+            .collect()
+    }
+}
+
+/// 状态传输请求：重启的副本携带自己最近的稳定检查点序列号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateTransferReq {
+    pub requesting_node: String,
+    pub last_stable_seq: u64,
+}
+
+/// 状态传输响应
+///
+/// 若响应方的稳定检查点比请求方新，则附带该检查点（含 2f+1 证明）与对应的状态快照；
+/// `missing_msgs` 为检查点之后的消息，按序列号排序。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateTransferResp {
+    pub checkpoint: Option<StableCheckpoint>,
+    pub snapshot: Vec<u8>,
+    pub missing_msgs: Vec<(u64, ByzantineMessage)>,
+}
+
+/// PBFT 副本间的传输抽象（状态传输等点对点请求）
+pub trait BftNetwork: Send + Sync {
+    fn request_state_transfer<'a>(
+        &'a self,
+        target: &'a str,
+        req: StateTransferReq,
+    ) -> Pin<Box<dyn Future<Output = Result<StateTransferResp, String>> + Send + 'a>>;
+}
+
+/// 已执行状态的快照格式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PbftStateSnapshot {
+    last_executed: u64,
+    committed: BTreeMap<String, Vec<u8>>,
+}
+
+/// 检查点垃圾回收器：收集检查点投票，形成稳定检查点后截断消息日志
+#[derive(Debug, Clone)]
+pub struct GarbageCollector {
+    quorum: usize,
+    votes: HashMap<(u64, String), Vec<CheckpointMsg>>,
+    stable: Option<StableCheckpoint>,
+}
+
+impl GarbageCollector {
+    pub fn new(quorum: usize) -> Self {
+        Self {
+            quorum,
+            votes: HashMap::new(),
+            stable: None,
+        }
+    }
+
+    /// 最近一次的稳定检查点
+    pub fn stable_checkpoint(&self) -> Option<&StableCheckpoint> {
+        self.stable.as_ref()
+    }
+
+    pub fn stable_seq(&self) -> u64 {
+        self.stable.as_ref().map(|c| c.seq).unwrap_or(0)
+    }
+
+    /// 直接安装（经状态传输获得且已校验的）稳定检查点
+    pub fn install(&mut self, checkpoint: StableCheckpoint) {
+        if checkpoint.seq <= self.stable_seq() {
+            return;
+        }
+        self.votes.retain(|(seq, _), _| *seq > checkpoint.seq);
+        self.stable = Some(checkpoint);
+    }
+
+    /// 记录一条检查点消息；收集到 `quorum` 条摘要一致的消息时形成新的稳定检查点，
+    /// 并截断 `log` 中序列号不大于该检查点的消息
+    pub fn record(&mut self, msg: CheckpointMsg, log: &mut MessageLog) -> Option<StableCheckpoint> {
+        if msg.seq <= self.stable_seq() {
+            return None;
+        }
+        let votes = self.votes.entry((msg.seq, msg.digest.clone())).or_default();
+        if votes.iter().any(|m| m.node_id == msg.node_id) {
+            return None;
+        }
+        votes.push(msg.clone());
+        if votes.len() < self.quorum {
+            return None;
+        }
+
+        let checkpoint = StableCheckpoint {
+            seq: msg.seq,
+            digest: msg.digest,
+            proof: votes.clone(),
+        };
+        self.votes.retain(|(seq, _), _| *seq > checkpoint.seq);
+        log.truncate_through(checkpoint.seq);
+        self.stable = Some(checkpoint.clone());
+        Some(checkpoint)
+    }
+}
+
+/// 默认检查点间隔（序列号个数）
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 100;
+
 /// PBFT（Practical Byzantine Fault Tolerance）实现
 #[derive(Debug, Clone)]
 pub struct PBFTNode {
@@ -109,6 +289,21 @@ pub struct PBFTNode {
     pub pending_requests: HashMap<String, ByzantineMessage>,
     pub max_faulty_nodes: usize,
     pub total_nodes: usize,
+    pub checkpoint_interval: u64,
+    pub message_log: MessageLog,
+    pub gc: GarbageCollector,
+    /// 已按序执行到的序列号
+    pub last_executed: u64,
+    /// 本地检查点对应的状态快照，稳定后仅保留最新一份
+    checkpoint_snapshots: BTreeMap<u64, Vec<u8>>,
+    /// 按请求摘要缓存的客户端请求，提交后按摘要取出执行
+    requests_by_digest: HashMap<String, ByzantineMessage>,
+    /// (view, seq, digest) 上已收到 PreCommit 的副本
+    pre_commit_votes: HashMap<(u64, u64, String), Vec<String>>,
+    /// (view, seq, digest) 上已收到 Commit 的副本
+    commit_votes: HashMap<(u64, u64, String), Vec<String>>,
+    /// 已形成 2f+1 提交证书、等待按序执行的序列号及其请求摘要
+    committed_digests: BTreeMap<u64, String>,
 }
 
 impl PBFTNode {
@@ -126,9 +321,24 @@ impl PBFTNode {
             pending_requests: HashMap::new(),
             max_faulty_nodes,
             total_nodes,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            message_log: MessageLog::new(),
+            gc: GarbageCollector::new(2 * max_faulty_nodes + 1),
+            last_executed: 0,
+            checkpoint_snapshots: BTreeMap::new(),
+            requests_by_digest: HashMap::new(),
+            pre_commit_votes: HashMap::new(),
+            commit_votes: HashMap::new(),
+            committed_digests: BTreeMap::new(),
         }
     }
 
+    /// 设置检查点间隔
+    pub fn with_checkpoint_interval(mut self, interval: u64) -> Self {
+        self.checkpoint_interval = interval.max(1);
+        self
+    }
+
     /// 检查是否满足拜占庭容错要求
     pub fn is_byzantine_fault_tolerant(&self) -> bool {
         self.total_nodes > 3 * self.max_faulty_nodes && self.total_nodes >= 4
@@ -139,6 +349,14 @@ impl PBFTNode {
         2 * self.max_faulty_nodes + 1
     }
 
+    /// 是否为配置中的副本；副本 ID 与 `get_primary_id` 一致，为 `node_0..node_{n-1}`
+    pub fn is_replica(&self, node_id: &str) -> bool {
+        node_id
+            .strip_prefix("node_")
+            .and_then(|i| i.parse::<usize>().ok())
+            .is_some_and(|i| i < self.total_nodes && node_id == format!("node_{i}"))
+    }
+
     /// 处理请求消息
     pub fn handle_request(
         &mut self,
@@ -148,7 +366,9 @@ impl PBFTNode {
             // 检查是否是主节点
             if self.is_primary() {
                 self.sequence += 1;
-                let digest = self.compute_digest(&content);
+                let digest = self.request_digest(&id, &content);
+                self.requests_by_digest
+                    .insert(digest.clone(), message.clone());
 
                 // 创建准备消息
                 let prepare_message = ByzantineMessage::Prepare {
@@ -160,6 +380,7 @@ impl PBFTNode {
                 };
 
                 self.pending_requests.insert(id.clone(), message);
+                self.log_message(self.sequence, prepare_message.clone());
 
                 Ok(vec![prepare_message])
             } else {
@@ -170,7 +391,25 @@ impl PBFTNode {
         }
     }
 
+    /// 副本缓存客户端请求（PBFT 中客户端可向全部副本多播请求）；
+    /// 该请求已有提交证书时随即按序执行，返回执行产生的检查点消息
+    pub fn receive_request(
+        &mut self,
+        message: ByzantineMessage,
+    ) -> Result<Vec<ByzantineMessage>, String> {
+        let ByzantineMessage::Request { id, content, .. } = &message else {
+            return Err("无效的消息类型".to_string());
+        };
+        let digest = self.request_digest(id, content);
+        self.requests_by_digest.insert(digest, message);
+        self.execute_committed()
+    }
+
     /// 处理准备消息
+    ///
+    /// 主节点的 Prepare 即 pre-prepare：开启 (view, seq) 的准备证书，备份节点随即回显自己的 Prepare；
+    /// 同一摘要上收集到 2f+1 个不同副本的 Prepare 后发出 PreCommit。
+    /// 早于 pre-prepare 到达的备份 Prepare 被忽略。调用方负责广播（含发送者自身）。
     pub fn handle_prepare(
         &mut self,
         message: ByzantineMessage,
@@ -179,6 +418,7 @@ impl PBFTNode {
             view,
             sequence,
             digest,
+            sender,
             ..
         } = message.clone()
         {
@@ -186,42 +426,63 @@ impl PBFTNode {
             if !self.validate_prepare_message(&message) {
                 return Err("无效的准备消息".to_string());
             }
+            self.log_message(sequence, message.clone());
+
+            let from_primary = sender == self.get_primary_id();
+            let is_primary = self.is_primary();
+            let quorum = self.quorum_size();
 
             // 收集准备消息
             let key = format!("{}-{}", view, sequence);
+            if !from_primary && !self.prepared_certificates.contains_key(&key) {
+                return Ok(vec![]);
+            }
             let certificate = self
                 .prepared_certificates
-                .entry(key.clone())
+                .entry(key)
                 .or_insert_with(|| PreparedCertificate {
                     view,
                     sequence,
                     digest: digest.clone(),
                     prepare_messages: Vec::new(),
                 });
-
+            let duplicate = certificate
+                .prepare_messages
+                .iter()
+                .any(|m| matches!(m, ByzantineMessage::Prepare { sender: s, .. } if *s == sender));
+            if certificate.digest != digest || duplicate {
+                return Ok(vec![]);
+            }
             certificate.prepare_messages.push(message);
+            let prepared = certificate.prepare_messages.len() == quorum;
 
+            let mut out = Vec::new();
+            if from_primary && !is_primary {
+                out.push(ByzantineMessage::Prepare {
+                    view,
+                    sequence,
+                    digest: digest.clone(),
+                    sender: self.node_id.clone(),
+                    timestamp: SystemTime::now(),
+                });
+            }
             // 检查是否收集到足够的准备消息
-            if certificate.prepare_messages.len() >= self.quorum_size() {
-                // 创建预提交消息
-                let pre_commit_message = ByzantineMessage::PreCommit {
+            if prepared {
+                out.push(ByzantineMessage::PreCommit {
                     view,
                     sequence,
                     digest,
                     sender: self.node_id.clone(),
                     timestamp: SystemTime::now(),
-                };
-
-                Ok(vec![pre_commit_message])
-            } else {
-                Ok(vec![])
+                });
             }
+            Ok(out)
         } else {
             Err("无效的消息类型".to_string())
         }
     }
 
-    /// 处理预提交消息
+    /// 处理预提交消息；同一摘要上收集到 2f+1 个不同副本的 PreCommit 后发出 Commit
     pub fn handle_pre_commit(
         &mut self,
         message: ByzantineMessage,
@@ -230,6 +491,7 @@ impl PBFTNode {
             view,
             sequence,
             digest,
+            sender,
             ..
         } = message.clone()
         {
@@ -237,32 +499,27 @@ impl PBFTNode {
             if !self.validate_pre_commit_message(&message) {
                 return Err("无效的预提交消息".to_string());
             }
-
-            // 收集预提交消息
-            let key = format!("{}-{}", view, sequence);
-            let mut pre_commit_count = 0;
-
-            // 这里应该维护预提交消息的收集，简化实现
-            pre_commit_count += 1;
+            self.log_message(sequence, message);
+
+            let quorum = self.quorum_size();
+            let voters = self
+                .pre_commit_votes
+                .entry((view, sequence, digest.clone()))
+                .or_default();
+            if voters.contains(&sender) {
+                return Ok(vec![]);
+            }
+            voters.push(sender);
 
             // 检查是否收集到足够的预提交消息
-            if pre_commit_count >= self.quorum_size() {
-                // 提交请求
-                if let Some(request) = self.pending_requests.get(&key)
-                    && let ByzantineMessage::Request { content, .. } = request {
-                        self.committed_requests.insert(key.clone(), content.clone());
-                    }
-
-                // 创建提交消息
-                let commit_message = ByzantineMessage::Commit {
+            if voters.len() == quorum {
+                Ok(vec![ByzantineMessage::Commit {
                     view,
                     sequence,
                     digest,
                     sender: self.node_id.clone(),
                     timestamp: SystemTime::now(),
-                };
-
-                Ok(vec![commit_message])
+                }])
             } else {
                 Ok(vec![])
             }
@@ -271,25 +528,284 @@ impl PBFTNode {
         }
     }
 
-    /// 处理提交消息
-    pub fn handle_commit(&mut self, message: ByzantineMessage) -> Result<(), String> {
-        if let ByzantineMessage::Commit { view, sequence, .. } = message.clone() {
+    /// 处理提交消息；同一摘要上收集到 2f+1 个不同副本的 Commit 即形成提交证书，
+    /// 随后按序执行请求体已知的序列号，返回执行产生的检查点消息
+    pub fn handle_commit(
+        &mut self,
+        message: ByzantineMessage,
+    ) -> Result<Vec<ByzantineMessage>, String> {
+        if let ByzantineMessage::Commit {
+            view,
+            sequence,
+            digest,
+            sender,
+            ..
+        } = message.clone()
+        {
             // 验证消息
             if !self.validate_commit_message(&message) {
                 return Err("无效的提交消息".to_string());
             }
-
-            // 这里应该收集提交消息并执行最终提交
-            // 简化实现，直接标记为已提交
-            let key = format!("{}-{}", view, sequence);
-            println!("节点 {} 提交了请求 {}", self.node_id, key);
-
-            Ok(())
+            self.log_message(sequence, message);
+
+            let quorum = self.quorum_size();
+            let voters = self
+                .commit_votes
+                .entry((view, sequence, digest.clone()))
+                .or_default();
+            if voters.contains(&sender) {
+                return Ok(vec![]);
+            }
+            voters.push(sender);
+            if voters.len() == quorum && sequence > self.last_executed {
+                self.committed_digests.entry(sequence).or_insert(digest);
+            }
+            self.execute_committed()
         } else {
             Err("无效的消息类型".to_string())
         }
     }
 
+    /// 按序执行已有提交证书且请求体已知的序列号
+    fn execute_committed(&mut self) -> Result<Vec<ByzantineMessage>, String> {
+        let mut checkpoints = Vec::new();
+        loop {
+            let seq = self.last_executed + 1;
+            let Some(digest) = self.committed_digests.get(&seq) else {
+                break;
+            };
+            let Some(request) = self.requests_by_digest.remove(digest) else {
+                break;
+            };
+            self.committed_digests.remove(&seq);
+            if let Some(cp) = self.execute(seq, request)? {
+                checkpoints.push(ByzantineMessage::Checkpoint(cp));
+            }
+        }
+        Ok(checkpoints)
+    }
+
+    /// 记录某序列号下的协议消息
+    pub fn log_message(&mut self, seq: u64, message: ByzantineMessage) {
+        if seq > self.gc.stable_seq() {
+            self.message_log.append(seq, message);
+        }
+    }
+
+    /// 执行完 `seq` 后调用；当 `seq` 是检查点间隔的整数倍时生成待广播的检查点消息
+    pub fn maybe_checkpoint(&mut self, seq: u64, state_digest: &str) -> Option<CheckpointMsg> {
+        if seq == 0 || !seq.is_multiple_of(self.checkpoint_interval) {
+            return None;
+        }
+        let msg = CheckpointMsg {
+            seq,
+            digest: state_digest.to_string(),
+            node_id: self.node_id.clone(),
+        };
+        // 自己的检查点同样计入投票
+        self.handle_checkpoint(msg.clone());
+        Some(msg)
+    }
+
+    /// 处理检查点消息；形成稳定检查点时丢弃 seq ≤ checkpoint.seq 的全部消息与证书
+    pub fn handle_checkpoint(&mut self, msg: CheckpointMsg) -> Option<StableCheckpoint> {
+        if !self.is_replica(&msg.node_id) {
+            return None;
+        }
+        if let Some(state) = self.node_states.get(&msg.node_id)
+            && *state == ByzantineNodeState::Byzantine {
+                return None;
+            }
+        let stable = self.gc.record(msg, &mut self.message_log)?;
+        self.prepared_certificates
+            .retain(|_, cert| cert.sequence > stable.seq);
+        self.checkpoint_snapshots.retain(|seq, _| *seq >= stable.seq);
+        self.discard_votes_through(stable.seq);
+        Some(stable)
+    }
+
+    /// 按序执行 `seq` 处的请求；`seq` 必须紧接 `last_executed`，否则需要先做状态传输。
+    /// 到达检查点间隔时返回待广播的检查点消息。
+    pub fn execute(
+        &mut self,
+        seq: u64,
+        request: ByzantineMessage,
+    ) -> Result<Option<CheckpointMsg>, String> {
+        if seq != self.last_executed + 1 {
+            return Err(format!(
+                "序列号不连续: 期望 {}, 实际 {}",
+                self.last_executed + 1,
+                seq
+            ));
+        }
+        let ByzantineMessage::Request { id, content, .. } = &request else {
+            return Err("无效的消息类型".to_string());
+        };
+        self.committed_requests.insert(id.clone(), content.clone());
+        self.log_message(seq, request);
+        self.last_executed = seq;
+
+        if seq.is_multiple_of(self.checkpoint_interval) {
+            let snapshot = self.encode_snapshot();
+            let digest = self.compute_digest(&snapshot);
+            self.checkpoint_snapshots.insert(seq, snapshot);
+            return Ok(self.maybe_checkpoint(seq, &digest));
+        }
+        Ok(None)
+    }
+
+    /// 当前已执行状态的摘要
+    pub fn state_digest(&self) -> String {
+        self.compute_digest(&self.encode_snapshot())
+    }
+
+    fn encode_snapshot(&self) -> Vec<u8> {
+        let snapshot = PbftStateSnapshot {
+            last_executed: self.last_executed,
+            committed: self
+                .committed_requests
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        };
+        serde_json::to_vec(&snapshot).unwrap_or_default()
+    }
+
+    /// 响应重启副本的状态传输请求
+    pub fn handle_state_transfer_request(
+        &self,
+        req: &StateTransferReq,
+    ) -> Result<StateTransferResp, String> {
+        match self.gc.stable_checkpoint() {
+            Some(stable) if stable.seq > req.last_stable_seq => {
+                let snapshot = self
+                    .checkpoint_snapshots
+                    .get(&stable.seq)
+                    .cloned()
+                    .ok_or_else(|| format!("缺少检查点 {} 的状态快照", stable.seq))?;
+                Ok(StateTransferResp {
+                    checkpoint: Some(stable.clone()),
+                    snapshot,
+                    missing_msgs: self.message_log.entries_after(stable.seq),
+                })
+            }
+            _ => Ok(StateTransferResp {
+                checkpoint: None,
+                snapshot: Vec::new(),
+                missing_msgs: self.message_log.entries_after(req.last_stable_seq),
+            }),
+        }
+    }
+
+    /// 重启后追赶：从 `leader` 拉取最新稳定检查点与其后的消息，安装快照并重放缺失请求
+    pub async fn catch_up(&mut self, leader: &str, transport: &dyn BftNetwork) -> Result<(), String> {
+        let req = StateTransferReq {
+            requesting_node: self.node_id.clone(),
+            last_stable_seq: self.gc.stable_seq(),
+        };
+        let resp = transport.request_state_transfer(leader, req).await?;
+
+        if let Some(checkpoint) = resp.checkpoint {
+            self.install_checkpoint(checkpoint, &resp.snapshot)?;
+        }
+        // 只重放带 2f+1 提交证书的请求；其余请求仅缓存，待后续 Commit 补齐证书后执行
+        let certified = self.commit_certificates(&resp.missing_msgs);
+        for (seq, message) in resp.missing_msgs {
+            match &message {
+                ByzantineMessage::Request { id, content, .. } if seq > self.last_executed => {
+                    let digest = self.request_digest(id, content);
+                    if seq == self.last_executed + 1 && certified.get(&seq) == Some(&digest) {
+                        self.execute(seq, message)?;
+                    } else {
+                        self.requests_by_digest.insert(digest, message);
+                    }
+                }
+                _ => self.log_message(seq, message),
+            }
+        }
+        Ok(())
+    }
+
+    /// 统计消息集合中的提交证书：同一 (view, seq, digest) 上来自 2f+1 个不同配置副本的 Commit
+    fn commit_certificates(&self, messages: &[(u64, ByzantineMessage)]) -> HashMap<u64, String> {
+        let mut votes: HashMap<(u64, u64, &str), Vec<&str>> = HashMap::new();
+        for (_, message) in messages {
+            if let ByzantineMessage::Commit {
+                view,
+                sequence,
+                digest,
+                sender,
+                ..
+            } = message
+                && self.is_replica(sender)
+            {
+                let voters = votes.entry((*view, *sequence, digest)).or_default();
+                if !voters.contains(&sender.as_str()) {
+                    voters.push(sender);
+                }
+            }
+        }
+        votes
+            .into_iter()
+            .filter(|(_, voters)| voters.len() >= self.quorum_size())
+            .map(|((_, seq, digest), _)| (seq, digest.to_string()))
+            .collect()
+    }
+
+    /// 丢弃 seq ≤ `seq` 的投票与提交证书
+    fn discard_votes_through(&mut self, seq: u64) {
+        self.pre_commit_votes.retain(|(_, s, _), _| *s > seq);
+        self.commit_votes.retain(|(_, s, _), _| *s > seq);
+        self.committed_digests.retain(|s, _| *s > seq);
+    }
+
+    /// 校验检查点证明与快照摘要后安装快照
+    fn install_checkpoint(
+        &mut self,
+        checkpoint: StableCheckpoint,
+        snapshot: &[u8],
+    ) -> Result<(), String> {
+        let mut signers: Vec<&str> = checkpoint
+            .proof
+            .iter()
+            .filter(|m| m.seq == checkpoint.seq && m.digest == checkpoint.digest)
+            .filter(|m| self.is_replica(&m.node_id))
+            .map(|m| m.node_id.as_str())
+            .collect();
+        signers.sort_unstable();
+        signers.dedup();
+        if signers.len() < self.quorum_size() {
+            return Err(format!(
+                "检查点 {} 的证明不足: {}/{}",
+                checkpoint.seq,
+                signers.len(),
+                self.quorum_size()
+            ));
+        }
+        if self.compute_digest(snapshot) != checkpoint.digest {
+            return Err(format!("检查点 {} 的快照摘要不匹配", checkpoint.seq));
+        }
+        let state: PbftStateSnapshot =
+            serde_json::from_slice(snapshot).map_err(|e| e.to_string())?;
+
+        self.committed_requests = state.committed.into_iter().collect();
+        self.last_executed = state.last_executed;
+        self.checkpoint_snapshots.clear();
+        self.checkpoint_snapshots
+            .insert(checkpoint.seq, snapshot.to_vec());
+        self.message_log.truncate_through(checkpoint.seq);
+        self.prepared_certificates
+            .retain(|_, cert| cert.sequence > checkpoint.seq);
+        self.discard_votes_through(checkpoint.seq);
+        self.gc.install(checkpoint);
+        Ok(())
+    }
+
+    /// 最近一次稳定检查点
+    pub fn stable_checkpoint(&self) -> Option<&StableCheckpoint> {
+        self.gc.stable_checkpoint()
+    }
+
     /// 检查是否是主节点
     pub fn is_primary(&self) -> bool {
         self.node_id == self.get_primary_id()
@@ -314,8 +830,13 @@ impl PBFTNode {
                 return false;
             }
 
-            // 检查序列号
-            if *sequence <= self.sequence {
+            // 检查序列号：不低于稳定检查点（低水位）
+            if *sequence <= self.gc.stable_seq() {
+                return false;
+            }
+
+            // 只接受配置中的副本
+            if !self.is_replica(sender) {
                 return false;
             }
 
@@ -345,8 +866,13 @@ impl PBFTNode {
                 return false;
             }
 
-            // 检查序列号
-            if *sequence <= self.sequence {
+            // 检查序列号：不低于稳定检查点（低水位）
+            if *sequence <= self.gc.stable_seq() {
+                return false;
+            }
+
+            // 只接受配置中的副本
+            if !self.is_replica(sender) {
                 return false;
             }
 
@@ -376,8 +902,13 @@ impl PBFTNode {
                 return false;
             }
 
-            // 检查序列号
-            if *sequence <= self.sequence {
+            // 检查序列号：不低于稳定检查点（低水位）
+            if *sequence <= self.gc.stable_seq() {
+                return false;
+            }
+
+            // 只接受配置中的副本
+            if !self.is_replica(sender) {
                 return false;
             }
 
@@ -393,14 +924,21 @@ impl PBFTNode {
         }
     }
 
-    /// 计算消息摘要
+    /// 计算消息摘要（SHA-256，十六进制）
     fn compute_digest(&self, content: &[u8]) -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
+        Sha256::digest(content)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
 
-        let mut hasher = DefaultHasher::new();
-        content.hash(&mut hasher);
-        format!("{:x}", hasher.finish())
+    /// 请求摘要覆盖请求 ID 与内容，内容相同的不同请求不会混淆
+    fn request_digest(&self, id: &str, content: &[u8]) -> String {
+        let mut bytes = Vec::with_capacity(id.len() + 1 + content.len());
+        bytes.extend_from_slice(id.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(content);
+        self.compute_digest(&bytes)
     }
 
     /// 标记节点为拜占庭节点
@@ -449,6 +987,34 @@ impl PBFTNode {
     }
 }
 
+/// 主节点即领导者；视图号充当任期
+impl ConsensusNode for PBFTNode {
+    fn role(&self) -> ConsensusRole {
+        if self.is_primary() {
+            ConsensusRole::Leader
+        } else {
+            ConsensusRole::Follower
+        }
+    }
+
+    fn current_term(&self) -> u64 {
+        self.view
+    }
+
+    /// 主节点为命令分配序列号并生成 Prepare；调用方负责广播
+    fn propose(&mut self, command: Vec<u8>) -> Result<ProposalId, DistributedError> {
+        let request = ByzantineMessage::Request {
+            id: format!("{}-{}", self.node_id, self.sequence + 1),
+            content: command,
+            timestamp: SystemTime::now(),
+            sender: self.node_id.clone(),
+        };
+        self.handle_request(request)
+            .map_err(DistributedError::Consensus)?;
+        Ok(ProposalId(self.sequence))
+    }
+}
+
 /// 拜占庭容错网络模拟器
 #[derive(Debug, Clone)]
 pub struct ByzantineNetwork {
@@ -507,6 +1073,7 @@ impl ByzantineNetwork {
             ByzantineMessage::Commit { sender, .. } => sender.clone(),
             ByzantineMessage::ViewChange { sender, .. } => sender.clone(),
             ByzantineMessage::NewView { sender, .. } => sender.clone(),
+            ByzantineMessage::Checkpoint(msg) => msg.node_id.clone(),
         };
 
         if let Some(node) = self.nodes.get_mut(&target_node) {
@@ -517,8 +1084,9 @@ impl ByzantineNetwork {
                     
                     node.handle_pre_commit(message)?
                 }
-                ByzantineMessage::Commit { .. } => {
-                    node.handle_commit(message)?;
+                ByzantineMessage::Commit { .. } => node.handle_commit(message)?,
+                ByzantineMessage::Checkpoint(msg) => {
+                    node.handle_checkpoint(msg.clone());
                     vec![]
                 }
                 _ => vec![],
@@ -567,6 +1135,22 @@ impl ByzantineNetwork {
     }
 }
 
+impl BftNetwork for ByzantineNetwork {
+    fn request_state_transfer<'a>(
+        &'a self,
+        target: &'a str,
+        req: StateTransferReq,
+    ) -> Pin<Box<dyn Future<Output = Result<StateTransferResp, String>> + Send + 'a>> {
+        Box::pin(async move {
+            let node = self
+                .nodes
+                .get(target)
+                .ok_or_else(|| format!("未知节点: {}", target))?;
+            node.handle_state_transfer_request(&req)
+        })
+    }
+}
+
 /// 拜占庭网络统计信息
 #[derive(Debug, Clone)]
 pub struct ByzantineNetworkStats {
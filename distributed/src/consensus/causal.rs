@@ -0,0 +1,127 @@
+//! 因果广播（Causal Broadcast）
+//!
+//! 设计意图：
+//! - 许多应用只需要因果序，不必为全序广播付出共识代价；本模块基于 `VectorClock` 实现因果投递。
+//! - 每条消息捎带发送者的向量时钟；接收方在其因果依赖全部投递前将消息暂存于缓冲区。
+//!
+//! 投递条件（来自发送者 `j` 的消息 `m`，本地已投递计数为 `D`）：
+//! - `m.clock[j] == D[j] + 1`：是 `j` 的下一条消息；
+//! - 对任意 `k != j`，`m.clock[k] <= D[k]`：`j` 发送前已见的消息本地均已投递。
+//!
+//! 性质（草图）：
+//! - 若 `send(m1)` 因果先于 `send(m2)`，则任何节点都在投递 `m2` 之前投递 `m1`。
+//! - 重复或已投递的消息被丢弃，不会二次投递。
+
+use crate::consistency::VectorClock;
+use crate::core::membership::ClusterNodeId;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// 捎带向量时钟的广播消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CausalMessage {
+    pub sender: ClusterNodeId,
+    pub clock: VectorClock,
+    pub payload: Vec<u8>,
+}
+
+/// 单节点的因果广播状态：已投递计数、待发送队列与延迟投递缓冲区
+#[derive(Debug, Clone)]
+pub struct CausalBroadcast {
+    node_id: ClusterNodeId,
+    /// 各发送者已投递的消息数
+    delivered: VectorClock,
+    outbox: VecDeque<CausalMessage>,
+    /// 因果依赖尚未满足的消息
+    pending: Vec<CausalMessage>,
+    /// 已满足投递条件、等待 `deliver()` 取走的消息
+    ready: VecDeque<CausalMessage>,
+}
+
+impl CausalBroadcast {
+    pub fn new(node_id: impl Into<ClusterNodeId>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            delivered: VectorClock::new(),
+            outbox: VecDeque::new(),
+            pending: Vec::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    pub fn node_id(&self) -> &ClusterNodeId {
+        &self.node_id
+    }
+
+    /// 广播负载：本地立即投递，并把消息放入待发送队列；返回消息携带的向量时钟
+    pub fn send(&mut self, payload: Vec<u8>) -> VectorClock {
+        let mut clock = self.delivered.clone();
+        clock.increment(self.node_id.as_str());
+        self.delivered = clock.clone();
+
+        let msg = CausalMessage {
+            sender: self.node_id.clone(),
+            clock: clock.clone(),
+            payload,
+        };
+        self.outbox.push_back(msg.clone());
+        self.ready.push_back(msg);
+        clock
+    }
+
+    /// 取走待发送给其他节点的消息
+    pub fn take_outgoing(&mut self) -> Vec<CausalMessage> {
+        self.outbox.drain(..).collect()
+    }
+
+    /// 接收其他节点的消息；依赖未满足时暂存于缓冲区
+    pub fn receive(&mut self, msg: CausalMessage) {
+        if msg.sender == self.node_id || self.is_duplicate(&msg) {
+            return;
+        }
+        self.pending.push(msg);
+        self.drain_pending();
+    }
+
+    /// 按因果序返回所有可投递的消息
+    pub fn deliver(&mut self) -> Vec<CausalMessage> {
+        self.ready.drain(..).collect()
+    }
+
+    /// 本地已投递的向量时钟
+    pub fn delivered_clock(&self) -> &VectorClock {
+        &self.delivered
+    }
+
+    /// 缓冲区中等待依赖的消息数
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn is_duplicate(&self, msg: &CausalMessage) -> bool {
+        msg.clock.get(msg.sender.as_str()) <= self.delivered.get(msg.sender.as_str())
+            || self
+                .pending
+                .iter()
+                .any(|p| p.sender == msg.sender && p.clock == msg.clock)
+    }
+
+    fn is_deliverable(&self, msg: &CausalMessage) -> bool {
+        if msg.clock.get(msg.sender.as_str()) != self.delivered.get(msg.sender.as_str()) + 1 {
+            return false;
+        }
+        // 除发送者分量外，消息时钟不得超过本地已投递计数
+        let mut bound = self.delivered.clone();
+        bound.increment(msg.sender.as_str());
+        msg.clock.is_equal(&bound) || msg.clock.happens_before(&bound)
+    }
+
+    /// 反复扫描缓冲区，直到没有新的消息变为可投递
+    fn drain_pending(&mut self) {
+        while let Some(pos) = self.pending.iter().position(|m| self.is_deliverable(m)) {
+            let msg = self.pending.swap_remove(pos);
+            self.delivered.increment(msg.sender.as_str());
+            self.ready.push_back(msg);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! 可靠 FIFO 广播（Reliable FIFO Broadcast）
+//!
+//! 设计意图：
+//! - Gossip 与因果广播在发送者传播到一半崩溃时不保证送达。本模块在每个发送者的序号上实现
+//!   可靠广播：消息携带 `(sender_id, seq_num)`，接收方按发送者的序号连续投递（FIFO），
+//!   乱序到达的消息暂存到缺口补齐。
+//! - 可靠性来自重传与转发：每个持有消息副本的节点（发送者本身，以及首次收到消息的转发者）
+//!   都对尚未确认的成员周期性重传（`tick`），因此只要有一个存活节点收到了消息，发送者崩溃
+//!   后其余存活节点仍会收到。
+//! - 确认是累积的：`Ack { origin, through }` 表示确认方已按序投递 `origin` 的前 `through` 条
+//!   消息。重复到达的消息同样回复确认（之前的确认可能丢失）。全部成员确认后副本被回收。
+//! - 与 `crate::broadcast::ReliableBroadcast`（绑定复制传输、按消息 ID 去重）不同，这里是
+//!   与传输无关的状态机：调用方把 `take_outgoing` 的消息送到对端并调用 `handle_message`，
+//!   与 `CausalBroadcast` 的用法一致。
+//! - `max_buffer_size` 限制缓冲的消息数：本节点发出的未确认消息达到上限时 `send` 返回
+//!   `Overloaded`；转发副本在缓冲已满时不保留（仍投递并确认）；超出投递窗口的乱序消息被
+//!   丢弃，等待重传。
+//!
+//! 不变量（草图）：
+//! - 每个节点按序号连续、恰好一次地投递每个发送者的消息。
+//! - 发送者的消息在被所有成员确认之前不会从发送者的缓冲中移除。
+
+use crate::core::errors::DistributedError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// 发送者内的消息序号，从 1 开始
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct SeqNum(pub u64);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RbMessage {
+    /// `from` 为本次传输的节点（发送者本身或转发者），确认回复给它
+    Data {
+        from: String,
+        sender_id: String,
+        seq_num: SeqNum,
+        payload: Vec<u8>,
+    },
+    /// `from` 已按序投递 `sender_id` 的前 `through` 条消息
+    Ack {
+        from: String,
+        sender_id: String,
+        through: SeqNum,
+    },
+}
+
+/// 单节点的可靠 FIFO 广播状态
+#[derive(Debug, Clone)]
+pub struct ReliableBroadcast {
+    node_id: String,
+    peers: BTreeSet<String>,
+    next_seq: u64,
+    max_buffer_size: usize,
+    /// 各发送者已按序投递到的序号
+    delivered: HashMap<String, u64>,
+    /// 各发送者乱序到达、等待缺口补齐的消息
+    pending: HashMap<String, BTreeMap<u64, Vec<u8>>>,
+    /// 持有的消息副本（自己发出的与转发的），直到全部成员确认
+    copies: BTreeMap<(String, u64), Vec<u8>>,
+    /// 本节点发出且未被全部成员确认的消息数
+    own_unacked: usize,
+    /// 各成员对各发送者的累积确认
+    acked: HashMap<String, HashMap<String, u64>>,
+    outbox: Vec<(String, RbMessage)>,
+    retransmissions: u64,
+}
+
+impl ReliableBroadcast {
+    /// `members` 为全部成员，可以包含本节点
+    pub fn new(node_id: impl Into<String>, members: impl IntoIterator<Item = String>) -> Self {
+        let node_id = node_id.into();
+        let peers = members.into_iter().filter(|m| *m != node_id).collect();
+        Self {
+            node_id,
+            peers,
+            next_seq: 1,
+            max_buffer_size: 1024,
+            delivered: HashMap::new(),
+            pending: HashMap::new(),
+            copies: BTreeMap::new(),
+            own_unacked: 0,
+            acked: HashMap::new(),
+            outbox: Vec::new(),
+            retransmissions: 0,
+        }
+    }
+
+    /// 缓冲消息数上限（见模块文档）
+    pub fn with_max_buffer_size(mut self, max_buffer_size: usize) -> Self {
+        self.max_buffer_size = max_buffer_size.max(1);
+        self
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// 广播负载，返回分配的序号；未确认的消息达到缓冲上限时返回 `Overloaded`。
+    /// 发送者不经 `handle_message` 投递自己的消息
+    pub fn send(&mut self, payload: Vec<u8>) -> Result<SeqNum, DistributedError> {
+        if self.own_unacked >= self.max_buffer_size {
+            return Err(DistributedError::Overloaded(format!(
+                "{} unacknowledged broadcasts buffered",
+                self.own_unacked
+            )));
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.delivered.insert(self.node_id.clone(), seq);
+        let origin = self.node_id.clone();
+        self.queue_data(&origin, seq, &payload, None);
+        if !self.peers.is_empty() {
+            self.copies.insert((origin, seq), payload);
+            self.own_unacked += 1;
+        }
+        Ok(SeqNum(seq))
+    }
+
+    /// 处理对端消息，返回按 FIFO 序新投递的 `(发送者, 负载)`
+    pub fn handle_message(&mut self, msg: RbMessage) -> Vec<(String, Vec<u8>)> {
+        match msg {
+            RbMessage::Data {
+                from,
+                sender_id,
+                seq_num,
+                payload,
+            } => self.on_data(from, sender_id, seq_num.0, payload),
+            RbMessage::Ack {
+                from,
+                sender_id,
+                through,
+            } => {
+                let acked = self.acked.entry(from).or_default();
+                let entry = acked.entry(sender_id.clone()).or_default();
+                *entry = (*entry).max(through.0);
+                self.collect_acked(&sender_id);
+                Vec::new()
+            }
+        }
+    }
+
+    /// 对持有的每个未确认副本，向尚未确认的成员重传
+    pub fn tick(&mut self) {
+        let unacked: Vec<((String, u64), Vec<u8>)> = self
+            .copies
+            .iter()
+            .map(|(key, payload)| (key.clone(), payload.clone()))
+            .collect();
+        for ((origin, seq), payload) in unacked {
+            let before = self.outbox.len();
+            self.queue_data(&origin, seq, &payload, None);
+            self.retransmissions += (self.outbox.len() - before) as u64;
+        }
+    }
+
+    /// 取走待发送的 `(目标, 消息)`
+    pub fn take_outgoing(&mut self) -> Vec<(String, RbMessage)> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    /// 已按序投递的 `sender` 的最大序号
+    pub fn delivered_through(&self, sender: &str) -> SeqNum {
+        SeqNum(self.delivered.get(sender).copied().unwrap_or(0))
+    }
+
+    /// 持有的消息副本数
+    pub fn buffered(&self) -> usize {
+        self.copies.len()
+    }
+
+    /// 乱序到达、等待缺口补齐的消息数
+    pub fn pending_len(&self) -> usize {
+        self.pending.values().map(BTreeMap::len).sum()
+    }
+
+    /// 累计重传的消息数
+    pub fn retransmissions(&self) -> u64 {
+        self.retransmissions
+    }
+
+    fn on_data(
+        &mut self,
+        from: String,
+        origin: String,
+        seq: u64,
+        payload: Vec<u8>,
+    ) -> Vec<(String, Vec<u8>)> {
+        if origin == self.node_id {
+            self.ack(&from, &origin);
+            return Vec::new();
+        }
+        let delivered = self.delivered.get(&origin).copied().unwrap_or(0);
+        let window = self.max_buffer_size as u64;
+        let fresh = seq > delivered
+            && seq <= delivered + window
+            && !self
+                .pending
+                .get(&origin)
+                .is_some_and(|p| p.contains_key(&seq));
+        if fresh {
+            // 首次收到：保留转发副本并转发给其他成员，发送者崩溃后仍能传播
+            if self.copies.len() < self.max_buffer_size {
+                self.copies.insert((origin.clone(), seq), payload.clone());
+                self.queue_data(&origin, seq, &payload, Some(&from));
+            }
+            self.pending
+                .entry(origin.clone())
+                .or_default()
+                .insert(seq, payload);
+        }
+        let out = self.drain_pending(&origin);
+        self.ack(&from, &origin);
+        self.collect_acked(&origin);
+        out
+    }
+
+    /// 按序取出 `origin` 已补齐的消息
+    fn drain_pending(&mut self, origin: &str) -> Vec<(String, Vec<u8>)> {
+        let mut out = Vec::new();
+        let Some(pending) = self.pending.get_mut(origin) else {
+            return out;
+        };
+        let delivered = self.delivered.entry(origin.to_string()).or_default();
+        while let Some(payload) = pending.remove(&(*delivered + 1)) {
+            *delivered += 1;
+            out.push((origin.to_string(), payload));
+        }
+        if pending.is_empty() {
+            self.pending.remove(origin);
+        }
+        out
+    }
+
+    fn ack(&mut self, to: &str, origin: &str) {
+        let through = self.delivered_through(origin);
+        self.outbox.push((
+            to.to_string(),
+            RbMessage::Ack {
+                from: self.node_id.clone(),
+                sender_id: origin.to_string(),
+                through,
+            },
+        ));
+    }
+
+    /// 向尚未确认 `(origin, seq)` 的成员发送数据消息；`skip` 为刚发来该消息的节点
+    fn queue_data(&mut self, origin: &str, seq: u64, payload: &[u8], skip: Option<&str>) {
+        for peer in &self.peers {
+            if peer == origin || Some(peer.as_str()) == skip || self.has_acked(peer, origin, seq) {
+                continue;
+            }
+            self.outbox.push((
+                peer.clone(),
+                RbMessage::Data {
+                    from: self.node_id.clone(),
+                    sender_id: origin.to_string(),
+                    seq_num: SeqNum(seq),
+                    payload: payload.to_vec(),
+                },
+            ));
+        }
+    }
+
+    fn has_acked(&self, peer: &str, origin: &str, seq: u64) -> bool {
+        self.acked
+            .get(peer)
+            .and_then(|acked| acked.get(origin))
+            .is_some_and(|through| *through >= seq)
+    }
+
+    /// 回收 `origin` 已被全部成员确认的副本
+    fn collect_acked(&mut self, origin: &str) {
+        let done: Vec<u64> = self
+            .copies
+            .range((origin.to_string(), 0)..=(origin.to_string(), u64::MAX))
+            .map(|((_, seq), _)| *seq)
+            .filter(|seq| {
+                self.peers
+                    .iter()
+                    .all(|peer| peer == origin || self.has_acked(peer, origin, *seq))
+            })
+            .collect();
+        for seq in done {
+            self.copies.remove(&(origin.to_string(), seq));
+            if origin == self.node_id {
+                self.own_unacked -= 1;
+            }
+        }
+    }
+}
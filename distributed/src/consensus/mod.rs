@@ -29,7 +29,46 @@
 pub mod raft;
 pub mod paxos;
 pub mod byzantine;
+pub mod causal;
+pub mod fifo_broadcast;
+pub mod snapshot_transfer;
+#[cfg(feature = "runtime-tokio")]
+pub mod total_order;
+#[cfg(feature = "transport-grpc")]
+pub mod raft_grpc;
 
 pub use raft::*;
 pub use paxos::*;
 pub use byzantine::*;
+pub use causal::{CausalBroadcast, CausalMessage};
+pub use fifo_broadcast::{RbMessage, ReliableBroadcast, SeqNum};
+pub use snapshot_transfer::{
+    ChunkOutcome, SnapshotChunk, SnapshotReceiver, SnapshotSender, install_snapshot_chunked,
+};
+#[cfg(feature = "runtime-tokio")]
+pub use total_order::{RaftTotalOrderBroadcast, SequenceNumber, TotalOrderBroadcast};
+
+use crate::core::errors::DistributedError;
+use serde::{Deserialize, Serialize};
+
+/// 各共识协议共用的节点角色；Raft 的 `RaftState` 即此类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusRole {
+    Leader,
+    Follower,
+    Candidate,
+}
+
+/// 一次提议在协议内的标识：Raft 为日志索引，Paxos 为提案轮次，PBFT 为序列号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProposalId(pub u64);
+
+/// 跨协议的共识节点抽象，便于在更高层统一编排 Raft、Paxos 与 PBFT
+pub trait ConsensusNode {
+    fn role(&self) -> ConsensusRole;
+    /// Raft 任期、Paxos 提案轮次或 PBFT 视图号
+    fn current_term(&self) -> u64;
+    /// 提交命令；非领导者（或协议不接受新提议时）返回错误
+    fn propose(&mut self, command: Vec<u8>) -> Result<ProposalId, DistributedError>;
+}
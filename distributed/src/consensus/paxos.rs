@@ -10,13 +10,455 @@
 //!
 //! 参考：见 `consensus::mod` 顶部列表（Lamport 1998；Chandra et al. 2007）。
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ConsensusRole {
-    Leader,
-    Follower,
-    Candidate,
-}
+pub use super::ConsensusRole;
 
 pub trait ConsensusApi {
     fn role(&self) -> ConsensusRole;
 }
+
+// ---------------- Flexible Paxos（Howard et al., 2016） ----------------
+//
+// 经典 Paxos 要求两阶段都使用多数派；Flexible Paxos 只要求任意 Phase 1 法定人数 Q1
+// 与任意 Phase 2 法定人数 Q2 相交（Q1 ∩ Q2 ≠ ∅）。例如 N=5 时 |Q1|=4、|Q2|=2 合法，
+// 以更昂贵（但少见）的领导者选举换取更便宜的稳态提交。
+//
+// 决斗的提议者：两个提议者同时 Prepare 时可能轮流以更高编号压过对方而永远无法提交。
+// 接受者拒绝时回送 `Nack`（携带已承诺的更高编号）；提议者据此按 `DuelingPrevention`
+// 指数退避后再重试 Phase 1，退避期间对方得以完成 Phase 2。得知值已选定后不再重试。
+
+use super::{ConsensusNode, ProposalId};
+use crate::core::errors::DistributedError;
+use crate::core::membership::ClusterNodeId;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+/// 两阶段法定人数可分别配置的 Paxos 配置
+///
+/// 法定人数可以显式列出（任意节点集合），也可以按阈值给出；阈值配置不枚举组合，
+/// 校验只比较个数，计数只统计属于成员集合的应答者。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlexiblePaxosConfig {
+    /// 显式列出的法定人数：`responders` 包含其中任一集合即构成法定人数
+    Explicit {
+        phase1_quorum: Vec<Vec<ClusterNodeId>>,
+        phase2_quorum: Vec<Vec<ClusterNodeId>>,
+    },
+    /// `members` 中任意 `q1`（`q2`）个构成 Phase 1（Phase 2）法定人数；
+    /// 不在 `members` 中的应答者不计数
+    Threshold {
+        members: HashSet<ClusterNodeId>,
+        q1: usize,
+        q2: usize,
+    },
+}
+
+impl FlexiblePaxosConfig {
+    pub fn new(
+        phase1_quorum: Vec<Vec<ClusterNodeId>>,
+        phase2_quorum: Vec<Vec<ClusterNodeId>>,
+    ) -> Self {
+        Self::Explicit {
+            phase1_quorum,
+            phase2_quorum,
+        }
+    }
+
+    /// 以阈值方式生成法定人数：`nodes` 中任意 `q1`（`q2`）个节点构成 Phase 1（Phase 2）法定人数
+    pub fn threshold(nodes: &[ClusterNodeId], q1: usize, q2: usize) -> Self {
+        Self::Threshold {
+            members: nodes.iter().cloned().collect(),
+            q1,
+            q2,
+        }
+    }
+
+    /// 经典多数派配置
+    pub fn majority(nodes: &[ClusterNodeId]) -> Self {
+        let q = nodes.len() / 2 + 1;
+        Self::threshold(nodes, q, q)
+    }
+
+    /// 校验每个 Phase 1 法定人数都与每个 Phase 2 法定人数相交；阈值配置即 `q1 + q2 > n`
+    pub fn check_valid(&self) -> Result<(), DistributedError> {
+        match self {
+            Self::Explicit {
+                phase1_quorum,
+                phase2_quorum,
+            } => {
+                if phase1_quorum.is_empty() || phase2_quorum.is_empty() {
+                    return Err(DistributedError::Configuration(
+                        "phase1/phase2 quorum must not be empty".to_string(),
+                    ));
+                }
+                for q1 in phase1_quorum {
+                    for q2 in phase2_quorum {
+                        if !q1.iter().any(|n| q2.contains(n)) {
+                            return Err(DistributedError::Configuration(format!(
+                                "phase1 quorum {q1:?} does not intersect phase2 quorum {q2:?}"
+                            )));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Self::Threshold { members, q1, q2 } => {
+                let (n, q1, q2) = (members.len(), *q1, *q2);
+                if !(1..=n).contains(&q1) || !(1..=n).contains(&q2) {
+                    return Err(DistributedError::Configuration(format!(
+                        "threshold quorums q1={q1}, q2={q2} must lie in 1..={n}"
+                    )));
+                }
+                if q1 + q2 <= n {
+                    return Err(DistributedError::Configuration(format!(
+                        "threshold quorums q1={q1}, q2={q2} do not intersect on {n} nodes"
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// `responders` 是否包含某个 Phase 1 法定人数
+    pub fn is_phase1_quorum(&self, responders: &HashSet<ClusterNodeId>) -> bool {
+        match self {
+            Self::Explicit { phase1_quorum, .. } => contains_quorum(phase1_quorum, responders),
+            Self::Threshold { members, q1, .. } => count_members(members, responders) >= *q1,
+        }
+    }
+
+    /// `responders` 是否包含某个 Phase 2 法定人数
+    pub fn is_phase2_quorum(&self, responders: &HashSet<ClusterNodeId>) -> bool {
+        match self {
+            Self::Explicit { phase2_quorum, .. } => contains_quorum(phase2_quorum, responders),
+            Self::Threshold { members, q2, .. } => count_members(members, responders) >= *q2,
+        }
+    }
+}
+
+/// `responders ∩ members` 的大小
+fn count_members(members: &HashSet<ClusterNodeId>, responders: &HashSet<ClusterNodeId>) -> usize {
+    responders.iter().filter(|n| members.contains(n)).count()
+}
+
+fn contains_quorum(quorums: &[Vec<ClusterNodeId>], responders: &HashSet<ClusterNodeId>) -> bool {
+    quorums
+        .iter()
+        .any(|q| q.iter().all(|n| responders.contains(n)))
+}
+
+/// 提案编号：先比较轮次，再以提议者 ID 打破平局
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ballot {
+    pub round: u64,
+    pub proposer: ClusterNodeId,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrepareReq {
+    pub ballot: Ballot,
+}
+
+#[derive(Debug, Clone)]
+pub struct Promise<V> {
+    pub from: ClusterNodeId,
+    pub ballot: Ballot,
+    /// 该接受者此前已接受的最高编号提案
+    pub accepted: Option<(Ballot, V)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AcceptReq<V> {
+    pub ballot: Ballot,
+    pub value: V,
+}
+
+#[derive(Debug, Clone)]
+pub struct Accepted {
+    pub from: ClusterNodeId,
+    pub ballot: Ballot,
+}
+
+/// 拒绝：提议者第 `round` 轮的编号低于接受者已承诺的 `promised`
+#[derive(Debug, Clone)]
+pub struct Nack {
+    pub round: u64,
+    pub promised: Ballot,
+}
+
+/// 单值（single-decree）Paxos 接受者
+#[derive(Debug, Clone)]
+pub struct PaxosAcceptor<V> {
+    pub id: ClusterNodeId,
+    promised: Option<Ballot>,
+    accepted: Option<(Ballot, V)>,
+}
+
+impl<V: Clone> PaxosAcceptor<V> {
+    pub fn new(id: impl Into<ClusterNodeId>) -> Self {
+        Self {
+            id: id.into(),
+            promised: None,
+            accepted: None,
+        }
+    }
+
+    /// Phase 1b：仅当编号不低于已承诺编号时给出承诺
+    pub fn handle_prepare(&mut self, req: &PrepareReq) -> Option<Promise<V>> {
+        self.respond_prepare(req).ok()
+    }
+
+    /// 同 `handle_prepare`，拒绝时回送 `Nack`
+    pub fn respond_prepare(&mut self, req: &PrepareReq) -> Result<Promise<V>, Nack> {
+        self.check_promised(&req.ballot)?;
+        self.promised = Some(req.ballot.clone());
+        Ok(Promise {
+            from: self.id.clone(),
+            ballot: req.ballot.clone(),
+            accepted: self.accepted.clone(),
+        })
+    }
+
+    /// Phase 2b：仅当未对更高编号做出承诺时接受
+    pub fn handle_accept(&mut self, req: &AcceptReq<V>) -> Option<Accepted> {
+        self.respond_accept(req).ok()
+    }
+
+    /// 同 `handle_accept`，拒绝时回送 `Nack`
+    pub fn respond_accept(&mut self, req: &AcceptReq<V>) -> Result<Accepted, Nack> {
+        self.check_promised(&req.ballot)?;
+        self.promised = Some(req.ballot.clone());
+        self.accepted = Some((req.ballot.clone(), req.value.clone()));
+        Ok(Accepted {
+            from: self.id.clone(),
+            ballot: req.ballot.clone(),
+        })
+    }
+
+    fn check_promised(&self, ballot: &Ballot) -> Result<(), Nack> {
+        match &self.promised {
+            Some(promised) if promised > ballot => Err(Nack {
+                round: ballot.round,
+                promised: promised.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn accepted(&self) -> Option<&(Ballot, V)> {
+        self.accepted.as_ref()
+    }
+}
+
+/// 被拒绝后重试 Phase 1 前的指数退避：第 n 次连续失败等待
+/// `min(base_backoff_ms × 2^(n-1), max_backoff_ms)`；`jitter` 时在 [一半, 全部] 间取值，
+/// 使同时失败的提议者错开重试
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuelingPrevention {
+    pub base_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub jitter: bool,
+}
+
+impl Default for DuelingPrevention {
+    fn default() -> Self {
+        Self {
+            base_backoff_ms: 10,
+            max_backoff_ms: 1_000,
+            jitter: true,
+        }
+    }
+}
+
+impl DuelingPrevention {
+    /// 第 `failed_attempts` 次连续失败后的退避；抖动由 `seed` 确定
+    pub fn backoff(&self, failed_attempts: u32, seed: u64) -> Duration {
+        let exp = failed_attempts.saturating_sub(1).min(63);
+        let full = self
+            .base_backoff_ms
+            .saturating_mul(1u64 << exp)
+            .min(self.max_backoff_ms);
+        let ms = if self.jitter {
+            let half = full / 2;
+            half + seed % (full - half + 1)
+        } else {
+            full
+        };
+        Duration::from_millis(ms)
+    }
+}
+
+/// 单值 Paxos 提议者；两阶段何时“足够”由 `FlexiblePaxosConfig` 决定
+#[derive(Debug, Clone)]
+pub struct PaxosProposer<V> {
+    pub id: ClusterNodeId,
+    config: FlexiblePaxosConfig,
+    ballot: Ballot,
+    proposal: Option<V>,
+    promises: HashMap<ClusterNodeId, Option<(Ballot, V)>>,
+    accepts: HashSet<ClusterNodeId>,
+    accept_sent: bool,
+    chosen: Option<V>,
+    dueling: DuelingPrevention,
+    /// 自上次 Phase 2 成功以来被拒绝的轮数
+    failed_attempts: u32,
+    /// 当前编号是否已被拒绝（同一轮的多个 `Nack` 只计一次）
+    nacked: bool,
+}
+
+impl<V: Clone> PaxosProposer<V> {
+    /// 创建提议者；非法（不相交）的法定人数配置会被拒绝
+    pub fn new(
+        id: impl Into<ClusterNodeId>,
+        config: FlexiblePaxosConfig,
+    ) -> Result<Self, DistributedError> {
+        config.check_valid()?;
+        let id = id.into();
+        Ok(Self {
+            ballot: Ballot {
+                round: 0,
+                proposer: id.clone(),
+            },
+            id,
+            config,
+            proposal: None,
+            promises: HashMap::new(),
+            accepts: HashSet::new(),
+            accept_sent: false,
+            chosen: None,
+            dueling: DuelingPrevention::default(),
+            failed_attempts: 0,
+            nacked: false,
+        })
+    }
+
+    pub fn with_dueling_prevention(mut self, dueling: DuelingPrevention) -> Self {
+        self.dueling = dueling;
+        self
+    }
+
+    /// 自上次 Phase 2 成功以来连续被拒绝的轮数
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    pub fn ballot(&self) -> &Ballot {
+        &self.ballot
+    }
+
+    pub fn chosen(&self) -> Option<&V> {
+        self.chosen.as_ref()
+    }
+
+    /// Phase 1a：以更高编号开始新一轮
+    pub fn prepare(&mut self, value: V) -> PrepareReq {
+        self.ballot = Ballot {
+            round: self.ballot.round + 1,
+            proposer: self.id.clone(),
+        };
+        self.proposal = Some(value);
+        self.promises.clear();
+        self.accepts.clear();
+        self.accept_sent = false;
+        self.nacked = false;
+        PrepareReq {
+            ballot: self.ballot.clone(),
+        }
+    }
+
+    /// 收集承诺；首次凑齐 Phase 1 法定人数时返回 Accept 请求，
+    /// 其值取承诺中已接受的最高编号值（若有），否则为自身提案
+    pub fn handle_promise(&mut self, promise: Promise<V>) -> Option<AcceptReq<V>> {
+        if promise.ballot != self.ballot || self.accept_sent {
+            return None;
+        }
+        self.promises.insert(promise.from, promise.accepted);
+        let responders: HashSet<ClusterNodeId> = self.promises.keys().cloned().collect();
+        if !self.config.is_phase1_quorum(&responders) {
+            return None;
+        }
+        let inherited = self
+            .promises
+            .values()
+            .flatten()
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, v)| v.clone());
+        let value = inherited.or_else(|| self.proposal.clone())?;
+        self.proposal = Some(value.clone());
+        self.accept_sent = true;
+        Some(AcceptReq {
+            ballot: self.ballot.clone(),
+            value,
+        })
+    }
+
+    /// 收集接受应答；凑齐 Phase 2 法定人数后返回被选定的值
+    pub fn handle_accepted(&mut self, accepted: Accepted) -> Option<V> {
+        if accepted.ballot != self.ballot || !self.accept_sent {
+            return None;
+        }
+        self.accepts.insert(accepted.from);
+        if self.chosen.is_none() && self.config.is_phase2_quorum(&self.accepts) {
+            self.chosen = self.proposal.clone();
+            self.failed_attempts = 0;
+        }
+        self.chosen.clone()
+    }
+
+    /// 处理当前编号收到的拒绝：放弃本轮，下一轮编号越过对方，返回重试 Phase 1 前应等待的
+    /// 退避。过期编号的拒绝、本轮的重复拒绝或值已选定时返回 `None`
+    pub fn handle_nack(&mut self, nack: Nack) -> Option<Duration> {
+        if nack.round != self.ballot.round || self.nacked || self.chosen.is_some() {
+            return None;
+        }
+        self.nacked = true;
+        self.accept_sent = false;
+        self.ballot.round = self.ballot.round.max(nack.promised.round);
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+        let mut hasher = DefaultHasher::new();
+        (&self.id, self.failed_attempts).hash(&mut hasher);
+        Some(self.dueling.backoff(self.failed_attempts, hasher.finish()))
+    }
+
+    /// 退避结束后以原提案重试 Phase 1；值已选定时不再重试
+    pub fn retry(&mut self) -> Option<PrepareReq> {
+        if self.chosen.is_some() {
+            return None;
+        }
+        let value = self.proposal.clone()?;
+        Some(self.prepare(value))
+    }
+
+    /// 得知值已被选定（如来自学习者或另一提议者），此后不再重试
+    pub fn learn(&mut self, value: V) {
+        self.chosen = Some(value);
+    }
+}
+
+/// 单值 Paxos：凑齐 Phase 1 法定人数即相当于领导者，Phase 1 进行中为候选人
+impl ConsensusNode for PaxosProposer<Vec<u8>> {
+    fn role(&self) -> ConsensusRole {
+        if self.accept_sent {
+            ConsensusRole::Leader
+        } else if self.proposal.is_some() {
+            ConsensusRole::Candidate
+        } else {
+            ConsensusRole::Follower
+        }
+    }
+
+    fn current_term(&self) -> u64 {
+        self.ballot.round
+    }
+
+    /// 开始新一轮 Phase 1；调用方随后把 `PrepareReq` 发给接受者
+    fn propose(&mut self, command: Vec<u8>) -> Result<ProposalId, DistributedError> {
+        if self.chosen.is_some() {
+            return Err(DistributedError::InvalidState(
+                "value already chosen".to_string(),
+            ));
+        }
+        Ok(ProposalId(self.prepare(command).ballot.round))
+    }
+}
@@ -13,24 +13,43 @@
 //!
 //! 形式化论证线索：
 //! - 前缀匹配引出提交唯一性：若某条目被多数派接受且作为领导者提交，则所有后续合法领导者在该索引处必须拥有相同 term，从而避免分叉提交。
-//! - 领导者更替安全：`RequestVote` 需候选人日志“不落后”（简化实现未完全覆盖，应在生产版加入“最新日志比较”）。
+//! - 领导者更替安全：`RequestVote` 需候选人日志“不落后”（比较 (last_log_term, last_log_index)），
+//!   否则日志更短的新节点当选后会截断已提交条目。
+//!
+//! 领导者路径：`start_election` / `become_leader` / `propose` / `append_entries_for` /
+//! `handle_append_entries_resp` 由外部驱动（如 `raft_grpc`），`MinimalRaft` 本身不含定时器与网络。
+//!
+//! 快照安装：`InstallSnapshot` 按块到达，经 `SnapshotReceiver` 校验并拼接，应答中的
+//! `next_offset` 告知领导者续传位置；整体哈希校验通过后才截断日志（见 `snapshot_transfer`）。
+//!
+//! 学习者（learner）：`ConfChange::AddLearner` 加入的对等节点照常接收 AppendEntries，但不计入
+//! 提交与选举仲裁；追上日志后经 `ConfChange::PromoteLearner` 提升为投票者，此后计入多数派。
+//! 本地以 `set_learner(true)` 标记为学习者的节点不投票。
+//!
+//! 异步应用（`runtime-tokio`）：`start_apply_queue` 之后已提交条目只入队
+//! （有界 `mpsc`），由独立任务驱动 `StateMachine` 并推进 `last_applied`，慢状态机不再
+//! 阻塞提交路径。队列满时条目留在日志中，下次提交或 `pump_apply_queue` 时补入队。
 //!
 //! 参考文献：参见模块 `consensus::mod` 顶部的参考列表（Raft 论文与实现经验文献）。
 
+use crate::consensus::snapshot_transfer::{ChunkOutcome, SnapshotChunk, SnapshotReceiver};
+use crate::core::ReplicaRole;
 use crate::core::errors::DistributedError;
-use std::collections::HashMap;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum RaftState {
-    Follower,
-    Candidate,
-    Leader,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+#[cfg(feature = "runtime-tokio")]
+use std::sync::Arc;
+#[cfg(feature = "runtime-tokio")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Raft 节点角色，与其他协议共用 `ConsensusRole`
+pub type RaftState = super::ConsensusRole;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Term(pub u64);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LogIndex(pub u64);
 
 #[derive(Debug, Clone)]
@@ -40,7 +59,7 @@ pub struct Snapshot {
     pub data: Vec<u8>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallSnapshotReq {
     pub term: Term,
     pub leader_id: String,
@@ -49,14 +68,23 @@ pub struct InstallSnapshotReq {
     pub offset: u64,
     pub data: Vec<u8>,
     pub done: bool,
+    /// 本块的校验和（`snapshot_transfer::chunk_checksum`）；`None` 时不校验
+    #[serde(default)]
+    pub checksum: Option<u64>,
+    /// 整份快照的哈希，只在最后一块上携带
+    #[serde(default)]
+    pub snapshot_hash: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallSnapshotResp {
     pub term: Term,
+    /// 跟随者已接收的字节数，即领导者下一块的偏移；安装完成时等于快照长度
+    #[serde(default)]
+    pub next_offset: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppendEntriesReq<E> {
     pub term: Term,
     pub leader_id: String,
@@ -66,13 +94,13 @@ pub struct AppendEntriesReq<E> {
     pub leader_commit: LogIndex,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppendEntriesResp {
     pub term: Term,
     pub success: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestVoteReq {
     pub term: Term,
     pub candidate_id: String,
@@ -80,12 +108,23 @@ pub struct RequestVoteReq {
     pub last_log_term: Term,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestVoteResp {
     pub term: Term,
     pub vote_granted: bool,
 }
 
+/// 单节点成员变更，每次只改变一个节点的角色
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfChange {
+    /// 加入只接收复制、不计入仲裁的学习者
+    AddLearner(String),
+    /// 把学习者提升为投票者
+    PromoteLearner(String),
+    /// 移除节点（投票者或学习者）
+    RemoveNode(String),
+}
+
 pub trait RaftNode<E> {
     fn state(&self) -> RaftState;
     fn current_term(&self) -> Term;
@@ -105,21 +144,85 @@ pub trait RaftNode<E> {
     fn should_compact(&self, threshold: LogIndex) -> bool;
 }
 
+/// 节点间 RPC 传输；`AppendEntries` 的条目携带其创建任期，跟随者据此保存日志任期
+pub trait RaftTransport<E> {
+    fn append_entries(
+        &self,
+        target: &str,
+        req: AppendEntriesReq<(Term, E)>,
+    ) -> impl Future<Output = Result<AppendEntriesResp, DistributedError>> + Send;
+    fn request_vote(
+        &self,
+        target: &str,
+        req: RequestVoteReq,
+    ) -> impl Future<Output = Result<RequestVoteResp, DistributedError>> + Send;
+    fn install_snapshot(
+        &self,
+        target: &str,
+        req: InstallSnapshotReq,
+    ) -> impl Future<Output = Result<InstallSnapshotResp, DistributedError>> + Send;
+}
+
 #[allow(dead_code)]
+/// 已提交、交给状态机应用的日志条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry<E> {
+    pub index: LogIndex,
+    pub term: Term,
+    pub command: E,
+}
+
+/// 由应用队列任务驱动的异步状态机
+#[cfg(feature = "runtime-tokio")]
+pub trait StateMachine<E>: Send + 'static {
+    fn apply(&mut self, entry: LogEntry<E>) -> impl Future<Output = ()> + Send;
+}
+
+#[cfg(feature = "runtime-tokio")]
+struct ApplyQueue<E> {
+    sender: tokio::sync::mpsc::Sender<LogEntry<E>>,
+    /// 已入队的条目数（即已入队的最大索引）
+    enqueued: usize,
+    /// 应用任务已应用的最大索引
+    applied: Arc<AtomicUsize>,
+}
+
+/// 节点的 Raft 运行指标，供运维检查
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RaftMetrics {
+    pub role: RaftState,
+    pub term: Term,
+    pub commit_index: LogIndex,
+    pub last_applied: LogIndex,
+    pub last_log_index: LogIndex,
+    /// 领导者视角下各跟随者已匹配的最大索引；非领导者为空
+    pub match_index: BTreeMap<String, LogIndex>,
+}
+
 pub struct MinimalRaft<E> {
     state: RaftState,
     term: Term,
     log: Vec<(Term, E)>,
+    /// 已被快照覆盖并截断的最后索引；`log[i]` 的索引为 `log_start + i + 1`
+    log_start: usize,
+    log_start_term: Term,
     commit_index: usize,
     last_applied: usize,
     apply: Option<Box<dyn FnMut(&E) + Send>>,
     // 快照相关字段
     snapshot: Option<Snapshot>,
+    snapshot_receiver: SnapshotReceiver,
     // 性能优化字段
     next_index: HashMap<String, usize>,
     match_index: HashMap<String, usize>,
+    /// 不计入仲裁的对等节点
+    learners: HashSet<String>,
+    /// 本节点是否为学习者（不投票）
+    is_learner: bool,
     // 批量操作支持
     batch_size: usize,
+    #[cfg(feature = "runtime-tokio")]
+    apply_queue: Option<ApplyQueue<E>>,
 }
 
 impl<E> MinimalRaft<E> {
@@ -128,13 +231,20 @@ impl<E> MinimalRaft<E> {
             state: RaftState::Follower,
             term: Term(0),
             log: Vec::new(),
+            log_start: 0,
+            log_start_term: Term(0),
             commit_index: 0,
             last_applied: 0,
             apply: None,
             snapshot: None,
+            snapshot_receiver: SnapshotReceiver::new(),
             next_index: HashMap::new(),
             match_index: HashMap::new(),
+            learners: HashSet::new(),
+            is_learner: false,
             batch_size: 100, // 默认批量大小
+            #[cfg(feature = "runtime-tokio")]
+            apply_queue: None,
         }
     }
 
@@ -143,28 +253,64 @@ impl<E> MinimalRaft<E> {
         self
     }
 
+    /// 分块接收中的快照落盘到 `dir`，重启后可从已接收的偏移续传
+    pub fn with_snapshot_spool(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.snapshot_receiver = SnapshotReceiver::new().with_spool_dir(dir);
+        self
+    }
+
+    /// 最近安装或创建的快照
+    pub fn snapshot(&self) -> Option<&Snapshot> {
+        self.snapshot.as_ref()
+    }
+
+    pub fn snapshot_receiver(&self) -> &SnapshotReceiver {
+        &self.snapshot_receiver
+    }
+
     pub fn install_snapshot(&mut self, snapshot: Snapshot) {
-        // 安装快照，截断日志
+        // 安装快照，截断日志：本地日志在快照边界处任期一致时保留其后的条目，否则整体丢弃
         let last_included_index = snapshot.last_included_index.0 as usize;
-        if last_included_index > 0 && last_included_index <= self.log.len() {
-            self.log.drain(0..last_included_index);
+        // 不晚于本地提交点的快照已被日志覆盖：忽略，避免回退提交/应用进度或丢掉已提交条目
+        if last_included_index <= self.commit_index {
+            return;
+        }
+        if last_included_index > self.log_start {
+            if self.entry(last_included_index).map(|(t, _)| *t) == Some(snapshot.last_included_term)
+            {
+                self.log.drain(..last_included_index - self.log_start);
+            } else {
+                self.log.clear();
+            }
+            self.log_start = last_included_index;
+            self.log_start_term = snapshot.last_included_term;
+        }
+        self.commit_index = self.commit_index.max(last_included_index);
+        self.last_applied = self.last_applied.max(last_included_index);
+        #[cfg(feature = "runtime-tokio")]
+        if let Some(queue) = &mut self.apply_queue {
+            queue.enqueued = queue.enqueued.max(last_included_index);
+            queue
+                .applied
+                .fetch_max(last_included_index, Ordering::AcqRel);
         }
-        self.commit_index = last_included_index;
-        self.last_applied = last_included_index;
         self.snapshot = Some(snapshot);
     }
 
     /// 创建快照
-    pub fn create_snapshot_internal(&self, last_included_index: LogIndex) -> Result<Snapshot, DistributedError> {
-        let last_included_term = if last_included_index.0 > 0 {
-            let idx = (last_included_index.0 - 1) as usize;
-            if let Some((term, _)) = self.log.get(idx) {
-                *term
-            } else {
-                return Err(DistributedError::InvalidState("Log index out of bounds".to_string()));
-            }
+    pub fn create_snapshot_internal(
+        &self,
+        last_included_index: LogIndex,
+    ) -> Result<Snapshot, DistributedError> {
+        let index = last_included_index.0 as usize;
+        let last_included_term = if index == self.log_start {
+            self.log_start_term
+        } else if let Some((term, _)) = self.entry(index) {
+            *term
         } else {
-            Term(0)
+            return Err(DistributedError::InvalidState(
+                "Log index out of bounds".to_string(),
+            ));
         };
 
         // 简化的快照数据（实际应用中应该序列化状态机状态）
@@ -182,6 +328,60 @@ impl<E> MinimalRaft<E> {
         self.log.len() > threshold.0 as usize
     }
 
+    /// 内存中保留的日志条目数（不含已截断的前缀）
+    pub fn retained_entries(&self) -> usize {
+        self.log.len()
+    }
+
+    /// 已截断前缀的最后索引；之后的条目仍在日志中
+    pub fn log_start(&self) -> LogIndex {
+        LogIndex(self.log_start as u64)
+    }
+
+    /// 丢弃 `index` 之前（不含）的条目，返回丢弃的条数。只能截断已应用的前缀，
+    /// 调用方须先持久化覆盖该前缀的快照
+    pub fn truncate_before(&mut self, index: LogIndex) -> Result<usize, DistributedError> {
+        let through = (index.0 as usize).saturating_sub(1);
+        if through <= self.log_start {
+            return Ok(0);
+        }
+        if through > self.last_applied().0 as usize {
+            return Err(DistributedError::InvalidState(format!(
+                "cannot truncate before {} past last applied {}",
+                index.0,
+                self.last_applied().0
+            )));
+        }
+        let term = self.term_at(through);
+        let dropped = through - self.log_start;
+        self.log.drain(..dropped);
+        self.log_start = through;
+        self.log_start_term = term;
+        Ok(dropped)
+    }
+
+    /// 为已应用的前缀创建快照并截断日志，返回丢弃的条数
+    pub fn compact_log(&mut self) -> Result<usize, DistributedError> {
+        let applied = self.last_applied();
+        if applied.0 as usize <= self.log_start {
+            return Ok(0);
+        }
+        self.snapshot = Some(self.create_snapshot_internal(applied)?);
+        self.truncate_before(LogIndex(applied.0 + 1))
+    }
+
+    /// 索引处的条目（1-based）；已截断或越界时为 `None`
+    fn entry(&self, index: usize) -> Option<&(Term, E)> {
+        index
+            .checked_sub(self.log_start + 1)
+            .and_then(|i| self.log.get(i))
+    }
+
+    /// 最后一条日志的索引
+    fn log_end(&self) -> usize {
+        self.log_start + self.log.len()
+    }
+
     pub fn set_apply(&mut self, f: Box<dyn FnMut(&E) + Send>) {
         self.apply = Some(f);
     }
@@ -203,8 +403,8 @@ impl<E> MinimalRaft<E> {
     /// 内部核心实现：可传入临时回调用于应用 entries
     fn handle_append_entries_core(
         &mut self,
-        req: AppendEntriesReq<E>,
-        mut apply: Option<&mut (dyn FnMut(&E) + Send)>,
+        req: AppendEntriesReq<(Term, E)>,
+        apply: Option<&mut (dyn FnMut(&E) + Send)>,
     ) -> Result<AppendEntriesResp, DistributedError>
     where
         E: Clone,
@@ -220,53 +420,450 @@ impl<E> MinimalRaft<E> {
         }
         self.state = RaftState::Follower;
 
-        // 前置匹配校验：确保 (prev_log_index, prev_log_term) 与本地日志一致
-        let prev_idx = req.prev_log_index.0 as usize;
-        if prev_idx > 0 {
-            let i = prev_idx - 1;
-            if let Some((t, _)) = self.log.get(i) {
-                if t.0 != req.prev_log_term.0 {
-                    return Ok(AppendEntriesResp {
-                        term: self.term,
-                        success: false,
-                    });
-                }
-            } else {
-                return Ok(AppendEntriesResp {
-                    term: self.term,
-                    success: false,
-                });
-            }
+        // 前置匹配校验：确保 (prev_log_index, prev_log_term) 与本地日志一致；
+        // 快照已覆盖的前缀必然一致，跳过请求中落在其内的条目
+        let mut prev_idx = req.prev_log_index.0 as usize;
+        let mut entries = req.entries;
+        if prev_idx < self.log_start {
+            let covered = (self.log_start - prev_idx).min(entries.len());
+            entries.drain(..covered);
+            prev_idx = self.log_start;
+        } else if prev_idx > 0 && self.term_at(prev_idx) != req.prev_log_term
+            || prev_idx > self.log_end()
+        {
+            return Ok(AppendEntriesResp {
+                term: self.term,
+                success: false,
+            });
         }
 
-        // 从 prev_log_index 截断并附加新的条目，维持前缀一致性
-        let mut insert_at = prev_idx;
-        if insert_at > self.log.len() {
-            insert_at = self.log.len();
-        }
-        self.log.truncate(insert_at);
-        for e in req.entries.into_iter() {
-            self.log.push((self.term, e));
+        // 跳过本地已有且任期一致的条目，只从第一个冲突处截断（Raft §5.3）：
+        // 迟到或重复的 AppendEntries 不会删掉其后已追加的条目
+        let last_new = prev_idx + entries.len();
+        let mut entries = entries.into_iter();
+        let mut index = prev_idx;
+        let mut first_new = None;
+        for entry in entries.by_ref() {
+            index += 1;
+            match self.entry(index) {
+                Some((term, _)) if *term == entry.0 => continue,
+                Some(_) => {
+                    self.log.truncate(index - 1 - self.log_start);
+                    first_new = Some(entry);
+                }
+                None => first_new = Some(entry),
+            }
+            break;
         }
+        self.log.extend(first_new.into_iter().chain(entries));
 
-        // 提交并应用：确保 last_applied 按序推进至 commit_index
+        // 提交并应用：commit_index 单调不减，且不越过本次请求确认的最后一条新条目
         let leader_commit = req.leader_commit.0 as usize;
-        let log_len = self.log.len();
-        self.commit_index = std::cmp::min(leader_commit, log_len);
+        self.commit_index = self
+            .commit_index
+            .max(std::cmp::min(leader_commit, last_new));
+        self.apply_committed(apply);
+        #[cfg(feature = "runtime-tokio")]
+        self.pump_apply_queue();
+
+        Ok(AppendEntriesResp {
+            term: self.term,
+            success: true,
+        })
+    }
+
+    fn apply_committed(&mut self, mut apply: Option<&mut (dyn FnMut(&E) + Send)>) {
+        // 异步应用模式下由应用任务推进 last_applied
+        #[cfg(feature = "runtime-tokio")]
+        if self.apply_queue.is_some() {
+            return;
+        }
         while self.last_applied < self.commit_index {
-            let idx = self.last_applied; // 0-based
-            if let Some((_, entry)) = self.log.get(idx) {
+            if let Some((_, entry)) = self.entry(self.last_applied + 1) {
                 if let Some(ref mut cb) = apply {
                     (cb)(entry);
                 }
             }
             self.last_applied += 1;
         }
+    }
 
-        Ok(AppendEntriesResp {
+    /// 以 'static 回调应用已提交条目（通过临时 take 避免可变别名）
+    fn apply_with_callback(&mut self) {
+        let mut taken = self.apply.take();
+        self.apply_committed(
+            taken
+                .as_mut()
+                .map(|cb| cb.as_mut() as &mut (dyn FnMut(&E) + Send)),
+        );
+        self.apply = taken;
+    }
+
+    /// 无条目任期信息的请求：条目按请求任期入日志
+    fn stamp(req: AppendEntriesReq<E>) -> AppendEntriesReq<(Term, E)> {
+        let term = req.term;
+        AppendEntriesReq {
+            term: req.term,
+            leader_id: req.leader_id,
+            prev_log_index: req.prev_log_index,
+            prev_log_term: req.prev_log_term,
+            entries: req.entries.into_iter().map(|e| (term, e)).collect(),
+            leader_commit: req.leader_commit,
+        }
+    }
+
+    /// 已应用到状态机的最大索引
+    pub fn last_applied(&self) -> LogIndex {
+        #[cfg(feature = "runtime-tokio")]
+        if let Some(queue) = &self.apply_queue {
+            return LogIndex(queue.applied.load(Ordering::Acquire) as u64);
+        }
+        LogIndex(self.last_applied as u64)
+    }
+
+    /// 已提交但尚未应用的条目数：队列中的条目加上因队列满而尚未入队的条目
+    #[cfg(feature = "runtime-tokio")]
+    pub fn apply_queue_depth(&self) -> usize {
+        match &self.apply_queue {
+            Some(queue) => {
+                let in_channel = queue.sender.max_capacity() - queue.sender.capacity();
+                in_channel + self.commit_index.saturating_sub(queue.enqueued)
+            }
+            None => 0,
+        }
+    }
+
+    pub fn commit_index(&self) -> LogIndex {
+        LogIndex(self.commit_index as u64)
+    }
+
+    pub fn last_log_index(&self) -> LogIndex {
+        LogIndex(self.log_end() as u64)
+    }
+
+    pub fn last_log_term(&self) -> Term {
+        self.term_at(self.log_end())
+    }
+
+    pub fn metrics(&self) -> RaftMetrics {
+        let match_index = match self.state {
+            RaftState::Leader => self
+                .match_index
+                .iter()
+                .map(|(peer, index)| (peer.clone(), LogIndex(*index as u64)))
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+        RaftMetrics {
+            role: self.state,
             term: self.term,
-            success: true,
-        })
+            commit_index: self.commit_index(),
+            last_applied: self.last_applied(),
+            last_log_index: self.last_log_index(),
+            match_index,
+        }
+    }
+
+    /// 索引处条目的任期（1-based，0 或越界为 `Term(0)`；截断边界处为快照的任期）
+    fn term_at(&self, index: usize) -> Term {
+        if index == self.log_start {
+            return self.log_start_term;
+        }
+        self.entry(index).map_or(Term(0), |(t, _)| *t)
+    }
+
+    /// 观察到更高任期时更新任期并退为跟随者；返回是否发生了退位
+    pub fn observe_term(&mut self, term: Term) -> bool {
+        if term.0 > self.term.0 {
+            self.term = term;
+            self.state = RaftState::Follower;
+            return true;
+        }
+        false
+    }
+
+    /// 发起选举：任期 +1 并转为候选人（隐式投票给自己，本任期不再投给他人）
+    pub fn start_election(&mut self, candidate_id: &str) -> RequestVoteReq {
+        self.term = Term(self.term.0 + 1);
+        self.state = RaftState::Candidate;
+        RequestVoteReq {
+            term: self.term,
+            candidate_id: candidate_id.to_string(),
+            last_log_index: self.last_log_index(),
+            last_log_term: self.last_log_term(),
+        }
+    }
+
+    /// 赢得多数派选票后成为领导者，为每个对等节点（及已知的学习者）重置复制进度
+    pub fn become_leader<I, S>(&mut self, peers: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.state = RaftState::Leader;
+        self.next_index.clear();
+        self.match_index.clear();
+        let learners: Vec<String> = self.learners.iter().cloned().collect();
+        for peer in peers.into_iter().map(Into::into).chain(learners) {
+            self.next_index.insert(peer.clone(), self.log_end() + 1);
+            self.match_index.insert(peer, 0);
+        }
+    }
+
+    /// 标记本节点为学习者：不为候选人投票，也不应发起选举
+    pub fn set_learner(&mut self, learner: bool) {
+        self.is_learner = learner;
+    }
+
+    pub fn is_learner(&self) -> bool {
+        self.is_learner
+    }
+
+    /// 对等节点的角色；领导者视角下未知的节点为 `None`
+    pub fn peer_role(&self, peer: &str) -> Option<ReplicaRole> {
+        if self.learners.contains(peer) {
+            Some(ReplicaRole::Learner)
+        } else if self.match_index.contains_key(peer) {
+            Some(ReplicaRole::Voter)
+        } else {
+            None
+        }
+    }
+
+    /// 已知的学习者（按名称排序）
+    pub fn learners(&self) -> Vec<&str> {
+        let mut learners: Vec<&str> = self.learners.iter().map(String::as_str).collect();
+        learners.sort_unstable();
+        learners
+    }
+
+    /// 领导者视角下的投票对等节点（按名称排序，不含自身）
+    pub fn voters(&self) -> Vec<&str> {
+        let mut voters: Vec<&str> = self
+            .match_index
+            .keys()
+            .map(String::as_str)
+            .filter(|peer| !self.learners.contains(*peer))
+            .collect();
+        voters.sort_unstable();
+        voters
+    }
+
+    /// 提交与选举所需的票数：投票者（含自身）的多数
+    pub fn quorum_size(&self) -> usize {
+        let members = self.voters().len() + 1;
+        members / 2 + 1
+    }
+}
+
+impl<E: Clone> MinimalRaft<E> {
+    /// 处理携带条目任期的 `AppendEntries`（网络传输路径）
+    pub fn handle_append_entries_with_terms(
+        &mut self,
+        req: AppendEntriesReq<(Term, E)>,
+    ) -> Result<AppendEntriesResp, DistributedError> {
+        let mut taken = self.apply.take();
+        let res = self.handle_append_entries_core(
+            req,
+            taken
+                .as_mut()
+                .map(|cb| cb.as_mut() as &mut (dyn FnMut(&E) + Send)),
+        );
+        self.apply = taken;
+        res
+    }
+
+    /// 领导者追加客户端命令，返回其日志索引；提交需等待多数派确认
+    pub fn propose(&mut self, entry: E) -> Result<LogIndex, DistributedError> {
+        if self.state != RaftState::Leader {
+            return Err(DistributedError::InvalidState("not the leader".to_string()));
+        }
+        self.log.push((self.term, entry));
+        // 单节点集群：自身即多数派
+        self.advance_commit();
+        Ok(self.last_log_index())
+    }
+
+    /// 按对等节点的 `next_index` 构造 `AppendEntries`，单次至多 `batch_size` 条。
+    /// 对等节点落后于已截断的前缀时从截断边界开始（见 `needs_snapshot`）
+    pub fn append_entries_for(&self, peer: &str, leader_id: &str) -> AppendEntriesReq<(Term, E)> {
+        let next = self
+            .next_index
+            .get(peer)
+            .copied()
+            .unwrap_or(self.log_end() + 1)
+            .clamp(self.log_start + 1, self.log_end() + 1);
+        let end = std::cmp::min(self.log_end(), next - 1 + self.batch_size.max(1));
+        AppendEntriesReq {
+            term: self.term,
+            leader_id: leader_id.to_string(),
+            prev_log_index: LogIndex((next - 1) as u64),
+            prev_log_term: self.term_at(next - 1),
+            entries: self.log[next - 1 - self.log_start..end - self.log_start].to_vec(),
+            leader_commit: self.commit_index(),
+        }
+    }
+
+    /// 对等节点需要的条目已被截断，只能经 InstallSnapshot 追赶
+    pub fn needs_snapshot(&self, peer: &str) -> bool {
+        self.next_index
+            .get(peer)
+            .is_some_and(|next| *next <= self.log_start)
+    }
+
+    /// 处理对等节点的响应：成功则推进 `match_index` 并尝试提交，失败则回退 `next_index`。
+    /// `last_sent` 为该请求覆盖的最后索引（`prev_log_index + entries.len()`）。
+    pub fn handle_append_entries_resp(
+        &mut self,
+        peer: &str,
+        last_sent: LogIndex,
+        resp: &AppendEntriesResp,
+    ) {
+        if self.observe_term(resp.term) || self.state != RaftState::Leader || resp.term != self.term
+        {
+            return;
+        }
+        if resp.success {
+            let matched = self.match_index.entry(peer.to_string()).or_insert(0);
+            *matched = (*matched).max(last_sent.0 as usize);
+            let matched = *matched;
+            self.next_index.insert(peer.to_string(), matched + 1);
+            self.advance_commit();
+        } else {
+            let next = self.next_index.entry(peer.to_string()).or_insert(1);
+            *next = next.saturating_sub(1).max(1);
+        }
+    }
+
+    /// 应用成员变更。任何节点都记录学习者集合；领导者同时调整复制进度，
+    /// 学习者提升或节点移除后按新的投票者集合重新判断提交
+    pub fn apply_conf_change(&mut self, change: ConfChange) -> Result<(), DistributedError> {
+        match change {
+            ConfChange::AddLearner(peer) => {
+                if self.peer_role(&peer) == Some(ReplicaRole::Voter) {
+                    return Err(DistributedError::InvalidState(format!(
+                        "{peer} is already a voter"
+                    )));
+                }
+                if self.state == RaftState::Leader {
+                    let next = self.log_end() + 1;
+                    self.next_index.entry(peer.clone()).or_insert(next);
+                    self.match_index.entry(peer.clone()).or_insert(0);
+                }
+                self.learners.insert(peer);
+            }
+            ConfChange::PromoteLearner(peer) => {
+                if !self.learners.remove(&peer) {
+                    return Err(DistributedError::InvalidState(format!(
+                        "{peer} is not a learner"
+                    )));
+                }
+            }
+            ConfChange::RemoveNode(peer) => {
+                self.learners.remove(&peer);
+                self.next_index.remove(&peer);
+                self.match_index.remove(&peer);
+            }
+        }
+        if self.state == RaftState::Leader {
+            self.advance_commit();
+        }
+        Ok(())
+    }
+
+    /// 领导者提交规则：仅当前任期的条目按投票者的多数派 `match_index` 提交，之前的条目随之提交；
+    /// 学习者的进度不计入
+    fn advance_commit(&mut self) {
+        let voters: Vec<usize> = self
+            .match_index
+            .iter()
+            .filter(|(peer, _)| !self.learners.contains(*peer))
+            .map(|(_, m)| *m)
+            .collect();
+        let cluster = voters.len() + 1;
+        for index in (self.commit_index + 1..=self.log_end()).rev() {
+            if self.term_at(index) != self.term {
+                break;
+            }
+            let acks = 1 + voters.iter().filter(|m| **m >= index).count();
+            if acks > cluster / 2 {
+                self.commit_index = index;
+                break;
+            }
+        }
+        self.apply_with_callback();
+        #[cfg(feature = "runtime-tokio")]
+        self.pump_apply_queue();
+    }
+
+    /// 启用异步应用：之后已提交条目进入容量为 `capacity` 的队列，由后台任务交给
+    /// `state_machine`。已应用的条目不会重放。须在 tokio 运行时内调用。
+    #[cfg(feature = "runtime-tokio")]
+    pub fn start_apply_queue<S>(
+        &mut self,
+        capacity: usize,
+        mut state_machine: S,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        E: Send + 'static,
+        S: StateMachine<E>,
+    {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<LogEntry<E>>(capacity.max(1));
+        let applied = Arc::new(AtomicUsize::new(self.last_applied));
+        let progress = applied.clone();
+        let handle = tokio::spawn(async move {
+            while let Some(entry) = receiver.recv().await {
+                let index = entry.index.0 as usize;
+                state_machine.apply(entry).await;
+                progress.store(index, Ordering::Release);
+            }
+        });
+        self.apply_queue = Some(ApplyQueue {
+            sender,
+            enqueued: self.last_applied,
+            applied,
+        });
+        self.pump_apply_queue();
+        handle
+    }
+
+    /// 把已提交但尚未入队的条目尽量放入应用队列；队列满时留待下次
+    #[cfg(feature = "runtime-tokio")]
+    pub fn pump_apply_queue(&mut self) {
+        let Some(queue) = &mut self.apply_queue else {
+            return;
+        };
+        while queue.enqueued < self.commit_index {
+            let Some((term, command)) = self.log.get(queue.enqueued.wrapping_sub(self.log_start))
+            else {
+                break;
+            };
+            let entry = LogEntry {
+                index: LogIndex(queue.enqueued as u64 + 1),
+                term: *term,
+                command: command.clone(),
+            };
+            if queue.sender.try_send(entry).is_err() {
+                break;
+            }
+            queue.enqueued += 1;
+        }
+        self.last_applied = self.last_applied.max(queue.applied.load(Ordering::Acquire));
+    }
+}
+
+impl super::ConsensusNode for MinimalRaft<Vec<u8>> {
+    fn role(&self) -> super::ConsensusRole {
+        self.state
+    }
+
+    fn current_term(&self) -> u64 {
+        self.term.0
+    }
+
+    /// 领导者追加命令，返回其日志索引
+    fn propose(&mut self, command: Vec<u8>) -> Result<super::ProposalId, DistributedError> {
+        MinimalRaft::propose(self, command).map(|index| super::ProposalId(index.0))
     }
 }
 
@@ -282,15 +879,8 @@ impl<E: Clone> RaftNode<E> for MinimalRaft<E> {
         &mut self,
         req: AppendEntriesReq<E>,
     ) -> Result<AppendEntriesResp, DistributedError> {
-        // 将已有的 'static 回调透传给核心实现（通过临时 take 避免可变别名）
-        let mut taken = self.apply.take();
-        let res = match taken.as_mut() {
-            Some(cb) => self
-                .handle_append_entries_core(req, Some(cb.as_mut() as &mut (dyn FnMut(&E) + Send))),
-            None => self.handle_append_entries_core(req, None),
-        };
-        self.apply = taken;
-        res
+        // 条目不带任期：按请求任期入日志
+        self.handle_append_entries_with_terms(Self::stamp(req))
     }
 
     fn handle_request_vote(
@@ -300,9 +890,12 @@ impl<E: Clone> RaftNode<E> for MinimalRaft<E> {
         if req.term.0 > self.term.0 {
             self.term = req.term;
             self.state = RaftState::Follower;
+            // 候选人日志须不落后于本地：先比最后条目任期，再比长度
+            let up_to_date = (req.last_log_term.0, req.last_log_index.0)
+                >= (self.last_log_term().0, self.last_log_index().0);
             return Ok(RequestVoteResp {
                 term: self.term,
-                vote_granted: true,
+                vote_granted: up_to_date && !self.is_learner,
             });
         }
         Ok(RequestVoteResp {
@@ -316,22 +909,46 @@ impl<E: Clone> RaftNode<E> for MinimalRaft<E> {
         req: InstallSnapshotReq,
     ) -> Result<InstallSnapshotResp, DistributedError> {
         if req.term.0 < self.term.0 {
-            return Ok(InstallSnapshotResp { term: self.term });
+            return Ok(InstallSnapshotResp {
+                term: self.term,
+                next_offset: 0,
+            });
         }
 
+        // 来自当前或更高任期领导者的快照：更新任期并退为跟随者（更高任期即清除本任期的投票）
         if req.term.0 > self.term.0 {
             self.term = req.term;
         }
+        self.state = RaftState::Follower;
 
-        // 安装快照
-        let snapshot = Snapshot {
-            last_included_index: req.last_included_index,
-            last_included_term: req.last_included_term,
+        let id = format!(
+            "snapshot-{}-{}",
+            req.last_included_index.0, req.last_included_term.0
+        );
+        let chunk = SnapshotChunk {
+            offset: req.offset,
             data: req.data,
+            checksum: req.checksum,
+            done: req.done,
+            snapshot_hash: req.snapshot_hash,
+        };
+        let next_offset = match self.snapshot_receiver.accept(&id, chunk)? {
+            ChunkOutcome::Progress(next_offset) => next_offset,
+            ChunkOutcome::Complete(data) => {
+                let next_offset = data.len() as u64;
+                self.install_snapshot(Snapshot {
+                    last_included_index: req.last_included_index,
+                    last_included_term: req.last_included_term,
+                    data,
+                });
+                next_offset
+            }
         };
-        self.install_snapshot(snapshot);
 
-        Ok(InstallSnapshotResp { term: self.term })
+        Ok(InstallSnapshotResp {
+            term: self.term,
+            next_offset,
+        })
     }
 
     fn create_snapshot(&self) -> Result<Snapshot, DistributedError> {
@@ -367,7 +984,8 @@ impl<'a, E: Clone> RaftNode<E> for ScopedApply<'a, E> {
         &mut self,
         req: AppendEntriesReq<E>,
     ) -> Result<AppendEntriesResp, DistributedError> {
-        self.raft.handle_append_entries_core(req, Some(self.apply))
+        self.raft
+            .handle_append_entries_core(MinimalRaft::stamp(req), Some(self.apply))
     }
     fn handle_request_vote(
         &mut self,
@@ -0,0 +1,981 @@
+//! 基于 gRPC 的 Raft 节点（`transport-grpc` 特性）
+//!
+//! 设计意图：
+//! - 协议见 `proto/raft.proto`：节点间的 `AppendEntries` / `RequestVote` / `InstallSnapshot`，
+//!   以及管理用的 `Propose` / `Status`。消息以 prost 手写（字段号与 proto 文件一致），无需 protoc。
+//! - `RaftGrpcNode` 把 `MinimalRaft<Vec<u8>>` 放在互斥锁后：`RaftServer` 作为 tonic 服务适配器在锁内
+//!   调用处理函数，`start` 启动的驱动循环负责选举超时、心跳与日志复制。
+//! - `GrpcRaftTransport` 实现 `RaftTransport`：每个对等节点缓存一个通道，传输失败后丢弃通道并按
+//!   指数退避延迟重连，宕机节点不会被每个心跳反复建连。
+//!
+//! 不变量（草图）：
+//! - 锁内不跨越 await：RPC 在锁外发出，响应回来后再加锁，并由任期校验丢弃过期结果。
+//! - 每个对等节点同时至多一个在途 `AppendEntries`，跟随者按序收到日志。
+//! - 日志仅在内存中：重启的节点以空日志加入，由领导者回退 `next_index` 补齐。
+//! - 配置了 `with_hard_state` 时，任期与投票先落盘再回复 `RequestVote`（自荐同样先落盘再发出），
+//!   重启的节点从文件恢复任期，不会在同一任期再投一票。
+
+use crate::consensus::raft::{
+    AppendEntriesReq, AppendEntriesResp, InstallSnapshotReq, InstallSnapshotResp, LogIndex,
+    MinimalRaft, RaftNode, RaftState, RaftTransport, RequestVoteReq, RequestVoteResp, Term,
+};
+use crate::core::errors::DistributedError;
+use crate::network::tls::TlsContext;
+use crate::storage::hard_state::{HardState, HardStateFile};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::{JoinHandle, JoinSet};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::codegen::{BoxFuture, Context, Poll, Service, http};
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Status};
+
+/// gRPC 服务名
+pub const SERVICE_NAME: &str = "distributed.raft.v1.Raft";
+pub const APPEND_ENTRIES_PATH: &str = "/distributed.raft.v1.Raft/AppendEntries";
+pub const REQUEST_VOTE_PATH: &str = "/distributed.raft.v1.Raft/RequestVote";
+pub const INSTALL_SNAPSHOT_PATH: &str = "/distributed.raft.v1.Raft/InstallSnapshot";
+pub const PROPOSE_PATH: &str = "/distributed.raft.v1.Raft/Propose";
+pub const STATUS_PATH: &str = "/distributed.raft.v1.Raft/Status";
+
+/// `proto/raft.proto` 对应的消息
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Entry {
+        #[prost(uint64, tag = "1")]
+        pub term: u64,
+        #[prost(bytes = "vec", tag = "2")]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AppendEntries {
+        #[prost(uint64, tag = "1")]
+        pub term: u64,
+        #[prost(string, tag = "2")]
+        pub leader_id: String,
+        #[prost(uint64, tag = "3")]
+        pub prev_log_index: u64,
+        #[prost(uint64, tag = "4")]
+        pub prev_log_term: u64,
+        #[prost(message, repeated, tag = "5")]
+        pub entries: Vec<Entry>,
+        #[prost(uint64, tag = "6")]
+        pub leader_commit: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AppendEntriesReply {
+        #[prost(uint64, tag = "1")]
+        pub term: u64,
+        #[prost(bool, tag = "2")]
+        pub success: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RequestVote {
+        #[prost(uint64, tag = "1")]
+        pub term: u64,
+        #[prost(string, tag = "2")]
+        pub candidate_id: String,
+        #[prost(uint64, tag = "3")]
+        pub last_log_index: u64,
+        #[prost(uint64, tag = "4")]
+        pub last_log_term: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RequestVoteReply {
+        #[prost(uint64, tag = "1")]
+        pub term: u64,
+        #[prost(bool, tag = "2")]
+        pub vote_granted: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InstallSnapshot {
+        #[prost(uint64, tag = "1")]
+        pub term: u64,
+        #[prost(string, tag = "2")]
+        pub leader_id: String,
+        #[prost(uint64, tag = "3")]
+        pub last_included_index: u64,
+        #[prost(uint64, tag = "4")]
+        pub last_included_term: u64,
+        #[prost(uint64, tag = "5")]
+        pub offset: u64,
+        #[prost(bytes = "vec", tag = "6")]
+        pub data: Vec<u8>,
+        #[prost(bool, tag = "7")]
+        pub done: bool,
+        #[prost(uint64, optional, tag = "8")]
+        pub checksum: Option<u64>,
+        #[prost(string, optional, tag = "9")]
+        pub snapshot_hash: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InstallSnapshotReply {
+        #[prost(uint64, tag = "1")]
+        pub term: u64,
+        #[prost(uint64, tag = "2")]
+        pub next_offset: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Propose {
+        #[prost(bytes = "vec", tag = "1")]
+        pub command: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ProposeReply {
+        #[prost(bool, tag = "1")]
+        pub accepted: bool,
+        #[prost(uint64, tag = "2")]
+        pub index: u64,
+        #[prost(string, optional, tag = "3")]
+        pub leader: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StatusRequest {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StatusReply {
+        #[prost(string, tag = "1")]
+        pub node_id: String,
+        #[prost(string, tag = "2")]
+        pub state: String,
+        #[prost(uint64, tag = "3")]
+        pub term: u64,
+        #[prost(uint64, tag = "4")]
+        pub commit_index: u64,
+        #[prost(uint64, tag = "5")]
+        pub last_log_index: u64,
+        #[prost(string, optional, tag = "6")]
+        pub leader: Option<String>,
+    }
+}
+
+impl From<AppendEntriesReq<(Term, Vec<u8>)>> for proto::AppendEntries {
+    fn from(req: AppendEntriesReq<(Term, Vec<u8>)>) -> Self {
+        Self {
+            term: req.term.0,
+            leader_id: req.leader_id,
+            prev_log_index: req.prev_log_index.0,
+            prev_log_term: req.prev_log_term.0,
+            entries: req
+                .entries
+                .into_iter()
+                .map(|(term, data)| proto::Entry { term: term.0, data })
+                .collect(),
+            leader_commit: req.leader_commit.0,
+        }
+    }
+}
+
+impl From<proto::AppendEntries> for AppendEntriesReq<(Term, Vec<u8>)> {
+    fn from(m: proto::AppendEntries) -> Self {
+        Self {
+            term: Term(m.term),
+            leader_id: m.leader_id,
+            prev_log_index: LogIndex(m.prev_log_index),
+            prev_log_term: Term(m.prev_log_term),
+            entries: m
+                .entries
+                .into_iter()
+                .map(|e| (Term(e.term), e.data))
+                .collect(),
+            leader_commit: LogIndex(m.leader_commit),
+        }
+    }
+}
+
+impl From<AppendEntriesResp> for proto::AppendEntriesReply {
+    fn from(resp: AppendEntriesResp) -> Self {
+        Self {
+            term: resp.term.0,
+            success: resp.success,
+        }
+    }
+}
+
+impl From<proto::AppendEntriesReply> for AppendEntriesResp {
+    fn from(m: proto::AppendEntriesReply) -> Self {
+        Self {
+            term: Term(m.term),
+            success: m.success,
+        }
+    }
+}
+
+impl From<RequestVoteReq> for proto::RequestVote {
+    fn from(req: RequestVoteReq) -> Self {
+        Self {
+            term: req.term.0,
+            candidate_id: req.candidate_id,
+            last_log_index: req.last_log_index.0,
+            last_log_term: req.last_log_term.0,
+        }
+    }
+}
+
+impl From<proto::RequestVote> for RequestVoteReq {
+    fn from(m: proto::RequestVote) -> Self {
+        Self {
+            term: Term(m.term),
+            candidate_id: m.candidate_id,
+            last_log_index: LogIndex(m.last_log_index),
+            last_log_term: Term(m.last_log_term),
+        }
+    }
+}
+
+impl From<RequestVoteResp> for proto::RequestVoteReply {
+    fn from(resp: RequestVoteResp) -> Self {
+        Self {
+            term: resp.term.0,
+            vote_granted: resp.vote_granted,
+        }
+    }
+}
+
+impl From<proto::RequestVoteReply> for RequestVoteResp {
+    fn from(m: proto::RequestVoteReply) -> Self {
+        Self {
+            term: Term(m.term),
+            vote_granted: m.vote_granted,
+        }
+    }
+}
+
+impl From<InstallSnapshotReq> for proto::InstallSnapshot {
+    fn from(req: InstallSnapshotReq) -> Self {
+        Self {
+            term: req.term.0,
+            leader_id: req.leader_id,
+            last_included_index: req.last_included_index.0,
+            last_included_term: req.last_included_term.0,
+            offset: req.offset,
+            data: req.data,
+            done: req.done,
+            checksum: req.checksum,
+            snapshot_hash: req.snapshot_hash,
+        }
+    }
+}
+
+impl From<proto::InstallSnapshot> for InstallSnapshotReq {
+    fn from(m: proto::InstallSnapshot) -> Self {
+        Self {
+            term: Term(m.term),
+            leader_id: m.leader_id,
+            last_included_index: LogIndex(m.last_included_index),
+            last_included_term: Term(m.last_included_term),
+            offset: m.offset,
+            data: m.data,
+            done: m.done,
+            checksum: m.checksum,
+            snapshot_hash: m.snapshot_hash,
+        }
+    }
+}
+
+/// 定时与重连参数
+#[derive(Debug, Clone)]
+pub struct RaftConfig {
+    pub heartbeat_interval: Duration,
+    /// 选举超时在 [min, max) 内随机，降低同时发起选举的概率
+    pub election_timeout_min: Duration,
+    pub election_timeout_max: Duration,
+    /// 单次 RPC（含建连）的超时
+    pub rpc_timeout: Duration,
+    /// 重连退避：第 n 次连续失败后等待 base * 2^(n-1)，不超过 max
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+}
+
+impl Default for RaftConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_millis(50),
+            election_timeout_min: Duration::from_millis(300),
+            election_timeout_max: Duration::from_millis(600),
+            rpc_timeout: Duration::from_millis(200),
+            backoff_base: Duration::from_millis(50),
+            backoff_max: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RaftConfig {
+    fn random_election_timeout(&self) -> Duration {
+        let span = self
+            .election_timeout_max
+            .saturating_sub(self.election_timeout_min)
+            .as_millis() as u64;
+        let jitter = match span {
+            0 => 0,
+            span => RandomState::new().hash_one(Instant::now()) % span,
+        };
+        self.election_timeout_min + Duration::from_millis(jitter)
+    }
+}
+
+#[derive(Default)]
+struct PeerChannel {
+    channel: Option<Channel>,
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+/// 客户端传输：节点 ID → 地址，每个节点缓存一个通道并在失败后退避重连
+pub struct GrpcRaftTransport {
+    endpoints: HashMap<String, String>,
+    config: RaftConfig,
+    peers: Mutex<HashMap<String, PeerChannel>>,
+    tls: Option<Arc<TlsContext>>,
+}
+
+impl GrpcRaftTransport {
+    /// `endpoints` 为 (节点 ID, `http://host:port`) 列表
+    pub fn new<I, K, V>(endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+            config: RaftConfig::default(),
+            peers: Mutex::new(HashMap::new()),
+            tls: None,
+        }
+    }
+
+    pub fn with_config(mut self, config: RaftConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 以 mTLS 建连
+    pub fn with_tls(mut self, tls: Arc<TlsContext>) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    async fn channel(&self, target: &str) -> Result<Channel, DistributedError> {
+        {
+            let peers = self.peers.lock().unwrap();
+            if let Some(peer) = peers.get(target) {
+                if let Some(channel) = &peer.channel {
+                    return Ok(channel.clone());
+                }
+                if peer.retry_at.is_some_and(|at| Instant::now() < at) {
+                    return Err(DistributedError::Network(format!(
+                        "{target} unreachable, backing off after {} failures",
+                        peer.failures
+                    )));
+                }
+            }
+        }
+        let uri = self
+            .endpoints
+            .get(target)
+            .ok_or_else(|| DistributedError::Configuration(format!("unknown node {target}")))?;
+        let endpoint = match &self.tls {
+            Some(_) => TlsContext::endpoint(uri)?,
+            None => Endpoint::from_shared(uri.clone())
+                .map_err(|e| DistributedError::Configuration(format!("{uri}: {e}")))?,
+        }
+        .connect_timeout(self.config.rpc_timeout);
+        let connected = match &self.tls {
+            Some(tls) => tls.connect(endpoint).await.map_err(|e| e.to_string()),
+            None => endpoint.connect().await.map_err(|e| e.to_string()),
+        };
+        let channel = connected.map_err(|e| {
+            self.record_failure(target);
+            DistributedError::Network(format!("{target}: {e}"))
+        })?;
+        self.peers
+            .lock()
+            .unwrap()
+            .entry(target.to_string())
+            .or_default()
+            .channel = Some(channel.clone());
+        Ok(channel)
+    }
+
+    /// 丢弃缓存通道并推迟下次重连
+    fn record_failure(&self, target: &str) {
+        let mut peers = self.peers.lock().unwrap();
+        let peer = peers.entry(target.to_string()).or_default();
+        peer.channel = None;
+        peer.failures = peer.failures.saturating_add(1);
+        let delay = self
+            .config
+            .backoff_base
+            .saturating_mul(1u32 << (peer.failures - 1).min(16))
+            .min(self.config.backoff_max);
+        peer.retry_at = Some(Instant::now() + delay);
+    }
+
+    fn record_success(&self, target: &str) {
+        if let Some(peer) = self.peers.lock().unwrap().get_mut(target) {
+            peer.failures = 0;
+            peer.retry_at = None;
+        }
+    }
+
+    async fn unary<Req, Resp>(
+        &self,
+        target: &str,
+        path: &'static str,
+        message: Req,
+    ) -> Result<Resp, DistributedError>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        let channel = self.channel(target).await?;
+        let mut grpc = tonic::client::Grpc::new(channel);
+        let mut request = tonic::Request::new(message);
+        request.set_timeout(self.config.rpc_timeout);
+        let call = async {
+            grpc.ready()
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+            let codec = tonic_prost::ProstCodec::<Req, Resp>::default();
+            grpc.unary(request, PathAndQuery::from_static(path), codec)
+                .await
+        };
+        match tokio::time::timeout(self.config.rpc_timeout, call).await {
+            Ok(Ok(response)) => {
+                self.record_success(target);
+                Ok(response.into_inner())
+            }
+            Ok(Err(status)) => {
+                // 传输层错误触发重连；业务错误（如参数非法）下通道仍可用
+                if matches!(
+                    status.code(),
+                    Code::Unavailable | Code::DeadlineExceeded | Code::Cancelled | Code::Unknown
+                ) {
+                    self.record_failure(target);
+                }
+                Err(status.into())
+            }
+            Err(_) => {
+                self.record_failure(target);
+                Err(DistributedError::Network(format!(
+                    "rpc to {target} timed out"
+                )))
+            }
+        }
+    }
+
+    /// 管理接口：向节点提交命令
+    pub async fn propose(
+        &self,
+        target: &str,
+        command: Vec<u8>,
+    ) -> Result<proto::ProposeReply, DistributedError> {
+        self.unary(target, PROPOSE_PATH, proto::Propose { command })
+            .await
+    }
+
+    /// 管理接口：查询节点状态
+    pub async fn status(&self, target: &str) -> Result<proto::StatusReply, DistributedError> {
+        self.unary(target, STATUS_PATH, proto::StatusRequest {})
+            .await
+    }
+}
+
+impl RaftTransport<Vec<u8>> for GrpcRaftTransport {
+    async fn append_entries(
+        &self,
+        target: &str,
+        req: AppendEntriesReq<(Term, Vec<u8>)>,
+    ) -> Result<AppendEntriesResp, DistributedError> {
+        let reply: proto::AppendEntriesReply = self
+            .unary(target, APPEND_ENTRIES_PATH, proto::AppendEntries::from(req))
+            .await?;
+        Ok(reply.into())
+    }
+
+    async fn request_vote(
+        &self,
+        target: &str,
+        req: RequestVoteReq,
+    ) -> Result<RequestVoteResp, DistributedError> {
+        let reply: proto::RequestVoteReply = self
+            .unary(target, REQUEST_VOTE_PATH, proto::RequestVote::from(req))
+            .await?;
+        Ok(reply.into())
+    }
+
+    async fn install_snapshot(
+        &self,
+        target: &str,
+        req: InstallSnapshotReq,
+    ) -> Result<InstallSnapshotResp, DistributedError> {
+        let reply: proto::InstallSnapshotReply = self
+            .unary(
+                target,
+                INSTALL_SNAPSHOT_PATH,
+                proto::InstallSnapshot::from(req),
+            )
+            .await?;
+        Ok(InstallSnapshotResp {
+            term: Term(reply.term),
+            next_offset: reply.next_offset,
+        })
+    }
+}
+
+/// 节点状态快照
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeStatus {
+    pub node_id: String,
+    pub state: RaftState,
+    pub term: Term,
+    pub commit_index: LogIndex,
+    pub last_log_index: LogIndex,
+    pub leader: Option<String>,
+}
+
+impl From<NodeStatus> for proto::StatusReply {
+    fn from(s: NodeStatus) -> Self {
+        Self {
+            node_id: s.node_id,
+            state: format!("{:?}", s.state),
+            term: s.term.0,
+            commit_index: s.commit_index.0,
+            last_log_index: s.last_log_index.0,
+            leader: s.leader,
+        }
+    }
+}
+
+struct NodeState {
+    raft: MinimalRaft<Vec<u8>>,
+    leader: Option<String>,
+    last_contact: Instant,
+    election_timeout: Duration,
+    in_flight: HashSet<String>,
+    stopped: bool,
+    hard_state: Option<HardStateFile>,
+    voted_for: Option<String>,
+}
+
+impl NodeState {
+    /// 记录当前任期的投票并落盘；未配置硬状态文件时只更新内存
+    fn persist_vote(&mut self, voted_for: Option<String>) -> Result<(), DistributedError> {
+        self.voted_for = voted_for;
+        match &self.hard_state {
+            Some(file) => file.save(&HardState {
+                term: self.raft.current_term().0,
+                voted_for: self.voted_for.clone(),
+                commit_index: self.raft.commit_index().0,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// 自身不再是领导者时清除自己的领导者记录
+    fn refresh_leader(&mut self, id: &str) {
+        if self.raft.state() != RaftState::Leader && self.leader.as_deref() == Some(id) {
+            self.leader = None;
+        }
+    }
+}
+
+/// 一个 Raft 节点：状态机 + 驱动循环 + 到对等节点的传输
+#[derive(Clone)]
+pub struct RaftGrpcNode {
+    id: String,
+    peers: Vec<String>,
+    state: Arc<Mutex<NodeState>>,
+    transport: Arc<GrpcRaftTransport>,
+    config: RaftConfig,
+}
+
+impl RaftGrpcNode {
+    /// `peers` 为其余节点的 (节点 ID, `http://host:port`) 列表；已提交条目经 `raft` 的 apply 回调应用
+    pub fn new<I, K, V>(id: impl Into<String>, raft: MinimalRaft<Vec<u8>>, peers: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let transport = GrpcRaftTransport::new(peers);
+        let mut peers: Vec<String> = transport.endpoints.keys().cloned().collect();
+        peers.sort();
+        let config = RaftConfig::default();
+        Self {
+            id: id.into(),
+            peers,
+            state: Arc::new(Mutex::new(NodeState {
+                raft,
+                leader: None,
+                last_contact: Instant::now(),
+                election_timeout: config.random_election_timeout(),
+                in_flight: HashSet::new(),
+                stopped: false,
+                hard_state: None,
+                voted_for: None,
+            })),
+            transport: Arc::new(transport),
+            config,
+        }
+    }
+
+    /// 须在 `start` / `server` 之前调用
+    pub fn with_config(mut self, config: RaftConfig) -> Self {
+        let endpoints = self.transport.endpoints.clone();
+        let mut transport = GrpcRaftTransport::new(endpoints).with_config(config.clone());
+        transport.tls = self.transport.tls.clone();
+        self.transport = Arc::new(transport);
+        self.state.lock().unwrap().election_timeout = config.random_election_timeout();
+        self.config = config;
+        self
+    }
+
+    /// 节点间 RPC 走 mTLS；须在 `start` / `server` 之前调用，服务端另需 `TlsContext::incoming`
+    pub fn with_tls(mut self, tls: Arc<TlsContext>) -> Self {
+        let endpoints = self.transport.endpoints.clone();
+        self.transport = Arc::new(
+            GrpcRaftTransport::new(endpoints)
+                .with_config(self.config.clone())
+                .with_tls(tls),
+        );
+        self
+    }
+
+    /// 任期与投票持久化到 `path`；文件已存在时从中恢复任期。须在 `start` / `server` 之前调用
+    pub fn with_hard_state(
+        self,
+        path: impl Into<std::path::PathBuf>,
+    ) -> Result<Self, DistributedError> {
+        let file = HardStateFile::new(path);
+        let restored = file.load()?;
+        {
+            let mut s = self.state.lock().unwrap();
+            if let Some(hard) = restored {
+                s.raft.observe_term(Term(hard.term));
+                s.voted_for = hard.voted_for;
+            }
+            s.hard_state = Some(file);
+        }
+        Ok(self)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn status(&self) -> NodeStatus {
+        let s = self.state.lock().unwrap();
+        NodeStatus {
+            node_id: self.id.clone(),
+            state: s.raft.state(),
+            term: s.raft.current_term(),
+            commit_index: s.raft.commit_index(),
+            last_log_index: s.raft.last_log_index(),
+            leader: s.leader.clone(),
+        }
+    }
+
+    /// 在领导者上追加命令；非领导者返回携带领导者提示的错误
+    pub fn propose(&self, command: Vec<u8>) -> Result<LogIndex, DistributedError> {
+        let index = {
+            let mut guard = self.state.lock().unwrap();
+            let s = &mut *guard;
+            s.raft.propose(command).map_err(|_| {
+                DistributedError::InvalidState(format!(
+                    "{} is not the leader (leader: {})",
+                    self.id,
+                    s.leader.as_deref().unwrap_or("unknown")
+                ))
+            })?
+        };
+        for peer in &self.peers {
+            self.replicate_to(peer);
+        }
+        Ok(index)
+    }
+
+    /// tonic 服务适配器
+    pub fn server(&self) -> RaftServer {
+        RaftServer { node: self.clone() }
+    }
+
+    /// 启动驱动循环：每个心跳周期检查选举超时，领导者向各节点复制日志
+    pub fn start(&self) -> JoinHandle<()> {
+        let node = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(node.config.heartbeat_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if node.state.lock().unwrap().stopped {
+                    return;
+                }
+                node.tick().await;
+            }
+        })
+    }
+
+    /// 模拟宕机：驱动循环退出，RPC 一律返回 `Unavailable`
+    pub fn shutdown(&self) {
+        self.state.lock().unwrap().stopped = true;
+    }
+
+    async fn tick(&self) {
+        let (state, timed_out) = {
+            let s = self.state.lock().unwrap();
+            (
+                s.raft.state(),
+                s.last_contact.elapsed() >= s.election_timeout,
+            )
+        };
+        if state == RaftState::Leader {
+            for peer in &self.peers {
+                self.replicate_to(peer);
+            }
+        } else if timed_out {
+            self.campaign().await;
+        }
+    }
+
+    async fn campaign(&self) {
+        let req = {
+            let mut s = self.state.lock().unwrap();
+            s.last_contact = Instant::now();
+            s.election_timeout = self.config.random_election_timeout();
+            s.leader = None;
+            let req = s.raft.start_election(&self.id);
+            // 自荐票落盘失败则放弃本轮：未持久化的投票不能发出
+            if s.persist_vote(Some(self.id.clone())).is_err() {
+                return;
+            }
+            req
+        };
+
+        let mut votes = JoinSet::new();
+        for peer in &self.peers {
+            let transport = self.transport.clone();
+            let (peer, req) = (peer.clone(), req.clone());
+            votes.spawn(async move { transport.request_vote(&peer, req).await });
+        }
+        let cluster = self.peers.len() + 1;
+        let need = cluster / 2 + 1;
+        let mut granted = 1;
+        while granted < need {
+            let Some(joined) = votes.join_next().await else {
+                return;
+            };
+            let Ok(Ok(resp)) = joined else {
+                continue;
+            };
+            let mut s = self.state.lock().unwrap();
+            if s.raft.observe_term(resp.term) {
+                return;
+            }
+            if resp.vote_granted && resp.term == req.term {
+                granted += 1;
+            }
+        }
+
+        {
+            let mut s = self.state.lock().unwrap();
+            // 等票期间可能已收到新领导者的心跳或更高任期
+            if s.raft.state() != RaftState::Candidate || s.raft.current_term() != req.term {
+                return;
+            }
+            s.raft.become_leader(self.peers.iter().cloned());
+            s.leader = Some(self.id.clone());
+        }
+        for peer in &self.peers {
+            self.replicate_to(peer);
+        }
+    }
+
+    /// 向对等节点发送一次 `AppendEntries`（该节点已有在途请求时跳过）
+    fn replicate_to(&self, peer: &str) {
+        let req = {
+            let mut s = self.state.lock().unwrap();
+            if s.raft.state() != RaftState::Leader || !s.in_flight.insert(peer.to_string()) {
+                return;
+            }
+            s.raft.append_entries_for(peer, &self.id)
+        };
+        let last_sent = LogIndex(req.prev_log_index.0 + req.entries.len() as u64);
+        let node = self.clone();
+        let peer = peer.to_string();
+        tokio::spawn(async move {
+            let result = node.transport.append_entries(&peer, req).await;
+            let mut s = node.state.lock().unwrap();
+            s.in_flight.remove(&peer);
+            if let Ok(resp) = result {
+                s.raft.handle_append_entries_resp(&peer, last_sent, &resp);
+                s.refresh_leader(&node.id);
+            }
+        });
+    }
+
+    fn locked(&self) -> Result<std::sync::MutexGuard<'_, NodeState>, Status> {
+        let s = self.state.lock().unwrap();
+        if s.stopped {
+            return Err(Status::unavailable(format!("{} is stopped", self.id)));
+        }
+        Ok(s)
+    }
+
+    fn on_append_entries(
+        &self,
+        message: proto::AppendEntries,
+    ) -> Result<proto::AppendEntriesReply, Status> {
+        let req = AppendEntriesReq::from(message);
+        let (term, leader) = (req.term, req.leader_id.clone());
+        let mut s = self.locked()?;
+        let resp = s.raft.handle_append_entries_with_terms(req)?;
+        // 来自当前任期领导者的请求（无论日志是否匹配）都重置选举计时
+        if resp.term == term {
+            s.leader = Some(leader);
+            s.last_contact = Instant::now();
+        }
+        Ok(resp.into())
+    }
+
+    fn on_request_vote(
+        &self,
+        message: proto::RequestVote,
+    ) -> Result<proto::RequestVoteReply, Status> {
+        let mut s = self.locked()?;
+        let term_before = s.raft.current_term();
+        let candidate = message.candidate_id.clone();
+        let resp = s.raft.handle_request_vote(message.into())?;
+        // 先把任期与投票落盘再回复：重启后不会在同一任期再投给别的候选人
+        if resp.vote_granted {
+            s.persist_vote(Some(candidate))?;
+            s.last_contact = Instant::now();
+            s.leader = None;
+        } else if resp.term != term_before {
+            s.persist_vote(None)?;
+        }
+        s.refresh_leader(&self.id);
+        Ok(resp.into())
+    }
+
+    fn on_install_snapshot(
+        &self,
+        message: proto::InstallSnapshot,
+    ) -> Result<proto::InstallSnapshotReply, Status> {
+        let mut s = self.locked()?;
+        let term_before = s.raft.current_term();
+        let resp = s.raft.handle_install_snapshot(message.into())?;
+        // 进入更高任期：本任期尚未投票
+        if resp.term != term_before {
+            s.persist_vote(None)?;
+        }
+        s.refresh_leader(&self.id);
+        Ok(proto::InstallSnapshotReply {
+            term: resp.term.0,
+            next_offset: resp.next_offset,
+        })
+    }
+
+    fn on_propose(&self, message: proto::Propose) -> Result<proto::ProposeReply, Status> {
+        drop(self.locked()?);
+        let reply = match self.propose(message.command) {
+            Ok(index) => proto::ProposeReply {
+                accepted: true,
+                index: index.0,
+                leader: Some(self.id.clone()),
+            },
+            Err(_) => proto::ProposeReply {
+                accepted: false,
+                index: 0,
+                leader: self.status().leader,
+            },
+        };
+        Ok(reply)
+    }
+
+    fn on_status(&self) -> Result<proto::StatusReply, Status> {
+        drop(self.locked()?);
+        Ok(self.status().into())
+    }
+}
+
+/// Raft 服务端适配器：挂到 `tonic::transport::Server` 上
+#[derive(Clone)]
+pub struct RaftServer {
+    node: RaftGrpcNode,
+}
+
+impl tonic::server::NamedService for RaftServer {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl Service<http::Request<tonic::body::Body>> for RaftServer {
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<tonic::body::Body>) -> Self::Future {
+        let node = self.node.clone();
+        match req.uri().path() {
+            APPEND_ENTRIES_PATH => unary(req, move |m| node.on_append_entries(m)),
+            REQUEST_VOTE_PATH => unary(req, move |m| node.on_request_vote(m)),
+            INSTALL_SNAPSHOT_PATH => unary(req, move |m| node.on_install_snapshot(m)),
+            PROPOSE_PATH => unary(req, move |m| node.on_propose(m)),
+            STATUS_PATH => unary(req, move |_: proto::StatusRequest| node.on_status()),
+            _ => Box::pin(async { Ok(Status::unimplemented("unknown method").into_http()) }),
+        }
+    }
+}
+
+fn unary<Req, Resp, F>(
+    req: http::Request<tonic::body::Body>,
+    handler: F,
+) -> BoxFuture<http::Response<tonic::body::Body>, Infallible>
+where
+    Req: prost::Message + Default + Send + Sync + 'static,
+    Resp: prost::Message + Send + Sync + 'static,
+    F: FnMut(Req) -> Result<Resp, Status> + Send + 'static,
+{
+    Box::pin(async move {
+        let codec = tonic_prost::ProstCodec::<Resp, Req>::default();
+        Ok(tonic::server::Grpc::new(codec)
+            .unary(Handler(handler), req)
+            .await)
+    })
+}
+
+/// 同步处理函数到 `UnaryService` 的适配
+struct Handler<F>(F);
+
+impl<F, Req, Resp> tonic::server::UnaryService<Req> for Handler<F>
+where
+    F: FnMut(Req) -> Result<Resp, Status>,
+{
+    type Response = Resp;
+    type Future = std::future::Ready<Result<tonic::Response<Resp>, Status>>;
+
+    fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+        std::future::ready((self.0)(request.into_inner()).map(tonic::Response::new))
+    }
+}
@@ -0,0 +1,457 @@
+//! 分块、可续传的快照传输
+//!
+//! 设计意图：
+//! - 数 GB 的快照无法可靠地经单个 RPC 发送。`SnapshotSender` 把快照切成固定大小的块，
+//!   每块携带偏移与校验和，最后一块携带整份快照的 SHA-256；同时在途的块数受
+//!   `max_in_flight` 限制（流控）。
+//! - `SnapshotReceiver` 按偏移顺序拼接块，应答“下一个期望的偏移”。连接断开后发送方从
+//!   最后被确认的偏移续传，已确认的块不会重发；接收方配置 `with_spool_dir` 时把进度
+//!   落盘，进程重启后同样可以续传。
+//! - 最后一块到达后先校验整体哈希，通过才交给调用方安装；不通过则丢弃已接收的数据。
+//! - `install_snapshot_chunked` 经 `RaftTransport` 驱动一次 InstallSnapshot 传输；
+//!   分片搬迁见 `partitioning::autoscaler::ChunkedShardDrain`。
+//!
+//! 不变量（草图）：
+//! - 接收方只追加偏移恰好等于已接收长度、且校验和正确的块；重复块与乱序块只返回当前进度。
+//! - 发送方的确认偏移单调不减（除非接收方报告更小的进度，如接收方丢失了未落盘的数据）。
+
+use crate::consensus::raft::{
+    InstallSnapshotReq, InstallSnapshotResp, RaftTransport, Snapshot, Term,
+};
+use crate::core::errors::DistributedError;
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 默认块大小 1 MiB
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// 块校验和：SHA-256 的前 8 字节
+pub fn chunk_checksum(data: &[u8]) -> u64 {
+    let digest = Sha256::digest(data);
+    u64::from_be_bytes(digest[..8].try_into().expect("8-byte prefix"))
+}
+
+/// 整份快照的 SHA-256（十六进制）
+pub fn snapshot_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 快照的一块；`snapshot_hash` 只在最后一块（`done`）上携带
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotChunk {
+    pub offset: u64,
+    pub data: Vec<u8>,
+    /// `None` 表示发送方不校验（旧版单块请求）
+    pub checksum: Option<u64>,
+    pub done: bool,
+    pub snapshot_hash: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SnapshotSenderStats {
+    chunks_sent: AtomicU64,
+    bytes_sent: AtomicU64,
+    resent_chunks: AtomicU64,
+}
+
+impl SnapshotSenderStats {
+    pub fn chunks_sent(&self) -> u64 {
+        self.chunks_sent.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    /// 断线或接收方回退后重发的块（均未被确认过）
+    pub fn resent_chunks(&self) -> u64 {
+        self.resent_chunks.load(Ordering::Relaxed)
+    }
+}
+
+/// 发送方状态机：`next_chunk` 取下一块，`on_ack` 记录接收方进度，`on_disconnect` 回退到
+/// 最后确认的偏移
+pub struct SnapshotSender {
+    data: Vec<u8>,
+    hash: String,
+    chunk_size: usize,
+    max_in_flight: usize,
+    /// 下一块的起始偏移
+    next_offset: u64,
+    /// 接收方确认的偏移
+    acked: u64,
+    /// 曾发送过的最大偏移，用于统计重发
+    high_water: u64,
+    /// 最后一块已发出且未作废
+    done_sent: bool,
+    done_acked: bool,
+    stats: SnapshotSenderStats,
+}
+
+impl SnapshotSender {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            hash: snapshot_hash(&data),
+            data,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_in_flight: 4,
+            next_offset: 0,
+            acked: 0,
+            high_water: 0,
+            done_sent: false,
+            done_acked: false,
+            stats: SnapshotSenderStats::default(),
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// 未确认的块数上限
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    pub fn stats(&self) -> &SnapshotSenderStats {
+        &self.stats
+    }
+
+    pub fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// 总块数（空快照为 1）
+    pub fn total_chunks(&self) -> usize {
+        self.data.len().div_ceil(self.chunk_size).max(1)
+    }
+
+    pub fn acked_offset(&self) -> u64 {
+        self.acked
+    }
+
+    /// 当前已发送未确认的块数
+    pub fn in_flight(&self) -> usize {
+        ((self.next_offset - self.acked) as usize).div_ceil(self.chunk_size)
+    }
+
+    /// 接收方已确认全部数据（含最后一块的哈希校验）
+    pub fn is_complete(&self) -> bool {
+        self.done_acked
+    }
+
+    /// 下一块；全部发出或在途块数已达上限时为 `None`
+    pub fn next_chunk(&mut self) -> Option<SnapshotChunk> {
+        if self.done_sent || self.in_flight() >= self.max_in_flight {
+            return None;
+        }
+        let start = self.next_offset as usize;
+        let end = (start + self.chunk_size).min(self.data.len());
+        let data = self.data[start..end].to_vec();
+        let done = end == self.data.len();
+        if self.next_offset < self.high_water {
+            self.stats.resent_chunks.fetch_add(1, Ordering::Relaxed);
+        }
+        self.stats.chunks_sent.fetch_add(1, Ordering::Relaxed);
+        self.stats
+            .bytes_sent
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+        self.next_offset = end as u64;
+        self.high_water = self.high_water.max(self.next_offset);
+        self.done_sent = done;
+        Some(SnapshotChunk {
+            offset: start as u64,
+            checksum: Some(chunk_checksum(&data)),
+            data,
+            done,
+            snapshot_hash: done.then(|| self.hash.clone()),
+        })
+    }
+
+    /// 接收方应答的下一个期望偏移；没有推进时从该偏移重发
+    pub fn on_ack(&mut self, next_offset: u64) {
+        let next_offset = next_offset.min(self.len());
+        if self.done_sent && next_offset == self.len() {
+            // 接收方只在最后一块通过哈希校验后才应答全长
+            self.acked = next_offset;
+            self.done_acked = true;
+        } else if next_offset > self.acked {
+            self.acked = next_offset;
+            // 接收方已落盘的进度可能超过本发送方的位置
+            self.next_offset = self.next_offset.max(next_offset);
+        } else {
+            self.acked = next_offset;
+            self.next_offset = next_offset;
+            self.done_sent = false;
+        }
+    }
+
+    /// 连接断开：未确认的块作废，从最后确认的偏移续传
+    pub fn on_disconnect(&mut self) {
+        self.next_offset = self.acked;
+        self.done_sent = false;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SnapshotReceiverStats {
+    accepted_chunks: AtomicU64,
+    duplicate_chunks: AtomicU64,
+    rejected_chunks: AtomicU64,
+    hash_mismatches: AtomicU64,
+    completed: AtomicU64,
+}
+
+impl SnapshotReceiverStats {
+    pub fn accepted_chunks(&self) -> u64 {
+        self.accepted_chunks.load(Ordering::Relaxed)
+    }
+
+    /// 偏移早于已接收长度的块
+    pub fn duplicate_chunks(&self) -> u64 {
+        self.duplicate_chunks.load(Ordering::Relaxed)
+    }
+
+    /// 校验和错误或偏移超前（出现空洞）的块
+    pub fn rejected_chunks(&self) -> u64 {
+        self.rejected_chunks.load(Ordering::Relaxed)
+    }
+
+    pub fn hash_mismatches(&self) -> u64 {
+        self.hash_mismatches.load(Ordering::Relaxed)
+    }
+
+    pub fn completed(&self) -> u64 {
+        self.completed.load(Ordering::Relaxed)
+    }
+}
+
+/// `SnapshotReceiver::accept` 的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkOutcome {
+    /// 尚未完成；值为下一个期望的偏移
+    Progress(u64),
+    /// 最后一块已到达且整体哈希校验通过
+    Complete(Vec<u8>),
+}
+
+struct Assembly {
+    id: String,
+    data: Vec<u8>,
+}
+
+/// 接收方：按快照标识拼接块，同一时刻只组装一份快照
+#[derive(Default)]
+pub struct SnapshotReceiver {
+    spool_dir: Option<PathBuf>,
+    current: Option<Assembly>,
+    stats: SnapshotReceiverStats,
+}
+
+impl SnapshotReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 把已接收的数据追加写入 `<dir>/<id>.part`，重启后从文件长度处续传
+    pub fn with_spool_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.spool_dir = Some(dir.into());
+        self
+    }
+
+    pub fn stats(&self) -> &SnapshotReceiverStats {
+        &self.stats
+    }
+
+    /// 正在组装的快照已接收的字节数
+    pub fn received(&self, id: &str) -> u64 {
+        self.current
+            .as_ref()
+            .filter(|a| a.id == id)
+            .map_or(0, |a| a.data.len() as u64)
+    }
+
+    /// 接收快照 `id` 的一块
+    pub fn accept(
+        &mut self,
+        id: &str,
+        chunk: SnapshotChunk,
+    ) -> Result<ChunkOutcome, DistributedError> {
+        if self.current.as_ref().is_none_or(|a| a.id != id) {
+            self.start(id)?;
+        }
+        if chunk.offset == 0 && chunk.checksum.is_none() {
+            // 旧版请求不带校验和，从头覆盖
+            self.truncate()?;
+        }
+        let received = self.received(id);
+        if chunk.offset < received {
+            self.stats.duplicate_chunks.fetch_add(1, Ordering::Relaxed);
+            return Ok(ChunkOutcome::Progress(received));
+        }
+        if chunk.offset > received
+            || chunk
+                .checksum
+                .is_some_and(|c| c != chunk_checksum(&chunk.data))
+        {
+            self.stats.rejected_chunks.fetch_add(1, Ordering::Relaxed);
+            return Ok(ChunkOutcome::Progress(received));
+        }
+        self.append(&chunk.data)?;
+        self.stats.accepted_chunks.fetch_add(1, Ordering::Relaxed);
+        let assembly = self.current.as_ref().expect("assembly started");
+        if !chunk.done {
+            return Ok(ChunkOutcome::Progress(assembly.data.len() as u64));
+        }
+        if let Some(expected) = &chunk.snapshot_hash
+            && *expected != snapshot_hash(&assembly.data)
+        {
+            self.stats.hash_mismatches.fetch_add(1, Ordering::Relaxed);
+            self.truncate()?;
+            return Err(DistributedError::Consensus(format!(
+                "snapshot {id} hash mismatch, discarded received data"
+            )));
+        }
+        let assembly = self.current.take().expect("assembly started");
+        if let Some(path) = self.spool_path(id) {
+            let _ = fs::remove_file(path);
+        }
+        self.stats.completed.fetch_add(1, Ordering::Relaxed);
+        Ok(ChunkOutcome::Complete(assembly.data))
+    }
+
+    fn spool_path(&self, id: &str) -> Option<PathBuf> {
+        let name: String = id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.spool_dir
+            .as_ref()
+            .map(|d| d.join(format!("{name}.part")))
+    }
+
+    /// 开始组装 `id`；放弃之前未完成的快照，已落盘的同名进度被恢复
+    fn start(&mut self, id: &str) -> Result<(), DistributedError> {
+        if let Some(old) = self.current.take()
+            && let Some(path) = self.spool_path(&old.id)
+        {
+            let _ = fs::remove_file(path);
+        }
+        let data = match self.spool_path(id) {
+            Some(path) if path.exists() => fs::read(&path).map_err(io_error)?,
+            _ => Vec::new(),
+        };
+        self.current = Some(Assembly {
+            id: id.to_string(),
+            data,
+        });
+        Ok(())
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<(), DistributedError> {
+        let id = &self.current.as_ref().expect("assembly started").id;
+        if let Some(path) = self.spool_path(id) {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(io_error)?;
+            }
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(io_error)?;
+            file.write_all(data).map_err(io_error)?;
+            file.sync_data().map_err(io_error)?;
+        }
+        let assembly = self.current.as_mut().expect("assembly started");
+        assembly.data.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn truncate(&mut self) -> Result<(), DistributedError> {
+        let Some(id) = self.current.as_ref().map(|a| a.id.clone()) else {
+            return Ok(());
+        };
+        if let Some(path) = self.spool_path(&id) {
+            let _ = fs::remove_file(path);
+        }
+        if let Some(assembly) = self.current.as_mut() {
+            assembly.data.clear();
+        }
+        Ok(())
+    }
+}
+
+fn io_error(e: std::io::Error) -> DistributedError {
+    DistributedError::Storage(format!("snapshot spool: {e}"))
+}
+
+/// 经 `transport` 把 `snapshot` 分块发给 `target`，从 `sender` 的确认偏移续传。
+///
+/// 每轮发出至多 `max_in_flight` 块并依次等待应答；任一块失败即视为断线，`sender` 回退到
+/// 最后确认的偏移后返回错误，调用方重连后以同一个 `sender` 再次调用即可续传。
+/// 对端任期更高时立即返回其应答。
+pub async fn install_snapshot_chunked<E, T>(
+    transport: &T,
+    target: &str,
+    term: Term,
+    leader_id: &str,
+    snapshot: &Snapshot,
+    sender: &mut SnapshotSender,
+) -> Result<InstallSnapshotResp, DistributedError>
+where
+    T: RaftTransport<E>,
+{
+    let mut last = InstallSnapshotResp {
+        term,
+        next_offset: sender.acked_offset(),
+    };
+    while !sender.is_complete() {
+        let mut window = Vec::new();
+        while let Some(chunk) = sender.next_chunk() {
+            let req = InstallSnapshotReq {
+                term,
+                leader_id: leader_id.to_string(),
+                last_included_index: snapshot.last_included_index,
+                last_included_term: snapshot.last_included_term,
+                offset: chunk.offset,
+                data: chunk.data,
+                done: chunk.done,
+                checksum: chunk.checksum,
+                snapshot_hash: chunk.snapshot_hash,
+            };
+            window.push(transport.install_snapshot(target, req));
+        }
+        for reply in window {
+            match reply.await {
+                Ok(resp) if resp.term.0 > term.0 => return Ok(resp),
+                Ok(resp) => {
+                    sender.on_ack(resp.next_offset);
+                    last = resp;
+                }
+                Err(e) => {
+                    sender.on_disconnect();
+                    return Err(e);
+                }
+            }
+        }
+    }
+    Ok(last)
+}
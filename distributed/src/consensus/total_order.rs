@@ -0,0 +1,202 @@
+//! 全序广播（Atomic / Total-Order Broadcast）
+//!
+//! 设计意图：
+//! - 为 Raft/Paxos 之上的应用提供“广播 + 按序订阅”的抽象，屏蔽原始日志条目与索引细节。
+//! - `RaftTotalOrderBroadcast` 以 `MinimalRaft` 作为副本：首个副本为领导者，`propose` 追加条目后
+//!   经 `RaftTransport` 向跟随者发送 `AppendEntries`，按应答推进 `match_index`，多数派确认后
+//!   提交；再发一轮携带新提交点的请求，副本按日志顺序应用并推送给订阅者。
+//! - 跟随者拒绝（日志不匹配）时领导者回退其 `next_index` 重发，直至日志前缀一致；未获多数派
+//!   确认的广播返回错误，条目留在领导者日志中，可能随之后的广播一并提交。
+//!
+//! 性质（草图）：
+//! - 全序：所有副本以相同顺序投递相同消息（由日志前缀匹配与按序 apply 保证）。
+//! - 序号无空洞且严格单调：序号即已提交日志索引，从 1 开始逐一递增。
+//! - 失败的广播不会使副本日志分叉：副本只追加领导者日志的前缀。
+
+use crate::consensus::raft::{
+    AppendEntriesReq, AppendEntriesResp, InstallSnapshotReq, InstallSnapshotResp, LogIndex,
+    MinimalRaft, RaftNode, RaftState, RaftTransport, RequestVoteReq, RequestVoteResp, Term,
+};
+use crate::core::errors::DistributedError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::{Mutex, broadcast};
+
+/// 全序广播序号（等于已提交日志索引）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SequenceNumber(pub u64);
+
+pub trait TotalOrderBroadcast {
+    /// 广播负载，提交后返回其全序序号
+    fn broadcast(
+        &self,
+        payload: Vec<u8>,
+    ) -> impl Future<Output = Result<SequenceNumber, DistributedError>> + Send;
+    /// 订阅本副本按序投递的消息
+    fn subscribe(&self) -> broadcast::Receiver<(SequenceNumber, Vec<u8>)>;
+}
+
+/// 领导者在 `AppendEntries` 中使用的标识
+const LEADER_ID: &str = "r0";
+
+/// 进程内传输：直接调用目标跟随者的处理函数
+struct LocalTransport {
+    followers: HashMap<String, Arc<StdMutex<MinimalRaft<Vec<u8>>>>>,
+}
+
+impl LocalTransport {
+    fn follower(
+        &self,
+        target: &str,
+    ) -> Result<&Arc<StdMutex<MinimalRaft<Vec<u8>>>>, DistributedError> {
+        self.followers
+            .get(target)
+            .ok_or_else(|| DistributedError::Network(format!("unknown replica {target}")))
+    }
+}
+
+impl RaftTransport<Vec<u8>> for LocalTransport {
+    async fn append_entries(
+        &self,
+        target: &str,
+        req: AppendEntriesReq<(Term, Vec<u8>)>,
+    ) -> Result<AppendEntriesResp, DistributedError> {
+        self.follower(target)?
+            .lock()
+            .unwrap()
+            .handle_append_entries_with_terms(req)
+    }
+
+    async fn request_vote(
+        &self,
+        target: &str,
+        req: RequestVoteReq,
+    ) -> Result<RequestVoteResp, DistributedError> {
+        self.follower(target)?
+            .lock()
+            .unwrap()
+            .handle_request_vote(req)
+    }
+
+    async fn install_snapshot(
+        &self,
+        target: &str,
+        req: InstallSnapshotReq,
+    ) -> Result<InstallSnapshotResp, DistributedError> {
+        self.follower(target)?
+            .lock()
+            .unwrap()
+            .handle_install_snapshot(req)
+    }
+}
+
+struct RaftGroup<T> {
+    leader: MinimalRaft<Vec<u8>>,
+    peers: Vec<String>,
+    transport: T,
+}
+
+impl<T: RaftTransport<Vec<u8>>> RaftGroup<T> {
+    async fn append(&mut self, payload: Vec<u8>) -> Result<SequenceNumber, DistributedError> {
+        let index = self.leader.propose(payload)?;
+        self.replicate().await;
+        if self.leader.commit_index().0 < index.0 {
+            return Err(DistributedError::Consensus(format!(
+                "entry {} not committed by a quorum",
+                index.0
+            )));
+        }
+        // 再发一轮：跟随者得知新的提交点后按序 apply 并投递给订阅者
+        self.replicate().await;
+        Ok(SequenceNumber(index.0))
+    }
+
+    /// 向每个跟随者发送 `AppendEntries`，被拒绝时回退 `next_index` 重发，直至其日志追上
+    /// 领导者或传输失败
+    async fn replicate(&mut self) {
+        for peer in &self.peers {
+            loop {
+                let req = self.leader.append_entries_for(peer, LEADER_ID);
+                let prev = req.prev_log_index;
+                let last_sent = LogIndex(prev.0 + req.entries.len() as u64);
+                let Ok(resp) = self.transport.append_entries(peer, req).await else {
+                    break;
+                };
+                self.leader
+                    .handle_append_entries_resp(peer, last_sent, &resp);
+                if self.leader.state() != RaftState::Leader {
+                    return;
+                }
+                let caught_up = resp.success && last_sent.0 >= self.leader.last_log_index().0;
+                if caught_up || (!resp.success && prev.0 == 0) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// 基于 `MinimalRaft` 的全序广播；每个句柄对应一个副本，共享同一复制组
+#[derive(Clone)]
+pub struct RaftTotalOrderBroadcast {
+    group: Arc<Mutex<RaftGroup<LocalTransport>>>,
+    delivered: broadcast::Sender<(SequenceNumber, Vec<u8>)>,
+}
+
+impl RaftTotalOrderBroadcast {
+    /// 创建 `replicas` 个副本组成的复制组，返回每个副本的句柄；首个副本为领导者
+    pub fn cluster(replicas: usize, capacity: usize) -> Vec<Self> {
+        if replicas == 0 {
+            return Vec::new();
+        }
+        let mut senders = Vec::with_capacity(replicas);
+        let mut nodes = Vec::with_capacity(replicas);
+        for _ in 0..replicas {
+            let (tx, _) = broadcast::channel(capacity.max(1));
+            let sink = tx.clone();
+            let mut next = 0u64;
+            let mut raft: MinimalRaft<Vec<u8>> = MinimalRaft::new();
+            raft.set_apply(Box::new(move |entry: &Vec<u8>| {
+                next += 1;
+                let _ = sink.send((SequenceNumber(next), entry.clone()));
+            }));
+            nodes.push(raft);
+            senders.push(tx);
+        }
+
+        let mut nodes = nodes.into_iter();
+        let mut leader = nodes.next().expect("at least one replica");
+        let followers: HashMap<String, _> = nodes
+            .enumerate()
+            .map(|(i, raft)| (format!("r{}", i + 1), Arc::new(StdMutex::new(raft))))
+            .collect();
+        let mut peers: Vec<String> = followers.keys().cloned().collect();
+        peers.sort();
+        leader.start_election(LEADER_ID);
+        leader.become_leader(peers.iter().cloned());
+
+        let group = Arc::new(Mutex::new(RaftGroup {
+            leader,
+            peers,
+            transport: LocalTransport { followers },
+        }));
+        senders
+            .into_iter()
+            .map(|delivered| Self {
+                group: group.clone(),
+                delivered,
+            })
+            .collect()
+    }
+}
+
+impl TotalOrderBroadcast for RaftTotalOrderBroadcast {
+    async fn broadcast(&self, payload: Vec<u8>) -> Result<SequenceNumber, DistributedError> {
+        self.group.lock().await.append(payload).await
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<(SequenceNumber, Vec<u8>)> {
+        self.delivered.subscribe()
+    }
+}
@@ -0,0 +1,58 @@
+//! 计数器 CRDT
+//!
+//! 设计意图：
+//! - 计数类状态（如租户用量）需要在各节点本地更新、惰性复制并在任意顺序合并后收敛。
+//!   `PNCounter` 为每个节点分别记录增量与减量，值为两者之差；合并按节点取各自的最大值。
+//!
+//! 不变量（草图）：
+//! - `merge` 满足交换律、结合律与幂等律，任意传播顺序下各副本收敛到相同的值。
+//! - 节点只修改自己的分量，单调不减，因此取最大值不会丢失更新。
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PNCounter {
+    increments: BTreeMap<String, u64>,
+    decrements: BTreeMap<String, u64>,
+}
+
+impl PNCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn increment(&mut self, node: &str, amount: u64) {
+        *self.increments.entry(node.to_string()).or_insert(0) += amount;
+    }
+
+    pub fn decrement(&mut self, node: &str, amount: u64) {
+        *self.decrements.entry(node.to_string()).or_insert(0) += amount;
+    }
+
+    /// 正数增加、负数减少
+    pub fn add(&mut self, node: &str, delta: i64) {
+        if delta >= 0 {
+            self.increment(node, delta as u64);
+        } else {
+            self.decrement(node, delta.unsigned_abs());
+        }
+    }
+
+    pub fn value(&self) -> i64 {
+        let up: u64 = self.increments.values().sum();
+        let down: u64 = self.decrements.values().sum();
+        up as i64 - down as i64
+    }
+
+    pub fn merge(&mut self, other: &PNCounter) {
+        for (node, n) in &other.increments {
+            let entry = self.increments.entry(node.clone()).or_insert(0);
+            *entry = (*entry).max(*n);
+        }
+        for (node, n) in &other.decrements {
+            let entry = self.decrements.entry(node.clone()).or_insert(0);
+            *entry = (*entry).max(*n);
+        }
+    }
+}
@@ -12,6 +12,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{Duration, SystemTime};
+
+pub mod crdt;
+
+pub use crdt::PNCounter;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[derive(Default)]
 pub enum ConsistencyLevel {
@@ -186,6 +190,11 @@ impl VectorClock {
         }
     }
 
+    /// 读取指定节点的时钟值（缺省为 0）
+    pub fn get(&self, node_id: &str) -> u64 {
+        self.clocks.get(node_id).copied().unwrap_or(0)
+    }
+
     /// 为指定节点增加时钟值
     pub fn increment(&mut self, node_id: &str) {
         let entry = self.clocks.entry(node_id.to_string()).or_insert(0);
@@ -224,6 +233,19 @@ impl VectorClock {
         strictly_less
     }
 
+    /// 检查是否在每个分量上都不小于另一个向量时钟（相等或在其之后）
+    pub fn dominates(&self, other: &VectorClock) -> bool {
+        other
+            .clocks
+            .iter()
+            .all(|(node_id, &other_clock)| self.get(node_id) >= other_clock)
+    }
+
+    /// 遍历非缺省的 (节点, 时钟值)
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.clocks.iter().map(|(node_id, &clock)| (node_id.as_str(), clock))
+    }
+
     /// 检查是否并发（既不在前也不在后，且不相等）
     pub fn is_concurrent(&self, other: &VectorClock) -> bool {
         !self.happens_before(other) && !other.happens_before(self) && !self.is_equal(other)
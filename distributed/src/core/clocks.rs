@@ -0,0 +1,213 @@
+//! 因果屏障与仲裁时钟
+//!
+//! 设计意图：
+//! - 某些算法要求节点在观察到某一时刻之前的全部因果事件后才能继续（如读取依赖于另一节点
+//!   写入的数据前，先等本地应用追上该写入）。`CausalBarrier` 以一个 `VectorClock` 作为屏障
+//!   时间戳，本地视图在每个分量上都不小于它（相等或在其之后）即视为已越过屏障。
+//! - `wait_for_causal_barrier` 按 `poll_interval` 轮询共享的本地视图，超时返回
+//!   `DeadlineExceeded`，其中列出仍落后的分量，便于定位缺失的是哪个节点的事件。
+//! - 单个节点的墙钟可能偏斜，基于时间的决策（租约到期、TTL）不应只信本地时钟。
+//!   `QuorumClock` 并发询问所有副本的墙钟，凑齐多数派应答后取中位数作为时间，
+//!   应答的极差作为不确定度；不确定度超过上限时拒绝给出时间，而不是返回一个可疑的值。
+//!   每次询问的超时由 `TimeTransport` 负责，超时即为该节点的失败应答。
+//!
+//! 不变量（草图）：
+//! - 返回 `Ok` 时，返回前最后一次读取的本地视图支配屏障时间戳。
+//! - 空屏障总是立即满足。
+//! - `QuorumTime::median_ms` 是某个副本的实际读数，且
+//!   `uncertainty_ms ≤ max_uncertainty_ms`。
+
+use crate::consistency::VectorClock;
+#[cfg(feature = "runtime-tokio")]
+use crate::core::errors::DistributedError;
+#[cfg(feature = "runtime-tokio")]
+use crate::swim::TransportError;
+#[cfg(feature = "runtime-tokio")]
+use std::future::Future;
+#[cfg(feature = "runtime-tokio")]
+use std::pin::Pin;
+#[cfg(feature = "runtime-tokio")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "runtime-tokio")]
+use std::task::Poll;
+use std::time::Duration;
+use thiserror::Error;
+
+/// 等待因果屏障超时
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("causal barrier not reached within {timeout:?}, behind on {missing:?}")]
+pub struct DeadlineExceeded {
+    pub timeout: Duration,
+    /// 超时时仍落后的分量：(节点, 本地值, 屏障值)
+    pub missing: Vec<(String, u64, u64)>,
+}
+
+impl From<DeadlineExceeded> for crate::core::errors::DistributedError {
+    fn from(err: DeadlineExceeded) -> Self {
+        crate::core::errors::DistributedError::Network(err.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CausalBarrier {
+    timestamp: VectorClock,
+}
+
+impl CausalBarrier {
+    pub fn new(timestamp: VectorClock) -> Self {
+        Self { timestamp }
+    }
+
+    pub fn timestamp(&self) -> &VectorClock {
+        &self.timestamp
+    }
+
+    pub fn is_passed(&self, local: &VectorClock) -> bool {
+        local.dominates(&self.timestamp)
+    }
+
+    /// 本地视图仍落后于屏障的分量，按节点排序
+    pub fn missing(&self, local: &VectorClock) -> Vec<(String, u64, u64)> {
+        let mut missing: Vec<_> = self
+            .timestamp
+            .iter()
+            .filter(|(node, clock)| local.get(node) < *clock)
+            .map(|(node, clock)| (node.to_string(), local.get(node), clock))
+            .collect();
+        missing.sort();
+        missing
+    }
+
+    /// 等待 `local_view` 越过屏障，见 [`wait_for_causal_barrier`]
+    #[cfg(feature = "runtime-tokio")]
+    pub async fn wait(
+        &self,
+        local_view: Arc<Mutex<VectorClock>>,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<(), DeadlineExceeded> {
+        wait_for_causal_barrier(&self.timestamp, local_view, poll_interval, timeout).await
+    }
+}
+
+/// 轮询 `local_view` 直到其支配 `barrier`；超过 `timeout` 返回 `DeadlineExceeded`
+#[cfg(feature = "runtime-tokio")]
+pub async fn wait_for_causal_barrier(
+    barrier: &VectorClock,
+    local_view: Arc<Mutex<VectorClock>>,
+    poll_interval: Duration,
+    timeout: Duration,
+) -> Result<(), DeadlineExceeded> {
+    let barrier = CausalBarrier::new(barrier.clone());
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let missing = barrier.missing(&local_view.lock().unwrap());
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let now = tokio::time::Instant::now();
+        if now >= deadline {
+            return Err(DeadlineExceeded { timeout, missing });
+        }
+        tokio::time::sleep(poll_interval.min(deadline - now)).await;
+    }
+}
+
+#[cfg(feature = "runtime-tokio")]
+pub type TimeFuture<'a> = Pin<Box<dyn Future<Output = Result<u64, TransportError>> + Send + 'a>>;
+
+/// 读取单个节点的墙钟（Unix 毫秒）
+#[cfg(feature = "runtime-tokio")]
+pub trait TimeTransport: Send + Sync {
+    fn get_time<'a>(&'a self, node: &'a str) -> TimeFuture<'a>;
+}
+
+/// 多数派协商出的时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuorumTime {
+    /// 应答读数的中位数（偶数个应答时取较小的一个）
+    pub median_ms: u64,
+    /// 应答读数的极差：最大值减最小值
+    pub uncertainty_ms: u64,
+    /// 参与计算的应答数
+    pub responses: usize,
+}
+
+/// 按多数派协商墙钟时间，不确定度超过 `max_uncertainty_ms` 时拒绝
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuorumClock {
+    max_uncertainty_ms: u64,
+}
+
+impl QuorumClock {
+    pub fn new(max_uncertainty_ms: u64) -> Self {
+        Self { max_uncertainty_ms }
+    }
+
+    pub fn max_uncertainty_ms(&self) -> u64 {
+        self.max_uncertainty_ms
+    }
+
+    /// 并发询问 `nodes` 的墙钟，等全部应答（或失败）后要求成功的应答达到多数派
+    #[cfg(feature = "runtime-tokio")]
+    pub async fn query_time(
+        &self,
+        nodes: &[String],
+        transport: &dyn TimeTransport,
+    ) -> Result<QuorumTime, DistributedError> {
+        if nodes.is_empty() {
+            return Err(DistributedError::Configuration(
+                "quorum clock needs at least one node".to_string(),
+            ));
+        }
+        let quorum = nodes.len() / 2 + 1;
+        let mut in_flight: Vec<(&str, TimeFuture<'_>)> = nodes
+            .iter()
+            .map(|node| (node.as_str(), transport.get_time(node)))
+            .collect();
+        let mut times = Vec::with_capacity(nodes.len());
+        let mut errors = Vec::new();
+        std::future::poll_fn(|cx| {
+            let mut i = 0;
+            while i < in_flight.len() {
+                let Poll::Ready(result) = in_flight[i].1.as_mut().poll(cx) else {
+                    i += 1;
+                    continue;
+                };
+                let (node, _) = in_flight.swap_remove(i);
+                match result {
+                    Ok(ms) => times.push(ms),
+                    Err(e) => errors.push(format!("{node}: {e}")),
+                }
+            }
+            if in_flight.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        if times.len() < quorum {
+            return Err(DistributedError::Network(format!(
+                "time quorum not reached: {} of {} nodes answered, need {quorum} ({})",
+                times.len(),
+                nodes.len(),
+                errors.join("; ")
+            )));
+        }
+        times.sort_unstable();
+        let time = QuorumTime {
+            median_ms: times[(times.len() - 1) / 2],
+            uncertainty_ms: times[times.len() - 1] - times[0],
+            responses: times.len(),
+        };
+        if time.uncertainty_ms > self.max_uncertainty_ms {
+            return Err(DistributedError::InvalidState(format!(
+                "quorum clock uncertainty {}ms exceeds {}ms",
+                time.uncertainty_ms, self.max_uncertainty_ms
+            )));
+        }
+        Ok(time)
+    }
+}
@@ -1,10 +1,18 @@
 //! 分布式系统配置模块
 
+use crate::core::errors::DistributedError;
+use crate::storage::fsck::{Fsck, FsckPolicy, FsckReport};
+use std::path::PathBuf;
+
 /// 分布式系统配置
 #[derive(Debug, Clone)]
 pub struct DistributedConfig {
     pub nodes: Vec<String>,
     pub replication_factor: usize,
+    /// 节点的持久化数据目录；未设置时不做启动前检查
+    pub data_dir: Option<PathBuf>,
+    /// 启动前完整性检查的策略
+    pub fsck: FsckPolicy,
 }
 
 impl Default for DistributedConfig {
@@ -12,6 +20,28 @@ impl Default for DistributedConfig {
         Self {
             nodes: Vec::new(),
             replication_factor: 3,
+            data_dir: None,
+            fsck: FsckPolicy::default(),
         }
     }
 }
+
+impl DistributedConfig {
+    pub fn with_data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_fsck(mut self, policy: FsckPolicy) -> Self {
+        self.fsck = policy;
+        self
+    }
+
+    /// 节点启动前调用：按 `fsck` 策略检查（并按需修复）数据目录，发现超过阈值时返回错误
+    pub fn preflight(&self) -> Result<Option<FsckReport>, DistributedError> {
+        self.data_dir
+            .as_ref()
+            .map(|dir| Fsck::for_data_dir(dir).preflight(&self.fsck))
+            .transpose()
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -12,4 +13,87 @@ pub enum DistributedError {
     Storage(String),
     #[error("invalid state: {0}")]
     InvalidState(String),
+    /// 过载：请求被显式拒绝或丢弃，调用方可稍后重试
+    #[error("overloaded: {0}")]
+    Overloaded(String),
+    /// 背压：下游积压达到上限，调用方应在积压回落后重试
+    #[error("back pressure: {0}")]
+    BackPressure(String),
+    /// 租户配额超限：写入被拒绝，读取与删除不受影响
+    #[error("quota exceeded: tenant {tenant} {resource}")]
+    QuotaExceeded { tenant: String, resource: String },
+    /// 失去写仲裁连通性：本节点只能到达 `reachable` 个副本，写入需要 `required` 个
+    #[error("quorum unavailable: {reachable} of {required} required replicas reachable")]
+    QuorumUnavailable { reachable: usize, required: usize },
+}
+
+impl DistributedError {
+    /// 变体名，用于按错误类别统计
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DistributedError::Configuration(_) => "Configuration",
+            DistributedError::Network(_) => "Network",
+            DistributedError::Consensus(_) => "Consensus",
+            DistributedError::Storage(_) => "Storage",
+            DistributedError::InvalidState(_) => "InvalidState",
+            DistributedError::Overloaded(_) => "Overloaded",
+            DistributedError::BackPressure(_) => "BackPressure",
+            DistributedError::QuotaExceeded { .. } => "QuotaExceeded",
+            DistributedError::QuorumUnavailable { .. } => "QuorumUnavailable",
+        }
+    }
+}
+
+/// 等待在截止时间前未满足
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("timed out after {0:?}")]
+pub struct TimeoutError(pub Duration);
+
+impl From<TimeoutError> for DistributedError {
+    fn from(err: TimeoutError) -> Self {
+        DistributedError::Network(err.to_string())
+    }
+}
+
+/// gRPC 状态到库错误的映射：参数类 → Configuration，状态类 → InvalidState，
+/// 数据类 → Storage，资源耗尽 → Overloaded，其余（不可达、超时、取消等）→ Network
+#[cfg(feature = "grpc")]
+impl From<tonic::Status> for DistributedError {
+    fn from(status: tonic::Status) -> Self {
+        use tonic::Code;
+        let message = format!("{:?}: {}", status.code(), status.message());
+        match status.code() {
+            Code::InvalidArgument => DistributedError::Configuration(message),
+            Code::FailedPrecondition
+            | Code::Aborted
+            | Code::AlreadyExists
+            | Code::NotFound
+            | Code::OutOfRange => DistributedError::InvalidState(message),
+            Code::DataLoss | Code::Internal => DistributedError::Storage(message),
+            Code::ResourceExhausted => DistributedError::Overloaded(message),
+            _ => DistributedError::Network(message),
+        }
+    }
+}
+
+#[cfg(feature = "grpc")]
+impl From<DistributedError> for tonic::Status {
+    fn from(err: DistributedError) -> Self {
+        match err {
+            DistributedError::Configuration(m) => tonic::Status::invalid_argument(m),
+            DistributedError::Network(m) => tonic::Status::unavailable(m),
+            DistributedError::Consensus(m) => tonic::Status::aborted(m),
+            DistributedError::Storage(m) => tonic::Status::internal(m),
+            DistributedError::InvalidState(m) => tonic::Status::failed_precondition(m),
+            DistributedError::Overloaded(m) | DistributedError::BackPressure(m) => {
+                tonic::Status::resource_exhausted(m)
+            }
+            e @ DistributedError::QuotaExceeded { .. } => {
+                tonic::Status::resource_exhausted(e.to_string())
+            }
+            e @ DistributedError::QuorumUnavailable { .. } => {
+                tonic::Status::unavailable(e.to_string())
+            }
+        }
+    }
 }
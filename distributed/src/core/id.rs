@@ -0,0 +1,246 @@
+//! 基于 Raft 复制计数器的单调整数 ID 服务
+//!
+//! 设计意图：
+//! - 发票号、事务号等需要全局有序、无随机性的 ID。`MonotoneIdService` 把每次取号写成一条
+//!   `IncrementCommand` 日志，经 `MinimalRaft` 复制提交后由 `CounterStateMachine` 应用；
+//!   取到的 ID 是该条目应用后的计数值，批量取号返回 `[start, start + count)`。
+//! - 计数值只由日志顺序决定，各副本应用同一日志得到相同的值。领导者切换后，新领导者已含
+//!   全部已提交条目（选举限制），其后的条目只会得到更大的值。
+//! - 取号者在提议前按“预期索引 + 当前任期”登记；应用时若该索引上的条目任期不同（旧领导者的
+//!   未提交条目被覆盖），取号失败而不会拿到别人的值。
+//!
+//! 不变量（草图）：
+//! - 同一计数值至多被一个取号者拿到；成功返回的 ID 随提交顺序严格递增。
+//! - 失败的取号可能仍在之后被提交，只会在 ID 序列中留下空洞，不会产生重复。
+
+use crate::consensus::raft::{
+    LogEntry, LogIndex, MinimalRaft, RaftNode, RaftState, RaftTransport, StateMachine, Term,
+};
+use crate::core::errors::DistributedError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// 应用队列的默认容量
+pub const DEFAULT_APPLY_CAPACITY: usize = 1024;
+
+/// 计数器增量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IncrementCommand {
+    pub by: u64,
+}
+
+type Waiter = (Term, oneshot::Sender<Result<u64, DistributedError>>);
+
+#[derive(Default)]
+struct CounterState {
+    value: u64,
+    last_index: u64,
+    waiters: HashMap<u64, Waiter>,
+}
+
+/// 计数器状态机：按日志顺序累加增量，并把应用后的值交给登记在该索引上的取号者
+#[derive(Clone, Default)]
+pub struct CounterStateMachine {
+    state: Arc<Mutex<CounterState>>,
+}
+
+impl CounterStateMachine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 已应用的计数值
+    pub fn value(&self) -> u64 {
+        self.state.lock().unwrap().value
+    }
+
+    /// 已应用的最大日志索引
+    pub fn last_index(&self) -> LogIndex {
+        LogIndex(self.state.lock().unwrap().last_index)
+    }
+
+    fn register(
+        &self,
+        index: LogIndex,
+        term: Term,
+    ) -> oneshot::Receiver<Result<u64, DistributedError>> {
+        let (tx, rx) = oneshot::channel();
+        self.state
+            .lock()
+            .unwrap()
+            .waiters
+            .insert(index.0, (term, tx));
+        rx
+    }
+
+    fn cancel(&self, index: LogIndex) {
+        self.state.lock().unwrap().waiters.remove(&index.0);
+    }
+}
+
+impl StateMachine<IncrementCommand> for CounterStateMachine {
+    async fn apply(&mut self, entry: LogEntry<IncrementCommand>) {
+        let mut state = self.state.lock().unwrap();
+        state.value += entry.command.by;
+        state.last_index = entry.index.0;
+        if let Some((term, tx)) = state.waiters.remove(&entry.index.0) {
+            let result = if term == entry.term {
+                Ok(state.value)
+            } else {
+                Err(DistributedError::Consensus(format!(
+                    "entry {} was overwritten by term {}",
+                    entry.index.0, entry.term.0
+                )))
+            };
+            let _ = tx.send(result);
+        }
+    }
+}
+
+pub struct MonotoneIdService<T> {
+    id: String,
+    raft: Arc<Mutex<MinimalRaft<IncrementCommand>>>,
+    transport: T,
+    peers: Vec<String>,
+    counter: CounterStateMachine,
+}
+
+impl<T: RaftTransport<IncrementCommand>> MonotoneIdService<T> {
+    /// 在 `raft` 上启动计数器的应用队列；须在 tokio 运行时内调用
+    pub fn new<I, S>(
+        id: impl Into<String>,
+        raft: Arc<Mutex<MinimalRaft<IncrementCommand>>>,
+        transport: T,
+        peers: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let counter = CounterStateMachine::new();
+        raft.lock()
+            .unwrap()
+            .start_apply_queue(DEFAULT_APPLY_CAPACITY, counter.clone());
+        Self {
+            id: id.into(),
+            raft,
+            transport,
+            peers: peers.into_iter().map(Into::into).collect(),
+            counter,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn raft(&self) -> &Arc<Mutex<MinimalRaft<IncrementCommand>>> {
+        &self.raft
+    }
+
+    pub fn counter(&self) -> &CounterStateMachine {
+        &self.counter
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.raft.lock().unwrap().state() == RaftState::Leader
+    }
+
+    /// 发起一轮选举；得到多数票则成为领导者
+    pub async fn campaign(&self) -> Result<bool, DistributedError> {
+        let req = self.raft.lock().unwrap().start_election(&self.id);
+        let mut granted = 1;
+        for peer in &self.peers {
+            let Ok(resp) = self.transport.request_vote(peer, req.clone()).await else {
+                continue;
+            };
+            let mut raft = self.raft.lock().unwrap();
+            if raft.observe_term(resp.term) {
+                return Ok(false);
+            }
+            if resp.vote_granted && resp.term == req.term {
+                granted += 1;
+            }
+        }
+        let mut raft = self.raft.lock().unwrap();
+        let cluster = self.peers.len() + 1;
+        let need = cluster / 2 + 1;
+        if granted < need || raft.state() != RaftState::Candidate || raft.current_term() != req.term
+        {
+            return Ok(false);
+        }
+        raft.become_leader(self.peers.iter().cloned());
+        Ok(true)
+    }
+
+    /// 向每个对等节点发送一轮 `AppendEntries`（兼作心跳），返回成功的应答数
+    pub async fn replicate(&self) -> usize {
+        let mut acked = 0;
+        for peer in &self.peers {
+            let req = {
+                let raft = self.raft.lock().unwrap();
+                if raft.state() != RaftState::Leader {
+                    return acked;
+                }
+                raft.append_entries_for(peer, &self.id)
+            };
+            let last_sent = LogIndex(req.prev_log_index.0 + req.entries.len() as u64);
+            let Ok(resp) = self.transport.append_entries(peer, req).await else {
+                continue;
+            };
+            acked += usize::from(resp.success);
+            self.raft
+                .lock()
+                .unwrap()
+                .handle_append_entries_resp(peer, last_sent, &resp);
+        }
+        acked
+    }
+
+    /// 取一个 ID：应用后的计数值
+    pub async fn next_id(&self) -> Result<u64, DistributedError> {
+        self.increment(1).await
+    }
+
+    /// 取 `count` 个连续的 ID
+    pub async fn batch_ids(&self, count: u64) -> Result<Range<u64>, DistributedError> {
+        if count == 0 {
+            return Err(DistributedError::Configuration(
+                "batch_ids count must be positive".to_string(),
+            ));
+        }
+        let end = self.increment(count).await?;
+        Ok(end - count + 1..end + 1)
+    }
+
+    async fn increment(&self, by: u64) -> Result<u64, DistributedError> {
+        let (index, applied) = {
+            let mut raft = self.raft.lock().unwrap();
+            if raft.state() != RaftState::Leader {
+                return Err(DistributedError::InvalidState("not the leader".to_string()));
+            }
+            // 先登记再提议：单节点集群在 `propose` 内即提交并入队应用
+            let index = LogIndex(raft.last_log_index().0 + 1);
+            let applied = self.counter.register(index, raft.current_term());
+            if let Err(e) = raft.propose(IncrementCommand { by }) {
+                self.counter.cancel(index);
+                return Err(e);
+            }
+            (index, applied)
+        };
+        self.replicate().await;
+        if self.raft.lock().unwrap().commit_index().0 < index.0 {
+            self.counter.cancel(index);
+            return Err(DistributedError::Consensus(format!(
+                "entry {} not committed by a quorum",
+                index.0
+            )));
+        }
+        applied.await.map_err(|_| {
+            DistributedError::InvalidState("counter state machine stopped".to_string())
+        })?
+    }
+}
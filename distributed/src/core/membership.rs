@@ -1,14 +1,301 @@
-use serde::{Deserialize, Serialize};
+//! 集群节点标识、成员视图与纪元
+//!
+//! 设计意图：
+//! - `ClusterNodeId` 是 UUID：`generate` 随机生成（v4）；地址稳定的机器可用
+//!   `from_components(地址, 启动时间)` 确定性派生（v5，SHA-1），同一进程重启前后
+//!   得到的 ID 可由部署方复算。文本形式为小写连字符格式，传输层以 `as_str` 寻址节点。
+//! - 成员集合相同的两个视图可能来自不同的变更历史；`ClusterEpoch` 在每次生效的加入或
+//!   离开时加一，消费者据此区分视图新旧。
+//! - 每个成员记录最近一次变更的纪元与是否在集群中（离开留下墓碑），`merge` 按条目取
+//!   纪元较高的一侧，纪元相同时加入优先。
+//! - 纪元通知在克隆间共享：把视图的克隆交给其他线程后，可用 `wait_for_epoch` 等待
+//!   原视图推进到目标纪元。
+//! - `ReplicaRole` 区分副本的职责：投票者保存数据并计入仲裁；学习者（learner）只复制数据，
+//!   不计入写确认与选举；见证者（witness）只保存元数据，计入写确认但从不服务读取。
+//!
+//! 不变量（草图）：
+//! - 纪元单调不减；`merge` 后的纪元为两侧较大者。
+//! - `ClusterNodeId` 的文本形式与 UUID 一一对应：`as_str` 的结果经 `parse` 解析回原值。
 
-pub type ClusterNodeId = String;
+use super::errors::TimeoutError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// UUID 文本形式的长度（小写连字符格式）
+const NODE_ID_TEXT_LEN: usize = uuid::fmt::Hyphenated::LENGTH;
+
+/// 集群节点标识
+///
+/// 同时保存 UUID 与其规范文本，`as_str` 无需分配；比较、排序与哈希均由 UUID 决定。
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClusterNodeId {
+    uuid: Uuid,
+    text: [u8; NODE_ID_TEXT_LEN],
+}
+
+impl ClusterNodeId {
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        let mut text = [0; NODE_ID_TEXT_LEN];
+        uuid.as_hyphenated().encode_lower(&mut text);
+        Self { uuid, text }
+    }
+
+    /// 随机生成（UUID v4）
+    pub fn generate() -> Self {
+        Self::from_uuid(Uuid::new_v4())
+    }
+
+    /// 由节点地址与启动时间（纳秒）确定性派生（UUID v5，URL 命名空间）
+    pub fn from_components(node_addr: SocketAddr, boot_time_ns: u64) -> Self {
+        let name = format!("node://{node_addr}/{boot_time_ns}");
+        Self::from_uuid(Uuid::new_v5(&Uuid::NAMESPACE_URL, name.as_bytes()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.text).expect("hyphenated UUID is ASCII")
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+}
+
+/// 解析文本形式；接受 `uuid` 支持的各种格式，规范化为小写连字符格式
+impl FromStr for ClusterNodeId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s).map(Self::from_uuid)
+    }
+}
+
+impl AsRef<str> for ClusterNodeId {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<Uuid> for ClusterNodeId {
+    fn from(uuid: Uuid) -> Self {
+        Self::from_uuid(uuid)
+    }
+}
+
+impl fmt::Display for ClusterNodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for ClusterNodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ClusterNodeId({})", self.as_str())
+    }
+}
+
+impl Serialize for ClusterNodeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ClusterNodeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 副本在复制组中的角色
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReplicaRole {
+    #[default]
+    Voter,
+    /// 非投票副本：接收复制，不计入写确认与选举仲裁，可被提升为投票者
+    Learner,
+    /// 只保存元数据的副本：计入写确认，从不服务读取
+    Witness,
+}
+
+impl ReplicaRole {
+    /// 确认是否计入写仲裁
+    pub fn counts_for_writes(self) -> bool {
+        matches!(self, ReplicaRole::Voter | ReplicaRole::Witness)
+    }
+
+    /// 是否保存完整数据、可以服务读取
+    pub fn serves_reads(self) -> bool {
+        matches!(self, ReplicaRole::Voter | ReplicaRole::Learner)
+    }
+}
+
+/// 成员视图的纪元
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct ClusterEpoch(pub u64);
+
+/// 某成员最近一次变更
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct MemberChange {
+    epoch: ClusterEpoch,
+    present: bool,
+}
+
+/// 克隆间共享的纪元通知
+#[derive(Default, Clone)]
+struct EpochSignal(Arc<(Mutex<ClusterEpoch>, Condvar)>);
+
+impl EpochSignal {
+    fn publish(&self, epoch: ClusterEpoch) {
+        let (current, changed) = &*self.0;
+        let mut current = current.lock().unwrap();
+        if epoch > *current {
+            *current = epoch;
+            changed.notify_all();
+        }
+    }
+}
+
+impl std::fmt::Debug for EpochSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EpochSignal")
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ClusterMembership {
     pub nodes: Vec<ClusterNodeId>,
+    #[serde(default)]
+    pub epoch: ClusterEpoch,
+    #[serde(default)]
+    changes: BTreeMap<ClusterNodeId, MemberChange>,
+    #[serde(skip)]
+    signal: EpochSignal,
+}
+
+/// 比较成员、纪元与变更记录，不比较通知句柄
+impl PartialEq for ClusterMembership {
+    fn eq(&self, other: &Self) -> bool {
+        self.nodes == other.nodes && self.epoch == other.epoch && self.changes == other.changes
+    }
 }
 
+impl Eq for ClusterMembership {}
+
 impl ClusterMembership {
-    pub fn is_member(&self, node: &ClusterNodeId) -> bool {
-        self.nodes.iter().any(|n| n == node)
+    /// 以给定成员作为纪元 0 的初始视图
+    pub fn new(nodes: Vec<ClusterNodeId>) -> Self {
+        Self {
+            nodes,
+            ..Self::default()
+        }
+    }
+
+    /// 按文本形式比较，传输层的字符串节点地址可直接查询
+    pub fn is_member(&self, node: impl AsRef<str>) -> bool {
+        let node = node.as_ref();
+        self.nodes.iter().any(|n| n.as_str() == node)
+    }
+
+    pub fn current_epoch(&self) -> ClusterEpoch {
+        self.epoch
+    }
+
+    /// 加入成员并推进纪元；已是成员时不变
+    pub fn join(&mut self, node: ClusterNodeId) -> ClusterEpoch {
+        if !self.is_member(&node) {
+            self.nodes.push(node.clone());
+            self.record(node, true);
+        }
+        self.epoch
+    }
+
+    /// 移除成员并推进纪元；不是成员时不变
+    pub fn leave(&mut self, node: &ClusterNodeId) -> ClusterEpoch {
+        if let Some(pos) = self.nodes.iter().position(|n| n == node) {
+            self.nodes.remove(pos);
+            self.record(node.clone(), false);
+        }
+        self.epoch
+    }
+
+    fn record(&mut self, node: ClusterNodeId, present: bool) {
+        self.epoch = ClusterEpoch(self.epoch.0 + 1);
+        self.changes.insert(
+            node,
+            MemberChange {
+                epoch: self.epoch,
+                present,
+            },
+        );
+        self.signal.publish(self.epoch);
+    }
+
+    /// 条目的最近变更；没有变更记录的现有成员视为纪元 0 加入
+    fn change_of(&self, node: &ClusterNodeId) -> Option<MemberChange> {
+        self.changes.get(node).copied().or_else(|| {
+            self.is_member(node).then_some(MemberChange {
+                epoch: ClusterEpoch(0),
+                present: true,
+            })
+        })
+    }
+
+    /// 合并另一视图：每个成员取纪元较高一侧的状态（相同则加入优先），纪元取较大者
+    pub fn merge(&mut self, other: &ClusterMembership) {
+        let mut nodes = self.nodes.clone();
+        nodes.extend(other.nodes.iter().cloned());
+        nodes.extend(self.changes.keys().cloned());
+        nodes.extend(other.changes.keys().cloned());
+
+        let mut merged_nodes = Vec::new();
+        let mut merged_changes = BTreeMap::new();
+        for node in nodes {
+            if merged_changes.contains_key(&node) {
+                continue;
+            }
+            let winner = match (self.change_of(&node), other.change_of(&node)) {
+                (Some(a), Some(b)) if b.epoch > a.epoch || (b.epoch == a.epoch && b.present) => b,
+                (Some(a), _) => a,
+                (None, Some(b)) => b,
+                (None, None) => continue,
+            };
+            if winner.present {
+                merged_nodes.push(node.clone());
+            }
+            merged_changes.insert(node, winner);
+        }
+        self.nodes = merged_nodes;
+        self.changes = merged_changes;
+        self.epoch = self.epoch.max(other.epoch);
+        self.signal.publish(self.epoch);
+    }
+
+    /// 阻塞等待（共享通知的）视图到达 `target` 纪元
+    pub fn wait_for_epoch(
+        &self,
+        target: ClusterEpoch,
+        timeout: Duration,
+    ) -> Result<(), TimeoutError> {
+        if self.epoch >= target {
+            return Ok(());
+        }
+        let (current, changed) = &*self.signal.0;
+        let current = current.lock().unwrap();
+        let (current, _) = changed
+            .wait_timeout_while(current, timeout, |epoch| *epoch < target)
+            .unwrap();
+        if *current >= target {
+            Ok(())
+        } else {
+            Err(TimeoutError(timeout))
+        }
     }
 }
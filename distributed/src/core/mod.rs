@@ -4,14 +4,28 @@
 //! - 汇聚公共抽象（配置、错误、成员关系、拓扑、调度），供其余子系统复用。
 //! - 确保类型稳定性与向后兼容，作为外部集成的稳定入口。
 
+pub mod clocks;
 pub mod config;
 pub mod errors;
+#[cfg(feature = "runtime-tokio")]
+pub mod id;
 pub mod membership;
 pub mod topology;
 pub mod scheduling;
+pub mod sequence;
+pub mod session;
 
+pub use clocks::{CausalBarrier, DeadlineExceeded, QuorumClock, QuorumTime};
+#[cfg(feature = "runtime-tokio")]
+pub use clocks::{TimeFuture, TimeTransport};
 pub use config::DistributedConfig;
-pub use errors::DistributedError;
-pub use membership::{ClusterMembership, ClusterNodeId};
-pub use topology::{ClusterTopology, ShardId};
-pub use scheduling::{LogicalClock, TimerService};
+pub use errors::{DistributedError, TimeoutError};
+#[cfg(feature = "runtime-tokio")]
+pub use id::{CounterStateMachine, IncrementCommand, MonotoneIdService};
+pub use membership::{ClusterEpoch, ClusterMembership, ClusterNodeId, ReplicaRole};
+pub use topology::{
+    ClusterTopology, CowHashRing, NodeId, RebalancePlan, ShardCounters, ShardId, ShardMove, ShardStats,
+};
+pub use scheduling::{ClockDriftError, HlcClock, HlcTimestamp, LogicalClock, TimerService};
+pub use sequence::{SequenceGenerator, SequenceStats};
+pub use session::{ClientSession, SessionError};
@@ -1,8 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct LogicalClock {
     pub tick: u64,
 }
 
+/// 混合逻辑时钟（HLC）时间戳：物理毫秒 + 同一毫秒内的逻辑计数
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct HlcTimestamp {
+    pub physical_ms: u64,
+    pub logical: u32,
+}
+
+/// 远端时间戳超前本地物理时钟超过允许的漂移
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error(
+    "remote timestamp {remote_ms}ms is ahead of local clock {local_ms}ms by more than {max_drift_ms}ms"
+)]
+pub struct ClockDriftError {
+    pub remote_ms: u64,
+    pub local_ms: u64,
+    pub max_drift_ms: u64,
+}
+
+impl From<ClockDriftError> for crate::core::errors::DistributedError {
+    fn from(err: ClockDriftError) -> Self {
+        crate::core::errors::DistributedError::InvalidState(err.to_string())
+    }
+}
+
+/// 混合逻辑时钟：`now` 单调递增，`update` 合并远端时间戳以保持因果序
+pub struct HlcClock {
+    last: Mutex<HlcTimestamp>,
+    source: Box<dyn Fn() -> u64 + Send + Sync>,
+    max_drift: Option<Box<dyn Fn() -> u64 + Send + Sync>>,
+}
+
+impl Default for HlcClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HlcClock {
+    /// 以系统时钟（Unix 毫秒）为物理时间源
+    pub fn new() -> Self {
+        Self::with_time_source(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0)
+        })
+    }
+
+    /// 自定义物理时间源（毫秒），用于测试时钟偏斜
+    pub fn with_time_source(source: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        Self {
+            last: Mutex::new(HlcTimestamp::default()),
+            source: Box::new(source),
+            max_drift: None,
+        }
+    }
+
+    /// 允许远端时间戳超前本地物理时钟的毫秒数，每次 `try_update` 时重新读取，
+    /// 可由 `SkewMonitor::drift_limit` 按观测到的偏斜动态给出
+    pub fn with_max_drift(mut self, max_drift: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.max_drift = Some(Box::new(max_drift));
+        self
+    }
+
+    pub fn now(&self) -> HlcTimestamp {
+        let physical = (self.source)();
+        let mut last = self.last.lock().unwrap();
+        if physical > last.physical_ms {
+            *last = HlcTimestamp {
+                physical_ms: physical,
+                logical: 0,
+            };
+        } else {
+            Self::bump(&mut last);
+        }
+        *last
+    }
+
+    /// 收到远端时间戳后推进本地时钟，返回的时间戳大于本地与远端的既有值
+    pub fn update(&self, remote: HlcTimestamp) -> HlcTimestamp {
+        let physical = (self.source)();
+        let mut last = self.last.lock().unwrap();
+        let base = (*last).max(remote);
+        if physical > base.physical_ms {
+            *last = HlcTimestamp {
+                physical_ms: physical,
+                logical: 0,
+            };
+        } else {
+            *last = base;
+            Self::bump(&mut last);
+        }
+        *last
+    }
+
+    /// 同 `update`，但远端时间戳超前超过 `with_max_drift` 给出的上限时拒绝合并，本地时钟不变
+    pub fn try_update(&self, remote: HlcTimestamp) -> Result<HlcTimestamp, ClockDriftError> {
+        if let Some(max_drift) = &self.max_drift {
+            let local_ms = (self.source)();
+            let max_drift_ms = max_drift();
+            if remote.physical_ms > local_ms.saturating_add(max_drift_ms) {
+                return Err(ClockDriftError {
+                    remote_ms: remote.physical_ms,
+                    local_ms,
+                    max_drift_ms,
+                });
+            }
+        }
+        Ok(self.update(remote))
+    }
+
+    fn bump(ts: &mut HlcTimestamp) {
+        if ts.logical == u32::MAX {
+            ts.physical_ms += 1;
+            ts.logical = 0;
+        } else {
+            ts.logical += 1;
+        }
+    }
+}
+
 pub trait TimerService {
     fn after_ms(&self, ms: u64, f: impl FnOnce() + Send + 'static);
 }
@@ -0,0 +1,299 @@
+//! 集群范围的唯一 ID 生成（号段分配 / 雪花算法）
+//!
+//! 设计意图：
+//! - 需要全局唯一、大致有序的 ID，又不能依赖单点。`SequenceGenerator` 提供两种模式：
+//!   - 号段（block）：各节点经复制 KV 的 CAS（以 Quorum 级别提交的 `VersionedStore`）把
+//!     计数键推进 N，预留 `[start, start + N)` 后在本地发放，用尽时再预留下一段。
+//!   - 雪花（snowflake）：`时间戳(41 位, 毫秒) | 节点 ID(10 位) | 序号(12 位)`，时间戳取自
+//!     `HlcClock` 的物理分量。
+//! - `next_id` 的快速路径无锁：号段模式把“段代数 + 段内偏移”打包在一个原子量中，以 CAS
+//!   领取偏移，段的起点按代数放在双缓冲槽位里；只有换段时才进入互斥的慢路径。雪花模式以
+//!   CAS 推进打包的 `(时间戳, 序号)`。
+//! - 时钟回拨保护：HLC 的物理分量单调不减，回拨期间沿用上次的毫秒继续递增序号；序号用尽时
+//!   借用下一毫秒，借用超前物理时钟超过 `max_lead` 时拒绝发号，而不是无限超前。
+//!
+//! 不变量（草图）：
+//! - 号段经 CAS 互斥预留，不同节点（及同一节点重启前后）发出的 ID 不重复；节点崩溃至多
+//!   丢失其当前号段中未发出的部分。
+//! - 雪花模式下同一节点发出的 ID 严格递增；节点 ID 不同的 ID 互不相同。
+
+use crate::core::errors::DistributedError;
+use crate::core::scheduling::HlcClock;
+use crate::storage::versioned::{CasError, VersionedStore};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 雪花 ID 的节点位数
+pub const SNOWFLAKE_NODE_BITS: u32 = 10;
+/// 雪花 ID 的序号位数
+pub const SNOWFLAKE_SEQUENCE_BITS: u32 = 12;
+/// 雪花时间戳的默认纪元（2024-01-01T00:00:00Z，Unix 毫秒）
+pub const SNOWFLAKE_EPOCH_MS: u64 = 1_704_067_200_000;
+
+const SEQUENCE_MASK: u64 = (1 << SNOWFLAKE_SEQUENCE_BITS) - 1;
+const OFFSET_MASK: u64 = u32::MAX as u64;
+
+type Reserve =
+    Box<dyn Fn(u64, &SequenceStats) -> Result<Range<u64>, DistributedError> + Send + Sync>;
+
+#[derive(Debug, Default)]
+pub struct SequenceStats {
+    issued: AtomicU64,
+    blocks_reserved: AtomicU64,
+    cas_conflicts: AtomicU64,
+    borrowed_ms: AtomicU64,
+}
+
+impl SequenceStats {
+    pub fn issued(&self) -> u64 {
+        self.issued.load(Ordering::Relaxed)
+    }
+
+    /// 经 CAS 预留的号段数（含 `reserve` 直接预留的区间）
+    pub fn blocks_reserved(&self) -> u64 {
+        self.blocks_reserved.load(Ordering::Relaxed)
+    }
+
+    pub fn cas_conflicts(&self) -> u64 {
+        self.cas_conflicts.load(Ordering::Relaxed)
+    }
+
+    /// 雪花模式下因序号用尽或时钟回拨而借用的毫秒数
+    pub fn borrowed_ms(&self) -> u64 {
+        self.borrowed_ms.load(Ordering::Relaxed)
+    }
+}
+
+struct BlockState {
+    reserve: Reserve,
+    block_size: u64,
+    /// 高 32 位为段代数，低 32 位为段内下一个偏移
+    cursor: AtomicU64,
+    /// 按代数奇偶存放的段起点；换段时先写入下一代的槽位再发布代数
+    starts: [AtomicU64; 2],
+    refill: Mutex<()>,
+}
+
+struct SnowflakeState {
+    clock: HlcClock,
+    node_id: u64,
+    epoch_ms: u64,
+    max_lead_ms: u64,
+    /// 打包的 `(时间戳 << 12) | 序号`，时间戳相对 `epoch_ms`
+    last: AtomicU64,
+}
+
+enum Mode {
+    Block(BlockState),
+    Snowflake(SnowflakeState),
+}
+
+pub struct SequenceGenerator {
+    mode: Mode,
+    stats: SequenceStats,
+}
+
+impl SequenceGenerator {
+    /// 号段模式：经 `store` 中键 `key` 的 CAS 每次预留 `block_size` 个 ID（至多 2^32 - 1）。
+    /// 同一键的所有节点共享一个 ID 空间，首个 ID 为 1
+    pub fn block<S>(store: Arc<S>, key: impl Into<String>, block_size: u64) -> Self
+    where
+        S: VersionedStore<String, u64> + Send + Sync + 'static,
+    {
+        let key = key.into();
+        let reserve: Reserve =
+            Box::new(move |n, stats| reserve_range(store.as_ref(), &key, n, stats));
+        Self {
+            mode: Mode::Block(BlockState {
+                reserve,
+                block_size: block_size.clamp(1, OFFSET_MASK),
+                // 代数 0 的段为空：首次发号即进入慢路径预留
+                cursor: AtomicU64::new(OFFSET_MASK),
+                starts: [AtomicU64::new(0), AtomicU64::new(0)],
+                refill: Mutex::new(()),
+            }),
+            stats: SequenceStats::default(),
+        }
+    }
+
+    /// 雪花模式：`node_id` 须在集群内唯一且小于 2^10
+    pub fn snowflake(node_id: u16, clock: HlcClock) -> Result<Self, DistributedError> {
+        if u64::from(node_id) >> SNOWFLAKE_NODE_BITS != 0 {
+            return Err(DistributedError::Configuration(format!(
+                "snowflake node id {node_id} exceeds {SNOWFLAKE_NODE_BITS} bits"
+            )));
+        }
+        Ok(Self {
+            mode: Mode::Snowflake(SnowflakeState {
+                clock,
+                node_id: u64::from(node_id),
+                epoch_ms: SNOWFLAKE_EPOCH_MS,
+                max_lead_ms: 5_000,
+                last: AtomicU64::new(0),
+            }),
+            stats: SequenceStats::default(),
+        })
+    }
+
+    /// 雪花时间戳的纪元（Unix 毫秒）；须早于时钟的当前读数
+    pub fn with_epoch_ms(mut self, epoch_ms: u64) -> Self {
+        if let Mode::Snowflake(state) = &mut self.mode {
+            state.epoch_ms = epoch_ms;
+        }
+        self
+    }
+
+    /// 雪花时间戳允许超前物理时钟的上限（序号用尽或时钟回拨时借用未来的毫秒）
+    pub fn with_max_lead(mut self, max_lead: Duration) -> Self {
+        if let Mode::Snowflake(state) = &mut self.mode {
+            state.max_lead_ms = max_lead.as_millis() as u64;
+        }
+        self
+    }
+
+    pub fn stats(&self) -> &SequenceStats {
+        &self.stats
+    }
+
+    /// 取一个 ID
+    pub fn next_id(&self) -> Result<u64, DistributedError> {
+        Ok(self.reserve(1)?.start)
+    }
+
+    /// 取 `n` 个连续的 ID。号段模式下 `n > 1` 直接经 CAS 预留专属区间；雪花模式下区间位于
+    /// 同一毫秒内，`n` 至多为 2^12
+    pub fn reserve(&self, n: u64) -> Result<Range<u64>, DistributedError> {
+        if n == 0 {
+            return Err(DistributedError::Configuration(
+                "reserve count must be positive".to_string(),
+            ));
+        }
+        let range = match &self.mode {
+            Mode::Block(state) if n == 1 => {
+                let id = self.next_in_block(state)?;
+                id..id + 1
+            }
+            Mode::Block(state) => self.reserve_block(state, n)?,
+            Mode::Snowflake(state) => self.next_snowflake(state, n)?,
+        };
+        self.stats.issued.fetch_add(n, Ordering::Relaxed);
+        Ok(range)
+    }
+
+    fn next_in_block(&self, state: &BlockState) -> Result<u64, DistributedError> {
+        loop {
+            let cursor = state.cursor.load(Ordering::SeqCst);
+            let (generation, offset) = (cursor >> 32, cursor & OFFSET_MASK);
+            if offset < state.block_size {
+                // 先读起点再 CAS：CAS 成功说明读起点时该代仍未被替换，槽位未被覆盖
+                let start = state.starts[(generation & 1) as usize].load(Ordering::SeqCst);
+                if state
+                    .cursor
+                    .compare_exchange(cursor, cursor + 1, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+                {
+                    return Ok(start + offset);
+                }
+                continue;
+            }
+            let _guard = state.refill.lock().unwrap();
+            if state.cursor.load(Ordering::SeqCst) != cursor {
+                // 其他线程已换段
+                continue;
+            }
+            let range = self.reserve_block(state, state.block_size)?;
+            let next = (generation + 1) & OFFSET_MASK;
+            state.starts[(next & 1) as usize].store(range.start, Ordering::SeqCst);
+            state.cursor.store(next << 32, Ordering::SeqCst);
+        }
+    }
+
+    fn reserve_block(&self, state: &BlockState, n: u64) -> Result<Range<u64>, DistributedError> {
+        let range = (state.reserve)(n, &self.stats)?;
+        self.stats.blocks_reserved.fetch_add(1, Ordering::Relaxed);
+        Ok(range)
+    }
+
+    fn next_snowflake(
+        &self,
+        state: &SnowflakeState,
+        n: u64,
+    ) -> Result<Range<u64>, DistributedError> {
+        if n > SEQUENCE_MASK + 1 {
+            return Err(DistributedError::Configuration(format!(
+                "cannot reserve {n} snowflake ids within one millisecond"
+            )));
+        }
+        let now_ms = state
+            .clock
+            .now()
+            .physical_ms
+            .checked_sub(state.epoch_ms)
+            .ok_or_else(|| {
+                DistributedError::InvalidState("clock is before the snowflake epoch".to_string())
+            })?;
+        loop {
+            let last = state.last.load(Ordering::SeqCst);
+            let mut first = if now_ms > last >> SNOWFLAKE_SEQUENCE_BITS {
+                now_ms << SNOWFLAKE_SEQUENCE_BITS
+            } else {
+                last + 1
+            };
+            if (first & SEQUENCE_MASK) + n > SEQUENCE_MASK + 1 {
+                first = ((first >> SNOWFLAKE_SEQUENCE_BITS) + 1) << SNOWFLAKE_SEQUENCE_BITS;
+            }
+            let ts = first >> SNOWFLAKE_SEQUENCE_BITS;
+            if ts > now_ms + state.max_lead_ms {
+                return Err(DistributedError::Overloaded(format!(
+                    "snowflake timestamp would lead the clock by {}ms",
+                    ts - now_ms
+                )));
+            }
+            let end = first + n - 1;
+            if state
+                .last
+                .compare_exchange(last, end, Ordering::SeqCst, Ordering::SeqCst)
+                .is_err()
+            {
+                continue;
+            }
+            if ts > now_ms && ts > last >> SNOWFLAKE_SEQUENCE_BITS {
+                self.stats.borrowed_ms.fetch_add(1, Ordering::Relaxed);
+            }
+            let node = state.node_id << SNOWFLAKE_SEQUENCE_BITS;
+            let id = (ts << (SNOWFLAKE_NODE_BITS + SNOWFLAKE_SEQUENCE_BITS))
+                | node
+                | (first & SEQUENCE_MASK);
+            return Ok(id..id + n);
+        }
+    }
+}
+
+/// 把计数键从 `next` 推进到 `next + n`，返回预留的区间；冲突时重读重试
+fn reserve_range<S>(
+    store: &S,
+    key: &String,
+    n: u64,
+    stats: &SequenceStats,
+) -> Result<Range<u64>, DistributedError>
+where
+    S: VersionedStore<String, u64>,
+{
+    let mut current = store.get(key);
+    loop {
+        let (next, expected) = current.map_or((1, None), |(next, version)| (next, Some(version)));
+        let end = next
+            .checked_add(n)
+            .ok_or_else(|| DistributedError::InvalidState(format!("sequence {key} exhausted")))?;
+        match store.put_if_version(key.clone(), end, expected) {
+            Ok(_) => return Ok(next..end),
+            Err(CasError::Conflict { .. }) => {
+                stats.cas_conflicts.fetch_add(1, Ordering::Relaxed);
+                current = store.get(key);
+            }
+            Err(CasError::Storage(e)) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! 客户端会话保证
+//!
+//! 设计意图：
+//! - 客户端可能先后连到不同副本；会话记录自己写入/读到的最大时间戳，
+//!   只允许从水位（已应用的最大写时间戳）不低于该时间戳的副本读取。
+//! - read-your-writes：副本水位 ≥ `last_write_timestamp`；
+//!   monotonic reads：副本水位 ≥ `last_read_timestamp`。
+//!
+//! 时间戳由写入方（如 `LocalReplicator`）分配，单调递增；0 表示尚未读写。
+
+use crate::core::errors::DistributedError;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SessionError {
+    #[error("replica watermark {watermark} is behind session write {required}")]
+    ReadYourWrites { watermark: u64, required: u64 },
+    #[error("replica watermark {watermark} is behind session read {required}")]
+    MonotonicReads { watermark: u64, required: u64 },
+}
+
+impl From<SessionError> for DistributedError {
+    fn from(e: SessionError) -> Self {
+        DistributedError::InvalidState(e.to_string())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClientSession {
+    pub last_write_timestamp: u64,
+    pub last_read_timestamp: u64,
+}
+
+impl ClientSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 副本水位低于本会话最近一次写入时返回错误
+    pub fn ensure_read_your_writes(&self, replica_watermark: u64) -> Result<(), SessionError> {
+        if replica_watermark < self.last_write_timestamp {
+            return Err(SessionError::ReadYourWrites {
+                watermark: replica_watermark,
+                required: self.last_write_timestamp,
+            });
+        }
+        Ok(())
+    }
+
+    /// 副本水位低于本会话已读到的状态时返回错误
+    pub fn ensure_monotonic_reads(&self, replica_watermark: u64) -> Result<(), SessionError> {
+        if replica_watermark < self.last_read_timestamp {
+            return Err(SessionError::MonotonicReads {
+                watermark: replica_watermark,
+                required: self.last_read_timestamp,
+            });
+        }
+        Ok(())
+    }
+
+    /// 同时检查 read-your-writes 与 monotonic reads
+    pub fn ensure_readable(&self, replica_watermark: u64) -> Result<(), SessionError> {
+        self.ensure_read_your_writes(replica_watermark)?;
+        self.ensure_monotonic_reads(replica_watermark)
+    }
+
+    pub fn record_write(&mut self, timestamp: u64) {
+        self.last_write_timestamp = self.last_write_timestamp.max(timestamp);
+    }
+
+    pub fn record_read(&mut self, timestamp: u64) {
+        self.last_read_timestamp = self.last_read_timestamp.max(timestamp);
+    }
+}
@@ -1,24 +1,207 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use super::membership::ReplicaRole;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ShardId(pub u64);
 
-#[derive(Debug, Clone)]
+/// 拓扑中的节点标识，与哈希环上的节点名一致
+pub type NodeId = String;
+
+/// 分片数与分片 → 副本节点的分配；每个分片的第一个节点为主副本
+#[derive(Debug, Clone, Default)]
 pub struct ClusterTopology {
     pub shard_count: u64,
+    pub node_for_shard: HashMap<ShardId, Vec<NodeId>>,
+    /// 参与分配的节点，按加入顺序
+    pub nodes: Vec<NodeId>,
+    /// 每个分片的副本数，至少为 1
+    pub replication_factor: usize,
+}
+
+/// 一次分片迁移：把 `shard` 在 `from` 上的副本移到 `to`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardMove {
+    pub shard: ShardId,
+    pub from: NodeId,
+    pub to: NodeId,
+}
+
+/// 节点增减后使分配重新均衡所需的迁移
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RebalancePlan {
+    pub moves: Vec<ShardMove>,
+}
+
+impl RebalancePlan {
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// 迁入 `node` 的分片
+    pub fn moves_to<'a>(&'a self, node: &'a str) -> impl Iterator<Item = &'a ShardMove> {
+        self.moves.iter().filter(move |m| m.to == node)
+    }
 }
 
 impl ClusterTopology {
+    pub fn new(shard_count: u64) -> Self {
+        Self {
+            shard_count,
+            replication_factor: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_replication_factor(mut self, replication_factor: usize) -> Self {
+        self.replication_factor = replication_factor.max(1);
+        self
+    }
+
     pub fn shards(&self) -> impl Iterator<Item = ShardId> + '_ {
         (0..self.shard_count).map(ShardId)
     }
-}
 
-use std::collections::BTreeMap;
-use std::hash::{Hash, Hasher};
+    /// 轮转分配：分片 `i` 的副本依次放在 `nodes[i]`、`nodes[i + 1]`……（下标对节点数取模）上，
+    /// 各节点承载的副本数至多相差 1；副本数超过节点数时按节点数截断
+    pub fn assign_shards(&mut self, nodes: Vec<NodeId>) -> HashMap<ShardId, Vec<NodeId>> {
+        let mut nodes = nodes;
+        let mut seen = HashSet::new();
+        nodes.retain(|n| seen.insert(n.clone()));
+        self.node_for_shard.clear();
+        if !nodes.is_empty() {
+            let replicas = self.replication_factor.clamp(1, nodes.len());
+            for shard in (0..self.shard_count).map(ShardId) {
+                let start = shard.0 as usize % nodes.len();
+                let owners = (0..replicas)
+                    .map(|r| nodes[(start + r) % nodes.len()].clone())
+                    .collect();
+                self.node_for_shard.insert(shard, owners);
+            }
+        }
+        self.nodes = nodes;
+        self.node_for_shard.clone()
+    }
+
+    /// 各节点承载的副本数；没有分片的节点计为 0
+    pub fn shards_per_node(&self) -> BTreeMap<NodeId, usize> {
+        let mut counts: BTreeMap<NodeId, usize> =
+            self.nodes.iter().map(|n| (n.clone(), 0)).collect();
+        for owners in self.node_for_shard.values() {
+            for node in owners {
+                *counts.entry(node.clone()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// 承载最多与最少副本的节点相差不超过 1
+    pub fn is_balanced(&self) -> bool {
+        let counts = self.shards_per_node();
+        match (counts.values().max(), counts.values().min()) {
+            (Some(max), Some(min)) => max - min <= 1,
+            _ => true,
+        }
+    }
+
+    /// 加入 `add_nodes`、移除 `remove_nodes` 并以最少的迁移恢复均衡，返回（已应用的）迁移计划。
+    ///
+    /// 被移除节点上的副本全部迁出；其余节点只迁出超出目标的部分（分片号大的先迁），迁入
+    /// 缺口最大且尚未持有该分片的节点。尚未分配过时只做初始分配，计划为空；移除后没有节点时拓扑不变。
+    pub fn rebalance(
+        &mut self,
+        add_nodes: Vec<NodeId>,
+        remove_nodes: Vec<NodeId>,
+    ) -> RebalancePlan {
+        let mut nodes: Vec<NodeId> = self
+            .nodes
+            .iter()
+            .filter(|n| !remove_nodes.contains(n))
+            .cloned()
+            .collect();
+        let mut added = Vec::new();
+        for node in add_nodes {
+            if !nodes.contains(&node) && !remove_nodes.contains(&node) {
+                added.push(node.clone());
+                nodes.push(node);
+            }
+        }
+        if nodes.is_empty() {
+            return RebalancePlan::default();
+        }
+        if self.node_for_shard.is_empty() {
+            self.assign_shards(nodes);
+            return RebalancePlan::default();
+        }
+        self.nodes = nodes;
+
+        // 目标：副本总数均分，余数先给新加入的节点，使迁出分摊到各原有节点上；其余余数给
+        // 当前承载最多的节点，使迁出最少（已均衡时不产生迁移）
+        let counts = self.shards_per_node();
+        let total: usize = self.node_for_shard.values().map(Vec::len).sum();
+        let mut by_load: Vec<&NodeId> = self.nodes.iter().collect();
+        by_load.sort_by_key(|n| (!added.contains(*n), std::cmp::Reverse(counts[*n])));
+        let (base, extra) = (total / self.nodes.len(), total % self.nodes.len());
+        let target: HashMap<NodeId, usize> = by_load
+            .iter()
+            .enumerate()
+            .map(|(i, n)| ((*n).clone(), base + usize::from(i < extra)))
+            .collect();
+
+        // 需要迁出的副本：被移除节点上的全部，超载节点上分片号最大的若干个
+        let mut load: HashMap<NodeId, usize> = HashMap::new();
+        let mut departing = Vec::new();
+        let mut shards: Vec<ShardId> = self.node_for_shard.keys().copied().collect();
+        shards.sort();
+        for shard in &shards {
+            for node in &self.node_for_shard[shard] {
+                let kept = load.entry(node.clone()).or_insert(0);
+                match target.get(node) {
+                    Some(limit) if *kept < *limit => *kept += 1,
+                    _ => departing.push((*shard, node.clone())),
+                }
+            }
+        }
+
+        let mut plan = RebalancePlan::default();
+        for (shard, from) in departing {
+            let owners = &self.node_for_shard[&shard];
+            let to = self
+                .nodes
+                .iter()
+                .filter(|n| !owners.contains(n))
+                .min_by_key(|n| {
+                    let held = load.get(*n).copied().unwrap_or(0);
+                    // 缺口大者优先，缺口相同时先加入的节点优先
+                    std::cmp::Reverse(target[*n] as isize - held as isize)
+                })
+                .cloned();
+            let Some(to) = to else { continue };
+            *load.entry(to.clone()).or_insert(0) += 1;
+            let owners = self.node_for_shard.get_mut(&shard).unwrap();
+            if let Some(slot) = owners.iter_mut().find(|n| **n == from) {
+                *slot = to.clone();
+            }
+            plan.moves.push(ShardMove { shard, from, to });
+        }
+        plan
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ConsistentHashRing {
     ring: BTreeMap<u64, String>,
     replicas: u32,
+    /// 节点 → 故障域（机架、可用区）
+    domains: HashMap<String, String>,
+    /// 非投票者节点的角色；未记录的节点为 `Voter`
+    roles: HashMap<String, ReplicaRole>,
 }
 
 impl ConsistentHashRing {
@@ -26,9 +209,52 @@ impl ConsistentHashRing {
         Self {
             ring: BTreeMap::new(),
             replicas,
+            domains: HashMap::new(),
+            roles: HashMap::new(),
         }
     }
 
+    /// 加入节点并记录其故障域
+    pub fn add_node_in_domain(&mut self, node: &str, domain: &str) {
+        self.add_node(node);
+        self.domains.insert(node.to_string(), domain.to_string());
+    }
+
+    /// 节点所属的故障域；未记录时为 `None`
+    pub fn domain_of(&self, node: &str) -> Option<&str> {
+        self.domains.get(node).map(String::as_str)
+    }
+
+    /// 全部已记录的 节点 → 故障域
+    pub fn domains(&self) -> &HashMap<String, String> {
+        &self.domains
+    }
+
+    /// 以指定角色加入节点
+    pub fn add_node_with_role(&mut self, node: &str, role: ReplicaRole) {
+        self.add_node(node);
+        self.set_role(node, role);
+    }
+
+    /// 变更已有节点的角色，如把学习者提升为投票者
+    pub fn set_role(&mut self, node: &str, role: ReplicaRole) {
+        if role == ReplicaRole::Voter {
+            self.roles.remove(node);
+        } else {
+            self.roles.insert(node.to_string(), role);
+        }
+    }
+
+    /// 节点的角色；未记录时为 `Voter`
+    pub fn role_of(&self, node: &str) -> ReplicaRole {
+        self.roles.get(node).copied().unwrap_or_default()
+    }
+
+    /// 全部非投票者节点的角色
+    pub fn roles(&self) -> &HashMap<String, ReplicaRole> {
+        &self.roles
+    }
+
     pub fn add_node(&mut self, node: &str) {
         for r in 0..self.replicas {
             let mut h = ahash::AHasher::default();
@@ -47,6 +273,58 @@ impl ConsistentHashRing {
         for k in keys {
             self.ring.remove(&k);
         }
+        self.domains.remove(node);
+        self.roles.remove(node);
+    }
+
+    /// 每个物理节点的虚拟节点数
+    pub fn vnodes_per_node(&self) -> u32 {
+        self.replicas
+    }
+
+    /// 环上的物理节点，按其第一个虚拟节点在环上的顺序，每个节点一项
+    pub fn distinct_nodes(&self) -> impl Iterator<Item = &str> {
+        let mut seen = std::collections::HashSet::new();
+        self.ring
+            .values()
+            .map(String::as_str)
+            .filter(move |node| seen.insert(*node))
+    }
+
+    /// 环上的虚拟节点总数
+    pub fn virtual_node_count(&self) -> usize {
+        self.ring.len()
+    }
+
+    /// `node` 实际占据的虚拟节点数
+    pub fn virtual_nodes_for(&self, node: &str) -> usize {
+        self.ring.values().filter(|n| n.as_str() == node).count()
+    }
+
+    /// 各节点实际占据的虚拟节点数（哈希碰撞时后加入者覆盖先加入者）
+    pub fn vnode_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for node in self.ring.values() {
+            *counts.entry(node.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// 各节点拥有的哈希空间占比；虚拟节点拥有 (前驱, 自身] 区间，之和为 1
+    pub fn ownership(&self) -> BTreeMap<String, f64> {
+        let mut owned: BTreeMap<String, u128> = BTreeMap::new();
+        let mut prev = self.ring.keys().next_back().copied();
+        for (&hash, node) in &self.ring {
+            let span = hash.wrapping_sub(prev.unwrap_or(hash)) as u128;
+            // 单个虚拟节点拥有整个环
+            let span = if span == 0 { 1u128 << 64 } else { span };
+            *owned.entry(node.clone()).or_insert(0) += span;
+            prev = Some(hash);
+        }
+        owned
+            .into_iter()
+            .map(|(node, span)| (node, span as f64 / (1u128 << 64) as f64))
+            .collect()
     }
 
     pub fn route<K: Hash>(&self, key: &K) -> Option<&str> {
@@ -84,4 +362,196 @@ impl ConsistentHashRing {
         }
         res
     }
+
+    /// `nodes_for` 的结果连同各节点的角色
+    pub fn nodes_for_with_roles<K: Hash>(
+        &self,
+        key: &K,
+        replicas: usize,
+    ) -> Vec<(String, ReplicaRole)> {
+        self.nodes_for(key, replicas)
+            .into_iter()
+            .map(|n| {
+                let role = self.role_of(&n);
+                (n, role)
+            })
+            .collect()
+    }
+
+    /// 键的副本中可以服务读取的节点（排除见证者），顺序同 `nodes_for`
+    pub fn read_nodes_for<K: Hash>(&self, key: &K, replicas: usize) -> Vec<String> {
+        self.nodes_for(key, replicas)
+            .into_iter()
+            .filter(|n| self.role_of(n).serves_reads())
+            .collect()
+    }
+}
+
+/// 写时复制的哈希环：读取者取当前环的 `Arc` 快照后在其上路由，不持有任何锁；
+/// 变更克隆当前环、修改后以比较并交换的方式替换，期间被其他写者抢先时基于新环重做。
+/// 因此每次路由都只看到某个完整的环版本，不会观察到迁移到一半的状态。
+#[derive(Debug)]
+pub struct CowHashRing {
+    current: RwLock<Arc<ConsistentHashRing>>,
+}
+
+impl CowHashRing {
+    pub fn new(ring: ConsistentHashRing) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(ring)),
+        }
+    }
+
+    /// 当前环的快照；读锁只在克隆 `Arc` 时持有
+    pub fn snapshot(&self) -> Arc<ConsistentHashRing> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn add_node(&self, node: &str) {
+        self.update(|ring| ring.add_node(node));
+    }
+
+    pub fn remove_node(&self, node: &str) {
+        self.update(|ring| ring.remove_node(node));
+    }
+
+    /// 以比较并交换的方式应用变更；`change` 可能因竞争而在新快照上重复执行
+    pub fn update(&self, mut change: impl FnMut(&mut ConsistentHashRing)) {
+        loop {
+            let base = self.snapshot();
+            let mut next = (*base).clone();
+            change(&mut next);
+            let mut current = self.current.write().unwrap();
+            if Arc::ptr_eq(&current, &base) {
+                *current = Arc::new(next);
+                return;
+            }
+        }
+    }
+
+    pub fn route<K: Hash>(&self, key: &K) -> Option<String> {
+        self.snapshot().route(key).map(str::to_string)
+    }
+
+    pub fn nodes_for<K: Hash>(&self, key: &K, replicas: usize) -> Vec<String> {
+        self.snapshot().nodes_for(key, replicas)
+    }
+}
+
+/// 单个分片的请求与流量计数
+#[derive(Debug, Default)]
+pub struct ShardCounters {
+    read_count: AtomicU64,
+    write_count: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+}
+
+impl ShardCounters {
+    pub fn read_count(&self) -> u64 {
+        self.read_count.load(Ordering::Relaxed)
+    }
+
+    pub fn write_count(&self) -> u64 {
+        self.write_count.load(Ordering::Relaxed)
+    }
+
+    /// 写入分片的字节数
+    pub fn bytes_in(&self) -> u64 {
+        self.bytes_in.load(Ordering::Relaxed)
+    }
+
+    /// 从分片读出的字节数
+    pub fn bytes_out(&self) -> u64 {
+        self.bytes_out.load(Ordering::Relaxed)
+    }
+
+    /// 读写请求总数
+    pub fn total_ops(&self) -> u64 {
+        self.read_count() + self.write_count()
+    }
+}
+
+/// 按分片统计请求数与字节数，用于发现热点分片。
+/// 已出现过的分片只取读锁后原子累加；首次出现的分片才取写锁登记。
+#[derive(Debug, Default)]
+pub struct ShardStats {
+    shards: RwLock<HashMap<ShardId, ShardCounters>>,
+}
+
+impl ShardStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 预先登记拓扑中的全部分片，没有请求的分片也出现在统计中
+    pub fn for_topology(topology: &ClusterTopology) -> Self {
+        Self {
+            shards: RwLock::new(
+                topology
+                    .shards()
+                    .map(|shard| (shard, ShardCounters::default()))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn record_read(&self, shard: ShardId, bytes: u64) {
+        self.record(shard, |c| {
+            c.read_count.fetch_add(1, Ordering::Relaxed);
+            c.bytes_out.fetch_add(bytes, Ordering::Relaxed);
+        });
+    }
+
+    pub fn record_write(&self, shard: ShardId, bytes: u64) {
+        self.record(shard, |c| {
+            c.write_count.fetch_add(1, Ordering::Relaxed);
+            c.bytes_in.fetch_add(bytes, Ordering::Relaxed);
+        });
+    }
+
+    /// 按读写总数降序返回前 `top_n` 个分片，总数相同时分片号小的在前
+    pub fn hottest_shards(&self, top_n: usize) -> Vec<(ShardId, u64)> {
+        let mut ops: Vec<(ShardId, u64)> = self
+            .shards
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(shard, counters)| (*shard, counters.total_ops()))
+            .collect();
+        ops.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.0.cmp(&b.0.0)));
+        ops.truncate(top_n);
+        ops
+    }
+
+    /// 对分片的计数器执行 `f`；分片未出现过时返回 `None`
+    pub fn with_counters<R>(
+        &self,
+        shard: ShardId,
+        f: impl FnOnce(&ShardCounters) -> R,
+    ) -> Option<R> {
+        self.shards.read().unwrap().get(&shard).map(f)
+    }
+
+    /// 所有分片的读写请求总数
+    pub fn total_ops(&self) -> u64 {
+        self.shards
+            .read()
+            .unwrap()
+            .values()
+            .map(ShardCounters::total_ops)
+            .sum()
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.read().unwrap().len()
+    }
+
+    fn record(&self, shard: ShardId, f: impl Fn(&ShardCounters)) {
+        if let Some(counters) = self.shards.read().unwrap().get(&shard) {
+            f(counters);
+            return;
+        }
+        f(self.shards.write().unwrap().entry(shard).or_default());
+    }
 }
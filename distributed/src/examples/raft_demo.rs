@@ -157,6 +157,8 @@ pub fn raft_snapshot_demo() -> Result<(), Box<dyn std::error::Error>> {
         offset: 0,
         data: snapshot.data.clone(),
         done: true,
+        checksum: None,
+        snapshot_hash: None,
     };
     
     let install_resp = raft.handle_install_snapshot(install_req)?;
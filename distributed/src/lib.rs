@@ -50,18 +50,27 @@ pub mod examples;
 pub mod benchmarks;
 
 // 其他实用模块
+pub mod admin;
+pub mod audit;
+#[cfg(feature = "runtime-tokio")]
+pub mod broadcast;
 pub mod cap_theorem;
 pub mod chaos;
 pub mod codec;
 pub mod config_management;
 pub mod load_balancing;
+pub mod nemesis;
 pub mod partitioning;
+pub mod resilience;
 pub mod service_discovery;
+pub mod simnet;
 pub mod swim;
+#[cfg(feature = "runtime-tokio")]
+pub mod sync;
 pub mod transactions;
 
 // 重新导出核心类型以保持向后兼容
-pub use core::{DistributedConfig, DistributedError, ClusterMembership, ClusterNodeId, ClusterTopology, ShardId, LogicalClock, TimerService};
+pub use core::{DistributedConfig, DistributedError, ClusterMembership, ClusterNodeId, ClusterTopology, ReplicaRole, ShardId, LogicalClock, TimerService};
 
 // 重新导出共识相关类型（保持向后兼容的模块名）
 pub use consensus::raft as consensus_raft;
@@ -98,7 +107,9 @@ pub use network::distributed_lock::{
 
 // 重新导出存储相关类型
 pub use storage::{LogStorage, StateMachineStorage};
-pub use storage::replication::{MajorityQuorum, QuorumPolicy, Replicator};
+pub use storage::replication::{
+    DomainAwareQuorum, MajorityQuorum, QuorumPolicy, Replicator, RoleAwareQuorum, WriteQuorum,
+};
 
 // 重新导出监控相关类型
 pub use monitoring::{
@@ -110,30 +121,41 @@ pub use monitoring::{
 // 重新导出安全相关类型
 pub use security::{
     AclManager, AclRule, Action, AuditEvent, Auditor, CircuitBreaker, CircuitConfig, CircuitState,
-    Governance, Principal, RateLimitConfig, Resource, TokenBucket,
+    Governance, KeyedRateLimiter, Principal, RateLimitConfig, Resource, TokenBucket,
 };
 
 // 重新导出其他实用类型
+#[cfg(feature = "runtime-tokio")]
+pub use broadcast::{BestEffortBroadcast, BroadcastConfig, BroadcastMessage, ReliableBroadcast};
 pub use cap_theorem::{
     CAPAnalysisReport, CAPAnalyzer, CAPManager, ConsistencyDecision, PartitionDetector,
     PartitionStats, PerformanceMetrics,
 };
 pub use chaos::{ChaosConfig, ChaosInjector};
-pub use codec::{BinaryCodec, BytesCodec, StringUtf8Codec};
+pub use codec::{BinaryCodec, BytesCodec, CodecError, StringUtf8Codec, UpcastingCodec};
 pub use config_management::{
     ConfigManager, ConfigSnapshot, ConfigSource, ConfigValue, EnvSource, FileSource, InMemorySource,
 };
 pub use load_balancing::{
-    ConsistentHashBalancer, GeographicBalancer, LeastConnectionsBalancer,
+    Balancer, ConsistentHashBalancer, GeographicBalancer, LeastConnectionsBalancer,
     LeastResponseTimeBalancer, LoadBalancerManager, LoadBalancingStrategy, RandomBalancer,
-    RoundRobinBalancer, ServerStats, WeightedRandomBalancer, WeightedRoundRobinBalancer,
+    RoundRobinBalancer, SelectionHistory, SelectionReason, SelectionRecord, ServerStats,
+    VersionAwareBalancer, WeightedRandomBalancer, WeightedRoundRobinBalancer, ZoneAwareBalancer,
 };
 pub use partitioning::{HashPartitioner, Partitioner};
+pub use resilience::{
+    AdmissionController, AdmissionDenied, AdmissionGuard, DenialReason, LoadPermit, LoadShedder,
+    RetryBreakerError, RetryWithBreaker, ShedError,
+};
 pub use service_discovery::{
-    ConfigServiceDiscovery, DiscoveryStrategy, DnsServiceDiscovery,
-    RegistryServiceDiscovery, ServiceDiscoveryConfig, ServiceDiscoveryManager, ServiceInstance,
+    AsyncHealthCheck, ConfigServiceDiscovery, DiscoveryStrategy, DnsServiceDiscovery,
+    RegistryServiceDiscovery, ServiceDiscovery, ServiceDiscoveryConfig, ServiceDiscoveryManager,
+    ServiceInstance,
 };
 pub use swim::{
-    EnhancedSwimTransport, MembershipView, SwimEvent, SwimMemberState, SwimNode, SwimTransport,
+    DisseminationBuffer, EnhancedSwimTransport, GossipEntry, MembershipView,
+    PhiAccrualFailureDetector, SwimEvent, SwimMemberState, SwimNode, SwimTransport,
 };
-pub use transactions::{Saga, SagaStep};
\ No newline at end of file
+#[cfg(feature = "runtime-tokio")]
+pub use sync::{Barrier, BarrierToken};
+pub use transactions::{Saga, SagaStep, TypedSaga, TypedSagaStep};
\ No newline at end of file
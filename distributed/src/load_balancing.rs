@@ -8,16 +8,23 @@
 //! - 一致性哈希：减少节点变更时的键迁移量（期望 O(Δ/N)）。
 //! - 最少连接/响应时间：需要持续观测 `ServerStats`，注意滑动窗口与平滑因子选择。
 //! - 加权策略：权重表示期望流量份额，需与健康度联动（避免将流量导向不健康实例）。
+//! - 就近路由：`ZoneAwareBalancer` 优先本可用区的健康实例，本区健康容量低于阈值时才按比例
+//!   溢出到其他区；一致性哈希可为每个键给出跨可用区的偏好列表，故障切换目标事先确定。
+//! - 蓝绿/金丝雀：`VersionAwareBalancer` 按 semver 找出最新与上一个部署版本，把
+//!   `canary_fraction` 的流量导向最新版本，其余导向上一个版本；比例可在发布过程中调整。
+//! - 选择历史：轮询与一致性哈希均衡器可经 `with_history(capacity)` 记录最近的选择决策
+//!   （时间、实例 ID、原因），`SelectionHistory::summarize` 按实例计数，用于排查负载不均。
 //!
 //! 参考：
 //! - Consistent Hashing 与 Jump Consistent Hash 相关论文。
 //! - NGINX/Envoy 负载均衡策略文档。
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::hash::{Hash, Hasher};
 use std::net::SocketAddr;
 // use std::sync::{Arc, RwLock}; // 暂时未使用
 use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, MutexGuard};
 use std::time::{Duration, Instant};
 
 use crate::service_discovery::ServiceInstance;
@@ -139,10 +146,81 @@ impl ServerStats {
     }
 }
 
+/// 选择原因：做出决策的均衡器类型及其输入
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionReason {
+    RoundRobin,
+    /// 请求键的哈希值
+    ConsistentHash { key_hash: u64 },
+}
+
+/// 一次选择决策
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionRecord {
+    pub timestamp: Instant,
+    pub selected_id: String,
+    pub reason: SelectionReason,
+}
+
+/// 最近 `capacity` 次选择决策，超出时丢弃最旧的记录
+#[derive(Debug, Clone)]
+pub struct SelectionHistory {
+    capacity: usize,
+    log: VecDeque<SelectionRecord>,
+}
+
+impl SelectionHistory {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            log: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, selected_id: impl Into<String>, reason: SelectionReason) {
+        if self.log.len() == self.capacity {
+            self.log.pop_front();
+        }
+        self.log.push_back(SelectionRecord {
+            timestamp: Instant::now(),
+            selected_id: selected_id.into(),
+            reason,
+        });
+    }
+
+    /// 从旧到新的记录
+    pub fn records(&self) -> impl Iterator<Item = &SelectionRecord> {
+        self.log.iter()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.log.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.log.is_empty()
+    }
+
+    /// 保留的记录中各实例被选中的次数
+    pub fn summarize(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for record in &self.log {
+            *counts.entry(record.selected_id.clone()).or_default() += 1;
+        }
+        counts
+    }
+}
+
 /// 轮询负载均衡器
 pub struct RoundRobinBalancer {
     servers: Vec<ServiceInstance>,
     current_index: usize,
+    history: Option<SelectionHistory>,
 }
 
 impl RoundRobinBalancer {
@@ -151,9 +229,20 @@ impl RoundRobinBalancer {
         Self {
             servers,
             current_index: 0,
+            history: None,
         }
     }
 
+    /// 记录最近 `capacity` 次选择
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history = Some(SelectionHistory::new(capacity));
+        self
+    }
+
+    pub fn history(&self) -> Option<&SelectionHistory> {
+        self.history.as_ref()
+    }
+
     /// 选择下一个服务器
     pub fn select_server(&mut self) -> Option<&ServiceInstance> {
         if self.servers.is_empty() {
@@ -162,6 +251,9 @@ impl RoundRobinBalancer {
 
         let server = &self.servers[self.current_index];
         self.current_index = (self.current_index + 1) % self.servers.len();
+        if let Some(history) = &mut self.history {
+            history.record(server.id.clone(), SelectionReason::RoundRobin);
+        }
         Some(server)
     }
 
@@ -296,6 +388,8 @@ pub struct ConsistentHashBalancer {
     servers: Vec<ServiceInstance>,
     virtual_nodes: usize,
     hash_ring: Vec<(u64, SocketAddr)>,
+    /// `select_server` 只借用 `&self`，历史需内部可变
+    history: Option<Mutex<SelectionHistory>>,
 }
 
 impl ConsistentHashBalancer {
@@ -305,11 +399,22 @@ impl ConsistentHashBalancer {
             servers,
             virtual_nodes,
             hash_ring: Vec::new(),
+            history: None,
         };
         balancer.build_hash_ring();
         balancer
     }
 
+    /// 记录最近 `capacity` 次选择
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history = Some(Mutex::new(SelectionHistory::new(capacity)));
+        self
+    }
+
+    pub fn history(&self) -> Option<MutexGuard<'_, SelectionHistory>> {
+        self.history.as_ref().map(|h| h.lock().unwrap())
+    }
+
     /// 构建哈希环
     fn build_hash_ring(&mut self) {
         self.hash_ring.clear();
@@ -341,15 +446,18 @@ impl ConsistentHashBalancer {
 
         let key_hash = self.hash(key);
 
-        // 找到第一个大于等于key_hash的节点
-        for (hash, address) in &self.hash_ring {
-            if *hash >= key_hash {
-                return self.servers.iter().find(|s| s.address == *address);
-            }
+        // 找到第一个大于等于key_hash的节点；如果没有找到，返回第一个节点（环的起点）
+        let selected = match self.hash_ring.iter().find(|(hash, _)| *hash >= key_hash) {
+            Some((_, address)) => self.servers.iter().find(|s| s.address == *address),
+            None => self.servers.first(),
+        };
+        if let (Some(server), Some(history)) = (selected, &self.history) {
+            history
+                .lock()
+                .unwrap()
+                .record(server.id.clone(), SelectionReason::ConsistentHash { key_hash });
         }
-
-        // 如果没有找到，返回第一个节点（环的起点）
-        self.servers.first()
+        selected
     }
 
     /// 更新服务器列表
@@ -357,6 +465,42 @@ impl ConsistentHashBalancer {
         self.servers = servers;
         self.build_hash_ring();
     }
+
+    /// 键的跨可用区偏好列表：从键的位置沿环顺时针，每个可用区取遇到的第一个实例
+    ///
+    /// 首项即主副本，其余为事先确定的故障切换目标，各项分属不同可用区（未标注可用区的实例
+    /// 视为同一区）。结果只依赖环与键，与健康状态无关。
+    pub fn zone_preference_list(&self, key: &str) -> Vec<&ServiceInstance> {
+        let start = self
+            .hash_ring
+            .partition_point(|(hash, _)| *hash < self.hash(key));
+        let mut zones: Vec<Option<&str>> = Vec::new();
+        let mut list = Vec::new();
+        for i in 0..self.hash_ring.len() {
+            let address = self.hash_ring[(start + i) % self.hash_ring.len()].1;
+            let Some(server) = self.servers.iter().find(|s| s.address == address) else {
+                continue;
+            };
+            if !zones.contains(&server.zone()) {
+                zones.push(server.zone());
+                list.push(server);
+            }
+        }
+        list
+    }
+
+    /// 按偏好列表选择：优先 `local_zone` 中的健康项，否则取列表中第一个健康项
+    pub fn select_server_zone_aware(
+        &self,
+        key: &str,
+        local_zone: &str,
+    ) -> Option<&ServiceInstance> {
+        let list = self.zone_preference_list(key);
+        list.iter()
+            .find(|s| s.is_healthy && s.zone() == Some(local_zone))
+            .or_else(|| list.iter().find(|s| s.is_healthy))
+            .copied()
+    }
 }
 
 /// 随机负载均衡器
@@ -505,6 +649,14 @@ impl LeastResponseTimeBalancer {
         }
     }
 
+    /// 用外部平滑好的估计（如心跳 RTT 的 EWMA）覆盖平均响应时间
+    pub fn set_response_time(&mut self, address: SocketAddr, response_time: Duration) {
+        if let Some(stats) = self.server_stats.get_mut(&address) {
+            stats.avg_response_time = response_time;
+            stats.last_updated = Instant::now();
+        }
+    }
+
     /// 更新服务器列表
     pub fn update_servers(&mut self, servers: Vec<ServiceInstance>) {
         self.servers = servers;
@@ -580,6 +732,305 @@ impl GeographicBalancer {
     }
 }
 
+/// 可按实例列表构建并逐次选择的负载均衡器，供 `ZoneAwareBalancer` 等包装器使用
+pub trait Balancer {
+    fn select_server(&mut self) -> Option<&ServiceInstance>;
+    fn update_servers(&mut self, servers: Vec<ServiceInstance>);
+}
+
+macro_rules! impl_balancer {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Balancer for $ty {
+                fn select_server(&mut self) -> Option<&ServiceInstance> {
+                    <$ty>::select_server(self)
+                }
+
+                fn update_servers(&mut self, servers: Vec<ServiceInstance>) {
+                    <$ty>::update_servers(self, servers)
+                }
+            }
+        )*
+    };
+}
+
+impl_balancer!(
+    RoundRobinBalancer,
+    WeightedRoundRobinBalancer,
+    LeastConnectionsBalancer,
+    RandomBalancer,
+    WeightedRandomBalancer,
+    LeastResponseTimeBalancer,
+    GeographicBalancer,
+    VersionAwareBalancer,
+);
+
+/// 可用区感知的负载均衡包装器
+///
+/// 实例按 `zone` 元数据分为本区与其他区两组，各由一个内部均衡器选择，只纳入健康实例。
+/// 本区健康实例占本区实例的比例不低于 `min_local_fraction` 时只选本区；低于阈值时按健康
+/// 实例数比例在两组间分配（确定性的配额累加，而非随机）。
+pub struct ZoneAwareBalancer<B> {
+    local_zone: String,
+    min_local_fraction: f64,
+    servers: Vec<ServiceInstance>,
+    local: B,
+    remote: B,
+    local_total: usize,
+    local_healthy: usize,
+    remote_healthy: usize,
+    /// 溢出模式下本区的累计配额
+    local_credit: f64,
+    selections: HashMap<String, u64>,
+}
+
+impl<B: Balancer> ZoneAwareBalancer<B> {
+    /// `factory` 为每组实例构建内部均衡器，如 `RoundRobinBalancer::new`；默认阈值 0.5
+    pub fn new(
+        local_zone: impl Into<String>,
+        servers: Vec<ServiceInstance>,
+        factory: impl Fn(Vec<ServiceInstance>) -> B,
+    ) -> Self {
+        let mut balancer = Self {
+            local_zone: local_zone.into(),
+            min_local_fraction: 0.5,
+            servers: Vec::new(),
+            local: factory(Vec::new()),
+            remote: factory(Vec::new()),
+            local_total: 0,
+            local_healthy: 0,
+            remote_healthy: 0,
+            local_credit: 0.0,
+            selections: HashMap::new(),
+        };
+        balancer.update_servers(servers);
+        balancer
+    }
+
+    /// 本区健康容量低于该比例时开始溢出到其他区
+    pub fn with_min_local_fraction(mut self, fraction: f64) -> Self {
+        self.min_local_fraction = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn local_zone(&self) -> &str {
+        &self.local_zone
+    }
+
+    /// 本区健康实例占本区实例的比例；本区没有实例时为 0
+    pub fn local_healthy_fraction(&self) -> f64 {
+        if self.local_total == 0 {
+            0.0
+        } else {
+            self.local_healthy as f64 / self.local_total as f64
+        }
+    }
+
+    /// 更新实例列表并重新分组
+    pub fn update_servers(&mut self, servers: Vec<ServiceInstance>) {
+        let is_local = |s: &ServiceInstance| s.zone() == Some(self.local_zone.as_str());
+        let (local, remote): (Vec<_>, Vec<_>) = servers.iter().cloned().partition(is_local);
+        self.local_total = local.len();
+        let local: Vec<_> = local.into_iter().filter(|s| s.is_healthy).collect();
+        let remote: Vec<_> = remote.into_iter().filter(|s| s.is_healthy).collect();
+        self.local_healthy = local.len();
+        self.remote_healthy = remote.len();
+        self.local.update_servers(local);
+        self.remote.update_servers(remote);
+        self.servers = servers;
+    }
+
+    /// 更新单个实例的健康状态并重新分组；实例不存在时返回 `false`
+    pub fn set_healthy(&mut self, id: &str, healthy: bool) -> bool {
+        let mut servers = std::mem::take(&mut self.servers);
+        let found = match servers.iter_mut().find(|s| s.id == id) {
+            Some(server) => {
+                server.update_health(healthy);
+                true
+            }
+            None => false,
+        };
+        self.update_servers(servers);
+        found
+    }
+
+    pub fn select_server(&mut self) -> Option<&ServiceInstance> {
+        let prefer_local = if self.local_healthy == 0 {
+            false
+        } else if self.remote_healthy == 0
+            || self.local_healthy_fraction() >= self.min_local_fraction
+        {
+            true
+        } else {
+            let share =
+                self.local_healthy as f64 / (self.local_healthy + self.remote_healthy) as f64;
+            self.local_credit += share;
+            if self.local_credit >= 1.0 {
+                self.local_credit -= 1.0;
+                true
+            } else {
+                false
+            }
+        };
+        let selected = if prefer_local {
+            self.local.select_server()
+        } else {
+            self.remote.select_server()
+        }?;
+        *self
+            .selections
+            .entry(selected.zone().unwrap_or_default().to_string())
+            .or_insert(0) += 1;
+        Some(selected)
+    }
+
+    /// 各可用区被选中的次数；未标注可用区的实例计在空字符串下
+    pub fn zone_selections(&self) -> &HashMap<String, u64> {
+        &self.selections
+    }
+
+    /// 本区被选中的次数
+    pub fn local_selections(&self) -> u64 {
+        self.selections.get(&self.local_zone).copied().unwrap_or(0)
+    }
+}
+
+/// 按 semver 排序的部署版本；缺失的数字段视为 0，预发布版本低于同号正式版本
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SemVer {
+    core: [u64; 3],
+    release: bool,
+    pre: String,
+}
+
+impl SemVer {
+    fn parse(version: &str) -> Self {
+        let version = version.trim().trim_start_matches('v');
+        let version = version.split('+').next().unwrap_or_default();
+        let (core, pre) = version.split_once('-').unwrap_or((version, ""));
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().unwrap_or(0));
+        Self {
+            core: [
+                parts.next().unwrap_or(0),
+                parts.next().unwrap_or(0),
+                parts.next().unwrap_or(0),
+            ],
+            release: pre.is_empty(),
+            pre: pre.to_string(),
+        }
+    }
+}
+
+/// 按部署版本分流的负载均衡器（蓝绿/金丝雀发布）
+///
+/// 健康实例按 `version` 的 semver 排序：最新版本为金丝雀组，次新版本为稳定组，更旧的版本
+/// 不再接收流量。每次选择按确定性的配额累加决定组别，组内按权重轮询；只有一个版本或某组
+/// 没有健康实例时全部流量导向另一组。
+pub struct VersionAwareBalancer {
+    servers: Vec<ServiceInstance>,
+    canary_fraction: f64,
+    canary: WeightedRoundRobinBalancer,
+    stable: WeightedRoundRobinBalancer,
+    canary_version: Option<String>,
+    stable_version: Option<String>,
+    /// 金丝雀组的累计配额
+    canary_credit: f64,
+    selections: HashMap<String, u64>,
+}
+
+impl VersionAwareBalancer {
+    /// `canary_fraction` 为导向最新版本的流量比例，取值 [0, 1]
+    pub fn new(instances: Vec<ServiceInstance>, canary_fraction: f64) -> Self {
+        let mut balancer = Self {
+            servers: Vec::new(),
+            canary_fraction: 0.0,
+            canary: WeightedRoundRobinBalancer::new(Vec::new()),
+            stable: WeightedRoundRobinBalancer::new(Vec::new()),
+            canary_version: None,
+            stable_version: None,
+            canary_credit: 0.0,
+            selections: HashMap::new(),
+        };
+        balancer.set_canary_fraction(canary_fraction);
+        balancer.update_servers(instances);
+        balancer
+    }
+
+    /// 调整导向最新版本的流量比例
+    pub fn set_canary_fraction(&mut self, f: f64) {
+        self.canary_fraction = if f.is_nan() { 0.0 } else { f.clamp(0.0, 1.0) };
+        self.canary_credit = 0.0;
+    }
+
+    pub fn canary_fraction(&self) -> f64 {
+        self.canary_fraction
+    }
+
+    /// 最新的部署版本；没有健康实例时为 `None`
+    pub fn canary_version(&self) -> Option<&str> {
+        self.canary_version.as_deref()
+    }
+
+    /// 上一个部署版本；只有一个版本时为 `None`
+    pub fn stable_version(&self) -> Option<&str> {
+        self.stable_version.as_deref()
+    }
+
+    /// 更新实例列表并按版本重新分组
+    pub fn update_servers(&mut self, servers: Vec<ServiceInstance>) {
+        let mut versions: Vec<&str> = servers
+            .iter()
+            .filter(|s| s.is_healthy)
+            .map(|s| s.version.as_str())
+            .collect();
+        versions.sort_by_key(|v| std::cmp::Reverse(SemVer::parse(v)));
+        versions.dedup_by_key(|v| SemVer::parse(v));
+        let canary = versions.first().map(|v| SemVer::parse(v));
+        let stable = versions.get(1).map(|v| SemVer::parse(v));
+        self.canary_version = versions.first().map(|v| v.to_string());
+        self.stable_version = versions.get(1).map(|v| v.to_string());
+        let group = |version: &Option<SemVer>| -> Vec<ServiceInstance> {
+            servers
+                .iter()
+                .filter(|s| s.is_healthy && Some(SemVer::parse(&s.version)) == *version)
+                .cloned()
+                .collect()
+        };
+        self.canary.update_servers(group(&canary));
+        self.stable.update_servers(group(&stable));
+        self.servers = servers;
+    }
+
+    pub fn select_server(&mut self) -> Option<&ServiceInstance> {
+        let to_canary = if self.stable_version.is_none() {
+            true
+        } else {
+            self.canary_credit += self.canary_fraction;
+            if self.canary_credit >= 1.0 {
+                self.canary_credit -= 1.0;
+                true
+            } else {
+                false
+            }
+        };
+        let selected = if to_canary {
+            self.canary.select_server()
+        } else {
+            self.stable.select_server()
+        }?;
+        *self
+            .selections
+            .entry(selected.version.clone())
+            .or_insert(0) += 1;
+        Some(selected)
+    }
+
+    /// 各版本被选中的次数
+    pub fn version_selections(&self) -> &HashMap<String, u64> {
+        &self.selections
+    }
+}
+
 /// 负载均衡管理器
 pub struct LoadBalancerManager {
     strategy: LoadBalancingStrategy,
@@ -0,0 +1,181 @@
+//! 集群健康快照
+//!
+//! 设计意图：
+//! - 运维面板需要一次调用拿到各组件的健康状况。`ClusterHealthComponents` 聚合对熔断器、
+//!   服务发现、本地复制器与 SWIM 会籍视图的引用（均可缺省），`ClusterHealth::snapshot`
+//!   只读取当前状态，不触发探测或状态迁移。
+//! - `HealthSnapshot` 可序列化：各熔断器状态、各服务健康/总实例数、各副本复制延迟（落后的
+//!   写入数）、SWIM 成员的存活/可疑/故障计数，以及据此汇总的整体状态。
+//!
+//! 不变量（草图）：
+//! - 每个服务 `healthy <= total`；成员计数之和等于视图中的成员数。
+//! - 整体状态：存在打开的熔断器、无健康实例的服务或故障成员时为 `Unhealthy`；存在半开熔断器、
+//!   部分实例不健康、可疑成员或复制延迟时为 `Degraded`；否则为 `Healthy`。
+
+use super::HealthStatus;
+use crate::security::{CircuitBreaker, CircuitState};
+use crate::service_discovery::ServiceDiscoveryManager;
+use crate::storage::IdempotencyKey;
+use crate::storage::replication::LocalReplicator;
+use crate::swim::{MembershipView, SwimMemberState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 快照所读取的组件
+pub struct ClusterHealthComponents<'a, ID = IdempotencyKey> {
+    pub circuit_breakers: Vec<(String, &'a CircuitBreaker)>,
+    pub discovery: Option<&'a ServiceDiscoveryManager>,
+    pub replicator: Option<&'a LocalReplicator<ID>>,
+    pub membership: Option<&'a MembershipView>,
+}
+
+impl<ID> Default for ClusterHealthComponents<'_, ID> {
+    fn default() -> Self {
+        Self {
+            circuit_breakers: Vec::new(),
+            discovery: None,
+            replicator: None,
+            membership: None,
+        }
+    }
+}
+
+impl<'a, ID> ClusterHealthComponents<'a, ID> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_circuit_breaker(mut self, name: impl Into<String>, cb: &'a CircuitBreaker) -> Self {
+        self.circuit_breakers.push((name.into(), cb));
+        self
+    }
+
+    pub fn with_discovery(mut self, discovery: &'a ServiceDiscoveryManager) -> Self {
+        self.discovery = Some(discovery);
+        self
+    }
+
+    pub fn with_replicator(mut self, replicator: &'a LocalReplicator<ID>) -> Self {
+        self.replicator = Some(replicator);
+        self
+    }
+
+    pub fn with_membership(mut self, membership: &'a MembershipView) -> Self {
+        self.membership = Some(membership);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    pub healthy: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemberCounts {
+    pub alive: usize,
+    pub suspect: usize,
+    pub dead: usize,
+}
+
+/// 某一时刻的集群健康状况
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthSnapshot {
+    /// Unix 毫秒
+    pub taken_at_ms: u64,
+    pub status: HealthStatus,
+    pub circuit_breakers: BTreeMap<String, CircuitState>,
+    pub services: BTreeMap<String, ServiceHealth>,
+    /// 副本 → 落后的写入数
+    pub replication_lag: BTreeMap<String, u64>,
+    pub members: MemberCounts,
+}
+
+impl HealthSnapshot {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("health snapshot is serializable")
+    }
+
+    fn overall_status(&self) -> HealthStatus {
+        let breakers = || self.circuit_breakers.values();
+        let unhealthy = breakers().any(|s| *s == CircuitState::Open)
+            || self
+                .services
+                .values()
+                .any(|s| s.total > 0 && s.healthy == 0)
+            || self.members.dead > 0;
+        let degraded = breakers().any(|s| *s == CircuitState::HalfOpen)
+            || self.services.values().any(|s| s.healthy < s.total)
+            || self.members.suspect > 0
+            || self.replication_lag.values().any(|lag| *lag > 0);
+        if unhealthy {
+            HealthStatus::Unhealthy
+        } else if degraded {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        }
+    }
+}
+
+pub struct ClusterHealth;
+
+impl ClusterHealth {
+    pub fn snapshot<ID>(components: &ClusterHealthComponents<'_, ID>) -> HealthSnapshot {
+        let circuit_breakers = components
+            .circuit_breakers
+            .iter()
+            .map(|(name, cb)| (name.clone(), cb.state()))
+            .collect();
+
+        let services = components
+            .discovery
+            .map(|d| {
+                d.get_all_services()
+                    .into_iter()
+                    .map(|(name, instances)| {
+                        let health = ServiceHealth {
+                            healthy: instances.iter().filter(|i| i.is_healthy).count(),
+                            total: instances.len(),
+                        };
+                        (name, health)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let replication_lag = components
+            .replicator
+            .map(|r| r.nodes.iter().map(|n| (n.clone(), r.lag(n))).collect())
+            .unwrap_or_default();
+
+        let mut members = MemberCounts::default();
+        for info in components
+            .membership
+            .iter()
+            .flat_map(|v| v.members.values())
+        {
+            match info.state {
+                SwimMemberState::Alive => members.alive += 1,
+                SwimMemberState::Suspect => members.suspect += 1,
+                SwimMemberState::Faulty => members.dead += 1,
+            }
+        }
+
+        let mut snapshot = HealthSnapshot {
+            taken_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            status: HealthStatus::Healthy,
+            circuit_breakers,
+            services,
+            replication_lag,
+            members,
+        };
+        snapshot.status = snapshot.overall_status();
+        snapshot
+    }
+}
@@ -0,0 +1,334 @@
+//! 收敛探针（convergence probe）
+//!
+//! 设计意图：
+//! - 最终一致的读路径没有“何时可见”的保证，运维与测试都需要一个可度量的收敛延迟。
+//!   `ConvergenceProbe` 周期性地经一个副本写入探针键，值为写入时的 HLC 时间戳，随后在其余
+//!   副本上以 `ConsistencyLevel::Eventual` 读取；读到同一时间戳即记录该副本的传播延迟
+//!   （从发起写入算起）到按副本划分的 `LatencyHistogram`。
+//! - 探针键按节点命名空间化：`__probe/{node_id}/{seq}`，多个节点同时探测时互不覆盖。
+//! - 只保留最近 `retained_probes` 个探针，更早的探针键经 `ProbeTarget::delete_probe` 删除；
+//!   被淘汰时仍未读到的副本记一次 `missed`。`clear` 删除本节点全部探针键。
+//! - `converged_within(deadline)` 针对最近一个探针：全部副本都已读到且最大延迟不超过
+//!   `deadline` 时返回该最大延迟，否则返回 `ConvergenceError`，供测试断言与场景检查使用；
+//!   `snapshot` 给出可序列化的汇总，供 admin 快照附带。
+//! - 时钟可注入（`with_clock`），HLC 的物理时间取同一时钟，仿真网络下结果可复现。
+//!
+//! 不变量（草图）：
+//! - 每个 (探针, 副本) 至多记录一次延迟；写入探针的副本本身不计入。
+//! - 任意时刻本节点持有的探针数不超过 `retained_probes`。
+
+use crate::benchmarks::loadgen::LatencyHistogram;
+use crate::consistency::ConsistencyLevel;
+use crate::core::errors::DistributedError;
+use crate::core::scheduling::{HlcClock, HlcTimestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// 探针键前缀；业务读写与全量比较可据此跳过探针键
+pub const PROBE_KEY_PREFIX: &str = "__probe/";
+
+/// 被探测的副本集合
+pub trait ProbeTarget {
+    /// 全部副本
+    fn replicas(&self) -> Vec<String>;
+    /// 经 `via` 写入探针键
+    fn write_probe(
+        &self,
+        via: &str,
+        key: &str,
+        stamp: HlcTimestamp,
+    ) -> Result<(), DistributedError>;
+    /// 在 `replica` 上按 `level` 读取探针键；未读到为 `None`
+    fn read_probe(&self, replica: &str, key: &str, level: ConsistencyLevel)
+    -> Option<HlcTimestamp>;
+    /// 删除探针键
+    fn delete_probe(&self, via: &str, key: &str) -> Result<(), DistributedError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConvergenceError {
+    #[error("no probe written yet")]
+    NoProbe,
+    #[error("probe {key} not visible on {replicas:?} after {elapsed:?}")]
+    Pending {
+        key: String,
+        replicas: Vec<String>,
+        elapsed: Duration,
+    },
+    #[error("probe {key} reached {replica} after {lag:?}, exceeding {deadline:?}")]
+    TooSlow {
+        key: String,
+        replica: String,
+        lag: Duration,
+        deadline: Duration,
+    },
+}
+
+struct Probe {
+    key: String,
+    stamp: HlcTimestamp,
+    written_at: Duration,
+    pending: BTreeSet<String>,
+    lags: BTreeMap<String, Duration>,
+}
+
+/// 单节点的收敛探针
+pub struct ConvergenceProbe {
+    node_id: String,
+    interval: Duration,
+    retained_probes: usize,
+    clock: Arc<dyn Fn() -> Duration + Send + Sync>,
+    hlc: HlcClock,
+    next_seq: u64,
+    last_write: Option<Duration>,
+    probes: VecDeque<Probe>,
+    histograms: BTreeMap<String, LatencyHistogram>,
+    missed: BTreeMap<String, u64>,
+    write_failures: u64,
+}
+
+impl ConvergenceProbe {
+    /// 每秒写入一个探针，保留最近 8 个；时钟为进程单调时钟
+    pub fn new(node_id: impl Into<String>) -> Self {
+        let start = Instant::now();
+        Self {
+            node_id: node_id.into(),
+            interval: Duration::from_secs(1),
+            retained_probes: 8,
+            clock: Arc::new(move || start.elapsed()),
+            hlc: HlcClock::new(),
+            next_seq: 0,
+            last_write: None,
+            probes: VecDeque::new(),
+            histograms: BTreeMap::new(),
+            missed: BTreeMap::new(),
+            write_failures: 0,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 保留的探针数上限（至少 1）
+    pub fn with_retained_probes(mut self, probes: usize) -> Self {
+        self.retained_probes = probes.max(1);
+        self
+    }
+
+    /// 注入时钟；HLC 的物理时间（毫秒）取自同一时钟
+    pub fn with_clock(mut self, clock: impl Fn() -> Duration + Send + Sync + 'static) -> Self {
+        let clock: Arc<dyn Fn() -> Duration + Send + Sync> = Arc::new(clock);
+        let source = clock.clone();
+        self.hlc = HlcClock::with_time_source(move || source().as_millis() as u64);
+        self.clock = clock;
+        self
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// 本节点第 `seq` 个探针的键
+    pub fn probe_key(&self, seq: u64) -> String {
+        format!("{PROBE_KEY_PREFIX}{}/{seq}", self.node_id)
+    }
+
+    /// 到期时写入新探针，然后读取未确认的副本并淘汰旧探针
+    pub fn tick(&mut self, target: &dyn ProbeTarget) {
+        let now = (self.clock)();
+        if self
+            .last_write
+            .is_none_or(|at| now.saturating_sub(at) >= self.interval)
+        {
+            self.write(target, now);
+        }
+        self.poll(target);
+        self.evict(target);
+    }
+
+    /// 删除本节点全部探针键
+    pub fn clear(&mut self, target: &dyn ProbeTarget) {
+        for probe in std::mem::take(&mut self.probes) {
+            let _ = target.delete_probe(&self.via(target), &probe.key);
+        }
+    }
+
+    /// 最近一个探针在 `deadline` 内到达了全部副本时返回其最大传播延迟
+    pub fn converged_within(&self, deadline: Duration) -> Result<Duration, ConvergenceError> {
+        let probe = self.probes.back().ok_or(ConvergenceError::NoProbe)?;
+        if !probe.pending.is_empty() {
+            return Err(ConvergenceError::Pending {
+                key: probe.key.clone(),
+                replicas: probe.pending.iter().cloned().collect(),
+                elapsed: (self.clock)().saturating_sub(probe.written_at),
+            });
+        }
+        let (replica, lag) = probe
+            .lags
+            .iter()
+            .max_by_key(|(_, lag)| **lag)
+            .map(|(r, lag)| (r.clone(), *lag))
+            .unwrap_or_default();
+        if lag > deadline {
+            return Err(ConvergenceError::TooSlow {
+                key: probe.key.clone(),
+                replica,
+                lag,
+                deadline,
+            });
+        }
+        Ok(lag)
+    }
+
+    /// 副本的传播延迟分布
+    pub fn lag_histogram(&self, replica: &str) -> Option<&LatencyHistogram> {
+        self.histograms.get(replica)
+    }
+
+    /// 当前持有的探针键，从旧到新
+    pub fn outstanding_keys(&self) -> Vec<String> {
+        self.probes.iter().map(|p| p.key.clone()).collect()
+    }
+
+    /// 已写入的探针数
+    pub fn probes_written(&self) -> u64 {
+        self.next_seq
+    }
+
+    pub fn write_failures(&self) -> u64 {
+        self.write_failures
+    }
+
+    pub fn snapshot(&self) -> ConvergenceSnapshot {
+        let ms = |d: Duration| d.as_secs_f64() * 1_000.0;
+        let latest = self.probes.back();
+        let mut replicas: BTreeMap<String, ReplicaLag> = self
+            .histograms
+            .iter()
+            .map(|(replica, h)| {
+                let lag = ReplicaLag {
+                    samples: h.count(),
+                    p50_ms: ms(h.percentile(0.5)),
+                    p99_ms: ms(h.percentile(0.99)),
+                    max_ms: ms(h.max()),
+                    missed: 0,
+                };
+                (replica.clone(), lag)
+            })
+            .collect();
+        for (replica, missed) in &self.missed {
+            replicas.entry(replica.clone()).or_default().missed = *missed;
+        }
+        ConvergenceSnapshot {
+            node_id: self.node_id.clone(),
+            probes_written: self.next_seq,
+            write_failures: self.write_failures,
+            latest_max_lag_ms: latest
+                .filter(|p| p.pending.is_empty())
+                .and_then(|p| p.lags.values().max().copied())
+                .map(ms),
+            pending: latest
+                .map(|p| p.pending.iter().cloned().collect())
+                .unwrap_or_default(),
+            replicas,
+        }
+    }
+
+    /// 本节点是副本时经自己写入，否则经第一个副本
+    fn via(&self, target: &dyn ProbeTarget) -> String {
+        let replicas = target.replicas();
+        if replicas.contains(&self.node_id) {
+            return self.node_id.clone();
+        }
+        replicas.into_iter().next().unwrap_or_default()
+    }
+
+    fn write(&mut self, target: &dyn ProbeTarget, now: Duration) {
+        self.last_write = Some(now);
+        let via = self.via(target);
+        let key = self.probe_key(self.next_seq + 1);
+        let stamp = self.hlc.now();
+        if target.write_probe(&via, &key, stamp).is_err() {
+            self.write_failures += 1;
+            return;
+        }
+        self.next_seq += 1;
+        let pending = target
+            .replicas()
+            .into_iter()
+            .filter(|r| *r != via)
+            .collect();
+        self.probes.push_back(Probe {
+            key,
+            stamp,
+            written_at: now,
+            pending,
+            lags: BTreeMap::new(),
+        });
+    }
+
+    fn poll(&mut self, target: &dyn ProbeTarget) {
+        let now = (self.clock)();
+        for probe in &mut self.probes {
+            let seen: Vec<String> = probe
+                .pending
+                .iter()
+                .filter(|r| {
+                    target.read_probe(r, &probe.key, ConsistencyLevel::Eventual)
+                        == Some(probe.stamp)
+                })
+                .cloned()
+                .collect();
+            for replica in seen {
+                let lag = now.saturating_sub(probe.written_at);
+                probe.pending.remove(&replica);
+                self.histograms
+                    .entry(replica.clone())
+                    .or_default()
+                    .record(lag);
+                probe.lags.insert(replica, lag);
+            }
+        }
+    }
+
+    fn evict(&mut self, target: &dyn ProbeTarget) {
+        while self.probes.len() > self.retained_probes {
+            let Some(probe) = self.probes.pop_front() else {
+                break;
+            };
+            for replica in probe.pending {
+                *self.missed.entry(replica).or_default() += 1;
+            }
+            let _ = target.delete_probe(&self.via(target), &probe.key);
+        }
+    }
+}
+
+/// 单个副本的传播延迟汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplicaLag {
+    pub samples: u64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// 探针被淘汰时仍未读到的次数
+    pub missed: u64,
+}
+
+/// 收敛探针的可序列化汇总
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConvergenceSnapshot {
+    pub node_id: String,
+    pub probes_written: u64,
+    pub write_failures: u64,
+    /// 最近一个探针到达全部副本时的最大延迟；尚未全部到达为 `None`
+    pub latest_max_lag_ms: Option<f64>,
+    /// 最近一个探针尚未到达的副本
+    pub pending: Vec<String>,
+    pub replicas: BTreeMap<String, ReplicaLag>,
+}
@@ -14,6 +14,16 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+pub mod cluster_health;
+pub mod convergence;
+
+pub use cluster_health::{
+    ClusterHealth, ClusterHealthComponents, HealthSnapshot, MemberCounts, ServiceHealth,
+};
+pub use convergence::{
+    ConvergenceError, ConvergenceProbe, ConvergenceSnapshot, ProbeTarget, ReplicaLag,
+};
+
 /// 指标类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MetricType {
@@ -0,0 +1,850 @@
+//! Jepsen 风格的故障编排（nemesis）与场景运行器
+//!
+//! 设计意图：
+//! - 在 `SimNetwork` 上运行一个小型复制 KV：任一副本都可协调写入，本地写 WAL 后复制到其余
+//!   副本，多数派确认即提交；版本为 `(协调者时钟, 节点)`，按最后写入者胜合并。
+//! - `Nemesis` 按虚拟时间表注入故障并在持续时间结束后恢复：多数派分区、隔离领导者（编号最小
+//!   的存活副本，客户端首选的协调者）、时钟偏移（只影响该节点生成的版本）、崩溃重启（内存
+//!   状态丢失，WAL 保留并在重启时重放）、慢盘（推迟副本的复制确认）。
+//! - `ScenarioRunner` 交织客户端写入与故障，超时的客户端换下一个副本重试；结束后恢复全部
+//!   故障，做若干轮全量同步，再检查：所有副本数据一致、任何已提交的写入都未丢失（各副本该键
+//!   的版本不低于提交版本）。失败时 `ScenarioReport` 给出可读的时间线。
+//! - 场景设置了 `convergence_bound` 时，故障恢复后先经领导者写入一个 `ConvergenceProbe` 探针，
+//!   其间每 10ms 做一次反熵推送，要求探针在界限内出现在全部副本上，否则记为违规；探针键在
+//!   全量同步前删除，不影响一致性检查。
+//!
+//! 不变量（草图）：
+//! - 场景只有一个随机源（网络的种子 RNG），相同场景与种子得到相同的时间线。
+//! - 已提交写入的版本在最终状态中被其本身或更高版本覆盖，不会回退。
+
+use crate::codec::{BinaryCodec, JsonCodec};
+use crate::consistency::ConsistencyLevel;
+use crate::core::errors::DistributedError;
+use crate::core::scheduling::HlcTimestamp;
+use crate::monitoring::convergence::{ConvergenceError, ConvergenceProbe, ProbeTarget};
+use crate::simnet::{Envelope, LatencyModel, LinkConfig, SimNetwork};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CLIENT: &str = "client";
+
+// ---------------- 故障 ----------------
+
+/// 可注入的故障
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// 把集群切成多数派与少数派两侧
+    PartitionMajorities,
+    /// 把当前领导者与其余副本隔离
+    IsolateLeader,
+    /// 偏移节点的时钟（毫秒，可为负）
+    ClockSkew { node: usize, offset_ms: i64 },
+    /// 崩溃节点，持续时间结束后以完整 WAL 重启
+    CrashRestart { node: usize },
+    /// 推迟节点的复制确认
+    SlowDisk { node: usize, latency: Duration },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NemesisEvent {
+    pub at: Duration,
+    pub duration: Duration,
+    pub fault: Fault,
+}
+
+/// 故障时间表
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Nemesis {
+    events: Vec<NemesisEvent>,
+}
+
+impl Nemesis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在虚拟时间 `at` 注入 `fault`，持续 `duration`
+    pub fn with(mut self, at: Duration, duration: Duration, fault: Fault) -> Self {
+        self.events.push(NemesisEvent {
+            at,
+            duration,
+            fault,
+        });
+        self
+    }
+
+    pub fn events(&self) -> &[NemesisEvent] {
+        &self.events
+    }
+
+    /// 最后一个故障恢复的时刻
+    pub fn end(&self) -> Duration {
+        self.events
+            .iter()
+            .map(|e| e.at + e.duration)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+// ---------------- 场景 ----------------
+
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub nodes: usize,
+    pub seed: u64,
+    /// 客户端写入持续的虚拟时间
+    pub duration: Duration,
+    pub write_interval: Duration,
+    pub keys: usize,
+    pub request_timeout: Duration,
+    pub link: LinkConfig,
+    pub nemesis: Nemesis,
+    /// 故障恢复后探针写入须在此时间内到达全部副本；`None` 不探测
+    pub convergence_bound: Option<Duration>,
+}
+
+impl Scenario {
+    /// 5 节点，写入 2s（每 10ms 一次，8 个键），请求超时 50ms，链路延迟 1~5ms
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            nodes: 5,
+            seed: 1,
+            duration: Duration::from_secs(2),
+            write_interval: Duration::from_millis(10),
+            keys: 8,
+            request_timeout: Duration::from_millis(50),
+            link: LinkConfig::default().with_latency(LatencyModel::Uniform {
+                min: Duration::from_millis(1),
+                max: Duration::from_millis(5),
+            }),
+            nemesis: Nemesis::new(),
+            convergence_bound: None,
+        }
+    }
+
+    pub fn with_nodes(mut self, nodes: usize) -> Self {
+        self.nodes = nodes.max(1);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn with_write_interval(mut self, interval: Duration) -> Self {
+        self.write_interval = interval.max(Duration::from_micros(1));
+        self
+    }
+
+    pub fn with_keys(mut self, keys: usize) -> Self {
+        self.keys = keys.max(1);
+        self
+    }
+
+    pub fn with_link(mut self, link: LinkConfig) -> Self {
+        self.link = link;
+        self
+    }
+
+    pub fn with_nemesis(mut self, nemesis: Nemesis) -> Self {
+        self.nemesis = nemesis;
+        self
+    }
+
+    pub fn with_convergence_bound(mut self, bound: Duration) -> Self {
+        self.convergence_bound = Some(bound);
+        self
+    }
+
+    /// 默认测试套件中的快速场景：一次隔离领导者与一次崩溃重启
+    pub fn quick() -> Self {
+        Self::new("quick")
+            .with_duration(Duration::from_millis(500))
+            .with_convergence_bound(ms(50))
+            .with_nemesis(
+                Nemesis::new()
+                    .with(ms(100), ms(150), Fault::IsolateLeader)
+                    .with(ms(300), ms(100), Fault::CrashRestart { node: 1 }),
+            )
+    }
+
+    /// 反复的多数派分区，链路有少量丢包与乱序
+    pub fn partition_majorities() -> Self {
+        let nemesis = (0..10).fold(Nemesis::new(), |n, i| {
+            n.with(ms(1_000 + i * 2_000), ms(800), Fault::PartitionMajorities)
+        });
+        Self::new("partition-majorities")
+            .with_duration(Duration::from_secs(20))
+            .with_link(
+                LinkConfig::default()
+                    .with_latency(LatencyModel::Uniform {
+                        min: ms(1),
+                        max: ms(8),
+                    })
+                    .with_loss(0.02)
+                    .with_reorder(0.1, ms(5)),
+            )
+            .with_nemesis(nemesis)
+            .with_convergence_bound(ms(100))
+    }
+
+    /// 隔离领导者并叠加时钟偏移
+    pub fn isolate_leader_with_skew() -> Self {
+        let nemesis = (0..8).fold(Nemesis::new(), |n, i| {
+            n.with(ms(500 + i * 2_500), ms(1_000), Fault::IsolateLeader)
+                .with(
+                    ms(1_500 + i * 2_500),
+                    ms(1_000),
+                    Fault::ClockSkew {
+                        node: (i as usize) % 5,
+                        offset_ms: if i % 2 == 0 { 200 } else { -200 },
+                    },
+                )
+        });
+        Self::new("isolate-leader-skew")
+            .with_duration(Duration::from_secs(20))
+            .with_nemesis(nemesis)
+    }
+
+    /// 轮流崩溃重启节点，其间有节点慢盘
+    pub fn crash_restart_slow_disk() -> Self {
+        let nemesis = (0..10).fold(Nemesis::new(), |n, i| {
+            let node = (i as usize) % 5;
+            n.with(ms(700 + i * 1_800), ms(600), Fault::CrashRestart { node })
+                .with(
+                    ms(1_000 + i * 1_800),
+                    ms(900),
+                    Fault::SlowDisk {
+                        node: (node + 2) % 5,
+                        latency: ms(30),
+                    },
+                )
+        });
+        Self::new("crash-restart-slow-disk")
+            .with_duration(Duration::from_secs(20))
+            .with_nemesis(nemesis)
+    }
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+// ---------------- 被测集群 ----------------
+
+/// 写入版本：先比协调者时钟，再比节点编号
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct KvVersion {
+    pub ts_nanos: u64,
+    pub node: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum KvMsg {
+    Write {
+        key: String,
+        value: u64,
+    },
+    Replicate {
+        key: String,
+        version: KvVersion,
+        value: u64,
+    },
+    Sync(Vec<(String, KvVersion, u64)>),
+    Ack,
+    Committed(KvVersion),
+}
+
+fn encode(msg: &KvMsg) -> Vec<u8> {
+    JsonCodec.encode(msg)
+}
+
+struct PendingWrite {
+    client: Envelope,
+    version: KvVersion,
+    acks: usize,
+}
+
+struct Replica {
+    index: usize,
+    up: bool,
+    /// 每次崩溃递增，使崩溃前安排的延迟确认失效
+    incarnation: u64,
+    /// 持久化：崩溃后保留
+    wal: Vec<(String, KvVersion, u64)>,
+    data: BTreeMap<String, (KvVersion, u64)>,
+    skew_ms: i64,
+    disk_latency: Duration,
+    last_ts: u64,
+    next_write: u64,
+    /// 复制请求序号 → 写入编号
+    replicating: HashMap<u64, u64>,
+    writes: HashMap<u64, PendingWrite>,
+}
+
+impl Replica {
+    fn new(index: usize) -> Self {
+        Self {
+            index,
+            up: true,
+            incarnation: 0,
+            wal: Vec::new(),
+            data: BTreeMap::new(),
+            skew_ms: 0,
+            disk_latency: Duration::ZERO,
+            last_ts: 0,
+            next_write: 0,
+            replicating: HashMap::new(),
+            writes: HashMap::new(),
+        }
+    }
+
+    fn apply(&mut self, key: String, version: KvVersion, value: u64) {
+        self.wal.push((key.clone(), version, value));
+        Self::merge(&mut self.data, key, version, value);
+    }
+
+    fn merge(data: &mut BTreeMap<String, (KvVersion, u64)>, key: String, v: KvVersion, x: u64) {
+        let entry = data.entry(key).or_insert((KvVersion::default(), 0));
+        if v > entry.0 {
+            *entry = (v, x);
+        }
+    }
+
+    fn next_version(&mut self, now: Duration) -> KvVersion {
+        let skewed = now.as_nanos() as i128 + self.skew_ms as i128 * 1_000_000;
+        self.last_ts = (self.last_ts + 1).max(skewed.max(0) as u64);
+        KvVersion {
+            ts_nanos: self.last_ts,
+            node: self.index as u32,
+        }
+    }
+
+    fn crash(&mut self) {
+        self.up = false;
+        self.incarnation += 1;
+        self.data.clear();
+        self.replicating.clear();
+        self.writes.clear();
+    }
+
+    /// 重放 WAL 重建内存状态
+    fn restart(&mut self) {
+        self.up = true;
+        for (key, version, value) in &self.wal {
+            Self::merge(&mut self.data, key.clone(), *version, *value);
+            self.last_ts = self.last_ts.max(version.ts_nanos);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Cluster {
+    net: SimNetwork,
+    replicas: Vec<Arc<Mutex<Replica>>>,
+    timeline: Arc<Mutex<Vec<(Duration, String)>>>,
+}
+
+fn node_id(index: usize) -> String {
+    format!("n{}", index + 1)
+}
+
+impl Cluster {
+    fn new(scenario: &Scenario) -> Self {
+        let net = SimNetwork::new(scenario.seed).with_default_link(scenario.link.clone());
+        net.register(CLIENT, |_, _| None);
+        let cluster = Self {
+            net,
+            replicas: (0..scenario.nodes)
+                .map(|i| Arc::new(Mutex::new(Replica::new(i))))
+                .collect(),
+            timeline: Arc::default(),
+        };
+        for i in 0..scenario.nodes {
+            cluster.serve(i);
+        }
+        cluster
+    }
+
+    fn log(&self, entry: impl Into<String>) {
+        let now = self.net.now();
+        self.timeline.lock().unwrap().push((now, entry.into()));
+    }
+
+    fn quorum(&self) -> usize {
+        self.replicas.len() / 2 + 1
+    }
+
+    fn is_up(&self, index: usize) -> bool {
+        self.replicas[index].lock().unwrap().up
+    }
+
+    /// 编号最小的存活副本
+    fn leader(&self) -> Option<usize> {
+        (0..self.replicas.len()).find(|i| self.is_up(*i))
+    }
+
+    fn serve(&self, index: usize) {
+        let me = node_id(index);
+        let peers: Vec<String> = (0..self.replicas.len())
+            .filter(|i| *i != index)
+            .map(node_id)
+            .collect();
+        let replica = self.replicas[index].clone();
+        let quorum = self.quorum();
+        self.net.register(me.clone(), move |net, envelope| {
+            let mut r = replica.lock().unwrap();
+            if let Some(request) = envelope.reply_to {
+                let write = r.replicating.remove(&request)?;
+                let pending = r.writes.get_mut(&write)?;
+                pending.acks += 1;
+                if pending.acks >= quorum {
+                    let pending = r.writes.remove(&write)?;
+                    net.reply(&pending.client, encode(&KvMsg::Committed(pending.version)));
+                }
+                return None;
+            }
+            match serde_json::from_slice(&envelope.payload).ok()? {
+                KvMsg::Write { key, value } => {
+                    let version = r.next_version(net.now());
+                    r.apply(key.clone(), version, value);
+                    if quorum <= 1 {
+                        return Some(encode(&KvMsg::Committed(version)));
+                    }
+                    r.next_write += 1;
+                    let write = r.next_write;
+                    for peer in &peers {
+                        let msg = KvMsg::Replicate {
+                            key: key.clone(),
+                            version,
+                            value,
+                        };
+                        let seq = net.send(&me, peer, encode(&msg));
+                        r.replicating.insert(seq, write);
+                    }
+                    r.writes.insert(
+                        write,
+                        PendingWrite {
+                            client: envelope.clone(),
+                            version,
+                            acks: 1,
+                        },
+                    );
+                    None
+                }
+                KvMsg::Replicate {
+                    key,
+                    version,
+                    value,
+                } => {
+                    r.apply(key, version, value);
+                    if r.disk_latency.is_zero() {
+                        return Some(encode(&KvMsg::Ack));
+                    }
+                    let (net, envelope) = (net.clone(), envelope.clone());
+                    let (replica, incarnation) = (replica.clone(), r.incarnation);
+                    net.clone().schedule(r.disk_latency, move || {
+                        if replica.lock().unwrap().incarnation == incarnation {
+                            net.reply(&envelope, encode(&KvMsg::Ack));
+                        }
+                    });
+                    None
+                }
+                KvMsg::Sync(entries) => {
+                    for (key, version, value) in entries {
+                        if r.data.get(&key).is_none_or(|(v, _)| *v < version) {
+                            r.apply(key, version, value);
+                        }
+                    }
+                    None
+                }
+                KvMsg::Ack | KvMsg::Committed(_) => None,
+            }
+        });
+    }
+
+    fn start_fault(&self, event: &NemesisEvent) {
+        let n = self.replicas.len();
+        let (from, to) = (self.net.now(), self.net.now() + event.duration);
+        match &event.fault {
+            Fault::PartitionMajorities => {
+                let shift = (self.net.next_random() % n as u64) as usize;
+                let ring: Vec<String> = (0..n).map(|i| node_id((i + shift) % n)).collect();
+                let (majority, minority) = ring.split_at(self.quorum());
+                self.log(format!("nemesis: partition {majority:?} | {minority:?}"));
+                self.net
+                    .partition([majority.to_vec(), minority.to_vec()], from, to);
+            }
+            Fault::IsolateLeader => {
+                let Some(leader) = self.leader() else {
+                    return;
+                };
+                let rest: Vec<String> = (0..n).filter(|i| *i != leader).map(node_id).collect();
+                self.log(format!("nemesis: isolate leader {}", node_id(leader)));
+                self.net.partition([vec![node_id(leader)], rest], from, to);
+            }
+            Fault::ClockSkew { node, offset_ms } => {
+                self.log(format!("nemesis: skew {} by {offset_ms}ms", node_id(*node)));
+                self.replicas[*node].lock().unwrap().skew_ms = *offset_ms;
+            }
+            Fault::CrashRestart { node } => {
+                self.log(format!("nemesis: crash {}", node_id(*node)));
+                self.replicas[*node].lock().unwrap().crash();
+                self.net.unregister(&node_id(*node));
+            }
+            Fault::SlowDisk { node, latency } => {
+                self.log(format!(
+                    "nemesis: slow disk {} +{latency:?}",
+                    node_id(*node)
+                ));
+                self.replicas[*node].lock().unwrap().disk_latency = *latency;
+            }
+        }
+    }
+
+    fn end_fault(&self, event: &NemesisEvent) {
+        match &event.fault {
+            // 分区按时间窗自动解除
+            Fault::PartitionMajorities | Fault::IsolateLeader => {
+                self.log("nemesis: heal partition");
+            }
+            Fault::ClockSkew { node, .. } => {
+                self.log(format!("nemesis: reset clock {}", node_id(*node)));
+                self.replicas[*node].lock().unwrap().skew_ms = 0;
+            }
+            Fault::CrashRestart { node } => {
+                self.log(format!("nemesis: restart {} from WAL", node_id(*node)));
+                self.replicas[*node].lock().unwrap().restart();
+                self.serve(*node);
+            }
+            Fault::SlowDisk { node, .. } => {
+                self.log(format!("nemesis: disk {} back to normal", node_id(*node)));
+                self.replicas[*node].lock().unwrap().disk_latency = Duration::ZERO;
+            }
+        }
+    }
+
+    /// 全量同步一轮：每个存活副本把全部条目推给其他副本
+    fn sync_round(&self) {
+        self.push_sync();
+        self.net.run_until_idle();
+    }
+
+    /// 发出一轮全量同步消息，不等待送达
+    fn push_sync(&self) {
+        for (i, replica) in self.replicas.iter().enumerate() {
+            let entries: Vec<_> = {
+                let r = replica.lock().unwrap();
+                if !r.up {
+                    continue;
+                }
+                r.data
+                    .iter()
+                    .map(|(k, (v, x))| (k.clone(), *v, *x))
+                    .collect()
+            };
+            for peer in (0..self.replicas.len()).filter(|p| *p != i) {
+                self.net.send(
+                    &node_id(i),
+                    &node_id(peer),
+                    encode(&KvMsg::Sync(entries.clone())),
+                );
+            }
+        }
+    }
+
+    /// 经领导者写入一个探针，等待它到达全部副本，返回最大传播延迟；结束后删除探针键
+    fn probe_convergence(&self, bound: Duration, timeout: Duration) -> Result<Duration, String> {
+        let leader = self.leader().ok_or("no live replica to probe through")?;
+        let clock = self.net.clock();
+        // 只写一个探针
+        let mut probe = ConvergenceProbe::new(node_id(leader))
+            .with_clock(move || clock.now())
+            .with_interval(Duration::MAX);
+        let target = ClusterProbe {
+            cluster: self,
+            timeout,
+        };
+        probe.tick(&target);
+        if probe.probes_written() == 0 {
+            return Err(format!("probe write via {} failed", node_id(leader)));
+        }
+        let mut next_sync = self.net.now() + ms(10);
+        let mut result = probe.converged_within(bound);
+        while matches!(result, Err(ConvergenceError::Pending { elapsed, .. }) if elapsed <= bound) {
+            self.net.run_for(ms(1));
+            if self.net.now() >= next_sync {
+                self.push_sync();
+                next_sync += ms(10);
+            }
+            probe.tick(&target);
+            result = probe.converged_within(bound);
+        }
+        self.net.run_until_idle();
+        probe.clear(&target);
+        result.map_err(|e| e.to_string())
+    }
+
+    fn states(&self) -> Vec<BTreeMap<String, (KvVersion, u64)>> {
+        self.replicas
+            .iter()
+            .map(|r| r.lock().unwrap().data.clone())
+            .collect()
+    }
+}
+
+/// 把探针映射到场景集群：写入走客户端请求，读取直接查看副本的内存状态
+struct ClusterProbe<'a> {
+    cluster: &'a Cluster,
+    timeout: Duration,
+}
+
+impl ClusterProbe<'_> {
+    /// 探针值：高 48 位为物理毫秒，低 16 位为逻辑计数
+    fn pack(stamp: HlcTimestamp) -> u64 {
+        (stamp.physical_ms << 16) | u64::from(stamp.logical.min(0xffff))
+    }
+
+    fn unpack(value: u64) -> HlcTimestamp {
+        HlcTimestamp {
+            physical_ms: value >> 16,
+            logical: (value & 0xffff) as u32,
+        }
+    }
+}
+
+impl ProbeTarget for ClusterProbe<'_> {
+    fn replicas(&self) -> Vec<String> {
+        (0..self.cluster.replicas.len()).map(node_id).collect()
+    }
+
+    fn write_probe(
+        &self,
+        via: &str,
+        key: &str,
+        stamp: HlcTimestamp,
+    ) -> Result<(), DistributedError> {
+        let request = encode(&KvMsg::Write {
+            key: key.to_string(),
+            value: Self::pack(stamp),
+        });
+        let reply = self.cluster.net.call(CLIENT, via, request, self.timeout)?;
+        match serde_json::from_slice(&reply) {
+            Ok(KvMsg::Committed(_)) => Ok(()),
+            _ => Err(DistributedError::Network(format!(
+                "unexpected probe reply from {via}"
+            ))),
+        }
+    }
+
+    fn read_probe(&self, replica: &str, key: &str, _: ConsistencyLevel) -> Option<HlcTimestamp> {
+        let index = (0..self.cluster.replicas.len()).find(|i| node_id(*i) == replica)?;
+        let r = self.cluster.replicas[index].lock().unwrap();
+        if !r.up {
+            return None;
+        }
+        r.data.get(key).map(|(_, value)| Self::unpack(*value))
+    }
+
+    /// 从全部副本的内存状态与 WAL 中抹去探针键
+    fn delete_probe(&self, _: &str, key: &str) -> Result<(), DistributedError> {
+        for replica in &self.cluster.replicas {
+            let mut r = replica.lock().unwrap();
+            r.data.remove(key);
+            r.wal.retain(|(k, _, _)| k != key);
+        }
+        Ok(())
+    }
+}
+
+// ---------------- 运行与报告 ----------------
+
+/// 客户端确认提交的写入
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedWrite {
+    pub key: String,
+    pub value: u64,
+    pub version: KvVersion,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub seed: u64,
+    pub committed: Vec<CommittedWrite>,
+    /// 超时、结果不确定的写入数
+    pub indeterminate: usize,
+    pub violations: Vec<String>,
+    pub timeline: Vec<(Duration, String)>,
+    /// 故障恢复后探针到达全部副本的最大延迟（场景设置了 `convergence_bound` 时）
+    pub convergence_lag: Option<Duration>,
+}
+
+impl ScenarioReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// 可读的时间线，每行 `[虚拟时间] 事件`
+    pub fn timeline_dump(&self) -> String {
+        let mut out = format!("scenario {} (seed {})\n", self.name, self.seed);
+        for (at, entry) in &self.timeline {
+            let _ = writeln!(out, "[{:>10.3}ms] {entry}", at.as_secs_f64() * 1_000.0);
+        }
+        for violation in &self.violations {
+            let _ = writeln!(out, "VIOLATION: {violation}");
+        }
+        out
+    }
+
+    /// 存在违规时 panic 并输出时间线
+    pub fn assert_ok(&self) {
+        assert!(self.is_ok(), "{}", self.timeline_dump());
+    }
+}
+
+pub struct ScenarioRunner {
+    scenario: Scenario,
+    sync_rounds: usize,
+}
+
+impl ScenarioRunner {
+    pub fn new(scenario: Scenario) -> Self {
+        Self {
+            scenario,
+            sync_rounds: 5,
+        }
+    }
+
+    /// 收敛阶段最多的全量同步轮数（链路丢包时可能需要多轮）
+    pub fn with_sync_rounds(mut self, rounds: usize) -> Self {
+        self.sync_rounds = rounds.max(1);
+        self
+    }
+
+    pub fn run(&self) -> ScenarioReport {
+        let s = &self.scenario;
+        let cluster = Cluster::new(s);
+        let net = cluster.net.clone();
+        for event in s.nemesis.events() {
+            let (start, end) = (cluster.clone(), cluster.clone());
+            let (started, ended) = (event.clone(), event.clone());
+            net.schedule(event.at, move || start.start_fault(&started));
+            net.schedule(event.at + event.duration, move || end.end_fault(&ended));
+        }
+
+        let mut report = ScenarioReport {
+            name: s.name.clone(),
+            seed: s.seed,
+            ..ScenarioReport::default()
+        };
+        let mut target = 0;
+        let mut value = 0;
+        while net.now() < s.duration {
+            value += 1;
+            let key = format!("k{}", net.next_random() % s.keys as u64);
+            let coordinator = node_id(target);
+            let request = encode(&KvMsg::Write {
+                key: key.clone(),
+                value,
+            });
+            match net.call(CLIENT, &coordinator, request, s.request_timeout) {
+                Ok(reply) => match serde_json::from_slice(&reply) {
+                    Ok(KvMsg::Committed(version)) => {
+                        cluster.log(format!("write {key}={value} via {coordinator}: ok"));
+                        report.committed.push(CommittedWrite {
+                            key,
+                            value,
+                            version,
+                        });
+                    }
+                    _ => cluster.log(format!("write {key}={value}: unexpected reply")),
+                },
+                Err(e) => {
+                    cluster.log(format!("write {key}={value} via {coordinator}: {e}"));
+                    report.indeterminate += 1;
+                    target = (target + 1) % s.nodes;
+                }
+            }
+            net.run_for(s.write_interval);
+        }
+
+        // 收敛：恢复全部故障后全量同步
+        net.run_until(s.nemesis.end().max(net.now()));
+        cluster.log("converge: all faults healed");
+        let mut probe_violation = None;
+        if let Some(bound) = s.convergence_bound {
+            match cluster.probe_convergence(bound, s.request_timeout) {
+                Ok(lag) => {
+                    cluster.log(format!(
+                        "converge: probe visible on all replicas after {lag:?}"
+                    ));
+                    report.convergence_lag = Some(lag);
+                }
+                Err(e) => {
+                    cluster.log(format!("converge: probe failed: {e}"));
+                    probe_violation = Some(format!("no convergence within {bound:?}: {e}"));
+                }
+            }
+        }
+        for round in 1..=self.sync_rounds {
+            cluster.sync_round();
+            let states = cluster.states();
+            if states.windows(2).all(|w| w[0] == w[1]) {
+                cluster.log(format!(
+                    "converge: replicas equal after {round} sync round(s)"
+                ));
+                break;
+            }
+        }
+
+        report.violations = check(&cluster.states(), &report.committed);
+        report.violations.extend(probe_violation);
+        report.timeline = cluster.timeline.lock().unwrap().clone();
+        report
+    }
+}
+
+/// 收敛检查：所有副本相同，且已提交写入未丢失
+fn check(
+    states: &[BTreeMap<String, (KvVersion, u64)>],
+    committed: &[CommittedWrite],
+) -> Vec<String> {
+    let mut violations = Vec::new();
+    for (i, state) in states.iter().enumerate().skip(1) {
+        if *state != states[0] {
+            violations.push(format!("{} diverges from {}", node_id(i), node_id(0)));
+        }
+    }
+    for write in committed {
+        for (i, state) in states.iter().enumerate() {
+            if state
+                .get(&write.key)
+                .is_none_or(|(v, _)| *v < write.version)
+            {
+                violations.push(format!(
+                    "committed write {}={} ({:?}) lost on {}",
+                    write.key,
+                    write.value,
+                    write.version,
+                    node_id(i)
+                ));
+            }
+        }
+    }
+    violations
+}
@@ -0,0 +1,588 @@
+//! 基于 gRPC 的复制传输（`transport-grpc` 特性）
+//!
+//! 设计意图：
+//! - 协议见 `proto/replication.proto`：`Replicate { idempotency_key, payload, level }` → `Ack`。
+//!   消息以 prost 手写（字段号与 proto 文件一致），无需 protoc。
+//! - 服务端 `ReplicationServer` 把请求分派给用户提供的 `NodeHandler`；处理器返回错误时
+//!   回送 `applied = false` 的确认并携带错误信息，gRPC 状态仅用于协议/传输层错误。
+//! - 客户端 `GrpcReplicationTransport` 经 `KeyedConnectionPool` 为每个节点维护连接池，
+//!   按 `Deadline` 设置 `grpc-timeout` 并在本地限时；`tonic::Status` 经 `From` 映射为
+//!   `DistributedError`。故障检测器判定可疑的节点（`pools().apply_membership`）不再建连。
+//! - 优先级既写入消息字段，也写入 `x-priority` 元数据，供服务端拦截器（如限流豁免）
+//!   在解码消息前读取；缺省（旧客户端）视为 `Normal`。
+//! - 可选地经 `ExactlyOnceExecutor` 按幂等键执行：重传的 `Replicate` 不再调用处理器，
+//!   而是返回首次成功应用时的 `Ack`；空幂等键不做去重。
+//! - schema 版本协商：`Replicate.schema_version` 为负载的编码版本，`Ack.schema_version` 为
+//!   应答方支持的最高版本（0 表示未版本化，不参与协商）。客户端按节点记录协商版本
+//!   `min(本地, 对端)`，首次交换即握手；此后经 `SchemaTranscoder` 把负载改写为该版本再发送。
+//!   服务端拒绝高于自身版本的负载，客户端据其确认中的版本降级后重发一次。
+//! - 能力协商：配置了 `PeerCapabilities` 时，客户端首次向某节点发送前调用 `Handshake`
+//!   交换握手，缺少必需特性的一方使连接失败（`Configuration`）；握手中的 schema 版本作为
+//!   初始协商版本。负载只在对端声明支持压缩算法时压缩，`Replicate.compression` 记录算法名。
+
+use crate::codec::{BinaryCodec, SchemaTranscoder};
+use crate::consistency::ConsistencyLevel;
+use crate::core::errors::DistributedError;
+use crate::network::Deadline;
+use crate::network::handshake::{FeatureSet, Handshake, PayloadCompressor, PeerCapabilities};
+use crate::network::interceptors::PRIORITY_HEADER;
+use crate::network::pool::{Connect, KeyedConnectionPool, PoolConfig, ReconnectBackoff};
+use crate::network::tls::TlsContext;
+use crate::storage::exactly_once::ExactlyOnceExecutor;
+use crate::storage::replication::{Priority, ReplicateAck, ReplicateRequest, ReplicationTransport};
+use prost::Message;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::codegen::{BoxFuture, Context, Poll, Service, http};
+use tonic::metadata::MetadataValue;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Status};
+
+/// gRPC 服务名
+pub const SERVICE_NAME: &str = "distributed.replication.v1.Replication";
+/// `Replicate` 方法路径
+pub const REPLICATE_PATH: &str = "/distributed.replication.v1.Replication/Replicate";
+/// `Handshake` 方法路径
+pub const HANDSHAKE_PATH: &str = "/distributed.replication.v1.Replication/Handshake";
+
+/// `proto/replication.proto` 对应的消息
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Replicate {
+        #[prost(string, tag = "1")]
+        pub idempotency_key: String,
+        #[prost(bytes = "vec", tag = "2")]
+        pub payload: Vec<u8>,
+        #[prost(string, tag = "3")]
+        pub level: String,
+        #[prost(string, tag = "4")]
+        pub priority: String,
+        #[prost(uint32, tag = "5")]
+        pub schema_version: u32,
+        #[prost(string, tag = "6")]
+        pub compression: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Ack {
+        #[prost(bool, tag = "1")]
+        pub applied: bool,
+        #[prost(string, tag = "2")]
+        pub node_id: String,
+        #[prost(string, optional, tag = "3")]
+        pub error: Option<String>,
+        #[prost(uint32, tag = "4")]
+        pub schema_version: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Handshake {
+        #[prost(string, tag = "1")]
+        pub node_id: String,
+        #[prost(string, tag = "2")]
+        pub crate_version: String,
+        #[prost(uint64, tag = "3")]
+        pub features: u64,
+        #[prost(uint64, tag = "4")]
+        pub required: u64,
+        #[prost(uint32, tag = "5")]
+        pub max_codec_version: u32,
+    }
+}
+
+impl From<&Handshake> for proto::Handshake {
+    fn from(h: &Handshake) -> Self {
+        Self {
+            node_id: h.node_id.clone(),
+            crate_version: h.crate_version.clone(),
+            features: h.features.0,
+            required: h.required.0,
+            max_codec_version: h.max_codec_version,
+        }
+    }
+}
+
+impl From<proto::Handshake> for Handshake {
+    fn from(h: proto::Handshake) -> Self {
+        Self {
+            node_id: h.node_id,
+            crate_version: h.crate_version,
+            features: FeatureSet(h.features),
+            required: FeatureSet(h.required),
+            max_codec_version: h.max_codec_version,
+        }
+    }
+}
+
+/// `Ack` 的 protobuf 编解码，用于记忆首次应用的确认
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AckCodec;
+
+impl BinaryCodec<proto::Ack> for AckCodec {
+    fn encode(&self, value: &proto::Ack) -> Vec<u8> {
+        value.encode_to_vec()
+    }
+    fn decode(&self, bytes: &[u8]) -> Option<proto::Ack> {
+        proto::Ack::decode(bytes).ok()
+    }
+}
+
+/// 一致性级别在线上以变体名传输，如 `"Quorum"`
+fn level_name(level: ConsistencyLevel) -> String {
+    match serde_json::to_value(level) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => format!("{:?}", level),
+    }
+}
+
+fn parse_priority(name: &str) -> Result<Priority, DistributedError> {
+    if name.is_empty() {
+        Ok(Priority::Normal)
+    } else {
+        name.parse()
+    }
+}
+
+fn parse_level(name: &str) -> Result<ConsistencyLevel, DistributedError> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| DistributedError::Configuration(format!("unknown consistency level {name}")))
+}
+
+/// 节点侧的复制请求处理器
+pub trait NodeHandler: Send + Sync + 'static {
+    /// 应用一条复制请求；返回错误时本节点的确认为 `applied = false`
+    fn apply(
+        &self,
+        request: ReplicateRequest,
+    ) -> impl Future<Output = Result<(), DistributedError>> + Send;
+}
+
+/// 复制服务端适配器：挂到 `tonic::transport::Server` 上
+pub struct ReplicationServer<H> {
+    node_id: String,
+    handler: Arc<H>,
+    exactly_once: Option<Arc<ExactlyOnceExecutor<AckCodec>>>,
+    schema_version: u32,
+    capabilities: Option<Arc<PeerCapabilities>>,
+    compressor: Option<Arc<dyn PayloadCompressor>>,
+}
+
+impl<H> Clone for ReplicationServer<H> {
+    fn clone(&self) -> Self {
+        Self {
+            node_id: self.node_id.clone(),
+            handler: self.handler.clone(),
+            exactly_once: self.exactly_once.clone(),
+            schema_version: self.schema_version,
+            capabilities: self.capabilities.clone(),
+            compressor: self.compressor.clone(),
+        }
+    }
+}
+
+impl<H: NodeHandler> ReplicationServer<H> {
+    pub fn new(node_id: impl Into<String>, handler: H) -> Self {
+        Self {
+            node_id: node_id.into(),
+            handler: Arc::new(handler),
+            exactly_once: None,
+            schema_version: 0,
+            capabilities: None,
+            compressor: None,
+        }
+    }
+
+    /// 应答 `Handshake`：回送本节点的握手，拒绝缺少本节点必需特性的客户端
+    pub fn with_capabilities(mut self, capabilities: Arc<PeerCapabilities>) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// 解压客户端按该算法压缩的负载；本节点的握手应声明对应特性
+    pub fn with_compressor(mut self, compressor: Arc<dyn PayloadCompressor>) -> Self {
+        self.compressor = Some(compressor);
+        self
+    }
+
+    fn handshake(&self, remote: proto::Handshake) -> Result<proto::Handshake, Status> {
+        match &self.capabilities {
+            Some(capabilities) => {
+                capabilities.accept(remote.into())?;
+                Ok(capabilities.local().into())
+            }
+            None => Ok((&Handshake::new(self.node_id.clone())).into()),
+        }
+    }
+
+    /// 本节点支持的最高 schema 版本，随每个确认返回；更高版本的负载不交给处理器
+    pub fn with_schema_version(mut self, version: u32) -> Self {
+        self.schema_version = version;
+        self
+    }
+
+    /// 按幂等键至多应用一次，重传返回首次的确认；执行器可在多个服务端间共享
+    pub fn with_exactly_once(mut self, executor: Arc<ExactlyOnceExecutor<AckCodec>>) -> Self {
+        self.exactly_once = Some(executor);
+        self
+    }
+
+    async fn replicate(&self, mut message: proto::Replicate) -> Result<proto::Ack, Status> {
+        let reject = |error: String| proto::Ack {
+            applied: false,
+            node_id: self.node_id.clone(),
+            error: Some(error),
+            schema_version: self.schema_version,
+        };
+        if self.schema_version != 0 && message.schema_version > self.schema_version {
+            return Ok(reject(format!(
+                "unsupported schema version {} (supported up to {})",
+                message.schema_version, self.schema_version
+            )));
+        }
+        if !message.compression.is_empty() {
+            match &self.compressor {
+                Some(c) if c.feature().as_str() == message.compression => {
+                    message.payload = c.decompress(&message.payload)?;
+                }
+                _ => {
+                    return Ok(reject(format!(
+                        "unsupported compression {}",
+                        message.compression
+                    )));
+                }
+            }
+        }
+        let key = message.idempotency_key.clone();
+        let request = ReplicateRequest {
+            idempotency_key: message.idempotency_key,
+            payload: message.payload,
+            level: parse_level(&message.level)?,
+            priority: parse_priority(&message.priority)?,
+        };
+        let apply = || async {
+            self.handler.apply(request).await?;
+            Ok(proto::Ack {
+                applied: true,
+                node_id: self.node_id.clone(),
+                error: None,
+                schema_version: self.schema_version,
+            })
+        };
+        let result = match &self.exactly_once {
+            Some(executor) if !key.is_empty() => executor.execute(&key, apply).await,
+            _ => apply().await,
+        };
+        Ok(result.unwrap_or_else(|e| reject(e.to_string())))
+    }
+}
+
+impl<H> tonic::server::NamedService for ReplicationServer<H> {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl<H: NodeHandler> Service<http::Request<tonic::body::Body>> for ReplicationServer<H> {
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<tonic::body::Body>) -> Self::Future {
+        match req.uri().path() {
+            REPLICATE_PATH => {
+                let method = ReplicateMethod(self.clone());
+                Box::pin(async move {
+                    let codec = tonic_prost::ProstCodec::<proto::Ack, proto::Replicate>::default();
+                    Ok(tonic::server::Grpc::new(codec).unary(method, req).await)
+                })
+            }
+            HANDSHAKE_PATH => {
+                let method = HandshakeMethod(self.clone());
+                Box::pin(async move {
+                    let codec =
+                        tonic_prost::ProstCodec::<proto::Handshake, proto::Handshake>::default();
+                    Ok(tonic::server::Grpc::new(codec).unary(method, req).await)
+                })
+            }
+            _ => Box::pin(async { Ok(Status::unimplemented("unknown method").into_http()) }),
+        }
+    }
+}
+
+struct HandshakeMethod<H>(ReplicationServer<H>);
+
+impl<H: NodeHandler> tonic::server::UnaryService<proto::Handshake> for HandshakeMethod<H> {
+    type Response = proto::Handshake;
+    type Future = BoxFuture<tonic::Response<proto::Handshake>, Status>;
+
+    fn call(&mut self, request: tonic::Request<proto::Handshake>) -> Self::Future {
+        let result = self.0.handshake(request.into_inner());
+        Box::pin(async move { result.map(tonic::Response::new) })
+    }
+}
+
+struct ReplicateMethod<H>(ReplicationServer<H>);
+
+impl<H: NodeHandler> tonic::server::UnaryService<proto::Replicate> for ReplicateMethod<H> {
+    type Response = proto::Ack;
+    type Future = BoxFuture<tonic::Response<proto::Ack>, Status>;
+
+    fn call(&mut self, request: tonic::Request<proto::Replicate>) -> Self::Future {
+        let server = self.0.clone();
+        Box::pin(async move {
+            server
+                .replicate(request.into_inner())
+                .await
+                .map(tonic::Response::new)
+        })
+    }
+}
+
+/// 可池化的 gRPC 通道
+#[derive(Clone)]
+pub struct ReplicationChannel(Channel);
+
+impl ReplicationChannel {
+    pub fn channel(&self) -> Channel {
+        self.0.clone()
+    }
+}
+
+impl Connect for ReplicationChannel {
+    async fn connect(endpoint: &str) -> Result<Self, DistributedError> {
+        let endpoint = Endpoint::from_shared(endpoint.to_string())
+            .map_err(|e| DistributedError::Configuration(format!("{endpoint}: {e}")))?;
+        let channel = endpoint
+            .connect()
+            .await
+            .map_err(|e| DistributedError::Network(e.to_string()))?;
+        Ok(Self(channel))
+    }
+}
+
+/// 客户端传输：节点 ID → 地址，每个节点一个连接池
+pub struct GrpcReplicationTransport {
+    pools: KeyedConnectionPool<ReplicationChannel>,
+    schema: Option<Arc<dyn SchemaTranscoder>>,
+    /// 各节点协商的 schema 版本
+    negotiated: Mutex<HashMap<String, u32>>,
+    capabilities: Option<Arc<PeerCapabilities>>,
+    compressor: Option<Arc<dyn PayloadCompressor>>,
+}
+
+impl GrpcReplicationTransport {
+    /// `endpoints` 为 (节点 ID, `http://host:port`) 列表
+    pub fn new<I, K, V>(endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            pools: KeyedConnectionPool::new(endpoints, PoolConfig::default()),
+            schema: None,
+            negotiated: Mutex::new(HashMap::new()),
+            capabilities: None,
+            compressor: None,
+        }
+    }
+
+    /// 首次向每个节点发送前交换握手，协商结果记入 `capabilities`
+    pub fn with_capabilities(mut self, capabilities: Arc<PeerCapabilities>) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// 对声明支持该算法的节点压缩负载；需同时配置 `with_capabilities`
+    pub fn with_compressor(mut self, compressor: Arc<dyn PayloadCompressor>) -> Self {
+        self.compressor = Some(compressor);
+        self
+    }
+
+    /// 负载为 `schema` 的当前版本编码；发往每个节点前改写为与其协商的版本
+    pub fn with_schema(mut self, schema: Arc<dyn SchemaTranscoder>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// 与 `node` 协商的 schema 版本；尚未交换过确认时为 `None`
+    pub fn negotiated_version(&self, node: &str) -> Option<u32> {
+        self.negotiated.lock().unwrap().get(node).copied()
+    }
+
+    pub fn with_pool_config(mut self, config: PoolConfig) -> Self {
+        self.pools = self.pools.with_config(config);
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: ReconnectBackoff) -> Self {
+        self.pools = self.pools.with_backoff(backoff);
+        self
+    }
+
+    /// 以 mTLS 建连；服务端需经 `TlsContext::incoming` 提供连接
+    pub fn with_tls(mut self, tls: Arc<TlsContext>) -> Self {
+        self.pools = self.pools.with_connector(Arc::new(move |endpoint| {
+            let tls = tls.clone();
+            Box::pin(async move {
+                let endpoint = TlsContext::endpoint(&endpoint)?;
+                Ok(ReplicationChannel(tls.connect(endpoint).await?))
+            })
+        }));
+        self
+    }
+
+    /// 各节点的连接池：统计、空闲回收与故障检测器联动
+    pub fn pools(&self) -> &KeyedConnectionPool<ReplicationChannel> {
+        &self.pools
+    }
+
+    /// 按协商版本发送；对端以更低版本拒绝时降级重发一次
+    async fn call_versioned(
+        &self,
+        node: &str,
+        request: ReplicateRequest,
+        deadline: Deadline,
+    ) -> Result<proto::Ack, DistributedError> {
+        if let Some(capabilities) = &self.capabilities
+            && !capabilities.is_known(node)
+        {
+            let remote = self.handshake(node, capabilities.local(), deadline).await?;
+            capabilities.accept(remote)?;
+        }
+        let Some(schema) = &self.schema else {
+            return self.call(node, request, 0, deadline).await;
+        };
+        let current = schema.current_version();
+        let mut version = self
+            .negotiated_version(node)
+            .or_else(|| self.capabilities.as_ref()?.codec_version(node))
+            .map_or(current, |v| v.min(current));
+        loop {
+            let mut attempt = request.clone();
+            attempt.payload = schema
+                .transcode(&request.payload, version)
+                .map_err(|e| DistributedError::Configuration(format!("{node}: {e}")))?;
+            let ack = self.call(node, attempt, version, deadline).await?;
+            if ack.schema_version == 0 {
+                return Ok(ack);
+            }
+            let agreed = current.min(ack.schema_version);
+            self.negotiated
+                .lock()
+                .unwrap()
+                .insert(node.to_string(), agreed);
+            if ack.applied || agreed >= version {
+                return Ok(ack);
+            }
+            version = agreed;
+        }
+    }
+
+    async fn call(
+        &self,
+        node: &str,
+        request: ReplicateRequest,
+        schema_version: u32,
+        deadline: Deadline,
+    ) -> Result<proto::Ack, DistributedError> {
+        let compressor = self.compressor.as_ref().filter(|c| {
+            self.capabilities
+                .as_ref()
+                .is_some_and(|caps| caps.supports(node, c.feature()))
+        });
+        let (payload, compression) = match compressor {
+            Some(c) => (c.compress(&request.payload), c.feature().as_str()),
+            None => (request.payload, ""),
+        };
+        let mut message = tonic::Request::new(proto::Replicate {
+            idempotency_key: request.idempotency_key,
+            payload,
+            level: level_name(request.level),
+            priority: request.priority.as_str().to_string(),
+            schema_version,
+            compression: compression.to_string(),
+        });
+        message.metadata_mut().insert(
+            PRIORITY_HEADER,
+            MetadataValue::from_static(request.priority.as_str()),
+        );
+        self.unary(node, message, REPLICATE_PATH, deadline).await
+    }
+
+    async fn handshake(
+        &self,
+        node: &str,
+        local: &Handshake,
+        deadline: Deadline,
+    ) -> Result<Handshake, DistributedError> {
+        let message = tonic::Request::new(proto::Handshake::from(local));
+        let remote: proto::Handshake = self.unary(node, message, HANDSHAKE_PATH, deadline).await?;
+        Ok(remote.into())
+    }
+
+    async fn unary<Req, Resp>(
+        &self,
+        node: &str,
+        mut message: tonic::Request<Req>,
+        path: &'static str,
+        deadline: Deadline,
+    ) -> Result<Resp, DistributedError>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        let conn = self.pools.acquire(node).await?;
+        let mut grpc = tonic::client::Grpc::new(conn.channel());
+        grpc.ready()
+            .await
+            .map_err(|e| DistributedError::Network(e.to_string()))?;
+        if let Some(remaining) = deadline.remaining() {
+            message.set_timeout(remaining);
+        }
+        let codec = tonic_prost::ProstCodec::<Req, Resp>::default();
+        match grpc
+            .unary(message, PathAndQuery::from_static(path), codec)
+            .await
+        {
+            Ok(response) => {
+                self.pools.release(conn);
+                Ok(response.into_inner())
+            }
+            Err(status) => {
+                // 不可达的连接直接丢弃，其余错误下连接仍可复用
+                if status.code() != Code::Unavailable {
+                    self.pools.release(conn);
+                }
+                Err(status.into())
+            }
+        }
+    }
+}
+
+impl ReplicationTransport for GrpcReplicationTransport {
+    async fn send(
+        &self,
+        node: &str,
+        request: ReplicateRequest,
+        deadline: Deadline,
+    ) -> Result<ReplicateAck, DistributedError> {
+        let expired = || DistributedError::Network(format!("deadline exceeded for {node}"));
+        let remaining = deadline.remaining().ok_or_else(expired)?;
+        // 本地限时与 grpc-timeout 以同一截止时间为准；到期后的任何失败都报告为超时
+        match tokio::time::timeout(remaining, self.call_versioned(node, request, deadline)).await {
+            Ok(Ok(ack)) => Ok(ReplicateAck {
+                applied: ack.applied,
+                node_id: ack.node_id,
+                error: ack.error,
+            }),
+            Ok(Err(e)) if !deadline.is_expired() => Err(e),
+            _ => Err(expired()),
+        }
+    }
+}
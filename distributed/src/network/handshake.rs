@@ -0,0 +1,245 @@
+//! 节点握手与能力协商
+//!
+//! 设计意图：
+//! - 滚动升级期间新旧节点混跑，可选特性（快照分块、对冲读、负载压缩、命令 schema 版本）
+//!   只能在双方都支持时使用。传输建连时双方交换 `Handshake`：节点 ID、crate 版本、
+//!   支持的特性位集、必需的特性位集与支持的最高命令 schema 版本。
+//! - `PeerCapabilities` 保存本节点的握手与各对端协商结果，传输与复制器在使用可选特性前
+//!   查询 `supports`。尚未握手的对端按最低公共集合处理：不支持任何可选特性。
+//! - 任一方缺少对方必需的特性时协商失败，以 `DistributedError::Configuration` 报告缺失的
+//!   特性名，连接不建立。
+//! - 负载压缩经 `PayloadCompressor` 插拔，算法对应一个特性位；只有对端声明支持该算法时
+//!   才压缩，否则原样发送。
+//!
+//! 不变量（草图）：
+//! - 协商出的特性集合 ⊆ 双方特性集合的交集。
+//! - 协商成功的对端满足双方的必需特性。
+
+use crate::core::errors::DistributedError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// 可协商的可选特性
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Feature {
+    SnapshotChunking,
+    HedgedReads,
+    CompressionLz4,
+    CompressionZstd,
+    /// 命令负载带 schema 版本前缀（见 `codec::UpcastingCodec`）
+    CodecVersioning,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 5] = [
+        Feature::SnapshotChunking,
+        Feature::HedgedReads,
+        Feature::CompressionLz4,
+        Feature::CompressionZstd,
+        Feature::CodecVersioning,
+    ];
+
+    /// 在特性位集中的位
+    pub fn bit(self) -> u64 {
+        1 << (self as u32)
+    }
+
+    /// 线上名称，如 `"snapshot-chunking"`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Feature::SnapshotChunking => "snapshot-chunking",
+            Feature::HedgedReads => "hedged-reads",
+            Feature::CompressionLz4 => "compression-lz4",
+            Feature::CompressionZstd => "compression-zstd",
+            Feature::CodecVersioning => "codec-versioning",
+        }
+    }
+}
+
+/// 特性位集；未知的位（来自更新的节点）被保留但不会与本地特性匹配
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FeatureSet(pub u64);
+
+impl FeatureSet {
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    /// 本版本认识的全部特性
+    pub fn all() -> Self {
+        Feature::ALL.into_iter().collect()
+    }
+
+    pub fn with(mut self, feature: Feature) -> Self {
+        self.0 |= feature.bit();
+        self
+    }
+
+    pub fn contains(self, feature: Feature) -> bool {
+        self.0 & feature.bit() != 0
+    }
+
+    pub fn intersection(self, other: FeatureSet) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// 本集合中 `available` 没有的已知特性
+    pub fn missing_from(self, available: FeatureSet) -> Vec<Feature> {
+        self.iter().filter(|f| !available.contains(*f)).collect()
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Feature> {
+        Feature::ALL.into_iter().filter(move |f| self.contains(*f))
+    }
+}
+
+impl FromIterator<Feature> for FeatureSet {
+    fn from_iter<I: IntoIterator<Item = Feature>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::empty(), FeatureSet::with)
+    }
+}
+
+/// 建连时交换的握手消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub node_id: String,
+    pub crate_version: String,
+    pub features: FeatureSet,
+    /// 对端必须支持的特性
+    pub required: FeatureSet,
+    /// 支持的最高命令 schema 版本；0 表示未版本化
+    pub max_codec_version: u32,
+}
+
+impl Handshake {
+    /// 以本 crate 版本创建，缺省不声明任何特性
+    pub fn new(node_id: impl Into<String>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            features: FeatureSet::empty(),
+            required: FeatureSet::empty(),
+            max_codec_version: 0,
+        }
+    }
+
+    pub fn with_features(mut self, features: FeatureSet) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// 必需的特性同时计入本节点支持的特性
+    pub fn with_required(mut self, required: FeatureSet) -> Self {
+        self.required = required;
+        self.features.0 |= required.0;
+        self
+    }
+
+    pub fn with_max_codec_version(mut self, version: u32) -> Self {
+        self.max_codec_version = version;
+        self
+    }
+}
+
+/// 与单个对端协商的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negotiated {
+    pub peer: Handshake,
+    pub features: FeatureSet,
+    pub codec_version: u32,
+}
+
+/// 本节点的握手与各对端的协商结果
+#[derive(Debug)]
+pub struct PeerCapabilities {
+    local: Handshake,
+    peers: RwLock<HashMap<String, Negotiated>>,
+}
+
+impl PeerCapabilities {
+    pub fn new(local: Handshake) -> Self {
+        Self {
+            local,
+            peers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 发给对端的握手
+    pub fn local(&self) -> &Handshake {
+        &self.local
+    }
+
+    /// 检查对端握手并记录协商结果；任一方缺少对方必需的特性时返回 `Configuration`
+    pub fn accept(&self, remote: Handshake) -> Result<Negotiated, DistributedError> {
+        let incompatible = |who: &str, missing: Vec<Feature>| {
+            let names: Vec<&str> = missing.iter().map(|f| f.as_str()).collect();
+            DistributedError::Configuration(format!(
+                "{who} lacks required capability {}",
+                names.join(", ")
+            ))
+        };
+        let missing = self.local.required.missing_from(remote.features);
+        if !missing.is_empty() {
+            return Err(incompatible(&format!("peer {}", remote.node_id), missing));
+        }
+        let missing = remote.required.missing_from(self.local.features);
+        if !missing.is_empty() {
+            return Err(incompatible(
+                &format!("node {}", self.local.node_id),
+                missing,
+            ));
+        }
+        let negotiated = Negotiated {
+            features: self.local.features.intersection(remote.features),
+            codec_version: self.local.max_codec_version.min(remote.max_codec_version),
+            peer: remote,
+        };
+        self.peers
+            .write()
+            .unwrap()
+            .insert(negotiated.peer.node_id.clone(), negotiated.clone());
+        Ok(negotiated)
+    }
+
+    /// 对端是否已握手
+    pub fn is_known(&self, peer: &str) -> bool {
+        self.peers.read().unwrap().contains_key(peer)
+    }
+
+    /// 与对端协商出的特性；未握手的对端为空集
+    pub fn features(&self, peer: &str) -> FeatureSet {
+        self.peers
+            .read()
+            .unwrap()
+            .get(peer)
+            .map(|n| n.features)
+            .unwrap_or_default()
+    }
+
+    pub fn supports(&self, peer: &str, feature: Feature) -> bool {
+        self.features(peer).contains(feature)
+    }
+
+    /// 与对端协商的命令 schema 版本；未握手或任一方未版本化时为 `None`
+    pub fn codec_version(&self, peer: &str) -> Option<u32> {
+        self.peers
+            .read()
+            .unwrap()
+            .get(peer)
+            .map(|n| n.codec_version)
+            .filter(|v| *v > 0)
+    }
+
+    /// 连接断开后遗忘对端，下次建连重新握手
+    pub fn forget(&self, peer: &str) {
+        self.peers.write().unwrap().remove(peer);
+    }
+}
+
+/// 负载压缩算法；`feature` 为对端须声明支持的特性
+pub trait PayloadCompressor: Send + Sync {
+    fn feature(&self) -> Feature;
+    fn compress(&self, data: &[u8]) -> Vec<u8>;
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, DistributedError>;
+}
@@ -0,0 +1,470 @@
+//! 节点间心跳服务
+//!
+//! 设计意图：
+//! - `HeartbeatService` 按固定周期向会籍中的其他节点并发发送轻量 ping（`PingTransport`，
+//!   语义类似 UDP：丢包表现为超时），ack 的到达时间喂给每个对等节点的 `PhiAccrualFailureDetector`。
+//! - 每轮结束时按 φ 推导 SWIM 状态写入 `MembershipView`：φ ≥ `phi_threshold` 转为 Suspect，
+//!   持续 `suspect_timeout` 仍未恢复则转为 Faulty；φ 回落（收到新的 ack）即恢复 Alive。
+//! - 往返时延（RTT）以 EWMA 平滑后对外暴露（`rtt_estimates`），供 `LeastResponseTimeBalancer` 使用。
+//!
+//! 时间一律取自 `tokio::time::Instant`，在暂停的测试时钟下状态转换发生在确定的模拟时刻。
+//!
+//! 传输实现：`InMemoryPingTransport`（可脚本化延迟/丢包）；`transport-grpc` 特性下另有
+//! `GrpcPingTransport` 与 `HeartbeatServer`（`proto/heartbeat.proto`）。
+
+use crate::core::ClusterMembership;
+use crate::core::errors::DistributedError;
+use crate::swim::{MembershipView, PhiAccrualFailureDetector, SwimMemberState};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::{JoinHandle, JoinSet};
+
+/// 轻量 ping：对端收到后回送 ack 即返回 `Ok`；丢包的 ping 可以永不完成，由调用方限时
+pub trait PingTransport: Send + Sync + 'static {
+    fn ping(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> impl Future<Output = Result<(), DistributedError>> + Send;
+}
+
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    pub interval: Duration,
+    /// 单次 ping 等待 ack 的时间，应小于 `interval`
+    pub timeout: Duration,
+    pub phi_threshold: f64,
+    /// Suspect 持续该时长后转为 Faulty
+    pub suspect_timeout: Duration,
+    /// RTT EWMA 平滑因子，越大越偏向最新样本
+    pub rtt_alpha: f64,
+    pub max_samples: usize,
+    pub min_std_deviation: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_millis(500),
+            phi_threshold: 8.0,
+            suspect_timeout: Duration::from_secs(5),
+            rtt_alpha: 0.2,
+            max_samples: 100,
+            min_std_deviation: Duration::from_millis(100),
+        }
+    }
+}
+
+struct PeerHealth {
+    detector: PhiAccrualFailureDetector,
+    added_at: Instant,
+    rtt: Option<Duration>,
+    suspected_at: Option<Instant>,
+}
+
+struct Inner {
+    peers: HashMap<String, PeerHealth>,
+    view: MembershipView,
+}
+
+pub struct HeartbeatService<T> {
+    me: String,
+    transport: Arc<T>,
+    config: HeartbeatConfig,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl<T> Clone for HeartbeatService<T> {
+    fn clone(&self) -> Self {
+        Self {
+            me: self.me.clone(),
+            transport: self.transport.clone(),
+            config: self.config.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+fn now() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+impl<T: PingTransport> HeartbeatService<T> {
+    /// 对 `membership` 中除自身外的节点发送心跳；初始状态均为 Alive
+    pub fn new(me: impl Into<String>, membership: &ClusterMembership, transport: T) -> Self {
+        let me = me.into();
+        let service = Self {
+            inner: Arc::new(Mutex::new(Inner {
+                peers: HashMap::new(),
+                view: MembershipView::new(me.clone()),
+            })),
+            me,
+            transport: Arc::new(transport),
+            config: HeartbeatConfig::default(),
+        };
+        service.set_membership(membership);
+        service
+    }
+
+    /// 须在首轮探测之前调用；已登记节点的检测器按新配置重建
+    pub fn with_config(mut self, config: HeartbeatConfig) -> Self {
+        self.config = config;
+        for health in self.inner.lock().unwrap().peers.values_mut() {
+            health.detector = self.detector();
+        }
+        self
+    }
+
+    fn detector(&self) -> PhiAccrualFailureDetector {
+        // 以心跳周期作为首个间隔的估计，从未 ack 的节点也会被怀疑
+        PhiAccrualFailureDetector::new(
+            self.config.max_samples,
+            self.config.min_std_deviation,
+            Duration::ZERO,
+        )
+        .with_first_heartbeat_estimate(self.config.interval)
+    }
+
+    /// 同步会籍：新节点以 Alive 加入，离开的节点从检测器与视图中移除
+    pub fn set_membership(&self, membership: &ClusterMembership) {
+        let at = now();
+        let mut guard = self.inner.lock().unwrap();
+        let Inner { peers, view } = &mut *guard;
+        peers.retain(|peer, _| membership.is_member(peer));
+        view.members.retain(|peer, _| membership.is_member(peer));
+        for node in &membership.nodes {
+            let node = node.as_str();
+            if node == self.me || peers.contains_key(node) {
+                continue;
+            }
+            peers.insert(
+                node.to_string(),
+                PeerHealth {
+                    detector: self.detector(),
+                    added_at: at,
+                    rtt: None,
+                    suspected_at: None,
+                },
+            );
+            view.local_update(node, SwimMemberState::Alive, 0);
+        }
+    }
+
+    /// 一轮探测：并发 ping 所有对等节点，记录 ack 后按 φ 更新会籍状态
+    pub async fn probe_round(&self) {
+        let peers: Vec<String> = self.inner.lock().unwrap().peers.keys().cloned().collect();
+        let mut pings = JoinSet::new();
+        for peer in peers {
+            let (transport, me) = (self.transport.clone(), self.me.clone());
+            let timeout = self.config.timeout;
+            pings.spawn(async move {
+                let sent = now();
+                let acked = matches!(
+                    tokio::time::timeout(timeout, transport.ping(&me, &peer)).await,
+                    Ok(Ok(()))
+                );
+                (peer, sent, acked.then(now))
+            });
+        }
+        while let Some(joined) = pings.join_next().await {
+            let Ok((peer, sent, Some(arrived))) = joined else {
+                continue;
+            };
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(health) = inner.peers.get_mut(&peer) {
+                health.detector.heartbeat(arrived);
+                let sample = arrived.saturating_duration_since(sent);
+                health.rtt = Some(match health.rtt {
+                    None => sample,
+                    Some(prev) => {
+                        prev.mul_f64(1.0 - self.config.rtt_alpha)
+                            + sample.mul_f64(self.config.rtt_alpha)
+                    }
+                });
+            }
+        }
+        self.evaluate(now());
+    }
+
+    fn evaluate(&self, at: Instant) {
+        let mut guard = self.inner.lock().unwrap();
+        let Inner { peers, view } = &mut *guard;
+        for (peer, health) in peers.iter_mut() {
+            let phi = if health.detector.last_heartbeat().is_some() {
+                health.detector.phi(at)
+            } else {
+                // 从未 ack：把加入会籍的时刻当作上一次心跳
+                let mut seeded = health.detector.clone();
+                seeded.heartbeat(health.added_at);
+                seeded.phi(at)
+            };
+            let next = if phi < self.config.phi_threshold {
+                health.suspected_at = None;
+                SwimMemberState::Alive
+            } else {
+                let since = *health.suspected_at.get_or_insert(at);
+                if at.saturating_duration_since(since) >= self.config.suspect_timeout {
+                    SwimMemberState::Faulty
+                } else {
+                    SwimMemberState::Suspect
+                }
+            };
+            let (state, incarnation) = view
+                .get_member(peer)
+                .map_or((None, 0), |m| (Some(m.state), m.incarnation));
+            if state != Some(next) {
+                view.local_update(peer, next, incarnation);
+            }
+        }
+    }
+
+    /// 启动周期探测
+    pub fn start(&self) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(service.config.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                service.probe_round().await;
+            }
+        })
+    }
+
+    pub fn phi(&self, peer: &str) -> Option<f64> {
+        let inner = self.inner.lock().unwrap();
+        inner.peers.get(peer).map(|h| h.detector.phi(now()))
+    }
+
+    pub fn state(&self, peer: &str) -> Option<SwimMemberState> {
+        self.inner
+            .lock()
+            .unwrap()
+            .view
+            .get_member(peer)
+            .map(|m| m.state)
+    }
+
+    /// 平滑后的 RTT；尚未收到 ack 时为 `None`
+    pub fn rtt(&self, peer: &str) -> Option<Duration> {
+        self.inner.lock().unwrap().peers.get(peer)?.rtt
+    }
+
+    /// 所有已有样本节点的 RTT EWMA
+    pub fn rtt_estimates(&self) -> HashMap<String, Duration> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .peers
+            .iter()
+            .filter_map(|(peer, h)| h.rtt.map(|rtt| (peer.clone(), rtt)))
+            .collect()
+    }
+
+    pub fn view(&self) -> MembershipView {
+        self.inner.lock().unwrap().view.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Link {
+    delay: Duration,
+    drop: bool,
+}
+
+/// 内存 ping 传输：按目标节点设置延迟与丢包
+#[derive(Clone, Default)]
+pub struct InMemoryPingTransport {
+    links: Arc<Mutex<HashMap<String, Link>>>,
+}
+
+impl InMemoryPingTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 到 `to` 的往返时延
+    pub fn set_delay(&self, to: &str, delay: Duration) {
+        self.links
+            .lock()
+            .unwrap()
+            .entry(to.to_string())
+            .or_default()
+            .delay = delay;
+    }
+
+    /// 丢弃发往 `to` 的 ping（永不 ack）
+    pub fn set_drop(&self, to: &str, drop: bool) {
+        self.links
+            .lock()
+            .unwrap()
+            .entry(to.to_string())
+            .or_default()
+            .drop = drop;
+    }
+}
+
+impl PingTransport for InMemoryPingTransport {
+    async fn ping(&self, _from: &str, to: &str) -> Result<(), DistributedError> {
+        let link = self
+            .links
+            .lock()
+            .unwrap()
+            .get(to)
+            .copied()
+            .unwrap_or_default();
+        if link.drop {
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(link.delay).await;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "transport-grpc")]
+pub use grpc::{GrpcPingTransport, HeartbeatServer};
+
+#[cfg(feature = "transport-grpc")]
+mod grpc {
+    use super::PingTransport;
+    use crate::core::errors::DistributedError;
+    use crate::network::grpc_replication::ReplicationChannel;
+    use crate::network::pool::{ConnectionPool, PoolConfig, PoolError};
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+    use tonic::Status;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::codegen::{BoxFuture, Context, Poll, Service, http};
+
+    pub const SERVICE_NAME: &str = "distributed.heartbeat.v1.Heartbeat";
+    pub const PING_PATH: &str = "/distributed.heartbeat.v1.Heartbeat/Ping";
+
+    /// `proto/heartbeat.proto` 对应的消息
+    pub mod proto {
+        #[derive(Clone, PartialEq, prost::Message)]
+        pub struct Ping {
+            #[prost(string, tag = "1")]
+            pub from: String,
+        }
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        pub struct Pong {
+            #[prost(string, tag = "1")]
+            pub node_id: String,
+        }
+    }
+
+    /// 心跳服务端：对任何 ping 立即回送 pong
+    #[derive(Clone)]
+    pub struct HeartbeatServer {
+        node_id: String,
+    }
+
+    impl HeartbeatServer {
+        pub fn new(node_id: impl Into<String>) -> Self {
+            Self {
+                node_id: node_id.into(),
+            }
+        }
+    }
+
+    impl tonic::server::NamedService for HeartbeatServer {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+
+    impl Service<http::Request<tonic::body::Body>> for HeartbeatServer {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<tonic::body::Body>) -> Self::Future {
+            if req.uri().path() != PING_PATH {
+                return Box::pin(async { Ok(Status::unimplemented("unknown method").into_http()) });
+            }
+            let method = PingMethod(self.node_id.clone());
+            Box::pin(async move {
+                let codec = tonic_prost::ProstCodec::<proto::Pong, proto::Ping>::default();
+                Ok(tonic::server::Grpc::new(codec).unary(method, req).await)
+            })
+        }
+    }
+
+    struct PingMethod(String);
+
+    impl tonic::server::UnaryService<proto::Ping> for PingMethod {
+        type Response = proto::Pong;
+        type Future = std::future::Ready<Result<tonic::Response<proto::Pong>, Status>>;
+
+        fn call(&mut self, _request: tonic::Request<proto::Ping>) -> Self::Future {
+            std::future::ready(Ok(tonic::Response::new(proto::Pong {
+                node_id: self.0.clone(),
+            })))
+        }
+    }
+
+    /// gRPC ping 传输：节点 ID → 地址，每个节点一个连接池
+    pub struct GrpcPingTransport {
+        endpoints: HashMap<String, String>,
+        pools: Mutex<HashMap<String, ConnectionPool<ReplicationChannel>>>,
+    }
+
+    impl GrpcPingTransport {
+        /// `endpoints` 为 (节点 ID, `http://host:port`) 列表
+        pub fn new<I, K, V>(endpoints: I) -> Self
+        where
+            I: IntoIterator<Item = (K, V)>,
+            K: Into<String>,
+            V: Into<String>,
+        {
+            Self {
+                endpoints: endpoints
+                    .into_iter()
+                    .map(|(k, v)| (k.into(), v.into()))
+                    .collect(),
+                pools: Mutex::new(HashMap::new()),
+            }
+        }
+
+        fn pool(&self, node: &str) -> Result<ConnectionPool<ReplicationChannel>, DistributedError> {
+            let endpoint = self
+                .endpoints
+                .get(node)
+                .ok_or_else(|| DistributedError::Configuration(format!("unknown node {node}")))?;
+            let mut pools = self.pools.lock().unwrap();
+            Ok(pools
+                .entry(node.to_string())
+                .or_insert_with(|| ConnectionPool::new(endpoint.clone(), PoolConfig::default()))
+                .clone())
+        }
+    }
+
+    impl PingTransport for GrpcPingTransport {
+        async fn ping(&self, from: &str, to: &str) -> Result<(), DistributedError> {
+            let pool = self.pool(to)?;
+            let conn = pool.acquire().await.map_err(|e| match e {
+                PoolError::Connect(e) => e,
+                other => DistributedError::Network(other.to_string()),
+            })?;
+            let mut grpc = tonic::client::Grpc::new(conn.channel());
+            grpc.ready()
+                .await
+                .map_err(|e| DistributedError::Network(e.to_string()))?;
+            let codec = tonic_prost::ProstCodec::<proto::Ping, proto::Pong>::default();
+            let request = tonic::Request::new(proto::Ping {
+                from: from.to_string(),
+            });
+            // 失败的连接直接丢弃，下次 ping 重新建连
+            grpc.unary(request, PathAndQuery::from_static(PING_PATH), codec)
+                .await?;
+            pool.release(conn);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! 仲裁读的对冲请求
+//!
+//! 设计意图：
+//! - 仲裁读的尾延迟由最慢的那个副本决定。`HedgedReader` 先向调用方给出的前 R 个副本发请求；
+//!   对冲延迟内未凑齐 R 个应答时，向其余副本中延迟估计最低的几个发出重复请求，取最先凑齐的
+//!   R 个应答，其余在途请求随 future 一起丢弃（即取消）。
+//! - 对冲延迟可以固定，也可以取自应答延迟的 EWMA：p95 ≈ 均值 + 1.645 × 标准差。
+//!   每次调用的额外请求（对冲与失败后的替补）不超过 `max_extra`。
+//! - 重复请求只对幂等请求安全：读与显式幂等的写（带幂等键）可以对冲，普通写只发往前 R 个副本。
+//! - 不绑定运行时：对冲与超时定时器经 `TimerService` 调度，时间取自可替换的时钟；
+//!   tokio（`TokioTimer`）与确定性模拟（`SimTimer` + `SimClock`）下行为一致。
+//!
+//! 不变量（草图）：
+//! - 每次调用发出的请求数不超过 min(R + `max_extra`, 副本数)。
+//! - `hedges_won ≤ hedges_issued`；非幂等请求从不计入 `hedges_issued`。
+
+use crate::consistency::ConsistencyLevel;
+use crate::core::TimerService;
+use crate::core::errors::{DistributedError, TimeoutError};
+use crate::storage::replication::{MajorityQuorum, QuorumPolicy};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+use std::time::{Duration, Instant};
+
+pub type HedgeFuture = Pin<Box<dyn Future<Output = Result<Vec<u8>, DistributedError>> + Send>>;
+
+/// 把请求送到单个副本；返回的 future 被丢弃即取消该请求
+pub trait HedgeTransport: Send + Sync + 'static {
+    fn call(&self, node: &str, request: &HedgedRequest) -> HedgeFuture;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestKind {
+    Read,
+    /// 副本按幂等键去重，重复执行无副作用
+    IdempotentWrite {
+        idempotency_key: String,
+    },
+    Write,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HedgedRequest {
+    pub kind: RequestKind,
+    pub payload: Vec<u8>,
+}
+
+impl HedgedRequest {
+    pub fn read(payload: Vec<u8>) -> Self {
+        Self {
+            kind: RequestKind::Read,
+            payload,
+        }
+    }
+
+    pub fn idempotent_write(idempotency_key: impl Into<String>, payload: Vec<u8>) -> Self {
+        Self {
+            kind: RequestKind::IdempotentWrite {
+                idempotency_key: idempotency_key.into(),
+            },
+            payload,
+        }
+    }
+
+    pub fn write(payload: Vec<u8>) -> Self {
+        Self {
+            kind: RequestKind::Write,
+            payload,
+        }
+    }
+
+    /// 只有幂等请求可以发出重复请求
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(self.kind, RequestKind::Write)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HedgeDelay {
+    Fixed(Duration),
+    /// 取应答延迟 EWMA 的 p95；尚无样本时用 `initial`
+    P95 {
+        initial: Duration,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct HedgeConfig {
+    pub delay: HedgeDelay,
+    /// 每次调用最多的额外请求数
+    pub max_extra: usize,
+    /// 整次调用的时间预算
+    pub timeout: Duration,
+    /// 延迟 EWMA 平滑因子，越大越偏向最新样本
+    pub alpha: f64,
+}
+
+impl Default for HedgeConfig {
+    fn default() -> Self {
+        Self {
+            delay: HedgeDelay::P95 {
+                initial: Duration::from_millis(50),
+            },
+            max_extra: 1,
+            timeout: Duration::from_secs(1),
+            alpha: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HedgeStats {
+    calls: AtomicU64,
+    hedges_issued: AtomicU64,
+    hedges_won: AtomicU64,
+}
+
+impl HedgeStats {
+    pub fn calls(&self) -> u64 {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// 额外发出的请求（对冲与失败替补）
+    pub fn hedges_issued(&self) -> u64 {
+        self.hedges_issued.load(Ordering::Relaxed)
+    }
+
+    /// 额外请求的应答进入了最终结果的次数
+    pub fn hedges_won(&self) -> u64 {
+        self.hedges_won.load(Ordering::Relaxed)
+    }
+}
+
+/// 凑齐仲裁的一条应答
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HedgedReply {
+    pub node: String,
+    pub payload: Vec<u8>,
+    /// 是否来自额外请求
+    pub hedged: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct LatencyEwma {
+    mean_ms: f64,
+    var_ms: f64,
+    samples: u64,
+}
+
+impl LatencyEwma {
+    fn observe(&mut self, ms: f64, alpha: f64) {
+        if self.samples == 0 {
+            self.mean_ms = ms;
+        } else {
+            let diff = ms - self.mean_ms;
+            self.mean_ms += alpha * diff;
+            self.var_ms = (1.0 - alpha) * (self.var_ms + alpha * diff * diff);
+        }
+        self.samples += 1;
+    }
+
+    fn p95_ms(&self) -> f64 {
+        self.mean_ms + 1.645 * self.var_ms.sqrt()
+    }
+}
+
+#[derive(Default)]
+struct Latencies {
+    /// 全部应答，用于推导对冲延迟
+    overall: LatencyEwma,
+    /// 每个副本，用于挑选对冲目标
+    per_node: HashMap<String, LatencyEwma>,
+}
+
+/// 定时器回调与轮询之间的共享状态
+#[derive(Default)]
+struct Alarms {
+    hedge_rounds: AtomicU64,
+    expired: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Alarms {
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+struct InFlight {
+    node: String,
+    hedged: bool,
+    sent_at: Duration,
+    future: HedgeFuture,
+}
+
+pub struct HedgedReader<T, S> {
+    transport: Arc<T>,
+    timer: S,
+    config: HedgeConfig,
+    clock: Box<dyn Fn() -> Duration + Send + Sync>,
+    latencies: Mutex<Latencies>,
+    stats: HedgeStats,
+}
+
+impl<T: HedgeTransport, S: TimerService> HedgedReader<T, S> {
+    /// 以本地单调时钟计时
+    pub fn new(transport: T, timer: S) -> Self {
+        let origin = Instant::now();
+        Self {
+            transport: Arc::new(transport),
+            timer,
+            config: HedgeConfig::default(),
+            clock: Box::new(move || origin.elapsed()),
+            latencies: Mutex::new(Latencies::default()),
+            stats: HedgeStats::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: HedgeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 替换时钟（自任意起点经过的时间），须与 `timer` 在同一时间轴上，如 `SimClock::now`
+    pub fn with_clock(mut self, clock: impl Fn() -> Duration + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn config(&self) -> &HedgeConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> &HedgeStats {
+        &self.stats
+    }
+
+    /// 当前的对冲延迟
+    pub fn hedge_delay(&self) -> Duration {
+        match self.config.delay {
+            HedgeDelay::Fixed(delay) => delay,
+            HedgeDelay::P95 { initial } => {
+                let overall = self.latencies.lock().unwrap().overall;
+                if overall.samples == 0 {
+                    initial
+                } else {
+                    Duration::from_secs_f64(overall.p95_ms() / 1000.0)
+                }
+            }
+        }
+    }
+
+    /// 副本的平滑应答延迟；被取消的慢请求以其已等待的时间计入
+    pub fn latency(&self, node: &str) -> Option<Duration> {
+        let latencies = self.latencies.lock().unwrap();
+        let ewma = latencies.per_node.get(node)?;
+        Some(Duration::from_secs_f64(ewma.mean_ms / 1000.0))
+    }
+
+    /// 向 `targets`（按偏好排列）发出请求，返回最先凑齐的仲裁数个应答（按到达顺序）
+    pub async fn call(
+        &self,
+        targets: &[String],
+        level: ConsistencyLevel,
+        request: HedgedRequest,
+    ) -> Result<Vec<HedgedReply>, DistributedError> {
+        if targets.is_empty() {
+            return Err(DistributedError::InvalidState("no replicas to read".into()));
+        }
+        self.stats.calls.fetch_add(1, Ordering::Relaxed);
+        let need = MajorityQuorum::required_acks(targets.len(), level).min(targets.len());
+        let budget = if request.is_idempotent() {
+            self.config.max_extra
+        } else {
+            0
+        };
+        let mut spare = self.fastest_first(&targets[need..]);
+        let alarms = Arc::new(Alarms::default());
+        self.arm(&alarms, self.config.timeout, |a| {
+            a.expired.store(true, Ordering::SeqCst)
+        });
+        if budget > 0 && !spare.is_empty() {
+            self.arm_hedge(&alarms);
+        }
+
+        let mut in_flight: Vec<InFlight> = targets[..need]
+            .iter()
+            .map(|node| self.issue(node, false, &request))
+            .collect();
+        let mut replies: Vec<HedgedReply> = Vec::with_capacity(need);
+        let mut errors: Vec<String> = Vec::new();
+        let mut extra = 0;
+        let mut rounds_seen = 0;
+
+        let outcome = std::future::poll_fn(|cx| {
+            *alarms.waker.lock().unwrap() = Some(cx.waker().clone());
+            loop {
+                let mut i = 0;
+                while i < in_flight.len() {
+                    let Poll::Ready(result) = in_flight[i].future.as_mut().poll(cx) else {
+                        i += 1;
+                        continue;
+                    };
+                    let done = in_flight.swap_remove(i);
+                    match result {
+                        Ok(payload) => {
+                            self.observe(&done.node, self.now().saturating_sub(done.sent_at));
+                            replies.push(HedgedReply {
+                                node: done.node,
+                                payload,
+                                hedged: done.hedged,
+                            });
+                        }
+                        Err(e) => {
+                            errors.push(format!("{}: {e}", done.node));
+                            // 失败的副本立即由下一个替补，计入额外请求
+                            if extra < budget && !spare.is_empty() {
+                                extra += 1;
+                                in_flight.push(self.issue(&spare.remove(0), true, &request));
+                            }
+                        }
+                    }
+                }
+                if replies.len() >= need {
+                    return Poll::Ready(Ok(()));
+                }
+
+                let rounds = alarms.hedge_rounds.load(Ordering::SeqCst);
+                if rounds > rounds_seen && extra < budget && !spare.is_empty() {
+                    rounds_seen = rounds;
+                    let wanted = (need - replies.len()).min(budget - extra).min(spare.len());
+                    for node in spare.drain(..wanted).collect::<Vec<_>>() {
+                        extra += 1;
+                        in_flight.push(self.issue(&node, true, &request));
+                    }
+                    if extra < budget && !spare.is_empty() {
+                        self.arm_hedge(&alarms);
+                    }
+                    // 新请求需要轮询一次以登记唤醒
+                    continue;
+                }
+
+                if alarms.expired.load(Ordering::SeqCst) {
+                    return Poll::Ready(Err(TimeoutError(self.config.timeout).into()));
+                }
+                if in_flight.is_empty() {
+                    return Poll::Ready(Err(DistributedError::Network(format!(
+                        "replies {}/{need}: {}",
+                        replies.len(),
+                        errors.join("; ")
+                    ))));
+                }
+                return Poll::Pending;
+            }
+        })
+        .await;
+
+        // 仍在途的请求随 `in_flight` 丢弃；以已等待的时间惩罚这些副本的延迟估计
+        let now = self.now();
+        let mut latencies = self.latencies.lock().unwrap();
+        for pending in &in_flight {
+            let waited = now.saturating_sub(pending.sent_at).as_secs_f64() * 1000.0;
+            let ewma = latencies.per_node.entry(pending.node.clone()).or_default();
+            if ewma.samples == 0 || waited > ewma.mean_ms {
+                ewma.observe(waited, self.config.alpha);
+            }
+        }
+        drop(latencies);
+
+        self.stats
+            .hedges_issued
+            .fetch_add(extra as u64, Ordering::Relaxed);
+        outcome?;
+        let won = replies.iter().filter(|r| r.hedged).count();
+        self.stats
+            .hedges_won
+            .fetch_add(won as u64, Ordering::Relaxed);
+        Ok(replies)
+    }
+
+    fn now(&self) -> Duration {
+        (self.clock)()
+    }
+
+    fn issue(&self, node: &str, hedged: bool, request: &HedgedRequest) -> InFlight {
+        InFlight {
+            node: node.to_string(),
+            hedged,
+            sent_at: self.now(),
+            future: self.transport.call(node, request),
+        }
+    }
+
+    fn observe(&self, node: &str, latency: Duration) {
+        let ms = latency.as_secs_f64() * 1000.0;
+        let alpha = self.config.alpha;
+        let mut latencies = self.latencies.lock().unwrap();
+        latencies.overall.observe(ms, alpha);
+        latencies
+            .per_node
+            .entry(node.to_string())
+            .or_default()
+            .observe(ms, alpha);
+    }
+
+    /// 按平滑延迟升序；尚无样本的副本排在最前以便探测，同类保持调用方顺序
+    fn fastest_first(&self, nodes: &[String]) -> Vec<String> {
+        let latencies = self.latencies.lock().unwrap();
+        let mut nodes = nodes.to_vec();
+        nodes.sort_by(|a, b| {
+            let key = |n: &String| latencies.per_node.get(n).map_or(0.0, |e| e.mean_ms);
+            key(a).total_cmp(&key(b))
+        });
+        nodes
+    }
+
+    fn arm_hedge(&self, alarms: &Arc<Alarms>) {
+        self.arm(alarms, self.hedge_delay(), |a| {
+            a.hedge_rounds.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+
+    fn arm(&self, alarms: &Arc<Alarms>, after: Duration, fire: fn(&Alarms)) {
+        let alarms = Arc::downgrade(alarms);
+        let ms = (after.as_secs_f64() * 1000.0).ceil().max(1.0) as u64;
+        self.timer.after_ms(ms, move || {
+            if let Some(alarms) = alarms.upgrade() {
+                fire(&alarms);
+                alarms.wake();
+            }
+        });
+    }
+}
@@ -0,0 +1,306 @@
+//! gRPC 拦截器链
+//!
+//! 设计意图：
+//! - tonic 一个服务只挂一个 `Interceptor`；`InterceptorChain` 把多个拦截器按顺序组合成一个，
+//!   每个拦截器拿到上一个的输出，任一返回 `Err(Status)` 即短路，后续拦截器不再调用。
+//! - 链可 `Clone`：克隆共享同一组拦截器实例，限流等有状态拦截器在服务副本间共享状态。
+//! - 内置三个拦截器：
+//!   - `JwtInterceptor`：校验 `authorization: Bearer <jwt>`（HS256、`exp`/`nbf`、可选
+//!     `iss`/`aud`），通过后把 `JwtClaims` 放入请求扩展；
+//!   - `RateLimitInterceptor`：令牌桶（`security::TokenBucket`），耗尽时 `ResourceExhausted`；
+//!     可选豁免 `x-priority: control` 的控制面请求，使其不被数据流量挤占；
+//!   - `TracingInterceptor`：沿用或生成 `x-trace-id`，写回元数据与请求扩展。
+//!
+//! 用法：`chain![auth, rate_limit, tracing]`，再经 `InterceptedService::new(svc, chain)` 挂载。
+
+use crate::security::TokenBucket;
+use crate::storage::replication::Priority;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tonic::metadata::MetadataValue;
+use tonic::{Request, Status};
+
+/// 供 `chain!` 与调用方使用，无需直接依赖 tonic 的路径
+pub use tonic::service::Interceptor;
+
+/// 按顺序组合多个拦截器；按 `chain!` 或 `new` 的顺序执行
+#[derive(Clone, Default)]
+pub struct InterceptorChain {
+    interceptors: Arc<Mutex<Vec<Box<dyn Interceptor + Send>>>>,
+}
+
+impl InterceptorChain {
+    pub fn new(interceptors: Vec<Box<dyn Interceptor + Send>>) -> Self {
+        Self {
+            interceptors: Arc::new(Mutex::new(interceptors)),
+        }
+    }
+
+    /// 在链尾追加一个拦截器
+    pub fn with(self, interceptor: impl Interceptor + Send + 'static) -> Self {
+        self.interceptors
+            .lock()
+            .unwrap()
+            .push(Box::new(interceptor));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.interceptors.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Interceptor for InterceptorChain {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let mut interceptors = self.interceptors.lock().unwrap();
+        for interceptor in interceptors.iter_mut() {
+            request = interceptor.call(request)?;
+        }
+        Ok(request)
+    }
+}
+
+/// 按顺序组合拦截器：`chain![auth, rate_limit, tracing]`
+#[macro_export]
+macro_rules! chain {
+    ($($interceptor:expr),* $(,)?) => {
+        $crate::network::interceptors::InterceptorChain::new(vec![
+            $(Box::new($interceptor)
+                as Box<dyn $crate::network::interceptors::Interceptor + Send>),*
+        ])
+    };
+}
+
+/// 校验通过的 JWT 载荷，放在请求扩展中
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtClaims(pub serde_json::Value);
+
+impl JwtClaims {
+    pub fn subject(&self) -> Option<&str> {
+        self.0.get("sub").and_then(|v| v.as_str())
+    }
+}
+
+/// HS256 JWT 校验
+#[derive(Clone)]
+pub struct JwtInterceptor {
+    secret: Vec<u8>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    /// `exp`/`nbf` 的时钟偏差容忍（秒）
+    leeway_secs: u64,
+}
+
+impl JwtInterceptor {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            issuer: None,
+            audience: None,
+            leeway_secs: 0,
+        }
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    pub fn with_leeway_secs(mut self, leeway_secs: u64) -> Self {
+        self.leeway_secs = leeway_secs;
+        self
+    }
+
+    /// 用同一密钥签发 HS256 令牌（测试与内部服务间调用）
+    pub fn issue(&self, claims: &serde_json::Value) -> String {
+        let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+        let signing_input = format!("{header}.{payload}");
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&signing_input).finalize().into_bytes());
+        format!("{signing_input}.{signature}")
+    }
+
+    /// 校验令牌并返回载荷
+    pub fn verify(&self, token: &str) -> Result<JwtClaims, Status> {
+        let invalid = |why: &str| Status::unauthenticated(format!("invalid token: {why}"));
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid("malformed"));
+        };
+
+        let decode = |part: &str| {
+            URL_SAFE_NO_PAD
+                .decode(part)
+                .map_err(|_| invalid("encoding"))
+        };
+        let header: serde_json::Value =
+            serde_json::from_slice(&decode(header)?).map_err(|_| invalid("header"))?;
+        if header.get("alg").and_then(|a| a.as_str()) != Some("HS256") {
+            return Err(invalid("unsupported alg"));
+        }
+        let signing_input = &token[..token.len() - signature.len() - 1];
+        self.mac(signing_input)
+            .verify_slice(&decode(signature)?)
+            .map_err(|_| invalid("signature"))?;
+
+        let claims: serde_json::Value =
+            serde_json::from_slice(&decode(payload)?).map_err(|_| invalid("claims"))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let number = |name: &str| claims.get(name).and_then(|v| v.as_u64());
+        match number("exp") {
+            Some(exp) if exp + self.leeway_secs > now => {}
+            Some(_) => return Err(invalid("expired")),
+            None => return Err(invalid("missing exp")),
+        }
+        if number("nbf").is_some_and(|nbf| nbf > now + self.leeway_secs) {
+            return Err(invalid("not yet valid"));
+        }
+        if let Some(issuer) = &self.issuer
+            && claims.get("iss").and_then(|v| v.as_str()) != Some(issuer.as_str())
+        {
+            return Err(invalid("issuer"));
+        }
+        if let Some(audience) = &self.audience {
+            let matches = match claims.get("aud") {
+                Some(serde_json::Value::String(aud)) => aud == audience,
+                Some(serde_json::Value::Array(auds)) => {
+                    auds.iter().any(|a| a.as_str() == Some(audience.as_str()))
+                }
+                _ => false,
+            };
+            if !matches {
+                return Err(invalid("audience"));
+            }
+        }
+        Ok(JwtClaims(claims))
+    }
+
+    fn mac(&self, signing_input: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(signing_input.as_bytes());
+        mac
+    }
+}
+
+impl Interceptor for JwtInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("missing bearer token"))?;
+        let claims = self.verify(token)?;
+        request.extensions_mut().insert(claims);
+        Ok(request)
+    }
+}
+
+/// 复制命令优先级的元数据键，值为 `Priority::as_str`
+pub const PRIORITY_HEADER: &str = "x-priority";
+
+/// 令牌桶限流；克隆共享同一个桶
+#[derive(Clone)]
+pub struct RateLimitInterceptor {
+    bucket: Arc<Mutex<TokenBucket>>,
+    exempt_control: bool,
+}
+
+impl RateLimitInterceptor {
+    /// `capacity` 为突发上限，`refill_per_sec` 为每秒补充的令牌数
+    pub fn new(capacity: u64, refill_per_sec: u64) -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(TokenBucket::new(capacity, refill_per_sec))),
+            exempt_control: false,
+        }
+    }
+
+    /// 为 `true` 时 `Control` 优先级的请求不受限，也不消耗令牌
+    pub fn with_control_exempt(mut self, exempt: bool) -> Self {
+        self.exempt_control = exempt;
+        self
+    }
+}
+
+impl Interceptor for RateLimitInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let control = request
+            .metadata()
+            .get(PRIORITY_HEADER)
+            .is_some_and(|v| v.as_bytes() == Priority::Control.as_str().as_bytes());
+        if (self.exempt_control && control) || self.bucket.lock().unwrap().allow() {
+            Ok(request)
+        } else {
+            Err(Status::resource_exhausted("rate limit exceeded"))
+        }
+    }
+}
+
+/// 请求的追踪 ID，放在请求扩展中
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceId(pub String);
+
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// 沿用调用方的 `x-trace-id`，缺失时生成一个并写回元数据
+#[derive(Clone, Default)]
+pub struct TracingInterceptor {
+    seq: Arc<AtomicU64>,
+    seed: RandomState,
+}
+
+impl TracingInterceptor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn next_id(&self) -> String {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        format!("{:016x}{seq:08x}", self.seed.hash_one(seq))
+    }
+}
+
+impl Interceptor for TracingInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let existing = request
+            .metadata()
+            .get(TRACE_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let trace_id = match existing {
+            Some(id) => id,
+            None => {
+                let id = self.next_id();
+                let value = MetadataValue::try_from(id.as_str())
+                    .map_err(|_| Status::internal("invalid trace id"))?;
+                request.metadata_mut().insert(TRACE_ID_HEADER, value);
+                id
+            }
+        };
+        #[cfg(feature = "observability")]
+        tracing::debug!(trace_id = %trace_id, "grpc request");
+        request.extensions_mut().insert(TraceId(trace_id));
+        Ok(request)
+    }
+}
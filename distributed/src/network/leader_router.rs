@@ -0,0 +1,269 @@
+//! 领导者感知的请求路由
+//!
+//! 设计意图：
+//! - 客户端按共识组缓存当前领导者，写请求直接发往缓存的领导者；未知时依次尝试组成员。
+//! - 节点以 `RouteError::NotLeader { hint }` 拒绝非领导者请求：带提示时更新缓存并立即
+//!   重试提示的节点；不带提示（通常选举进行中）时清空缓存并按 `RetryPolicy` 退避后
+//!   再试下一个成员，避免重试风暴。总尝试次数不超过 `1 + max_retries`。
+//! - 读请求可按 `ReadPreference::BoundedStaleness` 在落后不超过上限的跟随者之间轮询，
+//!   没有合格跟随者或跟随者失败时回到领导者。
+//! - 会籍变更（`on_membership_change` / `on_swim_event`）使失效节点的缓存作废。
+//!
+//! 不变量（草图）：
+//! - 缓存的领导者总是组成员（会籍变更移除成员时同步作废）。
+//! - 带提示的重定向不退避；只有无提示拒绝与节点错误才退避。
+
+use crate::core::ClusterMembership;
+use crate::core::errors::DistributedError;
+use crate::network::RetryPolicy;
+use crate::swim::{SwimEvent, SwimMemberState};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RouteError {
+    /// 节点不是领导者；`hint` 为其已知的领导者
+    #[error("not the leader (hint: {hint:?})")]
+    NotLeader { hint: Option<String> },
+    #[error("unknown consensus group {0}")]
+    UnknownGroup(String),
+    #[error(transparent)]
+    Node(#[from] DistributedError),
+}
+
+/// 读请求的目标选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadPreference {
+    /// 只读领导者（线性一致）
+    Leader,
+    /// 允许读落后不超过该上限的跟随者
+    BoundedStaleness(Duration),
+}
+
+#[derive(Debug, Default)]
+struct GroupRoute {
+    members: Vec<String>,
+    leader: Option<String>,
+    /// 跟随者最近报告的落后程度
+    staleness: HashMap<String, Duration>,
+    /// 无已知领导者时下一个尝试的成员
+    probe: usize,
+    /// 跟随者读的轮询位置
+    read_cursor: usize,
+}
+
+impl GroupRoute {
+    fn write_target(&mut self) -> Option<String> {
+        if let Some(leader) = &self.leader {
+            return Some(leader.clone());
+        }
+        if self.members.is_empty() {
+            return None;
+        }
+        let target = self.members[self.probe % self.members.len()].clone();
+        self.probe = self.probe.wrapping_add(1);
+        Some(target)
+    }
+
+    fn read_target(&mut self, max_staleness: Duration) -> Option<String> {
+        let eligible: Vec<&String> = self
+            .members
+            .iter()
+            .filter(|m| Some(*m) != self.leader.as_ref())
+            .filter(|m| self.staleness.get(*m).is_some_and(|s| *s <= max_staleness))
+            .collect();
+        if eligible.is_empty() {
+            return None;
+        }
+        let target = eligible[self.read_cursor % eligible.len()].clone();
+        self.read_cursor = self.read_cursor.wrapping_add(1);
+        Some(target)
+    }
+}
+
+/// 按共识组缓存领导者的请求路由器
+pub struct LeaderRouter {
+    groups: RwLock<HashMap<String, GroupRoute>>,
+    policy: RetryPolicy,
+}
+
+impl Default for LeaderRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LeaderRouter {
+    pub fn new() -> Self {
+        Self {
+            groups: RwLock::new(HashMap::new()),
+            policy: RetryPolicy {
+                max_retries: 3,
+                retry_on_empty: false,
+                backoff_base_ms: Some(50),
+            },
+        }
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 注册或更新共识组成员；不在新成员中的缓存领导者随之作废
+    pub fn set_group(&self, group: &str, members: Vec<String>) {
+        let mut groups = self.groups.write().unwrap();
+        let route = groups.entry(group.to_string()).or_default();
+        if route.leader.as_ref().is_some_and(|l| !members.contains(l)) {
+            route.leader = None;
+        }
+        route.staleness.retain(|node, _| members.contains(node));
+        route.members = members;
+    }
+
+    pub fn current_leader(&self, group: &str) -> Option<String> {
+        self.groups
+            .read()
+            .unwrap()
+            .get(group)
+            .and_then(|r| r.leader.clone())
+    }
+
+    /// 外部得知领导者时（如心跳、状态查询）直接更新缓存
+    pub fn set_leader(&self, group: &str, leader: &str) {
+        if let Some(route) = self.groups.write().unwrap().get_mut(group) {
+            route.leader = Some(leader.to_string());
+        }
+    }
+
+    pub fn invalidate(&self, group: &str) {
+        if let Some(route) = self.groups.write().unwrap().get_mut(group) {
+            route.leader = None;
+        }
+    }
+
+    /// 记录跟随者的落后程度，供有界陈旧读选择
+    pub fn report_staleness(&self, group: &str, node: &str, staleness: Duration) {
+        if let Some(route) = self.groups.write().unwrap().get_mut(group)
+            && route.members.iter().any(|m| m == node)
+        {
+            route.staleness.insert(node.to_string(), staleness);
+        }
+    }
+
+    /// 会籍变更钩子：对所有包含离开节点的组同步成员
+    pub fn on_membership_change(&self, membership: &ClusterMembership) {
+        let mut groups = self.groups.write().unwrap();
+        for route in groups.values_mut() {
+            route.members.retain(|m| membership.is_member(m));
+            if route
+                .leader
+                .as_ref()
+                .is_some_and(|l| !membership.is_member(l))
+            {
+                route.leader = None;
+            }
+            route.staleness.retain(|node, _| membership.is_member(node));
+        }
+    }
+
+    /// SWIM 事件钩子：节点被怀疑或判定失效时作废以其为领导者的缓存，并停止向其读
+    pub fn on_swim_event(&self, event: &SwimEvent) {
+        if event.state == SwimMemberState::Alive {
+            return;
+        }
+        let mut groups = self.groups.write().unwrap();
+        for route in groups.values_mut() {
+            if route.leader.as_deref() == Some(event.node_id.as_str()) {
+                route.leader = None;
+            }
+            route.staleness.remove(&event.node_id);
+        }
+    }
+
+    /// 把写请求路由到领导者，按 `NotLeader` 提示重定向
+    pub async fn write<F, Fut, R>(&self, group: &str, mut call: F) -> Result<R, RouteError>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<R, RouteError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let target = self
+                .groups
+                .write()
+                .unwrap()
+                .get_mut(group)
+                .ok_or_else(|| RouteError::UnknownGroup(group.to_string()))?
+                .write_target()
+                .ok_or_else(|| RouteError::UnknownGroup(group.to_string()))?;
+
+            let err = match call(target.clone()).await {
+                Ok(response) => {
+                    self.confirm_leader(group, &target);
+                    return Ok(response);
+                }
+                Err(err) => err,
+            };
+            if attempt >= self.policy.max_retries {
+                return Err(err);
+            }
+            attempt += 1;
+            match &err {
+                RouteError::NotLeader { hint: Some(hint) } if *hint != target => {
+                    self.set_leader(group, hint);
+                }
+                _ => {
+                    self.invalidate(group);
+                    self.backoff(attempt).await;
+                }
+            }
+        }
+    }
+
+    /// 按读偏好选择目标；跟随者失败时回到领导者路径
+    pub async fn read<F, Fut, R>(
+        &self,
+        group: &str,
+        preference: ReadPreference,
+        mut call: F,
+    ) -> Result<R, RouteError>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<R, RouteError>>,
+    {
+        if let ReadPreference::BoundedStaleness(max) = preference {
+            let follower = self
+                .groups
+                .write()
+                .unwrap()
+                .get_mut(group)
+                .ok_or_else(|| RouteError::UnknownGroup(group.to_string()))?
+                .read_target(max);
+            if let Some(follower) = follower
+                && let Ok(response) = call(follower).await
+            {
+                return Ok(response);
+            }
+        }
+        self.write(group, call).await
+    }
+
+    fn confirm_leader(&self, group: &str, node: &str) {
+        if let Some(route) = self.groups.write().unwrap().get_mut(group)
+            && route.leader.as_deref() != Some(node)
+        {
+            route.leader = Some(node.to_string());
+        }
+    }
+
+    async fn backoff(&self, attempt: usize) {
+        if let Some(base) = self.policy.backoff_base_ms {
+            let delay = base.saturating_mul(1u64 << (attempt - 1).min(16));
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+    }
+}
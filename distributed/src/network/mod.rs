@@ -12,6 +12,20 @@
 //! 参考：gRPC/gobrpc 设计、SRE 背压与流控章节、断路器与限流模式。
 
 pub mod distributed_lock;
+#[cfg(feature = "transport-grpc")]
+pub mod grpc_replication;
+pub mod handshake;
+pub mod hedging;
+#[cfg(feature = "runtime-tokio")]
+pub mod heartbeat;
+#[cfg(feature = "grpc")]
+pub mod interceptors;
+#[cfg(feature = "runtime-tokio")]
+pub mod leader_router;
+#[cfg(feature = "runtime-tokio")]
+pub mod pool;
+#[cfg(feature = "grpc")]
+pub mod tls;
 
 use crate::core::errors::DistributedError;
 use serde::{Deserialize, Serialize};
@@ -24,6 +38,39 @@ use tokio::sync::{mpsc, oneshot};
 #[cfg(feature = "runtime-tokio")]
 use tokio::time::timeout;
 
+/// 请求截止时间；跨调用传递同一个时间预算
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            at: Instant::now() + timeout,
+        }
+    }
+
+    pub fn at(instant: Instant) -> Self {
+        Self { at: instant }
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// 剩余时间；已到期时为 `None`
+    pub fn remaining(&self) -> Option<Duration> {
+        self.at
+            .checked_duration_since(Instant::now())
+            .filter(|d| !d.is_zero())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_none()
+    }
+}
+
 /// RPC 请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RpcRequest {
@@ -0,0 +1,650 @@
+//! 通用后端连接池
+//!
+//! 设计意图：
+//! - 复用昂贵的后端连接（如 gRPC 通道），避免每个请求都重新建连。
+//! - `Semaphore` 限制同时借出的连接数；空闲连接放入 `VecDeque`，按 LIFO 复用以保持连接热度。
+//!
+//! - `KeyedConnectionPool` 按节点管理多个端点池：建连失败后按指数退避惰性重连（退避期内的
+//!   借出直接失败），故障检测器判定可疑/故障的节点连接被主动关闭、借出快速失败。
+//! - 空闲计时使用 `tokio::time::Instant`，测试可用暂停的时钟驱动退避与空闲回收。
+//!
+//! 不变量（草图）：
+//! - 池内连接总数（借出 + 空闲）不超过 `max_size`：仅当空闲队列为空时才新建连接。
+//! - 健康检查失败的连接在借出前与归还时被丢弃，不会被复用。
+//! - 空闲超过 `max_idle` 的连接被关闭，但保留至少 `min_idle` 个空闲连接。
+
+use crate::core::errors::DistributedError;
+use crate::monitoring::{Histogram, HistogramData, MetricLabels};
+use crate::swim::{MembershipView, SwimMemberState};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// 可由端点地址建立的连接
+pub trait Connect: Clone + Send + Sync + 'static {
+    fn connect(endpoint: &str) -> impl Future<Output = Result<Self, DistributedError>> + Send;
+}
+
+/// 连接健康检查函数
+pub type HealthCheckFn<C> = Arc<dyn Fn(&C) -> bool + Send + Sync>;
+
+/// 自定义建连函数（如带 TLS 的连接器），替代 `Connect::connect`
+pub type ConnectFn<C> = Arc<
+    dyn Fn(String) -> Pin<Box<dyn Future<Output = Result<C, DistributedError>> + Send>>
+        + Send
+        + Sync,
+>;
+
+#[derive(Debug, Error)]
+pub enum PoolError {
+    #[error("acquire timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("pool closed")]
+    Closed,
+    #[error("connect failed: {0}")]
+    Connect(#[from] DistributedError),
+}
+
+/// 连接池配置
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    pub max_size: usize,
+    pub min_idle: usize,
+    /// 空闲连接最长保留时间（max_idle_ms）
+    pub max_idle: Duration,
+    pub acquire_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 10,
+            min_idle: 0,
+            max_idle: Duration::from_millis(300_000),
+            acquire_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+struct IdleConnection<C> {
+    conn: C,
+    idle_since: Instant,
+}
+
+struct PoolInner<C> {
+    endpoint: String,
+    config: PoolConfig,
+    semaphore: Arc<Semaphore>,
+    idle: Mutex<VecDeque<IdleConnection<C>>>,
+}
+
+/// 借出的连接；归还请调用 `ConnectionPool::release`，直接丢弃则关闭连接并释放名额
+pub struct PooledConnection<C> {
+    conn: C,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<C> PooledConnection<C> {
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+impl<C> Deref for PooledConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.conn
+    }
+}
+
+impl<C> DerefMut for PooledConnection<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.conn
+    }
+}
+
+/// 面向单个后端端点的连接池
+#[derive(Clone)]
+pub struct ConnectionPool<C: Connect> {
+    inner: Arc<PoolInner<C>>,
+    health_check: Option<HealthCheckFn<C>>,
+    connector: Option<ConnectFn<C>>,
+}
+
+impl<C: Connect> ConnectionPool<C> {
+    pub fn new(endpoint: impl Into<String>, config: PoolConfig) -> Self {
+        let max_size = config.max_size.max(1);
+        Self {
+            inner: Arc::new(PoolInner {
+                endpoint: endpoint.into(),
+                config: PoolConfig { max_size, ..config },
+                semaphore: Arc::new(Semaphore::new(max_size)),
+                idle: Mutex::new(VecDeque::new()),
+            }),
+            health_check: None,
+            connector: None,
+        }
+    }
+
+    /// 设置借出前/归还时执行的健康检查
+    pub fn with_health_check(mut self, check: impl Fn(&C) -> bool + Send + Sync + 'static) -> Self {
+        self.health_check = Some(Arc::new(check));
+        self
+    }
+
+    /// 以 `connector(endpoint)` 新建连接，替代 `C::connect`
+    pub fn with_connector(mut self, connector: ConnectFn<C>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    /// 借出连接：优先复用健康的空闲连接，否则新建；超过 `max_size` 时等待至超时
+    pub async fn acquire(&self) -> Result<PooledConnection<C>, PoolError> {
+        let timeout = self.inner.config.acquire_timeout;
+        let permit = tokio::time::timeout(timeout, self.inner.semaphore.clone().acquire_owned())
+            .await
+            .map_err(|_| PoolError::Timeout(timeout))?
+            .map_err(|_| PoolError::Closed)?;
+
+        while let Some(conn) = self.pop_idle() {
+            if self.is_healthy(&conn) {
+                return Ok(PooledConnection {
+                    conn,
+                    _permit: permit,
+                });
+            }
+        }
+
+        let conn = match &self.connector {
+            Some(connect) => connect(self.inner.endpoint.clone()).await?,
+            None => C::connect(&self.inner.endpoint).await?,
+        };
+        Ok(PooledConnection {
+            conn,
+            _permit: permit,
+        })
+    }
+
+    /// 归还连接；健康检查失败的连接直接关闭
+    pub fn release(&self, conn: PooledConnection<C>) {
+        let PooledConnection { conn, _permit } = conn;
+        if !self.is_healthy(&conn) {
+            return;
+        }
+        self.inner.idle.lock().unwrap().push_back(IdleConnection {
+            conn,
+            idle_since: Instant::now(),
+        });
+    }
+
+    /// 关闭空闲超过 `max_idle` 的连接（保留 `min_idle` 个）
+    pub fn reap_idle(&self) {
+        let mut idle = self.inner.idle.lock().unwrap();
+        Self::evict_expired(&mut idle, &self.inner.config);
+    }
+
+    pub fn idle_count(&self) -> usize {
+        self.inner.idle.lock().unwrap().len()
+    }
+
+    /// 当前借出的连接数
+    pub fn in_use(&self) -> usize {
+        self.inner.config.max_size - self.inner.semaphore.available_permits()
+    }
+
+    pub fn close(&self) {
+        self.inner.semaphore.close();
+        self.inner.idle.lock().unwrap().clear();
+    }
+
+    fn pop_idle(&self) -> Option<C> {
+        let mut idle = self.inner.idle.lock().unwrap();
+        Self::evict_expired(&mut idle, &self.inner.config);
+        idle.pop_back().map(|c| c.conn)
+    }
+
+    /// 队首为最久未用的连接
+    fn evict_expired(idle: &mut VecDeque<IdleConnection<C>>, config: &PoolConfig) {
+        while idle.len() > config.min_idle
+            && idle
+                .front()
+                .is_some_and(|c| c.idle_since.elapsed() >= config.max_idle)
+        {
+            idle.pop_front();
+        }
+    }
+
+    fn is_healthy(&self, conn: &C) -> bool {
+        self.health_check.as_ref().is_none_or(|check| check(conn))
+    }
+}
+
+/// 建连失败后的重连退避：第 n 次连续失败后等待 `min(initial * 2^(n-1), max)`
+#[derive(Debug, Clone)]
+pub struct ReconnectBackoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectBackoff {
+    pub fn delay(&self, failures: u32) -> Duration {
+        let exp = failures.saturating_sub(1).min(30);
+        self.initial.saturating_mul(1 << exp).min(self.max)
+    }
+}
+
+/// 借出等待时间直方图的桶上界（毫秒）
+const ACQUIRE_WAIT_BUCKETS_MS: [f64; 10] =
+    [0.1, 0.5, 1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0, 5000.0];
+
+/// `KeyedConnectionPool` 的计数
+#[derive(Debug)]
+pub struct KeyedPoolStats {
+    connect_failures: AtomicU64,
+    fail_fast: AtomicU64,
+    suspected_closes: AtomicU64,
+    acquire_wait: Histogram,
+}
+
+impl Default for KeyedPoolStats {
+    fn default() -> Self {
+        Self {
+            connect_failures: AtomicU64::new(0),
+            fail_fast: AtomicU64::new(0),
+            suspected_closes: AtomicU64::new(0),
+            acquire_wait: Histogram::new(
+                "pool_acquire_wait_ms".to_string(),
+                MetricLabels::new(),
+                ACQUIRE_WAIT_BUCKETS_MS.to_vec(),
+            ),
+        }
+    }
+}
+
+impl KeyedPoolStats {
+    pub fn connect_failures(&self) -> u64 {
+        self.connect_failures.load(Ordering::Relaxed)
+    }
+
+    /// 因退避或节点可疑而未尝试建连即失败的借出
+    pub fn fail_fast(&self) -> u64 {
+        self.fail_fast.load(Ordering::Relaxed)
+    }
+
+    /// 因节点可疑/故障而主动关闭的端点池
+    pub fn suspected_closes(&self) -> u64 {
+        self.suspected_closes.load(Ordering::Relaxed)
+    }
+
+    /// 成功借出的等待时间（毫秒）
+    pub fn acquire_wait(&self) -> HistogramData {
+        self.acquire_wait.get_data()
+    }
+}
+
+/// 从 `KeyedConnectionPool` 借出的连接；经 `KeyedConnectionPool::release` 归还
+pub struct KeyedConnection<C> {
+    node: String,
+    generation: u64,
+    conn: PooledConnection<C>,
+}
+
+impl<C> KeyedConnection<C> {
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+}
+
+impl<C> Deref for KeyedConnection<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.conn
+    }
+}
+
+struct EndpointState<C: Connect> {
+    pool: Option<ConnectionPool<C>>,
+    /// 每次关闭端点池时递增；旧代的连接归还时直接关闭
+    generation: u64,
+    failures: u32,
+    retry_at: Option<Instant>,
+    suspected: bool,
+}
+
+/// 按节点组织的连接池：节点 ID → 地址，每个节点一个 `ConnectionPool`
+pub struct KeyedConnectionPool<C: Connect> {
+    endpoints: HashMap<String, String>,
+    config: PoolConfig,
+    backoff: ReconnectBackoff,
+    health_check: Option<HealthCheckFn<C>>,
+    connector: Option<ConnectFn<C>>,
+    state: Mutex<HashMap<String, EndpointState<C>>>,
+    stats: KeyedPoolStats,
+}
+
+impl<C: Connect> KeyedConnectionPool<C> {
+    /// `endpoints` 为 (节点 ID, 地址) 列表；每个端点最多 `config.max_size` 个连接
+    pub fn new<I, K, V>(endpoints: I, config: PoolConfig) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+            config,
+            backoff: ReconnectBackoff::default(),
+            health_check: None,
+            connector: None,
+            state: Mutex::new(HashMap::new()),
+            stats: KeyedPoolStats::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: PoolConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: ReconnectBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// 各端点池借出前/归还时执行的健康检查
+    pub fn with_health_check(mut self, check: impl Fn(&C) -> bool + Send + Sync + 'static) -> Self {
+        self.health_check = Some(Arc::new(check));
+        self
+    }
+
+    /// 以 `connector(address)` 新建连接，替代 `C::connect`
+    pub fn with_connector(mut self, connector: ConnectFn<C>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    pub fn stats(&self) -> &KeyedPoolStats {
+        &self.stats
+    }
+
+    /// 借出 `node` 的连接；节点可疑或处于重连退避期时不建连，直接返回 `Network` 错误。
+    /// 建连失败原样返回连接器的错误，并开始（或延长）该节点的退避
+    pub async fn acquire(&self, node: &str) -> Result<KeyedConnection<C>, DistributedError> {
+        let (pool, generation) = self.endpoint_pool(node)?;
+        let started = Instant::now();
+        match pool.acquire().await {
+            Ok(conn) => {
+                let waited = started.elapsed().as_secs_f64() * 1000.0;
+                self.stats.acquire_wait.observe(waited);
+                if let Some(state) = self.state.lock().unwrap().get_mut(node) {
+                    state.failures = 0;
+                    state.retry_at = None;
+                }
+                Ok(KeyedConnection {
+                    node: node.to_string(),
+                    generation,
+                    conn,
+                })
+            }
+            Err(PoolError::Connect(e)) => {
+                self.stats.connect_failures.fetch_add(1, Ordering::Relaxed);
+                if let Some(state) = self.state.lock().unwrap().get_mut(node) {
+                    state.failures += 1;
+                    state.retry_at = Some(Instant::now() + self.backoff.delay(state.failures));
+                }
+                Err(e)
+            }
+            Err(PoolError::Closed) => Err(DistributedError::Network(format!(
+                "{node} is suspected; connection pool closed"
+            ))),
+            Err(e) => Err(DistributedError::Network(format!("{node}: {e}"))),
+        }
+    }
+
+    /// 归还连接；节点已被判定可疑（端点池已关闭）时直接关闭连接
+    pub fn release(&self, conn: KeyedConnection<C>) {
+        let state = self.state.lock().unwrap();
+        if let Some(EndpointState {
+            pool: Some(pool),
+            generation,
+            ..
+        }) = state.get(&conn.node)
+            && *generation == conn.generation
+        {
+            pool.release(conn.conn);
+        }
+    }
+
+    /// 故障检测器判定 `node` 可疑或故障：关闭其全部连接，此后的借出快速失败
+    pub fn mark_suspected(&self, node: &str) {
+        let mut state = self.state.lock().unwrap();
+        let entry = state
+            .entry(node.to_string())
+            .or_insert_with(EndpointState::new);
+        if entry.suspected {
+            return;
+        }
+        entry.suspected = true;
+        if let Some(pool) = entry.pool.take() {
+            pool.close();
+            entry.generation += 1;
+            self.stats.suspected_closes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// `node` 恢复存活：清除可疑标记与退避，下次借出时重新建连
+    pub fn mark_alive(&self, node: &str) {
+        if let Some(entry) = self.state.lock().unwrap().get_mut(node) {
+            entry.suspected = false;
+            entry.failures = 0;
+            entry.retry_at = None;
+        }
+    }
+
+    /// 按会籍视图同步：Suspect/Faulty 的已知节点关闭连接，Alive 的节点恢复
+    pub fn apply_membership(&self, view: &MembershipView) {
+        for node in self.endpoints.keys() {
+            match view.get_member(node).map(|m| m.state) {
+                Some(SwimMemberState::Suspect | SwimMemberState::Faulty) => {
+                    self.mark_suspected(node)
+                }
+                Some(SwimMemberState::Alive) => self.mark_alive(node),
+                None => {}
+            }
+        }
+    }
+
+    pub fn is_suspected(&self, node: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .get(node)
+            .is_some_and(|s| s.suspected)
+    }
+
+    /// 回收各端点空闲超时的连接
+    pub fn reap_idle(&self) {
+        for state in self.state.lock().unwrap().values() {
+            if let Some(pool) = &state.pool {
+                pool.reap_idle();
+            }
+        }
+    }
+
+    /// `node` 当前打开的连接数（借出 + 空闲）
+    pub fn open(&self, node: &str) -> usize {
+        self.with_pool(node, |p| p.in_use() + p.idle_count())
+    }
+
+    /// `node` 当前空闲的连接数
+    pub fn idle(&self, node: &str) -> usize {
+        self.with_pool(node, |p| p.idle_count())
+    }
+
+    /// 全部端点打开的连接数
+    pub fn total_open(&self) -> usize {
+        self.endpoints.keys().map(|n| self.open(n)).sum()
+    }
+
+    /// 全部端点空闲的连接数
+    pub fn total_idle(&self) -> usize {
+        self.endpoints.keys().map(|n| self.idle(n)).sum()
+    }
+
+    fn with_pool(&self, node: &str, f: impl Fn(&ConnectionPool<C>) -> usize) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .get(node)
+            .and_then(|s| s.pool.as_ref())
+            .map_or(0, f)
+    }
+
+    fn endpoint_pool(&self, node: &str) -> Result<(ConnectionPool<C>, u64), DistributedError> {
+        let address = self
+            .endpoints
+            .get(node)
+            .ok_or_else(|| DistributedError::Configuration(format!("unknown node {node}")))?;
+        let mut state = self.state.lock().unwrap();
+        let entry = state
+            .entry(node.to_string())
+            .or_insert_with(EndpointState::new);
+        if entry.suspected {
+            self.stats.fail_fast.fetch_add(1, Ordering::Relaxed);
+            return Err(DistributedError::Network(format!(
+                "{node} is suspected by the failure detector"
+            )));
+        }
+        if let Some(retry_at) = entry.retry_at
+            && retry_at > Instant::now()
+        {
+            self.stats.fail_fast.fetch_add(1, Ordering::Relaxed);
+            return Err(DistributedError::Network(format!(
+                "{node} reconnect backoff, retry in {:?}",
+                retry_at - Instant::now()
+            )));
+        }
+        let pool = entry.pool.get_or_insert_with(|| {
+            let mut pool = ConnectionPool::new(address.clone(), self.config.clone());
+            pool.health_check = self.health_check.clone();
+            pool.connector = self.connector.clone();
+            pool
+        });
+        Ok((pool.clone(), entry.generation))
+    }
+}
+
+impl<C: Connect> EndpointState<C> {
+    fn new() -> Self {
+        Self {
+            pool: None,
+            generation: 0,
+            failures: 0,
+            retry_at: None,
+            suspected: false,
+        }
+    }
+}
+
+/// 无需真实套接字的测试替身：按端点控制可达性，统计建连次数
+pub mod testing {
+    use super::{Connect, ConnectFn};
+    use crate::core::errors::DistributedError;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// 假连接，仅记录所属端点与序号
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct FakeConnection {
+        pub endpoint: String,
+        pub id: u64,
+    }
+
+    impl Connect for FakeConnection {
+        async fn connect(endpoint: &str) -> Result<Self, DistributedError> {
+            static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+            Ok(Self {
+                endpoint: endpoint.to_string(),
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeState {
+        down: HashSet<String>,
+        attempts: HashMap<String, u64>,
+        next_id: u64,
+    }
+
+    /// 假网络：`set_down` 的端点建连失败
+    #[derive(Clone, Default)]
+    pub struct FakeNetwork {
+        state: Arc<Mutex<FakeState>>,
+    }
+
+    impl FakeNetwork {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn set_down(&self, endpoint: &str, down: bool) {
+            let mut state = self.state.lock().unwrap();
+            if down {
+                state.down.insert(endpoint.to_string());
+            } else {
+                state.down.remove(endpoint);
+            }
+        }
+
+        /// 对 `endpoint` 的建连尝试次数（含失败）
+        pub fn connect_attempts(&self, endpoint: &str) -> u64 {
+            let state = self.state.lock().unwrap();
+            state.attempts.get(endpoint).copied().unwrap_or(0)
+        }
+
+        /// 供 `with_connector` 使用的建连函数
+        pub fn connector(&self) -> ConnectFn<FakeConnection> {
+            let state = self.state.clone();
+            Arc::new(move |endpoint: String| {
+                let state = state.clone();
+                Box::pin(async move {
+                    let mut state = state.lock().unwrap();
+                    *state.attempts.entry(endpoint.clone()).or_insert(0) += 1;
+                    if state.down.contains(&endpoint) {
+                        return Err(DistributedError::Network(format!(
+                            "connection refused: {endpoint}"
+                        )));
+                    }
+                    state.next_id += 1;
+                    Ok(FakeConnection {
+                        endpoint,
+                        id: state.next_id,
+                    })
+                })
+            })
+        }
+    }
+}
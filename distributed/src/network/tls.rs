@@ -0,0 +1,544 @@
+//! gRPC 传输的双向 TLS（mTLS）
+//!
+//! 设计意图：
+//! - `TlsContext` 从 PEM 文件加载本节点证书、私钥与 CA 证书包，构建 rustls 服务端/客户端配置；
+//!   复制、Raft 与 xDS 客户端共用同一份上下文。节点证书同时用作服务端证书与客户端证书。
+//! - 服务端：`incoming` 在 `TcpListener` 上完成 TLS 握手后把连接交给
+//!   `Server::serve_with_incoming`；`require_client_cert` 时只接受由 CA 签发的客户端证书。
+//!   `RequirePeer` 包装 tonic 服务，从客户端证书的 CN（缺省时取首个 DNS SAN）得到节点 ID，
+//!   交给授权函数（如 `membership_authorizer` 查询会籍视图）判定，通过后把 `PeerIdentity`
+//!   放入请求扩展供处理函数读取。
+//! - 客户端：`connect` 经自定义连接器建立 TLS 连接；SNI 取 `server_name` 或端点主机名，
+//!   `verify_hostname = false` 时只校验证书链、不校验主机名。端点仍写作 `http://host:port`，
+//!   加密由连接器完成（`https://` 会被改写）。
+//! - 证书轮换：`reload` 重新读取文件并原子替换配置；`watch` 订阅 `ConfigManager`，`tls.*`
+//!   配置变化时自动重载。已建立的连接不受影响，新连接与新握手使用新证书。
+//!
+//! 不变量（草图）：
+//! - 重载失败（文件缺失、证书与私钥不匹配等）时保留旧配置，服务不中断。
+//! - 未出示证书、证书无法解析或身份不在集群内的请求不会到达内层服务。
+
+use crate::config_management::{ConfigManager, ConfigSnapshot, ConfigValue};
+use crate::core::errors::DistributedError;
+use crate::swim::MembershipView;
+use hyper_util::rt::TokioIo;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, ServerConfig,
+    SignatureScheme,
+};
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::{TlsAcceptor, TlsConnector, server::TlsStream};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::Status;
+use tonic::codegen::{BoxFuture, Context, Poll, Service, http};
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tonic::transport::{Channel, Endpoint, Uri};
+use x509_parser::extensions::GeneralName;
+
+/// 单次 TLS 握手的超时，防止慢速客户端占住握手任务
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 证书与校验选项；证书以 PEM 文件路径给出，`reload` 时重新读取
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// 校验对端证书的 CA 证书包
+    pub ca_path: PathBuf,
+    /// 服务端是否要求并校验客户端证书
+    pub require_client_cert: bool,
+    /// 客户端是否校验服务端证书中的主机名
+    pub verify_hostname: bool,
+    /// 客户端发送的 SNI / 期望的主机名；`None` 时取端点 URI 的主机
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    pub const CERT_PATH_KEY: &str = "tls.cert_path";
+    pub const KEY_PATH_KEY: &str = "tls.key_path";
+    pub const CA_PATH_KEY: &str = "tls.ca_path";
+    pub const REQUIRE_CLIENT_CERT_KEY: &str = "tls.require_client_cert";
+    pub const VERIFY_HOSTNAME_KEY: &str = "tls.verify_hostname";
+    pub const SERVER_NAME_KEY: &str = "tls.server_name";
+
+    pub fn new(
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+        ca_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            ca_path: ca_path.into(),
+            require_client_cert: true,
+            verify_hostname: true,
+            server_name: None,
+        }
+    }
+
+    pub fn with_require_client_cert(mut self, require: bool) -> Self {
+        self.require_client_cert = require;
+        self
+    }
+
+    pub fn with_verify_hostname(mut self, verify: bool) -> Self {
+        self.verify_hostname = verify;
+        self
+    }
+
+    pub fn with_server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// 从配置快照读取 `tls.*` 键；证书、私钥或 CA 路径任一缺失时返回 `None`
+    pub fn from_snapshot(snapshot: &ConfigSnapshot) -> Option<Self> {
+        let string = |key: &str| match snapshot.values.get(key) {
+            Some(ConfigValue::String(s)) if !s.is_empty() => Some(s.clone()),
+            _ => None,
+        };
+        let flag = |key: &str, default: bool| match snapshot.values.get(key) {
+            Some(ConfigValue::Boolean(b)) => *b,
+            Some(ConfigValue::String(s)) => s.parse().unwrap_or(default),
+            _ => default,
+        };
+        let mut config = Self::new(
+            string(Self::CERT_PATH_KEY)?,
+            string(Self::KEY_PATH_KEY)?,
+            string(Self::CA_PATH_KEY)?,
+        )
+        .with_require_client_cert(flag(Self::REQUIRE_CLIENT_CERT_KEY, true))
+        .with_verify_hostname(flag(Self::VERIFY_HOSTNAME_KEY, true));
+        config.server_name = string(Self::SERVER_NAME_KEY);
+        Some(config)
+    }
+}
+
+/// 握手与身份校验计数
+#[derive(Debug, Default)]
+pub struct TlsStats {
+    handshakes: AtomicU64,
+    handshake_failures: AtomicU64,
+    rejected_peers: AtomicU64,
+    reloads: AtomicU64,
+    reload_failures: AtomicU64,
+}
+
+impl TlsStats {
+    pub fn handshakes(&self) -> u64 {
+        self.handshakes.load(Ordering::Relaxed)
+    }
+
+    /// 服务端握手失败次数（含客户端证书不被信任）
+    pub fn handshake_failures(&self) -> u64 {
+        self.handshake_failures.load(Ordering::Relaxed)
+    }
+
+    /// 被 `RequirePeer` 拒绝的请求数
+    pub fn rejected_peers(&self) -> u64 {
+        self.rejected_peers.load(Ordering::Relaxed)
+    }
+
+    pub fn reloads(&self) -> u64 {
+        self.reloads.load(Ordering::Relaxed)
+    }
+
+    pub fn reload_failures(&self) -> u64 {
+        self.reload_failures.load(Ordering::Relaxed)
+    }
+}
+
+struct Configs {
+    config: TlsConfig,
+    server: Arc<ServerConfig>,
+    client: Arc<ClientConfig>,
+}
+
+/// 可热重载的 TLS 上下文
+pub struct TlsContext {
+    configs: RwLock<Configs>,
+    stats: Arc<TlsStats>,
+}
+
+impl std::fmt::Debug for TlsContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsContext")
+            .field("config", &self.config())
+            .finish_non_exhaustive()
+    }
+}
+
+impl TlsContext {
+    pub fn new(config: TlsConfig) -> Result<Self, DistributedError> {
+        let (server, client) = build(&config)?;
+        Ok(Self {
+            configs: RwLock::new(Configs {
+                config,
+                server,
+                client,
+            }),
+            stats: Arc::new(TlsStats::default()),
+        })
+    }
+
+    pub fn config(&self) -> TlsConfig {
+        self.configs.read().unwrap().config.clone()
+    }
+
+    pub fn stats(&self) -> &TlsStats {
+        &self.stats
+    }
+
+    pub fn server_config(&self) -> Arc<ServerConfig> {
+        self.configs.read().unwrap().server.clone()
+    }
+
+    pub fn client_config(&self) -> Arc<ClientConfig> {
+        self.configs.read().unwrap().client.clone()
+    }
+
+    /// 按 `config` 重新读取证书并替换配置；失败时保留旧配置
+    pub fn reload(&self, config: TlsConfig) -> Result<(), DistributedError> {
+        match build(&config) {
+            Ok((server, client)) => {
+                *self.configs.write().unwrap() = Configs {
+                    config,
+                    server,
+                    client,
+                };
+                self.stats.reloads.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(e) => {
+                self.stats.reload_failures.fetch_add(1, Ordering::Relaxed);
+                #[cfg(feature = "observability")]
+                tracing::warn!(error = %e, "tls reload failed, keeping previous certificates");
+                Err(e)
+            }
+        }
+    }
+
+    /// 订阅配置变更：每次通知都按 `tls.*` 键重新加载（文件原地轮换时可改动任一键触发）
+    pub fn watch(self: &Arc<Self>, manager: &mut ConfigManager) {
+        let ctx = Arc::downgrade(self);
+        manager.subscribe(move |snapshot| {
+            if let (Some(ctx), Some(config)) = (ctx.upgrade(), TlsConfig::from_snapshot(snapshot)) {
+                // 失败已计入 reload_failures，旧证书继续生效
+                let _ = ctx.reload(config);
+            }
+        });
+    }
+
+    /// 在 `listener` 上接受连接并完成 TLS 握手，供 `Server::serve_with_incoming` 使用；
+    /// 握手失败的连接被丢弃并计数。返回的流被丢弃后接受循环退出。
+    pub fn incoming(
+        self: &Arc<Self>,
+        listener: TcpListener,
+    ) -> ReceiverStream<io::Result<TlsStream<TcpStream>>> {
+        let (tx, rx) = mpsc::channel(64);
+        let ctx = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let stream = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(_) => {
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                            continue;
+                        }
+                    },
+                    _ = tx.closed() => return,
+                };
+                let acceptor = TlsAcceptor::from(ctx.server_config());
+                let (tx, stats) = (tx.clone(), ctx.stats.clone());
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(tls)) => {
+                            stats.handshakes.fetch_add(1, Ordering::Relaxed);
+                            let _ = tx.send(Ok(tls)).await;
+                        }
+                        _ => {
+                            stats.handshake_failures.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+
+    /// 解析端点地址；`https://` 改写为 `http://`，TLS 由 `connect` 的连接器负责
+    pub fn endpoint(uri: &str) -> Result<Endpoint, DistributedError> {
+        let uri = match uri.strip_prefix("https://") {
+            Some(rest) => format!("http://{rest}"),
+            None => uri.to_string(),
+        };
+        Endpoint::from_shared(uri.clone())
+            .map_err(|e| DistributedError::Configuration(format!("{uri}: {e}")))
+    }
+
+    /// 以本节点证书建立到 `endpoint` 的 TLS 通道
+    pub async fn connect(&self, endpoint: Endpoint) -> Result<Channel, DistributedError> {
+        let host = self
+            .config()
+            .server_name
+            .or_else(|| {
+                endpoint
+                    .uri()
+                    .host()
+                    .map(|h| h.trim_matches(['[', ']']).to_string())
+            })
+            .ok_or_else(|| DistributedError::Configuration("endpoint has no host".into()))?;
+        let server_name = ServerName::try_from(host)
+            .map_err(|e| DistributedError::Configuration(format!("server name: {e}")))?;
+        let connector = TlsConnector::from(self.client_config());
+        endpoint
+            .connect_with_connector(tower::service_fn(move |uri: Uri| {
+                let (connector, server_name) = (connector.clone(), server_name.clone());
+                async move {
+                    let host = uri
+                        .host()
+                        .ok_or_else(|| io::Error::other("endpoint has no host"))?;
+                    let port = uri.port_u16().unwrap_or(443);
+                    let tcp = TcpStream::connect(format!("{host}:{port}")).await?;
+                    let tls = connector.connect(server_name, tcp).await?;
+                    Ok::<_, io::Error>(TokioIo::new(tls))
+                }
+            }))
+            .await
+            .map_err(|e| DistributedError::Network(format!("{}: {e}", endpoint.uri())))
+    }
+}
+
+fn build(config: &TlsConfig) -> Result<(Arc<ServerConfig>, Arc<ClientConfig>), DistributedError> {
+    let material = |what: &str, e: &dyn std::fmt::Display| {
+        DistributedError::Configuration(format!("tls {what}: {e}"))
+    };
+    let certs = CertificateDer::pem_file_iter(&config.cert_path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| material("certificate", &e))?;
+    let key = || PrivateKeyDer::from_pem_file(&config.key_path).map_err(|e| material("key", &e));
+    let mut roots = RootCertStore::empty();
+    for ca in CertificateDer::pem_file_iter(&config.ca_path).map_err(|e| material("ca", &e))? {
+        roots
+            .add(ca.map_err(|e| material("ca", &e))?)
+            .map_err(|e| material("ca", &e))?;
+    }
+    let roots = Arc::new(roots);
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| material("server", &e))?;
+    let builder = if config.require_client_cert {
+        let verifier = WebPkiClientVerifier::builder_with_provider(roots.clone(), provider.clone())
+            .build()
+            .map_err(|e| material("client verifier", &e))?;
+        builder.with_client_cert_verifier(verifier)
+    } else {
+        builder.with_no_client_auth()
+    };
+    let mut server = builder
+        .with_single_cert(certs.clone(), key()?)
+        .map_err(|e| material("server", &e))?;
+    server.alpn_protocols = vec![b"h2".to_vec()];
+
+    let verifier: Arc<dyn ServerCertVerifier> =
+        WebPkiServerVerifier::builder_with_provider(roots, provider.clone())
+            .build()
+            .map_err(|e| material("server verifier", &e))?;
+    let verifier = match config.verify_hostname {
+        true => verifier,
+        false => Arc::new(IgnoreHostname(verifier)),
+    };
+    let mut client = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| material("client", &e))?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_client_auth_cert(certs, key()?)
+        .map_err(|e| material("client", &e))?;
+    client.alpn_protocols = vec![b"h2".to_vec()];
+    Ok((Arc::new(server), Arc::new(client)))
+}
+
+/// 只校验证书链、忽略主机名不匹配的服务端证书校验器
+#[derive(Debug)]
+struct IgnoreHostname(Arc<dyn ServerCertVerifier>);
+
+impl ServerCertVerifier for IgnoreHostname {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match self
+            .0
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+        {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+            )) => Ok(ServerCertVerified::assertion()),
+            other => other,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// 从对端证书提取的身份
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    pub common_name: Option<String>,
+    pub dns_names: Vec<String>,
+    pub uris: Vec<String>,
+}
+
+impl PeerIdentity {
+    pub fn from_der(der: &[u8]) -> Result<Self, DistributedError> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der)
+            .map_err(|e| DistributedError::Configuration(format!("peer certificate: {e}")))?;
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_string);
+        let (mut dns_names, mut uris) = (Vec::new(), Vec::new());
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(dns) => dns_names.push(dns.to_string()),
+                    GeneralName::URI(uri) => uris.push(uri.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        Ok(Self {
+            common_name,
+            dns_names,
+            uris,
+        })
+    }
+
+    /// 节点 ID：证书 CN，缺省时取首个 DNS SAN
+    pub fn node_id(&self) -> Option<&str> {
+        self.common_name
+            .as_deref()
+            .or_else(|| self.dns_names.first().map(String::as_str))
+    }
+}
+
+/// 判定对端身份是否属于集群
+pub type PeerAuthorizer = Arc<dyn Fn(&PeerIdentity) -> bool + Send + Sync>;
+
+/// 节点 ID 出现在会籍视图中即放行
+pub fn membership_authorizer(view: Arc<Mutex<MembershipView>>) -> PeerAuthorizer {
+    Arc::new(move |peer| {
+        peer.node_id()
+            .is_some_and(|id| view.lock().unwrap().contains(id))
+    })
+}
+
+/// 要求客户端证书身份通过授权的 tonic 服务包装；放行的请求扩展中带有 `PeerIdentity`
+#[derive(Clone)]
+pub struct RequirePeer<S> {
+    inner: S,
+    authorize: PeerAuthorizer,
+    stats: Arc<TlsStats>,
+}
+
+impl<S> RequirePeer<S> {
+    pub fn new(inner: S, tls: &TlsContext, authorize: PeerAuthorizer) -> Self {
+        Self {
+            inner,
+            authorize,
+            stats: tls.stats.clone(),
+        }
+    }
+
+    fn identify<B>(&self, req: &http::Request<B>) -> Result<PeerIdentity, Status> {
+        let certs = req
+            .extensions()
+            .get::<TlsConnectInfo<TcpConnectInfo>>()
+            .and_then(|info| info.peer_certs())
+            .ok_or_else(|| Status::unauthenticated("client certificate required"))?;
+        let leaf = certs
+            .first()
+            .ok_or_else(|| Status::unauthenticated("client certificate required"))?;
+        let peer =
+            PeerIdentity::from_der(leaf).map_err(|e| Status::unauthenticated(e.to_string()))?;
+        if !(self.authorize)(&peer) {
+            return Err(Status::permission_denied(format!(
+                "{} is not a cluster member",
+                peer.node_id().unwrap_or("<anonymous>")
+            )));
+        }
+        Ok(peer)
+    }
+}
+
+impl<S: tonic::server::NamedService> tonic::server::NamedService for RequirePeer<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, B> Service<http::Request<B>> for RequirePeer<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<tonic::body::Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        match self.identify(&req) {
+            Ok(peer) => {
+                req.extensions_mut().insert(peer);
+                Box::pin(self.inner.call(req))
+            }
+            Err(status) => {
+                self.stats.rejected_peers.fetch_add(1, Ordering::Relaxed);
+                Box::pin(async move { Ok(status.into_http()) })
+            }
+        }
+    }
+}
@@ -10,6 +10,22 @@
 //! 参考：Dynamo/Riak 分区与副本放置文献。
 use crate::core::topology::{ConsistentHashRing, ShardId};
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+pub mod autoscaler;
+pub mod colocation;
+pub mod composite;
+pub mod hotspot;
+pub mod range;
+
+pub use autoscaler::{
+    AutoscaleAction, AutoscalerConfig, ChunkedShardDrain, DrainProgress, RebalancePlan,
+    ShardAutoscaler, ShardDrain, ShardMove,
+};
+pub use colocation::{CoLocationHintMap, CoLocationPartitioner};
+pub use composite::{CompositePartitioner, ShardStats};
+pub use hotspot::{HotKey, HotNode, HotspotDetector, HotspotReport, NodeLoad};
+pub use range::RangePartitioner;
 
 pub trait Partitioner<K> {
     fn shard_of(&self, key: &K) -> ShardId;
@@ -30,13 +46,64 @@ impl<K: Hash> Partitioner<K> for HashPartitioner {
 
 pub struct HashRingRouter {
     pub ring: ConsistentHashRing,
+    hotspots: Option<Arc<HotspotDetector>>,
 }
 
 impl HashRingRouter {
     pub fn new(ring: ConsistentHashRing) -> Self {
-        Self { ring }
+        Self {
+            ring,
+            hotspots: None,
+        }
+    }
+    /// 每次路由都把 (键, 归属节点) 记入热点检测器
+    pub fn with_hotspots(mut self, detector: Arc<HotspotDetector>) -> Self {
+        self.hotspots = Some(detector);
+        self
+    }
+    pub fn owner_of<K: Hash + ToString>(&self, key: &K) -> Option<String> {
+        let owner = self.ring.route(key).map(|s| s.to_string());
+        if let Some(detector) = &self.hotspots {
+            detector.record(&key.to_string(), owner.as_deref());
+        }
+        owner
+    }
+    /// 键的主副本与其余副本：主副本即 `route` 的结果，其余为 `nodes_for` 去掉主副本
+    pub fn primary_and_replicas<K: Hash>(
+        &self,
+        key: &K,
+        replica_count: usize,
+    ) -> (Option<String>, Vec<String>) {
+        let primary = self.ring.route(key).map(|s| s.to_string());
+        let replicas = self
+            .ring
+            .nodes_for(key, replica_count)
+            .into_iter()
+            .filter(|n| Some(n) != primary.as_ref())
+            .collect();
+        (primary, replicas)
     }
-    pub fn owner_of<K: std::hash::Hash>(&self, key: &K) -> Option<String> {
-        self.ring.route(key).map(|s| s.to_string())
+    /// 读取的目标副本：`preference` 在键的副本集（含主副本）中时返回它，否则返回主副本。
+    /// 见证者不保存数据，从不作为读取目标：此时改选副本集中第一个可读的副本
+    pub fn preferred_replica<K: Hash>(
+        &self,
+        key: &K,
+        replica_count: usize,
+        preference: &str,
+    ) -> Option<String> {
+        let (primary, replicas) = self.primary_and_replicas(key, replica_count);
+        let readable = |n: &str| self.ring.role_of(n).serves_reads();
+        if readable(preference)
+            && (primary.as_deref() == Some(preference) || replicas.iter().any(|n| n == preference))
+        {
+            Some(preference.to_string())
+        } else if primary.as_deref().is_some_and(readable) {
+            primary
+        } else {
+            self.ring
+                .read_nodes_for(key, replica_count)
+                .into_iter()
+                .next()
+        }
     }
 }
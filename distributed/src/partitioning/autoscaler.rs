@@ -0,0 +1,491 @@
+//! 热点分片的自动分裂
+//!
+//! 设计意图：
+//! - `ShardAutoscaler` 由数据路径调用 `record` 喂入访问：每个分片的次数与字节数记入指标门面
+//!   （`MetricCollector` 中的 `shard_<id>_ops_total` / `shard_<id>_bytes_total` 计数器），
+//!   键与所在节点记入内部的 `HotspotDetector`。
+//! - `evaluate` 按两次调用间的计数增量计算各分片的 ops/s 与 bytes/s；超过阈值并持续
+//!   `sustain` 的分片在热键的加权中位数处经 `RangePartitioner::split_shard` 分裂，并生成
+//!   `RebalancePlan`，把上半区间迁往当前负载最低的节点。
+//! - `drive` 经 `ShardDrain`（节点下线/排空所用的数据搬迁接口）执行计划，每秒搬迁字节数受
+//!   `move_bytes_per_sec` 限制；搬迁完成后才更新分片归属。`ChunkedShardDrain` 把分片数据
+//!   按快照块（`consensus::snapshot_transfer`）发送，投递失败后下一次 `drive` 从最后确认的
+//!   偏移续传，目标节点校验整体哈希后才算完成。
+//! - 迟滞：分裂后原分片进入冷却期，且负载须先回落到阈值的 `rearm_ratio` 以下才会再次
+//!   触发；单个热键无法再分，只报告一次 `Unsplittable`。`dry_run` 下只报告将要执行的动作，
+//!   不修改分区与归属。
+//!
+//! 不变量（草图）：
+//! - 同一分片在重新武装（负载回落）前至多分裂一次。
+//! - 分片归属只在搬迁完成时改变；搬迁中的分片仍由源节点服务。
+
+use super::{HotspotDetector, RangePartitioner};
+use crate::consensus::snapshot_transfer::{DEFAULT_CHUNK_SIZE, SnapshotChunk, SnapshotSender};
+use crate::core::errors::DistributedError;
+use crate::core::topology::ShardId;
+use crate::monitoring::{Counter, MetricCollector, MetricLabels};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct AutoscalerConfig {
+    /// 分片 ops/s 上限
+    pub max_ops_per_sec: f64,
+    /// 分片 bytes/s 上限
+    pub max_bytes_per_sec: f64,
+    /// 超限持续多久才分裂
+    pub sustain: Duration,
+    /// 负载回落到上限的该比例以下才重新武装
+    pub rearm_ratio: f64,
+    /// 分裂后原分片与新分片的冷却期
+    pub cooldown: Duration,
+    /// 数据搬迁限速
+    pub move_bytes_per_sec: u64,
+    pub dry_run: bool,
+}
+
+impl Default for AutoscalerConfig {
+    fn default() -> Self {
+        Self {
+            max_ops_per_sec: 1_000.0,
+            max_bytes_per_sec: 10.0 * 1024.0 * 1024.0,
+            sustain: Duration::from_secs(30),
+            rearm_ratio: 0.5,
+            cooldown: Duration::from_secs(300),
+            move_bytes_per_sec: 4 * 1024 * 1024,
+            dry_run: false,
+        }
+    }
+}
+
+/// 把分片区间 `[start, end)` 从 `from` 迁往 `to`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardMove {
+    pub shard: ShardId,
+    pub start: String,
+    pub end: Option<String>,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RebalancePlan {
+    pub moves: Vec<ShardMove>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoscaleAction {
+    /// 在 `at` 处分裂；`dry_run` 时分区未改变，`new_shard` 为将分配的 ID
+    Split {
+        shard: ShardId,
+        at: String,
+        new_shard: ShardId,
+        plan: RebalancePlan,
+        dry_run: bool,
+    },
+    /// 分片过热但热键不足两个，无法分裂
+    Unsplittable {
+        shard: ShardId,
+    },
+    MoveCompleted {
+        shard: ShardId,
+        to: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrainProgress {
+    pub bytes: u64,
+    pub done: bool,
+}
+
+/// 节点间的分片数据搬迁；每次调用最多搬迁 `max_bytes`
+pub trait ShardDrain {
+    fn drain(&mut self, mv: &ShardMove, max_bytes: u64) -> Result<DrainProgress, DistributedError>;
+}
+
+/// 以可续传的快照块搬迁分片：`export` 导出分片数据，`deliver` 把一块交给目标节点并返回
+/// 其下一个期望的偏移（目标端通常以 `SnapshotReceiver` 接收）
+pub struct ChunkedShardDrain<S, D> {
+    export: S,
+    deliver: D,
+    chunk_size: usize,
+    transfers: HashMap<ShardId, SnapshotSender>,
+}
+
+impl<S, D> ChunkedShardDrain<S, D>
+where
+    S: FnMut(&ShardMove) -> Result<Vec<u8>, DistributedError>,
+    D: FnMut(&ShardMove, SnapshotChunk) -> Result<u64, DistributedError>,
+{
+    pub fn new(export: S, deliver: D) -> Self {
+        Self {
+            export,
+            deliver,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            transfers: HashMap::new(),
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// 进行中的搬迁的发送方状态
+    pub fn transfer(&self, shard: ShardId) -> Option<&SnapshotSender> {
+        self.transfers.get(&shard)
+    }
+}
+
+impl<S, D> ShardDrain for ChunkedShardDrain<S, D>
+where
+    S: FnMut(&ShardMove) -> Result<Vec<u8>, DistributedError>,
+    D: FnMut(&ShardMove, SnapshotChunk) -> Result<u64, DistributedError>,
+{
+    fn drain(&mut self, mv: &ShardMove, max_bytes: u64) -> Result<DrainProgress, DistributedError> {
+        if !self.transfers.contains_key(&mv.shard) {
+            let data = (self.export)(mv)?;
+            let sender = SnapshotSender::new(data)
+                .with_chunk_size(self.chunk_size)
+                .with_max_in_flight(1);
+            self.transfers.insert(mv.shard, sender);
+        }
+        let sender = self.transfers.get_mut(&mv.shard).expect("transfer started");
+        let mut bytes = 0;
+        while bytes < max_bytes {
+            let Some(chunk) = sender.next_chunk() else {
+                break;
+            };
+            let len = chunk.data.len() as u64;
+            match (self.deliver)(mv, chunk) {
+                Ok(next_offset) => sender.on_ack(next_offset),
+                Err(e) => {
+                    sender.on_disconnect();
+                    return Err(e);
+                }
+            }
+            bytes += len;
+        }
+        let done = sender.is_complete();
+        if done {
+            self.transfers.remove(&mv.shard);
+        }
+        Ok(DrainProgress { bytes, done })
+    }
+}
+
+struct ShardCounters {
+    ops: Arc<Counter>,
+    bytes: Arc<Counter>,
+}
+
+#[derive(Debug, Clone)]
+struct ShardWatch {
+    last: Option<(Instant, u64, u64)>,
+    ops_rate: f64,
+    bytes_rate: f64,
+    hot_since: Option<Instant>,
+    armed: bool,
+    cooldown_until: Option<Instant>,
+}
+
+impl Default for ShardWatch {
+    fn default() -> Self {
+        Self {
+            last: None,
+            ops_rate: 0.0,
+            bytes_rate: 0.0,
+            hot_since: None,
+            armed: true,
+            cooldown_until: None,
+        }
+    }
+}
+
+pub struct ShardAutoscaler {
+    config: AutoscalerConfig,
+    partitioner: RangePartitioner,
+    nodes: Vec<String>,
+    placement: HashMap<ShardId, String>,
+    metrics: MetricCollector,
+    counters: HashMap<ShardId, ShardCounters>,
+    watches: HashMap<ShardId, ShardWatch>,
+    hotspots: HotspotDetector,
+    pending: VecDeque<ShardMove>,
+    move_budget: f64,
+    last_drive: Option<Instant>,
+}
+
+impl ShardAutoscaler {
+    /// 分片按区间顺序轮流放置到 `nodes` 上
+    pub fn new(partitioner: RangePartitioner, nodes: Vec<String>) -> Self {
+        assert!(!nodes.is_empty(), "autoscaler needs at least one node");
+        let placement = partitioner
+            .shards()
+            .into_iter()
+            .zip(nodes.iter().cycle())
+            .map(|(shard, node)| (shard, node.clone()))
+            .collect();
+        let mut scaler = Self {
+            config: AutoscalerConfig::default(),
+            partitioner,
+            nodes,
+            placement,
+            metrics: MetricCollector::new(),
+            counters: HashMap::new(),
+            watches: HashMap::new(),
+            hotspots: HotspotDetector::new(256),
+            pending: VecDeque::new(),
+            move_budget: 0.0,
+            last_drive: None,
+        };
+        for shard in scaler.partitioner.shards() {
+            scaler.register(shard);
+        }
+        scaler
+    }
+
+    pub fn with_config(mut self, config: AutoscalerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn partitioner(&self) -> &RangePartitioner {
+        &self.partitioner
+    }
+
+    pub fn node_of(&self, shard: ShardId) -> Option<&str> {
+        self.placement.get(&shard).map(String::as_str)
+    }
+
+    /// 分片计数器所在的指标门面
+    pub fn metrics(&self) -> &MetricCollector {
+        &self.metrics
+    }
+
+    /// 待执行的搬迁
+    pub fn pending_moves(&self) -> impl Iterator<Item = &ShardMove> {
+        self.pending.iter()
+    }
+
+    /// 最近一次 `evaluate` 得到的各节点 ops/s
+    pub fn node_loads(&self) -> BTreeMap<String, f64> {
+        let mut loads: BTreeMap<String, f64> =
+            self.nodes.iter().map(|n| (n.clone(), 0.0)).collect();
+        for (shard, watch) in &self.watches {
+            if let Some(node) = self.placement.get(shard) {
+                *loads.entry(node.clone()).or_insert(0.0) += watch.ops_rate;
+            }
+        }
+        loads
+    }
+
+    pub fn record(&self, key: &str, bytes: u64) {
+        self.record_at(key, bytes, Instant::now());
+    }
+
+    pub fn record_at(&self, key: &str, bytes: u64, now: Instant) {
+        let shard = self.partitioner.shard_for(key);
+        if let Some(counters) = self.counters.get(&shard) {
+            counters.ops.inc();
+            counters.bytes.add(bytes);
+        }
+        self.hotspots
+            .record_at(&key.to_string(), self.node_of(shard), now);
+    }
+
+    /// 更新各分片负载并做出分裂决策
+    pub fn evaluate(&mut self, now: Instant) -> Vec<AutoscaleAction> {
+        let mut actions = Vec::new();
+        let mut planned = 0;
+        for shard in self.partitioner.shards() {
+            if !self.observe(shard, now) {
+                continue;
+            }
+            let moving = self.pending.iter().any(|m| m.shard == shard);
+            let watch = &self.watches[&shard];
+            let ready = watch.armed
+                && !moving
+                && watch.cooldown_until.is_none_or(|until| now >= until)
+                && watch
+                    .hot_since
+                    .is_some_and(|since| now.duration_since(since) >= self.config.sustain);
+            if !ready {
+                continue;
+            }
+            let watch = self.watches.get_mut(&shard).expect("observed shard");
+            watch.armed = false;
+            watch.hot_since = None;
+            let Some(at) = self.median_hot_key(shard, now) else {
+                actions.push(AutoscaleAction::Unsplittable { shard });
+                continue;
+            };
+            actions.push(self.split(shard, at, now, planned));
+            planned += 1;
+        }
+        actions
+    }
+
+    /// 在限速内推进待执行的搬迁
+    pub fn drive(
+        &mut self,
+        now: Instant,
+        drain: &mut dyn ShardDrain,
+    ) -> Result<Vec<AutoscaleAction>, DistributedError> {
+        let rate = self.config.move_bytes_per_sec as f64;
+        let elapsed = self
+            .last_drive
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        self.last_drive = Some(now);
+        // 至多累积 1 秒的额度
+        self.move_budget = (self.move_budget + rate * elapsed.as_secs_f64()).min(rate);
+
+        let mut actions = Vec::new();
+        while let Some(mv) = self.pending.front() {
+            let allowed = self.move_budget as u64;
+            if allowed == 0 {
+                break;
+            }
+            let progress = drain.drain(mv, allowed)?;
+            self.move_budget -= progress.bytes.min(allowed) as f64;
+            if !progress.done {
+                break;
+            }
+            let mv = self.pending.pop_front().expect("front move");
+            self.placement.insert(mv.shard, mv.to.clone());
+            actions.push(AutoscaleAction::MoveCompleted {
+                shard: mv.shard,
+                to: mv.to,
+            });
+        }
+        Ok(actions)
+    }
+
+    fn register(&mut self, shard: ShardId) {
+        let labels: MetricLabels = HashMap::from([("shard".to_string(), shard.0.to_string())]);
+        let ops = self
+            .metrics
+            .counter(&format!("shard_{}_ops_total", shard.0), labels.clone());
+        let bytes = self
+            .metrics
+            .counter(&format!("shard_{}_bytes_total", shard.0), labels);
+        self.counters.insert(shard, ShardCounters { ops, bytes });
+    }
+
+    /// 更新分片速率与过热/武装状态；首次观测只记录基线，返回 false
+    fn observe(&mut self, shard: ShardId, now: Instant) -> bool {
+        let Some(counters) = self.counters.get(&shard) else {
+            return false;
+        };
+        let (ops, bytes) = (counters.ops.get(), counters.bytes.get());
+        let config = &self.config;
+        let watch = self.watches.entry(shard).or_default();
+        let Some((at, last_ops, last_bytes)) = watch.last.replace((now, ops, bytes)) else {
+            return false;
+        };
+        let elapsed = now.saturating_duration_since(at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return false;
+        }
+        watch.ops_rate = ops.saturating_sub(last_ops) as f64 / elapsed;
+        watch.bytes_rate = bytes.saturating_sub(last_bytes) as f64 / elapsed;
+        let hot =
+            watch.ops_rate > config.max_ops_per_sec || watch.bytes_rate > config.max_bytes_per_sec;
+        let cold = watch.ops_rate < config.max_ops_per_sec * config.rearm_ratio
+            && watch.bytes_rate < config.max_bytes_per_sec * config.rearm_ratio;
+        if cold {
+            watch.armed = true;
+        }
+        if hot {
+            watch.hot_since.get_or_insert(now);
+        } else {
+            watch.hot_since = None;
+        }
+        true
+    }
+
+    /// 分片内热键按次数加权的中位数；热键不足两个时为 `None`
+    fn median_hot_key(&self, shard: ShardId, now: Instant) -> Option<String> {
+        let (start, end) = self.partitioner.range_of(shard)?;
+        let mut keys: Vec<(String, u64)> = self
+            .hotspots
+            .report_at(now)
+            .keys
+            .into_iter()
+            .filter(|k| k.key >= start && end.as_ref().is_none_or(|end| k.key < *end))
+            .map(|k| (k.key, k.count))
+            .collect();
+        if keys.len() < 2 {
+            return None;
+        }
+        keys.sort();
+        let total: u64 = keys.iter().map(|(_, count)| count).sum();
+        let mut below = 0;
+        for (i, (key, count)) in keys.iter().enumerate() {
+            if i > 0 && below * 2 >= total {
+                return Some(key.clone());
+            }
+            below += count;
+        }
+        keys.pop().map(|(key, _)| key)
+    }
+
+    fn split(&mut self, shard: ShardId, at: String, now: Instant, planned: u64) -> AutoscaleAction {
+        let (_, end) = self
+            .partitioner
+            .range_of(shard)
+            .expect("split shard exists");
+        let from = self.placement[&shard].clone();
+        let loads = self.node_loads();
+        let to = self
+            .nodes
+            .iter()
+            .filter(|n| **n != from)
+            .min_by(|a, b| loads[*a].total_cmp(&loads[*b]).then(a.cmp(b)))
+            .cloned()
+            .unwrap_or_else(|| from.clone());
+        let dry_run = self.config.dry_run;
+        let new_shard = if dry_run {
+            ShardId(self.partitioner.next_shard_id().0 + planned)
+        } else {
+            self.partitioner
+                .split_shard(shard, &at)
+                .expect("median hot key lies inside the shard")
+        };
+        let mv = ShardMove {
+            shard: new_shard,
+            start: at.clone(),
+            end,
+            from: from.clone(),
+            to,
+        };
+        if !dry_run {
+            self.placement.insert(new_shard, from);
+            self.register(new_shard);
+            let cooldown_until = Some(now + self.config.cooldown);
+            self.watches
+                .get_mut(&shard)
+                .expect("observed shard")
+                .cooldown_until = cooldown_until;
+            self.watches.insert(
+                new_shard,
+                ShardWatch {
+                    cooldown_until,
+                    ..ShardWatch::default()
+                },
+            );
+            if mv.to != mv.from {
+                self.pending.push_back(mv.clone());
+            }
+        }
+        AutoscaleAction::Split {
+            shard,
+            at,
+            new_shard,
+            plan: RebalancePlan { moves: vec![mv] },
+            dry_run,
+        }
+    }
+}
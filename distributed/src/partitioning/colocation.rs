@@ -0,0 +1,115 @@
+//! 键共置提示
+//!
+//! 设计意图：
+//! - 经常一起读写的键（如 `user_id` 与 `user_profile_id`）放在同一分片上，可以把跨分片
+//!   事务或联合查询变成单分片操作。`CoLocationHintMap` 记录“键 → 锚点键”：被共置的键
+//!   跟随锚点键所在的分片。
+//! - `CoLocationPartitioner` 包装一个分区器（默认 `HashPartitioner`）：有提示的键按锚点键
+//!   定位，其余键原样交给内层分区器，因此未登记提示时路由结果与内层完全一致。
+//! - 锚点本身也可以有提示，定位时沿提示链追到最终锚点；登记会形成环的提示被拒绝。
+//!
+//! 不变量（草图）：
+//! - 登记 `key → anchor` 后 `shard_of(key) == shard_of(anchor)`，无论内层分区器如何放置二者。
+//! - 提示链无环，解析必然终止。
+
+use super::{HashPartitioner, Partitioner};
+use crate::core::errors::DistributedError;
+use crate::core::topology::ShardId;
+use std::collections::HashMap;
+
+/// 键 → 锚点键；被共置的键跟随锚点键的分片
+#[derive(Debug, Clone, Default)]
+pub struct CoLocationHintMap {
+    pub hints: HashMap<String, String>,
+}
+
+impl CoLocationHintMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记 `key` 跟随 `anchor`；形成环（含 `key == anchor`）时报配置错误
+    pub fn register(&mut self, key: &str, anchor: &str) -> Result<(), DistributedError> {
+        if self.resolve(anchor) == key {
+            return Err(DistributedError::Configuration(format!(
+                "co-locating {key} with {anchor} would form a cycle"
+            )));
+        }
+        self.hints.insert(key.to_string(), anchor.to_string());
+        Ok(())
+    }
+
+    /// 取消 `key` 的提示，返回原锚点
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.hints.remove(key)
+    }
+
+    /// 沿提示链找到最终锚点；没有提示的键是自己的锚点
+    pub fn resolve<'a>(&'a self, key: &'a str) -> &'a str {
+        let mut current = key;
+        // `hints` 可以直接改写，绕过 `register` 的环检查；步数上限保证终止
+        for _ in 0..self.hints.len() {
+            match self.hints.get(current) {
+                Some(anchor) => current = anchor,
+                None => break,
+            }
+        }
+        current
+    }
+
+    pub fn len(&self) -> usize {
+        self.hints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+}
+
+/// 按共置提示改写路由的分区器
+pub struct CoLocationPartitioner<P = HashPartitioner> {
+    inner: P,
+    hints: CoLocationHintMap,
+}
+
+impl<P: Partitioner<String>> CoLocationPartitioner<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            hints: CoLocationHintMap::new(),
+        }
+    }
+
+    pub fn with_hints(mut self, hints: CoLocationHintMap) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    /// 让 `key` 与 `anchor` 落在同一分片
+    pub fn register_colocated_key(
+        &mut self,
+        key: &str,
+        anchor: &str,
+    ) -> Result<(), DistributedError> {
+        self.hints.register(key, anchor)
+    }
+
+    pub fn hints(&self) -> &CoLocationHintMap {
+        &self.hints
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// 有提示的键按最终锚点定位，否则交给内层分区器
+    pub fn shard_of(&self, key: &str) -> ShardId {
+        self.inner.shard_of(&self.hints.resolve(key).to_string())
+    }
+}
+
+impl<P: Partitioner<String>> Partitioner<String> for CoLocationPartitioner<P> {
+    fn shard_of(&self, key: &String) -> ShardId {
+        CoLocationPartitioner::shard_of(self, key)
+    }
+}
@@ -0,0 +1,105 @@
+//! 组合分区：哈希分区 + 范围溢出分片
+//!
+//! 设计意图：
+//! - 顺序 ID 等键空间会让哈希分区中的个别分片过热。`CompositePartitioner` 先按主分区器
+//!   （通常是 `HashPartitioner`）定位分片；若 `ShardStats` 显示该分片负载超过
+//!   `overflow_threshold`，新写入改由溢出分区器（通常是 `RangePartitioner`）路由到溢出分片。
+//! - 溢出分片 ID 加上 `overflow_base` 偏移，与主分区器的分片 ID 区分。
+//! - 只有写入经 `route_with_stats` 按负载分流；已写入主分片的键不迁移，读路径需同时查询
+//!   主分片与溢出分片（或由调用方记录键的去向）。
+//!
+//! 不变量（草图）：
+//! - 负载不超过阈值时路由结果与主分区器完全一致。
+//! - 溢出分片 ID 恒不小于 `overflow_base`，不会与主分片冲突。
+
+use super::Partitioner;
+use crate::core::topology::ShardId;
+use std::collections::HashMap;
+
+/// 溢出分片 ID 的默认偏移
+pub const DEFAULT_OVERFLOW_BASE: u64 = 1 << 32;
+
+/// 各分片的当前负载（单位由调用方决定，如 ops/s）
+#[derive(Debug, Clone, Default)]
+pub struct ShardStats {
+    loads: HashMap<ShardId, f64>,
+}
+
+impl ShardStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_load(&mut self, shard: ShardId, load: f64) {
+        self.loads.insert(shard, load);
+    }
+
+    pub fn add_load(&mut self, shard: ShardId, delta: f64) {
+        *self.loads.entry(shard).or_insert(0.0) += delta;
+    }
+
+    /// 未记录的分片负载为 0
+    pub fn load(&self, shard: ShardId) -> f64 {
+        self.loads.get(&shard).copied().unwrap_or(0.0)
+    }
+}
+
+pub struct CompositePartitioner<P1, P2> {
+    primary: P1,
+    overflow: P2,
+    overflow_threshold: f64,
+    overflow_base: u64,
+}
+
+impl<P1, P2> CompositePartitioner<P1, P2> {
+    pub fn new(primary: P1, overflow: P2, overflow_threshold: f64) -> Self {
+        Self {
+            primary,
+            overflow,
+            overflow_threshold,
+            overflow_base: DEFAULT_OVERFLOW_BASE,
+        }
+    }
+
+    pub fn with_overflow_base(mut self, base: u64) -> Self {
+        self.overflow_base = base;
+        self
+    }
+
+    pub fn primary(&self) -> &P1 {
+        &self.primary
+    }
+
+    pub fn overflow(&self) -> &P2 {
+        &self.overflow
+    }
+
+    /// 溢出分区器可能需要分裂等变更
+    pub fn overflow_mut(&mut self) -> &mut P2 {
+        &mut self.overflow
+    }
+
+    pub fn is_overflow(&self, shard: ShardId) -> bool {
+        shard.0 >= self.overflow_base
+    }
+
+    /// 溢出分片对应的溢出分区器分片 ID
+    pub fn overflow_shard(&self, shard: ShardId) -> Option<ShardId> {
+        self.is_overflow(shard)
+            .then(|| ShardId(shard.0 - self.overflow_base))
+    }
+
+    /// 写入路由：主分片负载超过阈值时转到溢出分片
+    pub fn route_with_stats<K>(&self, key: &K, stats: &ShardStats) -> ShardId
+    where
+        P1: Partitioner<K>,
+        P2: Partitioner<K>,
+    {
+        let shard = self.primary.shard_of(key);
+        if stats.load(shard) > self.overflow_threshold {
+            ShardId(self.overflow_base + self.overflow.shard_of(key).0)
+        } else {
+            shard
+        }
+    }
+}
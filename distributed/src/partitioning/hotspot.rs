@@ -0,0 +1,277 @@
+//! 键级热点检测
+//!
+//! 设计意图：
+//! - 倾斜负载会压垮单个节点，而路由层只看到单次请求。`HotspotDetector` 由路由层
+//!   （`HashRingRouter::owner_of`）与 KV 读写路径喂入访问，报告滑动窗口内最热的键与节点。
+//! - 键用 Space-Saving 摘要统计：每个时间槽最多保留 `capacity` 个计数器，满时淘汰计数最小者，
+//!   新键继承其计数并记为误差上界。窗口由 `slots` 个时间槽组成，过期槽整体丢弃；节点数量少，
+//!   精确计数。
+//! - `NodeLoad` 是给有界负载路由的钩子：路由前可查询候选节点的窗口负载是否超过
+//!   `c × 平均负载` 的上限，超过则顺延到环上的下一个节点。
+//!
+//! 不变量（草图）：
+//! - 报告中每个键的真实次数落在 `[count - error, count]` 内；真实次数超过
+//!   `窗口总量 / capacity` 的键必定出现在摘要中。
+//! - 节点计数之和等于窗口内带节点的访问总数。
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 单个时间槽内的 Space-Saving 摘要
+#[derive(Debug)]
+struct SpaceSaving<K> {
+    capacity: usize,
+    /// 键 → (估计次数, 误差上界)
+    counters: HashMap<K, (u64, u64)>,
+}
+
+impl<K: Hash + Eq + Clone> SpaceSaving<K> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            counters: HashMap::new(),
+        }
+    }
+
+    fn offer(&mut self, key: &K) {
+        if let Some((count, _)) = self.counters.get_mut(key) {
+            *count += 1;
+            return;
+        }
+        if self.counters.len() < self.capacity {
+            self.counters.insert(key.clone(), (1, 0));
+            return;
+        }
+        let min = self.min_count();
+        let victim = self
+            .counters
+            .iter()
+            .find(|(_, (count, _))| *count == min)
+            .map(|(k, _)| k.clone());
+        if let Some(victim) = victim {
+            self.counters.remove(&victim);
+        }
+        self.counters.insert(key.clone(), (min + 1, min));
+    }
+
+    /// 未被跟踪的键在本槽中的次数上界
+    fn floor(&self) -> u64 {
+        if self.counters.len() < self.capacity {
+            0
+        } else {
+            self.min_count()
+        }
+    }
+
+    fn min_count(&self) -> u64 {
+        self.counters.values().map(|(c, _)| *c).min().unwrap_or(0)
+    }
+}
+
+#[derive(Debug)]
+struct Slot<K> {
+    index: u64,
+    keys: SpaceSaving<K>,
+    nodes: HashMap<String, u64>,
+    total: u64,
+}
+
+#[derive(Debug)]
+struct Window<K> {
+    origin: Instant,
+    slots: VecDeque<Slot<K>>,
+}
+
+/// 热键：真实次数在 `[count - error, count]` 内
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotKey<K = String> {
+    pub key: K,
+    pub count: u64,
+    pub error: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotNode {
+    pub node: String,
+    pub count: u64,
+    /// 占窗口内带节点访问的比例
+    pub share: f64,
+}
+
+/// 滑动窗口内的热点报告，键与节点均按次数降序
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotspotReport<K = String> {
+    pub window_ms: u64,
+    /// 窗口内的访问总数
+    pub total: u64,
+    pub keys: Vec<HotKey<K>>,
+    pub nodes: Vec<HotNode>,
+}
+
+/// 有界负载路由查询节点负载的钩子
+pub trait NodeLoad {
+    /// 窗口内路由到 `node` 的访问数
+    fn node_load(&self, node: &str) -> u64;
+    /// 窗口内带节点的访问总数
+    fn total_load(&self) -> u64;
+
+    /// 再分配一次访问后 `node` 是否超过 `c × 平均负载` 的上限（向上取整）
+    fn exceeds_bound(&self, node: &str, nodes: usize, c: f64) -> bool {
+        let bound = (c * (self.total_load() + 1) as f64 / nodes.max(1) as f64).ceil();
+        (self.node_load(node) + 1) as f64 > bound
+    }
+}
+
+pub struct HotspotDetector<K = String> {
+    top_k: usize,
+    capacity: usize,
+    window: Duration,
+    slot_count: usize,
+    state: Mutex<Window<K>>,
+}
+
+impl<K: Hash + Eq + Clone> HotspotDetector<K> {
+    /// 报告前 `top_k` 个键；默认每槽 `16 × top_k` 个计数器，60 秒窗口分 6 个槽
+    pub fn new(top_k: usize) -> Self {
+        let top_k = top_k.max(1);
+        Self {
+            top_k,
+            capacity: top_k * 16,
+            window: Duration::from_secs(60),
+            slot_count: 6,
+            state: Mutex::new(Window {
+                origin: Instant::now(),
+                slots: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// 每个时间槽的计数器数量，越大误差越小
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(self.top_k);
+        self
+    }
+
+    /// 窗口长度与槽数；过期以槽为单位
+    pub fn with_window(mut self, window: Duration, slots: usize) -> Self {
+        self.slot_count = slots.max(1);
+        self.window = window.max(Duration::from_millis(self.slot_count as u64));
+        self
+    }
+
+    pub fn record(&self, key: &K, node: Option<&str>) {
+        self.record_at(key, node, Instant::now());
+    }
+
+    pub fn record_at(&self, key: &K, node: Option<&str>, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        let index = self.slot_index(&state, now);
+        self.expire(&mut state, index);
+        if state.slots.back().is_none_or(|s| s.index != index) {
+            state.slots.push_back(Slot {
+                index,
+                keys: SpaceSaving::new(self.capacity),
+                nodes: HashMap::new(),
+                total: 0,
+            });
+        }
+        let slot = state.slots.back_mut().expect("current slot");
+        slot.total += 1;
+        slot.keys.offer(key);
+        if let Some(node) = node {
+            *slot.nodes.entry(node.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn report(&self) -> HotspotReport<K> {
+        self.report_at(Instant::now())
+    }
+
+    pub fn report_at(&self, now: Instant) -> HotspotReport<K> {
+        let mut state = self.state.lock().unwrap();
+        let index = self.slot_index(&state, now);
+        self.expire(&mut state, index);
+
+        let mut keys: HashMap<K, (u64, u64)> = HashMap::new();
+        for slot in &state.slots {
+            for key in slot.keys.counters.keys() {
+                keys.entry(key.clone()).or_insert((0, 0));
+            }
+        }
+        // 某槽未跟踪该键时，次数上界为该槽的最小计数
+        for (key, (count, error)) in keys.iter_mut() {
+            for slot in &state.slots {
+                let (c, e) = slot
+                    .keys
+                    .counters
+                    .get(key)
+                    .copied()
+                    .unwrap_or((slot.keys.floor(), slot.keys.floor()));
+                *count += c;
+                *error += e;
+            }
+        }
+        let mut keys: Vec<HotKey<K>> = keys
+            .into_iter()
+            .map(|(key, (count, error))| HotKey { key, count, error })
+            .collect();
+        keys.sort_by(|a, b| b.count.cmp(&a.count).then(a.error.cmp(&b.error)));
+        keys.truncate(self.top_k);
+
+        let mut nodes: HashMap<&str, u64> = HashMap::new();
+        for slot in &state.slots {
+            for (node, count) in &slot.nodes {
+                *nodes.entry(node.as_str()).or_insert(0) += count;
+            }
+        }
+        let routed: u64 = nodes.values().sum();
+        let mut nodes: Vec<HotNode> = nodes
+            .into_iter()
+            .map(|(node, count)| HotNode {
+                node: node.to_string(),
+                count,
+                share: count as f64 / routed.max(1) as f64,
+            })
+            .collect();
+        nodes.sort_by(|a, b| b.count.cmp(&a.count).then(a.node.cmp(&b.node)));
+        nodes.truncate(self.top_k);
+
+        HotspotReport {
+            window_ms: self.window.as_millis() as u64,
+            total: state.slots.iter().map(|s| s.total).sum(),
+            keys,
+            nodes,
+        }
+    }
+
+    fn slot_index(&self, state: &Window<K>, now: Instant) -> u64 {
+        let slot_len = self.window / self.slot_count as u32;
+        (now.saturating_duration_since(state.origin).as_nanos() / slot_len.as_nanos()) as u64
+    }
+
+    fn expire(&self, state: &mut Window<K>, current: u64) {
+        let oldest = (current + 1).saturating_sub(self.slot_count as u64);
+        while state.slots.front().is_some_and(|s| s.index < oldest) {
+            state.slots.pop_front();
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> NodeLoad for HotspotDetector<K> {
+    fn node_load(&self, node: &str) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let index = self.slot_index(&state, Instant::now());
+        self.expire(&mut state, index);
+        state.slots.iter().filter_map(|s| s.nodes.get(node)).sum()
+    }
+
+    fn total_load(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let index = self.slot_index(&state, Instant::now());
+        self.expire(&mut state, index);
+        state.slots.iter().flat_map(|s| s.nodes.values()).sum()
+    }
+}
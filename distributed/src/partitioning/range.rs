@@ -0,0 +1,94 @@
+//! 有序范围分区
+//!
+//! 设计意图：
+//! - 与哈希分区不同，范围分区保持键序，热点分片可以在某个键处一分为二，而不必整体重哈希。
+//! - 每个分片拥有 `[start, 下一个分片的 start)`，第一个分片从空串开始，覆盖全部键空间。
+//!
+//! 不变量（草图）：
+//! - 分片区间互不重叠且首尾相接；任意键恰好属于一个分片。
+//! - 分裂只在分片内部（`start < at < end`）进行，原分片保留下半区间，新分片取得上半区间。
+
+use super::Partitioner;
+use crate::core::errors::DistributedError;
+use crate::core::topology::ShardId;
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Included, Unbounded};
+
+#[derive(Debug, Clone)]
+pub struct RangePartitioner {
+    /// 区间起点 → 分片
+    starts: BTreeMap<String, ShardId>,
+    next_shard: u64,
+}
+
+impl Default for RangePartitioner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RangePartitioner {
+    /// 单个分片 `ShardId(0)` 覆盖全部键
+    pub fn new() -> Self {
+        Self {
+            starts: BTreeMap::from([(String::new(), ShardId(0))]),
+            next_shard: 1,
+        }
+    }
+
+    pub fn shard_for(&self, key: &str) -> ShardId {
+        self.starts
+            .range::<str, _>((Unbounded, Included(key)))
+            .next_back()
+            .map(|(_, shard)| *shard)
+            .expect("first range starts at the empty key")
+    }
+
+    /// 分片区间 `[start, end)`；`end` 为 `None` 表示直到键空间末尾
+    pub fn range_of(&self, shard: ShardId) -> Option<(String, Option<String>)> {
+        let start = self
+            .starts
+            .iter()
+            .find(|(_, s)| **s == shard)
+            .map(|(start, _)| start.clone())?;
+        let end = self
+            .starts
+            .range::<str, _>((Excluded(start.as_str()), Unbounded))
+            .next()
+            .map(|(end, _)| end.clone());
+        Some((start, end))
+    }
+
+    /// 按区间顺序列出分片
+    pub fn shards(&self) -> Vec<ShardId> {
+        self.starts.values().copied().collect()
+    }
+
+    /// 下一次分裂将分配的分片 ID
+    pub fn next_shard_id(&self) -> ShardId {
+        ShardId(self.next_shard)
+    }
+
+    /// 在 `at` 处分裂 `shard`，返回取得 `[at, end)` 的新分片
+    pub fn split_shard(&mut self, shard: ShardId, at: &str) -> Result<ShardId, DistributedError> {
+        let (start, end) = self
+            .range_of(shard)
+            .ok_or_else(|| DistributedError::InvalidState(format!("unknown shard {}", shard.0)))?;
+        if at <= start.as_str() || end.as_deref().is_some_and(|end| at >= end) {
+            return Err(DistributedError::Configuration(format!(
+                "split key {at:?} outside shard {} range ({start:?}, {end:?})",
+                shard.0
+            )));
+        }
+        let new_shard = self.next_shard_id();
+        self.next_shard += 1;
+        self.starts.insert(at.to_string(), new_shard);
+        Ok(new_shard)
+    }
+}
+
+impl<K: AsRef<str>> Partitioner<K> for RangePartitioner {
+    fn shard_of(&self, key: &K) -> ShardId {
+        self.shard_for(key.as_ref())
+    }
+}
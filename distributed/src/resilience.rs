@@ -0,0 +1,18 @@
+//! 弹性与过载保护
+//!
+//! 目标：
+//! - 在系统过载时主动丢弃低价值的工作，保护关键请求的延迟与成功率。
+//! - 按租户约束存储与吞吐，避免单个租户挤占共享资源。
+//! - 重试与熔断在同一个循环中协作，熔断器打开后不再重试。
+//!
+//! 参考：Google SRE「Handling Overload」、Netflix concurrency-limits。
+
+pub mod admission;
+pub mod load_shedder;
+pub mod quota;
+pub mod retry_breaker;
+
+pub use admission::{AdmissionController, AdmissionDenied, AdmissionGuard, DenialReason};
+pub use load_shedder::{LoadPermit, LoadShedder, ShedError};
+pub use quota::{QuotaKv, QuotaLimits, QuotaManager, QuotaResource, QuotaStatus};
+pub use retry_breaker::{RetryBreakerError, RetryWithBreaker};
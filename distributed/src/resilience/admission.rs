@@ -0,0 +1,121 @@
+//! 准入控制：限流、熔断与负载丢弃的组合
+//!
+//! 设计意图：
+//! - 调用方通常需要同时套用三种策略，逐个手写既冗长又容易漏掉结果回报。`AdmissionController`
+//!   持有共享的令牌桶、熔断器与 `LoadShedder`，`check(priority)` 依次检查限流 → 熔断 →
+//!   负载丢弃，在第一个拒绝处返回带原因的 `AdmissionDenied`。
+//! - 通过检查后得到 `AdmissionGuard`：它持有负载许可，丢弃时把请求结果回报给熔断器。
+//!   结果由 `succeed` / `fail` 标记；未标记就被丢弃（如 `?` 提前返回或 panic）按失败计。
+//!
+//! 不变量（草图）：
+//! - 被拒绝的请求不占用负载许可，也不向熔断器回报结果。
+//! - 每个 `AdmissionGuard` 恰好向熔断器回报一次。
+
+use super::load_shedder::{LoadPermit, LoadShedder, ShedError};
+use crate::core::errors::DistributedError;
+use crate::security::{CircuitBreaker, TokenBucket};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+pub enum DenialReason {
+    #[error("rate limit exceeded")]
+    RateLimited,
+    #[error("circuit open")]
+    CircuitOpen,
+    #[error("{0}")]
+    Shed(ShedError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+#[error("admission denied: {reason}")]
+pub struct AdmissionDenied {
+    pub reason: DenialReason,
+}
+
+impl From<AdmissionDenied> for DistributedError {
+    fn from(err: AdmissionDenied) -> Self {
+        DistributedError::Overloaded(err.to_string())
+    }
+}
+
+/// 克隆共享同一组策略状态
+#[derive(Debug, Clone)]
+pub struct AdmissionController {
+    rate_limiter: Arc<Mutex<TokenBucket>>,
+    circuit_breaker: Arc<Mutex<CircuitBreaker>>,
+    shedder: LoadShedder,
+}
+
+impl AdmissionController {
+    pub fn new(
+        rate_limiter: Arc<Mutex<TokenBucket>>,
+        circuit_breaker: Arc<Mutex<CircuitBreaker>>,
+        shedder: LoadShedder,
+    ) -> Self {
+        Self {
+            rate_limiter,
+            circuit_breaker,
+            shedder,
+        }
+    }
+
+    /// 依次检查限流、熔断与负载丢弃
+    pub fn check(&self, priority: u8) -> Result<AdmissionGuard, AdmissionDenied> {
+        let deny = |reason| AdmissionDenied { reason };
+        if !self.rate_limiter.lock().unwrap().allow() {
+            return Err(deny(DenialReason::RateLimited));
+        }
+        if !self.circuit_breaker.lock().unwrap().allow_request() {
+            return Err(deny(DenialReason::CircuitOpen));
+        }
+        let permit = self
+            .shedder
+            .add_request(priority)
+            .map_err(|e| deny(DenialReason::Shed(e)))?;
+        Ok(AdmissionGuard {
+            circuit_breaker: self.circuit_breaker.clone(),
+            ok: false,
+            _permit: permit,
+        })
+    }
+
+    pub fn circuit_breaker(&self) -> &Arc<Mutex<CircuitBreaker>> {
+        &self.circuit_breaker
+    }
+
+    pub fn shedder(&self) -> &LoadShedder {
+        &self.shedder
+    }
+}
+
+/// 已准入的请求；丢弃时向熔断器回报结果并归还负载许可
+#[derive(Debug)]
+pub struct AdmissionGuard {
+    circuit_breaker: Arc<Mutex<CircuitBreaker>>,
+    ok: bool,
+    _permit: LoadPermit,
+}
+
+impl AdmissionGuard {
+    /// 设置回报给熔断器的结果
+    pub fn set_ok(&mut self, ok: bool) {
+        self.ok = ok;
+    }
+
+    pub fn succeed(mut self) {
+        self.set_ok(true);
+    }
+
+    pub fn fail(mut self) {
+        self.set_ok(false);
+    }
+}
+
+impl Drop for AdmissionGuard {
+    fn drop(&mut self) {
+        if let Ok(mut cb) = self.circuit_breaker.lock() {
+            cb.on_result(self.ok);
+        }
+    }
+}
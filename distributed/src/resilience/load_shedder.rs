@@ -0,0 +1,119 @@
+//! 按优先级的负载丢弃
+//!
+//! 设计意图：
+//! - 过载时排队只会放大延迟；`LoadShedder` 以在途许可数 / `max_permits` 估计当前负载，
+//!   负载超过 `high_watermark` 后只接纳优先级不低于 `min_accept_priority` 的请求，
+//!   其余立即以 `ShedError::Shed` 拒绝，为高优先级请求预留余量。
+//! - 接纳的请求持有 `LoadPermit`，丢弃许可即归还名额，调用方无需显式释放。
+//! - 优先级数值越大越重要。
+//!
+//! 不变量（草图）：
+//! - 在途许可数不超过 `max_permits`：名额用尽时任何优先级都被拒绝（`AtCapacity`）。
+//! - 负载不超过 `high_watermark` 时不按优先级丢弃。
+
+use crate::core::errors::DistributedError;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+pub enum ShedError {
+    #[error("shed priority {priority} request at load {load:.2}")]
+    Shed { priority: u8, load: f64 },
+    #[error("all {max_permits} permits in flight")]
+    AtCapacity { max_permits: usize },
+}
+
+impl From<ShedError> for DistributedError {
+    fn from(err: ShedError) -> Self {
+        DistributedError::Overloaded(err.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadShedder {
+    max_permits: usize,
+    high_watermark: f64,
+    min_accept_priority: u8,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl LoadShedder {
+    /// 默认负载超过 0.8 后只接纳优先级 ≥ 128 的请求
+    pub fn new(max_permits: usize) -> Self {
+        Self {
+            max_permits: max_permits.max(1),
+            high_watermark: 0.8,
+            min_accept_priority: 128,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn with_high_watermark(mut self, high_watermark: f64) -> Self {
+        self.high_watermark = high_watermark.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_min_accept_priority(mut self, priority: u8) -> Self {
+        self.min_accept_priority = priority;
+        self
+    }
+
+    /// 按当前负载决定是否接纳优先级为 `priority` 的请求
+    pub fn add_request(&self, priority: u8) -> Result<LoadPermit, ShedError> {
+        let mut current = self.in_flight.load(Ordering::Acquire);
+        loop {
+            if current >= self.max_permits {
+                return Err(ShedError::AtCapacity {
+                    max_permits: self.max_permits,
+                });
+            }
+            let load = self.load_of(current);
+            if load > self.high_watermark && priority < self.min_accept_priority {
+                return Err(ShedError::Shed { priority, load });
+            }
+            match self.in_flight.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    return Ok(LoadPermit {
+                        in_flight: self.in_flight.clone(),
+                    });
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// 在途许可占 `max_permits` 的比例，0.0–1.0
+    pub fn current_load(&self) -> f64 {
+        self.load_of(self.in_flight())
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    pub fn max_permits(&self) -> usize {
+        self.max_permits
+    }
+
+    fn load_of(&self, in_flight: usize) -> f64 {
+        (in_flight as f64 / self.max_permits as f64).min(1.0)
+    }
+}
+
+/// 已接纳请求的名额；丢弃时归还
+#[derive(Debug)]
+pub struct LoadPermit {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for LoadPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
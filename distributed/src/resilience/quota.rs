@@ -0,0 +1,302 @@
+//! 租户配额
+//!
+//! 设计意图：
+//! - 多租户部署中，限流只约束请求速率，约束不了存储占用。`QuotaManager` 按租户跟踪写入的
+//!   字节数，并经 `KeyedRateLimiter` 约束每个租户的操作预算。
+//! - 字节配额分软、硬两级：越过软上限仍接纳写入，但计入 `quota_soft_breach_total` 并告警；
+//!   越过硬上限的写入以 `DistributedError::QuotaExceeded` 拒绝。读取与删除从不受限，删除
+//!   还会释放用量，租户因此总能自行回到配额以内。
+//! - 用量是 `PNCounter`：各节点只累加自己的分量，`sync` 以 `Eventual` 级别与共享存储中的
+//!   计数读-合并-写回，节点失效后其余节点仍保留它贡献的用量。检查使用本地视图，两次同步
+//!   之间其他节点的写入可能让租户短暂越过硬上限。
+//! - `QuotaKv` 把配额接入 KV 写路径：写入前按新旧值的大小差检查并在成功后记账，删除后
+//!   释放旧值占用的字节。
+//!
+//! 不变量（草图）：
+//! - 本地用量等于所有已合并节点分量之和；同步顺序不影响收敛结果。
+//! - 被拒绝的写入不改变用量；缩小占用的写入（差值不为正）不受字节配额限制。
+
+use crate::consistency::{ConsistencyLevel, PNCounter};
+use crate::core::errors::DistributedError;
+use crate::monitoring::{Counter, MetricCollector, MetricLabels};
+use crate::security::{KeyedRateLimiter, RateLimitConfig};
+use crate::storage::cached_kv::{ApplyListener, ReplicatedKv};
+use crate::storage::mvcc::KvSnapshot;
+use crate::storage::watch::{KeyPrefix, WatchError, WatchStream};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// 配额约束的资源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuotaResource {
+    Bytes,
+    Ops,
+}
+
+impl QuotaResource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaResource::Bytes => "bytes",
+            QuotaResource::Ops => "ops",
+        }
+    }
+}
+
+impl fmt::Display for QuotaResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 单个租户的上限；`None` 表示不限
+#[derive(Debug, Clone, Default)]
+pub struct QuotaLimits {
+    pub soft_bytes: Option<u64>,
+    pub hard_bytes: Option<u64>,
+    /// 写操作预算（令牌桶）
+    pub ops: Option<RateLimitConfig>,
+}
+
+/// 通过检查的写入相对软上限的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaStatus {
+    Within,
+    SoftLimitExceeded,
+}
+
+pub struct QuotaManager {
+    node_id: String,
+    defaults: QuotaLimits,
+    tenants: HashMap<String, QuotaLimits>,
+    ops: Mutex<KeyedRateLimiter>,
+    usage: Mutex<BTreeMap<String, PNCounter>>,
+    metrics: MetricCollector,
+    soft_breaches: Arc<Counter>,
+    rejections: Arc<Counter>,
+}
+
+impl QuotaManager {
+    /// 存储中用量计数的键前缀，后接租户名
+    pub const USAGE_KEY_PREFIX: &'static str = "quota/usage/";
+
+    /// `defaults` 适用于未单独配置的租户
+    pub fn new(node_id: impl Into<String>, defaults: QuotaLimits) -> Self {
+        let unlimited = RateLimitConfig {
+            capacity: u64::MAX,
+            refill_per_sec: 0,
+        };
+        let ops = KeyedRateLimiter::new(defaults.ops.clone().unwrap_or(unlimited));
+        let mut metrics = MetricCollector::new();
+        let soft_breaches = metrics.counter("quota_soft_breach_total", MetricLabels::new());
+        let rejections = metrics.counter("quota_rejected_total", MetricLabels::new());
+        Self {
+            node_id: node_id.into(),
+            defaults,
+            tenants: HashMap::new(),
+            ops: Mutex::new(ops),
+            usage: Mutex::new(BTreeMap::new()),
+            metrics,
+            soft_breaches,
+            rejections,
+        }
+    }
+
+    pub fn with_tenant_limits(mut self, tenant: impl Into<String>, limits: QuotaLimits) -> Self {
+        let tenant = tenant.into();
+        if let Some(ops) = &limits.ops {
+            let limiter = self.ops.into_inner().unwrap();
+            self.ops = Mutex::new(limiter.with_limit(tenant.clone(), ops.clone()));
+        }
+        self.tenants.insert(tenant, limits);
+        self
+    }
+
+    pub fn limits(&self, tenant: &str) -> &QuotaLimits {
+        self.tenants.get(tenant).unwrap_or(&self.defaults)
+    }
+
+    /// 检查一次使租户用量变化 `delta_bytes` 的写入；消耗一个操作令牌
+    pub fn check_write(
+        &self,
+        tenant: &str,
+        delta_bytes: i64,
+    ) -> Result<QuotaStatus, DistributedError> {
+        let limits = self.limits(tenant);
+        if limits.ops.is_some() && !self.ops.lock().unwrap().allow(&tenant.to_string()) {
+            return Err(self.reject(tenant, QuotaResource::Ops));
+        }
+        if delta_bytes <= 0 {
+            return Ok(QuotaStatus::Within);
+        }
+        let after = self.usage(tenant).saturating_add(delta_bytes as u64);
+        if limits.hard_bytes.is_some_and(|hard| after > hard) {
+            return Err(self.reject(tenant, QuotaResource::Bytes));
+        }
+        if limits.soft_bytes.is_some_and(|soft| after > soft) {
+            self.soft_breaches.inc();
+            #[cfg(feature = "observability")]
+            tracing::warn!(tenant, usage = after, "tenant over soft byte quota");
+            return Ok(QuotaStatus::SoftLimitExceeded);
+        }
+        Ok(QuotaStatus::Within)
+    }
+
+    /// 记入本节点对租户用量的贡献
+    pub fn record_usage(&self, tenant: &str, delta_bytes: i64) {
+        let mut usage = self.usage.lock().unwrap();
+        usage
+            .entry(tenant.to_string())
+            .or_default()
+            .add(&self.node_id, delta_bytes);
+    }
+
+    /// 本地视图中的租户字节用量
+    pub fn usage(&self, tenant: &str) -> u64 {
+        let usage = self.usage.lock().unwrap();
+        usage.get(tenant).map_or(0, |c| c.value().max(0) as u64)
+    }
+
+    /// 与共享存储交换本地已知租户的用量计数（`Eventual` 级别）
+    pub fn sync(
+        &self,
+        store: &dyn ReplicatedKv<String, PNCounter>,
+    ) -> Result<(), DistributedError> {
+        let tenants: Vec<String> = self.usage.lock().unwrap().keys().cloned().collect();
+        for tenant in tenants {
+            self.sync_tenant(&tenant, store)?;
+        }
+        Ok(())
+    }
+
+    /// 合并存储中某个租户的计数并写回；也用于拉取本节点尚未见过的租户
+    pub fn sync_tenant(
+        &self,
+        tenant: &str,
+        store: &dyn ReplicatedKv<String, PNCounter>,
+    ) -> Result<(), DistributedError> {
+        let key = format!("{}{tenant}", Self::USAGE_KEY_PREFIX);
+        let remote = store.get(&key, ConsistencyLevel::Eventual)?;
+        let merged = {
+            let mut usage = self.usage.lock().unwrap();
+            let local = usage.entry(tenant.to_string()).or_default();
+            if let Some(remote) = &remote {
+                local.merge(remote);
+            }
+            local.clone()
+        };
+        if remote.as_ref() != Some(&merged) {
+            store.put(key, merged, ConsistencyLevel::Eventual)?;
+        }
+        Ok(())
+    }
+
+    /// `quota_soft_breach_total` 与 `quota_rejected_total` 所在的指标门面
+    pub fn metrics(&self) -> &MetricCollector {
+        &self.metrics
+    }
+
+    fn reject(&self, tenant: &str, resource: QuotaResource) -> DistributedError {
+        self.rejections.inc();
+        DistributedError::QuotaExceeded {
+            tenant: tenant.to_string(),
+            resource: resource.to_string(),
+        }
+    }
+}
+
+type TenantOf<K> = Box<dyn Fn(&K) -> String + Send + Sync>;
+type SizeOf<K, V> = Box<dyn Fn(&K, &V) -> u64 + Send + Sync>;
+
+/// 在写路径上执行配额的 KV 包装
+pub struct QuotaKv<S, K, V> {
+    inner: S,
+    quotas: Arc<QuotaManager>,
+    tenant_of: TenantOf<K>,
+    size_of: SizeOf<K, V>,
+}
+
+impl<S, K, V> QuotaKv<S, K, V> {
+    /// `tenant_of` 从键取租户，`size_of` 给出一条记录占用的字节数
+    pub fn new(
+        inner: S,
+        quotas: Arc<QuotaManager>,
+        tenant_of: impl Fn(&K) -> String + Send + Sync + 'static,
+        size_of: impl Fn(&K, &V) -> u64 + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            inner,
+            quotas,
+            tenant_of: Box::new(tenant_of),
+            size_of: Box::new(size_of),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn stored_size(&self, key: &K, level: ConsistencyLevel) -> Result<i64, DistributedError>
+    where
+        S: ReplicatedKv<K, V>,
+    {
+        let old = self.inner.get(key, level)?;
+        Ok(old.map_or(0, |v| (self.size_of)(key, &v) as i64))
+    }
+}
+
+impl<S, K, V> ReplicatedKv<K, V> for QuotaKv<S, K, V>
+where
+    S: ReplicatedKv<K, V>,
+    K: Send + Sync,
+    V: Send + Sync,
+{
+    fn get(&self, key: &K, level: ConsistencyLevel) -> Result<Option<V>, DistributedError> {
+        self.inner.get(key, level)
+    }
+
+    /// 先读旧值以得到用量差值；覆盖写只按增量计费
+    fn put(&self, key: K, value: V, level: ConsistencyLevel) -> Result<(), DistributedError> {
+        let tenant = (self.tenant_of)(&key);
+        let delta = (self.size_of)(&key, &value) as i64 - self.stored_size(&key, level)?;
+        self.quotas.check_write(&tenant, delta)?;
+        self.inner.put(key, value, level)?;
+        self.quotas.record_usage(&tenant, delta);
+        Ok(())
+    }
+
+    fn delete(&self, key: &K, level: ConsistencyLevel) -> Result<(), DistributedError> {
+        let freed = self.stored_size(key, level)?;
+        self.inner.delete(key, level)?;
+        self.quotas.record_usage(&(self.tenant_of)(key), -freed);
+        Ok(())
+    }
+
+    fn subscribe(&self, listener: ApplyListener<K>) {
+        self.inner.subscribe(listener);
+    }
+
+    fn watch_from(&self, prefix: K, from: Option<u64>) -> Result<WatchStream<K, V>, WatchError>
+    where
+        K: KeyPrefix,
+    {
+        self.inner.watch_from(prefix, from)
+    }
+
+    fn snapshot(&self) -> Result<KvSnapshot<K, V>, DistributedError>
+    where
+        K: Hash + Eq + Clone,
+        V: Clone,
+    {
+        self.inner.snapshot()
+    }
+
+    fn snapshot_at(&self, version: u64) -> Result<KvSnapshot<K, V>, DistributedError>
+    where
+        K: Hash + Eq + Clone,
+        V: Clone,
+    {
+        self.inner.snapshot_at(version)
+    }
+}
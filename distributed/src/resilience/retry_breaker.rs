@@ -0,0 +1,119 @@
+//! 重试与熔断的组合
+//!
+//! 设计意图：
+//! - 分别使用 `RetryPolicy` 与 `CircuitBreaker` 时，常见的错误是熔断器打开后仍继续重试，
+//!   把压力压回已经失败的下游。`RetryWithBreaker::execute` 把二者放进同一个循环：每次尝试前
+//!   询问熔断器，尝试后把结果记入熔断器；失败后若熔断器已打开，立即返回
+//!   `RetryBreakerError::CircuitOpen`，不再退避与重试；熔断器仍关闭时按策略退避后重试。
+//! - 退避与 `RetryClient` 相同：第 `n` 次重试前等待 `backoff_base_ms * 2^n` 毫秒；等待函数
+//!   可注入，测试中无需真实休眠。
+//!
+//! 不变量（草图）：
+//! - 尝试次数不超过 `max_retries + 1`；熔断器拒绝请求后不再调用操作。
+//! - 每次尝试的结果都恰好记入熔断器一次。
+
+use crate::network::RetryPolicy;
+use crate::security::{CircuitBreaker, CircuitState};
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RetryBreakerError<E> {
+    /// 熔断器打开：尝试前被拒绝，或某次失败使其打开
+    #[error("circuit open after {attempts} attempt(s)")]
+    CircuitOpen {
+        attempts: usize,
+        last_error: Option<E>,
+    },
+    /// 重试次数用尽，熔断器仍未打开
+    #[error("retries exhausted after {attempts} attempt(s): {last_error}")]
+    Exhausted { attempts: usize, last_error: E },
+}
+
+impl<E> RetryBreakerError<E> {
+    /// 实际调用操作的次数
+    pub fn attempts(&self) -> usize {
+        match self {
+            RetryBreakerError::CircuitOpen { attempts, .. }
+            | RetryBreakerError::Exhausted { attempts, .. } => *attempts,
+        }
+    }
+
+    /// 最后一次尝试的错误；未尝试即被熔断时为 `None`
+    pub fn last_error(&self) -> Option<&E> {
+        match self {
+            RetryBreakerError::CircuitOpen { last_error, .. } => last_error.as_ref(),
+            RetryBreakerError::Exhausted { last_error, .. } => Some(last_error),
+        }
+    }
+}
+
+/// 带熔断的重试执行器
+pub struct RetryWithBreaker {
+    sleep: Box<dyn Fn(Duration) + Send + Sync>,
+}
+
+impl Default for RetryWithBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryWithBreaker {
+    /// 以 `std::thread::sleep` 退避
+    pub fn new() -> Self {
+        Self {
+            sleep: Box::new(std::thread::sleep),
+        }
+    }
+
+    /// 自定义退避等待
+    pub fn with_sleep(mut self, sleep: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        self.sleep = Box::new(sleep);
+        self
+    }
+
+    /// 执行 `f`（参数为从 0 开始的尝试序号），按 `policy` 重试，每次尝试的结果记入 `breaker`
+    pub fn execute<T, E>(
+        &self,
+        mut f: impl FnMut(usize) -> Result<T, E>,
+        policy: &RetryPolicy,
+        breaker: &mut CircuitBreaker,
+    ) -> Result<T, RetryBreakerError<E>> {
+        let mut last_error = None;
+        for attempt in 0..=policy.max_retries {
+            if !breaker.allow_request() {
+                return Err(RetryBreakerError::CircuitOpen {
+                    attempts: attempt,
+                    last_error,
+                });
+            }
+            match f(attempt) {
+                Ok(value) => {
+                    breaker.on_result(true);
+                    return Ok(value);
+                }
+                Err(e) => {
+                    breaker.on_result(false);
+                    if breaker.state() == CircuitState::Open {
+                        return Err(RetryBreakerError::CircuitOpen {
+                            attempts: attempt + 1,
+                            last_error: Some(e),
+                        });
+                    }
+                    last_error = Some(e);
+                }
+            }
+            if attempt < policy.max_retries
+                && let Some(base) = policy.backoff_base_ms
+            {
+                let delay = base.saturating_mul(1u64 << attempt.min(16));
+                (self.sleep)(Duration::from_millis(delay));
+            }
+        }
+        Err(RetryBreakerError::Exhausted {
+            attempts: policy.max_retries + 1,
+            last_error: last_error.expect("at least one attempt was made"),
+        })
+    }
+}
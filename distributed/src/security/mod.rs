@@ -1,12 +1,21 @@
 //! 安全与治理模块
 //!
-//! 提供基于内存热更新的 ACL、审计日志、限流与熔断策略。
+//! 提供基于内存热更新的 ACL、审计日志、限流与熔断策略；`signing` 子模块提供传输消息的签名与验签。
+
+pub mod signing;
 
 use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
 use std::time::{Duration, Instant, SystemTime};
 
 use serde::{Deserialize, Serialize};
 
+pub use signing::{
+    AuthError, AuthStats, KeyProvider, MessageAuthenticator, SignedMessage, SigningMiddleware,
+};
+#[cfg(feature = "signing")]
+pub use signing::{Ed25519Authenticator, HmacSha256Authenticator};
+
 // --- 访问控制（ACL） ---
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -133,9 +142,42 @@ impl TokenBucket {
     }
 }
 
+/// 按键（如租户）独立的令牌桶；未单独配置的键使用默认配置
+#[derive(Debug, Clone)]
+pub struct KeyedRateLimiter<K = String> {
+    default: RateLimitConfig,
+    overrides: HashMap<K, RateLimitConfig>,
+    buckets: HashMap<K, TokenBucket>,
+}
+
+impl<K: Hash + Eq + Clone> KeyedRateLimiter<K> {
+    pub fn new(default: RateLimitConfig) -> Self {
+        Self {
+            default,
+            overrides: HashMap::new(),
+            buckets: HashMap::new(),
+        }
+    }
+    /// 为 `key` 单独配置；已有的桶按新配置重建
+    pub fn with_limit(mut self, key: K, config: RateLimitConfig) -> Self {
+        self.buckets.remove(&key);
+        self.overrides.insert(key, config);
+        self
+    }
+    pub fn allow(&mut self, key: &K) -> bool {
+        if !self.buckets.contains_key(key) {
+            let cfg = self.overrides.get(key).unwrap_or(&self.default);
+            let bucket = TokenBucket::new(cfg.capacity, cfg.refill_per_sec);
+            self.buckets.insert(key.clone(), bucket);
+        }
+        self.buckets.get_mut(key).expect("bucket inserted").allow()
+    }
+}
+
 // --- 熔断器（半开） ---
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CircuitState {
     Closed,
     Open,
@@ -148,32 +190,101 @@ pub struct CircuitConfig {
     pub open_ms: u64,
 }
 
+/// 一次状态迁移
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitTransition {
+    pub from: CircuitState,
+    pub to: CircuitState,
+}
+
+/// 熔断器状态迁移记录；计数为迁移发生时当前状态内累计的失败/成功次数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitEvent {
+    pub timestamp: Instant,
+    pub transition: CircuitTransition,
+    pub error_count: u32,
+    pub success_count: u32,
+}
+
+/// 有界事件环：超过容量时淘汰最旧的事件
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    capacity: usize,
+    events: VecDeque<CircuitEvent>,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+    pub fn record(&mut self, event: CircuitEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &CircuitEvent> {
+        self.events.iter()
+    }
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CircuitBreaker {
     cfg: CircuitConfig,
     state: CircuitState,
     errors: u32,
+    successes: u32,
     opened_at: Option<Instant>,
+    events: EventLog,
 }
 
 impl CircuitBreaker {
+    /// 默认保留最近 64 次状态迁移
+    pub const DEFAULT_EVENT_CAPACITY: usize = 64;
+
     pub fn new(cfg: CircuitConfig) -> Self {
         Self {
             cfg,
             state: CircuitState::Closed,
             errors: 0,
+            successes: 0,
             opened_at: None,
+            events: EventLog::new(Self::DEFAULT_EVENT_CAPACITY),
         }
     }
+    /// 事件环容量；会清空已有事件
+    pub fn with_event_capacity(mut self, capacity: usize) -> Self {
+        self.events = EventLog::new(capacity);
+        self
+    }
     pub fn on_result(&mut self, ok: bool) {
         match self.state {
             CircuitState::Closed => {
                 if ok {
                     self.errors = 0;
+                    self.successes += 1;
                 } else {
                     self.errors += 1;
                     if self.errors >= self.cfg.error_threshold {
-                        self.state = CircuitState::Open;
+                        self.transition(CircuitState::Open);
                         self.opened_at = Some(Instant::now());
                     }
                 }
@@ -181,16 +292,16 @@ impl CircuitBreaker {
             CircuitState::Open => {
                 if let Some(t0) = self.opened_at
                     && t0.elapsed() >= Duration::from_millis(self.cfg.open_ms) {
-                        self.state = CircuitState::HalfOpen;
-                        self.errors = 0;
+                        self.transition(CircuitState::HalfOpen);
                     }
             }
             CircuitState::HalfOpen => {
                 if ok {
-                    self.state = CircuitState::Closed;
-                    self.errors = 0;
+                    self.successes += 1;
+                    self.transition(CircuitState::Closed);
                 } else {
-                    self.state = CircuitState::Open;
+                    self.errors += 1;
+                    self.transition(CircuitState::Open);
                     self.opened_at = Some(Instant::now());
                 }
             }
@@ -202,7 +313,7 @@ impl CircuitBreaker {
             CircuitState::Open => {
                 if let Some(t0) = self.opened_at {
                     if t0.elapsed() >= Duration::from_millis(self.cfg.open_ms) {
-                        self.state = CircuitState::HalfOpen;
+                        self.transition(CircuitState::HalfOpen);
                         true
                     } else {
                         false
@@ -217,6 +328,29 @@ impl CircuitBreaker {
     pub fn state(&self) -> CircuitState {
         self.state
     }
+    /// 最近的状态迁移，从旧到新
+    pub fn events(&self) -> impl Iterator<Item = &CircuitEvent> {
+        self.events.iter()
+    }
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+    }
+
+    /// 记录迁移并重置计数，进入新状态后重新累计
+    fn transition(&mut self, to: CircuitState) {
+        self.events.record(CircuitEvent {
+            timestamp: Instant::now(),
+            transition: CircuitTransition {
+                from: self.state,
+                to,
+            },
+            error_count: self.errors,
+            success_count: self.successes,
+        });
+        self.state = to;
+        self.errors = 0;
+        self.successes = 0;
+    }
 }
 
 // --- 汇总策略门面 ---
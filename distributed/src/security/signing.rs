@@ -0,0 +1,427 @@
+//! 消息签名与验证
+//!
+//! 设计意图：
+//! - PBFT 需要经过认证的消息，Raft 在半可信节点之间也需要发现篡改。`MessageAuthenticator`
+//!   对消息字节签名与验签；HMAC-SHA256（共享密钥）与 Ed25519（公私钥）两种实现由
+//!   `signing` 特性启用。
+//! - `SigningMiddleware` 供传输层使用：出站消息封装为 `SignedMessage`，签名覆盖签名者、接收方、
+//!   单调递增的序号与负载；入站消息验签失败、签名者未知、接收方不是本节点、格式错误或重放时
+//!   直接丢弃并计数，不把错误交给处理函数，伪造方因此只能观察到超时。
+//! - 防重放：每个签名者的请求序号须严格大于本节点记录的高水位；回复不占用高水位，而是回显
+//!   请求的序号（`reply_to`），调用方只接受回显了本次请求序号的回复。序号从墙钟纳秒起步，
+//!   签名方重启后仍高于接收方记录的高水位。
+//! - 密钥经 `KeyProvider` 按节点 ID 查找；查不到即视为未知签名者并拒绝。
+//!
+//! 不变量（草图）：
+//! - 只有验签通过的负载才会交给处理函数；`verified + dropped()` 等于入站消息总数。
+//! - 签名覆盖签名者、接收方、序号、回显序号与负载，改写其中任一部分都会导致验签失败。
+//! - 同一条请求至多被接受一次；发给别的节点的消息不会被本节点接受。
+
+use crate::core::errors::DistributedError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AuthError {
+    #[error("unknown signer {0}")]
+    UnknownSigner(String),
+    #[error("invalid signature from {0}")]
+    BadSignature(String),
+    #[error("malformed signed message")]
+    Malformed,
+    #[error("no signing key for local node {0}")]
+    MissingLocalKey(String),
+    #[error("message from {0} is addressed to another node")]
+    WrongRecipient(String),
+    #[error("replayed or stale message from {0}")]
+    Replayed(String),
+}
+
+impl From<AuthError> for DistributedError {
+    fn from(err: AuthError) -> Self {
+        DistributedError::Network(err.to_string())
+    }
+}
+
+/// 按节点 ID 提供密钥材料（HMAC 共享密钥或 Ed25519 公钥）
+pub trait KeyProvider: Send + Sync {
+    fn key(&self, node: &str) -> Option<Vec<u8>>;
+}
+
+impl KeyProvider for HashMap<String, Vec<u8>> {
+    fn key(&self, node: &str) -> Option<Vec<u8>> {
+        self.get(node).cloned()
+    }
+}
+
+pub trait MessageAuthenticator: Send + Sync {
+    /// 本节点作为签名者的 ID
+    fn node_id(&self) -> &str;
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, AuthError>;
+    fn verify(&self, signer: &str, message: &[u8], signature: &[u8]) -> Result<(), AuthError>;
+}
+
+/// 线上的签名消息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedMessage {
+    pub signer: String,
+    pub recipient: String,
+    /// 签名者的单调递增序号
+    pub seq: u64,
+    /// 回复所回显的请求序号；请求为 `None`
+    #[serde(default)]
+    pub reply_to: Option<u64>,
+    pub payload: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SignedMessage {
+    /// 被签名的字节：长度前缀的签名者与接收方 ID、序号、回显序号与负载
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(33 + self.signer.len() + self.recipient.len() + self.payload.len());
+        for id in [&self.signer, &self.recipient] {
+            bytes.extend_from_slice(&(id.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(id.as_bytes());
+        }
+        bytes.extend_from_slice(&self.seq.to_le_bytes());
+        match self.reply_to {
+            Some(seq) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&seq.to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    /// 编码为线上字节
+    pub fn to_bytes(&self) -> Result<Vec<u8>, AuthError> {
+        serde_json::to_vec(self).map_err(|_| AuthError::Malformed)
+    }
+}
+
+/// 入站验签计数
+#[derive(Debug, Default)]
+pub struct AuthStats {
+    verified: AtomicU64,
+    unknown_signer: AtomicU64,
+    bad_signature: AtomicU64,
+    malformed: AtomicU64,
+    misdirected: AtomicU64,
+    replayed: AtomicU64,
+}
+
+impl AuthStats {
+    pub fn verified(&self) -> u64 {
+        self.verified.load(Ordering::Relaxed)
+    }
+
+    pub fn unknown_signer(&self) -> u64 {
+        self.unknown_signer.load(Ordering::Relaxed)
+    }
+
+    pub fn bad_signature(&self) -> u64 {
+        self.bad_signature.load(Ordering::Relaxed)
+    }
+
+    pub fn malformed(&self) -> u64 {
+        self.malformed.load(Ordering::Relaxed)
+    }
+
+    /// 接收方不是本节点的消息
+    pub fn misdirected(&self) -> u64 {
+        self.misdirected.load(Ordering::Relaxed)
+    }
+
+    /// 序号不高于高水位的请求，以及未回显本次请求序号的回复
+    pub fn replayed(&self) -> u64 {
+        self.replayed.load(Ordering::Relaxed)
+    }
+
+    /// 被丢弃的入站消息总数
+    pub fn dropped(&self) -> u64 {
+        self.unknown_signer()
+            + self.bad_signature()
+            + self.malformed()
+            + self.misdirected()
+            + self.replayed()
+    }
+}
+
+/// 传输层的签名/验签中间件
+pub struct SigningMiddleware {
+    auth: Arc<dyn MessageAuthenticator>,
+    stats: AuthStats,
+    next_seq: AtomicU64,
+    /// 每个签名者已接受的最大请求序号
+    high_water: Mutex<HashMap<String, u64>>,
+}
+
+impl SigningMiddleware {
+    pub fn new(auth: Arc<dyn MessageAuthenticator>) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self {
+            auth,
+            stats: AuthStats::default(),
+            next_seq: AtomicU64::new(now),
+            high_water: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn node_id(&self) -> &str {
+        self.auth.node_id()
+    }
+
+    pub fn stats(&self) -> &AuthStats {
+        &self.stats
+    }
+
+    /// 以本节点身份签名发给 `recipient` 的请求，分配下一个序号
+    pub fn sign(&self, recipient: &str, payload: Vec<u8>) -> Result<SignedMessage, AuthError> {
+        self.sign_message(recipient, None, payload)
+    }
+
+    /// 签名对 `recipient` 序号为 `request_seq` 的请求的回复
+    pub fn sign_reply(
+        &self,
+        recipient: &str,
+        request_seq: u64,
+        payload: Vec<u8>,
+    ) -> Result<SignedMessage, AuthError> {
+        self.sign_message(recipient, Some(request_seq), payload)
+    }
+
+    fn sign_message(
+        &self,
+        recipient: &str,
+        reply_to: Option<u64>,
+        payload: Vec<u8>,
+    ) -> Result<SignedMessage, AuthError> {
+        let mut message = SignedMessage {
+            signer: self.auth.node_id().to_string(),
+            recipient: recipient.to_string(),
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+            reply_to,
+            payload,
+            signature: Vec::new(),
+        };
+        message.signature = self.auth.sign(&message.signing_bytes())?;
+        Ok(message)
+    }
+
+    /// 签名请求并编码为线上字节
+    pub fn seal(&self, recipient: &str, payload: Vec<u8>) -> Result<Vec<u8>, AuthError> {
+        self.sign(recipient, payload)?.to_bytes()
+    }
+
+    /// 签名回复并编码为线上字节
+    pub fn seal_reply(
+        &self,
+        recipient: &str,
+        request_seq: u64,
+        payload: Vec<u8>,
+    ) -> Result<Vec<u8>, AuthError> {
+        self.sign_reply(recipient, request_seq, payload)?.to_bytes()
+    }
+
+    /// 只验签（不检查接收方与序号）；失败时计数并返回错误
+    pub fn verify(&self, message: &SignedMessage) -> Result<(), AuthError> {
+        let result = self.verify_signature(message);
+        self.count(&result);
+        result
+    }
+
+    fn verify_signature(&self, message: &SignedMessage) -> Result<(), AuthError> {
+        self.auth.verify(
+            &message.signer,
+            &message.signing_bytes(),
+            &message.signature,
+        )
+    }
+
+    /// 接受一条入站请求：验签、接收方须为本节点、序号须高于该签名者的高水位；
+    /// 通过后推进高水位。失败时计数并返回错误
+    pub fn accept(&self, message: &SignedMessage) -> Result<(), AuthError> {
+        let result = self.check_addressed(message).and_then(|()| {
+            let mut high_water = self.high_water.lock().unwrap();
+            let seen = high_water.get(&message.signer).copied();
+            if message.reply_to.is_some() || seen.is_some_and(|seen| message.seq <= seen) {
+                return Err(AuthError::Replayed(message.signer.clone()));
+            }
+            high_water.insert(message.signer.clone(), message.seq);
+            Ok(())
+        });
+        self.count(&result);
+        result
+    }
+
+    /// 接受一条回复：验签、接收方须为本节点，且须回显本次请求的序号 `request_seq`
+    pub fn accept_reply(&self, message: &SignedMessage, request_seq: u64) -> Result<(), AuthError> {
+        let result = self.check_addressed(message).and_then(|()| {
+            if message.reply_to != Some(request_seq) {
+                return Err(AuthError::Replayed(message.signer.clone()));
+            }
+            Ok(())
+        });
+        self.count(&result);
+        result
+    }
+
+    fn check_addressed(&self, message: &SignedMessage) -> Result<(), AuthError> {
+        self.verify_signature(message)?;
+        if message.recipient != self.auth.node_id() {
+            return Err(AuthError::WrongRecipient(message.signer.clone()));
+        }
+        Ok(())
+    }
+
+    /// 解码并接受线上请求，返回签名者与负载；无效消息计数后返回 `None`
+    pub fn open(&self, bytes: &[u8]) -> Option<(String, Vec<u8>)> {
+        let message = self.decode(None, bytes)?;
+        self.accept(&message).ok()?;
+        Some((message.signer, message.payload))
+    }
+
+    /// 同 `open`，并要求签名者就是传输层看到的发送方 `from`（不一致计为签名无效）；
+    /// 返回请求序号（回复须回显）与负载
+    pub fn open_from(&self, from: &str, bytes: &[u8]) -> Option<(u64, Vec<u8>)> {
+        let message = self.decode(Some(from), bytes)?;
+        self.accept(&message).ok()?;
+        Some((message.seq, message.payload))
+    }
+
+    /// 解码并接受 `from` 对序号为 `request_seq` 的请求的回复，返回负载
+    pub fn open_reply(&self, from: &str, request_seq: u64, bytes: &[u8]) -> Option<Vec<u8>> {
+        let message = self.decode(Some(from), bytes)?;
+        self.accept_reply(&message, request_seq).ok()?;
+        Some(message.payload)
+    }
+
+    fn decode(&self, from: Option<&str>, bytes: &[u8]) -> Option<SignedMessage> {
+        let Ok(message) = serde_json::from_slice::<SignedMessage>(bytes) else {
+            self.count(&Err(AuthError::Malformed));
+            return None;
+        };
+        if from.is_some_and(|from| from != message.signer) {
+            self.count(&Err(AuthError::BadSignature(message.signer)));
+            return None;
+        }
+        Some(message)
+    }
+
+    fn count(&self, result: &Result<(), AuthError>) {
+        let counter = match result {
+            Ok(()) => &self.stats.verified,
+            Err(AuthError::UnknownSigner(_)) => &self.stats.unknown_signer,
+            Err(AuthError::BadSignature(_)) => &self.stats.bad_signature,
+            Err(AuthError::Malformed | AuthError::MissingLocalKey(_)) => &self.stats.malformed,
+            Err(AuthError::WrongRecipient(_)) => &self.stats.misdirected,
+            Err(AuthError::Replayed(_)) => &self.stats.replayed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// HMAC-SHA256：每个节点一把共享密钥，验证方持有全部节点的密钥
+#[cfg(feature = "signing")]
+pub struct HmacSha256Authenticator {
+    node_id: String,
+    keys: Arc<dyn KeyProvider>,
+}
+
+#[cfg(feature = "signing")]
+impl HmacSha256Authenticator {
+    pub fn new(node_id: impl Into<String>, keys: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            keys,
+        }
+    }
+}
+
+#[cfg(feature = "signing")]
+impl MessageAuthenticator for HmacSha256Authenticator {
+    fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, AuthError> {
+        let key = self
+            .keys
+            .key(&self.node_id)
+            .ok_or_else(|| AuthError::MissingLocalKey(self.node_id.clone()))?;
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, &key);
+        Ok(ring::hmac::sign(&key, message).as_ref().to_vec())
+    }
+
+    fn verify(&self, signer: &str, message: &[u8], signature: &[u8]) -> Result<(), AuthError> {
+        let key = self
+            .keys
+            .key(signer)
+            .ok_or_else(|| AuthError::UnknownSigner(signer.to_string()))?;
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, &key);
+        ring::hmac::verify(&key, message, signature)
+            .map_err(|_| AuthError::BadSignature(signer.to_string()))
+    }
+}
+
+/// Ed25519：本节点持有私钥种子，`KeyProvider` 提供各节点的公钥
+#[cfg(feature = "signing")]
+pub struct Ed25519Authenticator {
+    node_id: String,
+    key_pair: ring::signature::Ed25519KeyPair,
+    public_keys: Arc<dyn KeyProvider>,
+}
+
+#[cfg(feature = "signing")]
+impl Ed25519Authenticator {
+    pub fn new(
+        node_id: impl Into<String>,
+        seed: &[u8; 32],
+        public_keys: Arc<dyn KeyProvider>,
+    ) -> Result<Self, AuthError> {
+        let node_id = node_id.into();
+        let key_pair = ring::signature::Ed25519KeyPair::from_seed_unchecked(seed)
+            .map_err(|_| AuthError::MissingLocalKey(node_id.clone()))?;
+        Ok(Self {
+            node_id,
+            key_pair,
+            public_keys,
+        })
+    }
+
+    /// 由种子导出的公钥，供分发给其他节点
+    pub fn public_key(&self) -> Vec<u8> {
+        use ring::signature::KeyPair;
+        self.key_pair.public_key().as_ref().to_vec()
+    }
+}
+
+#[cfg(feature = "signing")]
+impl MessageAuthenticator for Ed25519Authenticator {
+    fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, AuthError> {
+        Ok(self.key_pair.sign(message).as_ref().to_vec())
+    }
+
+    fn verify(&self, signer: &str, message: &[u8], signature: &[u8]) -> Result<(), AuthError> {
+        let public_key = self
+            .public_keys
+            .key(signer)
+            .ok_or_else(|| AuthError::UnknownSigner(signer.to_string()))?;
+        ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public_key)
+            .verify(message, signature)
+            .map_err(|_| AuthError::BadSignature(signer.to_string()))
+    }
+}
@@ -7,18 +7,30 @@
 //! 性质与注意（草图）：
 //! - 新鲜度与一致性：缓存 TTL 与健康检查周期共同决定视图新鲜度；CAP 下倾向可用与分区容忍。
 //! - 退化路径：主要策略失败时可回退至备用策略；健康检查应具备超时与重试。
-//! - 权重与地域：实例包含 `weight` 与 `region` 元数据，供上层策略使用。
+//! - 权重、版本与地域：实例包含 `weight`、部署 `version` 与 `region` 元数据，供上层策略使用；可用区记在 `zone`
+//!   元数据（`ZONE_METADATA_KEY`）中，供就近路由优先选择本区实例。
+//! - 主动健康检查：`register_health_check` 为服务实例登记 `AsyncHealthCheck`，后台巡检任务
+//!   （`spawn_health_reaper`）按 `health_check_interval` 逐个调用，把结果映射为健康分
+//!   （健康 1.0、降级 0.5、不健康 0.0）并同步实例的 `is_healthy`，同时清理超过 TTL 的实例。
 //!
 //! 参考：
 //! - Google SRE Book：负载均衡与服务发现章节。
 //! - Consul/Eureka/ZooKeeper 等注册中心实践资料。
 
+#[cfg(feature = "grpc")]
+pub mod xds;
+
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
 use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
+/// 实例所在可用区的元数据键
+pub const ZONE_METADATA_KEY: &str = "zone";
+
 /// 服务实例信息
 #[derive(Debug, Clone, PartialEq)]
 pub struct ServiceInstance {
@@ -34,6 +46,8 @@ pub struct ServiceInstance {
     pub health_check_url: Option<String>,
     /// 权重（用于负载均衡）
     pub weight: u32,
+    /// 部署版本（semver，如 `1.4.0`），供蓝绿/金丝雀路由区分新旧版本
+    pub version: String,
     /// 最后更新时间
     pub last_updated: Instant,
     /// 是否健康
@@ -55,6 +69,7 @@ impl ServiceInstance {
             metadata,
             health_check_url: None,
             weight: 1,
+            version: String::new(),
             last_updated: Instant::now(),
             is_healthy: true,
         }
@@ -72,6 +87,23 @@ impl ServiceInstance {
         self
     }
 
+    /// 设置部署版本
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// 设置可用区（写入 `zone` 元数据）
+    pub fn with_zone(mut self, zone: impl Into<String>) -> Self {
+        self.metadata.insert(ZONE_METADATA_KEY.to_string(), zone.into());
+        self
+    }
+
+    /// 所在可用区；未标注时为 `None`
+    pub fn zone(&self) -> Option<&str> {
+        self.metadata.get(ZONE_METADATA_KEY).map(String::as_str)
+    }
+
     /// 更新健康状态
     pub fn update_health(&mut self, is_healthy: bool) {
         self.is_healthy = is_healthy;
@@ -420,6 +452,88 @@ impl RegistryServiceDiscovery {
     }
 }
 
+/// 服务实例视图的统一读取接口，供负载均衡与路由层使用
+pub trait ServiceDiscovery {
+    /// 获取指定服务的实例
+    fn get_services(&self, service_name: &str) -> Vec<ServiceInstance>;
+    /// 获取所有服务
+    fn get_all_services(&self) -> HashMap<String, Vec<ServiceInstance>>;
+}
+
+/// 主动健康检查的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthStatus {
+    Healthy,
+    /// 仍可服务但能力下降，如依赖变慢
+    Degraded(String),
+    Unhealthy(String),
+}
+
+impl HealthStatus {
+    /// 对应的健康分：健康 1.0、降级 0.5、不健康 0.0
+    pub fn score(&self) -> f64 {
+        match self {
+            HealthStatus::Healthy => 1.0,
+            HealthStatus::Degraded(_) => 0.5,
+            HealthStatus::Unhealthy(_) => 0.0,
+        }
+    }
+
+    /// 降级的实例仍接收流量
+    pub fn is_serving(&self) -> bool {
+        !matches!(self, HealthStatus::Unhealthy(_))
+    }
+}
+
+pub type HealthCheckFuture<'a> = Pin<Box<dyn Future<Output = HealthStatus> + Send + 'a>>;
+
+/// 服务实例的异步健康检查，如探测 `health_check_url`
+pub trait AsyncHealthCheck {
+    fn check(&self) -> HealthCheckFuture<'_>;
+}
+
+type SharedHealthCheck = Arc<dyn AsyncHealthCheck + Send + Sync>;
+
+/// 巡检任务与管理器共享的健康检查状态
+#[derive(Clone)]
+struct HealthRegistry {
+    checks: Arc<RwLock<HashMap<String, SharedHealthCheck>>>,
+    /// 实例 ID → 最近一次检查结果与健康分
+    results: Arc<RwLock<HashMap<String, (HealthStatus, f64)>>>,
+    service_cache: Arc<RwLock<HashMap<String, Vec<ServiceInstance>>>>,
+}
+
+impl HealthRegistry {
+    /// 逐个执行已登记的检查并更新健康分与实例状态；检查期间不持有锁
+    async fn run_checks(&self) {
+        let checks: Vec<(String, SharedHealthCheck)> = self
+            .checks
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, check)| (id.clone(), check.clone()))
+            .collect();
+        for (id, check) in checks {
+            let status = check.check().await;
+            let serving = status.is_serving();
+            let score = status.score();
+            self.results.write().unwrap().insert(id.clone(), (status, score));
+            let mut cache = self.service_cache.write().unwrap();
+            for instance in cache.values_mut().flatten().filter(|i| i.id == id) {
+                instance.update_health(serving);
+            }
+        }
+    }
+
+    fn reap_expired(&self, ttl: Duration) {
+        let mut cache = self.service_cache.write().unwrap();
+        for instances in cache.values_mut() {
+            instances.retain(|instance| !instance.is_expired(ttl));
+        }
+        cache.retain(|_, instances| !instances.is_empty());
+    }
+}
+
 /// 服务发现管理器
 pub struct ServiceDiscoveryManager {
     config: ServiceDiscoveryConfig,
@@ -428,6 +542,7 @@ pub struct ServiceDiscoveryManager {
     registry_discovery: Option<RegistryServiceDiscovery>,
     service_cache: Arc<RwLock<HashMap<String, Vec<ServiceInstance>>>>,
     health_checker: HealthChecker,
+    health: HealthRegistry,
 }
 
 /// 健康检查器
@@ -486,13 +601,19 @@ impl HealthChecker {
 impl ServiceDiscoveryManager {
     /// 创建服务发现管理器
     pub fn new(config: ServiceDiscoveryConfig) -> Self {
+        let service_cache = Arc::new(RwLock::new(HashMap::new()));
         let mut manager = Self {
             config: config.clone(),
             dns_discovery: None,
             config_discovery: None,
             registry_discovery: None,
-            service_cache: Arc::new(RwLock::new(HashMap::new())),
+            service_cache: service_cache.clone(),
             health_checker: HealthChecker::new(config.health_check_interval),
+            health: HealthRegistry {
+                checks: Arc::new(RwLock::new(HashMap::new())),
+                results: Arc::new(RwLock::new(HashMap::new())),
+                service_cache,
+            },
         };
 
         // 根据策略初始化相应的发现器
@@ -751,11 +872,68 @@ impl ServiceDiscoveryManager {
 
     /// 清理过期服务
     pub fn cleanup_expired_services(&mut self) {
-        let mut cache = self.service_cache.write().unwrap();
-        for (_service_name, instances) in cache.iter_mut() {
-            instances.retain(|instance| !instance.is_expired(self.config.service_ttl));
-        }
-        cache.retain(|_, instances| !instances.is_empty());
+        self.health.reap_expired(self.config.service_ttl);
+    }
+
+    /// 为服务实例（按实例 ID）登记健康检查，替换已有的检查
+    pub fn register_health_check(
+        &self,
+        service_id: &str,
+        check: Box<dyn AsyncHealthCheck + Send + Sync>,
+    ) {
+        self.health
+            .checks
+            .write()
+            .unwrap()
+            .insert(service_id.to_string(), Arc::from(check));
+    }
+
+    /// 移除实例的健康检查与已记录的结果
+    pub fn unregister_health_check(&self, service_id: &str) {
+        self.health.checks.write().unwrap().remove(service_id);
+        self.health.results.write().unwrap().remove(service_id);
+    }
+
+    /// 实例最近一次检查得到的健康分；尚未检查时为 `None`
+    pub fn service_health_score(&self, service_id: &str) -> Option<f64> {
+        self.health
+            .results
+            .read()
+            .unwrap()
+            .get(service_id)
+            .map(|(_, score)| *score)
+    }
+
+    /// 实例最近一次检查的结果
+    pub fn service_health(&self, service_id: &str) -> Option<HealthStatus> {
+        self.health
+            .results
+            .read()
+            .unwrap()
+            .get(service_id)
+            .map(|(status, _)| status.clone())
+    }
+
+    /// 立即执行一轮已登记的健康检查
+    pub async fn run_health_checks(&self) {
+        self.health.run_checks().await;
+    }
+
+    /// 启动后台巡检任务：每个 `health_check_interval` 执行一轮健康检查并清理超过
+    /// `service_ttl` 的实例。须在 tokio 运行时内调用；中止返回的句柄即停止巡检
+    #[cfg(feature = "runtime-tokio")]
+    pub fn spawn_health_reaper(&self) -> tokio::task::JoinHandle<()> {
+        let health = self.health.clone();
+        let (interval, ttl) = (self.config.health_check_interval, self.config.service_ttl);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                health.run_checks().await;
+                health.reap_expired(ttl);
+            }
+        })
     }
 
     /// 获取配置
@@ -770,6 +948,21 @@ impl ServiceDiscoveryManager {
     }
 }
 
+impl ServiceDiscovery for ServiceDiscoveryManager {
+    fn get_services(&self, service_name: &str) -> Vec<ServiceInstance> {
+        self.service_cache
+            .read()
+            .unwrap()
+            .get(service_name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn get_all_services(&self) -> HashMap<String, Vec<ServiceInstance>> {
+        ServiceDiscoveryManager::get_all_services(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,414 @@
+//! xDS（EDS）服务发现
+//!
+//! 设计意图：
+//! - 对接 Istio/Envoy 等 xDS 控制面：通过 gRPC 双向流 `EndpointDiscoveryService/StreamEndpoints`
+//!   订阅 `cluster_name`，把 `ClusterLoadAssignment` 中的 `LocalityLbEndpoints` 转换为 `ServiceInstance`。
+//! - 只实现 EDS 所需的 protobuf 子集，消息以 prost 手写（字段号与 envoy v3 API 一致），无需 protoc。
+//!
+//! 协议要点（草图）：
+//! - 首个 `DiscoveryRequest` 携带节点信息与资源名；每收到一次响应，回送带 `version_info` 与
+//!   `response_nonce` 的请求作为 ACK。
+//! - 每次响应是该集群端点的全量视图，直接替换本地缓存。
+//! - 流出错或被关闭时按指数退避重连；成功收到响应后退避时间复位。
+
+use super::{ServiceDiscovery, ServiceInstance};
+use crate::network::tls::TlsContext;
+use prost::Message;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Endpoint;
+
+/// `ClusterLoadAssignment` 的 type URL
+pub const EDS_TYPE_URL: &str = "type.googleapis.com/envoy.config.endpoint.v3.ClusterLoadAssignment";
+/// EDS 流式方法路径
+pub const STREAM_ENDPOINTS_PATH: &str =
+    "/envoy.service.endpoint.v3.EndpointDiscoveryService/StreamEndpoints";
+
+/// envoy v3 API 的 protobuf 子集
+pub mod proto {
+    /// google.protobuf.Any
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Any {
+        #[prost(string, tag = "1")]
+        pub type_url: String,
+        #[prost(bytes = "vec", tag = "2")]
+        pub value: Vec<u8>,
+    }
+
+    /// google.protobuf.UInt32Value
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct UInt32Value {
+        #[prost(uint32, tag = "1")]
+        pub value: u32,
+    }
+
+    /// envoy.config.core.v3.Node
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Node {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(string, tag = "2")]
+        pub cluster: String,
+    }
+
+    /// envoy.service.discovery.v3.DiscoveryRequest
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DiscoveryRequest {
+        #[prost(string, tag = "1")]
+        pub version_info: String,
+        #[prost(message, optional, tag = "2")]
+        pub node: Option<Node>,
+        #[prost(string, repeated, tag = "3")]
+        pub resource_names: Vec<String>,
+        #[prost(string, tag = "4")]
+        pub type_url: String,
+        #[prost(string, tag = "5")]
+        pub response_nonce: String,
+    }
+
+    /// envoy.service.discovery.v3.DiscoveryResponse
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DiscoveryResponse {
+        #[prost(string, tag = "1")]
+        pub version_info: String,
+        #[prost(message, repeated, tag = "2")]
+        pub resources: Vec<Any>,
+        #[prost(string, tag = "4")]
+        pub type_url: String,
+        #[prost(string, tag = "5")]
+        pub nonce: String,
+    }
+
+    /// envoy.config.endpoint.v3.ClusterLoadAssignment
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ClusterLoadAssignment {
+        #[prost(string, tag = "1")]
+        pub cluster_name: String,
+        #[prost(message, repeated, tag = "2")]
+        pub endpoints: Vec<LocalityLbEndpoints>,
+    }
+
+    /// envoy.config.core.v3.Locality
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Locality {
+        #[prost(string, tag = "1")]
+        pub region: String,
+        #[prost(string, tag = "2")]
+        pub zone: String,
+        #[prost(string, tag = "3")]
+        pub sub_zone: String,
+    }
+
+    /// envoy.config.endpoint.v3.LocalityLbEndpoints
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LocalityLbEndpoints {
+        #[prost(message, optional, tag = "1")]
+        pub locality: Option<Locality>,
+        #[prost(message, repeated, tag = "2")]
+        pub lb_endpoints: Vec<LbEndpoint>,
+        #[prost(message, optional, tag = "3")]
+        pub load_balancing_weight: Option<UInt32Value>,
+        #[prost(uint32, tag = "5")]
+        pub priority: u32,
+    }
+
+    /// envoy.config.core.v3.HealthStatus
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum HealthStatus {
+        Unknown = 0,
+        Healthy = 1,
+        Unhealthy = 2,
+        Draining = 3,
+        Timeout = 4,
+        Degraded = 5,
+    }
+
+    /// envoy.config.endpoint.v3.LbEndpoint
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LbEndpoint {
+        #[prost(message, optional, tag = "1")]
+        pub endpoint: Option<Endpoint>,
+        #[prost(enumeration = "HealthStatus", tag = "2")]
+        pub health_status: i32,
+        #[prost(message, optional, tag = "4")]
+        pub load_balancing_weight: Option<UInt32Value>,
+    }
+
+    /// envoy.config.endpoint.v3.Endpoint
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Endpoint {
+        #[prost(message, optional, tag = "1")]
+        pub address: Option<Address>,
+        #[prost(string, tag = "3")]
+        pub hostname: String,
+    }
+
+    /// envoy.config.core.v3.Address（仅 socket_address）
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Address {
+        #[prost(message, optional, tag = "1")]
+        pub socket_address: Option<SocketAddress>,
+    }
+
+    /// envoy.config.core.v3.SocketAddress（仅数字端口）
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SocketAddress {
+        #[prost(string, tag = "2")]
+        pub address: String,
+        #[prost(uint32, tag = "3")]
+        pub port_value: u32,
+    }
+}
+
+use proto::{
+    ClusterLoadAssignment, DiscoveryRequest, DiscoveryResponse, HealthStatus, LbEndpoint,
+    LocalityLbEndpoints, Node,
+};
+
+/// xDS 发现配置
+#[derive(Debug, Clone)]
+pub struct XdsConfig {
+    /// 管理服务器地址，如 `http://istiod:15010`
+    pub server_uri: String,
+    pub node_id: String,
+    pub cluster_name: String,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// 设置后以 mTLS 连接管理服务器
+    pub tls: Option<Arc<TlsContext>>,
+}
+
+impl XdsConfig {
+    pub fn new(
+        server_uri: impl Into<String>,
+        node_id: impl Into<String>,
+        cluster_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            server_uri: server_uri.into(),
+            node_id: node_id.into(),
+            cluster_name: cluster_name.into(),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            tls: None,
+        }
+    }
+
+    /// 设置重连退避区间
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    pub fn with_tls(mut self, tls: Arc<TlsContext>) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+}
+
+/// 基于 EDS 订阅的服务发现；`start` 后在后台维持订阅流
+pub struct XdsDiscovery {
+    config: XdsConfig,
+    services: Arc<RwLock<HashMap<String, Vec<ServiceInstance>>>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl XdsDiscovery {
+    pub fn new(config: XdsConfig) -> Self {
+        Self {
+            config,
+            services: Arc::new(RwLock::new(HashMap::new())),
+            task: None,
+        }
+    }
+
+    /// 启动后台订阅任务（幂等）
+    pub fn start(&mut self) {
+        if self.task.is_some() {
+            return;
+        }
+        let config = self.config.clone();
+        let services = self.services.clone();
+        self.task = Some(tokio::spawn(async move {
+            let mut backoff = config.initial_backoff;
+            loop {
+                let received = subscribe(&config, &services).await;
+                if received {
+                    backoff = config.initial_backoff;
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(config.max_backoff);
+            }
+        }));
+    }
+
+    /// 停止订阅；已缓存的实例保留
+    pub fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for XdsDiscovery {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl ServiceDiscovery for XdsDiscovery {
+    fn get_services(&self, service_name: &str) -> Vec<ServiceInstance> {
+        self.services
+            .read()
+            .unwrap()
+            .get(service_name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn get_all_services(&self) -> HashMap<String, Vec<ServiceInstance>> {
+        self.services.read().unwrap().clone()
+    }
+}
+
+/// 建立一条 EDS 订阅流并处理响应直到流结束；返回期间是否收到过响应
+async fn subscribe(
+    config: &XdsConfig,
+    services: &RwLock<HashMap<String, Vec<ServiceInstance>>>,
+) -> bool {
+    let mut received = false;
+    let channel = match &config.tls {
+        Some(tls) => match TlsContext::endpoint(&config.server_uri) {
+            Ok(endpoint) => tls.connect(endpoint).await.ok(),
+            Err(_) => None,
+        },
+        None => match Endpoint::from_shared(config.server_uri.clone()) {
+            Ok(endpoint) => endpoint.connect().await.ok(),
+            Err(_) => None,
+        },
+    };
+    let Some(channel) = channel else {
+        return false;
+    };
+    let mut grpc = tonic::client::Grpc::new(channel);
+    if grpc.ready().await.is_err() {
+        return false;
+    }
+
+    let (tx, rx) = mpsc::channel(16);
+    let _ = tx.send(request(config, "", "")).await;
+    let codec = tonic_prost::ProstCodec::<DiscoveryRequest, DiscoveryResponse>::default();
+    let response = grpc
+        .streaming(
+            tonic::Request::new(tokio_stream::wrappers::ReceiverStream::new(rx)),
+            PathAndQuery::from_static(STREAM_ENDPOINTS_PATH),
+            codec,
+        )
+        .await;
+    let Ok(response) = response else {
+        return false;
+    };
+
+    let mut inbound = response.into_inner();
+    while let Ok(Some(resp)) = inbound.message().await {
+        received = true;
+        for (cluster, instances) in to_service_instances(&resp) {
+            services.write().unwrap().insert(cluster, instances);
+        }
+        // ACK：回送本次响应的版本与 nonce
+        if tx
+            .send(request(config, &resp.version_info, &resp.nonce))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+    received
+}
+
+fn request(config: &XdsConfig, version_info: &str, nonce: &str) -> DiscoveryRequest {
+    DiscoveryRequest {
+        version_info: version_info.to_string(),
+        node: Some(Node {
+            id: config.node_id.clone(),
+            cluster: String::new(),
+        }),
+        resource_names: vec![config.cluster_name.clone()],
+        type_url: EDS_TYPE_URL.to_string(),
+        response_nonce: nonce.to_string(),
+    }
+}
+
+/// 把 EDS 响应中的每个 `ClusterLoadAssignment` 转换为 (集群名, 实例列表)
+pub fn to_service_instances(resp: &DiscoveryResponse) -> Vec<(String, Vec<ServiceInstance>)> {
+    resp.resources
+        .iter()
+        .filter(|any| any.type_url == EDS_TYPE_URL)
+        .filter_map(|any| ClusterLoadAssignment::decode(any.value.as_slice()).ok())
+        .map(|cla| {
+            let instances = cla
+                .endpoints
+                .iter()
+                .flat_map(|locality| {
+                    locality
+                        .lb_endpoints
+                        .iter()
+                        .filter_map(|lb| lb_instance(&cla.cluster_name, locality, lb))
+                })
+                .collect();
+            (cla.cluster_name, instances)
+        })
+        .collect()
+}
+
+/// 单个端点 → `ServiceInstance`；非 IP 地址（需 DNS 解析的主机名）或非法端口被跳过
+fn lb_instance(
+    cluster: &str,
+    locality: &LocalityLbEndpoints,
+    lb: &LbEndpoint,
+) -> Option<ServiceInstance> {
+    let socket = lb
+        .endpoint
+        .as_ref()?
+        .address
+        .as_ref()?
+        .socket_address
+        .as_ref()?;
+    let ip: IpAddr = socket.address.parse().ok()?;
+    let address = SocketAddr::new(ip, u16::try_from(socket.port_value).ok()?);
+
+    let mut metadata = HashMap::new();
+    if let Some(l) = &locality.locality {
+        metadata.insert("region".to_string(), l.region.clone());
+        metadata.insert("zone".to_string(), l.zone.clone());
+        metadata.insert("sub_zone".to_string(), l.sub_zone.clone());
+    }
+    metadata.insert("priority".to_string(), locality.priority.to_string());
+
+    // 端点权重优先，其次取所在 locality 的权重
+    let weight = lb
+        .load_balancing_weight
+        .or(locality.load_balancing_weight)
+        .map_or(1, |w| w.value.max(1));
+    let mut instance = ServiceInstance::new(
+        format!("{}-{}", cluster, address),
+        cluster.to_string(),
+        address,
+        metadata,
+    )
+    .with_weight(weight);
+    let healthy = matches!(
+        HealthStatus::try_from(lb.health_status),
+        Ok(HealthStatus::Unknown | HealthStatus::Healthy | HealthStatus::Degraded)
+    );
+    instance.update_health(healthy);
+    Some(instance)
+}
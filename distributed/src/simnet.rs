@@ -0,0 +1,909 @@
+//! 确定性网络模拟
+//!
+//! 设计意图：
+//! - Raft、SWIM、复制各自的内存假网络行为不一、不可复现。`SimNetwork` 统一为离散事件
+//!   调度器：消息与定时器按 `(虚拟时间, 事件序号)` 排序执行，所有随机性（延迟采样、丢包、
+//!   乱序）来自同一个带种子的 RNG，且按固定顺序抽取。
+//! - 每条有向链路可配置延迟分布、丢包率、乱序概率与带宽上限；带宽按字节数计算发送耗时并
+//!   在链路上串行排队。未乱序的消息在同一链路上保持 FIFO。
+//! - `partition(groups, from, to)` 编排分区：在 `[from, to)` 内分属不同组的端点之间发送与
+//!   到达的消息都被丢弃；未列入任何组的端点不受影响。
+//! - 虚拟时钟 `SimClock` 只随调度推进；`SimTimer` 实现 `TimerService`，
+//!   `SimClock::time_source` 可交给 `HlcClock::with_time_source`。
+//! - `call` 发送请求后推进调度直到收到应答或虚拟超时，供同步风格的传输适配器使用：
+//!   `SimRaftTransport`（`RaftTransport`）与 `SimSwimTransport`（`SwimTransport`），
+//!   消息以 JSON 编码，大小计入带宽。
+//! - `request` 返回等待应答的 future，可并发持有多个；`block_on` 在虚拟时间中驱动 future，
+//!   每次未就绪时执行一个事件。`SimHedgeTransport`（`HedgeTransport`）基于此实现。
+//!
+//! 不变量（草图）：
+//! - 相同种子、相同的调用序列得到逐条相同的事件轨迹（`trace`）。
+//! - 虚拟时间单调不减；事件从不早于其调度时间执行。
+//! - 处理器执行时不持有网络内部锁，可在处理器中 `send` / `reply`；但不应在处理器中 `call`。
+
+use crate::codec::{BinaryCodec, JsonCodec};
+use crate::consensus::raft::{
+    AppendEntriesReq, AppendEntriesResp, InstallSnapshotReq, InstallSnapshotResp, MinimalRaft,
+    RaftNode, RaftTransport, RequestVoteReq, RequestVoteResp, Term,
+};
+use crate::core::TimerService;
+use crate::core::errors::{DistributedError, TimeoutError};
+use crate::network::hedging::{HedgeFuture, HedgeTransport, HedgedRequest};
+use crate::security::signing::SigningMiddleware;
+use crate::swim::{SwimEvent, SwimTransport};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// SplitMix64
+struct SimRng(u64);
+
+impl SimRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// [0, 1) 上的均匀分布
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// ---------------- 虚拟时钟 ----------------
+
+/// 虚拟时钟：自模拟开始经过的时间，只由调度器推进
+#[derive(Debug, Clone, Default)]
+pub struct SimClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl SimClock {
+    pub fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        self.now().as_millis() as u64
+    }
+
+    /// 毫秒时间源，可用于 `HlcClock::with_time_source`
+    pub fn time_source(&self) -> impl Fn() -> u64 + Send + Sync + 'static {
+        let clock = self.clone();
+        move || clock.now_ms()
+    }
+
+    fn advance_to(&self, at: Duration) {
+        self.nanos.fetch_max(at.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+/// 在虚拟时间上调度回调的 `TimerService`
+#[derive(Clone)]
+pub struct SimTimer {
+    net: SimNetwork,
+}
+
+impl TimerService for SimTimer {
+    fn after_ms(&self, ms: u64, f: impl FnOnce() + Send + 'static) {
+        self.net.schedule(Duration::from_millis(ms), f);
+    }
+}
+
+// ---------------- 链路模型 ----------------
+
+/// 单向延迟分布
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LatencyModel {
+    Fixed(Duration),
+    Uniform {
+        min: Duration,
+        max: Duration,
+    },
+    /// 正态分布，负值截断为 0
+    Normal {
+        mean: Duration,
+        std_dev: Duration,
+    },
+}
+
+impl LatencyModel {
+    fn sample(&self, rng: &mut SimRng) -> Duration {
+        match *self {
+            LatencyModel::Fixed(d) => d,
+            LatencyModel::Uniform { min, max } => {
+                let span = max.saturating_sub(min).as_nanos() as f64;
+                min + Duration::from_nanos((span * rng.next_f64()) as u64)
+            }
+            LatencyModel::Normal { mean, std_dev } => {
+                // Box-Muller
+                let (u1, u2) = (1.0 - rng.next_f64(), rng.next_f64());
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                let nanos = mean.as_nanos() as f64 + z * std_dev.as_nanos() as f64;
+                Duration::from_nanos(nanos.max(0.0) as u64)
+            }
+        }
+    }
+}
+
+/// 有向链路参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkConfig {
+    pub latency: LatencyModel,
+    /// 丢包概率（0.0~1.0）
+    pub loss: f64,
+    /// 乱序概率：命中的消息额外延迟 `[0, reorder_window)` 且不受 FIFO 约束
+    pub reorder: f64,
+    pub reorder_window: Duration,
+    /// 带宽上限（字节/秒）；`None` 不限
+    pub bandwidth: Option<u64>,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            latency: LatencyModel::Fixed(Duration::from_millis(1)),
+            loss: 0.0,
+            reorder: 0.0,
+            reorder_window: Duration::from_millis(10),
+            bandwidth: None,
+        }
+    }
+}
+
+impl LinkConfig {
+    pub fn with_latency(mut self, latency: LatencyModel) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        self.loss = loss.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_reorder(mut self, probability: f64, window: Duration) -> Self {
+        self.reorder = probability.clamp(0.0, 1.0);
+        self.reorder_window = window;
+        self
+    }
+
+    pub fn with_bandwidth(mut self, bytes_per_sec: Option<u64>) -> Self {
+        self.bandwidth = bytes_per_sec.filter(|b| *b > 0);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct LinkState {
+    /// 链路发送端空闲的时刻（带宽排队）
+    free_at: Duration,
+    /// 最近一条 FIFO 消息的到达时刻
+    fifo_at: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct Partition {
+    groups: Vec<BTreeSet<String>>,
+    from: Duration,
+    to: Duration,
+}
+
+impl Partition {
+    fn separates(&self, a: &str, b: &str, at: Duration) -> bool {
+        if at < self.from || at >= self.to {
+            return false;
+        }
+        let group_of = |node: &str| self.groups.iter().position(|g| g.contains(node));
+        matches!((group_of(a), group_of(b)), (Some(x), Some(y)) if x != y)
+    }
+}
+
+// ---------------- 消息与轨迹 ----------------
+
+/// 在途消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub seq: u64,
+    pub from: String,
+    pub to: String,
+    pub payload: Vec<u8>,
+    pub sent_at: Duration,
+    /// 应答所对应请求的序号
+    pub reply_to: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    Loss,
+    Partition,
+    UnknownEndpoint,
+}
+
+/// 事件轨迹条目，用于复现与比较
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    Sent {
+        at: Duration,
+        seq: u64,
+        from: String,
+        to: String,
+        bytes: usize,
+    },
+    Delivered {
+        at: Duration,
+        seq: u64,
+    },
+    Dropped {
+        at: Duration,
+        seq: u64,
+        reason: DropReason,
+    },
+    Timer {
+        at: Duration,
+        id: u64,
+    },
+}
+
+/// 端点处理器；返回 `Some` 时作为应答发回
+pub type SimHandler = Box<dyn FnMut(&SimNetwork, &Envelope) -> Option<Vec<u8>> + Send>;
+
+enum Event {
+    Deliver(Envelope),
+    Timer(Box<dyn FnOnce() + Send>),
+}
+
+struct Inner {
+    rng: SimRng,
+    next_id: u64,
+    /// `(执行时间, 事件序号)` → 事件
+    queue: BTreeMap<(Duration, u64), Event>,
+    default_link: LinkConfig,
+    links: HashMap<(String, String), LinkConfig>,
+    link_state: HashMap<(String, String), LinkState>,
+    partitions: Vec<Partition>,
+    handlers: BTreeMap<String, Arc<Mutex<SimHandler>>>,
+    /// `call` / `request` 正在等待应答的请求序号
+    awaiting: HashSet<u64>,
+    replies: HashMap<u64, Vec<u8>>,
+    /// `SimResponse` 登记的唤醒器
+    wakers: HashMap<u64, Waker>,
+    trace: Vec<TraceEvent>,
+}
+
+impl Inner {
+    fn separated(&self, a: &str, b: &str, at: Duration) -> bool {
+        self.partitions.iter().any(|p| p.separates(a, b, at))
+    }
+}
+
+/// 确定性离散事件网络；克隆得到同一网络的句柄
+#[derive(Clone)]
+pub struct SimNetwork {
+    inner: Arc<Mutex<Inner>>,
+    clock: SimClock,
+}
+
+impl SimNetwork {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                rng: SimRng(seed),
+                next_id: 0,
+                queue: BTreeMap::new(),
+                default_link: LinkConfig::default(),
+                links: HashMap::new(),
+                link_state: HashMap::new(),
+                partitions: Vec::new(),
+                handlers: BTreeMap::new(),
+                awaiting: HashSet::new(),
+                replies: HashMap::new(),
+                wakers: HashMap::new(),
+                trace: Vec::new(),
+            })),
+            clock: SimClock::default(),
+        }
+    }
+
+    /// 未单独配置的链路使用的参数
+    pub fn with_default_link(self, link: LinkConfig) -> Self {
+        self.inner.lock().unwrap().default_link = link;
+        self
+    }
+
+    /// 配置 `from → to` 方向的链路
+    pub fn set_link(&self, from: &str, to: &str, link: LinkConfig) {
+        self.inner
+            .lock()
+            .unwrap()
+            .links
+            .insert((from.to_string(), to.to_string()), link);
+    }
+
+    /// 在虚拟时间 `[from, to)` 内把端点按组隔离
+    pub fn partition<G, S>(&self, groups: impl IntoIterator<Item = G>, from: Duration, to: Duration)
+    where
+        G: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let groups = groups
+            .into_iter()
+            .map(|g| g.into_iter().map(Into::into).collect())
+            .collect();
+        self.inner
+            .lock()
+            .unwrap()
+            .partitions
+            .push(Partition { groups, from, to });
+    }
+
+    /// 注册（或替换）端点
+    pub fn register(
+        &self,
+        id: impl Into<String>,
+        handler: impl FnMut(&SimNetwork, &Envelope) -> Option<Vec<u8>> + Send + 'static,
+    ) {
+        self.inner
+            .lock()
+            .unwrap()
+            .handlers
+            .insert(id.into(), Arc::new(Mutex::new(Box::new(handler))));
+    }
+
+    /// 移除端点（模拟宕机）；之后到达的消息以 `UnknownEndpoint` 丢弃
+    pub fn unregister(&self, id: &str) {
+        self.inner.lock().unwrap().handlers.remove(id);
+    }
+
+    pub fn clock(&self) -> SimClock {
+        self.clock.clone()
+    }
+
+    pub fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    pub fn timer(&self) -> SimTimer {
+        SimTimer { net: self.clone() }
+    }
+
+    pub fn trace(&self) -> Vec<TraceEvent> {
+        self.inner.lock().unwrap().trace.clone()
+    }
+
+    /// 尚未执行的事件数
+    pub fn pending(&self) -> usize {
+        self.inner.lock().unwrap().queue.len()
+    }
+
+    /// 从网络的种子 RNG 抽取，供工作负载生成使用，保证整个场景只有一个随机源
+    pub fn next_random(&self) -> u64 {
+        self.inner.lock().unwrap().rng.next_u64()
+    }
+
+    /// 在 `delay` 后的虚拟时间执行 `f`
+    pub fn schedule(&self, delay: Duration, f: impl FnOnce() + Send + 'static) {
+        let at = self.now() + delay;
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.queue.insert((at, id), Event::Timer(Box::new(f)));
+    }
+
+    /// 单向发送，返回消息序号
+    pub fn send(&self, from: &str, to: &str, payload: Vec<u8>) -> u64 {
+        self.transmit(from, to, payload, None)
+    }
+
+    /// 对收到的请求发送应答
+    pub fn reply(&self, request: &Envelope, payload: Vec<u8>) -> u64 {
+        self.transmit(&request.to, &request.from, payload, Some(request.seq))
+    }
+
+    fn transmit(&self, from: &str, to: &str, payload: Vec<u8>, reply_to: Option<u64>) -> u64 {
+        let now = self.now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let seq = inner.next_id;
+        let bytes = payload.len();
+        inner.trace.push(TraceEvent::Sent {
+            at: now,
+            seq,
+            from: from.to_string(),
+            to: to.to_string(),
+            bytes,
+        });
+        let key = (from.to_string(), to.to_string());
+        let link = inner.links.get(&key).unwrap_or(&inner.default_link).clone();
+        // 每条消息固定抽取三次，轨迹只取决于种子与调用序列
+        let lost = inner.rng.next_f64() < link.loss;
+        let reordered = inner.rng.next_f64() < link.reorder;
+        let reorder_frac = inner.rng.next_f64();
+        let latency = link.latency.sample(&mut inner.rng);
+        let reason = if inner.separated(from, to, now) {
+            Some(DropReason::Partition)
+        } else if lost {
+            Some(DropReason::Loss)
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            inner.trace.push(TraceEvent::Dropped {
+                at: now,
+                seq,
+                reason,
+            });
+            return seq;
+        }
+
+        let state = inner.link_state.entry(key).or_default();
+        let tx = link.bandwidth.map_or(Duration::ZERO, |bw| {
+            Duration::from_nanos((bytes as u128 * 1_000_000_000 / bw as u128) as u64)
+        });
+        state.free_at = state.free_at.max(now) + tx;
+        let mut at = state.free_at + latency;
+        if reordered {
+            at += link.reorder_window.mul_f64(reorder_frac);
+        } else {
+            at = at.max(state.fifo_at);
+            state.fifo_at = at;
+        }
+        let envelope = Envelope {
+            seq,
+            from: from.to_string(),
+            to: to.to_string(),
+            payload,
+            sent_at: now,
+            reply_to,
+        };
+        inner.queue.insert((at, seq), Event::Deliver(envelope));
+        seq
+    }
+
+    /// 执行下一个事件；队列为空时返回 `false`
+    pub fn step(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(((at, id), event)) = inner.queue.pop_first() else {
+            return false;
+        };
+        self.clock.advance_to(at);
+        match event {
+            Event::Timer(f) => {
+                inner.trace.push(TraceEvent::Timer { at, id });
+                drop(inner);
+                f();
+            }
+            Event::Deliver(envelope) => {
+                let seq = envelope.seq;
+                let handler = inner.handlers.get(&envelope.to).cloned();
+                let reason = if inner.separated(&envelope.from, &envelope.to, at) {
+                    Some(DropReason::Partition)
+                } else if handler.is_none() {
+                    Some(DropReason::UnknownEndpoint)
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    inner.trace.push(TraceEvent::Dropped { at, seq, reason });
+                    return true;
+                }
+                inner.trace.push(TraceEvent::Delivered { at, seq });
+                if let Some(request) = envelope.reply_to
+                    && inner.awaiting.remove(&request)
+                {
+                    inner.replies.insert(request, envelope.payload);
+                    let waker = inner.wakers.remove(&request);
+                    drop(inner);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                    return true;
+                }
+                drop(inner);
+                let reply = handler.and_then(|h| (h.lock().unwrap())(self, &envelope));
+                if let Some(reply) = reply {
+                    self.reply(&envelope, reply);
+                }
+            }
+        }
+        true
+    }
+
+    /// 执行所有不晚于 `deadline` 的事件，之后把时钟推进到 `deadline`
+    pub fn run_until(&self, deadline: Duration) {
+        while self.next_event_at().is_some_and(|at| at <= deadline) {
+            self.step();
+        }
+        self.clock.advance_to(deadline);
+    }
+
+    pub fn run_for(&self, duration: Duration) {
+        self.run_until(self.now() + duration);
+    }
+
+    /// 执行直到队列为空（周期性定时器会使其不返回）
+    pub fn run_until_idle(&self) {
+        while self.step() {}
+    }
+
+    fn next_event_at(&self) -> Option<Duration> {
+        self.inner
+            .lock()
+            .unwrap()
+            .queue
+            .first_key_value()
+            .map(|((at, _), _)| *at)
+    }
+
+    /// 发送请求并推进调度，直到收到应答或经过 `timeout` 虚拟时间
+    pub fn call(
+        &self,
+        from: &str,
+        to: &str,
+        payload: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, DistributedError> {
+        let deadline = self.now() + timeout;
+        let seq = self.send(from, to, payload);
+        self.inner.lock().unwrap().awaiting.insert(seq);
+        loop {
+            if let Some(reply) = self.inner.lock().unwrap().replies.remove(&seq) {
+                return Ok(reply);
+            }
+            if self.next_event_at().is_none_or(|at| at > deadline) {
+                break;
+            }
+            self.step();
+        }
+        self.clock.advance_to(deadline);
+        self.inner.lock().unwrap().awaiting.remove(&seq);
+        Err(TimeoutError(timeout).into())
+    }
+
+    /// 发送请求，返回等待其应答的 future；不推进调度，也没有超时
+    pub fn request(&self, from: &str, to: &str, payload: Vec<u8>) -> SimResponse {
+        let seq = self.send(from, to, payload);
+        self.inner.lock().unwrap().awaiting.insert(seq);
+        SimResponse {
+            net: self.clone(),
+            seq,
+        }
+    }
+
+    /// 在虚拟时间中驱动 `future`：每次轮询未就绪时执行一个事件；事件耗尽仍未就绪则 panic
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            assert!(self.step(), "simnet went idle before the future completed");
+        }
+    }
+}
+
+/// `request` 的应答；丢弃即放弃等待，之后到达的应答按普通消息交给发送端的处理器
+pub struct SimResponse {
+    net: SimNetwork,
+    seq: u64,
+}
+
+impl Future for SimResponse {
+    type Output = Vec<u8>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Vec<u8>> {
+        let mut inner = self.net.inner.lock().unwrap();
+        if let Some(reply) = inner.replies.remove(&self.seq) {
+            return Poll::Ready(reply);
+        }
+        inner.wakers.insert(self.seq, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for SimResponse {
+    fn drop(&mut self) {
+        let mut inner = self.net.inner.lock().unwrap();
+        inner.awaiting.remove(&self.seq);
+        inner.replies.remove(&self.seq);
+        inner.wakers.remove(&self.seq);
+    }
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DistributedError> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| DistributedError::Network(format!("malformed simnet payload: {e}")))
+}
+
+// ---------------- Raft 适配 ----------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum RaftRpc<E> {
+    AppendEntries(AppendEntriesReq<(Term, E)>),
+    RequestVote(RequestVoteReq),
+    InstallSnapshot(InstallSnapshotReq),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum RaftRpcResp {
+    AppendEntries(AppendEntriesResp),
+    RequestVote(RequestVoteResp),
+    InstallSnapshot(InstallSnapshotResp),
+    Error(String),
+}
+
+/// 经 `SimNetwork` 的 Raft RPC；返回的 future 在创建时即已在虚拟时间中完成往返
+#[derive(Clone)]
+pub struct SimRaftTransport {
+    net: SimNetwork,
+    local: String,
+    timeout: Duration,
+    signing: Option<Arc<SigningMiddleware>>,
+}
+
+impl SimRaftTransport {
+    /// 默认 RPC 超时 100ms（虚拟时间）
+    pub fn new(net: SimNetwork, local: impl Into<String>) -> Self {
+        Self {
+            net,
+            local: local.into(),
+            timeout: Duration::from_millis(100),
+            signing: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 对出站 RPC 签名，只接受回显了本次请求序号的签名回复；对端须以 `serve_signed` 注册
+    pub fn with_signing(mut self, signing: Arc<SigningMiddleware>) -> Self {
+        self.signing = Some(signing);
+        self
+    }
+
+    /// 把 `node` 注册为端点 `id`，处理入站 Raft RPC
+    pub fn serve<E>(net: &SimNetwork, id: impl Into<String>, node: Arc<Mutex<MinimalRaft<E>>>)
+    where
+        E: Clone + Serialize + DeserializeOwned + Send + 'static,
+    {
+        net.register(id, move |_, envelope| {
+            Some(handle_raft_rpc(&node, &envelope.payload))
+        });
+    }
+
+    /// 同 `serve`，但只处理经 `signing` 验签、签名者与发送方一致且未重放的 RPC，并对回复签名
+    /// （回显请求序号）；无效请求被丢弃并计数，不作回复，调用方只会观察到超时
+    pub fn serve_signed<E>(
+        net: &SimNetwork,
+        id: impl Into<String>,
+        node: Arc<Mutex<MinimalRaft<E>>>,
+        signing: Arc<SigningMiddleware>,
+    ) where
+        E: Clone + Serialize + DeserializeOwned + Send + 'static,
+    {
+        net.register(id, move |_, envelope| {
+            let (seq, payload) = signing.open_from(&envelope.from, &envelope.payload)?;
+            signing
+                .seal_reply(&envelope.from, seq, handle_raft_rpc(&node, &payload))
+                .ok()
+        });
+    }
+
+    fn rpc<E: Serialize + DeserializeOwned>(
+        &self,
+        target: &str,
+        rpc: RaftRpc<E>,
+    ) -> Result<RaftRpcResp, DistributedError> {
+        let mut payload = JsonCodec.encode(&rpc);
+        let mut request_seq = 0;
+        if let Some(signing) = &self.signing {
+            let request = signing.sign(target, payload)?;
+            request_seq = request.seq;
+            payload = request.to_bytes()?;
+        }
+        let mut reply = self.net.call(&self.local, target, payload, self.timeout)?;
+        if let Some(signing) = &self.signing {
+            reply = signing
+                .open_reply(target, request_seq, &reply)
+                .ok_or_else(|| {
+                    DistributedError::Network(format!("unauthenticated reply from {target}"))
+                })?;
+        }
+        match decode(&reply)? {
+            RaftRpcResp::Error(e) => Err(DistributedError::Consensus(e)),
+            resp => Ok(resp),
+        }
+    }
+}
+
+fn handle_raft_rpc<E>(node: &Mutex<MinimalRaft<E>>, payload: &[u8]) -> Vec<u8>
+where
+    E: Clone + Serialize + DeserializeOwned,
+{
+    let resp = decode::<RaftRpc<E>>(payload).and_then(|rpc| {
+        let mut node = node.lock().unwrap();
+        match rpc {
+            RaftRpc::AppendEntries(req) => node
+                .handle_append_entries_with_terms(req)
+                .map(RaftRpcResp::AppendEntries),
+            RaftRpc::RequestVote(req) => {
+                node.handle_request_vote(req).map(RaftRpcResp::RequestVote)
+            }
+            RaftRpc::InstallSnapshot(req) => node
+                .handle_install_snapshot(req)
+                .map(RaftRpcResp::InstallSnapshot),
+        }
+    });
+    let resp = resp.unwrap_or_else(|e| RaftRpcResp::Error(e.to_string()));
+    JsonCodec.encode(&resp)
+}
+
+fn unexpected_resp() -> DistributedError {
+    DistributedError::Network("unexpected raft response".to_string())
+}
+
+impl<E: Serialize + DeserializeOwned + Send> RaftTransport<E> for SimRaftTransport {
+    fn append_entries(
+        &self,
+        target: &str,
+        req: AppendEntriesReq<(Term, E)>,
+    ) -> impl Future<Output = Result<AppendEntriesResp, DistributedError>> + Send {
+        let result = match self.rpc(target, RaftRpc::AppendEntries(req)) {
+            Ok(RaftRpcResp::AppendEntries(resp)) => Ok(resp),
+            Ok(_) => Err(unexpected_resp()),
+            Err(e) => Err(e),
+        };
+        std::future::ready(result)
+    }
+
+    fn request_vote(
+        &self,
+        target: &str,
+        req: RequestVoteReq,
+    ) -> impl Future<Output = Result<RequestVoteResp, DistributedError>> + Send {
+        let result = match self.rpc::<E>(target, RaftRpc::RequestVote(req)) {
+            Ok(RaftRpcResp::RequestVote(resp)) => Ok(resp),
+            Ok(_) => Err(unexpected_resp()),
+            Err(e) => Err(e),
+        };
+        std::future::ready(result)
+    }
+
+    fn install_snapshot(
+        &self,
+        target: &str,
+        req: InstallSnapshotReq,
+    ) -> impl Future<Output = Result<InstallSnapshotResp, DistributedError>> + Send {
+        let result = match self.rpc::<E>(target, RaftRpc::InstallSnapshot(req)) {
+            Ok(RaftRpcResp::InstallSnapshot(resp)) => Ok(resp),
+            Ok(_) => Err(unexpected_resp()),
+            Err(e) => Err(e),
+        };
+        std::future::ready(result)
+    }
+}
+
+// ---------------- SWIM 适配 ----------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum SwimWire {
+    Ping,
+    PingReq { target: String },
+    Gossip(Vec<SwimEvent>),
+    Ack,
+}
+
+/// 经 `SimNetwork` 的 SWIM 探测与 gossip
+#[derive(Clone)]
+pub struct SimSwimTransport {
+    net: SimNetwork,
+    local: String,
+    timeout: Duration,
+}
+
+impl SimSwimTransport {
+    /// 默认探测超时 50ms（虚拟时间）
+    pub fn new(net: SimNetwork, local: impl Into<String>) -> Self {
+        Self {
+            net,
+            local: local.into(),
+            timeout: Duration::from_millis(50),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 注册 SWIM 端点 `id`：应答 ping、代为转发 ping-req，返回收到的 gossip 事件
+    pub fn serve(net: &SimNetwork, id: impl Into<String>) -> Arc<Mutex<Vec<SwimEvent>>> {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        // 代为探测的请求序号 → 原始 ping-req
+        let mut relayed: HashMap<u64, Envelope> = HashMap::new();
+        net.register(id, move |net, envelope| {
+            if let Some(origin) = envelope.reply_to.and_then(|seq| relayed.remove(&seq)) {
+                net.reply(&origin, JsonCodec.encode(&SwimWire::Ack));
+                return None;
+            }
+            match decode::<SwimWire>(&envelope.payload).ok()? {
+                SwimWire::Ping => Some(JsonCodec.encode(&SwimWire::Ack)),
+                SwimWire::PingReq { target } => {
+                    let seq = net.send(&envelope.to, &target, JsonCodec.encode(&SwimWire::Ping));
+                    relayed.insert(seq, envelope.clone());
+                    None
+                }
+                SwimWire::Gossip(events) => {
+                    sink.lock().unwrap().extend(events);
+                    Some(JsonCodec.encode(&SwimWire::Ack))
+                }
+                SwimWire::Ack => None,
+            }
+        });
+        received
+    }
+
+    fn acked(&self, to: &str, msg: &SwimWire) -> bool {
+        self.net
+            .call(&self.local, to, JsonCodec.encode(msg), self.timeout)
+            .is_ok_and(|reply| matches!(decode(&reply), Ok(SwimWire::Ack)))
+    }
+}
+
+impl SwimTransport for SimSwimTransport {
+    fn ping(&self, to: &str) -> bool {
+        self.acked(to, &SwimWire::Ping)
+    }
+
+    fn ping_req(&self, relay: &str, target: &str) -> bool {
+        self.acked(
+            relay,
+            &SwimWire::PingReq {
+                target: target.to_string(),
+            },
+        )
+    }
+
+    fn gossip(&self, to: &str, events: &[SwimEvent]) -> bool {
+        self.acked(to, &SwimWire::Gossip(events.to_vec()))
+    }
+}
+
+// ---------------- 对冲请求适配 ----------------
+
+/// 经 `SimNetwork` 的对冲请求传输：负载原样发给副本，副本处理器的应答即响应；
+/// 本地端点须已注册（可为不处理消息的空处理器），否则应答被丢弃
+#[derive(Clone)]
+pub struct SimHedgeTransport {
+    net: SimNetwork,
+    local: String,
+}
+
+impl SimHedgeTransport {
+    pub fn new(net: SimNetwork, local: impl Into<String>) -> Self {
+        Self {
+            net,
+            local: local.into(),
+        }
+    }
+}
+
+impl HedgeTransport for SimHedgeTransport {
+    fn call(&self, node: &str, request: &HedgedRequest) -> HedgeFuture {
+        let response = self.net.request(&self.local, node, request.payload.clone());
+        Box::pin(async move { Ok(response.await) })
+    }
+}
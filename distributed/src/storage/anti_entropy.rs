@@ -0,0 +1,223 @@
+//! 反熵（anti-entropy）：基于 Merkle 树的副本对账
+//!
+//! 设计意图：
+//! - 键按哈希落入固定数量（`2^depth`）的桶，桶即叶子；叶子哈希由桶内按键排序的
+//!   `(key, version, value)` 条目哈希组合而成，内部节点哈希其左右子节点。
+//!   按桶而非按排序位置划分叶子，增删一个键只影响一条根到叶的路径。
+//! - `diff` 自顶向下比较两棵树，只进入哈希不同的子树；在分歧叶子内逐键比较，
+//!   返回两侧版本或值不同、或只存在于一侧的键。交换时只需传输这些键的条目。
+//! - `sync` 按版本合并：远端版本更高或本地缺失时采用远端条目（版本相同以远端为准，
+//!   使两侧收敛）。
+//!
+//! 不变量（草图）：
+//! - 相同数据、相同深度构建的树根哈希相同（哈希使用固定密钥的 `AHasher`）。
+//! - 双向 `sync` 分歧键后两侧树根相同。
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// 带版本的键值数据：`key -> (version, value)`
+pub type VersionedData = HashMap<String, (u64, Vec<u8>)>;
+
+/// 默认深度：1024 个桶
+pub const DEFAULT_DEPTH: u32 = 10;
+
+fn hash_of(parts: impl Hash) -> u64 {
+    let mut h = ahash::AHasher::default();
+    parts.hash(&mut h);
+    h.finish()
+}
+
+/// 按键哈希分桶的完全二叉 Merkle 树
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree {
+    depth: u32,
+    /// 堆式布局：节点 i 的子节点为 2i+1 与 2i+2，叶子在最后 `2^depth` 个位置
+    nodes: Vec<u64>,
+    /// 每个桶内按键排序的条目哈希
+    buckets: Vec<Vec<(String, u64)>>,
+}
+
+impl MerkleTree {
+    pub fn build(data: &VersionedData, depth: u32) -> Self {
+        let leaves = 1usize << depth;
+        let mut buckets: Vec<Vec<(String, u64)>> = vec![Vec::new(); leaves];
+        for (key, (version, value)) in data {
+            let bucket = (hash_of(key) as usize) & (leaves - 1);
+            buckets[bucket].push((key.clone(), hash_of((key, version, value))));
+        }
+        let mut nodes = vec![0u64; 2 * leaves - 1];
+        for (i, bucket) in buckets.iter_mut().enumerate() {
+            bucket.sort_unstable();
+            nodes[leaves - 1 + i] = hash_of(&*bucket);
+        }
+        for i in (0..leaves - 1).rev() {
+            nodes[i] = hash_of((nodes[2 * i + 1], nodes[2 * i + 2]));
+        }
+        Self {
+            depth,
+            nodes,
+            buckets,
+        }
+    }
+
+    pub fn root(&self) -> u64 {
+        self.nodes[0]
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    fn first_leaf(&self) -> usize {
+        (1usize << self.depth) - 1
+    }
+
+    fn bucket_entries(&self, bucket: usize) -> HashMap<&str, u64> {
+        self.buckets[bucket]
+            .iter()
+            .map(|(k, h)| (k.as_str(), *h))
+            .collect()
+    }
+
+    /// 全部条目（深度不同无法逐层比较时使用）
+    fn all_entries(&self) -> HashMap<&str, u64> {
+        self.buckets
+            .iter()
+            .flatten()
+            .map(|(k, h)| (k.as_str(), *h))
+            .collect()
+    }
+}
+
+/// 使用默认深度构建 Merkle 树
+pub fn build_merkle(data: &VersionedData) -> MerkleTree {
+    MerkleTree::build(data, DEFAULT_DEPTH)
+}
+
+/// 返回两棵树之间分歧的键（升序、去重）
+pub fn diff(local: &MerkleTree, remote: &MerkleTree) -> Vec<String> {
+    let mut divergent = Vec::new();
+    if local.depth != remote.depth {
+        diff_entries(&local.all_entries(), &remote.all_entries(), &mut divergent);
+    } else {
+        diff_node(local, remote, 0, &mut divergent);
+    }
+    divergent.sort_unstable();
+    divergent.dedup();
+    divergent
+}
+
+fn diff_node(local: &MerkleTree, remote: &MerkleTree, index: usize, out: &mut Vec<String>) {
+    if local.nodes[index] == remote.nodes[index] {
+        return;
+    }
+    let first_leaf = local.first_leaf();
+    if index >= first_leaf {
+        let bucket = index - first_leaf;
+        diff_entries(
+            &local.bucket_entries(bucket),
+            &remote.bucket_entries(bucket),
+            out,
+        );
+        return;
+    }
+    diff_node(local, remote, 2 * index + 1, out);
+    diff_node(local, remote, 2 * index + 2, out);
+}
+
+fn diff_entries(local: &HashMap<&str, u64>, remote: &HashMap<&str, u64>, out: &mut Vec<String>) {
+    for (key, hash) in local {
+        if remote.get(key) != Some(hash) {
+            out.push(key.to_string());
+        }
+    }
+    for key in remote.keys() {
+        if !local.contains_key(key) {
+            out.push(key.to_string());
+        }
+    }
+}
+
+/// 用远端快照中的 `keys` 条目修补本地数据；返回被更新的键数
+pub fn sync(
+    local_data: &mut VersionedData,
+    mut remote_snapshot: VersionedData,
+    keys: Vec<String>,
+) -> usize {
+    let mut updated = 0;
+    for key in keys {
+        let Some(remote) = remote_snapshot.remove(&key) else {
+            continue;
+        };
+        let newer = local_data.get(&key).is_none_or(|(version, value)| {
+            remote.0 > *version || (remote.0 == *version && remote.1 != *value)
+        });
+        if newer {
+            local_data.insert(key, remote);
+            updated += 1;
+        }
+    }
+    updated
+}
+
+/// 对账结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconcileReport {
+    /// 分歧的键
+    pub divergent: Vec<String>,
+    /// 本地从远端采纳的条目数
+    pub pulled: usize,
+    /// 远端从本地采纳的条目数
+    pub pushed: usize,
+}
+
+/// 两个副本间的反熵对账：比较 Merkle 树并只交换分歧的条目
+#[derive(Debug, Clone)]
+pub struct MerkleReconciler {
+    depth: u32,
+}
+
+impl Default for MerkleReconciler {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEPTH)
+    }
+}
+
+impl MerkleReconciler {
+    pub fn new(depth: u32) -> Self {
+        Self { depth }
+    }
+
+    pub fn build(&self, data: &VersionedData) -> MerkleTree {
+        MerkleTree::build(data, self.depth)
+    }
+
+    /// 双向对账：两侧各自采纳对方更新的分歧条目
+    pub fn reconcile(
+        &self,
+        local: &mut VersionedData,
+        remote: &mut VersionedData,
+    ) -> ReconcileReport {
+        let divergent = diff(&self.build(local), &self.build(remote));
+        let subset = |data: &VersionedData| -> VersionedData {
+            divergent
+                .iter()
+                .filter_map(|k| data.get(k).map(|e| (k.clone(), e.clone())))
+                .collect()
+        };
+        let (from_remote, from_local) = (subset(remote), subset(local));
+        let pulled = sync(local, from_remote, divergent.clone());
+        // 版本相同而值不同的键已由本地采纳远端值，回推时不会再覆盖
+        let from_local: VersionedData = from_local
+            .into_iter()
+            .filter(|(k, e)| local.get(k) == Some(e))
+            .collect();
+        let pushed = sync(remote, from_local, divergent.clone());
+        ReconcileReport {
+            divergent,
+            pulled,
+            pushed,
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! 复制 KV 前的读穿透缓存
+//!
+//! 设计意图：
+//! - 热点键反复走法定人数读路径代价高。`CachedKv` 包装任意 `ReplicatedKv`，在本地维护
+//!   有界缓存：容量满时淘汰最久未用的条目，条目按 TTL 过期；未命中（键不存在）可按
+//!   单独的 TTL 负缓存，避免对不存在的键反复穿透。
+//! - 失效：本地写入经 `CachedKv` 透传后失效对应条目；其他副本的写入通过订阅复制应用
+//!   事件（`ReplicatedKv::subscribe`）失效。
+//! - `Strong` / `Linearizable` 读总是绕过缓存直达底层，既不读也不填充缓存。
+//!
+//! 不变量（草图）：
+//! - 穿透读取期间若发生任何失效，读到的值不入缓存，避免把失效前的旧值重新缓存。
+//! - 缓存条目数不超过容量。
+
+use crate::consistency::ConsistencyLevel;
+use crate::core::errors::DistributedError;
+use crate::storage::mvcc::KvSnapshot;
+use crate::storage::watch::{KeyPrefix, WatchError, WatchStream};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 本副本应用了一次写入（任意来源）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplyEvent<K> {
+    pub key: K,
+    /// 发起写入的节点
+    pub origin: String,
+}
+
+pub type ApplyListener<K> = Box<dyn Fn(&ApplyEvent<K>) + Send + Sync>;
+
+/// 按一致性级别读写的复制键值存储
+pub trait ReplicatedKv<K, V>: Send + Sync {
+    fn get(&self, key: &K, level: ConsistencyLevel) -> Result<Option<V>, DistributedError>;
+    fn put(&self, key: K, value: V, level: ConsistencyLevel) -> Result<(), DistributedError>;
+    fn delete(&self, key: &K, level: ConsistencyLevel) -> Result<(), DistributedError>;
+    /// 注册复制应用事件监听
+    fn subscribe(&self, listener: ApplyListener<K>);
+
+    /// 订阅前缀 `prefix` 下之后应用的变更
+    fn watch(&self, prefix: K) -> Result<WatchStream<K, V>, WatchError>
+    where
+        K: KeyPrefix,
+    {
+        self.watch_from(prefix, None)
+    }
+
+    /// 同 `watch`，`from` 给定时先回放版本不小于它的历史变更；不保留历史的实现不支持订阅
+    fn watch_from(&self, prefix: K, from: Option<u64>) -> Result<WatchStream<K, V>, WatchError>
+    where
+        K: KeyPrefix,
+    {
+        let _ = (prefix, from);
+        Err(WatchError::Unsupported)
+    }
+
+    /// 在已应用的最新版本上开启只读快照；不保留多版本的实现不支持快照
+    fn snapshot(&self) -> Result<KvSnapshot<K, V>, DistributedError>
+    where
+        K: Hash + Eq + Clone,
+        V: Clone,
+    {
+        Err(DistributedError::InvalidState(
+            "snapshots are not supported by this store".to_string(),
+        ))
+    }
+
+    /// 在版本 `version` 上开启只读快照
+    fn snapshot_at(&self, version: u64) -> Result<KvSnapshot<K, V>, DistributedError>
+    where
+        K: Hash + Eq + Clone,
+        V: Clone,
+    {
+        Err(DistributedError::InvalidState(format!(
+            "snapshot at {version} is not supported by this store"
+        )))
+    }
+}
+
+/// 缓存计数
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl CacheStats {
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn invalidations(&self) -> u64 {
+        self.invalidations.load(Ordering::Relaxed)
+    }
+}
+
+struct CacheEntry<V> {
+    /// `None` 为负缓存
+    value: Option<V>,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+struct Cache<K, V> {
+    capacity: usize,
+    ttl: Duration,
+    negative_ttl: Option<Duration>,
+    entries: HashMap<K, CacheEntry<V>>,
+    /// `last_used -> key`，最小者最久未用
+    lru: BTreeMap<u64, K>,
+    tick: u64,
+    /// 每次失效递增；穿透读取前后不同则放弃填充
+    epoch: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Cache<K, V> {
+    fn lookup(&mut self, key: &K, now: Instant) -> Option<Option<V>> {
+        let entry = self.entries.get_mut(key)?;
+        if entry.expires_at <= now {
+            let last_used = entry.last_used;
+            self.entries.remove(key);
+            self.lru.remove(&last_used);
+            return None;
+        }
+        self.tick += 1;
+        self.lru.remove(&entry.last_used);
+        self.lru.insert(self.tick, key.clone());
+        entry.last_used = self.tick;
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: K, value: Option<V>, now: Instant) {
+        let ttl = match (&value, self.negative_ttl) {
+            (Some(_), _) => self.ttl,
+            (None, Some(ttl)) => ttl,
+            (None, None) => return,
+        };
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(&key);
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.lru.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.tick += 1;
+        self.lru.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                expires_at: now + ttl,
+                last_used: self.tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.lru.remove(&entry.last_used);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// `ReplicatedKv` 之上的读穿透 / 写失效缓存
+pub struct CachedKv<K, V, S> {
+    inner: S,
+    cache: Arc<Mutex<Cache<K, V>>>,
+    stats: Arc<CacheStats>,
+}
+
+impl<K, V, S> CachedKv<K, V, S>
+where
+    K: Hash + Eq + Clone + Send + 'static,
+    V: Clone + Send + 'static,
+    S: ReplicatedKv<K, V>,
+{
+    /// 默认容量 1024、TTL 1s、负缓存 TTL 100ms；订阅 `inner` 的应用事件
+    pub fn new(inner: S) -> Self {
+        let cache = Arc::new(Mutex::new(Cache {
+            capacity: 1024,
+            ttl: Duration::from_secs(1),
+            negative_ttl: Some(Duration::from_millis(100)),
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            epoch: 0,
+        }));
+        let stats = Arc::new(CacheStats::default());
+        let (listener_cache, listener_stats) = (cache.clone(), stats.clone());
+        inner.subscribe(Box::new(move |event: &ApplyEvent<K>| {
+            invalidate(&listener_cache, &listener_stats, &event.key);
+        }));
+        Self {
+            inner,
+            cache,
+            stats,
+        }
+    }
+
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        self.cache.lock().unwrap().capacity = capacity;
+        self
+    }
+
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        self.cache.lock().unwrap().ttl = ttl;
+        self
+    }
+
+    /// `None` 关闭负缓存
+    pub fn with_negative_ttl(self, negative_ttl: Option<Duration>) -> Self {
+        self.cache.lock().unwrap().negative_ttl = negative_ttl;
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &K, level: ConsistencyLevel) -> Result<Option<V>, DistributedError> {
+        if matches!(
+            level,
+            ConsistencyLevel::Strong | ConsistencyLevel::Linearizable
+        ) {
+            return self.inner.get(key, level);
+        }
+        let epoch = {
+            let mut cache = self.cache.lock().unwrap();
+            if let Some(value) = cache.lookup(key, Instant::now()) {
+                self.stats.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(value);
+            }
+            cache.epoch
+        };
+        self.stats.misses.fetch_add(1, Ordering::Relaxed);
+        let value = self.inner.get(key, level)?;
+        let mut cache = self.cache.lock().unwrap();
+        if cache.epoch == epoch {
+            cache.insert(key.clone(), value.clone(), Instant::now());
+        }
+        Ok(value)
+    }
+
+    /// 写入底层并失效本地条目（写入失败时结果未知，同样失效）
+    pub fn put(&self, key: K, value: V, level: ConsistencyLevel) -> Result<(), DistributedError> {
+        let result = self.inner.put(key.clone(), value, level);
+        invalidate(&self.cache, &self.stats, &key);
+        result
+    }
+
+    pub fn delete(&self, key: &K, level: ConsistencyLevel) -> Result<(), DistributedError> {
+        let result = self.inner.delete(key, level);
+        invalidate(&self.cache, &self.stats, key);
+        result
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        invalidate(&self.cache, &self.stats, key);
+    }
+}
+
+fn invalidate<K: Hash + Eq + Clone, V: Clone>(
+    cache: &Mutex<Cache<K, V>>,
+    stats: &CacheStats,
+    key: &K,
+) {
+    let mut cache = cache.lock().unwrap();
+    cache.epoch += 1;
+    if cache.remove(key) {
+        stats.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+}
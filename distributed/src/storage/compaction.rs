@@ -0,0 +1,275 @@
+//! 日志压缩策略与压缩任务
+//!
+//! 设计意图：
+//! - `CompactionPolicy` 只根据日志的当前规模（字节数、条目数、距上次压缩的时间）决定是否压缩；
+//!   `AnyOf` 组合多个策略，任一满足即压缩。
+//! - `Compactable` 抽象可压缩的日志：通用 WAL（`SegmentedWal`）改写存活记录并删除旧段；
+//!   Raft 日志（`MinimalRaft`）先在已应用的位置创建快照，再 `truncate_before` 丢弃其前缀。
+//! - `Compactor` 由 `TimerService` 周期驱动，每次检查策略并在需要时压缩。压缩期间持有日志的
+//!   互斥锁，追加与压缩串行化：追加要么在压缩前完成、要么在压缩后进入活跃段，不会写入被改写的段。
+//!
+//! 不变量（草图）：
+//! - 压缩只回收过期数据：压缩前后可读的存活记录相同。
+//! - 指标单调累加；`last_run` 只在实际压缩后更新。
+
+use super::wal::{Reclaimed, SegmentedWal};
+use crate::codec::BinaryCodec;
+use crate::consensus::raft::MinimalRaft;
+use crate::core::TimerService;
+use crate::core::errors::DistributedError;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 策略判断的输入
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LogStats {
+    pub bytes: u64,
+    pub entries: u64,
+    pub since_last_compaction: Duration,
+}
+
+pub trait CompactionPolicy: Send + Sync {
+    fn should_compact(&self, stats: &LogStats) -> bool;
+}
+
+/// 日志字节数超过阈值
+#[derive(Debug, Clone, Copy)]
+pub struct SizeThreshold(pub u64);
+
+impl CompactionPolicy for SizeThreshold {
+    fn should_compact(&self, stats: &LogStats) -> bool {
+        stats.bytes > self.0
+    }
+}
+
+/// 日志条目数超过阈值
+#[derive(Debug, Clone, Copy)]
+pub struct EntryCount(pub u64);
+
+impl CompactionPolicy for EntryCount {
+    fn should_compact(&self, stats: &LogStats) -> bool {
+        stats.entries > self.0
+    }
+}
+
+/// 距上次压缩超过给定时间（且日志非空）
+#[derive(Debug, Clone, Copy)]
+pub struct TimeBased(pub Duration);
+
+impl CompactionPolicy for TimeBased {
+    fn should_compact(&self, stats: &LogStats) -> bool {
+        stats.entries > 0 && stats.since_last_compaction >= self.0
+    }
+}
+
+/// 任一子策略满足即压缩
+#[derive(Default)]
+pub struct AnyOf(Vec<Box<dyn CompactionPolicy>>);
+
+impl AnyOf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, policy: impl CompactionPolicy + 'static) -> Self {
+        self.0.push(Box::new(policy));
+        self
+    }
+}
+
+impl CompactionPolicy for AnyOf {
+    fn should_compact(&self, stats: &LogStats) -> bool {
+        self.0.iter().any(|p| p.should_compact(stats))
+    }
+}
+
+/// 可被 `Compactor` 压缩的日志
+pub trait Compactable {
+    fn log_bytes(&self) -> u64;
+    fn log_entries(&self) -> u64;
+    fn compact(&mut self) -> Result<Reclaimed, DistributedError>;
+}
+
+impl<E, C: BinaryCodec<E>> Compactable for SegmentedWal<E, C> {
+    fn log_bytes(&self) -> u64 {
+        self.bytes()
+    }
+
+    fn log_entries(&self) -> u64 {
+        self.len() as u64
+    }
+
+    fn compact(&mut self) -> Result<Reclaimed, DistributedError> {
+        SegmentedWal::compact(self)
+    }
+}
+
+/// 内存日志的字节数按条目的内存大小估算
+impl<E> Compactable for MinimalRaft<E> {
+    fn log_bytes(&self) -> u64 {
+        self.log_entries() * std::mem::size_of::<(crate::consensus::raft::Term, E)>() as u64
+    }
+
+    fn log_entries(&self) -> u64 {
+        self.retained_entries() as u64
+    }
+
+    fn compact(&mut self) -> Result<Reclaimed, DistributedError> {
+        let entries = self.compact_log()? as u64;
+        Ok(Reclaimed {
+            bytes: entries * std::mem::size_of::<(crate::consensus::raft::Term, E)>() as u64,
+            entries,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CompactionMetrics {
+    runs: AtomicU64,
+    failures: AtomicU64,
+    bytes_reclaimed: AtomicU64,
+    entries_reclaimed: AtomicU64,
+    last_duration_us: AtomicU64,
+    /// 时钟读数（毫秒）加一；0 表示尚未压缩过
+    last_run_ms: AtomicU64,
+}
+
+impl CompactionMetrics {
+    /// 实际执行的压缩次数
+    pub fn runs(&self) -> u64 {
+        self.runs.load(Ordering::Relaxed)
+    }
+
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.bytes_reclaimed.load(Ordering::Relaxed)
+    }
+
+    pub fn entries_reclaimed(&self) -> u64 {
+        self.entries_reclaimed.load(Ordering::Relaxed)
+    }
+
+    /// 最近一次压缩的耗时（本地单调时钟）
+    pub fn last_duration(&self) -> Duration {
+        Duration::from_micros(self.last_duration_us.load(Ordering::Relaxed))
+    }
+
+    /// 最近一次压缩的时钟读数
+    pub fn last_run(&self) -> Option<Duration> {
+        match self.last_run_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms - 1)),
+        }
+    }
+}
+
+pub struct Compactor<T> {
+    log: Arc<Mutex<T>>,
+    policy: Box<dyn CompactionPolicy>,
+    interval: Duration,
+    clock: Box<dyn Fn() -> Duration + Send + Sync>,
+    last_compaction: Mutex<Duration>,
+    metrics: CompactionMetrics,
+    /// 每次 `start`/`stop` 递增；定时回调发现代数变化即退出
+    epoch: AtomicU64,
+}
+
+impl<T: Compactable> Compactor<T> {
+    /// 默认每秒检查一次，以本地单调时钟计时
+    pub fn new(log: Arc<Mutex<T>>, policy: impl CompactionPolicy + 'static) -> Self {
+        let origin = Instant::now();
+        Self {
+            log,
+            policy: Box::new(policy),
+            interval: Duration::from_secs(1),
+            clock: Box::new(move || origin.elapsed()),
+            last_compaction: Mutex::new(Duration::ZERO),
+            metrics: CompactionMetrics::default(),
+            epoch: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 替换时钟（自任意起点经过的时间），须与驱动的 `TimerService` 在同一时间轴上
+    pub fn with_clock(mut self, clock: impl Fn() -> Duration + Send + Sync + 'static) -> Self {
+        *self.last_compaction.get_mut().unwrap() = clock();
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn metrics(&self) -> &CompactionMetrics {
+        &self.metrics
+    }
+
+    pub fn log(&self) -> &Arc<Mutex<T>> {
+        &self.log
+    }
+
+    /// 检查一次策略，需要时压缩；未压缩时返回 `None`
+    pub fn run_once(&self) -> Result<Option<Reclaimed>, DistributedError> {
+        let now = (self.clock)();
+        let mut log = self.log.lock().unwrap();
+        let stats = LogStats {
+            bytes: log.log_bytes(),
+            entries: log.log_entries(),
+            since_last_compaction: now.saturating_sub(*self.last_compaction.lock().unwrap()),
+        };
+        if !self.policy.should_compact(&stats) {
+            return Ok(None);
+        }
+        let started = Instant::now();
+        let reclaimed = log.compact().inspect_err(|_| {
+            self.metrics.failures.fetch_add(1, Ordering::Relaxed);
+        })?;
+        drop(log);
+        *self.last_compaction.lock().unwrap() = now;
+        let m = &self.metrics;
+        m.runs.fetch_add(1, Ordering::Relaxed);
+        m.bytes_reclaimed
+            .fetch_add(reclaimed.bytes, Ordering::Relaxed);
+        m.entries_reclaimed
+            .fetch_add(reclaimed.entries, Ordering::Relaxed);
+        m.last_duration_us
+            .store(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+        m.last_run_ms
+            .store(now.as_millis() as u64 + 1, Ordering::Relaxed);
+        Ok(Some(reclaimed))
+    }
+
+    /// 每隔 `interval` 经 `timer` 运行一次 `run_once`，直到 `stop`；失败计入指标后继续
+    pub fn start<S>(self: &Arc<Self>, timer: S)
+    where
+        T: Send + 'static,
+        S: TimerService + Clone + Send + 'static,
+    {
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst) + 1;
+        Self::schedule(self.clone(), timer, epoch);
+    }
+
+    pub fn stop(&self) {
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn schedule<S>(this: Arc<Self>, timer: S, epoch: u64)
+    where
+        T: Send + 'static,
+        S: TimerService + Clone + Send + 'static,
+    {
+        let ms = this.interval.as_millis() as u64;
+        timer.clone().after_ms(ms, move || {
+            if this.epoch.load(Ordering::SeqCst) != epoch {
+                return;
+            }
+            let _ = this.run_once();
+            Self::schedule(this, timer, epoch);
+        });
+    }
+}
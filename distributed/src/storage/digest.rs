@@ -0,0 +1,272 @@
+//! 分片摘要交换：廉价判断副本是否分歧
+//!
+//! 设计意图：
+//! - 副本大多相同时，逐分片构建并比较 Merkle 树仍然昂贵。`KeyDigest` 把一个分片的
+//!   `(key, version, value)` 概括为：键数、与顺序无关的滚动校验和（各条目哈希的回绕和，
+//!   可随单条写入增量维护），以及按 `(key, version)` 构建的 Bloom 过滤器。
+//! - 对端先交换摘要：`compare` 在键数与校验和都相同时判定 `Identical`，直接跳过该分片；
+//!   只有校验和不同的分片才回退到 Merkle 范围比较（`anti_entropy`）。
+//! - Bloom 过滤器按配置的每分片预期键数与误判率定长，两侧尺寸一致；`missing_from`
+//!   找出对端确定没有的 `(key, version)`，可在 Merkle 比较前直接推送。
+//!
+//! 不变量（草图）：
+//! - 相同数据在相同配置下得到相同摘要（哈希使用固定密钥的 `AHasher`）。
+//! - Bloom 过滤器无漏报：对端确有的 `(key, version)` 不会出现在 `missing_from` 中。
+
+use crate::core::ShardId;
+use crate::storage::anti_entropy::{MerkleReconciler, ReconcileReport, VersionedData};
+use std::hash::{Hash, Hasher};
+
+fn hash_of(parts: impl Hash) -> u64 {
+    let mut h = ahash::AHasher::default();
+    parts.hash(&mut h);
+    h.finish()
+}
+
+/// 摘要参数；交换摘要的两侧须使用相同配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestConfig {
+    pub shard_count: u64,
+    /// 每分片预期键数，决定 Bloom 过滤器尺寸
+    pub expected_keys: usize,
+    /// 达到预期键数时的目标误判率
+    pub false_positive_rate: f64,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            shard_count: 16,
+            expected_keys: 1024,
+            false_positive_rate: 0.01,
+        }
+    }
+}
+
+impl DigestConfig {
+    pub fn with_shard_count(mut self, shard_count: u64) -> Self {
+        self.shard_count = shard_count.max(1);
+        self
+    }
+
+    pub fn with_expected_keys(mut self, expected_keys: usize) -> Self {
+        self.expected_keys = expected_keys.max(1);
+        self
+    }
+
+    pub fn with_false_positive_rate(mut self, rate: f64) -> Self {
+        self.false_positive_rate = rate.clamp(1e-9, 0.5);
+        self
+    }
+
+    /// 键所属分片（与 Merkle 分桶使用不同的哈希输入，避免分片内只落入部分桶）
+    pub fn shard_of(&self, key: &str) -> ShardId {
+        ShardId(hash_of(("shard", key)) % self.shard_count)
+    }
+
+    pub fn shards(&self) -> impl Iterator<Item = ShardId> {
+        (0..self.shard_count).map(ShardId)
+    }
+
+    /// 分片内的条目
+    pub fn shard_data(&self, store: &VersionedData, shard: ShardId) -> VersionedData {
+        store
+            .iter()
+            .filter(|(k, _)| self.shard_of(k) == shard)
+            .map(|(k, e)| (k.clone(), e.clone()))
+            .collect()
+    }
+
+    pub fn build(&self, store: &VersionedData, shard: ShardId) -> KeyDigest {
+        let mut bloom = BloomFilter::new(self.expected_keys, self.false_positive_rate);
+        let mut checksum = 0u64;
+        let mut key_count = 0;
+        for (key, (version, value)) in store {
+            if self.shard_of(key) != shard {
+                continue;
+            }
+            bloom.insert(&(key, version));
+            checksum = checksum.wrapping_add(hash_of((key, version, value)));
+            key_count += 1;
+        }
+        KeyDigest {
+            shard,
+            key_count,
+            checksum,
+            bloom,
+        }
+    }
+}
+
+/// 定长 Bloom 过滤器（双重哈希生成 k 个位置）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// 按预期元素数 `n` 与误判率 `p` 取最优尺寸：`m = -n ln p / (ln 2)^2`，`k = m/n · ln 2`
+    pub fn new(expected: usize, false_positive_rate: f64) -> Self {
+        let n = expected.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = ((-n * false_positive_rate.ln()) / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().max(1.0) as u32;
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    fn positions(num_bits: u64, num_hashes: u32, item: &impl Hash) -> impl Iterator<Item = u64> {
+        let h1 = hash_of(item);
+        let h2 = hash_of((item, 0x9e37_79b9_7f4a_7c15u64)) | 1;
+        (0..num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    pub fn insert(&mut self, item: &impl Hash) {
+        for bit in Self::positions(self.num_bits, self.num_hashes, item) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    pub fn might_contain(&self, item: &impl Hash) -> bool {
+        Self::positions(self.num_bits, self.num_hashes, item)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+}
+
+/// 一个分片的摘要
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyDigest {
+    pub shard: ShardId,
+    pub key_count: usize,
+    /// 条目哈希的回绕和，与顺序无关
+    pub checksum: u64,
+    pub bloom: BloomFilter,
+}
+
+impl KeyDigest {
+    /// 对端可能持有该版本
+    pub fn might_contain(&self, key: &str, version: u64) -> bool {
+        self.bloom.might_contain(&(key, &version))
+    }
+
+    /// 本地分片中对端确定没有的键（按键排序）
+    pub fn missing_from(&self, local_shard: &VersionedData) -> Vec<String> {
+        let mut missing: Vec<String> = local_shard
+            .iter()
+            .filter(|(k, (version, _))| !self.might_contain(k, *version))
+            .map(|(k, _)| k.clone())
+            .collect();
+        missing.sort_unstable();
+        missing
+    }
+}
+
+/// 摘要比较结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestVerdict {
+    /// 键数与校验和一致：视为相同，无需范围比较
+    Identical,
+    /// 校验和不同：需对该分片做 Merkle 范围比较
+    Divergent,
+}
+
+/// 使用默认配置构建分片摘要
+pub fn build_digest(store: &VersionedData, shard: ShardId) -> KeyDigest {
+    DigestConfig::default().build(store, shard)
+}
+
+pub fn compare(a: &KeyDigest, b: &KeyDigest) -> DigestVerdict {
+    if a.key_count == b.key_count && a.checksum == b.checksum {
+        DigestVerdict::Identical
+    } else {
+        DigestVerdict::Divergent
+    }
+}
+
+/// 摘要驱动的对账结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DigestReport {
+    /// 判定分歧、做了 Merkle 范围比较的分片
+    pub divergent_shards: Vec<ShardId>,
+    pub reconcile: ReconcileReport,
+}
+
+impl DigestReport {
+    /// Merkle 范围比较次数
+    pub fn range_comparisons(&self) -> usize {
+        self.divergent_shards.len()
+    }
+}
+
+/// 先交换分片摘要，只对校验和不同的分片做 Merkle 对账
+#[derive(Debug, Clone, Default)]
+pub struct DigestReconciler {
+    config: DigestConfig,
+    merkle: MerkleReconciler,
+}
+
+impl DigestReconciler {
+    pub fn new(config: DigestConfig) -> Self {
+        Self {
+            config,
+            merkle: MerkleReconciler::default(),
+        }
+    }
+
+    pub fn with_merkle(mut self, merkle: MerkleReconciler) -> Self {
+        self.merkle = merkle;
+        self
+    }
+
+    pub fn config(&self) -> &DigestConfig {
+        &self.config
+    }
+
+    pub fn reconcile(&self, local: &mut VersionedData, remote: &mut VersionedData) -> DigestReport {
+        let mut report = DigestReport::default();
+        for shard in self.config.shards() {
+            let verdict = compare(
+                &self.config.build(local, shard),
+                &self.config.build(remote, shard),
+            );
+            if verdict == DigestVerdict::Identical {
+                continue;
+            }
+            report.divergent_shards.push(shard);
+            let mut local_shard = self.config.shard_data(local, shard);
+            let mut remote_shard = self.config.shard_data(remote, shard);
+            let shard_report = self.merkle.reconcile(&mut local_shard, &mut remote_shard);
+            for key in &shard_report.divergent {
+                apply_entry(local, &local_shard, key);
+                apply_entry(remote, &remote_shard, key);
+            }
+            report.reconcile.divergent.extend(shard_report.divergent);
+            report.reconcile.pulled += shard_report.pulled;
+            report.reconcile.pushed += shard_report.pushed;
+        }
+        report.reconcile.divergent.sort_unstable();
+        report
+    }
+}
+
+/// 把分片副本中对账后的条目写回完整数据
+fn apply_entry(store: &mut VersionedData, shard: &VersionedData, key: &str) {
+    if let Some(entry) = shard.get(key) {
+        store.insert(key.to_string(), entry.clone());
+    }
+}
@@ -0,0 +1,401 @@
+//! 静态加密（encryption at rest）
+//!
+//! 设计意图：
+//! - 合规要求 WAL 与快照在磁盘上加密。`EncryptedStorage` 包装按字节存取的日志/快照存储：
+//!   条目先经内层编解码器编码，再以 AES-256-GCM 逐条加密后交给被包装的存储，读回时解密、
+//!   校验并解码。
+//! - 每条记录自带头部 `magic | key_id | nonce`：nonce 每条随机生成，头部作为附加认证数据
+//!   参与认证，篡改密文或头部（包括把记录改指到别的密钥）都会在读取时以
+//!   `DistributedError::Storage` 报告。
+//! - 密钥来自 `KeyProvider`（静态密钥、环境变量或回调）。轮换后新记录使用新密钥，旧记录
+//!   凭头部的 `key_id` 继续可读；`rewrap(old, new)` 是维护操作，只重写仍使用旧密钥的记录，
+//!   完成后即可下线旧密钥。
+//!
+//! 不变量（草图）：
+//! - 同一密钥下 nonce 不重复（96 位随机数，碰撞概率可忽略）。
+//! - 认证失败的记录绝不解码返回；`rewrap` 以临时文件加改名替换日志，中途崩溃时旧日志完整保留。
+
+use super::{FileLogStorage, LogStorage, SnapshotStorage};
+use crate::codec::{BinaryCodec, BytesCodec};
+use crate::core::errors::DistributedError;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+const MAGIC: [u8; 4] = *b"EAR1";
+const HEADER_LEN: usize = MAGIC.len() + 4 + NONCE_LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyId(pub u32);
+
+/// AES-256 密钥；`Debug` 不输出密钥内容
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// 64 个十六进制字符
+    pub fn from_hex(hex: &str) -> Result<Self, DistributedError> {
+        let hex = hex.trim();
+        let invalid = || DistributedError::Configuration("key must be 64 hex characters".into());
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+pub trait KeyProvider: Send + Sync {
+    /// 新记录使用的密钥
+    fn current(&self) -> Result<(KeyId, EncryptionKey), DistributedError>;
+    /// 按记录头部的 `key_id` 取密钥
+    fn key(&self, id: KeyId) -> Result<EncryptionKey, DistributedError>;
+}
+
+fn unknown_key(id: KeyId) -> DistributedError {
+    DistributedError::Configuration(format!("unknown encryption key {}", id.0))
+}
+
+/// 内存中的密钥表；轮换后旧密钥保留用于读取，直到显式 `retire`
+pub struct StaticKeyProvider {
+    current: RwLock<KeyId>,
+    keys: RwLock<HashMap<KeyId, EncryptionKey>>,
+}
+
+impl StaticKeyProvider {
+    pub fn new(id: KeyId, key: EncryptionKey) -> Self {
+        Self {
+            current: RwLock::new(id),
+            keys: RwLock::new(HashMap::from([(id, key)])),
+        }
+    }
+
+    /// 登记只用于读取的旧密钥
+    pub fn with_key(self, id: KeyId, key: EncryptionKey) -> Self {
+        self.keys.write().unwrap().insert(id, key);
+        self
+    }
+
+    /// 切换新记录使用的密钥
+    pub fn rotate(&self, id: KeyId, key: EncryptionKey) {
+        self.keys.write().unwrap().insert(id, key);
+        *self.current.write().unwrap() = id;
+    }
+
+    /// 下线旧密钥；当前密钥不能下线
+    pub fn retire(&self, id: KeyId) -> bool {
+        *self.current.read().unwrap() != id && self.keys.write().unwrap().remove(&id).is_some()
+    }
+}
+
+impl KeyProvider for StaticKeyProvider {
+    fn current(&self) -> Result<(KeyId, EncryptionKey), DistributedError> {
+        let id = *self.current.read().unwrap();
+        Ok((id, self.key(id)?))
+    }
+
+    fn key(&self, id: KeyId) -> Result<EncryptionKey, DistributedError> {
+        self.keys
+            .read()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| unknown_key(id))
+    }
+}
+
+/// 每次使用时从环境变量读取十六进制密钥
+pub struct EnvKeyProvider {
+    id: KeyId,
+    var: String,
+}
+
+impl EnvKeyProvider {
+    pub fn new(id: KeyId, var: impl Into<String>) -> Self {
+        Self {
+            id,
+            var: var.into(),
+        }
+    }
+}
+
+impl KeyProvider for EnvKeyProvider {
+    fn current(&self) -> Result<(KeyId, EncryptionKey), DistributedError> {
+        Ok((self.id, self.key(self.id)?))
+    }
+
+    fn key(&self, id: KeyId) -> Result<EncryptionKey, DistributedError> {
+        if id != self.id {
+            return Err(unknown_key(id));
+        }
+        let hex = std::env::var(&self.var).map_err(|e| {
+            DistributedError::Configuration(format!("encryption key {}: {e}", self.var))
+        })?;
+        EncryptionKey::from_hex(&hex)
+    }
+}
+
+type CurrentKeyFn = Box<dyn Fn() -> Result<(KeyId, EncryptionKey), DistributedError> + Send + Sync>;
+type LookupKeyFn = Box<dyn Fn(KeyId) -> Result<EncryptionKey, DistributedError> + Send + Sync>;
+
+/// 由回调提供密钥，如接入外部 KMS
+pub struct CallbackKeyProvider {
+    current: CurrentKeyFn,
+    lookup: LookupKeyFn,
+}
+
+impl CallbackKeyProvider {
+    pub fn new(
+        current: impl Fn() -> Result<(KeyId, EncryptionKey), DistributedError> + Send + Sync + 'static,
+        lookup: impl Fn(KeyId) -> Result<EncryptionKey, DistributedError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            current: Box::new(current),
+            lookup: Box::new(lookup),
+        }
+    }
+}
+
+impl KeyProvider for CallbackKeyProvider {
+    fn current(&self) -> Result<(KeyId, EncryptionKey), DistributedError> {
+        (self.current)()
+    }
+
+    fn key(&self, id: KeyId) -> Result<EncryptionKey, DistributedError> {
+        (self.lookup)(id)
+    }
+}
+
+/// 逐条记录的 AES-256-GCM 加解密
+#[derive(Clone)]
+pub struct RecordCipher {
+    keys: Arc<dyn KeyProvider>,
+    rng: SystemRandom,
+}
+
+impl RecordCipher {
+    pub fn new(keys: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            keys,
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// 以当前密钥加密
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, DistributedError> {
+        let (id, key) = self.keys.current()?;
+        self.seal_with(id, &key, plaintext)
+    }
+
+    pub fn open(&self, record: &[u8]) -> Result<Vec<u8>, DistributedError> {
+        let id = Self::key_id(record)?;
+        let key = self.keys.key(id)?;
+        let (header, ciphertext) = record.split_at(HEADER_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&header[MAGIC.len() + 4..])
+            .map_err(|_| DistributedError::Storage("invalid record nonce".into()))?;
+        let mut buf = ciphertext.to_vec();
+        let plaintext = aead_key(&key)?
+            .open_in_place(nonce, Aad::from(header), &mut buf)
+            .map_err(|_| {
+                DistributedError::Storage(format!("record failed authentication (key {})", id.0))
+            })?;
+        Ok(plaintext.to_vec())
+    }
+
+    /// 记录头部中的密钥 ID
+    pub fn key_id(record: &[u8]) -> Result<KeyId, DistributedError> {
+        if record.len() < HEADER_LEN || record[..MAGIC.len()] != MAGIC {
+            return Err(DistributedError::Storage("not an encrypted record".into()));
+        }
+        let id: [u8; 4] = record[MAGIC.len()..MAGIC.len() + 4]
+            .try_into()
+            .expect("4-byte key id");
+        Ok(KeyId(u32::from_le_bytes(id)))
+    }
+
+    /// 若记录使用 `old`，用 `new` 重新加密；否则返回 `None`
+    pub fn rewrap(
+        &self,
+        record: &[u8],
+        old: KeyId,
+        new: KeyId,
+    ) -> Result<Option<Vec<u8>>, DistributedError> {
+        if Self::key_id(record)? != old {
+            return Ok(None);
+        }
+        let plaintext = self.open(record)?;
+        let key = self.keys.key(new)?;
+        self.seal_with(new, &key, &plaintext).map(Some)
+    }
+
+    fn seal_with(
+        &self,
+        id: KeyId,
+        key: &EncryptionKey,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, DistributedError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| DistributedError::Storage("nonce generation failed".into()))?;
+        let mut record = Vec::with_capacity(HEADER_LEN + plaintext.len() + AES_256_GCM.tag_len());
+        record.extend_from_slice(&MAGIC);
+        record.extend_from_slice(&id.0.to_le_bytes());
+        record.extend_from_slice(&nonce);
+        let mut buf = plaintext.to_vec();
+        aead_key(key)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(&record[..HEADER_LEN]),
+                &mut buf,
+            )
+            .map_err(|_| DistributedError::Storage("encryption failed".into()))?;
+        record.extend_from_slice(&buf);
+        Ok(record)
+    }
+}
+
+fn aead_key(key: &EncryptionKey) -> Result<LessSafeKey, DistributedError> {
+    UnboundKey::new(&AES_256_GCM, &key.0)
+        .map(LessSafeKey::new)
+        .map_err(|_| DistributedError::Configuration("invalid AES-256 key".into()))
+}
+
+/// 加密包装：`S` 按字节存取（如 `FileLogStorage<BytesCodec, Vec<u8>>`、
+/// `FileSnapshot<BytesCodec, Vec<u8>>`），`C` 编解码明文条目
+pub struct EncryptedStorage<S, C> {
+    inner: S,
+    codec: C,
+    cipher: RecordCipher,
+}
+
+impl<S, C> EncryptedStorage<S, C> {
+    pub fn new(inner: S, codec: C, keys: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            inner,
+            codec,
+            cipher: RecordCipher::new(keys),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn cipher(&self) -> &RecordCipher {
+        &self.cipher
+    }
+
+    fn decode<T>(&self, record: &[u8]) -> Result<T, DistributedError>
+    where
+        C: BinaryCodec<T>,
+    {
+        let plaintext = self.cipher.open(record)?;
+        self.codec
+            .decode(&plaintext)
+            .ok_or_else(|| DistributedError::Storage("corrupt decrypted record".into()))
+    }
+}
+
+impl<S, C, E> LogStorage<E> for EncryptedStorage<S, C>
+where
+    S: LogStorage<Vec<u8>>,
+    C: BinaryCodec<E>,
+{
+    fn append(&mut self, entry: E) -> Result<u64, DistributedError> {
+        let record = self.cipher.seal(&self.codec.encode(&entry))?;
+        self.inner.append(record)
+    }
+}
+
+impl<S, C, T> SnapshotStorage<T> for EncryptedStorage<S, C>
+where
+    S: SnapshotStorage<Vec<u8>>,
+    C: BinaryCodec<T>,
+{
+    fn save_snapshot(&mut self, state: &T) -> Result<(), DistributedError> {
+        let record = self.cipher.seal(&self.codec.encode(state))?;
+        self.inner.save_snapshot(&record)
+    }
+
+    fn load_snapshot(&self) -> Result<Option<T>, DistributedError>
+    where
+        T: Clone,
+    {
+        match self.inner.load_snapshot()? {
+            Some(record) => self.decode(&record).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<S: SnapshotStorage<Vec<u8>>, C> EncryptedStorage<S, C> {
+    /// 快照仍使用 `old` 时以 `new` 重新加密；返回是否重写
+    pub fn rewrap_snapshot(&mut self, old: KeyId, new: KeyId) -> Result<bool, DistributedError> {
+        let Some(record) = self.inner.load_snapshot()? else {
+            return Ok(false);
+        };
+        match self.cipher.rewrap(&record, old, new)? {
+            Some(record) => self.inner.save_snapshot(&record).map(|()| true),
+            None => Ok(false),
+        }
+    }
+}
+
+impl<C> EncryptedStorage<FileLogStorage<BytesCodec, Vec<u8>>, C> {
+    /// 按写入顺序解密读回全部条目
+    pub fn read_all<E>(&self) -> Result<Vec<E>, DistributedError>
+    where
+        C: BinaryCodec<E>,
+    {
+        self.inner
+            .read_all()?
+            .iter()
+            .map(|record| self.decode(record))
+            .collect()
+    }
+
+    /// 把仍使用 `old` 的记录改用 `new` 加密，其余记录原样保留；返回重写的记录数。
+    /// 没有旧密钥记录时不触碰文件
+    pub fn rewrap(&mut self, old: KeyId, new: KeyId) -> Result<usize, DistributedError> {
+        let records = self.inner.read_all()?;
+        let mut rewrapped = 0;
+        let mut out = Vec::with_capacity(records.len());
+        for record in records {
+            match self.cipher.rewrap(&record, old, new)? {
+                Some(record) => {
+                    rewrapped += 1;
+                    out.push(record);
+                }
+                None => out.push(record),
+            }
+        }
+        if rewrapped == 0 {
+            return Ok(0);
+        }
+        let path = self.inner.path().to_path_buf();
+        let tmp = path.with_extension("rewrap");
+        let mut staged = FileLogStorage::new(tmp.clone(), BytesCodec);
+        staged.truncate()?;
+        for record in out {
+            staged.append(record)?;
+        }
+        std::fs::rename(&tmp, &path).map_err(|e| DistributedError::Storage(e.to_string()))?;
+        Ok(rewrapped)
+    }
+}
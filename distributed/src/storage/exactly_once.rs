@@ -0,0 +1,274 @@
+//! 记忆结果的恰好一次执行
+//!
+//! 设计意图：
+//! - 重试、对冲与至少一次投递都可能让同一条非幂等命令到达两次。`IdempotencyStore` 只能回答
+//!   “是否见过”，重复提交只能得到一个空的成功。`ExactlyOnceExecutor` 在去重之外记住首次
+//!   执行成功的结果（经 `BinaryCodec` 编码），重复提交原样返回该结果而不再执行。
+//! - 同一键的并发提交：首个提交者执行，其余等待其完成后读取结果。执行失败或执行者被取消
+//!   （future 被丢弃）时撤销占位并唤醒等待者，由其中之一重新执行；失败不被记忆。等待基于
+//!   `Waker`，不依赖特定运行时。
+//! - 结果按 TTL 过期、超出容量时淘汰最早完成的条目。键仍留在 `IdempotencyStore` 中，
+//!   此后的重复提交返回错误而不是再执行一次。
+//!
+//! 不变量（草图）：
+//! - 每个键的命令至多成功执行一次；结果仍在缓存中时，所有提交者得到字节相同的结果。
+//! - 缓存的结果数不超过容量。
+
+use crate::codec::BinaryCodec;
+use crate::core::errors::DistributedError;
+use crate::storage::{IdempotencyStore, InMemoryIdempotency};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// 执行计数
+#[derive(Debug, Default)]
+pub struct ExactlyOnceStats {
+    executed: AtomicU64,
+    replayed: AtomicU64,
+    forgotten: AtomicU64,
+}
+
+impl ExactlyOnceStats {
+    /// 实际执行并记忆了结果的提交
+    pub fn executed(&self) -> u64 {
+        self.executed.load(Ordering::Relaxed)
+    }
+
+    /// 返回已记忆结果的重复提交
+    pub fn replayed(&self) -> u64 {
+        self.replayed.load(Ordering::Relaxed)
+    }
+
+    /// 结果已过期或被淘汰、因而被拒绝的重复提交
+    pub fn forgotten(&self) -> u64 {
+        self.forgotten.load(Ordering::Relaxed)
+    }
+}
+
+enum Slot {
+    /// 首个提交者正在执行
+    Pending {
+        waiters: Vec<Waker>,
+    },
+    Done(Vec<u8>),
+}
+
+struct Memo {
+    slots: HashMap<String, Slot>,
+    /// 完成序号 → (键, 过期时刻)；TTL 固定，最小序号者最早过期
+    completed: BTreeMap<u64, (String, Duration)>,
+    seq: u64,
+    store: Box<dyn IdempotencyStore<String> + Send>,
+}
+
+impl Memo {
+    fn evict_oldest(&mut self) {
+        if let Some((_, (key, _))) = self.completed.pop_first() {
+            self.slots.remove(&key);
+        }
+    }
+
+    fn purge(&mut self, now: Duration) {
+        while self
+            .completed
+            .first_key_value()
+            .is_some_and(|(_, (_, expires_at))| *expires_at <= now)
+        {
+            self.evict_oldest();
+        }
+    }
+
+    /// 撤销仍在执行中的占位并唤醒等待者
+    fn abandon(&mut self, key: &str) {
+        if let Some(Slot::Pending { waiters }) = self.slots.get_mut(key) {
+            let waiters = std::mem::take(waiters);
+            self.slots.remove(key);
+            waiters.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+enum Claim {
+    Execute,
+    Replay(Vec<u8>),
+    Wait,
+    Forgotten,
+}
+
+/// 执行中途被丢弃时撤销占位
+struct PendingGuard<'a> {
+    memo: &'a Mutex<Memo>,
+    key: &'a str,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.memo.lock().unwrap().abandon(self.key);
+    }
+}
+
+pub struct ExactlyOnceExecutor<C> {
+    codec: C,
+    ttl: Duration,
+    capacity: usize,
+    clock: Box<dyn Fn() -> Duration + Send + Sync>,
+    memo: Mutex<Memo>,
+    stats: ExactlyOnceStats,
+}
+
+impl<C> ExactlyOnceExecutor<C> {
+    /// 默认结果保留 10 分钟、最多 10000 条，键记录在内存去重集合中
+    pub fn new(codec: C) -> Self {
+        let origin = Instant::now();
+        Self {
+            codec,
+            ttl: Duration::from_secs(600),
+            capacity: 10_000,
+            clock: Box::new(move || origin.elapsed()),
+            memo: Mutex::new(Memo {
+                slots: HashMap::new(),
+                completed: BTreeMap::new(),
+                seq: 0,
+                store: Box::new(InMemoryIdempotency::default()),
+            }),
+            stats: ExactlyOnceStats::default(),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// 替换记录已执行键的去重存储（如持久化实现）
+    pub fn with_idempotency(self, store: Box<dyn IdempotencyStore<String> + Send>) -> Self {
+        self.memo.lock().unwrap().store = store;
+        self
+    }
+
+    /// 替换时钟（自任意起点经过的时间），如 `SimClock::now`
+    pub fn with_clock(mut self, clock: impl Fn() -> Duration + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn stats(&self) -> &ExactlyOnceStats {
+        &self.stats
+    }
+
+    /// 当前缓存的结果数（不含执行中的键）
+    pub fn len(&self) -> usize {
+        let mut memo = self.memo.lock().unwrap();
+        memo.purge((self.clock)());
+        memo.completed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 以 `key` 执行 `command` 至多一次
+    ///
+    /// 首次提交执行 `command`，成功时记忆结果；重复提交返回记忆的结果。结果已过期或被淘汰的
+    /// 重复提交返回 `InvalidState`，不会再次执行。
+    pub async fn execute<R, F, Fut>(&self, key: &str, command: F) -> Result<R, DistributedError>
+    where
+        C: BinaryCodec<R>,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<R, DistributedError>>,
+    {
+        loop {
+            match self.claim(key) {
+                Claim::Execute => {
+                    let guard = PendingGuard {
+                        memo: &self.memo,
+                        key,
+                    };
+                    let result = command().await?;
+                    self.complete(key, self.codec.encode(&result));
+                    drop(guard);
+                    return Ok(result);
+                }
+                Claim::Replay(bytes) => {
+                    self.stats.replayed.fetch_add(1, Ordering::Relaxed);
+                    return self.codec.decode(&bytes).ok_or_else(|| {
+                        DistributedError::Storage(format!("undecodable memoized result for {key}"))
+                    });
+                }
+                Claim::Wait => self.completion(key).await,
+                Claim::Forgotten => {
+                    self.stats.forgotten.fetch_add(1, Ordering::Relaxed);
+                    return Err(DistributedError::InvalidState(format!(
+                        "{key} already executed and its result is no longer retained"
+                    )));
+                }
+            }
+        }
+    }
+
+    fn claim(&self, key: &str) -> Claim {
+        let mut memo = self.memo.lock().unwrap();
+        memo.purge((self.clock)());
+        match memo.slots.get(key) {
+            Some(Slot::Done(bytes)) => Claim::Replay(bytes.clone()),
+            Some(Slot::Pending { .. }) => Claim::Wait,
+            None if memo.store.seen(&key.to_string()) => Claim::Forgotten,
+            None => {
+                memo.slots.insert(
+                    key.to_string(),
+                    Slot::Pending {
+                        waiters: Vec::new(),
+                    },
+                );
+                Claim::Execute
+            }
+        }
+    }
+
+    fn complete(&self, key: &str, bytes: Vec<u8>) {
+        let expires_at = (self.clock)() + self.ttl;
+        let mut memo = self.memo.lock().unwrap();
+        memo.store.record(key.to_string());
+        self.stats.executed.fetch_add(1, Ordering::Relaxed);
+        while !memo.completed.is_empty() && memo.completed.len() >= self.capacity {
+            memo.evict_oldest();
+        }
+        let previous = if self.capacity == 0 {
+            memo.slots.remove(key)
+        } else {
+            memo.seq += 1;
+            let seq = memo.seq;
+            memo.completed.insert(seq, (key.to_string(), expires_at));
+            memo.slots.insert(key.to_string(), Slot::Done(bytes))
+        };
+        if let Some(Slot::Pending { waiters }) = previous {
+            waiters.into_iter().for_each(Waker::wake);
+        }
+    }
+
+    /// 等待执行中的 `key` 完成或被撤销
+    async fn completion(&self, key: &str) {
+        std::future::poll_fn(|cx| {
+            let mut memo = self.memo.lock().unwrap();
+            match memo.slots.get_mut(key) {
+                Some(Slot::Pending { waiters }) => {
+                    if !waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                        waiters.push(cx.waker().clone());
+                    }
+                    Poll::Pending
+                }
+                _ => Poll::Ready(()),
+            }
+        })
+        .await
+    }
+}
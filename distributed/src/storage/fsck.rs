@@ -0,0 +1,553 @@
+//! 持久化状态的离线完整性检查（fsck）
+//!
+//! 设计意图：
+//! - 硬崩溃之后，节点在重新加入集群前应先确认本地状态可信。`Fsck` 逐一检查数据目录中的
+//!   持久化产物，输出结构化的 `FsckReport`：每条发现带严重级别（`Severity`）、所属产物与
+//!   可选的修复动作（`RepairAction`）。
+//! - 检查项：
+//!   - WAL（`SegmentedWal` 目录）：逐帧 CRC、段名与首序号一致、段内序号严格递增、同代段
+//!     不重叠、截断水位文件可解析；
+//!   - 快照（`FileSnapshot` 文件）：头部 CRC；无头部的旧格式只作提示；
+//!   - 硬状态（`HardStateFile`）：CRC；提交索引不超过日志与快照覆盖的范围；配置了
+//!     `with_entry_term` 时日志中的任期不超过硬状态的任期；
+//!   - 幂等记录（`FileIdempotency` 文件）：逐条 CRC；
+//!   - 交叉校验：WAL 首个存活序号不大于快照索引 + 1，否则两者之间的条目已经丢失。
+//! - 只有写入中途崩溃能解释的问题才给出修复动作：截掉不完整的末尾帧（`Truncate`），删除
+//!   改名前崩溃留下的临时文件（`Remove`）。中间帧校验失败、快照或硬状态损坏不自动修复，
+//!   需要从副本重建。
+//! - `FsckPolicy` 是启动开关：`preflight` 先按需修复，仍有不低于 `refuse_at` 的发现时拒绝
+//!   启动。`DistributedConfig::preflight` 以数据目录的标准布局调用它。
+//!
+//! 不变量（草图）：
+//! - `check` 只读；`repair` 只删除临时文件或截掉已无法读取的末尾字节，不丢弃任何完好的记录。
+//! - 修复后重新检查的报告中不再包含已修复的发现。
+
+use crate::core::errors::DistributedError;
+use crate::storage::SnapshotFile;
+use crate::storage::hard_state::HardState;
+use crate::storage::idempotency::scan_idempotency_file;
+use crate::storage::wal::{FrameDamage, WATERMARK_FILE, parse_segment_name, scan_segment};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// 发现的严重级别，按 `Info < Warning < Error` 排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// 不影响正确性，如旧格式的快照
+    Info,
+    /// 崩溃留下的残余，修复后即可启动
+    Warning,
+    /// 数据损坏或不一致，需要人工处理或从副本重建
+    Error,
+}
+
+/// 被检查的持久化产物
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Artifact {
+    Wal,
+    Snapshot,
+    HardState,
+    Idempotency,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairAction {
+    /// 把文件截断到 `len` 字节
+    Truncate { path: PathBuf, len: u64 },
+    /// 删除文件
+    Remove { path: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub artifact: Artifact,
+    pub path: PathBuf,
+    pub message: String,
+    pub repair: Option<RepairAction>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {:?} {}: {}",
+            self.severity,
+            self.artifact,
+            self.path.display(),
+            self.message
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FsckReport {
+    pub findings: Vec<Finding>,
+    /// `repair` 执行过的动作
+    pub repaired: Vec<RepairAction>,
+}
+
+impl FsckReport {
+    /// 最高的严重级别；没有发现时为 `None`
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity).max()
+    }
+
+    /// 没有 `Warning` 及以上的发现
+    pub fn is_clean(&self) -> bool {
+        self.max_severity().is_none_or(|s| s == Severity::Info)
+    }
+
+    pub fn at_least(&self, severity: Severity) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(move |f| f.severity >= severity)
+    }
+
+    pub fn for_artifact(&self, artifact: Artifact) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(move |f| f.artifact == artifact)
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        artifact: Artifact,
+        path: &Path,
+        message: String,
+        repair: Option<RepairAction>,
+    ) {
+        self.findings.push(Finding {
+            severity,
+            artifact,
+            path: path.to_path_buf(),
+            message,
+            repair,
+        });
+    }
+}
+
+/// 启动前检查的策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsckPolicy {
+    /// 存在不低于该级别的发现时拒绝启动；`None` 表示只检查不拒绝
+    pub refuse_at: Option<Severity>,
+    /// 检查前先执行修复动作
+    pub repair: bool,
+}
+
+impl Default for FsckPolicy {
+    fn default() -> Self {
+        Self {
+            refuse_at: Some(Severity::Error),
+            repair: false,
+        }
+    }
+}
+
+type EntryTermFn = Box<dyn Fn(&[u8]) -> Option<u64> + Send + Sync>;
+
+/// WAL 检查的摘要，供交叉校验使用
+#[derive(Default)]
+struct WalSummary {
+    first_live: Option<u64>,
+    last: Option<u64>,
+    max_term: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct Fsck {
+    wal: Option<PathBuf>,
+    snapshot: Option<PathBuf>,
+    hard_state: Option<PathBuf>,
+    idempotency: Option<PathBuf>,
+    entry_term: Option<EntryTermFn>,
+}
+
+impl Fsck {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 数据目录的标准布局：`wal/`、`snapshot`、`hard_state`、`idempotency`
+    pub fn for_data_dir(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+        Self::new()
+            .with_wal(dir.join("wal"))
+            .with_snapshot(dir.join("snapshot"))
+            .with_hard_state(dir.join("hard_state"))
+            .with_idempotency(dir.join("idempotency"))
+    }
+
+    /// `SegmentedWal` 的目录
+    pub fn with_wal(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.wal = Some(dir.into());
+        self
+    }
+
+    /// `FileSnapshot` 的文件
+    pub fn with_snapshot(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot = Some(path.into());
+        self
+    }
+
+    /// `HardStateFile` 的文件
+    pub fn with_hard_state(mut self, path: impl Into<PathBuf>) -> Self {
+        self.hard_state = Some(path.into());
+        self
+    }
+
+    /// `FileIdempotency` 的文件
+    pub fn with_idempotency(mut self, path: impl Into<PathBuf>) -> Self {
+        self.idempotency = Some(path.into());
+        self
+    }
+
+    /// 从 WAL 记录的负载中取出任期，用于核对硬状态的任期
+    pub fn with_entry_term(
+        mut self,
+        term: impl Fn(&[u8]) -> Option<u64> + Send + Sync + 'static,
+    ) -> Self {
+        self.entry_term = Some(Box::new(term));
+        self
+    }
+
+    /// 只读检查；不存在的产物视为尚未创建，不报告
+    pub fn check(&self) -> Result<FsckReport, DistributedError> {
+        let mut report = FsckReport::default();
+        let wal = match &self.wal {
+            Some(dir) => self.check_wal(dir, &mut report)?,
+            None => WalSummary::default(),
+        };
+        let snapshot_index = match &self.snapshot {
+            Some(path) => check_snapshot(path, &mut report)?,
+            None => None,
+        };
+        if let (Some(first), Some(path)) = (wal.first_live, &self.wal) {
+            let covered = snapshot_index.unwrap_or(0);
+            if first > covered + 1 {
+                report.push(
+                    Severity::Error,
+                    Artifact::Wal,
+                    path,
+                    format!(
+                        "WAL starts at {first} but the snapshot only covers up to {covered}; \
+                         entries {}..{first} are missing",
+                        covered + 1
+                    ),
+                    None,
+                );
+            }
+        }
+        if let Some(path) = &self.hard_state {
+            check_hard_state(path, &wal, snapshot_index, self.wal.is_some(), &mut report)?;
+        }
+        if let Some(path) = &self.idempotency {
+            check_idempotency(path, &mut report)?;
+        }
+        Ok(report)
+    }
+
+    /// 执行全部修复动作后重新检查；返回的报告记录执行过的动作
+    pub fn repair(&self) -> Result<FsckReport, DistributedError> {
+        let before = self.check()?;
+        let mut repaired = Vec::new();
+        for action in before.findings.into_iter().filter_map(|f| f.repair) {
+            apply(&action)?;
+            repaired.push(action);
+        }
+        let mut after = self.check()?;
+        after.repaired = repaired;
+        Ok(after)
+    }
+
+    /// 启动前检查：按策略修复，仍有不低于 `refuse_at` 的发现时返回错误
+    pub fn preflight(&self, policy: &FsckPolicy) -> Result<FsckReport, DistributedError> {
+        let report = if policy.repair {
+            self.repair()?
+        } else {
+            self.check()?
+        };
+        if let Some(threshold) = policy.refuse_at {
+            let blocking: Vec<String> = report.at_least(threshold).map(|f| f.to_string()).collect();
+            if !blocking.is_empty() {
+                return Err(DistributedError::Storage(format!(
+                    "refusing to start: fsck found {} problem(s) at or above {threshold:?}: {}",
+                    blocking.len(),
+                    blocking.join("; ")
+                )));
+            }
+        }
+        Ok(report)
+    }
+
+    fn check_wal(
+        &self,
+        dir: &Path,
+        report: &mut FsckReport,
+    ) -> Result<WalSummary, DistributedError> {
+        let mut summary = WalSummary::default();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(summary),
+            Err(e) => return Err(storage_err(e)),
+        };
+        let mut truncated_before = 0;
+        let mut segments = Vec::new();
+        for dirent in entries {
+            let path = dirent.map_err(storage_err)?.path();
+            if let Some((first_seq, generation)) = parse_segment_name(&path) {
+                segments.push((first_seq, generation, path));
+            } else if path.extension().is_some_and(|ext| ext == "tmp") {
+                orphaned_tmp(Artifact::Wal, &path, report);
+            } else if path.file_name().is_some_and(|name| name == WATERMARK_FILE) {
+                let raw = fs::read_to_string(&path).map_err(storage_err)?;
+                match raw.trim().parse() {
+                    Ok(seq) => truncated_before = seq,
+                    Err(_) => report.push(
+                        Severity::Error,
+                        Artifact::Wal,
+                        &path,
+                        format!("unparseable truncation watermark {raw:?}"),
+                        None,
+                    ),
+                }
+            } else {
+                report.push(
+                    Severity::Info,
+                    Artifact::Wal,
+                    &path,
+                    "unrecognised file in WAL directory".to_string(),
+                    None,
+                );
+            }
+        }
+        segments.sort();
+
+        // (首序号, 末序号, 代)
+        let mut ranges: Vec<(u64, u64, u64)> = Vec::new();
+        for (first_seq, generation, path) in segments {
+            let scan = scan_segment(&path)?;
+            match scan.damage {
+                Some(FrameDamage::TornTail) => report.push(
+                    Severity::Warning,
+                    Artifact::Wal,
+                    &path,
+                    format!(
+                        "torn tail: {} of {} byte(s) unreadable",
+                        scan.file_len - scan.valid_len,
+                        scan.file_len
+                    ),
+                    Some(RepairAction::Truncate {
+                        path: path.clone(),
+                        len: scan.valid_len,
+                    }),
+                ),
+                Some(FrameDamage::ChecksumMismatch { offset }) => report.push(
+                    Severity::Error,
+                    Artifact::Wal,
+                    &path,
+                    format!("checksum mismatch in frame at byte {offset}"),
+                    None,
+                ),
+                None => {}
+            }
+            let Some(&(first, _)) = scan.frames.first() else {
+                continue;
+            };
+            if first != first_seq {
+                report.push(
+                    Severity::Error,
+                    Artifact::Wal,
+                    &path,
+                    format!("segment named for seq {first_seq} starts at seq {first}"),
+                    None,
+                );
+            }
+            if let Some(pair) = scan.frames.windows(2).find(|w| w[1].0 <= w[0].0) {
+                report.push(
+                    Severity::Error,
+                    Artifact::Wal,
+                    &path,
+                    format!("seq {} follows seq {}", pair[1].0, pair[0].0),
+                    None,
+                );
+            }
+            let mut last = first;
+            for (seq, payload) in &scan.frames {
+                last = last.max(*seq);
+                if *seq >= truncated_before {
+                    summary.first_live = Some(summary.first_live.map_or(*seq, |f| f.min(*seq)));
+                }
+                if let Some(term) = self.entry_term.as_ref().and_then(|term| term(payload)) {
+                    summary.max_term = Some(summary.max_term.map_or(term, |t| t.max(term)));
+                }
+            }
+            summary.last = Some(summary.last.map_or(last, |l| l.max(last)));
+            // 不同代之间的重叠是压缩中途崩溃的残余，恢复时按序号去重
+            for &(other_first, other_last, other_generation) in &ranges {
+                if other_generation == generation && first <= other_last && other_first <= last {
+                    report.push(
+                        Severity::Error,
+                        Artifact::Wal,
+                        &path,
+                        format!(
+                            "seqs {first}..={last} overlap another generation-{generation} \
+                             segment ({other_first}..={other_last})"
+                        ),
+                        None,
+                    );
+                }
+            }
+            ranges.push((first, last, generation));
+        }
+        Ok(summary)
+    }
+}
+
+fn check_snapshot(path: &Path, report: &mut FsckReport) -> Result<Option<u64>, DistributedError> {
+    let tmp = path.with_extension("tmp");
+    if tmp.exists() {
+        orphaned_tmp(Artifact::Snapshot, &tmp, report);
+    }
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(storage_err(e)),
+    };
+    match SnapshotFile::decode(&bytes) {
+        Ok(file) => {
+            if !file.checksummed {
+                report.push(
+                    Severity::Info,
+                    Artifact::Snapshot,
+                    path,
+                    "legacy snapshot without checksum".to_string(),
+                    None,
+                );
+            }
+            Ok(Some(file.index))
+        }
+        Err(e) => {
+            report.push(
+                Severity::Error,
+                Artifact::Snapshot,
+                path,
+                e.to_string(),
+                None,
+            );
+            Ok(None)
+        }
+    }
+}
+
+fn check_hard_state(
+    path: &Path,
+    wal: &WalSummary,
+    snapshot_index: Option<u64>,
+    has_wal: bool,
+    report: &mut FsckReport,
+) -> Result<(), DistributedError> {
+    let tmp = path.with_extension("tmp");
+    if tmp.exists() {
+        orphaned_tmp(Artifact::HardState, &tmp, report);
+    }
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(storage_err(e)),
+    };
+    let state = match HardState::decode(&bytes) {
+        Ok(state) => state,
+        Err(e) => {
+            report.push(
+                Severity::Error,
+                Artifact::HardState,
+                path,
+                e.to_string(),
+                None,
+            );
+            return Ok(());
+        }
+    };
+    let durable = wal.last.unwrap_or(0).max(snapshot_index.unwrap_or(0));
+    if has_wal && state.commit_index > durable {
+        report.push(
+            Severity::Error,
+            Artifact::HardState,
+            path,
+            format!(
+                "commit index {} is beyond the last durable entry {durable}",
+                state.commit_index
+            ),
+            None,
+        );
+    }
+    if let Some(max_term) = wal.max_term.filter(|t| *t > state.term) {
+        report.push(
+            Severity::Error,
+            Artifact::HardState,
+            path,
+            format!(
+                "log contains term {max_term} but hard state term is {}",
+                state.term
+            ),
+            None,
+        );
+    }
+    Ok(())
+}
+
+fn check_idempotency(path: &Path, report: &mut FsckReport) -> Result<(), DistributedError> {
+    let scan = scan_idempotency_file(path)?;
+    match scan.damage {
+        Some(FrameDamage::TornTail) => report.push(
+            Severity::Warning,
+            Artifact::Idempotency,
+            path,
+            format!(
+                "torn tail: {} of {} byte(s) unreadable",
+                scan.file_len - scan.valid_len,
+                scan.file_len
+            ),
+            Some(RepairAction::Truncate {
+                path: path.to_path_buf(),
+                len: scan.valid_len,
+            }),
+        ),
+        Some(FrameDamage::ChecksumMismatch { offset }) => report.push(
+            Severity::Error,
+            Artifact::Idempotency,
+            path,
+            format!("checksum mismatch in record at byte {offset}"),
+            None,
+        ),
+        None => {}
+    }
+    Ok(())
+}
+
+fn orphaned_tmp(artifact: Artifact, path: &Path, report: &mut FsckReport) {
+    report.push(
+        Severity::Warning,
+        artifact,
+        path,
+        "orphaned temporary file from an interrupted write".to_string(),
+        Some(RepairAction::Remove {
+            path: path.to_path_buf(),
+        }),
+    );
+}
+
+fn apply(action: &RepairAction) -> Result<(), DistributedError> {
+    match action {
+        RepairAction::Truncate { path, len } => OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_len(*len).and_then(|()| file.sync_all()))
+            .map_err(storage_err),
+        RepairAction::Remove { path } => fs::remove_file(path).map_err(storage_err),
+    }
+}
+
+fn storage_err(e: std::io::Error) -> DistributedError {
+    DistributedError::Storage(e.to_string())
+}
@@ -0,0 +1,76 @@
+//! 持久化的共识硬状态
+//!
+//! 设计意图：
+//! - 节点重启后必须记得自己投过票的任期与投给了谁，否则同一任期可能投出两票；提交索引
+//!   则决定重放日志时哪些条目可以直接应用。`HardState` 收拢这三项，`HardStateFile` 以
+//!   `[crc32 u32 LE][JSON]` 保存，先写临时文件再改名，任意时刻崩溃都只会看到旧值或新值。
+//! - 读取时校验 CRC：损坏的硬状态以错误报告，而不是退回默认值（退回默认值等于忘记投票）。
+//!
+//! 不变量（草图）：
+//! - `load` 返回的值总是某次 `save` 完整写入的值。
+
+use crate::codec::crc32;
+use crate::core::errors::DistributedError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardState {
+    pub term: u64,
+    pub voted_for: Option<String>,
+    pub commit_index: u64,
+}
+
+impl HardState {
+    pub fn encode(&self) -> Vec<u8> {
+        let body = serde_json::to_vec(self).expect("serializable hard state");
+        let mut bytes = Vec::with_capacity(4 + body.len());
+        bytes.extend_from_slice(&crc32(&body).to_le_bytes());
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DistributedError> {
+        let corrupt = |reason: &str| DistributedError::Storage(format!("hard state {reason}"));
+        let (crc, body) = bytes
+            .split_first_chunk::<4>()
+            .ok_or_else(|| corrupt("truncated"))?;
+        if crc32(body) != u32::from_le_bytes(*crc) {
+            return Err(corrupt("checksum mismatch"));
+        }
+        serde_json::from_slice(body).map_err(|e| corrupt(&e.to_string()))
+    }
+}
+
+pub struct HardStateFile {
+    path: PathBuf,
+}
+
+impl HardStateFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&self, state: &HardState) -> Result<(), DistributedError> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, state.encode()).map_err(storage_err)?;
+        std::fs::rename(&tmp, &self.path).map_err(storage_err)
+    }
+
+    /// 文件不存在（从未保存）时为 `None`
+    pub fn load(&self) -> Result<Option<HardState>, DistributedError> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => HardState::decode(&bytes).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_err(e)),
+        }
+    }
+}
+
+fn storage_err(e: std::io::Error) -> DistributedError {
+    DistributedError::Storage(e.to_string())
+}
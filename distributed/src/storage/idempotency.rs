@@ -0,0 +1,280 @@
+//! 幂等键
+//!
+//! 设计意图：
+//! - 调用方自行编造的 ID 容易碰撞，碰撞会被去重存储静默吞掉。`IdempotencyKey` 由
+//!   HLC 时间戳、节点 ID 与节点内序号组成：不同节点的节点 ID 不同，同一节点内
+//!   HLC 与序号都严格递增，因此不会碰撞。
+//! - 文本形式为 `{physical_ms}-{logical}-{seq}-{node_id}`，节点 ID 放在最后以允许其
+//!   包含 `-`；serde 使用同一文本形式。
+//! - 内嵌时间戳可取出，按 TTL 过期旧键的存储据此判断键的年龄。
+//! - `FileIdempotency` 把已见过的 ID 追加到文件，重启后仍能去重。记录格式为
+//!   `[len u32 LE][crc32 u32 LE][id UTF-8]`；打开时在第一条不完整或校验失败的记录处停止。
+//!
+//! 不变量（草图）：
+//! - 同一生成器产生的键按生成顺序严格递增（排序先比较时间戳，再比较节点与序号）。
+
+use crate::codec::crc32;
+use crate::core::errors::DistributedError;
+use crate::core::{HlcClock, HlcTimestamp};
+use crate::storage::IdempotencyStore;
+use crate::storage::wal::FrameDamage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct IdempotencyKey {
+    timestamp: HlcTimestamp,
+    node_id: String,
+    seq: u64,
+}
+
+impl IdempotencyKey {
+    pub fn new(timestamp: HlcTimestamp, node_id: impl Into<String>, seq: u64) -> Self {
+        Self {
+            timestamp,
+            node_id: node_id.into(),
+            seq,
+        }
+    }
+
+    /// 生成时的 HLC 时间戳
+    pub fn timestamp(&self) -> HlcTimestamp {
+        self.timestamp
+    }
+
+    /// 生成时的物理时间（Unix 毫秒）
+    pub fn physical_ms(&self) -> u64 {
+        self.timestamp.physical_ms
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+impl fmt::Display for IdempotencyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}-{}-{}",
+            self.timestamp.physical_ms, self.timestamp.logical, self.seq, self.node_id
+        )
+    }
+}
+
+impl FromStr for IdempotencyKey {
+    type Err = DistributedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DistributedError::Configuration(format!("invalid idempotency key {s}"));
+        let mut parts = s.splitn(4, '-');
+        let mut number = || parts.next().and_then(|p| p.parse::<u64>().ok());
+        let physical_ms = number().ok_or_else(invalid)?;
+        let logical = number()
+            .and_then(|l| u32::try_from(l).ok())
+            .ok_or_else(invalid)?;
+        let seq = number().ok_or_else(invalid)?;
+        let node_id = parts.next().filter(|n| !n.is_empty()).ok_or_else(invalid)?;
+        Ok(Self::new(
+            HlcTimestamp {
+                physical_ms,
+                logical,
+            },
+            node_id,
+            seq,
+        ))
+    }
+}
+
+impl From<IdempotencyKey> for String {
+    fn from(key: IdempotencyKey) -> Self {
+        key.to_string()
+    }
+}
+
+impl TryFrom<String> for IdempotencyKey {
+    type Error = DistributedError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// 单个节点的幂等键生成器
+pub struct IdempotencyKeyGenerator {
+    node_id: String,
+    clock: HlcClock,
+    seq: AtomicU64,
+}
+
+impl IdempotencyKeyGenerator {
+    pub fn new(node_id: impl Into<String>) -> Self {
+        Self::with_clock(node_id, HlcClock::new())
+    }
+
+    /// 共享或自定义时间源的时钟（如测试中的偏斜时钟）
+    pub fn with_clock(node_id: impl Into<String>, clock: HlcClock) -> Self {
+        Self {
+            node_id: node_id.into(),
+            clock,
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn clock(&self) -> &HlcClock {
+        &self.clock
+    }
+
+    pub fn next_key(&self) -> IdempotencyKey {
+        let timestamp = self.clock.now();
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        IdempotencyKey::new(timestamp, self.node_id.clone(), seq)
+    }
+}
+
+const RECORD_HEADER: usize = 8;
+
+/// 逐条扫描幂等记录文件的结果
+#[derive(Debug, Clone)]
+pub struct IdempotencyScan {
+    pub ids: Vec<String>,
+    /// 完好记录占用的字节数
+    pub valid_len: u64,
+    pub file_len: u64,
+    pub damage: Option<FrameDamage>,
+}
+
+/// 读取幂等记录文件，在第一条不完整或校验失败的记录处停止；文件不存在视为空
+pub fn scan_idempotency_file(path: &Path) -> Result<IdempotencyScan, DistributedError> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(DistributedError::Storage(e.to_string())),
+    };
+    let mut ids = Vec::new();
+    let mut offset = 0;
+    let mut damage = None;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        let Some(header) = rest.get(..RECORD_HEADER) else {
+            damage = Some(FrameDamage::TornTail);
+            break;
+        };
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let Some(body) = rest.get(RECORD_HEADER..RECORD_HEADER + len) else {
+            damage = Some(FrameDamage::TornTail);
+            break;
+        };
+        let id = (crc32(body) == crc)
+            .then(|| std::str::from_utf8(body).ok())
+            .flatten();
+        let Some(id) = id else {
+            damage = Some(if offset + RECORD_HEADER + len == bytes.len() {
+                FrameDamage::TornTail
+            } else {
+                FrameDamage::ChecksumMismatch {
+                    offset: offset as u64,
+                }
+            });
+            break;
+        };
+        ids.push(id.to_string());
+        offset += RECORD_HEADER + len;
+    }
+    Ok(IdempotencyScan {
+        ids,
+        valid_len: offset as u64,
+        file_len: bytes.len() as u64,
+        damage,
+    })
+}
+
+/// 持久化到追加文件的幂等去重存储
+pub struct FileIdempotency {
+    path: PathBuf,
+    seen: HashSet<String>,
+    file: File,
+    last_error: Option<DistributedError>,
+}
+
+impl FileIdempotency {
+    /// 打开（或创建）记录文件并载入其中完好的记录
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, DistributedError> {
+        let path = path.into();
+        let scan = scan_idempotency_file(&path)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| DistributedError::Storage(e.to_string()))?;
+        Ok(Self {
+            path,
+            seen: scan.ids.into_iter().collect(),
+            file,
+            last_error: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// 记录并落盘；已见过的 ID 不重复写入
+    pub fn try_record(&mut self, id: String) -> Result<(), DistributedError> {
+        if self.seen.contains(&id) {
+            return Ok(());
+        }
+        let mut record = Vec::with_capacity(RECORD_HEADER + id.len());
+        record.extend_from_slice(&(id.len() as u32).to_le_bytes());
+        record.extend_from_slice(&crc32(id.as_bytes()).to_le_bytes());
+        record.extend_from_slice(id.as_bytes());
+        self.file
+            .write_all(&record)
+            .map_err(|e| DistributedError::Storage(e.to_string()))?;
+        self.seen.insert(id);
+        Ok(())
+    }
+
+    /// `IdempotencyStore::record` 无法返回错误，最近一次落盘失败保存在这里
+    pub fn take_error(&mut self) -> Option<DistributedError> {
+        self.last_error.take()
+    }
+}
+
+impl IdempotencyStore<String> for FileIdempotency {
+    fn seen(&self, id: &String) -> bool {
+        self.seen.contains(id)
+    }
+
+    fn record(&mut self, id: String) {
+        if let Err(e) = self.try_record(id.clone()) {
+            // 落盘失败时仍在内存中去重，重启后该 ID 可能被再次执行
+            self.seen.insert(id);
+            self.last_error = Some(e);
+        }
+    }
+}
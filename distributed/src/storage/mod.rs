@@ -1,14 +1,55 @@
 //! 存储模块
 //!
 //! 范围与目标：
-//! - 抽象日志与快照持久化接口，提供内存/文件最小实现与幂等去重存储。
+//! - 抽象日志与快照持久化接口，提供内存/文件最小实现与幂等去重存储（含分片锁版本）。
 //! - 与共识/复制对齐：日志 `append` 的持久性语义、快照的原子保存/加载语义。
 //!
 //! 不变量与注意（草图）：
 //! - `append` 返回偏移或序号，用作提交索引对齐；文件实现需持久化长度与校验。
-//! - 快照保存/加载应具备版本与校验能力（此处示例化，工程化需扩展）。
+//! - 快照文件带魔数、覆盖到的日志索引与 CRC 头（`SnapshotFile`），加载时校验；`fsck` 据此与
+//!   WAL 交叉校验。
 
+pub mod anti_entropy;
+pub mod cached_kv;
+pub mod compaction;
+pub mod digest;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod exactly_once;
+pub mod fsck;
+pub mod hard_state;
+pub mod idempotency;
+pub mod mvcc;
+pub mod quorum_guard;
 pub mod replication;
+#[cfg(feature = "runtime-tokio")]
+pub mod replication_queue;
+pub mod versioned;
+pub mod wal;
+pub mod watch;
+
+pub use anti_entropy::MerkleReconciler;
+pub use cached_kv::{ApplyEvent, CachedKv, ReplicatedKv};
+pub use compaction::{
+    AnyOf, Compactable, CompactionMetrics, CompactionPolicy, Compactor, EntryCount, LogStats,
+    SizeThreshold, TimeBased,
+};
+#[cfg(feature = "encryption")]
+pub use encryption::{
+    CallbackKeyProvider, EncryptedStorage, EncryptionKey, EnvKeyProvider, KeyId, KeyProvider,
+    RecordCipher, StaticKeyProvider,
+};
+pub use exactly_once::{ExactlyOnceExecutor, ExactlyOnceStats};
+pub use fsck::{Artifact, Finding, Fsck, FsckPolicy, FsckReport, RepairAction, Severity};
+pub use hard_state::{HardState, HardStateFile};
+pub use idempotency::{FileIdempotency, IdempotencyKey, IdempotencyKeyGenerator};
+pub use mvcc::{KvSnapshot, MvccKv, MvccStore};
+pub use quorum_guard::QuorumGuard;
+pub use versioned::{
+    CasError, FileVersionedStore, InMemoryVersionedStore, Version, VersionedStore,
+};
+pub use wal::{FrameDamage, Reclaimed, SegmentScan, SegmentedWal};
+pub use watch::{KeyPrefix, KvEvent, WalWatchLog, WatchError, WatchHub, WatchLog, WatchStream};
 
 use crate::codec::BinaryCodec;
 use crate::core::errors::DistributedError;
@@ -28,11 +69,18 @@ pub trait IdempotencyStore<ID> {
     fn record(&mut self, id: ID);
 }
 
-#[derive(Default)]
 pub struct InMemoryIdempotency<ID: std::hash::Hash + Eq> {
     set: HashSet<ID>,
 }
 
+impl<ID: std::hash::Hash + Eq> Default for InMemoryIdempotency<ID> {
+    fn default() -> Self {
+        Self {
+            set: HashSet::new(),
+        }
+    }
+}
+
 impl<ID: std::hash::Hash + Eq + Clone> IdempotencyStore<ID> for InMemoryIdempotency<ID> {
     fn seen(&self, id: &ID) -> bool {
         self.set.contains(id)
@@ -42,6 +90,67 @@ impl<ID: std::hash::Hash + Eq + Clone> IdempotencyStore<ID> for InMemoryIdempote
     }
 }
 
+/// 分片的幂等去重存储：`N` 个子集合各由独立的 `Mutex` 保护，
+/// `seen` / `record` 只锁定 `hash(id) % N` 对应的分片，高并发下减少锁争用。
+/// `N = 1` 即单锁实现。
+pub struct ShardedIdempotency<ID: std::hash::Hash + Eq + Clone, const N: usize = 256> {
+    shards: Vec<std::sync::Mutex<HashSet<ID>>>,
+}
+
+impl<ID: std::hash::Hash + Eq + Clone, const N: usize> Default for ShardedIdempotency<ID, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<ID: std::hash::Hash + Eq + Clone, const N: usize> ShardedIdempotency<ID, N> {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..N.max(1))
+                .map(|_| std::sync::Mutex::new(HashSet::new()))
+                .collect(),
+        }
+    }
+
+    pub fn shard_index(&self, id: &ID) -> usize {
+        use std::hash::Hasher;
+        let mut h = ahash::AHasher::default();
+        id.hash(&mut h);
+        (h.finish() % self.shards.len() as u64) as usize
+    }
+
+    pub fn seen(&self, id: &ID) -> bool {
+        self.shards[self.shard_index(id)]
+            .lock()
+            .unwrap()
+            .contains(id)
+    }
+
+    pub fn record(&self, id: ID) {
+        let shard = self.shard_index(&id);
+        self.shards[shard].lock().unwrap().insert(id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<ID: std::hash::Hash + Eq + Clone, const N: usize> IdempotencyStore<ID>
+    for ShardedIdempotency<ID, N>
+{
+    fn seen(&self, id: &ID) -> bool {
+        ShardedIdempotency::seen(self, id)
+    }
+    fn record(&mut self, id: ID) {
+        ShardedIdempotency::record(self, id)
+    }
+}
+
 pub trait SnapshotStorage<S> {
     fn save_snapshot(&mut self, state: &S) -> Result<(), DistributedError>;
     fn load_snapshot(&self) -> Result<Option<S>, DistributedError>
@@ -89,9 +198,102 @@ impl<C: BinaryCodec<E>, E> LogStorage<E> for FileLogStorage<C, E> {
     }
 }
 
+impl<C: BinaryCodec<E>, E> FileLogStorage<C, E> {
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// 按写入顺序读回全部条目；末尾不完整的帧（写入中途崩溃）被忽略
+    pub fn read_all(&self) -> Result<Vec<E>, DistributedError> {
+        let bytes = match std::fs::read(&self.path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(DistributedError::Storage(e.to_string())),
+        };
+        let mut entries = Vec::new();
+        let mut rest = bytes.as_slice();
+        while rest.len() >= 8 {
+            let (len, tail) = rest.split_at(8);
+            let len = u64::from_le_bytes(len.try_into().expect("8-byte length prefix")) as usize;
+            if tail.len() < len {
+                break;
+            }
+            let (frame, tail) = tail.split_at(len);
+            let entry = self
+                .codec
+                .decode(frame)
+                .ok_or_else(|| DistributedError::Storage("corrupt log entry".to_string()))?;
+            entries.push(entry);
+            rest = tail;
+        }
+        Ok(entries)
+    }
+
+    /// 清空日志（快照已覆盖其中全部条目时）
+    pub fn truncate(&mut self) -> Result<(), DistributedError> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(DistributedError::Storage(e.to_string())),
+        }
+    }
+}
+
+/// 快照文件头的魔数；头部为 `[magic 4][覆盖到的索引 u64 LE][crc32 u32 LE]`，其后是负载
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"DSNP";
+const SNAPSHOT_HEADER: usize = 16;
+
+/// 解析后的快照文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFile {
+    /// 快照覆盖到的日志索引；未配置 `with_index` 或无头部的旧格式为 0
+    pub index: u64,
+    pub payload: Vec<u8>,
+    /// 是否带校验头（旧格式的快照没有）
+    pub checksummed: bool,
+}
+
+impl SnapshotFile {
+    pub fn encode(index: u64, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SNAPSHOT_HEADER + payload.len());
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        bytes.extend_from_slice(&index.to_le_bytes());
+        bytes.extend_from_slice(&crate::codec::crc32(payload).to_le_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// 校验失败时返回错误；没有魔数的内容按旧格式整体视为负载
+    pub fn decode(bytes: &[u8]) -> Result<Self, DistributedError> {
+        if bytes.len() < SNAPSHOT_HEADER || &bytes[..4] != SNAPSHOT_MAGIC {
+            return Ok(Self {
+                index: 0,
+                payload: bytes.to_vec(),
+                checksummed: false,
+            });
+        }
+        let index = u64::from_le_bytes(bytes[4..12].try_into().expect("8-byte index"));
+        let crc = u32::from_le_bytes(bytes[12..16].try_into().expect("4-byte checksum"));
+        let payload = &bytes[SNAPSHOT_HEADER..];
+        if crate::codec::crc32(payload) != crc {
+            return Err(DistributedError::Storage(
+                "snapshot checksum mismatch".to_string(),
+            ));
+        }
+        Ok(Self {
+            index,
+            payload: payload.to_vec(),
+            checksummed: true,
+        })
+    }
+}
+
+type SnapshotIndexFn<S> = Box<dyn Fn(&S) -> u64 + Send + Sync>;
+
 pub struct FileSnapshot<C: BinaryCodec<S>, S: Clone> {
     path: std::path::PathBuf,
     codec: C,
+    index: Option<SnapshotIndexFn<S>>,
     _marker: std::marker::PhantomData<S>,
 }
 
@@ -100,19 +302,34 @@ impl<C: BinaryCodec<S>, S: Clone> FileSnapshot<C, S> {
         Self {
             path,
             codec,
+            index: None,
             _marker: std::marker::PhantomData,
         }
     }
+
+    /// 快照覆盖到的日志索引，写入文件头供 `fsck` 与日志交叉校验
+    pub fn with_index(mut self, index: impl Fn(&S) -> u64 + Send + Sync + 'static) -> Self {
+        self.index = Some(Box::new(index));
+        self
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
 }
 
 impl<C: BinaryCodec<S>, S: Clone> SnapshotStorage<S> for FileSnapshot<C, S> {
     fn save_snapshot(&mut self, state: &S) -> Result<(), DistributedError> {
-        let bytes = self.codec.encode(state);
-        std::fs::write(&self.path, bytes).map_err(|e| DistributedError::Storage(e.to_string()))
+        // 先写临时文件再改名，崩溃时不会留下半个快照
+        let index = self.index.as_ref().map_or(0, |index| index(state));
+        let bytes = SnapshotFile::encode(index, &self.codec.encode(state));
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, bytes).map_err(|e| DistributedError::Storage(e.to_string()))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| DistributedError::Storage(e.to_string()))
     }
     fn load_snapshot(&self) -> Result<Option<S>, DistributedError> {
         match std::fs::read(&self.path) {
-            Ok(b) => Ok(self.codec.decode(&b)),
+            Ok(b) => Ok(self.codec.decode(&SnapshotFile::decode(&b)?.payload)),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
             Err(e) => Err(DistributedError::Storage(e.to_string())),
         }
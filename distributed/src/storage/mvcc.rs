@@ -0,0 +1,272 @@
+//! 多版本并发控制（MVCC）存储
+//!
+//! 设计意图：
+//! - 每个键保存按时间戳升序排列的版本链 `Vec<(timestamp, value)>`，读取按时间点
+//!   （`as_of`）返回不晚于该时间的最新版本，读写互不阻塞。
+//! - `gc_before(horizon)` 回收不再可见的旧版本：对每个键保留 ≤ horizon 的最新版本，
+//!   因此任意 `as_of ≥ horizon` 的读取结果在 GC 前后不变。
+//! - `MvccKv` 为复制 KV 提供只读快照：写入按应用索引（版本）追加到版本链，删除记为墓碑；
+//!   `snapshot_at(version)` 登记一个活跃快照，快照上的每个键解析为不晚于快照点的最新版本，
+//!   跨键的 `multi_get` 因此不会读到撕裂的状态。
+//! - `gc` 的回收界限取“最旧的活跃快照”与“已应用版本减去保留窗口”中的较小者；快照的登记与
+//!   GC 在同一把锁内判定，快照点一经登记就不会被回收，早于回收界限的快照点被拒绝。
+//!
+//! 不变量（草图）：
+//! - 版本链按时间戳严格递增；同一时间戳的重复写入覆盖旧值。
+//! - 活跃快照在其生命周期内对同一键的读取结果不变。
+
+use crate::core::errors::DistributedError;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, RwLock};
+
+pub struct MvccStore<K, V> {
+    entries: HashMap<K, Vec<(u64, V)>>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for MvccStore<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> MvccStore<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// 追加版本；乱序到达的写入按时间戳插入到正确位置
+    pub fn write(&mut self, key: K, value: V, timestamp: u64) {
+        let versions = self.entries.entry(key).or_default();
+        match versions.binary_search_by_key(&timestamp, |(ts, _)| *ts) {
+            Ok(i) => versions[i].1 = value,
+            Err(i) => versions.insert(i, (timestamp, value)),
+        }
+    }
+
+    /// 时间点读取：不晚于 `as_of` 的最新版本
+    pub fn read(&self, key: &K, as_of: u64) -> Option<&V> {
+        let versions = self.entries.get(key)?;
+        let idx = versions.partition_point(|(ts, _)| *ts <= as_of);
+        idx.checked_sub(1).map(|i| &versions[i].1)
+    }
+
+    /// 最新版本
+    pub fn latest(&self, key: &K) -> Option<(u64, &V)> {
+        self.entries.get(key)?.last().map(|(ts, v)| (*ts, v))
+    }
+
+    /// 按时间戳升序遍历某个键的全部版本
+    pub fn versions(&self, key: &K) -> impl Iterator<Item = (u64, &V)> {
+        self.entries
+            .get(key)
+            .into_iter()
+            .flatten()
+            .map(|(ts, v)| (*ts, v))
+    }
+
+    /// 回收早于 `timestamp` 的版本，每个键保留 ≤ `timestamp` 的最新版本；返回回收的版本数
+    pub fn gc_before(&mut self, timestamp: u64) -> usize {
+        let mut removed = 0;
+        for versions in self.entries.values_mut() {
+            let visible = versions.partition_point(|(ts, _)| *ts <= timestamp);
+            let drop = visible.saturating_sub(1);
+            versions.drain(..drop);
+            removed += drop;
+        }
+        removed
+    }
+
+    /// 所有键的版本总数
+    pub fn version_count(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+struct SnapshotRegistry {
+    /// 快照点 → 活跃快照数
+    active: BTreeMap<u64, usize>,
+    /// 已回收到的界限；早于它的快照点不可再登记
+    horizon: u64,
+}
+
+/// 复制 KV 的多版本视图：按应用索引记录写入，提供一致的只读快照
+pub struct MvccKv<K, V> {
+    versions: RwLock<MvccStore<K, Option<V>>>,
+    applied: RwLock<u64>,
+    snapshots: Mutex<SnapshotRegistry>,
+    retention: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for MvccKv<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> MvccKv<K, V> {
+    pub fn new() -> Self {
+        Self {
+            versions: RwLock::new(MvccStore::new()),
+            applied: RwLock::new(0),
+            snapshots: Mutex::new(SnapshotRegistry {
+                active: BTreeMap::new(),
+                horizon: 0,
+            }),
+            retention: 0,
+        }
+    }
+
+    /// GC 至少保留最近 `versions` 个版本范围内的历史，供稍后开启的快照使用
+    pub fn with_retention(mut self, versions: u64) -> Self {
+        self.retention = versions;
+        self
+    }
+
+    /// 记录已提交的写入；`value` 为 `None` 表示删除。版本须单调递增地应用
+    pub fn apply(&self, key: K, value: Option<V>, version: u64) {
+        let mut applied = self.applied.write().unwrap();
+        self.versions.write().unwrap().write(key, value, version);
+        *applied = (*applied).max(version);
+    }
+
+    /// 已应用的最大版本
+    pub fn applied_version(&self) -> u64 {
+        *self.applied.read().unwrap()
+    }
+
+    /// 在已应用的最新版本上开启快照
+    pub fn snapshot(self: &Arc<Self>) -> KvSnapshot<K, V> {
+        let applied = self.applied.read().unwrap();
+        let mut snapshots = self.snapshots.lock().unwrap();
+        *snapshots.active.entry(*applied).or_default() += 1;
+        KvSnapshot {
+            kv: self.clone(),
+            version: *applied,
+        }
+    }
+
+    /// 在 `version` 上开启快照；版本尚未应用或已被回收时返回错误
+    pub fn snapshot_at(
+        self: &Arc<Self>,
+        version: u64,
+    ) -> Result<KvSnapshot<K, V>, DistributedError> {
+        let applied = self.applied.read().unwrap();
+        if version > *applied {
+            return Err(DistributedError::InvalidState(format!(
+                "snapshot version {version} not yet applied (applied {})",
+                *applied
+            )));
+        }
+        let mut snapshots = self.snapshots.lock().unwrap();
+        if version < snapshots.horizon {
+            return Err(DistributedError::InvalidState(format!(
+                "snapshot version {version} garbage collected (oldest {})",
+                snapshots.horizon
+            )));
+        }
+        *snapshots.active.entry(version).or_default() += 1;
+        Ok(KvSnapshot {
+            kv: self.clone(),
+            version,
+        })
+    }
+
+    /// 回收活跃快照与保留窗口都不再需要的版本，返回回收的版本数
+    pub fn gc(&self) -> usize {
+        let applied = self.applied_version();
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let mut horizon = applied.saturating_sub(self.retention);
+        if let Some((oldest, _)) = snapshots.active.first_key_value() {
+            horizon = horizon.min(*oldest);
+        }
+        if horizon <= snapshots.horizon {
+            return 0;
+        }
+        snapshots.horizon = horizon;
+        self.versions.write().unwrap().gc_before(horizon)
+    }
+
+    /// 最旧的活跃快照点
+    pub fn oldest_snapshot(&self) -> Option<u64> {
+        let snapshots = self.snapshots.lock().unwrap();
+        snapshots
+            .active
+            .first_key_value()
+            .map(|(version, _)| *version)
+    }
+
+    pub fn active_snapshots(&self) -> usize {
+        self.snapshots.lock().unwrap().active.values().sum()
+    }
+
+    /// 所有键的版本总数（含墓碑）
+    pub fn version_count(&self) -> usize {
+        self.versions.read().unwrap().version_count()
+    }
+
+    fn read_at(&self, key: &K, version: u64) -> Option<V> {
+        self.versions
+            .read()
+            .unwrap()
+            .read(key, version)
+            .cloned()
+            .flatten()
+    }
+}
+
+impl<K, V> MvccKv<K, V> {
+    fn release(&self, version: u64) {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        if let Some(count) = snapshots.active.get_mut(&version) {
+            *count -= 1;
+            if *count == 0 {
+                snapshots.active.remove(&version);
+            }
+        }
+    }
+}
+
+/// 只读快照：每个键解析为不晚于快照点的最新版本；析构时注销，之后其版本可被回收
+pub struct KvSnapshot<K, V> {
+    kv: Arc<MvccKv<K, V>>,
+    version: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> KvSnapshot<K, V> {
+    /// 快照点
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.kv.read_at(key, self.version)
+    }
+
+    /// 在同一快照点上读取多个键
+    pub fn multi_get<'a>(&self, keys: impl IntoIterator<Item = &'a K>) -> Vec<Option<V>>
+    where
+        K: 'a,
+    {
+        let versions = self.kv.versions.read().unwrap();
+        keys.into_iter()
+            .map(|key| versions.read(key, self.version).cloned().flatten())
+            .collect()
+    }
+}
+
+impl<K, V> Drop for KvSnapshot<K, V> {
+    fn drop(&mut self) {
+        self.kv.release(self.version);
+    }
+}
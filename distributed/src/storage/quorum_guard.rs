@@ -0,0 +1,268 @@
+//! 脑裂防护：失去多数派连通性时隔离（fence）写入
+//!
+//! 设计意图：
+//! - 与多数派失联的节点若继续接受 `Strong`/`Quorum` 写入，只会把它们无限期排队或在分区
+//!   两侧各自提交。`QuorumGuard` 消费故障检测器给出的对端可用性（`observe` 或直接读取
+//!   `PhiAccrualFailureDetector`），对本节点拥有的每个分片计算能否到达写仲裁（本节点自身
+//!   总是可达）。仲裁由 `WriteQuorum` 判定，缺省为副本多数；接入 `LocalReplicator` 时改用
+//!   副本器配置的策略，如 `DomainAwareQuorum` 下可达副本还须跨越足够的故障域。
+//! - 分片失去仲裁连通性超过宽限期 `grace` 后被隔离：需要多数确认的写入立即以
+//!   `DistributedError::QuorumUnavailable { reachable, required }` 失败，不再进入复制。
+//!   宽限期吸收检测器的短暂抖动，避免一次丢包就拒绝写入。
+//! - 只需一个确认的写入（`Eventual`）在开启 `with_hinted_handoff` 时继续放行，未确认的副本
+//!   记入复制器的待补齐队列，分区恢复后补齐；未开启时同样被拒绝。
+//! - 检测器重新报告多数副本可用时隔离自动解除，无需人工干预。
+//! - 副本器不经分片名的复制（如 `Replicator::replicate`）经 `admit_nodes` 按副本集合找到
+//!   登记的分片再准入，防护不会被绕过。
+//! - 时间由调用方传入，与故障检测器一致，便于在模拟时钟下测试。
+//!
+//! 不变量（草图）：
+//! - 分片被隔离 ⇒ 该分片的可达副本持续不构成写仲裁至少 `grace`。
+//! - 可达副本构成写仲裁的分片从不被隔离。
+
+use crate::consistency::ConsistencyLevel;
+use crate::core::errors::DistributedError;
+use crate::storage::replication::{MajorityQuorum, WriteQuorum};
+use crate::swim::PhiAccrualFailureDetector;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+struct GuardState {
+    /// 分片 → 副本（可以包含本节点）
+    shards: BTreeMap<String, Vec<String>>,
+    unreachable: HashSet<String>,
+    /// 分片失去仲裁连通性的起始时刻
+    lost_since: HashMap<String, Instant>,
+    fast_failures: u64,
+    quorum: Arc<dyn WriteQuorum>,
+    /// 最近一次 `set_shard`/`observe` 的时刻，更换仲裁策略时以此重新计算
+    last_update: Option<Instant>,
+}
+
+impl Default for GuardState {
+    fn default() -> Self {
+        Self {
+            shards: BTreeMap::new(),
+            unreachable: HashSet::new(),
+            lost_since: HashMap::new(),
+            fast_failures: 0,
+            quorum: Arc::new(MajorityQuorum),
+            last_update: None,
+        }
+    }
+}
+
+/// 按分片的写仲裁连通性防护，可经 `Arc` 在故障检测循环与复制器间共享
+pub struct QuorumGuard {
+    local: String,
+    grace: Duration,
+    hinted_handoff: bool,
+    state: RwLock<GuardState>,
+}
+
+impl QuorumGuard {
+    pub fn new(local: impl Into<String>, grace: Duration) -> Self {
+        Self {
+            local: local.into(),
+            grace,
+            hinted_handoff: false,
+            state: RwLock::new(GuardState::default()),
+        }
+    }
+
+    /// 隔离期间是否放行 `Eventual` 写入（进入待补齐队列）
+    pub fn with_hinted_handoff(mut self, enabled: bool) -> Self {
+        self.hinted_handoff = enabled;
+        self
+    }
+
+    /// 判定写仲裁的策略，缺省为 `MajorityQuorum`
+    pub fn with_quorum(self, quorum: Arc<dyn WriteQuorum>) -> Self {
+        self.set_quorum(quorum);
+        self
+    }
+
+    /// 更换写仲裁策略并按最近一次观测的时刻重新计算各分片的连通性
+    pub fn set_quorum(&self, quorum: Arc<dyn WriteQuorum>) {
+        let mut state = self.state.write().unwrap();
+        state.quorum = quorum;
+        if let Some(now) = state.last_update {
+            self.refresh(&mut state, now);
+        }
+    }
+
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    /// 登记（或替换）本节点拥有的分片
+    pub fn set_shard(&self, shard: impl Into<String>, replicas: Vec<String>, now: Instant) {
+        let mut state = self.state.write().unwrap();
+        state.shards.insert(shard.into(), replicas);
+        self.refresh(&mut state, now);
+    }
+
+    pub fn remove_shard(&self, shard: &str) {
+        let mut state = self.state.write().unwrap();
+        state.shards.remove(shard);
+        state.lost_since.remove(shard);
+    }
+
+    pub fn replicas(&self, shard: &str) -> Option<Vec<String>> {
+        self.state.read().unwrap().shards.get(shard).cloned()
+    }
+
+    /// 记录故障检测器对 `peer` 的判断
+    pub fn observe(&self, peer: &str, available: bool, now: Instant) {
+        let mut state = self.state.write().unwrap();
+        if available {
+            state.unreachable.remove(peer);
+        } else {
+            state.unreachable.insert(peer.to_string());
+        }
+        self.refresh(&mut state, now);
+    }
+
+    /// 以 φ 阈值读取故障检测器并记录
+    pub fn observe_detector(
+        &self,
+        peer: &str,
+        detector: &PhiAccrualFailureDetector,
+        threshold: f64,
+        now: Instant,
+    ) {
+        self.observe(peer, detector.is_available(now, threshold), now);
+    }
+
+    /// 分片的 (可达副本数, 写仲裁所需副本数)；未登记的分片为 `None`
+    pub fn reachability(&self, shard: &str) -> Option<(usize, usize)> {
+        let state = self.state.read().unwrap();
+        state
+            .shards
+            .get(shard)
+            .map(|replicas| self.count(&state, replicas))
+    }
+
+    /// 分片失去仲裁连通性是否已超过宽限期
+    pub fn is_fenced(&self, shard: &str, now: Instant) -> bool {
+        let state = self.state.read().unwrap();
+        self.fenced(&state, shard, now)
+    }
+
+    /// 当前被隔离的分片（按名称排序）
+    pub fn fenced_shards(&self, now: Instant) -> Vec<String> {
+        let state = self.state.read().unwrap();
+        state
+            .shards
+            .keys()
+            .filter(|shard| self.fenced(&state, shard, now))
+            .cloned()
+            .collect()
+    }
+
+    /// 写入准入：分片被隔离时拒绝需要多数确认的写入（以及未开启暂存时的全部写入）
+    pub fn admit(
+        &self,
+        shard: &str,
+        level: ConsistencyLevel,
+        now: Instant,
+    ) -> Result<(), DistributedError> {
+        let mut state = self.state.write().unwrap();
+        let Some(replicas) = state.shards.get(shard) else {
+            return Err(DistributedError::Configuration(format!(
+                "shard {shard} is not owned by {}",
+                self.local
+            )));
+        };
+        let (reachable, required) = self.count(&state, replicas);
+        if !self.fenced(&state, shard, now) {
+            return Ok(());
+        }
+        if self.hinted_handoff && state.quorum.min_acks(replicas, level) <= 1 {
+            return Ok(());
+        }
+        state.fast_failures += 1;
+        Err(DistributedError::QuorumUnavailable {
+            reachable,
+            required,
+        })
+    }
+
+    /// 按副本集合准入：`targets`（不计顺序）须是某个登记分片的副本集合，经该分片 `admit`
+    pub fn admit_nodes(
+        &self,
+        targets: &[String],
+        level: ConsistencyLevel,
+        now: Instant,
+    ) -> Result<(), DistributedError> {
+        let wanted: HashSet<&str> = targets.iter().map(String::as_str).collect();
+        let shard = {
+            let state = self.state.read().unwrap();
+            state
+                .shards
+                .iter()
+                .find(|(_, replicas)| {
+                    replicas.len() == wanted.len()
+                        && replicas.iter().all(|r| wanted.contains(r.as_str()))
+                })
+                .map(|(shard, _)| shard.clone())
+        };
+        match shard {
+            Some(shard) => self.admit(&shard, level, now),
+            None => Err(DistributedError::Configuration(format!(
+                "replicas {targets:?} do not form a shard owned by {}",
+                self.local
+            ))),
+        }
+    }
+
+    /// 因隔离而立即失败的写入数
+    pub fn fast_failures(&self) -> u64 {
+        self.state.read().unwrap().fast_failures
+    }
+
+    fn reachable<'a>(&self, state: &GuardState, replicas: &'a [String]) -> Vec<&'a str> {
+        replicas
+            .iter()
+            .filter(|r| **r == self.local || !state.unreachable.contains(*r))
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn count(&self, state: &GuardState, replicas: &[String]) -> (usize, usize) {
+        let reachable = self.reachable(state, replicas).len();
+        let required = state.quorum.min_acks(replicas, ConsistencyLevel::Quorum);
+        (reachable, required)
+    }
+
+    fn fenced(&self, state: &GuardState, shard: &str, now: Instant) -> bool {
+        state
+            .lost_since
+            .get(shard)
+            .is_some_and(|since| now.saturating_duration_since(*since) >= self.grace)
+    }
+
+    fn refresh(&self, state: &mut GuardState, now: Instant) {
+        state.last_update = Some(now);
+        let lost: Vec<(String, bool)> = state
+            .shards
+            .iter()
+            .map(|(shard, replicas)| {
+                let reachable = self.reachable(state, replicas);
+                let lost = state
+                    .quorum
+                    .check(replicas, &reachable, ConsistencyLevel::Quorum)
+                    .is_err();
+                (shard.clone(), lost)
+            })
+            .collect();
+        for (shard, lost) in lost {
+            if lost {
+                state.lost_since.entry(shard).or_insert(now);
+            } else {
+                state.lost_since.remove(&shard);
+            }
+        }
+    }
+}
@@ -1,12 +1,68 @@
 use crate::consistency::ConsistencyLevel;
 use crate::core::errors::DistributedError;
+use crate::core::session::ClientSession;
 use crate::storage::IdempotencyStore;
+use crate::storage::idempotency::{IdempotencyKey, IdempotencyKeyGenerator};
 use crate::core::topology::ConsistentHashRing;
+use crate::core::ClusterNodeId;
+use crate::core::ReplicaRole;
+use crate::storage::quorum_guard::QuorumGuard;
+use serde::{Deserialize, Serialize};
 
 pub trait Replicator<C> {
     fn replicate(&mut self, command: C, level: ConsistencyLevel) -> Result<(), DistributedError>;
 }
 
+/// 复制命令的优先级类别；声明顺序即调度顺序（`Control` 最先）
+///
+/// 控制面消息（成员变更、租约续期）使用 `Control`，不应排在批量数据复制之后。
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Priority {
+    Control,
+    High,
+    #[default]
+    Normal,
+    Bulk,
+}
+
+impl Priority {
+    /// 按调度顺序排列的全部类别
+    pub const ALL: [Priority; 4] = [
+        Priority::Control,
+        Priority::High,
+        Priority::Normal,
+        Priority::Bulk,
+    ];
+
+    /// 在 `ALL` 中的下标
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// 线上名称，如 `"control"`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Control => "control",
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Bulk => "bulk",
+        }
+    }
+}
+
+impl std::str::FromStr for Priority {
+    type Err = DistributedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Priority::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| DistributedError::Configuration(format!("unknown priority {s}")))
+    }
+}
+
 pub trait QuorumPolicy {
     fn required_acks(total: usize, level: ConsistencyLevel) -> usize;
 }
@@ -34,6 +90,175 @@ impl QuorumPolicy for MajorityQuorum {
     }
 }
 
+// ---------------- 实例级写仲裁 ----------------
+
+/// 实例级写仲裁：与按节点数的静态 `QuorumPolicy` 不同，策略可携带配置（如节点所属故障域）。
+/// `LocalReplicator`/`RemoteReplicator` 经 `with_quorum` 接受，缺省为 `MajorityQuorum`
+pub trait WriteQuorum: Send + Sync {
+    /// `acked` 是否构成 `targets` 上的写仲裁；不满足时返回原因
+    fn check(
+        &self,
+        targets: &[String],
+        acked: &[&str],
+        level: ConsistencyLevel,
+    ) -> Result<(), String>;
+
+    /// `targets` 上构成写仲裁所需的最少确认数，缺省为数量多数
+    fn min_acks(&self, targets: &[String], level: ConsistencyLevel) -> usize {
+        MajorityQuorum::required_acks(targets.len(), level)
+    }
+}
+
+impl WriteQuorum for MajorityQuorum {
+    fn check(
+        &self,
+        targets: &[String],
+        acked: &[&str],
+        level: ConsistencyLevel,
+    ) -> Result<(), String> {
+        let need = Self::required_acks(targets.len(), level);
+        if acked.len() >= need {
+            Ok(())
+        } else {
+            Err(format!("acks {}/{need}", acked.len()))
+        }
+    }
+}
+
+/// 故障域感知的写仲裁：在数量多数之外，确认还须来自至少 `min_domains` 个不同故障域，
+/// 使多数副本同处一个机架时，该机架整体故障前的写入不会只落在单个机架上。
+///
+/// 只需 1 个确认的级别（如 `Eventual`）不施加故障域要求；未记录故障域的节点不计入任何域。
+/// 目标副本跨越的故障域不足 `min_domains` 时退化为数量多数，并记录告警。
+pub struct DomainAwareQuorum {
+    domains: HashMap<String, String>,
+    min_domains: usize,
+    downgrades: AtomicU64,
+}
+
+impl DomainAwareQuorum {
+    /// `domains` 为 节点 → 故障域
+    pub fn new(domains: HashMap<String, String>, min_domains: usize) -> Self {
+        Self {
+            domains,
+            min_domains,
+            downgrades: AtomicU64::new(0),
+        }
+    }
+
+    /// 取哈希环上记录的故障域
+    pub fn from_ring(ring: &ConsistentHashRing, min_domains: usize) -> Self {
+        Self::new(ring.domains().clone(), min_domains)
+    }
+
+    pub fn min_domains(&self) -> usize {
+        self.min_domains
+    }
+
+    /// 因故障域不足而退化为数量多数的次数
+    pub fn downgrades(&self) -> u64 {
+        self.downgrades.load(Ordering::Relaxed)
+    }
+
+    fn distinct_domains<'a>(&self, nodes: impl IntoIterator<Item = &'a str>) -> usize {
+        nodes
+            .into_iter()
+            .filter_map(|n| self.domains.get(n))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+impl WriteQuorum for DomainAwareQuorum {
+    fn check(
+        &self,
+        targets: &[String],
+        acked: &[&str],
+        level: ConsistencyLevel,
+    ) -> Result<(), String> {
+        MajorityQuorum.check(targets, acked, level)?;
+        if MajorityQuorum::required_acks(targets.len(), level) <= 1 {
+            return Ok(());
+        }
+        let available = self.distinct_domains(targets.iter().map(String::as_str));
+        if available < self.min_domains {
+            self.downgrades.fetch_add(1, Ordering::Relaxed);
+            #[cfg(feature = "observability")]
+            tracing::warn!(
+                available,
+                min_domains = self.min_domains,
+                "too few failure domains, falling back to majority quorum"
+            );
+            return Ok(());
+        }
+        let spanned = self.distinct_domains(acked.iter().copied());
+        if spanned >= self.min_domains {
+            Ok(())
+        } else {
+            Err(format!(
+                "acks span {spanned}/{} failure domains",
+                self.min_domains
+            ))
+        }
+    }
+}
+
+/// 角色感知的写仲裁：只有投票者与见证者的确认计入仲裁，学习者的确认不计；
+/// 计数在过滤后的目标与确认上交给内部策略（缺省 `MajorityQuorum`）。
+///
+/// 2 个投票者加 1 个见证者时，任一投票者与见证者的确认即构成多数，见证者只需保存元数据。
+pub struct RoleAwareQuorum {
+    roles: HashMap<String, ReplicaRole>,
+    inner: Arc<dyn WriteQuorum>,
+}
+
+impl RoleAwareQuorum {
+    /// `roles` 为 节点 → 角色，未列出的节点为投票者
+    pub fn new(roles: HashMap<String, ReplicaRole>) -> Self {
+        Self {
+            roles,
+            inner: Arc::new(MajorityQuorum),
+        }
+    }
+
+    /// 取哈希环上记录的角色
+    pub fn from_ring(ring: &ConsistentHashRing) -> Self {
+        Self::new(ring.roles().clone())
+    }
+
+    /// 在计入仲裁的副本上再施加的策略，如 `DomainAwareQuorum`
+    pub fn with_inner(mut self, inner: Arc<dyn WriteQuorum>) -> Self {
+        self.inner = inner;
+        self
+    }
+
+    fn counts(&self, node: &str) -> bool {
+        self.roles
+            .get(node)
+            .copied()
+            .unwrap_or_default()
+            .counts_for_writes()
+    }
+}
+
+impl WriteQuorum for RoleAwareQuorum {
+    fn check(
+        &self,
+        targets: &[String],
+        acked: &[&str],
+        level: ConsistencyLevel,
+    ) -> Result<(), String> {
+        let voting: Vec<String> = targets.iter().filter(|n| self.counts(n)).cloned().collect();
+        let acked: Vec<&str> = acked.iter().copied().filter(|n| self.counts(n)).collect();
+        self.inner.check(&voting, &acked, level)
+    }
+
+    fn min_acks(&self, targets: &[String], level: ConsistencyLevel) -> usize {
+        let voting: Vec<String> = targets.iter().filter(|n| self.counts(n)).cloned().collect();
+        self.inner.min_acks(&voting, level)
+    }
+}
+
 // ---------------- Read/Write 可插拔仲裁（不破坏现有 API） ----------------
 
 pub trait ReadQuorumPolicy {
@@ -81,13 +306,26 @@ impl<R, W> CompositeQuorum<R, W> {
     }
 }
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub struct LocalReplicator<ID> {
     pub ring: ConsistentHashRing,
     pub nodes: Vec<String>,
     pub successes: HashMap<String, bool>,
     pub idempotency: Option<Box<dyn IdempotencyStore<ID> + Send>>,
+    /// 各副本已应用的最大写时间戳；缺省为 0
+    pub watermarks: HashMap<String, u64>,
+    /// 各副本 (确认次数, 复制尝试次数)
+    ack_counts: HashMap<String, (u64, u64)>,
+    write_clock: u64,
+    key_generator: Option<IdempotencyKeyGenerator>,
+    /// 已达仲裁但仍有目标副本未确认的条目，按提交顺序记录各自缺少确认的副本
+    pending: VecDeque<HashSet<String>>,
+    max_pending_entries: usize,
+    quorum: Arc<dyn WriteQuorum>,
+    guard: Option<Arc<QuorumGuard>>,
 }
 
 impl<ID> LocalReplicator<ID> {
@@ -97,32 +335,263 @@ impl<ID> LocalReplicator<ID> {
             nodes,
             successes: HashMap::new(),
             idempotency: None,
+            watermarks: HashMap::new(),
+            ack_counts: HashMap::new(),
+            write_clock: 0,
+            key_generator: None,
+            pending: VecDeque::new(),
+            max_pending_entries: 1000,
+            quorum: Arc::new(MajorityQuorum),
+            guard: None,
         }
     }
 
+    /// 写仲裁策略，缺省为 `MajorityQuorum`；已接入脑裂防护时同步给防护
+    pub fn with_quorum(mut self, quorum: Arc<dyn WriteQuorum>) -> Self {
+        if let Some(guard) = &self.guard {
+            guard.set_quorum(quorum.clone());
+        }
+        self.quorum = quorum;
+        self
+    }
+
+    /// 脑裂防护：全部复制路径先经其准入，分片被隔离时写入立即失败。
+    /// 防护改用本副本器的写仲裁判断分片是否失去仲裁连通性
+    pub fn with_quorum_guard(mut self, guard: Arc<QuorumGuard>) -> Self {
+        guard.set_quorum(self.quorum.clone());
+        self.guard = Some(guard);
+        self
+    }
+
+    /// 未被全部目标副本确认的条目数上限（缺省 1000）。待确认数不会超过上限：已有
+    /// `max_pending_entries` 个条目待确认时，新的复制（即使全部副本都会确认）以 `BackPressure` 拒绝
+    pub fn with_max_pending_entries(mut self, max_pending_entries: usize) -> Self {
+        self.max_pending_entries = max_pending_entries.max(1);
+        self
+    }
+
+    pub fn max_pending_entries(&self) -> usize {
+        self.max_pending_entries
+    }
+
+    /// 已达仲裁、仍在等待落后副本确认的条目数
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 落后副本追上后确认其欠下的全部条目；返回因此完成（全部副本确认）的条目数
+    pub fn ack_pending(&mut self, node: &str) -> usize {
+        let before = self.pending.len();
+        for missing in &mut self.pending {
+            missing.remove(node);
+        }
+        self.pending.retain(|missing| !missing.is_empty());
+        before - self.pending.len()
+    }
+
     pub fn with_idempotency(mut self, store: Box<dyn IdempotencyStore<ID> + Send>) -> Self {
         self.idempotency = Some(store);
         self
     }
 
+    /// 模拟节点故障：此后复制到该节点不计确认
+    pub fn mark_node_failed(&mut self, node: &str) {
+        self.successes.insert(node.to_string(), false);
+    }
+
+    /// 模拟节点恢复
+    pub fn mark_node_recovered(&mut self, node: &str) {
+        self.successes.insert(node.to_string(), true);
+    }
+
+    /// 当前标记为故障的节点（按名称排序）
+    pub fn failed_nodes(&self) -> Vec<&str> {
+        self.marked_nodes(false)
+    }
+
+    /// 显式标记为可用的节点，如已恢复的节点（按名称排序）
+    pub fn recovered_nodes(&self) -> Vec<&str> {
+        self.marked_nodes(true)
+    }
+
+    fn marked_nodes(&self, up: bool) -> Vec<&str> {
+        let mut nodes: Vec<&str> = self
+            .successes
+            .iter()
+            .filter(|(_, ok)| **ok == up)
+            .map(|(n, _)| n.as_str())
+            .collect();
+        nodes.sort_unstable();
+        nodes
+    }
+
+    /// 模拟脑裂：返回 A、B 两侧协调者各自看到的 `successes`（对侧节点全部故障）。
+    /// 本副本器位于 A 侧并采用 A 侧视角；模拟 B 侧协调者时把返回的第二个视图赋给 `successes`。
+    pub fn split_brain_scenario(
+        &mut self,
+        partition_a: Vec<&str>,
+        partition_b: Vec<&str>,
+    ) -> (HashMap<String, bool>, HashMap<String, bool>) {
+        let view = |reachable: &[&str], unreachable: &[&str]| -> HashMap<String, bool> {
+            reachable
+                .iter()
+                .map(|n| (n.to_string(), true))
+                .chain(unreachable.iter().map(|n| (n.to_string(), false)))
+                .collect()
+        };
+        let side_a = view(&partition_a, &partition_b);
+        let side_b = view(&partition_b, &partition_a);
+        self.successes = side_a.clone();
+        (side_a, side_b)
+    }
+
+    /// 复制到 `targets`；接入脑裂防护时 `targets` 须是防护登记的某个分片的副本集合，
+    /// 按当前时刻经该分片准入
     pub fn replicate_to_nodes<C: Clone>(
+        &mut self,
+        targets: &[String],
+        command: C,
+        level: ConsistencyLevel,
+    ) -> Result<(), DistributedError> {
+        if let Some(guard) = &self.guard {
+            guard.admit_nodes(targets, level, std::time::Instant::now())?;
+        }
+        self.replicate_admitted(targets, command, level)
+    }
+
+    fn replicate_admitted<C: Clone>(
         &mut self,
         targets: &[String],
         _command: C,
         level: ConsistencyLevel,
     ) -> Result<(), DistributedError> {
-        let total = targets.len();
-        let need = MajorityQuorum::required_acks(total, level);
-        let mut acks = 0usize;
+        if self.pending.len() >= self.max_pending_entries {
+            return Err(DistributedError::BackPressure(format!(
+                "{} entries pending replication (max {})",
+                self.pending.len(),
+                self.max_pending_entries
+            )));
+        }
+        let mut acked = Vec::with_capacity(targets.len());
+        let mut missing = HashSet::new();
+        for n in targets {
+            let ok = *self.successes.get(n).unwrap_or(&true);
+            let counts = self.ack_counts.entry(n.clone()).or_insert((0, 0));
+            counts.1 += 1;
+            if ok {
+                counts.0 += 1;
+                acked.push(n.as_str());
+            } else {
+                missing.insert(n.clone());
+            }
+        }
+        self.quorum
+            .check(targets, &acked, level)
+            .map_err(DistributedError::Network)?;
+        if !missing.is_empty() {
+            self.pending.push_back(missing);
+        }
+        Ok(())
+    }
+
+    /// 复制到分片的副本，副本集合取自脑裂防护；分片被隔离时以 `QuorumUnavailable` 立即失败，
+    /// 隔离期间放行的 `Eventual` 写入把未确认的副本记入待补齐队列
+    pub fn replicate_shard<C: Clone>(
+        &mut self,
+        shard: &str,
+        command: C,
+        level: ConsistencyLevel,
+        now: std::time::Instant,
+    ) -> Result<(), DistributedError> {
+        let guard = self.guard.clone().ok_or_else(|| {
+            DistributedError::Configuration("replicate_shard requires a quorum guard".into())
+        })?;
+        guard.admit(shard, level, now)?;
+        let targets = guard.replicas(shard).unwrap_or_default();
+        self.replicate_admitted(&targets, command, level)
+    }
+
+    /// 带会话的写入：成功后分配新的写时间戳，确认的副本推进水位，并记入会话
+    pub fn replicate_with_session<C: Clone>(
+        &mut self,
+        targets: &[String],
+        command: C,
+        level: ConsistencyLevel,
+        session: &mut ClientSession,
+    ) -> Result<u64, DistributedError> {
+        self.replicate_to_nodes(targets, command, level)?;
+        self.write_clock += 1;
+        let ts = self.write_clock;
         for n in targets {
             if *self.successes.get(n).unwrap_or(&true) {
-                acks += 1;
+                self.watermarks.insert(n.clone(), ts);
             }
         }
-        if acks >= need {
-            Ok(())
-        } else {
-            Err(DistributedError::Network(format!("acks {acks}/{need}")))
+        session.record_write(ts);
+        Ok(ts)
+    }
+
+    /// 落后副本追平（如反熵修复）后推进其水位
+    pub fn advance_watermark(&mut self, node: &str, ts: u64) {
+        let w = self.watermarks.entry(node.to_string()).or_insert(0);
+        *w = (*w).max(ts);
+    }
+
+    pub fn watermark(&self, node: &str) -> u64 {
+        self.watermarks.get(node).copied().unwrap_or(0)
+    }
+
+    /// 最近一次带会话写入分配的时间戳
+    pub fn write_clock(&self) -> u64 {
+        self.write_clock
+    }
+
+    /// 副本水位落后最新写时间戳的写入数
+    pub fn lag(&self, node: &str) -> u64 {
+        self.write_clock.saturating_sub(self.watermark(node))
+    }
+
+    /// 副本确认复制请求的比例；尚未向其复制过时为 `None`
+    pub fn ack_rate(&self, node: &str) -> Option<f64> {
+        self.ack_counts
+            .get(node)
+            .filter(|(_, attempts)| *attempts > 0)
+            .map(|(acked, attempts)| *acked as f64 / *attempts as f64)
+    }
+
+    /// 带会话保证的仲裁读：水位低于会话读/写时间戳的副本不参与本次读取（在其追平前视为不可用），
+    /// 可用副本不足仲裁数时返回错误，由调用方稍后重试（`AsyncLocalReplicator::quorum_read`
+    /// 在限定时间内等待落后副本追平）；否则从水位最高的副本读取。
+    /// 仲裁读只在投票者（按 `ring` 上记录的角色）间进行：见证者不保存数据，学习者不计入
+    /// 仲裁，二者都不作为读取目标。
+    pub fn quorum_read<T>(
+        &self,
+        targets: &[String],
+        level: ConsistencyLevel,
+        session: &mut ClientSession,
+        read: impl FnOnce(&str) -> T,
+    ) -> Result<T, DistributedError> {
+        let voters: Vec<&String> = targets
+            .iter()
+            .filter(|n| self.ring.role_of(n) == ReplicaRole::Voter)
+            .collect();
+        let need = MajorityQuorum::required_acks(voters.len(), level);
+        let eligible: Vec<(&String, u64)> = voters
+            .into_iter()
+            .filter(|n| *self.successes.get(*n).unwrap_or(&true))
+            .map(|n| (n, self.watermark(n)))
+            .filter(|(_, w)| session.ensure_readable(*w).is_ok())
+            .collect();
+        let caught_up = eligible.len();
+        let newest = eligible.into_iter().max_by_key(|(_, w)| *w);
+        match newest {
+            Some((node, w)) if caught_up >= need => {
+                session.record_read(w);
+                Ok(read(node))
+            }
+            _ => Err(DistributedError::InvalidState(format!(
+                "replicas caught up with session {caught_up}/{need}"
+            ))),
         }
     }
 
@@ -149,9 +618,414 @@ impl<ID> LocalReplicator<ID> {
     }
 }
 
+impl LocalReplicator<IdempotencyKey> {
+    /// 自动生成幂等键所用的生成器；未设置时首次使用以节点 ID `local` 创建
+    pub fn with_key_generator(mut self, generator: IdempotencyKeyGenerator) -> Self {
+        self.key_generator = Some(generator);
+        self
+    }
+
+    /// 生成新的幂等键并复制到全部节点；返回键以便调用方重试时复用
+    pub fn replicate_auto_idempotent<C: Clone>(
+        &mut self,
+        command: C,
+        level: ConsistencyLevel,
+    ) -> (IdempotencyKey, Result<(), DistributedError>) {
+        let key = self
+            .key_generator
+            .get_or_insert_with(|| IdempotencyKeyGenerator::new("local"))
+            .next_key();
+        let nodes = self.nodes.clone();
+        let res = self.replicate_idempotent(&key, &nodes, command, level);
+        (key, res)
+    }
+}
+
 impl<C: Clone, ID> Replicator<C> for LocalReplicator<ID> {
     fn replicate(&mut self, command: C, level: ConsistencyLevel) -> Result<(), DistributedError> {
         let nodes = self.nodes.clone();
         self.replicate_to_nodes(&nodes, command, level)
     }
 }
+
+// ---------------- 复制延迟监控 ----------------
+
+/// 跟随者落后领导者的提交索引数，超过阈值时告警
+///
+/// 告警按跨越阈值触发：落后超过阈值时回调一次，回落到阈值以内后重新武装，
+/// 避免持续落后的节点在每次更新时重复告警。
+pub struct ReplicationLagMonitor {
+    leader_commit: u64,
+    followers: HashMap<ClusterNodeId, u64>,
+    pub alert_threshold: u64,
+    alert: Option<Box<dyn Fn(ClusterNodeId, u64) + Send>>,
+    alerting: HashSet<ClusterNodeId>,
+}
+
+impl ReplicationLagMonitor {
+    pub fn new(alert_threshold: u64) -> Self {
+        Self {
+            leader_commit: 0,
+            followers: HashMap::new(),
+            alert_threshold,
+            alert: None,
+            alerting: HashSet::new(),
+        }
+    }
+
+    pub fn set_alert_callback(&mut self, f: impl Fn(ClusterNodeId, u64) + Send + 'static) {
+        self.alert = Some(Box::new(f));
+    }
+
+    /// 领导者提交索引前进；只增不减
+    pub fn update_leader_commit(&mut self, index: u64) {
+        self.leader_commit = self.leader_commit.max(index);
+        let nodes: Vec<ClusterNodeId> = self.followers.keys().cloned().collect();
+        for node in nodes {
+            self.check(&node);
+        }
+    }
+
+    /// 跟随者提交索引前进；只增不减，首次上报即开始跟踪该节点
+    pub fn update_follower_commit(&mut self, node: &ClusterNodeId, index: u64) {
+        let commit = self.followers.entry(node.clone()).or_insert(0);
+        *commit = (*commit).max(index);
+        self.check(node);
+    }
+
+    pub fn remove_follower(&mut self, node: &ClusterNodeId) {
+        self.followers.remove(node);
+        self.alerting.remove(node);
+    }
+
+    pub fn leader_commit(&self) -> u64 {
+        self.leader_commit
+    }
+
+    /// 未上报过的节点视为提交索引 0
+    pub fn lag(&self, node: &ClusterNodeId) -> u64 {
+        let commit = self.followers.get(node).copied().unwrap_or(0);
+        self.leader_commit.saturating_sub(commit)
+    }
+
+    pub fn max_lag(&self) -> u64 {
+        self.followers
+            .keys()
+            .map(|n| self.lag(n))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn check(&mut self, node: &ClusterNodeId) {
+        let lag = self.lag(node);
+        if lag <= self.alert_threshold {
+            self.alerting.remove(node);
+            return;
+        }
+        if self.alerting.insert(node.clone())
+            && let Some(alert) = &self.alert
+        {
+            alert(node.clone(), lag);
+        }
+    }
+}
+
+// ---------------- 远程复制（跨网络传输，需 runtime-tokio） ----------------
+
+#[cfg(feature = "runtime-tokio")]
+pub use remote::{RemoteReplicator, ReplicateAck, ReplicateRequest, ReplicationTransport};
+
+#[cfg(feature = "runtime-tokio")]
+mod remote {
+    use super::{MajorityQuorum, Priority, WriteQuorum};
+    use crate::consistency::ConsistencyLevel;
+    use crate::core::errors::DistributedError;
+    use crate::network::Deadline;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// 发往单个节点的复制请求
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ReplicateRequest {
+        pub idempotency_key: String,
+        pub payload: Vec<u8>,
+        pub level: ConsistencyLevel,
+        pub priority: Priority,
+    }
+
+    /// 单个节点的确认；发送失败的节点也以 `applied = false` 的确认表示
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ReplicateAck {
+        pub applied: bool,
+        pub node_id: String,
+        pub error: Option<String>,
+    }
+
+    /// 复制传输：把请求送达指定节点并在截止时间前返回确认
+    pub trait ReplicationTransport: Send + Sync + 'static {
+        fn send(
+            &self,
+            node: &str,
+            request: ReplicateRequest,
+            deadline: Deadline,
+        ) -> impl Future<Output = Result<ReplicateAck, DistributedError>> + Send;
+    }
+
+    /// 经由传输层向全部节点并发复制，按 `MajorityQuorum` 判定成功
+    pub struct RemoteReplicator<T> {
+        transport: Arc<T>,
+        nodes: Vec<String>,
+        timeout: Duration,
+        quorum: Arc<dyn WriteQuorum>,
+    }
+
+    impl<T: ReplicationTransport> RemoteReplicator<T> {
+        pub fn new(transport: T, nodes: Vec<String>) -> Self {
+            Self {
+                transport: Arc::new(transport),
+                nodes,
+                timeout: Duration::from_secs(5),
+                quorum: Arc::new(MajorityQuorum),
+            }
+        }
+
+        /// 写仲裁策略，缺省为 `MajorityQuorum`
+        pub fn with_quorum(mut self, quorum: Arc<dyn WriteQuorum>) -> Self {
+            self.quorum = quorum;
+            self
+        }
+
+        /// 每次复制的总时间预算，所有节点共享同一截止时间
+        pub fn with_timeout(mut self, timeout: Duration) -> Self {
+            self.timeout = timeout;
+            self
+        }
+
+        pub fn nodes(&self) -> &[String] {
+            &self.nodes
+        }
+
+        pub fn transport(&self) -> &T {
+            &self.transport
+        }
+
+        /// 以 `Priority::Normal` 复制，见 `replicate_with_priority`
+        pub async fn replicate(
+            &self,
+            idempotency_key: &str,
+            payload: Vec<u8>,
+            level: ConsistencyLevel,
+        ) -> Result<Vec<ReplicateAck>, DistributedError> {
+            self.replicate_with_priority(idempotency_key, payload, level, Priority::Normal)
+                .await
+        }
+
+        /// 并发发送到全部节点；`applied` 的确认满足写仲裁策略时成功，
+        /// 返回按节点顺序排列的全部确认。优先级随请求交给传输层
+        pub async fn replicate_with_priority(
+            &self,
+            idempotency_key: &str,
+            payload: Vec<u8>,
+            level: ConsistencyLevel,
+            priority: Priority,
+        ) -> Result<Vec<ReplicateAck>, DistributedError> {
+            let deadline = Deadline::after(self.timeout);
+            let mut tasks = tokio::task::JoinSet::new();
+            for node in &self.nodes {
+                let transport = self.transport.clone();
+                let node = node.clone();
+                let request = ReplicateRequest {
+                    idempotency_key: idempotency_key.to_string(),
+                    payload: payload.clone(),
+                    level,
+                    priority,
+                };
+                tasks.spawn(async move {
+                    match transport.send(&node, request, deadline).await {
+                        Ok(ack) => ack,
+                        Err(e) => ReplicateAck {
+                            applied: false,
+                            node_id: node,
+                            error: Some(e.to_string()),
+                        },
+                    }
+                });
+            }
+
+            let mut acks = Vec::with_capacity(self.nodes.len());
+            while let Some(joined) = tasks.join_next().await {
+                if let Ok(ack) = joined {
+                    acks.push(ack);
+                }
+            }
+            acks.sort_by_key(|a| self.nodes.iter().position(|n| *n == a.node_id));
+
+            let applied: Vec<&str> = acks
+                .iter()
+                .filter(|a| a.applied)
+                .map(|a| a.node_id.as_str())
+                .collect();
+            self.quorum
+                .check(&self.nodes, &applied, level)
+                .map_err(DistributedError::Network)?;
+            Ok(acks)
+        }
+    }
+}
+
+// ---------------- 异步背压（挂起调用方，需 runtime-tokio） ----------------
+
+#[cfg(feature = "runtime-tokio")]
+pub use backpressure::AsyncLocalReplicator;
+
+#[cfg(feature = "runtime-tokio")]
+mod backpressure {
+    use super::LocalReplicator;
+    use crate::consistency::ConsistencyLevel;
+    use crate::core::errors::DistributedError;
+    use crate::core::session::ClientSession;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::{Notify, Semaphore};
+
+    /// `LocalReplicator` 的异步包装：待确认条目达到上限时挂起调用方而不是返回 `BackPressure`
+    ///
+    /// 每个待确认条目占用一个许可；落后副本经 `ack_pending` 确认后归还许可，唤醒等待者。
+    /// 副本水位推进或节点恢复时唤醒等待会话水位的仲裁读。
+    pub struct AsyncLocalReplicator<ID> {
+        inner: Mutex<LocalReplicator<ID>>,
+        permits: Arc<Semaphore>,
+        caught_up: Notify,
+    }
+
+    impl<ID> AsyncLocalReplicator<ID> {
+        /// 许可数取自 `replicator` 的 `max_pending_entries`
+        pub fn new(replicator: LocalReplicator<ID>) -> Self {
+            let permits = replicator
+                .max_pending_entries()
+                .saturating_sub(replicator.pending_count());
+            Self {
+                inner: Mutex::new(replicator),
+                permits: Arc::new(Semaphore::new(permits)),
+                caught_up: Notify::new(),
+            }
+        }
+
+        /// 复制到全部节点；待确认条目已满时等待许可。全部副本立即确认的条目不占用许可
+        pub async fn replicate<C: Clone>(
+            &self,
+            command: C,
+            level: ConsistencyLevel,
+        ) -> Result<(), DistributedError> {
+            let permit = self
+                .permits
+                .acquire()
+                .await
+                .map_err(|_| DistributedError::InvalidState("replicator closed".into()))?;
+            let mut inner = self.inner.lock().unwrap();
+            let before = inner.pending_count();
+            let nodes = inner.nodes.clone();
+            inner.replicate_to_nodes(&nodes, command, level)?;
+            if inner.pending_count() > before {
+                permit.forget();
+            }
+            Ok(())
+        }
+
+        /// 带会话的写入（见 `LocalReplicator::replicate_with_session`）；待确认条目已满时等待许可
+        pub async fn replicate_with_session<C: Clone>(
+            &self,
+            targets: &[String],
+            command: C,
+            level: ConsistencyLevel,
+            session: &mut ClientSession,
+        ) -> Result<u64, DistributedError> {
+            let permit = self
+                .permits
+                .acquire()
+                .await
+                .map_err(|_| DistributedError::InvalidState("replicator closed".into()))?;
+            let ts = {
+                let mut inner = self.inner.lock().unwrap();
+                let before = inner.pending_count();
+                let ts = inner.replicate_with_session(targets, command, level, session)?;
+                if inner.pending_count() > before {
+                    permit.forget();
+                }
+                ts
+            };
+            self.caught_up.notify_waiters();
+            Ok(ts)
+        }
+
+        /// 落后副本追平后推进其水位，唤醒等待中的仲裁读
+        pub fn advance_watermark(&self, node: &str, ts: u64) {
+            self.inner.lock().unwrap().advance_watermark(node, ts);
+            self.caught_up.notify_waiters();
+        }
+
+        pub fn watermark(&self, node: &str) -> u64 {
+            self.inner.lock().unwrap().watermark(node)
+        }
+
+        /// 带会话保证的仲裁读（见 `LocalReplicator::quorum_read`）：追平会话的副本不足仲裁数时
+        /// 至多等待 `max_wait`，期间每次水位推进或节点恢复都重试；超时返回最后一次的错误。
+        /// `read` 在锁外对选中的副本调用
+        pub async fn quorum_read<T>(
+            &self,
+            targets: &[String],
+            level: ConsistencyLevel,
+            session: &mut ClientSession,
+            max_wait: Duration,
+            read: impl FnOnce(&str) -> T,
+        ) -> Result<T, DistributedError> {
+            let deadline = tokio::time::Instant::now() + max_wait;
+            loop {
+                // 先登记唤醒再检查，检查与等待之间推进的水位不会被错过
+                let mut notified = std::pin::pin!(self.caught_up.notified());
+                notified.as_mut().enable();
+                let attempt = self.inner.lock().unwrap().quorum_read(
+                    targets,
+                    level,
+                    session,
+                    str::to_string,
+                );
+                let err = match attempt {
+                    Ok(node) => return Ok(read(&node)),
+                    Err(err) => err,
+                };
+                if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                    return Err(err);
+                }
+            }
+        }
+
+        /// 标记节点故障：此后复制到该节点的条目进入待确认队列并占用许可
+        pub fn mark_node_failed(&self, node: &str) {
+            self.inner.lock().unwrap().mark_node_failed(node);
+        }
+
+        /// 标记节点恢复；恢复前欠下的条目仍需经 `ack_pending` 确认才归还许可
+        pub fn mark_node_recovered(&self, node: &str) {
+            self.inner.lock().unwrap().mark_node_recovered(node);
+            self.caught_up.notify_waiters();
+        }
+
+        /// 落后副本追上后确认其欠下的条目，归还完成条目的许可；返回完成的条目数
+        pub fn ack_pending(&self, node: &str) -> usize {
+            let completed = self.inner.lock().unwrap().ack_pending(node);
+            self.permits.add_permits(completed);
+            completed
+        }
+
+        pub fn pending_count(&self) -> usize {
+            self.inner.lock().unwrap().pending_count()
+        }
+
+        /// 可用许可数，即不必等待即可新增的待确认条目数
+        pub fn available_permits(&self) -> usize {
+            self.permits.available_permits()
+        }
+    }
+}
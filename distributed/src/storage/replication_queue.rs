@@ -0,0 +1,405 @@
+//! 带背压的复制提交队列
+//!
+//! 设计意图：
+//! - 写入突发快于对端确认时，`RemoteReplicator` 前的有界队列限制积压；固定数量的工作
+//!   任务按提交顺序取出条目并复制。
+//! - 队列满时按 `ShedPolicy` 处理新提交：
+//!   - `RejectNew`：立即以 `DistributedError::Overloaded` 拒绝；
+//!   - `DropOldestEventual`：丢弃队列中最旧的 `Eventual` 条目腾出位置（其票据得到
+//!     `Overloaded`），队列中没有 `Eventual` 条目时拒绝新提交；
+//!   - `BlockWithDeadline(d)`：等待空位，至多 `d`，超时以 `Overloaded` 拒绝。
+//! - `submit` 返回 `ReplicationTicket`，`wait` 得到复制结果；被丢弃的条目同样经票据报告。
+//! - 优先级：每个 `Priority` 一条队列，工作任务总是先取较高类别，使控制面命令不会排在
+//!   批量复制之后。为防 `Bulk` 饿死，`Bulk` 有条目等待时，每连续取出 `bulk_quota` 条
+//!   较高类别的条目后必取一条 `Bulk`。`DropOldestEventual` 先从最低类别找可丢弃条目。
+//!
+//! 不变量（草图）：
+//! - 排队条目数（各类别合计）不超过容量。
+//! - 非 `Eventual` 写入不会被丢弃：要么入队并得到复制结果，要么在 `submit` 时被显式拒绝。
+//! - 同一类别内按提交顺序取出。
+
+use crate::consistency::ConsistencyLevel;
+use crate::core::errors::DistributedError;
+use crate::storage::replication::{Priority, RemoteReplicator, ReplicateAck, ReplicationTransport};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Notify, oneshot};
+
+type ReplicateResult = Result<Vec<ReplicateAck>, DistributedError>;
+
+/// 队列满时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShedPolicy {
+    RejectNew,
+    DropOldestEventual,
+    BlockWithDeadline(Duration),
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplicationQueueConfig {
+    pub capacity: usize,
+    pub policy: ShedPolicy,
+    /// 并发复制的工作任务数
+    pub concurrency: usize,
+    /// `Bulk` 等待时，两次取出 `Bulk` 之间至多取出的较高类别条目数
+    pub bulk_quota: usize,
+}
+
+impl Default for ReplicationQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            policy: ShedPolicy::RejectNew,
+            concurrency: 4,
+            bulk_quota: 8,
+        }
+    }
+}
+
+impl ReplicationQueueConfig {
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: ShedPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_bulk_quota(mut self, bulk_quota: usize) -> Self {
+        self.bulk_quota = bulk_quota;
+        self
+    }
+}
+
+/// 单个优先级类别的指标
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PriorityStats {
+    pub depth: usize,
+    /// 已取得复制结果（成功或失败）的条目
+    pub completed: u64,
+    /// 被拒绝、丢弃或超时的提交
+    pub shed: u64,
+}
+
+/// 队列指标快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplicationQueueStats {
+    /// 排队中（尚未被工作任务取出）的条目数
+    pub depth: usize,
+    /// 队列满时被拒绝的提交（`RejectNew`，或 `DropOldestEventual` 无可丢弃条目）
+    pub rejected: u64,
+    /// 为腾出位置被丢弃的 `Eventual` 条目
+    pub dropped: u64,
+    /// 阻塞等待超过截止时间的提交
+    pub timed_out: u64,
+    /// 按 `Priority::index` 排列的各类别指标
+    pub classes: [PriorityStats; 4],
+}
+
+impl ReplicationQueueStats {
+    /// 被卸载的写入总数
+    pub fn shed(&self) -> u64 {
+        self.rejected + self.dropped + self.timed_out
+    }
+
+    pub fn class(&self, priority: Priority) -> &PriorityStats {
+        &self.classes[priority.index()]
+    }
+}
+
+struct Pending {
+    idempotency_key: String,
+    payload: Vec<u8>,
+    level: ConsistencyLevel,
+    priority: Priority,
+    reply: oneshot::Sender<ReplicateResult>,
+}
+
+/// 按类别分开的排队条目
+#[derive(Default)]
+struct Lanes {
+    lanes: [VecDeque<Pending>; 4],
+    len: usize,
+    /// `Bulk` 等待期间连续取出的较高类别条目数
+    since_bulk: usize,
+}
+
+impl Lanes {
+    fn push(&mut self, pending: Pending) {
+        self.lanes[pending.priority.index()].push_back(pending);
+        self.len += 1;
+    }
+
+    fn pop(&mut self, bulk_quota: usize) -> Option<Pending> {
+        let bulk = Priority::Bulk.index();
+        let bulk_waiting = !self.lanes[bulk].is_empty();
+        let lane = if bulk_waiting && self.since_bulk >= bulk_quota {
+            bulk
+        } else {
+            self.lanes.iter().position(|lane| !lane.is_empty())?
+        };
+        if lane == bulk {
+            self.since_bulk = 0;
+        } else if bulk_waiting {
+            self.since_bulk += 1;
+        }
+        self.len -= 1;
+        self.lanes[lane].pop_front()
+    }
+
+    /// 从最低类别起移除最旧的 `Eventual` 条目
+    fn remove_oldest_eventual(&mut self) -> Option<Pending> {
+        let lane = self.lanes.iter_mut().rev().find_map(|lane| {
+            let index = lane
+                .iter()
+                .position(|p| p.level == ConsistencyLevel::Eventual)?;
+            lane.remove(index)
+        })?;
+        self.len -= 1;
+        Some(lane)
+    }
+}
+
+#[derive(Default)]
+struct ClassCounters {
+    completed: AtomicU64,
+    shed: AtomicU64,
+}
+
+struct Shared {
+    queue: Mutex<Lanes>,
+    capacity: usize,
+    bulk_quota: usize,
+    /// 有新条目或队列关闭
+    available: Notify,
+    /// 工作任务取出条目后有空位
+    space: Notify,
+    closed: AtomicBool,
+    depth: AtomicUsize,
+    rejected: AtomicU64,
+    dropped: AtomicU64,
+    timed_out: AtomicU64,
+    classes: [ClassCounters; 4],
+}
+
+impl Shared {
+    fn pop(&self) -> Option<Pending> {
+        let mut queue = self.queue.lock().unwrap();
+        let pending = queue.pop(self.bulk_quota);
+        self.depth.store(queue.len, Ordering::Relaxed);
+        pending
+    }
+
+    fn shed(&self, priority: Priority, counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.classes[priority.index()]
+            .shed
+            .fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 复制结果的票据
+pub struct ReplicationTicket {
+    reply: oneshot::Receiver<ReplicateResult>,
+}
+
+impl ReplicationTicket {
+    pub async fn wait(self) -> ReplicateResult {
+        self.reply.await.unwrap_or_else(|_| {
+            Err(DistributedError::InvalidState(
+                "replication queue closed".to_string(),
+            ))
+        })
+    }
+}
+
+/// `RemoteReplicator` 前的有界提交队列；丢弃后工作任务处理完已排队的条目即退出
+pub struct ReplicationQueue {
+    shared: Arc<Shared>,
+    policy: ShedPolicy,
+}
+
+impl ReplicationQueue {
+    /// 启动工作任务；须在 tokio 运行时内调用
+    pub fn start<T: ReplicationTransport>(
+        replicator: RemoteReplicator<T>,
+        config: ReplicationQueueConfig,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Lanes::default()),
+            capacity: config.capacity.max(1),
+            bulk_quota: config.bulk_quota,
+            available: Notify::new(),
+            space: Notify::new(),
+            closed: AtomicBool::new(false),
+            depth: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            timed_out: AtomicU64::new(0),
+            classes: Default::default(),
+        });
+        let replicator = Arc::new(replicator);
+        for _ in 0..config.concurrency.max(1) {
+            tokio::spawn(worker(shared.clone(), replicator.clone()));
+        }
+        Self {
+            shared,
+            policy: config.policy,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.shared.depth.load(Ordering::Relaxed)
+    }
+
+    pub fn stats(&self) -> ReplicationQueueStats {
+        let mut classes = [PriorityStats::default(); 4];
+        {
+            let queue = self.shared.queue.lock().unwrap();
+            for (stats, lane) in classes.iter_mut().zip(&queue.lanes) {
+                stats.depth = lane.len();
+            }
+        }
+        for (stats, counters) in classes.iter_mut().zip(&self.shared.classes) {
+            stats.completed = counters.completed.load(Ordering::Relaxed);
+            stats.shed = counters.shed.load(Ordering::Relaxed);
+        }
+        ReplicationQueueStats {
+            depth: self.depth(),
+            rejected: self.shared.rejected.load(Ordering::Relaxed),
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+            timed_out: self.shared.timed_out.load(Ordering::Relaxed),
+            classes,
+        }
+    }
+
+    /// 以 `Priority::Normal` 提交，见 `submit_with_priority`
+    pub async fn submit(
+        &self,
+        idempotency_key: impl Into<String>,
+        payload: Vec<u8>,
+        level: ConsistencyLevel,
+    ) -> Result<ReplicationTicket, DistributedError> {
+        self.submit_with_priority(idempotency_key, payload, level, Priority::Normal)
+            .await
+    }
+
+    /// 提交一次复制；队列满时按策略拒绝、腾位或等待
+    pub async fn submit_with_priority(
+        &self,
+        idempotency_key: impl Into<String>,
+        payload: Vec<u8>,
+        level: ConsistencyLevel,
+        priority: Priority,
+    ) -> Result<ReplicationTicket, DistributedError> {
+        let (reply, receiver) = oneshot::channel();
+        let mut pending = Pending {
+            idempotency_key: idempotency_key.into(),
+            payload,
+            level,
+            priority,
+            reply,
+        };
+        let wait = match self.policy {
+            ShedPolicy::BlockWithDeadline(wait) => wait,
+            _ => Duration::ZERO,
+        };
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let space = self.shared.space.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+            pending = match self.try_push(pending) {
+                Ok(()) => {
+                    return Ok(ReplicationTicket { reply: receiver });
+                }
+                Err(pending) => pending,
+            };
+            match self.policy {
+                ShedPolicy::RejectNew | ShedPolicy::DropOldestEventual => {
+                    self.shared.shed(priority, &self.shared.rejected);
+                    return Err(overloaded("replication queue full"));
+                }
+                ShedPolicy::BlockWithDeadline(_) => {
+                    if tokio::time::timeout_at(deadline, space).await.is_err() {
+                        self.shared.shed(priority, &self.shared.timed_out);
+                        return Err(overloaded("replication queue full past deadline"));
+                    }
+                }
+            }
+        }
+    }
+
+    fn try_push(&self, pending: Pending) -> Result<(), Pending> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.len >= self.shared.capacity {
+            let oldest_eventual = match self.policy {
+                ShedPolicy::DropOldestEventual => queue.remove_oldest_eventual(),
+                _ => None,
+            };
+            let Some(dropped) = oldest_eventual else {
+                return Err(pending);
+            };
+            self.shared.shed(dropped.priority, &self.shared.dropped);
+            let _ = dropped
+                .reply
+                .send(Err(overloaded("dropped to relieve backpressure")));
+        }
+        queue.push(pending);
+        self.shared.depth.store(queue.len, Ordering::Relaxed);
+        drop(queue);
+        self.shared.available.notify_one();
+        Ok(())
+    }
+}
+
+impl Drop for ReplicationQueue {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.available.notify_waiters();
+    }
+}
+
+fn overloaded(reason: &str) -> DistributedError {
+    DistributedError::Overloaded(reason.to_string())
+}
+
+async fn worker<T: ReplicationTransport>(
+    shared: Arc<Shared>,
+    replicator: Arc<RemoteReplicator<T>>,
+) {
+    loop {
+        let available = shared.available.notified();
+        tokio::pin!(available);
+        available.as_mut().enable();
+        if let Some(pending) = shared.pop() {
+            shared.space.notify_waiters();
+            let result = replicator
+                .replicate_with_priority(
+                    &pending.idempotency_key,
+                    pending.payload,
+                    pending.level,
+                    pending.priority,
+                )
+                .await;
+            shared.classes[pending.priority.index()]
+                .completed
+                .fetch_add(1, Ordering::Relaxed);
+            let _ = pending.reply.send(result);
+            continue;
+        }
+        if shared.closed.load(Ordering::Acquire) {
+            return;
+        }
+        available.await;
+    }
+}
@@ -0,0 +1,343 @@
+//! 带版本的键值存储与比较并交换（CAS）
+//!
+//! 设计意图：
+//! - 复制 KV、租约管理等需要乐观并发：读取得到 `(value, version)`，写入携带期望版本，
+//!   版本不符时以 `CasError::Conflict { actual }` 拒绝并报告当前版本，调用方据此重读重试。
+//! - 版本取自存储级单调递增的修订号（每次写入或删除消耗一个），而非按键计数：
+//!   删除后重建的键得到更高的版本，旧版本的 CAS 不会误中（避免 ABA）。
+//! - `InMemoryVersionedStore`：`RwLock` 保护的 `BTreeMap`，读并发、写串行。
+//! - `FileVersionedStore`：同一内存结构之上叠加 WAL（`FileLogStorage`）与周期快照
+//!   （`FileSnapshot`）。写入先追加 WAL 再生效；每 `snapshot_every` 条记录保存一次快照
+//!   并截断 WAL。打开时加载快照并重放 WAL。
+//!
+//! 不变量（草图）：
+//! - 同一键的版本严格递增；成功的 CAS 返回的版本即随后 `get` 读到的版本。
+//! - WAL 记录携带结果版本，重放是幂等的：快照保存后、截断 WAL 前崩溃，重放不会改变状态。
+
+use crate::codec::JsonCodec;
+use crate::core::errors::DistributedError;
+use crate::storage::{FileLogStorage, FileSnapshot, LogStorage, SnapshotStorage};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
+use std::path::Path;
+use std::sync::RwLock;
+use thiserror::Error;
+
+/// 存储级修订号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Version(pub u64);
+
+#[derive(Debug, Error)]
+pub enum CasError {
+    /// 期望版本与当前版本不符；`actual` 为当前版本，`None` 表示键不存在
+    #[error("version conflict (actual: {actual:?})")]
+    Conflict { actual: Option<Version> },
+    #[error(transparent)]
+    Storage(#[from] DistributedError),
+}
+
+impl CasError {
+    /// 冲突时的当前版本
+    pub fn actual(&self) -> Option<Version> {
+        match self {
+            CasError::Conflict { actual } => *actual,
+            CasError::Storage(_) => None,
+        }
+    }
+}
+
+pub trait VersionedStore<K, V> {
+    fn get(&self, key: &K) -> Option<(V, Version)>;
+    /// 当前版本等于 `expected` 时写入（`None` 要求键不存在），返回新版本
+    fn put_if_version(
+        &self,
+        key: K,
+        value: V,
+        expected: Option<Version>,
+    ) -> Result<Version, CasError>;
+    /// 当前版本等于 `expected` 时删除
+    fn delete_if_version(&self, key: &K, expected: Version) -> Result<(), CasError>;
+    /// 按键升序返回范围内的条目
+    fn scan<R: RangeBounds<K>>(&self, range: R) -> Vec<(K, V, Version)>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum WalRecord<K, V> {
+    Put { key: K, value: V, version: Version },
+    Delete { key: K, version: Version },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotState<K, V> {
+    revision: u64,
+    entries: Vec<(K, V, Version)>,
+}
+
+#[derive(Debug)]
+struct VersionedMap<K, V> {
+    revision: u64,
+    entries: BTreeMap<K, (V, Version)>,
+}
+
+impl<K: Ord + Clone, V: Clone> VersionedMap<K, V> {
+    fn new() -> Self {
+        Self {
+            revision: 0,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<(V, Version)> {
+        self.entries.get(key).cloned()
+    }
+
+    fn check(&self, key: &K, expected: Option<Version>) -> Result<Version, CasError> {
+        let actual = self.entries.get(key).map(|(_, version)| *version);
+        if actual != expected {
+            return Err(CasError::Conflict { actual });
+        }
+        Ok(Version(self.revision + 1))
+    }
+
+    fn apply(&mut self, record: WalRecord<K, V>) {
+        match record {
+            WalRecord::Put {
+                key,
+                value,
+                version,
+            } => {
+                self.revision = self.revision.max(version.0);
+                self.entries.insert(key, (value, version));
+            }
+            WalRecord::Delete { key, version } => {
+                self.revision = self.revision.max(version.0);
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    fn scan<R: RangeBounds<K>>(&self, range: R) -> Vec<(K, V, Version)> {
+        self.entries
+            .range(range)
+            .map(|(k, (v, version))| (k.clone(), v.clone(), *version))
+            .collect()
+    }
+
+    fn to_snapshot(&self) -> SnapshotState<K, V> {
+        SnapshotState {
+            revision: self.revision,
+            entries: self.scan(..),
+        }
+    }
+
+    fn from_snapshot(snapshot: SnapshotState<K, V>) -> Self {
+        Self {
+            revision: snapshot.revision,
+            entries: snapshot
+                .entries
+                .into_iter()
+                .map(|(k, v, version)| (k, (v, version)))
+                .collect(),
+        }
+    }
+}
+
+/// 内存实现
+pub struct InMemoryVersionedStore<K, V> {
+    map: RwLock<VersionedMap<K, V>>,
+}
+
+impl<K: Ord + Clone, V: Clone> Default for InMemoryVersionedStore<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> InMemoryVersionedStore<K, V> {
+    pub fn new() -> Self {
+        Self {
+            map: RwLock::new(VersionedMap::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.read().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> VersionedStore<K, V> for InMemoryVersionedStore<K, V> {
+    fn get(&self, key: &K) -> Option<(V, Version)> {
+        self.map.read().unwrap().get(key)
+    }
+
+    fn put_if_version(
+        &self,
+        key: K,
+        value: V,
+        expected: Option<Version>,
+    ) -> Result<Version, CasError> {
+        let mut map = self.map.write().unwrap();
+        let version = map.check(&key, expected)?;
+        map.apply(WalRecord::Put {
+            key,
+            value,
+            version,
+        });
+        Ok(version)
+    }
+
+    fn delete_if_version(&self, key: &K, expected: Version) -> Result<(), CasError> {
+        let mut map = self.map.write().unwrap();
+        let version = map.check(key, Some(expected))?;
+        map.apply(WalRecord::Delete {
+            key: key.clone(),
+            version,
+        });
+        Ok(())
+    }
+
+    fn scan<R: RangeBounds<K>>(&self, range: R) -> Vec<(K, V, Version)> {
+        self.map.read().unwrap().scan(range)
+    }
+}
+
+/// 默认每 1000 条 WAL 记录保存一次快照
+pub const DEFAULT_SNAPSHOT_EVERY: usize = 1000;
+
+struct FileState<K, V>
+where
+    K: Ord + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    map: VersionedMap<K, V>,
+    wal: FileLogStorage<JsonCodec, WalRecord<K, V>>,
+    snapshot: FileSnapshot<JsonCodec, SnapshotState<K, V>>,
+    since_snapshot: usize,
+    snapshot_every: usize,
+}
+
+impl<K, V> FileState<K, V>
+where
+    K: Ord + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    fn write(&mut self, record: WalRecord<K, V>) -> Result<(), CasError> {
+        self.wal.append(record.clone())?;
+        self.map.apply(record);
+        self.since_snapshot += 1;
+        if self.since_snapshot >= self.snapshot_every {
+            // 写入已持久化于 WAL；快照失败只意味着 WAL 暂不截断，下次写入再试
+            let _ = self.compact();
+        }
+        Ok(())
+    }
+
+    fn compact(&mut self) -> Result<(), DistributedError> {
+        self.snapshot.save_snapshot(&self.map.to_snapshot())?;
+        self.wal.truncate()?;
+        self.since_snapshot = 0;
+        Ok(())
+    }
+}
+
+/// WAL + 周期快照的持久化实现；目录下保存 `wal` 与 `snapshot` 两个文件
+pub struct FileVersionedStore<K, V>
+where
+    K: Ord + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    state: RwLock<FileState<K, V>>,
+}
+
+impl<K, V> FileVersionedStore<K, V>
+where
+    K: Ord + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// 打开（或创建）目录中的存储：加载快照并重放 WAL
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, DistributedError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(|e| DistributedError::Storage(e.to_string()))?;
+        let snapshot = FileSnapshot::new(dir.join("snapshot"), JsonCodec);
+        let wal = FileLogStorage::new(dir.join("wal"), JsonCodec);
+
+        let mut map = snapshot
+            .load_snapshot()?
+            .map(VersionedMap::from_snapshot)
+            .unwrap_or_else(VersionedMap::new);
+        let records = wal.read_all()?;
+        let since_snapshot = records.len();
+        for record in records {
+            map.apply(record);
+        }
+        Ok(Self {
+            state: RwLock::new(FileState {
+                map,
+                wal,
+                snapshot,
+                since_snapshot,
+                snapshot_every: DEFAULT_SNAPSHOT_EVERY,
+            }),
+        })
+    }
+
+    pub fn with_snapshot_every(self, snapshot_every: usize) -> Self {
+        self.state.write().unwrap().snapshot_every = snapshot_every.max(1);
+        self
+    }
+
+    /// 立即保存快照并截断 WAL
+    pub fn snapshot(&self) -> Result<(), DistributedError> {
+        self.state.write().unwrap().compact()
+    }
+
+    /// 自上次快照以来的 WAL 记录数
+    pub fn wal_len(&self) -> usize {
+        self.state.read().unwrap().since_snapshot
+    }
+}
+
+impl<K, V> VersionedStore<K, V> for FileVersionedStore<K, V>
+where
+    K: Ord + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    fn get(&self, key: &K) -> Option<(V, Version)> {
+        self.state.read().unwrap().map.get(key)
+    }
+
+    fn put_if_version(
+        &self,
+        key: K,
+        value: V,
+        expected: Option<Version>,
+    ) -> Result<Version, CasError> {
+        let mut state = self.state.write().unwrap();
+        let version = state.map.check(&key, expected)?;
+        state.write(WalRecord::Put {
+            key,
+            value,
+            version,
+        })?;
+        Ok(version)
+    }
+
+    fn delete_if_version(&self, key: &K, expected: Version) -> Result<(), CasError> {
+        let mut state = self.state.write().unwrap();
+        let version = state.map.check(key, Some(expected))?;
+        state.write(WalRecord::Delete {
+            key: key.clone(),
+            version,
+        })
+    }
+
+    fn scan<R: RangeBounds<K>>(&self, range: R) -> Vec<(K, V, Version)> {
+        self.state.read().unwrap().map.scan(range)
+    }
+}
@@ -0,0 +1,414 @@
+//! 分段预写日志（WAL）
+//!
+//! 设计意图：
+//! - 记录按递增序号追加到当前活跃段；段写满 `segment_entries` 条后封存，新记录进入下一段。
+//!   帧格式为 `[len u64 LE][seq u64 LE][crc32 u32 LE][payload]`（CRC 覆盖序号与负载），
+//!   文件名为 `{首序号:020}-{代:06}.seg`。
+//! - 压缩（`compact`）只读写已封存的段：把其中仍然存活的记录（不低于截断水位，且配置了
+//!   `with_key` 时为该键的最新版本）改写进新一代的段，再删除旧段。活跃段不参与压缩，
+//!   因此追加不必等待改写完成的文件。
+//! - 新段先写临时文件再改名，旧段在新段就位后才删除；任意时刻崩溃，恢复时按序号去重即可
+//!   得到同一组存活记录。截断水位写入 `TRUNCATED` 文件，恢复后仍然生效。
+//!
+//! 不变量（草图）：
+//! - 序号严格递增且不复用；压缩不改变任何存活记录的序号与内容。
+//! - `read_live` 在压缩前后返回相同的结果（被截断或被覆盖的版本除外）。
+
+use crate::codec::{BinaryCodec, crc32};
+use crate::core::errors::DistributedError;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// 每段默认的记录数
+pub const DEFAULT_SEGMENT_ENTRIES: usize = 1024;
+
+const FRAME_HEADER: usize = 20;
+pub(crate) const WATERMARK_FILE: &str = "TRUNCATED";
+
+#[derive(Debug, Clone)]
+struct Segment {
+    path: PathBuf,
+    generation: u64,
+    entries: usize,
+    bytes: u64,
+}
+
+/// 一次压缩回收的空间
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reclaimed {
+    pub bytes: u64,
+    pub entries: u64,
+}
+
+type KeyFn<E> = Box<dyn Fn(&E) -> String + Send + Sync>;
+
+pub struct SegmentedWal<E, C> {
+    dir: PathBuf,
+    codec: C,
+    segment_entries: usize,
+    key: Option<KeyFn<E>>,
+    /// 按首序号排序；最后一段为活跃段
+    segments: Vec<Segment>,
+    active: Option<File>,
+    next_seq: u64,
+    truncated_before: u64,
+    generation: u64,
+    _entry: PhantomData<fn() -> E>,
+}
+
+impl<E, C: BinaryCodec<E>> SegmentedWal<E, C> {
+    /// 构造后须调用 `open` 恢复目录中已有的段
+    pub fn new(dir: impl AsRef<Path>, codec: C) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            codec,
+            segment_entries: DEFAULT_SEGMENT_ENTRIES,
+            key: None,
+            segments: Vec::new(),
+            active: None,
+            next_seq: 1,
+            truncated_before: 0,
+            generation: 0,
+            _entry: PhantomData,
+        }
+    }
+
+    pub fn with_segment_entries(mut self, entries: usize) -> Self {
+        self.segment_entries = entries.max(1);
+        self
+    }
+
+    /// 记录的键：压缩时同一键只保留序号最大的版本
+    pub fn with_key(mut self, key: impl Fn(&E) -> String + Send + Sync + 'static) -> Self {
+        self.key = Some(Box::new(key));
+        self
+    }
+
+    /// 创建目录并恢复已有的段与截断水位；重复的序号（压缩中途崩溃留下的）只计一次
+    pub fn open(mut self) -> Result<Self, DistributedError> {
+        fs::create_dir_all(&self.dir).map_err(storage_err)?;
+        if let Ok(raw) = fs::read_to_string(self.dir.join(WATERMARK_FILE)) {
+            self.truncated_before = raw.trim().parse().map_err(|_| {
+                DistributedError::Storage(format!("corrupt {WATERMARK_FILE} file: {raw:?}"))
+            })?;
+        }
+        let mut found = Vec::new();
+        for dirent in fs::read_dir(&self.dir).map_err(storage_err)? {
+            let path = dirent.map_err(storage_err)?.path();
+            if let Some((first_seq, generation)) = parse_segment_name(&path) {
+                found.push((first_seq, generation, path));
+            } else if path.extension().is_some_and(|ext| ext == "tmp") {
+                // 压缩写到一半的临时文件，对应的旧段仍然完整
+                fs::remove_file(&path).map_err(storage_err)?;
+            }
+        }
+        found.sort();
+        self.segments.clear();
+        for (_, generation, path) in found {
+            let records = read_frames(&path)?;
+            if let Some((seq, _)) = records.last() {
+                self.next_seq = self.next_seq.max(seq + 1);
+            }
+            self.generation = self.generation.max(generation);
+            self.segments.push(Segment {
+                bytes: fs::metadata(&path).map_err(storage_err)?.len(),
+                entries: records.len(),
+                path,
+                generation,
+            });
+        }
+        // 压缩产物不接受追加，之后的记录进入新段
+        if self.segments.last().is_some_and(|s| s.generation > 0) {
+            self.active = None;
+        } else if let Some(last) = self.segments.last() {
+            self.active = Some(open_append(&last.path)?);
+        }
+        Ok(self)
+    }
+
+    /// 追加一条记录，返回其序号
+    pub fn append(&mut self, entry: &E) -> Result<u64, DistributedError> {
+        let full = self
+            .segments
+            .last()
+            .is_none_or(|s| s.generation > 0 || s.entries >= self.segment_entries);
+        if self.active.is_none() || full {
+            let path = self.segment_path(self.next_seq, 0);
+            self.active = Some(open_append(&path)?);
+            self.segments.push(Segment {
+                path,
+                generation: 0,
+                entries: 0,
+                bytes: 0,
+            });
+        }
+        let seq = self.next_seq;
+        let frame = encode_frame(seq, &self.codec.encode(entry));
+        let file = self.active.as_mut().expect("active segment opened above");
+        file.write_all(&frame).map_err(storage_err)?;
+        let segment = self
+            .segments
+            .last_mut()
+            .expect("active segment pushed above");
+        segment.entries += 1;
+        segment.bytes += frame.len() as u64;
+        self.next_seq += 1;
+        Ok(seq)
+    }
+
+    /// 标记序号小于 `seq` 的记录为过期；空间在下次 `compact` 时回收
+    pub fn truncate_before(&mut self, seq: u64) -> Result<(), DistributedError> {
+        if seq <= self.truncated_before {
+            return Ok(());
+        }
+        let tmp = self.dir.join(format!("{WATERMARK_FILE}.tmp"));
+        fs::write(&tmp, seq.to_string()).map_err(storage_err)?;
+        fs::rename(&tmp, self.dir.join(WATERMARK_FILE)).map_err(storage_err)?;
+        self.truncated_before = seq;
+        Ok(())
+    }
+
+    /// 所有存活记录，按序号升序
+    pub fn read_live(&self) -> Result<Vec<(u64, E)>, DistributedError> {
+        let mut records = BTreeMap::new();
+        for segment in &self.segments {
+            for (seq, payload) in read_frames(&segment.path)? {
+                if seq >= self.truncated_before {
+                    records.insert(seq, payload);
+                }
+            }
+        }
+        let mut latest: HashMap<String, u64> = HashMap::new();
+        let mut decoded = Vec::with_capacity(records.len());
+        for (seq, payload) in records {
+            let entry = self.codec.decode(&payload).ok_or_else(|| {
+                DistributedError::Storage(format!("undecodable WAL record {seq}"))
+            })?;
+            if let Some(key) = &self.key {
+                latest.insert(key(&entry), seq);
+            }
+            decoded.push((seq, entry));
+        }
+        if let Some(key) = &self.key {
+            decoded.retain(|(seq, entry)| latest.get(&key(entry)) == Some(seq));
+        }
+        Ok(decoded)
+    }
+
+    /// 改写已封存段中的存活记录并删除旧段
+    pub fn compact(&mut self) -> Result<Reclaimed, DistributedError> {
+        let sealed = match self.active {
+            Some(_) => self.segments.len().saturating_sub(1),
+            None => self.segments.len(),
+        };
+        if sealed == 0 {
+            return Ok(Reclaimed::default());
+        }
+        // 键的最新版本可能位于活跃段，需一并考虑
+        let mut latest: HashMap<String, u64> = HashMap::new();
+        let mut sealed_records = Vec::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            for (seq, payload) in read_frames(&segment.path)? {
+                if let Some(key) = &self.key {
+                    let entry = self.codec.decode(&payload).ok_or_else(|| {
+                        DistributedError::Storage(format!("undecodable WAL record {seq}"))
+                    })?;
+                    let slot = latest.entry(key(&entry)).or_insert(seq);
+                    *slot = (*slot).max(seq);
+                    if i < sealed {
+                        sealed_records.push((seq, payload, Some(key(&entry))));
+                    }
+                } else if i < sealed {
+                    sealed_records.push((seq, payload, None));
+                }
+            }
+        }
+        let before_entries = sealed_records.len() as u64;
+        let before_bytes: u64 = self.segments[..sealed].iter().map(|s| s.bytes).sum();
+        let mut live = BTreeMap::new();
+        for (seq, payload, key) in sealed_records {
+            let superseded = key.is_some_and(|k| latest.get(&k) != Some(&seq));
+            if seq >= self.truncated_before && !superseded {
+                live.insert(seq, payload);
+            }
+        }
+
+        self.generation += 1;
+        let live: Vec<_> = live.into_iter().collect();
+        let mut rewritten = Vec::new();
+        for chunk in live.chunks(self.segment_entries) {
+            let path = self.segment_path(chunk[0].0, self.generation);
+            let tmp = path.with_extension("tmp");
+            let mut bytes = Vec::new();
+            for (seq, payload) in chunk {
+                bytes.extend_from_slice(&encode_frame(*seq, payload));
+            }
+            let mut file = File::create(&tmp).map_err(storage_err)?;
+            file.write_all(&bytes).map_err(storage_err)?;
+            file.sync_all().map_err(storage_err)?;
+            fs::rename(&tmp, &path).map_err(storage_err)?;
+            rewritten.push(Segment {
+                path,
+                generation: self.generation,
+                entries: chunk.len(),
+                bytes: bytes.len() as u64,
+            });
+        }
+        let obsolete: Vec<_> = self.segments.drain(..sealed).collect();
+        let after_bytes: u64 = rewritten.iter().map(|s| s.bytes).sum();
+        self.segments.splice(0..0, rewritten);
+        for segment in obsolete {
+            fs::remove_file(&segment.path).map_err(storage_err)?;
+        }
+        Ok(Reclaimed {
+            bytes: before_bytes.saturating_sub(after_bytes),
+            entries: before_entries - live.len() as u64,
+        })
+    }
+
+    /// 磁盘上的记录数（含尚未回收的过期记录）
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(|s| s.entries).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 磁盘上的字节数
+    pub fn bytes(&self) -> u64 {
+        self.segments.iter().map(|s| s.bytes).sum()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn segment_entries(&self) -> usize {
+        self.segment_entries
+    }
+
+    /// 下一条记录的序号
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    pub fn truncated_before(&self) -> u64 {
+        self.truncated_before
+    }
+
+    fn segment_path(&self, first_seq: u64, generation: u64) -> PathBuf {
+        self.dir
+            .join(format!("{first_seq:020}-{generation:06}.seg"))
+    }
+}
+
+fn storage_err(e: std::io::Error) -> DistributedError {
+    DistributedError::Storage(e.to_string())
+}
+
+pub(crate) fn parse_segment_name(path: &Path) -> Option<(u64, u64)> {
+    if path.extension()? != "seg" {
+        return None;
+    }
+    let (first_seq, generation) = path.file_stem()?.to_str()?.split_once('-')?;
+    Some((first_seq.parse().ok()?, generation.parse().ok()?))
+}
+
+fn open_append(path: &Path) -> Result<File, DistributedError> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(storage_err)
+}
+
+fn encode_frame(seq: u64, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER + payload.len());
+    frame.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    frame.extend_from_slice(&seq.to_le_bytes());
+    frame.extend_from_slice(&frame_crc(seq, payload).to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn frame_crc(seq: u64, payload: &[u8]) -> u32 {
+    let mut covered = Vec::with_capacity(8 + payload.len());
+    covered.extend_from_slice(&seq.to_le_bytes());
+    covered.extend_from_slice(payload);
+    crc32(&covered)
+}
+
+/// 段文件中无法读取的部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDamage {
+    /// 最后一帧不完整或校验失败：写入中途崩溃，截掉即可
+    TornTail,
+    /// 校验失败的帧之后仍有数据：不是写入中途崩溃能解释的损坏
+    ChecksumMismatch { offset: u64 },
+}
+
+/// 逐帧扫描一个段文件的结果
+#[derive(Debug, Clone)]
+pub struct SegmentScan {
+    /// 完好的帧 (序号, 负载)
+    pub frames: Vec<(u64, Vec<u8>)>,
+    /// 完好帧占用的字节数
+    pub valid_len: u64,
+    pub file_len: u64,
+    pub damage: Option<FrameDamage>,
+}
+
+/// 读取段文件，在第一个不完整或校验失败的帧处停止
+pub fn scan_segment(path: &Path) -> Result<SegmentScan, DistributedError> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut bytes))
+        .map_err(storage_err)?;
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    let mut damage = None;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        let Some(header) = rest.get(..FRAME_HEADER) else {
+            damage = Some(FrameDamage::TornTail);
+            break;
+        };
+        let len = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let seq = u64::from_le_bytes(header[8..16].try_into().unwrap());
+        let crc = u32::from_le_bytes(header[16..20].try_into().unwrap());
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_add(FRAME_HEADER));
+        let Some(payload) = end.and_then(|end| rest.get(FRAME_HEADER..end)) else {
+            damage = Some(FrameDamage::TornTail);
+            break;
+        };
+        if frame_crc(seq, payload) != crc {
+            damage = Some(if offset + FRAME_HEADER + payload.len() == bytes.len() {
+                FrameDamage::TornTail
+            } else {
+                FrameDamage::ChecksumMismatch {
+                    offset: offset as u64,
+                }
+            });
+            break;
+        }
+        frames.push((seq, payload.to_vec()));
+        offset += FRAME_HEADER + payload.len();
+    }
+    Ok(SegmentScan {
+        frames,
+        valid_len: offset as u64,
+        file_len: bytes.len() as u64,
+        damage,
+    })
+}
+
+/// 读取段中的帧；末尾不完整的帧（写入中途崩溃）被忽略
+fn read_frames(path: &Path) -> Result<Vec<(u64, Vec<u8>)>, DistributedError> {
+    Ok(scan_segment(path)?.frames)
+}
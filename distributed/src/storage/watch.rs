@@ -0,0 +1,493 @@
+//! 复制 KV 的键变更订阅（watch）
+//!
+//! 设计意图：
+//! - 客户端需要 etcd 式的 watch：按键前缀订阅 `KvEvent::Put` / `KvEvent::Delete`，事件按应用
+//!   顺序（即版本顺序）投递。订阅可从“现在”开始，也可从给定版本开始回放历史；历史被压缩掉的
+//!   版本返回 `WatchError::Compacted(min_version)`。
+//! - 历史来自 `WatchLog`：`WalWatchLog` 把已应用的事件按版本顺序追加到分段 WAL，回放直接读
+//!   WAL，压缩边界即 WAL 的截断水位，重启或换领导者后从同一日志恢复。未配置日志的 `WatchHub`
+//!   退化为在内存中保留有界的尾部历史。
+//! - 登记订阅与回放在发布锁内完成，回放与实时事件之间既无空洞也无重复。
+//! - 领导者切换后新领导者可能重放已发布过的条目：`publish` 丢弃版本不大于已发布最大版本的
+//!   事件（接管日志时该值从日志恢复），每个订阅者对每个版本恰好收到一次，日志也不重复追加。
+//! - 慢订阅者有界：每个订阅的缓冲区容量可配置，溢出时取消该订阅（已缓冲的事件仍可读出，
+//!   之后返回 `WatchError::Overflow`），不拖慢写入路径。
+//!
+//! 不变量（草图）：
+//! - 订阅者收到的事件版本严格递增。
+//! - 历史中保留的事件版本连续覆盖 `[min_version, last_version]` 内已发布的全部事件。
+//! - 事件先追加到日志再投递给订阅者；日志追加失败的事件不发布。
+
+use crate::codec::JsonCodec;
+use crate::core::errors::DistributedError;
+use crate::storage::wal::SegmentedWal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// 每个订阅的默认缓冲容量
+pub const DEFAULT_WATCH_BUFFER: usize = 1024;
+/// 默认保留的历史事件数
+pub const DEFAULT_WATCH_HISTORY: usize = 4096;
+
+/// 键的前缀匹配
+pub trait KeyPrefix {
+    fn has_prefix(&self, prefix: &Self) -> bool;
+}
+
+impl KeyPrefix for String {
+    fn has_prefix(&self, prefix: &Self) -> bool {
+        self.starts_with(prefix.as_str())
+    }
+}
+
+impl KeyPrefix for Vec<u8> {
+    fn has_prefix(&self, prefix: &Self) -> bool {
+        self.starts_with(prefix)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KvEvent<K, V> {
+    Put { key: K, value: V, version: u64 },
+    Delete { key: K, version: u64 },
+}
+
+impl<K, V> KvEvent<K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            KvEvent::Put { key, .. } | KvEvent::Delete { key, .. } => key,
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        match self {
+            KvEvent::Put { version, .. } | KvEvent::Delete { version, .. } => *version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum WatchError {
+    /// 请求的起始版本已被压缩，可用的最小版本为该值
+    #[error("requested version compacted; minimum available version is {0}")]
+    Compacted(u64),
+    /// 订阅者跟不上，缓冲区溢出后被取消
+    #[error("watch cancelled: buffer of {0} events overflowed")]
+    Overflow(usize),
+    /// 读取历史日志失败
+    #[error("watch history unavailable: {0}")]
+    Unavailable(String),
+    #[error("watch is not supported by this store")]
+    Unsupported,
+}
+
+/// watch 历史的来源：按版本顺序保存已应用事件的日志
+pub trait WatchLog<K, V>: Send + Sync {
+    /// 追加一个事件；版本不大于已追加最大版本的事件（重放）被忽略
+    fn append(&self, event: &KvEvent<K, V>) -> Result<(), DistributedError>;
+    /// 版本不小于 `from` 的全部事件，按版本升序
+    fn events_from(&self, from: u64) -> Result<Vec<KvEvent<K, V>>, DistributedError>;
+    /// 压缩版本小于 `before` 的事件
+    fn compact(&self, before: u64) -> Result<(), DistributedError>;
+    /// 可回放的最小版本
+    fn min_version(&self) -> u64;
+    /// 已追加的最大版本
+    fn last_version(&self) -> u64;
+}
+
+struct WalLogState<K, V> {
+    wal: SegmentedWal<KvEvent<K, V>, JsonCodec>,
+    min_version: u64,
+    last_version: u64,
+}
+
+/// 以分段 WAL 保存的 watch 历史
+///
+/// 压缩把 WAL 截断到第一条版本不小于 `before` 的记录，并总是保留最后一条记录，重新打开时
+/// 据此恢复压缩边界与已追加的最大版本。WAL 不得配置 `with_key`：按键压缩会丢掉同一键的旧
+/// 版本，回放因此出现空洞。
+pub struct WalWatchLog<K, V> {
+    state: Mutex<WalLogState<K, V>>,
+}
+
+impl<K, V> WalWatchLog<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// 打开（尚未 `open` 的）`wal` 并恢复已有的事件
+    pub fn open(wal: SegmentedWal<KvEvent<K, V>, JsonCodec>) -> Result<Self, DistributedError> {
+        let wal = wal.open()?;
+        let live = wal.read_live()?;
+        let last_version = live.last().map_or(0, |(_, e)| e.version());
+        let min_version = match live.first() {
+            Some((_, first)) if wal.truncated_before() > 1 => first.version(),
+            _ => 1,
+        };
+        Ok(Self {
+            state: Mutex::new(WalLogState {
+                wal,
+                min_version,
+                last_version,
+            }),
+        })
+    }
+}
+
+impl<K, V> WatchLog<K, V> for WalWatchLog<K, V>
+where
+    K: Serialize + DeserializeOwned + Send,
+    V: Serialize + DeserializeOwned + Send,
+{
+    fn append(&self, event: &KvEvent<K, V>) -> Result<(), DistributedError> {
+        let mut state = self.state.lock().unwrap();
+        if event.version() <= state.last_version {
+            return Ok(());
+        }
+        state.wal.append(event)?;
+        state.last_version = event.version();
+        Ok(())
+    }
+
+    fn events_from(&self, from: u64) -> Result<Vec<KvEvent<K, V>>, DistributedError> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .wal
+            .read_live()?
+            .into_iter()
+            .map(|(_, event)| event)
+            .filter(|e| e.version() >= from)
+            .collect())
+    }
+
+    fn compact(&self, before: u64) -> Result<(), DistributedError> {
+        let mut state = self.state.lock().unwrap();
+        if before <= state.min_version {
+            return Ok(());
+        }
+        let live = state.wal.read_live()?;
+        let boundary = live
+            .iter()
+            .find(|(_, e)| e.version() >= before)
+            .or(live.last());
+        let Some((seq, event)) = boundary else {
+            return Ok(());
+        };
+        let min_version = event.version();
+        state.wal.truncate_before(*seq)?;
+        state.wal.compact()?;
+        state.min_version = state.min_version.max(min_version);
+        Ok(())
+    }
+
+    fn min_version(&self) -> u64 {
+        self.state.lock().unwrap().min_version
+    }
+
+    fn last_version(&self) -> u64 {
+        self.state.lock().unwrap().last_version
+    }
+}
+
+struct WatcherState<K, V> {
+    queue: VecDeque<KvEvent<K, V>>,
+    overflowed: bool,
+    dropped: bool,
+}
+
+struct Watcher<K, V> {
+    prefix: K,
+    matches: fn(&K, &K) -> bool,
+    capacity: usize,
+    state: Mutex<WatcherState<K, V>>,
+    ready: Condvar,
+}
+
+impl<K, V> Watcher<K, V> {
+    /// 返回 false 表示订阅已失效，应从列表中移除
+    fn offer(&self, event: &KvEvent<K, V>) -> bool
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut state = self.state.lock().unwrap();
+        if state.dropped || state.overflowed {
+            return false;
+        }
+        if !(self.matches)(event.key(), &self.prefix) {
+            return true;
+        }
+        if state.queue.len() >= self.capacity {
+            state.overflowed = true;
+        } else {
+            state.queue.push_back(event.clone());
+        }
+        self.ready.notify_all();
+        !state.overflowed
+    }
+}
+
+/// 一个订阅：按版本顺序取出匹配前缀的事件
+pub struct WatchStream<K, V> {
+    watcher: Arc<Watcher<K, V>>,
+}
+
+impl<K, V> WatchStream<K, V> {
+    /// 非阻塞地取下一个事件；缓冲为空时返回 `Ok(None)`
+    pub fn try_next(&self) -> Result<Option<KvEvent<K, V>>, WatchError> {
+        let mut state = self.watcher.state.lock().unwrap();
+        Self::pop(&mut state, self.watcher.capacity)
+    }
+
+    /// 至多等待 `timeout` 取下一个事件
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<KvEvent<K, V>>, WatchError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.watcher.state.lock().unwrap();
+        loop {
+            if let Some(event) = Self::pop(&mut state, self.watcher.capacity)? {
+                return Ok(Some(event));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            state = self
+                .watcher
+                .ready
+                .wait_timeout(state, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// 取出当前缓冲的全部事件；订阅已取消时先交出缓冲的事件，下一次调用才返回错误
+    pub fn drain(&self) -> Result<Vec<KvEvent<K, V>>, WatchError> {
+        let mut events = Vec::new();
+        loop {
+            match self.try_next() {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => return Ok(events),
+                Err(e) if events.is_empty() => return Err(e),
+                Err(_) => return Ok(events),
+            }
+        }
+    }
+
+    /// 缓冲中尚未取出的事件数
+    pub fn pending(&self) -> usize {
+        self.watcher.state.lock().unwrap().queue.len()
+    }
+
+    pub fn prefix(&self) -> &K {
+        &self.watcher.prefix
+    }
+
+    fn pop(
+        state: &mut WatcherState<K, V>,
+        capacity: usize,
+    ) -> Result<Option<KvEvent<K, V>>, WatchError> {
+        match state.queue.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None if state.overflowed => Err(WatchError::Overflow(capacity)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<K, V> Drop for WatchStream<K, V> {
+    fn drop(&mut self) {
+        self.watcher.state.lock().unwrap().dropped = true;
+    }
+}
+
+struct HubState<K, V> {
+    history: VecDeque<KvEvent<K, V>>,
+    /// 已被压缩的最大版本；可回放的最小版本为其加一
+    compacted_through: u64,
+    last_version: u64,
+    watchers: Vec<Arc<Watcher<K, V>>>,
+}
+
+/// 按版本顺序向订阅者分发事件；历史来自配置的 `WatchLog`，否则在内存中保留有界的尾部
+pub struct WatchHub<K, V> {
+    state: Mutex<HubState<K, V>>,
+    buffer: usize,
+    history_limit: usize,
+    log: Option<Arc<dyn WatchLog<K, V>>>,
+}
+
+impl<K: Clone, V: Clone> Default for WatchHub<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Clone, V: Clone> WatchHub<K, V> {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(HubState {
+                history: VecDeque::new(),
+                compacted_through: 0,
+                last_version: 0,
+                watchers: Vec::new(),
+            }),
+            buffer: DEFAULT_WATCH_BUFFER,
+            history_limit: DEFAULT_WATCH_HISTORY,
+            log: None,
+        }
+    }
+
+    /// 每个订阅的缓冲容量（回放的历史事件同样计入）
+    pub fn with_buffer(mut self, events: usize) -> Self {
+        self.buffer = events.max(1);
+        self
+    }
+
+    /// 保留的历史事件数上限；超出时最旧的事件被压缩（配置了日志时不适用）
+    pub fn with_history_limit(mut self, events: usize) -> Self {
+        self.history_limit = events;
+        self
+    }
+
+    /// 以 `log` 保存并回放历史；已发布的最大版本从日志恢复，之后的重放不会重复发布
+    pub fn with_log(mut self, log: Arc<dyn WatchLog<K, V>>) -> Self {
+        let hub = self.state.get_mut().unwrap();
+        hub.history.clear();
+        hub.last_version = hub.last_version.max(log.last_version());
+        self.log = Some(log);
+        self
+    }
+
+    /// 订阅前缀 `prefix`；`from` 为 `None` 时只接收之后发布的事件，否则先回放版本不小于
+    /// `from` 的历史事件
+    pub fn watch(&self, prefix: K, from: Option<u64>) -> Result<WatchStream<K, V>, WatchError>
+    where
+        K: KeyPrefix,
+    {
+        let mut hub = self.state.lock().unwrap();
+        let watcher = Arc::new(Watcher {
+            prefix,
+            matches: K::has_prefix,
+            capacity: self.buffer,
+            state: Mutex::new(WatcherState {
+                queue: VecDeque::new(),
+                overflowed: false,
+                dropped: false,
+            }),
+            ready: Condvar::new(),
+        });
+        match (from, &self.log) {
+            (Some(from), Some(log)) => {
+                let min_version = log.min_version();
+                if from < min_version {
+                    return Err(WatchError::Compacted(min_version));
+                }
+                let events = log
+                    .events_from(from)
+                    .map_err(|e| WatchError::Unavailable(e.to_string()))?;
+                for event in events.iter().filter(|e| e.version() <= hub.last_version) {
+                    if !watcher.offer(event) {
+                        break;
+                    }
+                }
+            }
+            (Some(from), None) => {
+                if from <= hub.compacted_through {
+                    return Err(WatchError::Compacted(hub.compacted_through + 1));
+                }
+                for event in hub.history.iter().filter(|e| e.version() >= from) {
+                    if !watcher.offer(event) {
+                        break;
+                    }
+                }
+            }
+            (None, _) => {}
+        }
+        hub.watchers.push(watcher.clone());
+        Ok(WatchStream { watcher })
+    }
+
+    /// 发布一个已应用的事件；版本不大于已发布最大版本的事件（重放）被忽略，返回是否发布
+    pub fn publish(&self, event: KvEvent<K, V>) -> Result<bool, DistributedError> {
+        let mut hub = self.state.lock().unwrap();
+        self.publish_locked(&mut hub, event)
+    }
+
+    /// 在持有发布锁时执行 `apply` 并发布其返回的事件：版本分配与应用在锁内完成，
+    /// 并发写入的事件因此按版本顺序发布
+    pub fn publish_with<E: From<DistributedError>>(
+        &self,
+        apply: impl FnOnce() -> Result<KvEvent<K, V>, E>,
+    ) -> Result<(), E> {
+        let mut hub = self.state.lock().unwrap();
+        let event = apply()?;
+        self.publish_locked(&mut hub, event)?;
+        Ok(())
+    }
+
+    /// 压缩版本小于 `before` 的历史
+    pub fn compact(&self, before: u64) -> Result<(), DistributedError> {
+        let mut hub = self.state.lock().unwrap();
+        let before = before.min(hub.last_version + 1);
+        if let Some(log) = &self.log {
+            return log.compact(before);
+        }
+        while hub.history.front().is_some_and(|e| e.version() < before) {
+            hub.history.pop_front();
+        }
+        hub.compacted_through = hub.compacted_through.max(before.saturating_sub(1));
+        Ok(())
+    }
+
+    /// 可回放的最小版本
+    pub fn min_version(&self) -> u64 {
+        match &self.log {
+            Some(log) => log.min_version(),
+            None => self.state.lock().unwrap().compacted_through + 1,
+        }
+    }
+
+    /// 已发布的最大版本
+    pub fn last_version(&self) -> u64 {
+        self.state.lock().unwrap().last_version
+    }
+
+    /// 仍然有效的订阅数
+    pub fn watcher_count(&self) -> usize {
+        let mut hub = self.state.lock().unwrap();
+        hub.watchers.retain(|w| {
+            let state = w.state.lock().unwrap();
+            !state.dropped && !state.overflowed
+        });
+        hub.watchers.len()
+    }
+
+    fn publish_locked(
+        &self,
+        hub: &mut HubState<K, V>,
+        event: KvEvent<K, V>,
+    ) -> Result<bool, DistributedError> {
+        let version = event.version();
+        if version <= hub.last_version {
+            return Ok(false);
+        }
+        if let Some(log) = &self.log {
+            log.append(&event)?;
+        }
+        hub.last_version = version;
+        hub.watchers.retain(|w| w.offer(&event));
+        if self.log.is_none() {
+            hub.history.push_back(event);
+            while hub.history.len() > self.history_limit {
+                if let Some(oldest) = hub.history.pop_front() {
+                    hub.compacted_through = oldest.version();
+                }
+            }
+        }
+        Ok(true)
+    }
+}
@@ -3,6 +3,12 @@
 //! 设计目标：
 //! - 实现探测（ping/ping-req/ack）与反熵式 gossip，维护 `MembershipView` 收敛。
 //! - 显式使用 `incarnation` 消除 ABA 与回退，使用 `suspect_timeout` 降低误判。
+//! - `gossip::start_gossip_task`（`runtime-tokio`）在 tokio 定时器上驱动 gossip，传输可插拔。
+//! - `skew::SkewMonitor` 在心跳/gossip 上捎带时间戳估计各节点的时钟偏移，越界时告警并收紧 HLC。
+//! - `PhiAccrualFailureDetector` 把心跳到达间隔建模为正态分布，输出连续的怀疑度 φ，
+//!   由调用方按阈值映射到 Suspect/Faulty。
+//! - `DisseminationBuffer` 限制捎带在探测/gossip 消息上的会籍更新字节数：传播次数少的更新
+//!   优先，传播满 `k × ⌈log2 n⌉` 次后淘汰，使负载不随集群规模无界增长。
 //!
 //! 不变量与性质（草图）：
 //! - 单调版本：`MembershipView.version` 单调递增；节点条目 `(incarnation, version)` 按字典序单调推进。
@@ -12,12 +18,25 @@
 //! 参考：
 //! - Das et al., SWIM: Scalable Weakly-consistent Infection-style Process Group Membership Protocol, 2002.
 //! - Lifeguard (SWIM 改进)：减少误判并改进探测准确率。
+//! - Hayashibara et al., The φ Accrual Failure Detector, 2004.
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant, SystemTime};
 
+#[cfg(feature = "runtime-tokio")]
+pub mod gossip;
+pub mod skew;
+
+#[cfg(feature = "runtime-tokio")]
+pub use gossip::{
+    GossipPayload, GossipTransport, InProcessGossipTransport, SignedGossipTransport,
+    TransportError, start_gossip_task,
+};
+pub use skew::{MembershipEvent, SkewConfig, SkewMonitor};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SwimMemberState {
     Alive,
@@ -309,6 +328,12 @@ impl<T: SwimTransport> SwimNode<T> {
         events
     }
 
+    /// 一轮 gossip：向 `view.gossip_peers` 选出的至多 `fanout` 个节点广播，返回成功数
+    pub fn gossip_round(&self, view: &MembershipView, prefer_rack: bool) -> usize {
+        let peers = view.gossip_peers(&self.node_id, self.fanout, prefer_rack);
+        self.broadcast_gossip(&self.generate_gossip(view), &peers)
+    }
+
     /// 广播gossip消息
     pub fn broadcast_gossip(&self, events: &[SwimEvent], peers: &[String]) -> usize {
         let mut success_count = 0;
@@ -323,10 +348,10 @@ impl<T: SwimTransport> SwimNode<T> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct Version(pub u64);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemberInfo {
     pub state: SwimMemberState,
     pub version: Version,
@@ -339,6 +364,8 @@ pub struct MembershipView {
     pub me: String,
     pub members: HashMap<String, MemberInfo>,
     pub version: Version,
+    /// 节点 → 机架；未登记的节点视为机架未知
+    pub rack_of: HashMap<String, String>,
 }
 
 impl MembershipView {
@@ -347,9 +374,44 @@ impl MembershipView {
             me,
             members: HashMap::new(),
             version: Version(0),
+            rack_of: HashMap::new(),
         }
     }
 
+    pub fn set_rack(&mut self, node: &str, rack: &str) {
+        self.rack_of.insert(node.to_string(), rack.to_string());
+    }
+
+    pub fn rack(&self, node: &str) -> Option<&str> {
+        self.rack_of.get(node).map(String::as_str)
+    }
+
+    /// 为 `source` 选出至多 `max_peers` 个 gossip 对象（排除自身与故障节点）。
+    ///
+    /// 候选按 `(source, version, peer)` 的哈希排序，每轮视图版本变化后顺序随之轮换；
+    /// `prefer_rack` 时先取与 `source` 同机架的节点，不足时再取跨机架节点。
+    pub fn gossip_peers(&self, source: &str, max_peers: usize, prefer_rack: bool) -> Vec<String> {
+        let mut candidates: Vec<(bool, u64, &String)> = self
+            .members
+            .iter()
+            .filter(|(node, info)| node.as_str() != source && info.state != SwimMemberState::Faulty)
+            .map(|(node, _)| {
+                let same_rack = prefer_rack
+                    && self.rack(source).is_some()
+                    && self.rack(source) == self.rack(node);
+                let mut hasher = ahash::AHasher::default();
+                (source, self.version.0, node).hash(&mut hasher);
+                (!same_rack, hasher.finish(), node)
+            })
+            .collect();
+        candidates.sort_unstable();
+        candidates
+            .into_iter()
+            .take(max_peers)
+            .map(|(_, _, node)| node.clone())
+            .collect()
+    }
+
     pub fn local_update(&mut self, node: &str, state: SwimMemberState, incarnation: u64) {
         let ent = self.members.entry(node.to_string()).or_insert(MemberInfo {
             state,
@@ -478,3 +540,238 @@ impl MembershipView {
             .count()
     }
 }
+
+/// 待捎带传播的一条会籍更新
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GossipEntry {
+    pub node_id: String,
+    pub state: SwimMemberState,
+    pub incarnation: u64,
+    /// 已被捎带发送的次数
+    pub dissemination_count: u32,
+}
+
+impl GossipEntry {
+    pub fn new(node_id: impl Into<String>, state: SwimMemberState, incarnation: u64) -> Self {
+        Self {
+            node_id: node_id.into(),
+            state,
+            incarnation,
+            dissemination_count: 0,
+        }
+    }
+
+    /// 线上编码长度：长度前缀（4）+ 节点 ID + 状态（1）+ incarnation（8）
+    pub fn encoded_len(&self) -> usize {
+        4 + self.node_id.len() + 1 + 8
+    }
+}
+
+/// 有界的捎带传播缓冲
+///
+/// 条目按 `dissemination_count` 升序排列（同次数按入队先后），`pop_entries` 依次取出放得进
+/// 字节预算的条目并把其传播次数加一；传播满 `dissemination_limit` 次的条目被淘汰。
+/// 同一节点的新更新（incarnation 不低于已有条目）替换旧条目并重新从 0 次开始传播。
+#[derive(Debug, Clone)]
+pub struct DisseminationBuffer {
+    entries: VecDeque<GossipEntry>,
+    max_payload_bytes: usize,
+    retransmit_multiplier: u32,
+    cluster_size: usize,
+    evicted: u64,
+}
+
+impl DisseminationBuffer {
+    pub fn new(max_payload_bytes: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_payload_bytes,
+            retransmit_multiplier: 3,
+            cluster_size: 1,
+            evicted: 0,
+        }
+    }
+
+    /// 传播次数上限中的系数 k（缺省 3）
+    pub fn with_retransmit_multiplier(mut self, k: u32) -> Self {
+        self.retransmit_multiplier = k.max(1);
+        self
+    }
+
+    pub fn with_cluster_size(mut self, n: usize) -> Self {
+        self.set_cluster_size(n);
+        self
+    }
+
+    /// 会籍变化后更新集群规模，影响此后的传播次数上限
+    pub fn set_cluster_size(&mut self, n: usize) {
+        self.cluster_size = n.max(1);
+    }
+
+    pub fn max_payload_bytes(&self) -> usize {
+        self.max_payload_bytes
+    }
+
+    /// 每条更新的传播次数上限：k × ⌈log2 n⌉，至少为 k
+    pub fn dissemination_limit(&self) -> u32 {
+        let log2 = self
+            .cluster_size
+            .next_power_of_two()
+            .trailing_zeros()
+            .max(1);
+        self.retransmit_multiplier.saturating_mul(log2)
+    }
+
+    /// 加入一条更新；同一节点 incarnation 更旧的更新被忽略
+    pub fn push(&mut self, entry: GossipEntry) {
+        if let Some(pos) = self.entries.iter().position(|e| e.node_id == entry.node_id) {
+            if self.entries[pos].incarnation > entry.incarnation {
+                return;
+            }
+            self.entries.remove(pos);
+        }
+        let at = self
+            .entries
+            .partition_point(|e| e.dissemination_count <= entry.dissemination_count);
+        self.entries.insert(at, entry);
+    }
+
+    /// 取出放得进 `budget_bytes`（不超过 `max_payload_bytes`）的条目，传播次数少的优先；
+    /// 返回的条目已计入本次传播
+    pub fn pop_entries(&mut self, budget_bytes: usize) -> Vec<GossipEntry> {
+        let mut remaining = budget_bytes.min(self.max_payload_bytes);
+        let mut out = Vec::new();
+        for entry in self.entries.iter_mut() {
+            let len = entry.encoded_len();
+            if len > remaining {
+                continue;
+            }
+            remaining -= len;
+            entry.dissemination_count += 1;
+            out.push(entry.clone());
+        }
+        let limit = self.dissemination_limit();
+        let before = self.entries.len();
+        self.entries.retain(|e| e.dissemination_count < limit);
+        self.evicted += (before - self.entries.len()) as u64;
+        self.entries
+            .make_contiguous()
+            .sort_by_key(|e| e.dissemination_count);
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 因传播次数达到上限而淘汰的条目数
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+}
+
+/// φ 累积故障检测器（单个节点）
+///
+/// φ = -log10(P(下一次心跳晚于 now))，到达间隔按滑动窗口内的均值/标准差估计；
+/// 时间由调用方传入，便于在模拟时钟下测试。
+#[derive(Debug, Clone)]
+pub struct PhiAccrualFailureDetector {
+    max_samples: usize,
+    /// 标准差下限，避免间隔非常规律时 φ 过于敏感
+    min_std_deviation: Duration,
+    /// 额外容忍的停顿（如 GC），从观测间隔中扣除
+    acceptable_pause: Duration,
+    /// 尚无间隔样本时假定的心跳间隔（标准差取其 1/4）
+    first_heartbeat_estimate: Option<Duration>,
+    intervals: VecDeque<f64>,
+    last_heartbeat: Option<Instant>,
+}
+
+impl Default for PhiAccrualFailureDetector {
+    fn default() -> Self {
+        Self::new(100, Duration::from_millis(100), Duration::ZERO)
+    }
+}
+
+impl PhiAccrualFailureDetector {
+    pub fn new(
+        max_samples: usize,
+        min_std_deviation: Duration,
+        acceptable_pause: Duration,
+    ) -> Self {
+        Self {
+            max_samples: max_samples.max(1),
+            min_std_deviation,
+            acceptable_pause,
+            first_heartbeat_estimate: None,
+            intervals: VecDeque::new(),
+            last_heartbeat: None,
+        }
+    }
+
+    pub fn with_first_heartbeat_estimate(mut self, estimate: Duration) -> Self {
+        self.first_heartbeat_estimate = Some(estimate);
+        self
+    }
+
+    /// 记录一次心跳到达
+    pub fn heartbeat(&mut self, now: Instant) {
+        if let Some(last) = self.last_heartbeat {
+            if self.intervals.len() == self.max_samples {
+                self.intervals.pop_front();
+            }
+            let interval = now.saturating_duration_since(last).as_secs_f64() * 1000.0;
+            self.intervals.push_back(interval);
+        }
+        self.last_heartbeat = Some(now);
+    }
+
+    pub fn last_heartbeat(&self) -> Option<Instant> {
+        self.last_heartbeat
+    }
+
+    /// 当前怀疑度；从未收到心跳，或没有间隔样本且未设置初始估计时为 0
+    pub fn phi(&self, now: Instant) -> f64 {
+        let Some(last) = self.last_heartbeat else {
+            return 0.0;
+        };
+        let (mean, std) = if self.intervals.is_empty() {
+            let Some(estimate) = self.first_heartbeat_estimate else {
+                return 0.0;
+            };
+            let mean = estimate.as_secs_f64() * 1000.0;
+            (mean, mean / 4.0)
+        } else {
+            let n = self.intervals.len() as f64;
+            let mean = self.intervals.iter().sum::<f64>() / n;
+            let variance = self
+                .intervals
+                .iter()
+                .map(|i| (i - mean).powi(2))
+                .sum::<f64>()
+                / n;
+            (mean, variance.sqrt())
+        };
+        let std = std.max(self.min_std_deviation.as_secs_f64() * 1000.0);
+        let elapsed = now.saturating_duration_since(last).as_secs_f64() * 1000.0;
+        let mean = mean + self.acceptable_pause.as_secs_f64() * 1000.0;
+
+        // 正态分布尾概率的 logistic 近似（同 Akka 实现）
+        let y = (elapsed - mean) / std;
+        let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+        let phi = if elapsed > mean {
+            -(e / (1.0 + e)).log10()
+        } else {
+            -(1.0 - 1.0 / (1.0 + e)).log10()
+        };
+        if phi.is_finite() { phi } else { f64::MAX }
+    }
+
+    pub fn is_available(&self, now: Instant, threshold: f64) -> bool {
+        self.phi(now) < threshold
+    }
+}
@@ -0,0 +1,235 @@
+//! 异步 gossip 任务
+//!
+//! 设计意图：
+//! - `SwimNode::gossip_round` 需要调用方自行驱动；`start_gossip_task` 把传播交给 tokio：
+//!   每隔 `interval_ms` 从 `gossip_peers` 的候选中随机选出至多 `DEFAULT_GOSSIP_FANOUT` 个节点，
+//!   把 `gossip_payload` 的快照经 `GossipTransport` 推送过去；收到的负载一到达即经
+//!   `merge_from` 合并，不必等到下一个周期。
+//! - 传输可插拔：`GossipTransport` 以装箱 future 返回，可作为 trait object 共享；
+//!   `InProcessGossipTransport` 用进程内通道连接同一进程中的多个视图，供测试使用；
+//!   `SignedGossipTransport` 包装任意传输，为负载签名并丢弃无法验证或重放的入站负载。
+//!
+//! 不变量（草图）：
+//! - 持有视图锁期间不跨越 `await`：负载在发送前复制出来。
+//! - 发送失败只影响本轮对该节点的推送，任务继续运行直到被 abort。
+
+use super::{MemberInfo, MembershipView};
+use crate::core::errors::DistributedError;
+use crate::security::signing::{SignedMessage, SigningMiddleware};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// 每轮推送的节点数上限
+pub const DEFAULT_GOSSIP_FANOUT: usize = 3;
+
+/// 一次 gossip 推送：发送方的会籍视图快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GossipPayload {
+    pub from: String,
+    pub members: Vec<(String, MemberInfo)>,
+    /// `SignedGossipTransport` 附加的签名；未签名传输留空
+    #[serde(default)]
+    pub signature: Vec<u8>,
+    /// 签名序号，接收方据此拒绝重放；未签名传输为 0
+    #[serde(default)]
+    pub seq: u64,
+}
+
+impl GossipPayload {
+    /// 被签名的字节：会籍快照的 JSON 编码
+    fn signed_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.members).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TransportError {
+    #[error("peer {0} unreachable")]
+    Unreachable(String),
+    #[error("transport closed")]
+    Closed,
+    #[error("signing failed: {0}")]
+    Signing(String),
+}
+
+impl From<TransportError> for DistributedError {
+    fn from(err: TransportError) -> Self {
+        DistributedError::Network(err.to_string())
+    }
+}
+
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + 'a>>;
+pub type RecvFuture<'a> = Pin<Box<dyn Future<Output = Option<GossipPayload>> + Send + 'a>>;
+
+pub trait GossipTransport: Send + Sync {
+    fn send<'a>(&'a self, to: &'a str, payload: GossipPayload) -> SendFuture<'a>;
+    /// 等待下一条发给 `me` 的负载；返回 `None` 表示不会再有入站负载
+    fn recv<'a>(&'a self, me: &'a str) -> RecvFuture<'a>;
+}
+
+type Inbox = Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<GossipPayload>>>;
+
+/// 进程内传输：每个登记的节点一个无界收件箱
+#[derive(Default)]
+pub struct InProcessGossipTransport {
+    nodes: Mutex<HashMap<String, (mpsc::UnboundedSender<GossipPayload>, Inbox)>>,
+}
+
+impl InProcessGossipTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记节点；重复登记会替换收件箱，丢弃未读的负载
+    pub fn register(&self, node: &str) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let inbox = Arc::new(tokio::sync::Mutex::new(rx));
+        self.nodes
+            .lock()
+            .unwrap()
+            .insert(node.to_string(), (tx, inbox));
+    }
+
+    /// 注销节点，之后发往它的负载返回 `Unreachable`
+    pub fn unregister(&self, node: &str) {
+        self.nodes.lock().unwrap().remove(node);
+    }
+}
+
+impl GossipTransport for InProcessGossipTransport {
+    fn send<'a>(&'a self, to: &'a str, payload: GossipPayload) -> SendFuture<'a> {
+        let sender = self.nodes.lock().unwrap().get(to).map(|(tx, _)| tx.clone());
+        Box::pin(async move {
+            let sender = sender.ok_or_else(|| TransportError::Unreachable(to.to_string()))?;
+            sender
+                .send(payload)
+                .map_err(|_| TransportError::Unreachable(to.to_string()))
+        })
+    }
+
+    fn recv<'a>(&'a self, me: &'a str) -> RecvFuture<'a> {
+        let inbox = self.nodes.lock().unwrap().get(me).map(|(_, rx)| rx.clone());
+        Box::pin(async move { inbox?.lock().await.recv().await })
+    }
+}
+
+/// 签名中间件：出站负载以本节点身份签名给接收方，入站负载验签失败、签名者未知、
+/// 接收方不是本节点或重放时丢弃并计数，`recv` 继续等待下一条
+pub struct SignedGossipTransport<T> {
+    inner: T,
+    signing: Arc<SigningMiddleware>,
+}
+
+impl<T: GossipTransport> SignedGossipTransport<T> {
+    pub fn new(inner: T, signing: Arc<SigningMiddleware>) -> Self {
+        Self { inner, signing }
+    }
+
+    pub fn signing(&self) -> &SigningMiddleware {
+        &self.signing
+    }
+}
+
+impl<T: GossipTransport> GossipTransport for SignedGossipTransport<T> {
+    fn send<'a>(&'a self, to: &'a str, mut payload: GossipPayload) -> SendFuture<'a> {
+        let signed = self.signing.sign(to, payload.signed_bytes());
+        Box::pin(async move {
+            let signed = signed.map_err(|e| TransportError::Signing(e.to_string()))?;
+            payload.from = signed.signer;
+            payload.seq = signed.seq;
+            payload.signature = signed.signature;
+            self.inner.send(to, payload).await
+        })
+    }
+
+    fn recv<'a>(&'a self, me: &'a str) -> RecvFuture<'a> {
+        Box::pin(async move {
+            loop {
+                let payload = self.inner.recv(me).await?;
+                let message = SignedMessage {
+                    signer: payload.from.clone(),
+                    recipient: me.to_string(),
+                    seq: payload.seq,
+                    reply_to: None,
+                    payload: payload.signed_bytes(),
+                    signature: payload.signature.clone(),
+                };
+                if self.signing.accept(&message).is_ok() {
+                    return Some(payload);
+                }
+            }
+        })
+    }
+}
+
+/// SplitMix64：按节点 ID 播种，选择 gossip 对象
+struct PeerRng(u64);
+
+impl PeerRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// 在候选中随机取 `k` 个（部分 Fisher–Yates）
+fn pick_peers(mut candidates: Vec<String>, k: usize, rng: &mut PeerRng) -> Vec<String> {
+    let k = k.min(candidates.len());
+    for i in 0..k {
+        let j = i + (rng.next_u64() % (candidates.len() - i) as u64) as usize;
+        candidates.swap(i, j);
+    }
+    candidates.truncate(k);
+    candidates
+}
+
+/// 启动周期性 gossip；丢弃返回的句柄不会停止任务，需显式 `abort`
+pub fn start_gossip_task(
+    view: Arc<Mutex<MembershipView>>,
+    transport: Arc<dyn GossipTransport + Send + Sync>,
+    interval_ms: u64,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let me = view.lock().unwrap().me.clone();
+        let mut hasher = ahash::AHasher::default();
+        me.hash(&mut hasher);
+        let mut rng = PeerRng(hasher.finish());
+        let mut ticker = tokio::time::interval(Duration::from_millis(interval_ms.max(1)));
+        let mut inbox_open = true;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let (peers, payload) = {
+                        let view = view.lock().unwrap();
+                        let peers = view.gossip_peers(&me, usize::MAX, false);
+                        let payload = GossipPayload {
+                            from: me.clone(),
+                            members: view.gossip_payload(),
+                            signature: Vec::new(),
+                            seq: 0,
+                        };
+                        (peers, payload)
+                    };
+                    for peer in pick_peers(peers, DEFAULT_GOSSIP_FANOUT, &mut rng) {
+                        let _ = transport.send(&peer, payload.clone()).await;
+                    }
+                }
+                incoming = transport.recv(&me), if inbox_open => match incoming {
+                    Some(payload) => view.lock().unwrap().merge_from(&payload.members),
+                    None => inbox_open = false,
+                },
+            }
+        }
+    })
+}
@@ -0,0 +1,323 @@
+//! 集群时钟偏斜检测
+//!
+//! 设计意图：
+//! - HLC 只保证因果序，不发现物理时钟本身的偏差；某节点时钟快出数百毫秒时，它签发的时间戳
+//!   会把其余节点的 HLC 一起拖快。`SkewMonitor` 在心跳/gossip 消息上捎带时间戳，按 NTP 式
+//!   四时刻交换估计每个对等节点的时钟偏移与往返时延：本地发出 `SkewProbe`（t1），对端
+//!   `answer` 盖上收到（t2）与回送（t3）时刻，本地在 `complete` 中记下到达时刻 t4。
+//!   偏移 = ((t2 − t1) + (t3 − t4)) / 2（对端减本地），RTT = (t4 − t1) − (t3 − t2)。
+//! - 单个样本的误差不超过其 RTT/2；偏移与 RTT 以同一平滑因子做 EWMA，平滑后的偏移误差同样
+//!   不超过平滑后的 RTT/2。RTT 超过 `max_rtt` 的样本误差界太宽，直接丢弃。
+//! - 平滑偏移的绝对值超过 `max_offset` 时返回 `MembershipEvent::ClockSkewWarning` 并计入
+//!   `clock_skew_warnings_total`；回落到界内后重新布防，同一次越界只告警一次。
+//! - `drift_limit` 交给 `HlcClock::with_max_drift`：容忍度为 `base_tolerance` 加上界内节点中
+//!   最大的已知偏移（含 RTT/2 误差）。偏斜已知且在界内的节点不会被拒，越界节点的时间戳
+//!   由 `HlcClock::try_update` 拒绝。
+//!
+//! 不变量（草图）：
+//! - 未收到任何有效样本的节点没有估计值，不参与容忍度计算，也不会触发告警。
+//! - `drift_limit` 的结果不小于 `base_tolerance`，不大于 `base_tolerance + max_offset + max_rtt / 2`。
+
+use crate::monitoring::{Counter, Gauge, MetricCollector, MetricLabels};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 会籍层面的通知
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MembershipEvent {
+    /// 节点的平滑时钟偏移（毫秒，对端减本地）超出 `SkewConfig::max_offset`
+    ClockSkewWarning(String, i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct SkewConfig {
+    /// 告警阈值
+    pub max_offset: Duration,
+    /// 偏移与 RTT 的 EWMA 平滑因子，越大越偏向最新样本
+    pub alpha: f64,
+    /// RTT 超过该值的样本被丢弃
+    pub max_rtt: Duration,
+    /// HLC 在没有任何偏斜信息时允许的漂移
+    pub base_tolerance: Duration,
+}
+
+impl Default for SkewConfig {
+    fn default() -> Self {
+        Self {
+            max_offset: Duration::from_millis(250),
+            alpha: 0.2,
+            max_rtt: Duration::from_secs(1),
+            base_tolerance: Duration::from_millis(50),
+        }
+    }
+}
+
+/// 请求方捎带的发送时刻
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkewProbe {
+    pub t1: u64,
+}
+
+/// 应答方回送的三个时刻
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkewReply {
+    pub t1: u64,
+    pub t2: u64,
+    pub t3: u64,
+}
+
+/// 一次完整交换的四个时刻（毫秒）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkewSample {
+    pub t1: u64,
+    pub t2: u64,
+    pub t3: u64,
+    pub t4: u64,
+}
+
+impl SkewSample {
+    /// 对端时钟减本地时钟
+    pub fn offset_ms(&self) -> f64 {
+        ((self.t2 as f64 - self.t1 as f64) + (self.t3 as f64 - self.t4 as f64)) / 2.0
+    }
+
+    /// 扣除对端处理时间后的往返时延
+    pub fn rtt_ms(&self) -> u64 {
+        self.t4
+            .saturating_sub(self.t1)
+            .saturating_sub(self.t3.saturating_sub(self.t2))
+    }
+}
+
+/// 某节点的平滑估计
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkewEstimate {
+    pub offset_ms: f64,
+    pub rtt_ms: f64,
+    pub samples: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SkewStats {
+    samples: AtomicU64,
+    discarded: AtomicU64,
+    warnings: AtomicU64,
+}
+
+impl SkewStats {
+    pub fn samples(&self) -> u64 {
+        self.samples.load(Ordering::Relaxed)
+    }
+
+    /// RTT 超过 `max_rtt` 而被丢弃的样本
+    pub fn discarded(&self) -> u64 {
+        self.discarded.load(Ordering::Relaxed)
+    }
+
+    pub fn warnings(&self) -> u64 {
+        self.warnings.load(Ordering::Relaxed)
+    }
+}
+
+struct PeerSkew {
+    estimate: SkewEstimate,
+    warned: bool,
+}
+
+pub struct SkewMonitor {
+    config: SkewConfig,
+    source: Box<dyn Fn() -> u64 + Send + Sync>,
+    peers: Mutex<HashMap<String, PeerSkew>>,
+    stats: SkewStats,
+    metrics: MetricCollector,
+    warnings_total: Arc<Counter>,
+    max_offset_gauge: Arc<Gauge>,
+}
+
+impl Default for SkewMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkewMonitor {
+    /// 以系统时钟（Unix 毫秒）为本地时间源
+    pub fn new() -> Self {
+        Self::with_time_source(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0)
+        })
+    }
+
+    /// 自定义本地时间源（毫秒），须与本节点 HLC 的时间源一致
+    pub fn with_time_source(source: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        let mut metrics = MetricCollector::new();
+        let warnings_total = metrics.counter("clock_skew_warnings_total", MetricLabels::new());
+        let max_offset_gauge = metrics.gauge("clock_skew_max_offset_ms", MetricLabels::new());
+        Self {
+            config: SkewConfig::default(),
+            source: Box::new(source),
+            peers: Mutex::new(HashMap::new()),
+            stats: SkewStats::default(),
+            metrics,
+            warnings_total,
+            max_offset_gauge,
+        }
+    }
+
+    pub fn with_config(mut self, config: SkewConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &SkewConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> &SkewStats {
+        &self.stats
+    }
+
+    /// `clock_skew_warnings_total` 与 `clock_skew_max_offset_ms`（各节点平滑偏移绝对值的最大值）
+    pub fn metrics(&self) -> &MetricCollector {
+        &self.metrics
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        (self.source)()
+    }
+
+    /// 随请求发出的时间戳
+    pub fn probe(&self) -> SkewProbe {
+        SkewProbe { t1: self.now_ms() }
+    }
+
+    /// 应答方在回送前调用；处理耗时可忽略时 t2 = t3
+    pub fn answer(&self, probe: SkewProbe) -> SkewReply {
+        let t2 = self.now_ms();
+        SkewReply {
+            t1: probe.t1,
+            t2,
+            t3: self.now_ms(),
+        }
+    }
+
+    /// 应答到达时调用，以当前时刻为 t4
+    pub fn complete(&self, peer: &str, reply: SkewReply) -> Option<MembershipEvent> {
+        let sample = SkewSample {
+            t1: reply.t1,
+            t2: reply.t2,
+            t3: reply.t3,
+            t4: self.now_ms(),
+        };
+        self.observe(peer, sample)
+    }
+
+    /// 合并一个样本；平滑偏移首次越界时返回告警
+    pub fn observe(&self, peer: &str, sample: SkewSample) -> Option<MembershipEvent> {
+        let rtt = sample.rtt_ms();
+        if rtt > self.config.max_rtt.as_millis() as u64 {
+            self.stats.discarded.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        self.stats.samples.fetch_add(1, Ordering::Relaxed);
+        let (offset, rtt) = (sample.offset_ms(), rtt as f64);
+
+        let mut peers = self.peers.lock().unwrap();
+        let entry = peers.entry(peer.to_string()).or_insert(PeerSkew {
+            estimate: SkewEstimate {
+                offset_ms: offset,
+                rtt_ms: rtt,
+                samples: 0,
+            },
+            warned: false,
+        });
+        let alpha = self.config.alpha;
+        let estimate = &mut entry.estimate;
+        if estimate.samples > 0 {
+            estimate.offset_ms += alpha * (offset - estimate.offset_ms);
+            estimate.rtt_ms += alpha * (rtt - estimate.rtt_ms);
+        }
+        estimate.samples += 1;
+        let offset_ms = estimate.offset_ms;
+
+        let exceeded = offset_ms.abs() > self.config.max_offset.as_millis() as f64;
+        let event = if exceeded && !entry.warned {
+            self.stats.warnings.fetch_add(1, Ordering::Relaxed);
+            self.warnings_total.inc();
+            #[cfg(feature = "observability")]
+            tracing::warn!(peer, offset_ms, "clock skew exceeds bound");
+            Some(MembershipEvent::ClockSkewWarning(
+                peer.to_string(),
+                offset_ms.round() as i64,
+            ))
+        } else {
+            None
+        };
+        entry.warned = exceeded;
+
+        let max = peers
+            .values()
+            .map(|p| p.estimate.offset_ms.abs())
+            .fold(0.0, f64::max);
+        self.max_offset_gauge.set(max.round() as u64);
+        event
+    }
+
+    pub fn estimate(&self, peer: &str) -> Option<SkewEstimate> {
+        self.peers.lock().unwrap().get(peer).map(|p| p.estimate)
+    }
+
+    pub fn estimates(&self) -> HashMap<String, SkewEstimate> {
+        self.peers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(peer, p)| (peer.clone(), p.estimate))
+            .collect()
+    }
+
+    /// 节点离开会籍后丢弃其估计
+    pub fn remove(&self, peer: &str) {
+        self.peers.lock().unwrap().remove(peer);
+    }
+
+    /// 当前平滑偏移越界的节点
+    pub fn skewed_peers(&self) -> Vec<String> {
+        let mut peers: Vec<String> = self
+            .peers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, p)| p.warned)
+            .map(|(peer, _)| peer.clone())
+            .collect();
+        peers.sort();
+        peers
+    }
+
+    /// HLC 允许远端超前的毫秒数：`base_tolerance` 加上界内节点中最大的超前量（含 RTT/2）
+    pub fn drift_tolerance_ms(&self) -> u64 {
+        let widen = self
+            .peers
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|p| !p.warned && p.estimate.offset_ms > 0.0)
+            .map(|p| p.estimate.offset_ms + p.estimate.rtt_ms / 2.0)
+            .fold(0.0, f64::max);
+        self.config.base_tolerance.as_millis() as u64 + widen.ceil() as u64
+    }
+
+    /// 供 `HlcClock::with_max_drift` 使用
+    pub fn drift_limit(self: &Arc<Self>) -> impl Fn() -> u64 + Send + Sync + 'static {
+        let monitor = Arc::downgrade(self);
+        let base = self.config.base_tolerance.as_millis() as u64;
+        move || monitor.upgrade().map_or(base, |m| m.drift_tolerance_ms())
+    }
+}
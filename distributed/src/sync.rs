@@ -0,0 +1,387 @@
+//! 分布式同步原语
+//!
+//! 设计意图：
+//! - `Barrier` 用于多阶段算法（如 MapReduce 的 map → reduce）的阶段边界：
+//!   `expected` 个不同参与者都调用 `wait` 后，所有等待者同时放行。
+//! - 以参与者 ID 去重：同一参与者在同一轮重复到达视为错误，而不是多计一次。
+//! - 放行通过 `tokio::sync::Notify::notify_waiters` 一次唤醒；等待者在锁内登记
+//!   `Notified`，不会错过在登记与 await 之间发生的放行。
+//!
+//! - `DistributedSemaphore` 限制跨节点的资源并发：许可计数是一个复制状态机，
+//!   获取与释放都是提交到日志的命令（`SemaphoreCommand`），按日志顺序在每个副本上
+//!   确定性地应用；余量不足的 `Acquire` 在应用时被拒绝，因此"检查并扣减"是原子的。
+//! - 日志后端经 `ReplicatedCounter` 抽象；`RaftCounter` 以单节点 Raft 组为日志，
+//!   多个节点上的信号量实例共享同一计数器即共享同一组许可。
+//! - 余量不足时按 `poll_interval` 重试直到超时；`SemaphoreGuard` 析构时在后台提交释放。
+//!
+//! 不变量（草图）：
+//! - 每一轮（generation）恰有一个等待者拿到 `is_leader = true`，即最后到达者。
+//! - 放行或 `reset` 后屏障自动进入下一轮，可重复使用。
+//! - 任一时刻被持有的许可总数不超过 `max_permits`；释放只对仍持有许可的 holder 生效，
+//!   重复释放不会凭空增加许可。
+
+use crate::consensus::raft::MinimalRaft;
+use crate::core::errors::DistributedError;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+pub mod coordination;
+
+/// `wait` 的返回值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierToken {
+    /// 是否为本轮最后到达者（可用于只由一个参与者执行的收尾工作）
+    pub is_leader: bool,
+    /// 放行的轮次，从 0 开始
+    pub generation: u64,
+}
+
+struct BarrierState {
+    arrived: HashSet<String>,
+    generation: u64,
+    /// 最近一次被 `reset` 中止的轮次
+    aborted: Option<u64>,
+}
+
+pub struct Barrier {
+    expected: usize,
+    state: Mutex<BarrierState>,
+    notify: Notify,
+}
+
+impl Barrier {
+    pub fn new(expected: usize) -> Arc<Barrier> {
+        Arc::new(Barrier {
+            expected,
+            state: Mutex::new(BarrierState {
+                arrived: HashSet::new(),
+                generation: 0,
+                aborted: None,
+            }),
+            notify: Notify::new(),
+        })
+    }
+
+    /// 等待本轮全部参与者到达；本轮被 `reset` 时返回错误
+    pub async fn wait(&self, participant_id: &str) -> Result<BarrierToken, DistributedError> {
+        let mut notified = pin!(self.notify.notified());
+        let generation = {
+            let mut state = self.state.lock().unwrap();
+            if !state.arrived.insert(participant_id.to_string()) {
+                return Err(DistributedError::InvalidState(format!(
+                    "participant {participant_id} already waiting at barrier"
+                )));
+            }
+            let generation = state.generation;
+            if state.arrived.len() >= self.expected {
+                state.arrived.clear();
+                state.generation += 1;
+                self.notify.notify_waiters();
+                return Ok(BarrierToken {
+                    is_leader: true,
+                    generation,
+                });
+            }
+            // 锁内登记，之后的 notify_waiters 一定能唤醒本等待者
+            notified.as_mut().enable();
+            generation
+        };
+
+        loop {
+            notified.as_mut().await;
+            let state = self.state.lock().unwrap();
+            if state.generation != generation {
+                if state.aborted == Some(generation) {
+                    return Err(DistributedError::InvalidState(format!(
+                        "barrier reset while {participant_id} was waiting"
+                    )));
+                }
+                return Ok(BarrierToken {
+                    is_leader: false,
+                    generation,
+                });
+            }
+            notified.set(self.notify.notified());
+            notified.as_mut().enable();
+        }
+    }
+
+    /// 中止当前轮次：已到达的等待者收到错误，屏障进入下一轮
+    pub async fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.arrived.is_empty() {
+            return;
+        }
+        state.arrived.clear();
+        state.aborted = Some(state.generation);
+        state.generation += 1;
+        self.notify.notify_waiters();
+    }
+
+    /// 本轮已到达的参与者数
+    pub fn arrived(&self) -> usize {
+        self.state.lock().unwrap().arrived.len()
+    }
+
+    pub fn expected(&self) -> usize {
+        self.expected
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SemaphoreError {
+    #[error("acquire timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("invalid permit count {requested} (max {max})")]
+    InvalidPermits { requested: u64, max: u64 },
+    #[error(transparent)]
+    Log(#[from] DistributedError),
+}
+
+/// 信号量复制状态机的命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemaphoreCommand {
+    /// 余量足够时扣减并记到 `holder` 名下，否则不生效
+    Acquire { holder: String, permits: u64 },
+    /// 归还 `holder` 名下的许可；`holder` 不持有许可时不生效
+    Release { holder: String, permits: u64 },
+}
+
+/// 复制的许可计数器：命令经共识提交后在各副本上按日志顺序应用
+pub trait ReplicatedCounter: Send + Sync + 'static {
+    /// 提交命令并等待其应用；返回命令是否生效
+    fn submit(
+        &self,
+        command: SemaphoreCommand,
+    ) -> impl Future<Output = Result<bool, DistributedError>> + Send;
+
+    /// 本副本上当前可用的许可数
+    fn available(&self) -> u64;
+
+    fn max_permits(&self) -> u64;
+}
+
+/// 许可计数状态机
+struct CounterMachine {
+    available: u64,
+    held: HashMap<String, u64>,
+    applied: u64,
+    /// 已应用但尚未被提交方取走的结果，按日志索引
+    outcomes: HashMap<u64, bool>,
+}
+
+impl CounterMachine {
+    fn apply(&mut self, command: &SemaphoreCommand) {
+        let effective = match command {
+            SemaphoreCommand::Acquire { holder, permits } => {
+                if *permits <= self.available {
+                    self.available -= permits;
+                    *self.held.entry(holder.clone()).or_default() += permits;
+                    true
+                } else {
+                    false
+                }
+            }
+            SemaphoreCommand::Release { holder, permits } => match self.held.get_mut(holder) {
+                Some(held) => {
+                    let returned = (*permits).min(*held);
+                    *held -= returned;
+                    if *held == 0 {
+                        self.held.remove(holder);
+                    }
+                    self.available += returned;
+                    returned > 0
+                }
+                None => false,
+            },
+        };
+        self.applied += 1;
+        self.outcomes.insert(self.applied, effective);
+    }
+}
+
+/// 以 `MinimalRaft` 为日志的复制计数器（单节点 Raft 组：提案即提交）
+pub struct RaftCounter {
+    raft: Mutex<MinimalRaft<SemaphoreCommand>>,
+    machine: Arc<Mutex<CounterMachine>>,
+    max_permits: u64,
+}
+
+impl RaftCounter {
+    pub fn new(max_permits: u64) -> Self {
+        let machine = Arc::new(Mutex::new(CounterMachine {
+            available: max_permits,
+            held: HashMap::new(),
+            applied: 0,
+            outcomes: HashMap::new(),
+        }));
+        let mut raft = MinimalRaft::new();
+        let apply_to = machine.clone();
+        raft.set_apply(Box::new(move |command: &SemaphoreCommand| {
+            apply_to.lock().unwrap().apply(command)
+        }));
+        raft.start_election("local");
+        raft.become_leader(std::iter::empty::<String>());
+        Self {
+            raft: Mutex::new(raft),
+            machine,
+            max_permits,
+        }
+    }
+}
+
+impl ReplicatedCounter for RaftCounter {
+    fn submit(
+        &self,
+        command: SemaphoreCommand,
+    ) -> impl Future<Output = Result<bool, DistributedError>> + Send {
+        let result = self
+            .raft
+            .lock()
+            .unwrap()
+            .propose(command)
+            .and_then(|index| {
+                self.machine
+                    .lock()
+                    .unwrap()
+                    .outcomes
+                    .remove(&index.0)
+                    .ok_or_else(|| {
+                        DistributedError::Consensus(format!("entry {} not applied", index.0))
+                    })
+            });
+        async move { result }
+    }
+
+    fn available(&self) -> u64 {
+        self.machine.lock().unwrap().available
+    }
+
+    fn max_permits(&self) -> u64 {
+        self.max_permits
+    }
+}
+
+/// 跨节点的计数信号量；同一计数器上的各实例共享许可
+pub struct DistributedSemaphore<C> {
+    node_id: String,
+    counter: Arc<C>,
+    poll_interval: Duration,
+    seq: AtomicU64,
+}
+
+impl<C: ReplicatedCounter> DistributedSemaphore<C> {
+    pub fn new(node_id: impl Into<String>, counter: Arc<C>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            counter,
+            poll_interval: Duration::from_millis(10),
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    /// 余量不足时的重试间隔
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn max_permits(&self) -> u64 {
+        self.counter.max_permits()
+    }
+
+    pub fn current_permits(&self) -> u64 {
+        self.counter.available()
+    }
+
+    /// 原子地获取 `permits` 个许可；余量不足时等待，超过 `timeout` 返回 `Timeout`
+    pub async fn acquire(
+        &self,
+        permits: u64,
+        timeout: Duration,
+    ) -> Result<SemaphoreGuard<C>, SemaphoreError> {
+        let max = self.max_permits();
+        if permits == 0 || permits > max {
+            return Err(SemaphoreError::InvalidPermits {
+                requested: permits,
+                max,
+            });
+        }
+        let holder = format!(
+            "{}-{}",
+            self.node_id,
+            self.seq.fetch_add(1, Ordering::Relaxed)
+        );
+        let deadline = Instant::now() + timeout;
+        loop {
+            let command = SemaphoreCommand::Acquire {
+                holder: holder.clone(),
+                permits,
+            };
+            if self.counter.submit(command).await? {
+                return Ok(SemaphoreGuard {
+                    counter: self.counter.clone(),
+                    holder,
+                    permits,
+                    released: false,
+                });
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(SemaphoreError::Timeout(timeout));
+            }
+            tokio::time::sleep(self.poll_interval.min(deadline - now)).await;
+        }
+    }
+}
+
+/// 持有的许可；析构时在后台提交释放
+pub struct SemaphoreGuard<C: ReplicatedCounter> {
+    counter: Arc<C>,
+    holder: String,
+    permits: u64,
+    released: bool,
+}
+
+impl<C: ReplicatedCounter> SemaphoreGuard<C> {
+    pub fn permits(&self) -> u64 {
+        self.permits
+    }
+
+    /// 提交释放并等待其应用
+    pub async fn release(mut self) -> Result<(), SemaphoreError> {
+        self.released = true;
+        self.counter.submit(self.release_command()).await?;
+        Ok(())
+    }
+
+    fn release_command(&self) -> SemaphoreCommand {
+        SemaphoreCommand::Release {
+            holder: self.holder.clone(),
+            permits: self.permits,
+        }
+    }
+}
+
+impl<C: ReplicatedCounter> Drop for SemaphoreGuard<C> {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        // 无运行时（如进程退出阶段）时无法提交释放，许可仍记在 holder 名下
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let counter = self.counter.clone();
+            let command = self.release_command();
+            handle.spawn(async move {
+                let _ = counter.submit(command).await;
+            });
+        }
+    }
+}
@@ -0,0 +1,457 @@
+//! 基于 KV 租约的屏障与信号量
+//!
+//! 设计意图：
+//! - 批处理作业需要跨集群的“等 K 个 worker 都到达这一点”和“至多 N 个 worker 并发”。
+//!   与进程内的 `Barrier` / 以日志为后端的 `DistributedSemaphore` 不同，这里的状态是共享
+//!   `VersionedStore` 中的一条记录（JSON 编码），各节点以 CAS 读改写；不需要常驻的协调者，
+//!   任何能访问同一 KV 的节点都可以参与。
+//! - `DistributedBarrier`：记录保存期望人数与已登记的参与者，第 K 个参与者的 CAS 同时把记录
+//!   标记为已放行。等待者轮询记录直到放行。每次登记把记录的过期时间推后 `ttl`；过期仍未放行
+//!   的屏障视为被遗弃：等待者收到错误，同名的新屏障可以覆盖它，`cleanup` 删除过期记录。
+//! - `DistributedSemaphore`（租约版）：记录保存上限与各持有者的租约 `(permits, expires_at)`。
+//!   获取时先剔除过期租约再检查余量，因此崩溃的持有者不释放也会在租约到期后归还许可；
+//!   存活的持有者需在到期前 `renew`。
+//! - 时间取自可替换的毫秒时间源（默认 Unix 时间），租约到期时间写入共享记录，
+//!   各节点的时间源需大致同步；模拟测试中共用 `SimClock::time_source`。
+//! - 阻塞版本（`enter` / `acquire`）以 `poll_interval` 轮询；它们在写入成功的同一次轮询中返回，
+//!   被外层超时丢弃不会留下半完成的状态。
+//!
+//! 不变量（草图）：
+//! - 屏障在恰好第 `expected` 个不同参与者登记时放行，之后不再接受新参与者。
+//! - 任一时刻未过期租约的许可总数不超过 `max_permits`。
+
+use crate::codec::{BinaryCodec, JsonCodec};
+use crate::core::errors::DistributedError;
+use crate::storage::versioned::{CasError, Version, VersionedStore};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BARRIER_PREFIX: &str = "barrier/";
+const SEMAPHORE_PREFIX: &str = "semaphore/";
+
+type TimeSource = Box<dyn Fn() -> u64 + Send + Sync>;
+
+fn unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 共享 KV 中的 JSON 记录与 CAS 读改写
+struct Records<S> {
+    store: Arc<S>,
+    now: TimeSource,
+}
+
+impl<S: VersionedStore<String, Vec<u8>>> Records<S> {
+    fn new(store: Arc<S>) -> Self {
+        Self {
+            store,
+            now: Box::new(unix_ms),
+        }
+    }
+
+    fn now_ms(&self) -> u64 {
+        (self.now)()
+    }
+
+    fn read<T: Serialize + DeserializeOwned>(
+        &self,
+        key: &String,
+    ) -> Result<Option<(T, Version)>, DistributedError> {
+        self.store
+            .get(key)
+            .map(|(bytes, version)| {
+                JsonCodec
+                    .decode(&bytes)
+                    .map(|record| (record, version))
+                    .ok_or_else(|| DistributedError::Storage(format!("corrupt record {key}")))
+            })
+            .transpose()
+    }
+
+    /// 读改写直到 CAS 成功；`f` 返回 `None` 表示不写入
+    fn update<T, R>(
+        &self,
+        key: &String,
+        mut f: impl FnMut(Option<T>) -> Result<(Option<T>, R), DistributedError>,
+    ) -> Result<R, DistributedError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        loop {
+            let current = self.read::<T>(key)?;
+            let expected = current.as_ref().map(|(_, version)| *version);
+            let (next, result) = f(current.map(|(record, _)| record))?;
+            let Some(next) = next else {
+                return Ok(result);
+            };
+            match self
+                .store
+                .put_if_version(key.clone(), JsonCodec.encode(&next), expected)
+            {
+                Ok(_) => return Ok(result),
+                Err(CasError::Conflict { .. }) => continue,
+                Err(CasError::Storage(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BarrierRecord {
+    expected: usize,
+    participants: BTreeSet<String>,
+    released: bool,
+    expires_at_ms: u64,
+}
+
+/// 跨节点的一次性屏障；同一 KV 上的各实例共享同名屏障
+pub struct DistributedBarrier<S> {
+    records: Records<S>,
+    ttl: Duration,
+    poll_interval: Duration,
+}
+
+impl<S: VersionedStore<String, Vec<u8>>> DistributedBarrier<S> {
+    pub fn new(store: Arc<S>) -> Self {
+        Self {
+            records: Records::new(store),
+            ttl: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    /// 屏障在最后一次登记（或放行）后保留的时长；过期未放行即视为被遗弃
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// 毫秒时间源，各节点须大致同步
+    pub fn with_time_source(mut self, now: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.records.now = Box::new(now);
+        self
+    }
+
+    /// 登记并等待 `expected_count` 个参与者全部到达；屏障过期未放行时返回错误
+    pub async fn enter(
+        &self,
+        barrier_id: &str,
+        participant_id: &str,
+        expected_count: usize,
+    ) -> Result<(), DistributedError> {
+        while !self.try_enter(barrier_id, participant_id, expected_count)? {
+            tokio::time::sleep(self.poll_interval).await;
+        }
+        Ok(())
+    }
+
+    /// 登记（重复登记是幂等的）并返回屏障是否已放行
+    pub fn try_enter(
+        &self,
+        barrier_id: &str,
+        participant_id: &str,
+        expected_count: usize,
+    ) -> Result<bool, DistributedError> {
+        if expected_count == 0 {
+            return Err(DistributedError::Configuration(
+                "barrier expected count must be positive".to_string(),
+            ));
+        }
+        let now = self.records.now_ms();
+        let expires_at_ms = now.saturating_add(self.ttl.as_millis() as u64);
+        let key = format!("{BARRIER_PREFIX}{barrier_id}");
+        self.records.update(&key, |current: Option<BarrierRecord>| {
+            let fresh = || BarrierRecord {
+                expected: expected_count,
+                participants: BTreeSet::new(),
+                released: false,
+                expires_at_ms,
+            };
+            let mut record = match current {
+                // 过期未放行的记录属于被遗弃的上一轮：其中的等待者收到错误，新来者从头开始
+                Some(record) if !record.released && record.expires_at_ms <= now => {
+                    if record.participants.contains(participant_id) {
+                        return Err(abandoned(barrier_id));
+                    }
+                    fresh()
+                }
+                Some(record) => record,
+                None => fresh(),
+            };
+            if record.expected != expected_count {
+                return Err(DistributedError::InvalidState(format!(
+                    "barrier {barrier_id} expects {} participants, not {expected_count}",
+                    record.expected
+                )));
+            }
+            if record.participants.contains(participant_id) {
+                return Ok((None, record.released));
+            }
+            if record.released {
+                return Err(DistributedError::InvalidState(format!(
+                    "barrier {barrier_id} already released"
+                )));
+            }
+            record.participants.insert(participant_id.to_string());
+            record.released = record.participants.len() >= record.expected;
+            record.expires_at_ms = expires_at_ms;
+            let released = record.released;
+            Ok((Some(record), released))
+        })
+    }
+
+    /// 已登记的参与者数；屏障不存在或已被遗弃时为 0
+    pub fn arrived(&self, barrier_id: &str) -> Result<usize, DistributedError> {
+        let key = format!("{BARRIER_PREFIX}{barrier_id}");
+        let now = self.records.now_ms();
+        Ok(self
+            .records
+            .read::<BarrierRecord>(&key)?
+            .filter(|(record, _)| record.released || record.expires_at_ms > now)
+            .map_or(0, |(record, _)| record.participants.len()))
+    }
+
+    /// 删除过期的屏障记录（含已放行的），返回删除数
+    pub fn cleanup(&self) -> Result<usize, DistributedError> {
+        let now = self.records.now_ms();
+        let mut removed = 0;
+        for (key, bytes, version) in self.records.store.scan(prefix_range(BARRIER_PREFIX)) {
+            let expired = JsonCodec
+                .decode(&bytes)
+                .is_none_or(|record: BarrierRecord| record.expires_at_ms <= now);
+            // 冲突说明有节点刚登记，记录已续期
+            if expired && self.records.store.delete_if_version(&key, version).is_ok() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+fn abandoned(barrier_id: &str) -> DistributedError {
+    DistributedError::InvalidState(format!(
+        "barrier {barrier_id} expired before all participants arrived"
+    ))
+}
+
+fn prefix_range(prefix: &str) -> std::ops::Range<String> {
+    // '/' 之后的下一个字符，覆盖全部以 prefix 开头的键
+    let mut end = prefix.to_string();
+    end.pop();
+    end.push('0');
+    prefix.to_string()..end
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HolderLease {
+    permits: u64,
+    expires_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SemaphoreRecord {
+    max_permits: u64,
+    holders: BTreeMap<String, HolderLease>,
+}
+
+impl SemaphoreRecord {
+    fn prune(&mut self, now: u64) {
+        self.holders.retain(|_, lease| lease.expires_at_ms > now);
+    }
+
+    fn held(&self) -> u64 {
+        self.holders.values().map(|lease| lease.permits).sum()
+    }
+}
+
+/// 持有的许可租约；须在 `expires_at_ms` 前续约，否则许可被回收
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemaphoreLease {
+    pub sem_id: String,
+    pub holder: String,
+    pub permits: u64,
+    pub expires_at_ms: u64,
+}
+
+/// 以 KV 记录与持有者租约实现的跨节点计数信号量
+pub struct DistributedSemaphore<S> {
+    records: Records<S>,
+    node_id: String,
+    max_permits: u64,
+    lease: Duration,
+    poll_interval: Duration,
+    seq: AtomicU64,
+}
+
+impl<S: VersionedStore<String, Vec<u8>>> DistributedSemaphore<S> {
+    /// 本实例创建的信号量上限为 `max_permits`；同名信号量的各实例须使用相同上限
+    pub fn new(store: Arc<S>, node_id: impl Into<String>, max_permits: u64) -> Self {
+        Self {
+            records: Records::new(store),
+            node_id: node_id.into(),
+            max_permits,
+            lease: Duration::from_secs(10),
+            poll_interval: Duration::from_millis(10),
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    /// 持有者租约时长
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// 毫秒时间源，各节点须大致同步
+    pub fn with_time_source(mut self, now: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.records.now = Box::new(now);
+        self
+    }
+
+    pub fn max_permits(&self) -> u64 {
+        self.max_permits
+    }
+
+    /// 获取 `permits` 个许可，余量不足时轮询等待
+    pub async fn acquire(
+        &self,
+        sem_id: &str,
+        permits: u64,
+    ) -> Result<SemaphoreLease, DistributedError> {
+        loop {
+            if let Some(lease) = self.try_acquire(sem_id, permits)? {
+                return Ok(lease);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// 余量足够时获取许可，否则返回 `Ok(None)`；过期租约的许可先被回收
+    pub fn try_acquire(
+        &self,
+        sem_id: &str,
+        permits: u64,
+    ) -> Result<Option<SemaphoreLease>, DistributedError> {
+        if permits == 0 || permits > self.max_permits {
+            return Err(DistributedError::Configuration(format!(
+                "invalid permit count {permits} (max {})",
+                self.max_permits
+            )));
+        }
+        let holder = format!(
+            "{}-{}",
+            self.node_id,
+            self.seq.fetch_add(1, Ordering::Relaxed)
+        );
+        let now = self.records.now_ms();
+        let expires_at_ms = now.saturating_add(self.lease.as_millis() as u64);
+        let key = format!("{SEMAPHORE_PREFIX}{sem_id}");
+        let acquired = self
+            .records
+            .update(&key, |current: Option<SemaphoreRecord>| {
+                let mut record = current.unwrap_or_else(|| SemaphoreRecord {
+                    max_permits: self.max_permits,
+                    holders: BTreeMap::new(),
+                });
+                if record.max_permits != self.max_permits {
+                    return Err(DistributedError::InvalidState(format!(
+                        "semaphore {sem_id} has {} permits, not {}",
+                        record.max_permits, self.max_permits
+                    )));
+                }
+                record.prune(now);
+                if record.held() + permits > record.max_permits {
+                    return Ok((None, false));
+                }
+                record.holders.insert(
+                    holder.clone(),
+                    HolderLease {
+                        permits,
+                        expires_at_ms,
+                    },
+                );
+                Ok((Some(record), true))
+            })?;
+        Ok(acquired.then(|| SemaphoreLease {
+            sem_id: sem_id.to_string(),
+            holder,
+            permits,
+            expires_at_ms,
+        }))
+    }
+
+    /// 把租约延长 `lease`；租约已过期时返回错误（许可可能已被他人获取）
+    pub fn renew(&self, lease: &SemaphoreLease) -> Result<SemaphoreLease, DistributedError> {
+        let now = self.records.now_ms();
+        let expires_at_ms = now.saturating_add(self.lease.as_millis() as u64);
+        let key = format!("{SEMAPHORE_PREFIX}{}", lease.sem_id);
+        self.records
+            .update(&key, |current: Option<SemaphoreRecord>| {
+                let mut record = current.ok_or_else(|| expired(lease))?;
+                match record.holders.get_mut(&lease.holder) {
+                    Some(held) if held.expires_at_ms > now => held.expires_at_ms = expires_at_ms,
+                    _ => return Err(expired(lease)),
+                }
+                Ok((Some(record), ()))
+            })?;
+        Ok(SemaphoreLease {
+            expires_at_ms,
+            ..lease.clone()
+        })
+    }
+
+    /// 归还许可；返回租约是否仍然有效（过期后许可已被回收，归还不生效）
+    pub fn release(&self, lease: &SemaphoreLease) -> Result<bool, DistributedError> {
+        let now = self.records.now_ms();
+        let key = format!("{SEMAPHORE_PREFIX}{}", lease.sem_id);
+        self.records
+            .update(&key, |current: Option<SemaphoreRecord>| {
+                let Some(mut record) = current else {
+                    return Ok((None, false));
+                };
+                let live = record
+                    .holders
+                    .remove(&lease.holder)
+                    .is_some_and(|held| held.expires_at_ms > now);
+                record.prune(now);
+                Ok((Some(record), live))
+            })
+    }
+
+    /// 当前可用的许可数（不计过期租约）
+    pub fn available(&self, sem_id: &str) -> Result<u64, DistributedError> {
+        let key = format!("{SEMAPHORE_PREFIX}{sem_id}");
+        let now = self.records.now_ms();
+        Ok(match self.records.read::<SemaphoreRecord>(&key)? {
+            Some((mut record, _)) => {
+                record.prune(now);
+                record.max_permits.saturating_sub(record.held())
+            }
+            None => self.max_permits,
+        })
+    }
+}
+
+fn expired(lease: &SemaphoreLease) -> DistributedError {
+    DistributedError::InvalidState(format!(
+        "lease {} on semaphore {} expired",
+        lease.holder, lease.sem_id
+    ))
+}
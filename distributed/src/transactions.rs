@@ -14,6 +14,12 @@
 //! - Pat Helland, Life beyond Distributed Transactions, 2007.
 use crate::core::errors::DistributedError;
 
+pub mod occ;
+pub mod typed_saga;
+
+pub use occ::{CommitToken, ConflictError, OccStore, OccTransaction, Snapshot};
+pub use typed_saga::{TypedSaga, TypedSagaStep};
+
 pub trait SagaStep {
     fn execute(&mut self) -> Result<(), DistributedError>;
     fn compensate(&mut self) -> Result<(), DistributedError>;
@@ -0,0 +1,160 @@
+//! 乐观并发控制（OCC）
+//!
+//! 设计意图：
+//! - 事务执行期间不加锁：读取的键记入读集，写入先缓冲在事务内。
+//! - 提交时做后向校验：读集中任一键的当前版本晚于快照版本（快照之后被其他事务修改），
+//!   则中止并返回 `ConflictError`；否则在存储锁内一次性应用写集并分配新的提交版本。
+//!
+//! 不变量（草图）：
+//! - 校验与应用在同一临界区内完成，两个读写同一键的并发事务至多一个提交成功。
+//! - 版本单调递增：每次含写入的提交使全局版本 +1，写入键的版本即该提交版本。
+
+use crate::core::errors::DistributedError;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("optimistic transaction conflict on keys {keys:?}")]
+pub struct ConflictError {
+    /// 快照之后被修改的读集键
+    pub keys: Vec<String>,
+}
+
+impl From<ConflictError> for DistributedError {
+    fn from(e: ConflictError) -> Self {
+        DistributedError::InvalidState(e.to_string())
+    }
+}
+
+/// 事务开始时的全局版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot {
+    pub version: u64,
+}
+
+/// 提交成功后的版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitToken {
+    pub version: u64,
+}
+
+struct Versioned<T> {
+    value: T,
+    version: u64,
+}
+
+struct StoreInner<T> {
+    data: HashMap<String, Versioned<T>>,
+    version: u64,
+}
+
+/// OCC 事务共享的版本化存储
+pub struct OccStore<T> {
+    inner: Arc<Mutex<StoreInner<T>>>,
+}
+
+impl<T> Clone for OccStore<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Default for OccStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> OccStore<T> {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(StoreInner {
+                data: HashMap::new(),
+                version: 0,
+            })),
+        }
+    }
+
+    pub fn transaction(&self) -> OccTransaction<T> {
+        OccTransaction {
+            store: self.clone(),
+            read_set: HashSet::new(),
+            write_set: HashMap::new(),
+        }
+    }
+
+    /// 读取已提交的值
+    pub fn get(&self, key: &str) -> Option<T> {
+        let inner = self.inner.lock().unwrap();
+        inner.data.get(key).map(|v| v.value.clone())
+    }
+
+    pub fn version(&self) -> u64 {
+        self.inner.lock().unwrap().version
+    }
+}
+
+/// 单个乐观事务：记录读集，写集缓冲到提交时应用
+pub struct OccTransaction<T> {
+    store: OccStore<T>,
+    read_set: HashSet<String>,
+    write_set: HashMap<String, T>,
+}
+
+impl<T: Clone> OccTransaction<T> {
+    pub fn begin(&self) -> Snapshot {
+        Snapshot {
+            version: self.store.version(),
+        }
+    }
+
+    /// 读取键：优先返回本事务缓冲的写入；否则读取已提交值并记入读集。
+    /// 若该键已在快照之后被修改，读取照常返回，但提交时会因冲突中止。
+    pub fn read(&mut self, key: &str, _snapshot: &Snapshot) -> Option<T> {
+        if let Some(v) = self.write_set.get(key) {
+            return Some(v.clone());
+        }
+        let inner = self.store.inner.lock().unwrap();
+        let entry = inner.data.get(key);
+        self.read_set.insert(key.to_string());
+        entry.map(|v| v.value.clone())
+    }
+
+    pub fn write(&mut self, key: &str, value: T) {
+        self.write_set.insert(key.to_string(), value);
+    }
+
+    /// 校验读集并应用写集
+    pub fn commit(self, snapshot: Snapshot) -> Result<CommitToken, ConflictError> {
+        let mut inner = self.store.inner.lock().unwrap();
+        let mut keys: Vec<String> = self
+            .read_set
+            .iter()
+            .filter(|k| {
+                inner
+                    .data
+                    .get(*k)
+                    .is_some_and(|v| v.version > snapshot.version)
+            })
+            .cloned()
+            .collect();
+        if !keys.is_empty() {
+            keys.sort();
+            return Err(ConflictError { keys });
+        }
+        if self.write_set.is_empty() {
+            return Ok(CommitToken {
+                version: inner.version,
+            });
+        }
+        inner.version += 1;
+        let version = inner.version;
+        for (key, value) in self.write_set {
+            inner.data.insert(key, Versioned { value, version });
+        }
+        Ok(CommitToken { version })
+    }
+}
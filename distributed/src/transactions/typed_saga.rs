@@ -0,0 +1,74 @@
+//! 带类型的 Saga：步骤输出串联到下一步输入
+//!
+//! 设计意图：
+//! - `SagaStep::execute` 不返回数据，而工作流中后续步骤常依赖前一步的结果（如扣款步骤返回的
+//!   流水号供入账步骤使用）。`TypedSagaStep<In, Out>` 以前一步的输出为输入，补偿时拿回自己
+//!   的输出，以便撤销具体的副作用。
+//! - `TypedSaga<Initial, Current>` 的 `then` 只接受 `TypedSagaStep<Current, Next>`，链条在
+//!   编译期校验；`run(initial)` 返回最后一步的输出。
+//!
+//! 不变量（草图）：
+//! - 与 `Saga` 相同：步骤 i 失败时按逆序补偿 1..i-1，补偿错误被忽略，返回步骤 i 的错误。
+//! - 每个已完成步骤恰好收到一次补偿，参数是它执行时产生的输出。
+
+use crate::core::errors::DistributedError;
+
+pub trait TypedSagaStep<In, Out> {
+    fn execute(&mut self, input: In) -> Result<Out, DistributedError>;
+    fn compensate(&mut self, output: Out) -> Result<(), DistributedError>;
+}
+
+type Compensation = Box<dyn FnOnce() -> Result<(), DistributedError> + Send>;
+type Chain<Initial, Current> =
+    Box<dyn FnOnce(Initial, &mut Vec<Compensation>) -> Result<Current, DistributedError> + Send>;
+
+pub struct TypedSaga<Initial, Current = Initial> {
+    chain: Chain<Initial, Current>,
+}
+
+impl<Initial: 'static> Default for TypedSaga<Initial> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Initial: 'static> TypedSaga<Initial> {
+    pub fn new() -> Self {
+        Self {
+            chain: Box::new(|input, _| Ok(input)),
+        }
+    }
+}
+
+impl<Initial: 'static, Current: 'static> TypedSaga<Initial, Current> {
+    /// 追加一步；输出需可克隆，一份传给下一步，一份留作补偿参数
+    pub fn then<Next, S>(self, step: S) -> TypedSaga<Initial, Next>
+    where
+        Next: Clone + Send + 'static,
+        S: TypedSagaStep<Current, Next> + Send + 'static,
+    {
+        let prev = self.chain;
+        TypedSaga {
+            chain: Box::new(move |input, compensations| {
+                let current = prev(input, compensations)?;
+                let mut step = step;
+                let output = step.execute(current)?;
+                let saved = output.clone();
+                compensations.push(Box::new(move || step.compensate(saved)));
+                Ok(output)
+            }),
+        }
+    }
+
+    pub fn run(self, initial: Initial) -> Result<Current, DistributedError> {
+        let mut compensations = Vec::new();
+        let result = (self.chain)(initial, &mut compensations);
+        if result.is_err() {
+            // rollback in reverse
+            while let Some(compensate) = compensations.pop() {
+                let _ = compensate();
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,132 @@
+// 测试目的：集群状态检查快照与差异
+// - 不变量：
+//   1) 快照中的环（每节点虚拟节点数、占比之和为 1）、成员化身号与状态、副本确认率与延迟、
+//      熔断器状态与 Raft 指标都与已知配置一致；
+//   2) 从环、会籍与复制器中移除一个节点后，diff 在各分区报告该节点被移除，相同快照无差异。
+use distributed::ConsistencyLevel;
+use distributed::admin::{ClusterInspector, ClusterSnapshot, SnapshotChange};
+use distributed::consensus::raft::{LogIndex, MinimalRaft, RaftState, Term};
+use distributed::replication::{LocalReplicator, Replicator};
+use distributed::security::{CircuitBreaker, CircuitConfig, CircuitState};
+use distributed::swim::{MembershipView, SwimMemberState};
+use distributed::topology::ConsistentHashRing;
+
+const NODES: [&str; 3] = ["n1", "n2", "n3"];
+
+struct Cluster {
+    ring: ConsistentHashRing,
+    view: MembershipView,
+    replicator: LocalReplicator<String>,
+    breaker: CircuitBreaker,
+    raft: MinimalRaft<String>,
+}
+
+impl Cluster {
+    fn new() -> Self {
+        let mut ring = ConsistentHashRing::new(64);
+        let mut view = MembershipView::new("n1".to_string());
+        for (i, node) in NODES.iter().enumerate() {
+            ring.add_node(node);
+            view.local_update(node, SwimMemberState::Alive, i as u64);
+        }
+        view.local_update("n3", SwimMemberState::Suspect, 5);
+
+        let nodes = NODES.map(String::from).to_vec();
+        let mut replicator = LocalReplicator::new(ring.clone(), nodes);
+        replicator.successes.insert("n3".to_string(), false);
+        for _ in 0..4 {
+            Replicator::<u8>::replicate(&mut replicator, 0, ConsistencyLevel::Quorum).unwrap();
+        }
+
+        let mut raft = MinimalRaft::new();
+        raft.start_election("n1");
+        raft.become_leader(["n2", "n3"]);
+        raft.propose("set x".to_string()).unwrap();
+
+        Self {
+            ring,
+            view,
+            replicator,
+            breaker: CircuitBreaker::new(CircuitConfig {
+                error_threshold: 1,
+                open_ms: 60_000,
+            }),
+            raft,
+        }
+    }
+
+    fn snapshot(&self) -> ClusterSnapshot {
+        ClusterInspector::new("n1")
+            .with_ring(&self.ring)
+            .with_membership(&self.view)
+            .with_replicator(&self.replicator)
+            .with_circuit_breaker("storage", &self.breaker)
+            .with_raft("meta", &self.raft)
+            .snapshot()
+    }
+}
+
+#[test]
+fn snapshot_matches_known_configuration() {
+    let snapshot = Cluster::new().snapshot();
+    assert_eq!(snapshot.node_id, "n1");
+
+    let ring = snapshot.ring.as_ref().unwrap();
+    assert_eq!(ring.vnodes_per_node, 64);
+    assert_eq!(ring.nodes.keys().collect::<Vec<_>>(), NODES);
+    assert!(ring.nodes.values().all(|n| n.vnodes == 64));
+    let total: f64 = ring.nodes.values().map(|n| n.ownership).sum();
+    assert!((total - 1.0).abs() < 1e-9, "{total}");
+    assert!(ring.nodes.values().all(|n| n.ownership > 0.1));
+
+    assert_eq!(snapshot.members["n2"].incarnation, 1);
+    assert_eq!(snapshot.members["n3"].state, SwimMemberState::Suspect);
+    assert_eq!(snapshot.members["n3"].incarnation, 5);
+    assert_eq!(snapshot.replication["n1"].ack_rate, Some(1.0));
+    assert_eq!(snapshot.replication["n3"].ack_rate, Some(0.0));
+    assert_eq!(snapshot.circuit_breakers["storage"], CircuitState::Closed);
+
+    let raft = &snapshot.raft["meta"];
+    assert_eq!(raft.role, RaftState::Leader);
+    assert_eq!(raft.term, Term(1));
+    assert_eq!(raft.last_log_index, LogIndex(1));
+    assert_eq!(raft.match_index.len(), 2);
+
+    let json = snapshot.to_json();
+    assert_eq!(json["raft"]["meta"]["role"], "leader");
+    assert_eq!(json["members"]["n3"]["state"], "Suspect");
+    let back: ClusterSnapshot = serde_json::from_value(json).unwrap();
+    assert_eq!(back, snapshot);
+}
+
+#[test]
+fn diff_detects_removed_node() {
+    let mut cluster = Cluster::new();
+    let before = cluster.snapshot();
+    assert!(before.diff(&before).is_empty());
+
+    cluster.ring.remove_node("n3");
+    cluster.view.members.remove("n3");
+    cluster.replicator.nodes.retain(|n| n != "n3");
+    let after = cluster.snapshot();
+
+    let changes = before.diff(&after);
+    let removed: Vec<_> = changes
+        .iter()
+        .filter(|c| matches!(c, SnapshotChange::Removed { .. }))
+        .map(|c| (c.section(), c.key()))
+        .collect();
+    assert_eq!(
+        removed,
+        [("members", "n3"), ("replication", "n3"), ("ring", "n3")]
+    );
+    // 余下节点接管 n3 的哈希区间，占比变化
+    assert!(changes.iter().any(|c| matches!(
+        c,
+        SnapshotChange::Changed { section, key, .. } if section == "ring" && key == "n1"
+    )));
+    assert!(after.diff(&before).iter().any(|c| matches!(
+        c,
+        SnapshotChange::Added { section, key, .. } if section == "ring" && key == "n3"
+    )));
+}
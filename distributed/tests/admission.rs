@@ -0,0 +1,67 @@
+// 测试目的：准入控制按 限流 → 熔断 → 负载丢弃 的顺序检查
+// - 不变量：1) 任一策略拒绝时返回对应原因，所有策略放行时准入成功；
+//           2) 守卫丢弃时向熔断器回报结果并归还负载许可。
+use distributed::core::errors::DistributedError;
+use distributed::resilience::{AdmissionController, DenialReason, LoadShedder, ShedError};
+use distributed::security::{CircuitBreaker, CircuitConfig, CircuitState, TokenBucket};
+use std::sync::{Arc, Mutex};
+
+fn controller(tokens: u64, error_threshold: u32, max_permits: usize) -> AdmissionController {
+    AdmissionController::new(
+        Arc::new(Mutex::new(TokenBucket::new(tokens, 0))),
+        Arc::new(Mutex::new(CircuitBreaker::new(CircuitConfig {
+            error_threshold,
+            open_ms: 60_000,
+        }))),
+        LoadShedder::new(max_permits),
+    )
+}
+
+#[test]
+fn admits_when_all_policies_permit() {
+    let ac = controller(10, 3, 10);
+    let guard = ac.check(0).expect("all policies permit");
+    assert_eq!(ac.shedder().in_flight(), 1);
+    guard.succeed();
+    assert_eq!(ac.shedder().in_flight(), 0);
+    assert_eq!(
+        ac.circuit_breaker().lock().unwrap().state(),
+        CircuitState::Closed
+    );
+}
+
+#[test]
+fn reports_each_denial_reason() {
+    // 令牌耗尽
+    let ac = controller(1, 3, 10);
+    ac.check(0).unwrap().succeed();
+    let denied = ac.check(0).unwrap_err();
+    assert_eq!(denied.reason, DenialReason::RateLimited);
+    let err: DistributedError = denied.into();
+    assert!(matches!(err, DistributedError::Overloaded(_)));
+
+    // 失败回报使熔断器打开
+    let ac = controller(10, 1, 10);
+    ac.check(0).unwrap().fail();
+    assert_eq!(ac.check(0).unwrap_err().reason, DenialReason::CircuitOpen);
+
+    // 负载许可用尽
+    let ac = controller(10, 3, 1);
+    let _held = ac.check(0).unwrap();
+    assert_eq!(
+        ac.check(255).unwrap_err().reason,
+        DenialReason::Shed(ShedError::AtCapacity { max_permits: 1 })
+    );
+}
+
+#[test]
+fn unmarked_guard_counts_as_failure() {
+    let ac = controller(10, 2, 10);
+    drop(ac.check(0).unwrap());
+    drop(ac.check(0).unwrap());
+    assert_eq!(
+        ac.circuit_breaker().lock().unwrap().state(),
+        CircuitState::Open
+    );
+    assert_eq!(ac.shedder().in_flight(), 0);
+}
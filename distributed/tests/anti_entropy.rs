@@ -0,0 +1,94 @@
+// 测试目的：Merkle 树反熵对账
+// - 不变量：
+//   1) 1000 个相同条目中有 10 处差异（改值、仅一侧存在）时，diff 恰好返回这 10 个键；
+//   2) 相同数据的树根相同；只有分歧的桶需要逐键比较；
+//   3) 双向对账后两侧数据与树根一致，且版本高者胜出。
+use distributed::storage::anti_entropy::{
+    MerkleReconciler, VersionedData, build_merkle, diff, sync,
+};
+
+fn dataset(n: usize) -> VersionedData {
+    (0..n)
+        .map(|i| {
+            (
+                format!("key-{i:04}"),
+                (1, format!("value-{i}").into_bytes()),
+            )
+        })
+        .collect()
+}
+
+/// 在副本 b 上制造 10 处差异，返回分歧键
+fn diverge(a: &mut VersionedData, b: &mut VersionedData) -> Vec<String> {
+    let mut expected = Vec::new();
+    // 4 个键在 b 上被更新
+    for i in [3, 250, 517, 999] {
+        let key = format!("key-{i:04}");
+        b.insert(key.clone(), (2, b"updated".to_vec()));
+        expected.push(key);
+    }
+    // 3 个键只在 b 上
+    for i in 0..3 {
+        let key = format!("extra-b-{i}");
+        b.insert(key.clone(), (1, b"new".to_vec()));
+        expected.push(key);
+    }
+    // 3 个键只在 a 上（其中一个在 b 上被删除）
+    for i in 0..2 {
+        let key = format!("extra-a-{i}");
+        a.insert(key.clone(), (1, b"new".to_vec()));
+        expected.push(key);
+    }
+    b.remove("key-0042");
+    expected.push("key-0042".to_string());
+    expected.sort();
+    expected
+}
+
+#[test]
+fn diff_finds_exactly_the_divergent_keys() {
+    let mut a = dataset(1000);
+    let mut b = dataset(1000);
+    assert_eq!(build_merkle(&a).root(), build_merkle(&b).root());
+    assert!(diff(&build_merkle(&a), &build_merkle(&b)).is_empty());
+
+    let expected = diverge(&mut a, &mut b);
+    let divergent = diff(&build_merkle(&a), &build_merkle(&b));
+    assert_eq!(divergent.len(), 10);
+    assert_eq!(divergent, expected);
+    assert_eq!(diff(&build_merkle(&b), &build_merkle(&a)), expected);
+}
+
+#[test]
+fn sync_takes_only_newer_remote_entries() {
+    let mut local = dataset(10);
+    let mut remote = dataset(10);
+    remote.insert("key-0001".into(), (5, b"remote".to_vec()));
+    local.insert("key-0002".into(), (7, b"local".to_vec()));
+    remote.insert("key-0002".into(), (3, b"stale".to_vec()));
+
+    let keys = diff(&build_merkle(&local), &build_merkle(&remote));
+    assert_eq!(keys, ["key-0001", "key-0002"]);
+    assert_eq!(sync(&mut local, remote.clone(), keys), 1);
+    assert_eq!(local["key-0001"], (5, b"remote".to_vec()));
+    assert_eq!(local["key-0002"], (7, b"local".to_vec()));
+}
+
+#[test]
+fn reconcile_converges_both_replicas() {
+    let mut a = dataset(1000);
+    let mut b = dataset(1000);
+    diverge(&mut a, &mut b);
+    // 同版本不同值的冲突：两侧收敛到同一个值
+    a.insert("key-0600".into(), (1, b"conflict-a".to_vec()));
+
+    let reconciler = MerkleReconciler::default();
+    let report = reconciler.reconcile(&mut a, &mut b);
+    assert_eq!(report.divergent.len(), 11);
+    assert_eq!(report.pulled, 8);
+    assert_eq!(report.pushed, 3);
+
+    assert_eq!(reconciler.build(&a).root(), reconciler.build(&b).root());
+    assert_eq!(a["key-0517"], (2, b"updated".to_vec()));
+    assert!(a.contains_key("key-0042") && b.contains_key("extra-a-1"));
+}
@@ -0,0 +1,134 @@
+// 测试目的：服务发现的主动健康检查
+// - 不变量：1) 后台巡检按 health_check_interval 调用登记的检查，健康/不健康交替时健康分在 1.0 与 0.0
+//              之间随之变化，实例的 is_healthy 同步更新；降级记为 0.5 且仍可服务；
+//           2) 巡检清理超过 TTL 且未被检查刷新的实例；注销检查后不再有健康分。
+#[cfg(feature = "runtime-tokio")]
+mod reaper {
+    use distributed::service_discovery::{HealthCheckFuture, HealthStatus};
+    use distributed::{
+        AsyncHealthCheck, ServiceDiscovery, ServiceDiscoveryConfig, ServiceDiscoveryManager,
+        ServiceInstance,
+    };
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// 依次返回 `script` 中的结果并循环
+    struct Scripted {
+        script: Vec<HealthStatus>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl AsyncHealthCheck for Scripted {
+        fn check(&self) -> HealthCheckFuture<'_> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let status = self.script[call % self.script.len()].clone();
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                status
+            })
+        }
+    }
+
+    fn instance(id: &str) -> ServiceInstance {
+        ServiceInstance::new(
+            id.to_string(),
+            "orders".to_string(),
+            "127.0.0.1:8080".parse().unwrap(),
+            HashMap::new(),
+        )
+    }
+
+    fn manager(interval: Duration, ttl: Duration) -> ServiceDiscoveryManager {
+        ServiceDiscoveryManager::new(ServiceDiscoveryConfig {
+            health_check_interval: interval,
+            service_ttl: ttl,
+            ..ServiceDiscoveryConfig::default()
+        })
+    }
+
+    fn is_healthy(manager: &ServiceDiscoveryManager, id: &str) -> bool {
+        manager
+            .get_services("orders")
+            .iter()
+            .find(|i| i.id == id)
+            .unwrap()
+            .is_healthy
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn alternating_check_moves_score_and_instance_health() {
+        let mut manager = manager(Duration::from_secs(1), Duration::from_secs(300));
+        manager.register_service(instance("orders-1")).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        manager.register_health_check(
+            "orders-1",
+            Box::new(Scripted {
+                script: vec![
+                    HealthStatus::Healthy,
+                    HealthStatus::Unhealthy("connection refused".to_string()),
+                ],
+                calls: calls.clone(),
+            }),
+        );
+        assert_eq!(manager.service_health_score("orders-1"), None);
+
+        let reaper = manager.spawn_health_reaper();
+        let mut scores = Vec::new();
+        for _ in 0..4 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            scores.push(manager.service_health_score("orders-1").unwrap());
+            assert_eq!(
+                is_healthy(&manager, "orders-1"),
+                *scores.last().unwrap() > 0.0
+            );
+            tokio::time::sleep(Duration::from_millis(990)).await;
+        }
+        reaper.abort();
+        assert_eq!(scores, [1.0, 0.0, 1.0, 0.0]);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(
+            manager.service_health("orders-1"),
+            Some(HealthStatus::Unhealthy("connection refused".to_string()))
+        );
+
+        manager.register_health_check(
+            "orders-1",
+            Box::new(Scripted {
+                script: vec![HealthStatus::Degraded("slow disk".to_string())],
+                calls,
+            }),
+        );
+        manager.run_health_checks().await;
+        assert_eq!(manager.service_health_score("orders-1"), Some(0.5));
+        assert!(is_healthy(&manager, "orders-1"));
+    }
+
+    /// 实例的过期按真实时间计算，此用例不暂停时钟
+    #[tokio::test]
+    async fn reaper_drops_expired_unchecked_instances() {
+        let mut manager = manager(Duration::from_millis(20), Duration::from_millis(200));
+        manager.register_service(instance("checked")).unwrap();
+        manager.register_service(instance("silent")).unwrap();
+        manager.register_health_check(
+            "checked",
+            Box::new(Scripted {
+                script: vec![HealthStatus::Healthy],
+                calls: Arc::new(AtomicUsize::new(0)),
+            }),
+        );
+        let reaper = manager.spawn_health_reaper();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let ids: Vec<String> = manager
+            .get_services("orders")
+            .into_iter()
+            .map(|i| i.id)
+            .collect();
+        assert_eq!(ids, ["checked"]);
+
+        manager.unregister_health_check("checked");
+        assert_eq!(manager.service_health_score("checked"), None);
+        reaper.abort();
+    }
+}
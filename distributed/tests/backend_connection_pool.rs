@@ -0,0 +1,119 @@
+// 测试目的：通用后端连接池
+// - 不变量：
+//   1) 并发借出时同时在用的连接数与新建连接数均不超过 max_size；
+//   2) 健康检查失败的连接不会被复用；
+//   3) 空闲超过 max_idle 的连接被关闭。
+#[cfg(feature = "runtime-tokio")]
+mod backend_connection_pool {
+    use distributed::DistributedError;
+    use distributed::network::pool::{Connect, ConnectionPool, PoolConfig, PoolError};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Clone)]
+    struct FakeChannel {
+        id: usize,
+        healthy: Arc<AtomicBool>,
+    }
+
+    impl Connect for FakeChannel {
+        async fn connect(_endpoint: &str) -> Result<Self, DistributedError> {
+            Ok(FakeChannel {
+                id: NEXT_ID.fetch_add(1, Ordering::SeqCst),
+                healthy: Arc::new(AtomicBool::new(true)),
+            })
+        }
+    }
+
+    fn pool(max_size: usize, max_idle: Duration) -> ConnectionPool<FakeChannel> {
+        let config = PoolConfig {
+            max_size,
+            min_idle: 0,
+            max_idle,
+            acquire_timeout: Duration::from_secs(5),
+        };
+        ConnectionPool::new("backend:50051", config)
+            .with_health_check(|c: &FakeChannel| c.healthy.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn max_size_is_never_exceeded_under_concurrency() {
+        let pool = pool(4, Duration::from_secs(60));
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let ids = Arc::new(std::sync::Mutex::new(std::collections::HashSet::new()));
+
+        let mut handles = Vec::new();
+        for _ in 0..64 {
+            let (pool, active, peak, ids) =
+                (pool.clone(), active.clone(), peak.clone(), ids.clone());
+            handles.push(tokio::spawn(async move {
+                let conn = pool.acquire().await.unwrap();
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                ids.lock().unwrap().insert(conn.id);
+                tokio::time::sleep(Duration::from_millis(2)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                pool.release(conn);
+            }));
+        }
+        for h in handles {
+            h.await.unwrap();
+        }
+
+        assert!(peak.load(Ordering::SeqCst) <= 4);
+        assert!(ids.lock().unwrap().len() <= 4);
+        assert_eq!(pool.in_use(), 0);
+        assert!(pool.idle_count() <= 4);
+    }
+
+    #[tokio::test]
+    async fn health_failed_connections_are_not_reused() {
+        let pool = pool(2, Duration::from_secs(60));
+        let conn = pool.acquire().await.unwrap();
+        let first = conn.id;
+        pool.release(conn);
+
+        // 归还后健康状态恶化：借出前的检查应将其丢弃
+        let conn = pool.acquire().await.unwrap();
+        assert_eq!(conn.id, first);
+        let healthy = conn.healthy.clone();
+        pool.release(conn);
+        healthy.store(false, Ordering::SeqCst);
+
+        let conn = pool.acquire().await.unwrap();
+        assert_ne!(conn.id, first);
+        assert_eq!(pool.idle_count(), 0);
+
+        // 归还时即不健康的连接不会进入空闲队列
+        conn.healthy.store(false, Ordering::SeqCst);
+        pool.release(conn);
+        assert_eq!(pool.idle_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn idle_connections_expire_and_acquire_times_out_when_exhausted() {
+        let pool = pool(1, Duration::from_millis(10));
+        let conn = pool.acquire().await.unwrap();
+        let first = conn.id;
+        pool.release(conn);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        pool.reap_idle();
+        assert_eq!(pool.idle_count(), 0);
+
+        let held = pool.acquire().await.unwrap();
+        assert_ne!(held.id, first);
+
+        let config = PoolConfig {
+            max_size: 1,
+            acquire_timeout: Duration::from_millis(10),
+            ..PoolConfig::default()
+        };
+        let tight: ConnectionPool<FakeChannel> = ConnectionPool::new("backend:50051", config);
+        let _held = tight.acquire().await.unwrap();
+        assert!(matches!(tight.acquire().await, Err(PoolError::Timeout(_))));
+    }
+}
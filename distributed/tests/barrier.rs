@@ -0,0 +1,92 @@
+// 测试目的：分布式屏障
+// - 不变量：
+//   1) 第 expected 个不同参与者到达前，任何等待者都不会放行；
+//   2) 每轮恰有一个等待者（最后到达者）拿到 is_leader；
+//   3) 重复到达被拒绝；reset 中止本轮并使等待者收到错误，屏障可继续使用。
+#[cfg(feature = "runtime-tokio")]
+mod barrier {
+    use distributed::sync::Barrier;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn no_participant_passes_before_the_last_arrives() {
+        let barrier = Barrier::new(5);
+        let arrived = Arc::new(AtomicUsize::new(0));
+
+        let spawn = |i: usize| {
+            let (barrier, arrived) = (barrier.clone(), arrived.clone());
+            tokio::spawn(async move {
+                arrived.fetch_add(1, Ordering::SeqCst);
+                let token = barrier.wait(&format!("w{i}")).await.unwrap();
+                // 放行时所有参与者都已到达
+                assert_eq!(arrived.load(Ordering::SeqCst), 5);
+                token
+            })
+        };
+
+        let mut handles: Vec<_> = (0..4).map(spawn).collect();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(barrier.arrived(), 4);
+        assert!(handles.iter().all(|h| !h.is_finished()));
+
+        handles.push(spawn(4));
+        let mut leaders = 0;
+        for h in handles {
+            let token = h.await.unwrap();
+            assert_eq!(token.generation, 0);
+            leaders += token.is_leader as usize;
+        }
+        assert_eq!(leaders, 1);
+        assert_eq!(barrier.arrived(), 0);
+    }
+
+    #[tokio::test]
+    async fn duplicate_arrival_is_rejected_and_barrier_is_reusable() {
+        let barrier = Barrier::new(2);
+        let first = tokio::spawn({
+            let barrier = barrier.clone();
+            async move { barrier.wait("a").await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(barrier.wait("a").await.is_err());
+
+        let token = barrier.wait("b").await.unwrap();
+        assert!(token.is_leader);
+        assert!(!first.await.unwrap().unwrap().is_leader);
+
+        // 下一轮
+        let again = tokio::spawn({
+            let barrier = barrier.clone();
+            async move { barrier.wait("a").await }
+        });
+        let token = barrier.wait("b").await.unwrap();
+        assert_eq!(token.generation, 1);
+        assert_eq!(again.await.unwrap().unwrap().generation, 1);
+    }
+
+    #[tokio::test]
+    async fn reset_aborts_waiting_participants() {
+        let barrier = Barrier::new(3);
+        let waiter = tokio::spawn({
+            let barrier = barrier.clone();
+            async move { barrier.wait("a").await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        barrier.reset().await;
+        assert!(waiter.await.unwrap().is_err());
+        assert_eq!(barrier.arrived(), 0);
+
+        let handles: Vec<_> = ["a", "b", "c"]
+            .into_iter()
+            .map(|id| {
+                let barrier = barrier.clone();
+                tokio::spawn(async move { barrier.wait(id).await })
+            })
+            .collect();
+        for h in handles {
+            assert_eq!(h.await.unwrap().unwrap().generation, 1);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// 测试目的：尽力而为组播与可靠组播
+// - 不变量：
+//   1) 发送方送达 5 个节点中的 2 个后崩溃，可靠组播经转发仍到达全部存活节点，
+//      且每个节点恰好交付一次；尽力而为组播只到达已送达的节点；
+//   2) 回调按主题分发，未订阅的主题不会触发；
+//   3) 丢包的成员由重传补齐，全部确认后不再有待重传消息。
+#[cfg(feature = "runtime-tokio")]
+mod broadcast {
+    use distributed::DistributedError;
+    use distributed::broadcast::{BestEffortBroadcast, BroadcastConfig, ReliableBroadcast};
+    use distributed::network::Deadline;
+    use distributed::storage::replication::{ReplicateAck, ReplicateRequest, ReplicationTransport};
+    use std::collections::{HashMap, HashSet};
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Clone)]
+    enum Receiver {
+        Reliable(ReliableBroadcast<SimTransport>),
+        BestEffort(BestEffortBroadcast<SimTransport>),
+    }
+
+    /// 模拟网络：发送配额耗尽的节点视为崩溃，不再收发
+    #[derive(Default)]
+    struct Net {
+        nodes: Mutex<HashMap<String, Receiver>>,
+        send_budget: Mutex<HashMap<String, usize>>,
+        crashed: Mutex<HashSet<String>>,
+        drop_next: Mutex<HashMap<String, usize>>,
+    }
+
+    impl Net {
+        fn admit(&self, from: &str, to: &str) -> Result<Receiver, DistributedError> {
+            let mut crashed = self.crashed.lock().unwrap();
+            if crashed.contains(from) || crashed.contains(to) {
+                return Err(DistributedError::Network(format!(
+                    "{from} -> {to}: crashed"
+                )));
+            }
+            if let Some(budget) = self.send_budget.lock().unwrap().get_mut(from) {
+                *budget -= 1;
+                if *budget == 0 {
+                    crashed.insert(from.to_string());
+                }
+            }
+            if let Some(n) = self.drop_next.lock().unwrap().get_mut(to)
+                && *n > 0
+            {
+                *n -= 1;
+                return Err(DistributedError::Network(format!(
+                    "{from} -> {to}: dropped"
+                )));
+            }
+            Ok(self.nodes.lock().unwrap()[to].clone())
+        }
+    }
+
+    struct SimTransport {
+        from: String,
+        net: Arc<Net>,
+    }
+
+    impl ReplicationTransport for SimTransport {
+        fn send(
+            &self,
+            node: &str,
+            request: ReplicateRequest,
+            _deadline: Deadline,
+        ) -> impl Future<Output = Result<ReplicateAck, DistributedError>> + Send {
+            let admitted = self.net.admit(&self.from, node);
+            let node = node.to_string();
+            async move {
+                match admitted? {
+                    Receiver::Reliable(r) => r.receive(&request)?,
+                    Receiver::BestEffort(b) => b.receive(&request)?,
+                };
+                Ok(ReplicateAck {
+                    applied: true,
+                    node_id: node,
+                    error: None,
+                })
+            }
+        }
+    }
+
+    const NODES: [&str; 5] = ["n1", "n2", "n3", "n4", "n5"];
+
+    fn members() -> Vec<String> {
+        NODES.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn transport(net: &Arc<Net>, from: &str) -> SimTransport {
+        SimTransport {
+            from: from.to_string(),
+            net: net.clone(),
+        }
+    }
+
+    fn counter(
+        counts: &Arc<Mutex<HashMap<String, usize>>>,
+        node: &str,
+    ) -> impl Fn(&distributed::broadcast::BroadcastMessage) + Send + Sync + 'static {
+        let counts = counts.clone();
+        let node = node.to_string();
+        move |msg| {
+            assert_eq!(msg.payload, b"v1");
+            *counts.lock().unwrap().entry(node.clone()).or_default() += 1;
+        }
+    }
+
+    async fn eventually(mut done: impl FnMut() -> bool) {
+        for _ in 0..100 {
+            if done() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(done(), "condition not reached in time");
+    }
+
+    #[tokio::test]
+    async fn reliable_broadcast_survives_sender_crash_after_partial_delivery() {
+        let net = Arc::new(Net::default());
+        let counts = Arc::new(Mutex::new(HashMap::new()));
+        let mut nodes = Vec::new();
+        for id in NODES {
+            let node = ReliableBroadcast::new(id, transport(&net, id), members());
+            node.subscribe("config", counter(&counts, id));
+            net.nodes
+                .lock()
+                .unwrap()
+                .insert(id.to_string(), Receiver::Reliable(node.clone()));
+            nodes.push(node);
+        }
+        net.send_budget.lock().unwrap().insert("n1".into(), 2);
+
+        let report = nodes[0].broadcast("config", b"v1".to_vec()).await.unwrap();
+        assert_eq!(report.delivered.len(), 2);
+        assert_eq!(report.failed.len(), 2);
+        assert!(net.crashed.lock().unwrap().contains("n1"));
+
+        let all_alive = || {
+            let counts = counts.lock().unwrap();
+            NODES[1..].iter().all(|n| counts.get(*n) == Some(&1))
+        };
+        eventually(all_alive).await;
+        eventually(|| nodes[1..].iter().all(|n| n.pending() == 0)).await;
+
+        // 转发全部结束后，每个节点仍只交付一次
+        let counts = counts.lock().unwrap();
+        assert_eq!(counts.len(), 5);
+        assert!(counts.values().all(|c| *c == 1), "{counts:?}");
+    }
+
+    #[tokio::test]
+    async fn best_effort_broadcast_reaches_only_contacted_nodes() {
+        let net = Arc::new(Net::default());
+        let counts = Arc::new(Mutex::new(HashMap::new()));
+        let mut nodes = Vec::new();
+        for id in NODES {
+            let node = BestEffortBroadcast::new(id, transport(&net, id), members());
+            node.subscribe("config", counter(&counts, id));
+            net.nodes
+                .lock()
+                .unwrap()
+                .insert(id.to_string(), Receiver::BestEffort(node.clone()));
+            nodes.push(node);
+        }
+        net.send_budget.lock().unwrap().insert("n1".into(), 2);
+
+        let report = nodes[0].broadcast("config", b"v1".to_vec()).await.unwrap();
+        assert_eq!(report.delivered.len(), 2);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let counts = counts.lock().unwrap();
+        // 发送方自身 + 已送达的 2 个节点
+        assert_eq!(counts.len(), 3);
+        for node in &report.delivered {
+            assert_eq!(counts[node], 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn callbacks_are_per_topic_and_dropped_messages_are_retransmitted() {
+        let net = Arc::new(Net::default());
+        let config = BroadcastConfig {
+            retransmit_interval: Duration::from_millis(20),
+            ..BroadcastConfig::default()
+        };
+        let pair = vec!["a".to_string(), "b".to_string()];
+        let a = ReliableBroadcast::new("a", transport(&net, "a"), pair.clone())
+            .with_config(config.clone());
+        let b = ReliableBroadcast::new("b", transport(&net, "b"), pair).with_config(config);
+        for (id, node) in [("a", &a), ("b", &b)] {
+            net.nodes
+                .lock()
+                .unwrap()
+                .insert(id.to_string(), Receiver::Reliable(node.clone()));
+        }
+
+        let on_config = Arc::new(AtomicUsize::new(0));
+        let on_drain = Arc::new(AtomicUsize::new(0));
+        b.subscribe("config", {
+            let hits = on_config.clone();
+            move |_| {
+                hits.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        b.subscribe("drain", {
+            let hits = on_drain.clone();
+            move |_| {
+                hits.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        net.drop_next.lock().unwrap().insert("b".into(), 2);
+        let report = a.broadcast("config", b"v1".to_vec()).await.unwrap();
+        assert_eq!(report.failed, vec!["b".to_string()]);
+        assert_eq!(a.pending(), 1);
+
+        let driver = a.start();
+        eventually(|| on_config.load(Ordering::SeqCst) == 1).await;
+        eventually(|| a.pending() == 0).await;
+        driver.abort();
+        assert_eq!(on_config.load(Ordering::SeqCst), 1);
+        assert_eq!(on_drain.load(Ordering::SeqCst), 0);
+    }
+}
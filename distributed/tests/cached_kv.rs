@@ -0,0 +1,139 @@
+// 测试目的：复制 KV 前的读穿透缓存
+// - 不变量：
+//   1) Eventual 读首次穿透后命中缓存，不再访问底层；
+//   2) 其他副本写入的应用事件使缓存条目失效，之后读到新值；本地写入同样失效；
+//   3) Linearizable 读总是绕过缓存，不会读到缓存中的旧值。
+use distributed::consistency::ConsistencyLevel;
+use distributed::core::errors::DistributedError;
+use distributed::storage::cached_kv::ApplyListener;
+use distributed::storage::{ApplyEvent, CachedKv, ReplicatedKv};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 模拟副本：每次读取耗时 `read_delay`，可模拟远端写入
+#[derive(Clone, Default)]
+struct Replica {
+    data: Arc<Mutex<HashMap<String, String>>>,
+    listeners: Arc<Mutex<Vec<ApplyListener<String>>>>,
+    reads: Arc<AtomicUsize>,
+    read_delay: Duration,
+}
+
+impl Replica {
+    /// 来自其他副本的写入被本副本应用
+    fn apply_remote(&self, key: &str, value: &str, notify: bool) {
+        self.data
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        if notify {
+            let event = ApplyEvent {
+                key: key.to_string(),
+                origin: "n2".to_string(),
+            };
+            for listener in self.listeners.lock().unwrap().iter() {
+                listener(&event);
+            }
+        }
+    }
+
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+}
+
+impl ReplicatedKv<String, String> for Replica {
+    fn get(&self, key: &String, _: ConsistencyLevel) -> Result<Option<String>, DistributedError> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        std::thread::sleep(self.read_delay);
+        Ok(self.data.lock().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: String, value: String, _: ConsistencyLevel) -> Result<(), DistributedError> {
+        self.data.lock().unwrap().insert(key, value);
+        Ok(())
+    }
+
+    fn delete(&self, key: &String, _: ConsistencyLevel) -> Result<(), DistributedError> {
+        self.data.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn subscribe(&self, listener: ApplyListener<String>) {
+        self.listeners.lock().unwrap().push(listener);
+    }
+}
+
+const EVENTUAL: ConsistencyLevel = ConsistencyLevel::Eventual;
+
+#[test]
+fn eventual_reads_hit_cache_after_first_fetch() {
+    let replica = Replica {
+        read_delay: Duration::from_millis(20),
+        ..Default::default()
+    };
+    replica.apply_remote("hot", "v1", false);
+    let kv = CachedKv::new(replica.clone());
+    let key = "hot".to_string();
+
+    assert_eq!(kv.get(&key, EVENTUAL).unwrap().as_deref(), Some("v1"));
+    let start = Instant::now();
+    for _ in 0..100 {
+        assert_eq!(kv.get(&key, EVENTUAL).unwrap().as_deref(), Some("v1"));
+    }
+    assert!(start.elapsed() < Duration::from_millis(20));
+    assert_eq!(replica.reads(), 1);
+    assert_eq!((kv.stats().hits(), kv.stats().misses()), (100, 1));
+
+    // 负缓存：不存在的键只穿透一次
+    let missing = "missing".to_string();
+    assert_eq!(kv.get(&missing, EVENTUAL).unwrap(), None);
+    assert_eq!(kv.get(&missing, EVENTUAL).unwrap(), None);
+    assert_eq!(replica.reads(), 2);
+}
+
+#[test]
+fn remote_and_local_writes_invalidate() {
+    let replica = Replica::default();
+    replica.apply_remote("k", "v1", false);
+    let kv = CachedKv::new(replica.clone()).with_capacity(2);
+    let key = "k".to_string();
+
+    kv.get(&key, EVENTUAL).unwrap();
+    replica.apply_remote("k", "v2", true);
+    assert_eq!(kv.stats().invalidations(), 1);
+    assert_eq!(kv.get(&key, EVENTUAL).unwrap().as_deref(), Some("v2"));
+
+    kv.put(key.clone(), "v3".into(), EVENTUAL).unwrap();
+    assert_eq!(kv.stats().invalidations(), 2);
+    assert_eq!(kv.get(&key, EVENTUAL).unwrap().as_deref(), Some("v3"));
+
+    // 容量为 2：第三个键淘汰最久未用的条目
+    for k in ["a", "b"] {
+        kv.get(&k.to_string(), EVENTUAL).unwrap();
+    }
+    assert_eq!(kv.len(), 2);
+    let reads = replica.reads();
+    kv.get(&key, EVENTUAL).unwrap();
+    assert_eq!(replica.reads(), reads + 1);
+}
+
+#[test]
+fn linearizable_reads_never_see_stale_cache() {
+    let replica = Replica::default();
+    replica.apply_remote("k", "v1", false);
+    let kv = CachedKv::new(replica.clone()).with_ttl(Duration::from_secs(60));
+    let key = "k".to_string();
+    kv.get(&key, EVENTUAL).unwrap();
+
+    // 应用事件尚未到达：缓存中是旧值
+    replica.apply_remote("k", "v2", false);
+    assert_eq!(kv.get(&key, EVENTUAL).unwrap().as_deref(), Some("v1"));
+    for level in [ConsistencyLevel::Linearizable, ConsistencyLevel::Strong] {
+        assert_eq!(kv.get(&key, level).unwrap().as_deref(), Some("v2"));
+    }
+    // 绕过的读不计入命中/未命中
+    assert_eq!((kv.stats().hits(), kv.stats().misses()), (1, 1));
+}
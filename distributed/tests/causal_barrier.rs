@@ -0,0 +1,78 @@
+// 测试目的：因果屏障
+// - 不变量：
+//   1) 屏障在某一分量上超前于本地视图时等待不会完成，本地视图推进该分量后才放行；
+//   2) 超时返回 DeadlineExceeded，并列出仍落后的分量。
+use distributed::consistency::VectorClock;
+use distributed::core::CausalBarrier;
+
+fn clock(entries: &[(&str, u64)]) -> VectorClock {
+    let mut clock = VectorClock::new();
+    for (node, n) in entries {
+        for _ in 0..*n {
+            clock.increment(node);
+        }
+    }
+    clock
+}
+
+#[test]
+fn barrier_passes_only_when_every_component_caught_up() {
+    let barrier = CausalBarrier::new(clock(&[("a", 2), ("b", 1)]));
+    assert!(!barrier.is_passed(&clock(&[("a", 1), ("b", 1)])));
+    assert_eq!(
+        barrier.missing(&clock(&[("b", 3)])),
+        [("a".to_string(), 0, 2)]
+    );
+    assert!(barrier.is_passed(&clock(&[("a", 2), ("b", 1)])));
+    assert!(barrier.is_passed(&clock(&[("a", 3), ("b", 1), ("c", 1)])));
+    assert!(CausalBarrier::default().is_passed(&VectorClock::new()));
+}
+
+#[cfg(feature = "runtime-tokio")]
+mod wait {
+    use super::clock;
+    use distributed::core::clocks::wait_for_causal_barrier;
+    use distributed::core::{CausalBarrier, DeadlineExceeded};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_completes_only_after_local_clock_advances() {
+        let barrier = clock(&[("a", 1), ("b", 2)]);
+        let local = Arc::new(Mutex::new(clock(&[("a", 1), ("b", 1)])));
+        let waiter = tokio::spawn({
+            let (barrier, local) = (barrier.clone(), local.clone());
+            async move {
+                wait_for_causal_barrier(
+                    &barrier,
+                    local,
+                    Duration::from_millis(10),
+                    Duration::from_secs(5),
+                )
+                .await
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiter.is_finished());
+        local.lock().unwrap().increment("b");
+        assert_eq!(waiter.await.unwrap(), Ok(()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout_reports_lagging_components() {
+        let barrier = CausalBarrier::new(clock(&[("a", 1), ("b", 3)]));
+        let local = Arc::new(Mutex::new(clock(&[("a", 1), ("b", 1)])));
+        let err = barrier
+            .wait(local, Duration::from_millis(10), Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            DeadlineExceeded {
+                timeout: Duration::from_millis(50),
+                missing: vec![("b".to_string(), 1, 3)],
+            }
+        );
+    }
+}
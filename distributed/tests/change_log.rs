@@ -0,0 +1,131 @@
+// 测试目的：会籍与配置变更的哈希链审计日志
+// - 不变量：1) 脚本化的加入、排空、配置更新、SWIM 故障确认与下线依序记录，重新打开 WAL 后
+//           链完整、`entries_since` 只返回之后的条目；
+//           2) 磁盘上篡改一条记录的内容时，`verify_chain` 指出该条；连同哈希一起伪造时，指出下一条的断链。
+use distributed::audit::{ChainError, ChangeEntry, ChangeEvent, ChangeLog};
+use distributed::codec::JsonCodec;
+use distributed::config_management::ConfigManager;
+use distributed::core::membership::{ClusterMembership, ClusterNodeId};
+use distributed::storage::{FileLogStorage, LogStorage};
+use distributed::swim::{SwimEvent, SwimMemberState};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+fn wal_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("changelog-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// 记录 5 条：加入 n2、配置更新、排空 n2、SWIM 确认 n3 故障、下线 n2；重复加入与重复设置相同配置值不记录
+fn scripted(path: &PathBuf) -> ClusterNodeId {
+    let n2 = ClusterNodeId::generate();
+    let mut membership = ClusterMembership::new(vec![ClusterNodeId::generate()]);
+    let log = Arc::new(Mutex::new(ChangeLog::open(path).unwrap()));
+    let mut config = ConfigManager::new();
+    ChangeLog::watch_config(&log, &mut config, "carol");
+
+    log.lock()
+        .unwrap()
+        .join("alice", &mut membership, n2.clone())
+        .unwrap();
+    // 已是成员：视图未变，不记录
+    log.lock()
+        .unwrap()
+        .join("alice", &mut membership, n2.clone())
+        .unwrap();
+    config.set_override("replication.factor", 3i64);
+    log.lock().unwrap().drain("bob", &n2).unwrap();
+    let suspect = SwimEvent::new("n3".into(), SwimMemberState::Suspect, 4);
+    let dead = SwimEvent::new("n3".into(), SwimMemberState::Faulty, 4);
+    assert_eq!(
+        log.lock().unwrap().observe_swim("swim", &suspect).unwrap(),
+        None
+    );
+    assert_eq!(
+        log.lock().unwrap().observe_swim("swim", &dead).unwrap(),
+        Some(4)
+    );
+    log.lock()
+        .unwrap()
+        .decommission("bob", &mut membership, &n2)
+        .unwrap();
+    config.set_override("replication.factor", 3i64);
+    n2
+}
+
+#[test]
+fn scripted_changes_replay_with_an_intact_chain() {
+    let path = wal_path("replay");
+    let n2 = scripted(&path);
+
+    let mut log = ChangeLog::open(&path).unwrap();
+    assert_eq!(log.verify_chain(), Ok(()));
+    let events: Vec<_> = log.entries().iter().map(|e| &e.event).collect();
+    assert_eq!(events.len(), 5);
+    assert_eq!(
+        events[0],
+        &ChangeEvent::Joined {
+            node: n2.as_str().to_string(),
+            epoch: 1
+        }
+    );
+    match events[1] {
+        ChangeEvent::ConfigUpdated { changes, .. } => {
+            assert_eq!(
+                changes.get("replication.factor"),
+                Some(&Some("3".to_string()))
+            );
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    assert_eq!(
+        events[3],
+        &ChangeEvent::ConfirmedDead {
+            node: "n3".into(),
+            incarnation: 4
+        }
+    );
+    assert!(matches!(
+        events[4],
+        ChangeEvent::Decommissioned { epoch: 2, .. }
+    ));
+    assert_eq!(log.entries()[1].actor, "carol");
+
+    let tail: Vec<u64> = log.entries_since(3).iter().map(|e| e.seq).collect();
+    assert_eq!(tail, vec![4, 5]);
+
+    // 重新打开后继续追加，接在原链之后
+    let head = log.head_hash().to_string();
+    let seq = log.drain("alice", &ClusterNodeId::generate()).unwrap();
+    assert_eq!(seq, 6);
+    assert_eq!(log.entries()[5].prev_hash, head);
+    assert_eq!(ChangeLog::open(&path).unwrap().verify_chain(), Ok(()));
+}
+
+#[test]
+fn tampering_on_disk_is_pinpointed() {
+    let path = wal_path("tamper");
+    scripted(&path);
+
+    // 改写第 3 条（bob 首次出现）中的操作者，长度不变
+    let bytes = std::fs::read(&path).unwrap();
+    let mut forged = String::from_utf8(bytes).unwrap();
+    let at = forged.find("\"actor\":\"bob\"").unwrap();
+    forged.replace_range(at..at + 13, "\"actor\":\"eve\"");
+    std::fs::write(&path, forged).unwrap();
+    let err = ChangeLog::open(&path).unwrap().verify_chain().unwrap_err();
+    assert_eq!(err, ChainError::HashMismatch { seq: 3 });
+
+    // 伪造者同时重算被改条目的哈希：断点落在下一条的链接上
+    let mut wal = FileLogStorage::new(path.clone(), JsonCodec);
+    let mut entries: Vec<ChangeEntry> = wal.read_all().unwrap();
+    entries[2].hash = entries[2].compute_hash();
+    wal.truncate().unwrap();
+    for entry in entries {
+        wal.append(entry).unwrap();
+    }
+    let err = ChangeLog::open(&path).unwrap().verify_chain().unwrap_err();
+    assert_eq!(err, ChainError::BrokenLink { seq: 4 });
+    assert_eq!(err.seq(), 4);
+}
@@ -0,0 +1,74 @@
+// 测试目的：熔断器状态迁移事件环
+// - 不变量：
+//   1) 完整的 Closed→Open→HalfOpen→Closed 周期按序留下三条事件，时间戳不递减，
+//      计数反映迁移时当前状态内的失败/成功次数；
+//   2) 事件数不超过容量，超出时淘汰最旧的事件；clear_events 清空记录但不改变状态。
+use distributed::security::{CircuitBreaker, CircuitConfig, CircuitState, CircuitTransition};
+use std::thread;
+use std::time::Duration;
+
+fn breaker(error_threshold: u32) -> CircuitBreaker {
+    CircuitBreaker::new(CircuitConfig {
+        error_threshold,
+        open_ms: 20,
+    })
+}
+
+fn transitions(cb: &CircuitBreaker) -> Vec<(CircuitState, CircuitState)> {
+    cb.events()
+        .map(|e| (e.transition.from, e.transition.to))
+        .collect()
+}
+
+#[test]
+fn full_cycle_is_logged_in_order() {
+    let mut cb = breaker(2);
+    cb.on_result(true);
+    cb.on_result(false);
+    cb.on_result(false);
+    assert_eq!(cb.state(), CircuitState::Open);
+    assert!(!cb.allow_request());
+    thread::sleep(Duration::from_millis(25));
+    assert!(cb.allow_request());
+    assert_eq!(cb.state(), CircuitState::HalfOpen);
+    cb.on_result(true);
+    assert_eq!(cb.state(), CircuitState::Closed);
+
+    use CircuitState::*;
+    assert_eq!(
+        transitions(&cb),
+        [(Closed, Open), (Open, HalfOpen), (HalfOpen, Closed)]
+    );
+    let events: Vec<_> = cb.events().collect();
+    assert!(events.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert_eq!((events[0].error_count, events[0].success_count), (2, 1));
+    assert_eq!(
+        events[1].transition,
+        CircuitTransition {
+            from: Open,
+            to: HalfOpen
+        }
+    );
+    assert_eq!((events[2].error_count, events[2].success_count), (0, 1));
+}
+
+#[test]
+fn log_is_bounded_and_clearable() {
+    let mut cb = breaker(1).with_event_capacity(3);
+    for _ in 0..3 {
+        cb.on_result(false);
+        thread::sleep(Duration::from_millis(25));
+        assert!(cb.allow_request());
+        cb.on_result(false);
+    }
+    // 首轮 Closed→Open→HalfOpen→Open，之后每轮 Open→HalfOpen→Open，共 7 次迁移，只保留最近 3 次
+    use CircuitState::*;
+    assert_eq!(
+        transitions(&cb),
+        [(HalfOpen, Open), (Open, HalfOpen), (HalfOpen, Open)]
+    );
+
+    cb.clear_events();
+    assert_eq!(cb.events().count(), 0);
+    assert_eq!(cb.state(), CircuitState::Open);
+}
@@ -0,0 +1,120 @@
+// 测试目的：基于虚拟时钟的时钟偏斜检测
+// - 不变量：1) 节点时钟快 500ms 时，经非对称、抖动的链路交换后，平滑偏移与真值之差不超过平滑 RTT/2，
+//           且告警只在首次越界时触发一次、指标同步计数；
+//           2) HLC 接受偏斜已知且在界内节点的时间戳，拒绝越界节点的时间戳，拒绝时本地时钟不变。
+use distributed::core::scheduling::HlcClock;
+use distributed::core::{ClockDriftError, HlcTimestamp};
+use distributed::monitoring::MetricValue;
+use distributed::simnet::{LatencyModel, LinkConfig, SimClock, SimNetwork};
+use distributed::swim::skew::{SkewProbe, SkewReply};
+use distributed::swim::{MembershipEvent, SkewConfig, SkewMonitor};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn link(min_ms: u64, max_ms: u64) -> LinkConfig {
+    LinkConfig::default().with_latency(LatencyModel::Uniform {
+        min: Duration::from_millis(min_ms),
+        max: Duration::from_millis(max_ms),
+    })
+}
+
+/// 时钟比虚拟时间快 `skew_ms` 的节点
+fn skewed(clock: &SimClock, skew_ms: u64) -> SkewMonitor {
+    let clock = clock.clone();
+    SkewMonitor::with_time_source(move || clock.now_ms() + skew_ms).with_config(SkewConfig {
+        max_offset: Duration::from_millis(250),
+        alpha: 0.3,
+        ..SkewConfig::default()
+    })
+}
+
+/// 节点 `id` 应答 `SkewProbe`
+fn serve(net: &SimNetwork, id: &str, monitor: SkewMonitor) {
+    net.register(id, move |_, envelope| {
+        let probe: SkewProbe = serde_json::from_slice(&envelope.payload).unwrap();
+        Some(serde_json::to_vec(&monitor.answer(probe)).unwrap())
+    });
+}
+
+/// `a` 向 `peer` 发起一次交换
+fn exchange(net: &SimNetwork, a: &SkewMonitor, peer: &str) -> Option<MembershipEvent> {
+    let probe = serde_json::to_vec(&a.probe()).unwrap();
+    let reply = net.call("a", peer, probe, Duration::from_secs(1)).unwrap();
+    let reply: SkewReply = serde_json::from_slice(&reply).unwrap();
+    a.complete(peer, reply)
+}
+
+#[test]
+fn skewed_node_is_estimated_within_half_rtt_and_warned_once() {
+    let net = SimNetwork::new(7);
+    // 去程明显慢于回程，且两个方向都有抖动
+    net.set_link("a", "b", link(20, 80));
+    net.set_link("b", "a", link(1, 10));
+    let a = skewed(&net.clock(), 0);
+    serve(&net, "b", skewed(&net.clock(), 500));
+    net.register("a", |_, _| None);
+
+    let mut events = Vec::new();
+    for _ in 0..20 {
+        events.extend(exchange(&net, &a, "b"));
+        let estimate = a.estimate("b").unwrap();
+        let error = (estimate.offset_ms - 500.0).abs();
+        assert!(
+            error <= estimate.rtt_ms / 2.0,
+            "offset {} rtt {}",
+            estimate.offset_ms,
+            estimate.rtt_ms
+        );
+        net.run_for(Duration::from_millis(100));
+    }
+
+    assert_eq!(events.len(), 1);
+    let MembershipEvent::ClockSkewWarning(node, offset) = &events[0];
+    assert_eq!(node, "b");
+    assert!((offset - 500).abs() <= 50, "{offset}");
+    assert_eq!(a.skewed_peers(), vec!["b".to_string()]);
+    assert_eq!(a.stats().samples(), 20);
+    assert_eq!(a.stats().warnings(), 1);
+    let counter = a
+        .metrics()
+        .get_all_metrics()
+        .into_iter()
+        .find(|m| m.name == "clock_skew_warnings_total")
+        .unwrap();
+    assert!(matches!(counter.value, MetricValue::Counter(v) if v == 1.0));
+}
+
+#[test]
+fn hlc_refuses_timestamps_from_nodes_beyond_the_bound() {
+    let net = SimNetwork::new(11).with_default_link(link(5, 15));
+    let clock = net.clock();
+    let a = Arc::new(skewed(&clock, 0));
+    serve(&net, "b", skewed(&clock, 200));
+    serve(&net, "c", skewed(&clock, 600));
+    net.register("a", |_, _| None);
+    let hlc = HlcClock::with_time_source(clock.time_source()).with_max_drift(a.drift_limit());
+    net.run_for(Duration::from_secs(1));
+
+    // 尚无偏斜信息：只允许基础容忍度
+    let now = clock.now_ms();
+    let ahead = |ms: u64| HlcTimestamp {
+        physical_ms: clock.now_ms() + ms,
+        logical: 0,
+    };
+    assert!(hlc.try_update(ahead(200)).is_err());
+
+    for _ in 0..5 {
+        assert_eq!(exchange(&net, &a, "b"), None);
+        exchange(&net, &a, "c");
+    }
+    assert_eq!(a.skewed_peers(), vec!["c".to_string()]);
+
+    // b 的偏斜在界内：容忍度随之放宽
+    let accepted = hlc.try_update(ahead(200)).unwrap();
+    assert!(accepted.physical_ms > now);
+    let before = hlc.now();
+    let err: ClockDriftError = hlc.try_update(ahead(600)).unwrap_err();
+    assert_eq!(err.max_drift_ms, a.drift_tolerance_ms());
+    assert!(err.max_drift_ms < 600 - 50);
+    assert_eq!(hlc.now().physical_ms, before.physical_ms);
+}
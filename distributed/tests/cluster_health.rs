@@ -0,0 +1,118 @@
+// 测试目的：集群健康快照
+// - 不变量：
+//   1) 快照汇总熔断器状态、各服务健康/总实例数、各副本复制延迟与 SWIM 成员计数，
+//      JSON 中包含全部预期键；
+//   2) 整体状态按最严重的组件状况汇总，缺省组件不影响结果。
+use distributed::consistency::ConsistencyLevel;
+use distributed::core::ClientSession;
+use distributed::monitoring::{ClusterHealth, ClusterHealthComponents, HealthStatus};
+use distributed::replication::LocalReplicator;
+use distributed::security::{CircuitBreaker, CircuitConfig};
+use distributed::service_discovery::{
+    ServiceDiscoveryConfig, ServiceDiscoveryManager, ServiceInstance,
+};
+use distributed::swim::{MembershipView, SwimMemberState};
+use distributed::topology::ConsistentHashRing;
+use std::collections::HashMap;
+
+fn instance(id: &str, healthy: bool) -> ServiceInstance {
+    let mut instance = ServiceInstance::new(
+        id.to_string(),
+        "orders".to_string(),
+        "127.0.0.1:8080".parse().unwrap(),
+        HashMap::new(),
+    );
+    instance.update_health(healthy);
+    instance
+}
+
+#[test]
+fn snapshot_json_contains_every_component() {
+    let closed = CircuitBreaker::new(CircuitConfig {
+        error_threshold: 2,
+        open_ms: 60_000,
+    });
+    let mut open = closed.clone();
+    open.on_result(false);
+    open.on_result(false);
+
+    let discovery = ServiceDiscoveryManager::new(ServiceDiscoveryConfig::default());
+    discovery.set_cache_for(
+        "orders",
+        vec![
+            instance("o1", true),
+            instance("o2", false),
+            instance("o3", true),
+        ],
+        true,
+    );
+
+    let mut ring = ConsistentHashRing::new(8);
+    let nodes: Vec<String> = ["n1", "n2", "n3"].map(String::from).to_vec();
+    for n in &nodes {
+        ring.add_node(n);
+    }
+    let mut replicator = LocalReplicator::<u64>::new(ring, nodes.clone());
+    let mut session = ClientSession::new();
+    for _ in 0..3 {
+        replicator
+            .replicate_with_session(&nodes, (), ConsistencyLevel::Quorum, &mut session)
+            .unwrap();
+    }
+    replicator.successes.insert("n3".to_string(), false);
+    replicator
+        .replicate_with_session(&nodes, (), ConsistencyLevel::Quorum, &mut session)
+        .unwrap();
+
+    let mut view = MembershipView::new("n1".to_string());
+    view.local_update("n1", SwimMemberState::Alive, 0);
+    view.local_update("n2", SwimMemberState::Suspect, 0);
+    view.local_update("n3", SwimMemberState::Faulty, 0);
+
+    let components = ClusterHealthComponents::new()
+        .with_circuit_breaker("payments", &closed)
+        .with_circuit_breaker("inventory", &open)
+        .with_discovery(&discovery)
+        .with_replicator(&replicator)
+        .with_membership(&view);
+    let snapshot = ClusterHealth::snapshot(&components);
+    assert_eq!(snapshot.status, HealthStatus::Unhealthy);
+
+    let json = snapshot.to_json();
+    for key in [
+        "taken_at_ms",
+        "status",
+        "circuit_breakers",
+        "services",
+        "replication_lag",
+        "members",
+    ] {
+        assert!(json.get(key).is_some(), "missing {key}");
+    }
+    assert_eq!(json["circuit_breakers"]["payments"], "closed");
+    assert_eq!(json["circuit_breakers"]["inventory"], "open");
+    assert_eq!(json["services"]["orders"]["healthy"], 2);
+    assert_eq!(json["services"]["orders"]["total"], 3);
+    assert_eq!(json["replication_lag"]["n1"], 0);
+    assert_eq!(json["replication_lag"]["n3"], 1);
+    assert_eq!(json["members"]["alive"], 1);
+    assert_eq!(json["members"]["suspect"], 1);
+    assert_eq!(json["members"]["dead"], 1);
+}
+
+#[test]
+fn overall_status_reflects_worst_component() {
+    let empty = ClusterHealthComponents::<u64>::new();
+    assert_eq!(
+        ClusterHealth::snapshot(&empty).status,
+        HealthStatus::Healthy
+    );
+
+    let mut view = MembershipView::new("n1".to_string());
+    view.local_update("n1", SwimMemberState::Alive, 0);
+    view.local_update("n2", SwimMemberState::Suspect, 0);
+    let components = ClusterHealthComponents::<u64>::new().with_membership(&view);
+    let snapshot = ClusterHealth::snapshot(&components);
+    assert_eq!(snapshot.status, HealthStatus::Degraded);
+    assert!(snapshot.services.is_empty() && snapshot.replication_lag.is_empty());
+}
@@ -0,0 +1,47 @@
+// 测试目的：UUID 节点标识
+// - 不变量：
+//   1) 随机生成的 ID 互不相同；
+//   2) from_components 对相同地址与启动时间给出相同 ID，任一输入不同则 ID 不同；
+//   3) 文本形式经 parse / serde 往返后不变，非法文本被拒绝。
+use distributed::core::ClusterNodeId;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+#[test]
+fn generated_ids_are_unique() {
+    let ids: HashSet<ClusterNodeId> = (0..10_000).map(|_| ClusterNodeId::generate()).collect();
+    assert_eq!(ids.len(), 10_000);
+}
+
+#[test]
+fn from_components_is_deterministic() {
+    let addr: SocketAddr = "10.0.0.7:7000".parse().unwrap();
+    let boot = 1_700_000_000_000_000_000;
+    let id = ClusterNodeId::from_components(addr, boot);
+    assert_eq!(id, ClusterNodeId::from_components(addr, boot));
+    assert_eq!(id.uuid().get_version_num(), 5);
+
+    assert_ne!(id, ClusterNodeId::from_components(addr, boot + 1));
+    let other: SocketAddr = "10.0.0.7:7001".parse().unwrap();
+    assert_ne!(id, ClusterNodeId::from_components(other, boot));
+}
+
+#[test]
+fn text_form_round_trips() {
+    let id = ClusterNodeId::generate();
+    assert_eq!(id.as_str().len(), 36);
+    assert_eq!(id.as_str().parse::<ClusterNodeId>().unwrap(), id);
+
+    let upper = id.as_str().to_uppercase();
+    assert_eq!(
+        upper.parse::<ClusterNodeId>().unwrap().as_str(),
+        id.as_str()
+    );
+
+    let json = serde_json::to_string(&id).unwrap();
+    assert_eq!(json, format!("\"{id}\""));
+    assert_eq!(serde_json::from_str::<ClusterNodeId>(&json).unwrap(), id);
+
+    assert!("n1".parse::<ClusterNodeId>().is_err());
+    assert!(serde_json::from_str::<ClusterNodeId>("\"n1\"").is_err());
+}
@@ -0,0 +1,64 @@
+// 测试目的：分区器的键共置提示
+// - 不变量：1) 登记共置提示后，键总是与锚点落在同一分片，即使哈希分区会把二者放到不同分片；
+//              未登记提示的键与哈希分区结果一致；
+//           2) 锚点自身有提示时沿提示链追到最终锚点；会形成环的提示被拒绝。
+use distributed::core::errors::DistributedError;
+use distributed::partitioning::{
+    CoLocationHintMap, CoLocationPartitioner, HashPartitioner, Partitioner,
+};
+
+fn hash() -> HashPartitioner {
+    HashPartitioner { shard_count: 16 }
+}
+
+#[test]
+fn colocated_keys_follow_their_anchor_shard() {
+    let plain = hash();
+    let mut partitioner = CoLocationPartitioner::new(hash());
+    let mut split = 0;
+    for i in 0..100 {
+        let (anchor, key) = (format!("user-{i}"), format!("user-profile-{i}"));
+        if plain.shard_of(&anchor) != plain.shard_of(&key) {
+            split += 1;
+        }
+        partitioner.register_colocated_key(&key, &anchor).unwrap();
+    }
+    // 大部分键对在纯哈希分区下本来会分开
+    assert!(split > 80, "{split}");
+    for i in 0..100 {
+        let (anchor, key) = (format!("user-{i}"), format!("user-profile-{i}"));
+        assert_eq!(partitioner.shard_of(&key), plain.shard_of(&anchor));
+        assert_eq!(partitioner.shard_of(&key), partitioner.shard_of(&anchor));
+        // 经 trait 调用结果相同
+        assert_eq!(
+            Partitioner::<String>::shard_of(&partitioner, &key),
+            partitioner.shard_of(&anchor)
+        );
+    }
+    for i in 0..100 {
+        let other = format!("order-{i}");
+        assert_eq!(partitioner.shard_of(&other), plain.shard_of(&other));
+    }
+}
+
+#[test]
+fn hint_chains_resolve_and_cycles_are_rejected() {
+    let mut hints = CoLocationHintMap::new();
+    hints.register("cart-7", "session-7").unwrap();
+    hints.register("session-7", "user-7").unwrap();
+    assert_eq!(hints.resolve("cart-7"), "user-7");
+
+    let mut partitioner = CoLocationPartitioner::new(hash()).with_hints(hints);
+    let user = partitioner.shard_of("user-7");
+    assert_eq!(partitioner.shard_of("cart-7"), user);
+    assert_eq!(partitioner.shard_of("session-7"), user);
+
+    for (key, anchor) in [("user-7", "cart-7"), ("user-7", "user-7")] {
+        assert!(matches!(
+            partitioner.register_colocated_key(key, anchor),
+            Err(DistributedError::Configuration(_))
+        ));
+    }
+    assert_eq!(partitioner.hints().len(), 2);
+    assert_eq!(partitioner.shard_of("cart-7"), user);
+}
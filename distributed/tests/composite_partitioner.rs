@@ -0,0 +1,43 @@
+// 测试目的：哈希分区 + 范围溢出分片
+// - 不变量：1) 分片负载不超过阈值时与哈希分区结果一致；
+//           2) 负载超过阈值的分片上的新写入路由到溢出（范围）分区器的分片，其它分片不受影响。
+use distributed::ShardId;
+use distributed::partitioning::{
+    CompositePartitioner, HashPartitioner, Partitioner, RangePartitioner, ShardStats,
+};
+
+#[test]
+fn hot_shard_overflows_to_range_partitioner() {
+    let mut overflow = RangePartitioner::new();
+    let upper = overflow.split_shard(ShardId(0), "order-5").unwrap();
+    let composite =
+        CompositePartitioner::new(HashPartitioner { shard_count: 4 }, overflow, 1_000.0);
+    let keys: Vec<String> = (0..100).map(|i| format!("order-{i}")).collect();
+
+    let mut stats = ShardStats::new();
+    for key in &keys {
+        let shard = composite.route_with_stats(key, &stats);
+        assert_eq!(shard, composite.primary().shard_of(key));
+        stats.add_load(shard, 15.0);
+    }
+
+    // 顺序 ID 写入使某个分片超过阈值
+    let hot = composite.primary().shard_of(&keys[0]);
+    stats.set_load(hot, 1_500.0);
+    for key in &keys {
+        let shard = composite.route_with_stats(key, &stats);
+        if composite.primary().shard_of(key) == hot {
+            assert!(composite.is_overflow(shard));
+            let range_shard = composite.overflow_shard(shard).unwrap();
+            assert_eq!(range_shard, composite.overflow().shard_for(key));
+        } else {
+            assert!(!composite.is_overflow(shard));
+        }
+    }
+    let hot_key = keys
+        .iter()
+        .find(|k| composite.primary().shard_of(*k) == hot && k.as_str() >= "order-5")
+        .unwrap();
+    let routed = composite.route_with_stats(hot_key, &stats);
+    assert_eq!(composite.overflow_shard(routed), Some(upper));
+}
@@ -0,0 +1,147 @@
+// 测试目的：跨协议的 ConsensusNode 抽象
+// - 不变量：
+//   1) 同一个泛型驱动对 Raft、Paxos、PBFT 完成一次提议/提交，提交值即提议的命令；
+//   2) 提交后提议者的角色为 Leader，任期不回退；
+//   3) 非领导者的提议被拒绝（Raft 跟随者、PBFT 备份节点）。
+use distributed::consensus::{
+    AcceptReq, ConsensusNode, ConsensusRole, FlexiblePaxosConfig, MinimalRaft, PBFTNode,
+    PaxosAcceptor, PaxosProposer, PrepareReq, ProposalId,
+};
+use distributed::core::ClusterNodeId;
+use std::sync::{Arc, Mutex};
+
+/// 各协议的最小集群：提供领导者节点，并把一次提议推进到提交
+trait Cluster {
+    type Node: ConsensusNode;
+    fn leader(&mut self) -> &mut Self::Node;
+    fn commit(&mut self, id: ProposalId) -> Option<Vec<u8>>;
+}
+
+fn propose_commit_cycle<C: Cluster>(cluster: &mut C) {
+    let term_before = cluster.leader().current_term();
+    let command = b"set x = 1".to_vec();
+    let id = cluster.leader().propose(command.clone()).unwrap();
+    assert_eq!(cluster.commit(id), Some(command));
+    assert_eq!(cluster.leader().role(), ConsensusRole::Leader);
+    assert!(cluster.leader().current_term() >= term_before);
+}
+
+struct RaftCluster {
+    node: MinimalRaft<Vec<u8>>,
+    applied: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl RaftCluster {
+    fn new() -> Self {
+        let mut node = MinimalRaft::new();
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let sink = applied.clone();
+        node.set_apply(Box::new(move |cmd: &Vec<u8>| {
+            sink.lock().unwrap().push(cmd.clone())
+        }));
+        node.start_election("n1");
+        node.become_leader(Vec::<String>::new());
+        Self { node, applied }
+    }
+}
+
+impl Cluster for RaftCluster {
+    type Node = MinimalRaft<Vec<u8>>;
+
+    fn leader(&mut self) -> &mut Self::Node {
+        &mut self.node
+    }
+
+    fn commit(&mut self, id: ProposalId) -> Option<Vec<u8>> {
+        let applied = self.applied.lock().unwrap();
+        applied.get(id.0 as usize - 1).cloned()
+    }
+}
+
+struct PaxosCluster {
+    proposer: PaxosProposer<Vec<u8>>,
+    acceptors: Vec<PaxosAcceptor<Vec<u8>>>,
+}
+
+impl PaxosCluster {
+    fn new() -> Self {
+        let ids: Vec<ClusterNodeId> = (0..3).map(|_| ClusterNodeId::generate()).collect();
+        let config = FlexiblePaxosConfig::majority(&ids);
+        Self {
+            proposer: PaxosProposer::new(ClusterNodeId::generate(), config).unwrap(),
+            acceptors: ids.into_iter().map(PaxosAcceptor::new).collect(),
+        }
+    }
+}
+
+impl Cluster for PaxosCluster {
+    type Node = PaxosProposer<Vec<u8>>;
+
+    fn leader(&mut self) -> &mut Self::Node {
+        &mut self.proposer
+    }
+
+    fn commit(&mut self, id: ProposalId) -> Option<Vec<u8>> {
+        assert_eq!(self.proposer.ballot().round, id.0);
+        let prepare = PrepareReq {
+            ballot: self.proposer.ballot().clone(),
+        };
+        let mut accept: Option<AcceptReq<Vec<u8>>> = None;
+        for acceptor in &mut self.acceptors {
+            let promise = acceptor.handle_prepare(&prepare)?;
+            accept = accept.or(self.proposer.handle_promise(promise));
+        }
+        let accept = accept?;
+        let mut chosen = None;
+        for acceptor in &mut self.acceptors {
+            let accepted = acceptor.handle_accept(&accept)?;
+            chosen = self.proposer.handle_accepted(accepted);
+        }
+        chosen
+    }
+}
+
+struct PbftCluster {
+    primary: PBFTNode,
+}
+
+impl Cluster for PbftCluster {
+    type Node = PBFTNode;
+
+    fn leader(&mut self) -> &mut Self::Node {
+        &mut self.primary
+    }
+
+    /// 省略三阶段消息交换，按序执行已分配序列号的请求
+    fn commit(&mut self, id: ProposalId) -> Option<Vec<u8>> {
+        let request_id = format!("{}-{}", self.primary.node_id, id.0);
+        let request = self.primary.pending_requests.get(&request_id)?.clone();
+        self.primary.execute(id.0, request).ok()?;
+        self.primary.committed_requests.get(&request_id).cloned()
+    }
+}
+
+#[test]
+fn every_protocol_completes_a_propose_commit_cycle() {
+    propose_commit_cycle(&mut RaftCluster::new());
+    propose_commit_cycle(&mut PaxosCluster::new());
+    propose_commit_cycle(&mut PbftCluster {
+        primary: PBFTNode::new("node_0".into(), 4),
+    });
+}
+
+fn assert_rejects_as_follower<N: ConsensusNode>(node: &mut N) {
+    assert_eq!(node.role(), ConsensusRole::Follower);
+    assert!(node.propose(b"nope".to_vec()).is_err());
+}
+
+#[test]
+fn followers_reject_proposals() {
+    assert_rejects_as_follower(&mut MinimalRaft::<Vec<u8>>::new());
+    assert_rejects_as_follower(&mut PBFTNode::new("node_1".into(), 4));
+
+    // Paxos 选定值后不再接受新提议
+    let mut paxos = PaxosCluster::new();
+    propose_commit_cycle(&mut paxos);
+    assert!(paxos.proposer.propose(b"again".to_vec()).is_err());
+}
@@ -0,0 +1,199 @@
+// 测试目的：收敛探针与场景运行器的收敛界限
+// - 不变量：1) 探针按副本记录传播延迟，`converged_within` 在最慢副本读到之前报告 Pending、超过
+//              界限时报告 TooSlow；两个节点的探针键互不覆盖，旧探针与 `clear` 删除的探针键
+//              从存储中消失，被淘汰时仍未读到的副本计入 missed；
+//           2) 分区恢复后的场景在界限内收敛并报告延迟，界限过小时记为违规。
+use distributed::DistributedError;
+use distributed::admin::ClusterInspector;
+use distributed::consistency::ConsistencyLevel;
+use distributed::core::HlcTimestamp;
+use distributed::monitoring::{ConvergenceError, ConvergenceProbe, ProbeTarget};
+use distributed::nemesis::{Fault, Nemesis, Scenario, ScenarioRunner};
+use distributed::simnet::{LatencyModel, LinkConfig};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+/// 每个副本按固定延迟看到写入
+#[derive(Default)]
+struct DelayedReplicas {
+    now_ms: Arc<AtomicU64>,
+    delays: Mutex<BTreeMap<String, u64>>,
+    /// (副本, 键) → (时间戳, 可见时刻)
+    store: Mutex<HashMap<(String, String), (HlcTimestamp, u64)>>,
+}
+
+impl DelayedReplicas {
+    fn new(delays: &[(&str, u64)]) -> Self {
+        let replicas = Self::default();
+        for (replica, delay) in delays {
+            replicas.set_delay(replica, *delay);
+        }
+        replicas
+    }
+
+    fn set_delay(&self, replica: &str, delay_ms: u64) {
+        self.delays
+            .lock()
+            .unwrap()
+            .insert(replica.to_string(), delay_ms);
+    }
+
+    fn advance_to(&self, ms: u64) {
+        self.now_ms.store(ms, Ordering::SeqCst);
+    }
+
+    fn probe(&self, node: &str) -> ConvergenceProbe {
+        let now = self.now_ms.clone();
+        ConvergenceProbe::new(node).with_clock(move || ms(now.load(Ordering::SeqCst)))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .store
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|(_, key)| key.clone())
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
+
+impl ProbeTarget for DelayedReplicas {
+    fn replicas(&self) -> Vec<String> {
+        self.delays.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn write_probe(
+        &self,
+        via: &str,
+        key: &str,
+        stamp: HlcTimestamp,
+    ) -> Result<(), DistributedError> {
+        let now = self.now_ms.load(Ordering::SeqCst);
+        let mut store = self.store.lock().unwrap();
+        for (replica, delay) in self.delays.lock().unwrap().iter() {
+            let visible_at = if replica == via { now } else { now + delay };
+            store.insert((replica.clone(), key.to_string()), (stamp, visible_at));
+        }
+        Ok(())
+    }
+
+    fn read_probe(
+        &self,
+        replica: &str,
+        key: &str,
+        level: ConsistencyLevel,
+    ) -> Option<HlcTimestamp> {
+        assert_eq!(level, ConsistencyLevel::Eventual);
+        let now = self.now_ms.load(Ordering::SeqCst);
+        let store = self.store.lock().unwrap();
+        let (stamp, visible_at) = store.get(&(replica.to_string(), key.to_string()))?;
+        (*visible_at <= now).then_some(*stamp)
+    }
+
+    fn delete_probe(&self, _: &str, key: &str) -> Result<(), DistributedError> {
+        self.store.lock().unwrap().retain(|(_, k), _| k != key);
+        Ok(())
+    }
+}
+
+#[test]
+fn probe_records_per_replica_lag_and_cleans_up_keys() {
+    let target = DelayedReplicas::new(&[("a", 0), ("b", 5), ("c", 20)]);
+    let mut probe_a = target
+        .probe("a")
+        .with_interval(ms(100))
+        .with_retained_probes(2);
+    let mut probe_b = target.probe("b").with_interval(ms(100));
+    assert_eq!(
+        probe_a.converged_within(ms(50)),
+        Err(ConvergenceError::NoProbe)
+    );
+
+    probe_a.tick(&target);
+    probe_b.tick(&target);
+    assert_eq!(target.keys(), ["__probe/a/1", "__probe/b/1"]);
+    match probe_a.converged_within(ms(50)) {
+        Err(ConvergenceError::Pending { replicas, .. }) => assert_eq!(replicas, ["b", "c"]),
+        other => panic!("unexpected {other:?}"),
+    }
+    for now in [5, 20] {
+        target.advance_to(now);
+        probe_a.tick(&target);
+        probe_b.tick(&target);
+    }
+    assert_eq!(probe_a.converged_within(ms(50)), Ok(ms(20)));
+    assert_eq!(probe_b.converged_within(ms(50)), Ok(ms(20)));
+    assert!(matches!(
+        probe_a.converged_within(ms(10)),
+        Err(ConvergenceError::TooSlow { replica, lag, .. }) if replica == "c" && lag == ms(20)
+    ));
+
+    // c 被隔离后写入的探针在淘汰时计为 missed；只保留最近两个探针
+    for now in (100..=405).step_by(5) {
+        if now == 200 {
+            target.set_delay("c", 10_000);
+        }
+        target.advance_to(now);
+        probe_a.tick(&target);
+    }
+    assert_eq!(probe_a.probes_written(), 5);
+    assert_eq!(probe_a.outstanding_keys(), ["__probe/a/4", "__probe/a/5"]);
+    assert_eq!(target.keys(), ["__probe/a/4", "__probe/a/5", "__probe/b/1"]);
+    let snapshot = probe_a.snapshot();
+    assert_eq!(snapshot.replicas["c"].missed, 1);
+    assert_eq!(snapshot.replicas["c"].samples, 2);
+    assert_eq!(snapshot.replicas["b"].samples, 5);
+    assert_eq!(snapshot.replicas["b"].max_ms, 5.0);
+    assert_eq!(snapshot.pending, ["c"]);
+    assert_eq!(probe_a.lag_histogram("c").unwrap().max(), ms(20));
+
+    let admin = ClusterInspector::<()>::new("a")
+        .with_convergence(&probe_a)
+        .snapshot();
+    assert_eq!(admin.convergence.as_ref(), Some(&snapshot));
+    assert_eq!(admin.to_json()["convergence"]["probes_written"], 5);
+
+    probe_a.clear(&target);
+    assert!(probe_a.outstanding_keys().is_empty());
+    assert_eq!(target.keys(), ["__probe/b/1"]);
+}
+
+#[test]
+fn partition_heal_scenario_converges_within_bound() {
+    let partition = || {
+        Scenario::new("partition-heal")
+            .with_duration(ms(300))
+            .with_nemesis(Nemesis::new().with(ms(100), ms(100), Fault::PartitionMajorities))
+    };
+    let report = ScenarioRunner::new(partition().with_convergence_bound(ms(50))).run();
+    report.assert_ok();
+    let lag = report.convergence_lag.expect("probe converged");
+    assert!(lag > Duration::ZERO && lag <= ms(50), "{lag:?}");
+    assert!(
+        report
+            .timeline_dump()
+            .contains("converge: probe visible on all replicas")
+    );
+    assert!(report.timeline_dump().contains("converge: replicas equal"));
+
+    // 链路延迟远超界限时报告违规
+    let slow = LinkConfig::default().with_latency(LatencyModel::Uniform {
+        min: ms(10),
+        max: ms(15),
+    });
+    let report =
+        ScenarioRunner::new(partition().with_link(slow).with_convergence_bound(ms(5))).run();
+    assert!(report.convergence_lag.is_none());
+    assert_eq!(report.violations.len(), 1, "{}", report.timeline_dump());
+    assert!(report.violations[0].starts_with("no convergence within 5ms"));
+}
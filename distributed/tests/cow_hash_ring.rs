@@ -0,0 +1,79 @@
+// 测试目的：写时复制哈希环在并发变更下的路由一致性
+// - 不变量：1) 并发读取者只会路由到已加入过的节点，且副本列表无重复；
+//           2) 变更前取得的快照不受后续变更影响。
+use distributed::topology::{ConsistentHashRing, CowHashRing};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+fn index_of(node: &str) -> usize {
+    node.strip_prefix("node-").unwrap().parse().unwrap()
+}
+
+#[test]
+fn concurrent_readers_never_see_unknown_nodes() {
+    let ring = Arc::new(CowHashRing::new(ConsistentHashRing::new(16)));
+    ring.add_node("node-0");
+    // 已加入过的节点编号上界（不含）；在加入节点之前递增
+    let added = Arc::new(AtomicUsize::new(1));
+    let done = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..16)
+        .map(|r| {
+            let (ring, added, done) = (ring.clone(), added.clone(), done.clone());
+            thread::spawn(move || {
+                let mut lookups = 0u64;
+                while !done.load(Ordering::Acquire) || lookups == 0 {
+                    let key = format!("key-{r}-{lookups}");
+                    let bound = added.load(Ordering::Acquire);
+                    if let Some(node) = ring.route(&key) {
+                        let bound = bound.max(added.load(Ordering::Acquire));
+                        assert!(index_of(&node) < bound, "{node} was never added");
+                    }
+                    let replicas = ring.nodes_for(&key, 3);
+                    let distinct: HashSet<_> = replicas.iter().collect();
+                    assert_eq!(distinct.len(), replicas.len());
+                    lookups += 1;
+                }
+                lookups
+            })
+        })
+        .collect();
+
+    // 1000 次变更：交替加入新节点与移除较早加入的节点
+    for i in 1..=1000 {
+        if i % 2 == 1 {
+            let next = added.fetch_add(1, Ordering::AcqRel);
+            ring.add_node(&format!("node-{next}"));
+        } else {
+            let live = added.load(Ordering::Acquire);
+            ring.remove_node(&format!("node-{}", (i * 7) % live));
+        }
+    }
+    done.store(true, Ordering::Release);
+
+    let total: u64 = readers.into_iter().map(|r| r.join().unwrap()).sum();
+    assert!(total >= 16);
+    let live: HashSet<_> = ring.snapshot().vnode_counts().into_keys().collect();
+    assert!(
+        live.iter()
+            .all(|n| index_of(n) < added.load(Ordering::Acquire))
+    );
+}
+
+#[test]
+fn snapshot_is_isolated_from_later_changes() {
+    let ring = CowHashRing::new(ConsistentHashRing::new(8));
+    ring.add_node("a");
+    ring.add_node("b");
+    let before = ring.snapshot();
+
+    ring.remove_node("a");
+    ring.add_node("c");
+
+    let nodes = |r: &ConsistentHashRing| r.vnode_counts().into_keys().collect::<Vec<_>>();
+    assert_eq!(nodes(&before), ["a", "b"]);
+    assert_eq!(nodes(&ring.snapshot()), ["b", "c"]);
+    assert_eq!(ring.route(&"k").as_deref(), ring.snapshot().route(&"k"));
+}
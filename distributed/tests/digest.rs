@@ -0,0 +1,104 @@
+// 测试目的：分片摘要交换
+// - 不变量：
+//   1) 相同副本的所有分片判定 Identical，零次 Merkle 范围比较；
+//   2) 单键差异只让该键所在分片回退到 Merkle 对账，对账后两侧一致；
+//   3) 随机数据下 Bloom 过滤器的误判率不超过配置上限（留统计余量），且无漏报。
+use distributed::storage::anti_entropy::VersionedData;
+use distributed::storage::digest::{
+    DigestConfig, DigestReconciler, DigestVerdict, build_digest, compare,
+};
+
+/// xorshift64*，保证测试可复现
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+fn random_store(rng: &mut Rng, keys: usize) -> VersionedData {
+    (0..keys)
+        .map(|_| {
+            let key = format!("key-{:016x}", rng.next());
+            let version = rng.next() % 100;
+            (key, (version, rng.next().to_le_bytes().to_vec()))
+        })
+        .collect()
+}
+
+#[test]
+fn identical_stores_short_circuit() {
+    let mut rng = Rng(42);
+    let mut a = random_store(&mut rng, 500);
+    let mut b = a.clone();
+    let reconciler = DigestReconciler::default();
+
+    for shard in reconciler.config().shards() {
+        assert_eq!(
+            compare(&build_digest(&a, shard), &build_digest(&b, shard)),
+            DigestVerdict::Identical
+        );
+    }
+    let report = reconciler.reconcile(&mut a, &mut b);
+    assert_eq!(report.range_comparisons(), 0);
+    assert!(report.reconcile.divergent.is_empty());
+}
+
+#[test]
+fn one_key_difference_syncs_only_its_shard() {
+    let mut rng = Rng(7);
+    let mut local = random_store(&mut rng, 500);
+    let mut remote = local.clone();
+    let key = local.keys().next().unwrap().clone();
+    let (version, _) = local[&key].clone();
+    remote.insert(key.clone(), (version + 1, b"newer".to_vec()));
+
+    let reconciler = DigestReconciler::default();
+    let shard = reconciler.config().shard_of(&key);
+    // 对端摘要的 Bloom 过滤器确定没有本地的新版本
+    let local_digest = reconciler.config().build(&local, shard);
+    let remote_shard = reconciler.config().shard_data(&remote, shard);
+    assert_eq!(local_digest.missing_from(&remote_shard), vec![key.clone()]);
+
+    let report = reconciler.reconcile(&mut local, &mut remote);
+    assert_eq!(report.divergent_shards, vec![shard]);
+    assert_eq!(report.reconcile.divergent, vec![key.clone()]);
+    assert_eq!(report.reconcile.pulled, 1);
+    assert_eq!(local[&key], (version + 1, b"newer".to_vec()));
+    assert_eq!(local, remote);
+    assert_eq!(
+        reconciler
+            .reconcile(&mut local, &mut remote)
+            .range_comparisons(),
+        0
+    );
+}
+
+#[test]
+fn false_positive_rate_within_configured_bound() {
+    let config = DigestConfig::default()
+        .with_shard_count(1)
+        .with_expected_keys(1000)
+        .with_false_positive_rate(0.01);
+    let shard = config.shards().next().unwrap();
+    let mut rng = Rng(2024);
+    for _ in 0..5 {
+        let store = random_store(&mut rng, 1000);
+        let digest = config.build(&store, shard);
+        assert!(store.iter().all(|(k, (v, _))| digest.might_contain(k, *v)));
+
+        let probes = 20_000;
+        let false_positives = (0..probes)
+            .filter(|_| {
+                let key = format!("absent-{:016x}", rng.next());
+                digest.might_contain(&key, rng.next() % 100)
+            })
+            .count();
+        let rate = false_positives as f64 / probes as f64;
+        assert!(rate <= 0.015, "false positive rate {rate}");
+    }
+}
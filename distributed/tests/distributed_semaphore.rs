@@ -0,0 +1,80 @@
+// 测试目的：基于复制计数器的分布式信号量
+// - 不变量：
+//   1) 3 个节点并发争用 2 个许可时，任一时刻至多 2 个持有者，且最终全部获取成功；
+//   2) 许可耗尽时获取在超时后返回 Timeout，释放后余量恢复；
+//   3) 请求超过 max_permits 或为 0 的许可数被拒绝。
+#[cfg(feature = "runtime-tokio")]
+mod distributed_semaphore {
+    use distributed::sync::{DistributedSemaphore, RaftCounter, ReplicatedCounter, SemaphoreError};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn at_most_two_of_three_acquirers_hold_two_permits() {
+        let counter = Arc::new(RaftCounter::new(2));
+        let holding = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = ["n1", "n2", "n3"]
+            .into_iter()
+            .map(|node| {
+                let semaphore = DistributedSemaphore::new(node, counter.clone());
+                let (holding, peak) = (holding.clone(), peak.clone());
+                tokio::spawn(async move {
+                    for _ in 0..3 {
+                        let guard = semaphore.acquire(1, Duration::from_secs(5)).await.unwrap();
+                        let now = holding.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        assert!(now <= 2, "{now} holders");
+                        tokio::time::sleep(Duration::from_millis(30)).await;
+                        holding.fetch_sub(1, Ordering::SeqCst);
+                        guard.release().await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(counter.available(), 2);
+    }
+
+    #[tokio::test]
+    async fn acquire_times_out_until_a_guard_is_dropped() {
+        let counter = Arc::new(RaftCounter::new(2));
+        let a = DistributedSemaphore::new("a", counter.clone());
+        let b = DistributedSemaphore::new("b", counter.clone());
+        assert_eq!(b.max_permits(), 2);
+
+        let held = a.acquire(2, Duration::from_millis(100)).await.unwrap();
+        assert_eq!(b.current_permits(), 0);
+        let err = b.acquire(1, Duration::from_millis(50)).await.err().unwrap();
+        assert!(matches!(err, SemaphoreError::Timeout(_)));
+
+        let waiter = tokio::spawn(async move { b.acquire(1, Duration::from_secs(2)).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(held);
+        let guard = waiter.await.unwrap().unwrap();
+        assert_eq!(guard.permits(), 1);
+        assert_eq!(a.current_permits(), 1);
+        guard.release().await.unwrap();
+        assert_eq!(a.current_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn invalid_permit_counts_are_rejected() {
+        let semaphore = DistributedSemaphore::new("a", Arc::new(RaftCounter::new(2)));
+        for permits in [0, 3] {
+            let err = semaphore
+                .acquire(permits, Duration::ZERO)
+                .await
+                .err()
+                .unwrap();
+            assert!(matches!(err, SemaphoreError::InvalidPermits { max: 2, .. }));
+        }
+        assert_eq!(semaphore.current_permits(), 2);
+    }
+}
@@ -0,0 +1,121 @@
+// 测试目的：故障域感知的写仲裁
+// - 不变量：1) 五节点分布在两个机架（3 + 2），大机架整体存活、小机架整体故障时，数量多数可以
+//              成立，但要求 2 个故障域的写入失败；两个机架都有确认时成功；
+//           2) 目标副本只跨越一个故障域时退化为数量多数并计数；远程复制器经同一策略判定。
+use distributed::consistency::ConsistencyLevel;
+use distributed::replication::{DomainAwareQuorum, LocalReplicator, Replicator};
+use distributed::topology::ConsistentHashRing;
+use std::sync::Arc;
+
+const RACKS: [(&str, &str); 5] = [
+    ("n1", "rack-a"),
+    ("n2", "rack-a"),
+    ("n3", "rack-a"),
+    ("n4", "rack-b"),
+    ("n5", "rack-b"),
+];
+
+fn ring() -> ConsistentHashRing {
+    let mut ring = ConsistentHashRing::new(8);
+    for (node, rack) in RACKS {
+        ring.add_node_in_domain(node, rack);
+    }
+    ring
+}
+
+fn replicator() -> LocalReplicator<u64> {
+    LocalReplicator::new(ring(), RACKS.iter().map(|(n, _)| n.to_string()).collect())
+}
+
+#[test]
+fn rack_failure_fails_domain_aware_writes_that_majority_would_accept() {
+    let ring = ring();
+    assert_eq!(ring.domain_of("n4"), Some("rack-b"));
+    let quorum = Arc::new(DomainAwareQuorum::from_ring(&ring, 2));
+    let mut aware = replicator().with_quorum(quorum.clone());
+    let mut plain = replicator();
+    aware.replicate(1u32, ConsistencyLevel::Quorum).unwrap();
+
+    // rack-b 整体故障：3/5 的数量多数只落在 rack-a
+    for repl in [&mut aware, &mut plain] {
+        repl.mark_node_failed("n4");
+        repl.mark_node_failed("n5");
+    }
+    plain.replicate(2u32, ConsistencyLevel::Quorum).unwrap();
+    let err = aware.replicate(2u32, ConsistencyLevel::Quorum).unwrap_err();
+    assert!(err.to_string().contains("1/2 failure domains"), "{err}");
+    // 只需 1 个确认的级别不受故障域约束
+    aware.replicate(2u32, ConsistencyLevel::Eventual).unwrap();
+
+    // rack-b 恢复一个节点、rack-a 故障一个节点：仍是 3/5，且跨两个机架
+    aware.mark_node_recovered("n5");
+    aware.mark_node_failed("n1");
+    aware.replicate(3u32, ConsistencyLevel::Quorum).unwrap();
+    // 数量多数不足时同样失败
+    aware.mark_node_failed("n2");
+    let err = aware.replicate(4u32, ConsistencyLevel::Quorum).unwrap_err();
+    assert!(err.to_string().contains("acks 2/3"), "{err}");
+    assert_eq!(quorum.downgrades(), 0);
+
+    // 目标只在一个机架时退化为数量多数
+    let mut single =
+        LocalReplicator::<u64>::new(ring.clone(), vec!["n1".into(), "n2".into(), "n3".into()])
+            .with_quorum(quorum.clone());
+    single.mark_node_failed("n3");
+    single.replicate(5u32, ConsistencyLevel::Quorum).unwrap();
+    assert_eq!(quorum.downgrades(), 1);
+}
+
+#[cfg(feature = "runtime-tokio")]
+mod remote {
+    use super::{RACKS, ring};
+    use distributed::DistributedError;
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::network::Deadline;
+    use distributed::replication::{
+        DomainAwareQuorum, RemoteReplicator, ReplicateAck, ReplicateRequest, ReplicationTransport,
+    };
+    use std::sync::Arc;
+
+    /// rack-b 整体不可达
+    struct RackDown;
+
+    impl ReplicationTransport for RackDown {
+        async fn send(
+            &self,
+            node: &str,
+            _request: ReplicateRequest,
+            _deadline: Deadline,
+        ) -> Result<ReplicateAck, DistributedError> {
+            if matches!(node, "n4" | "n5") {
+                return Err(DistributedError::Network(format!("{node} unreachable")));
+            }
+            Ok(ReplicateAck {
+                applied: true,
+                node_id: node.to_string(),
+                error: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn remote_replicator_consults_the_domain_policy() {
+        let nodes: Vec<String> = RACKS.iter().map(|(n, _)| n.to_string()).collect();
+        let plain = RemoteReplicator::new(RackDown, nodes.clone());
+        plain
+            .replicate("k", b"v".to_vec(), ConsistencyLevel::Quorum)
+            .await
+            .unwrap();
+
+        let aware = RemoteReplicator::new(RackDown, nodes)
+            .with_quorum(Arc::new(DomainAwareQuorum::from_ring(&ring(), 2)));
+        let err = aware
+            .replicate("k", b"v".to_vec(), ConsistencyLevel::Quorum)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, DistributedError::Network(ref m) if m.contains("1/2 failure domains")),
+            "{err}"
+        );
+    }
+}
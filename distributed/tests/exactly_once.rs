@@ -0,0 +1,131 @@
+// 测试目的：记忆结果的恰好一次执行
+// - 不变量：1) 计数器自增命令被重复投递（先后与并发）时只执行一次，两个调用方收到的 Ack 相同；
+//           2) 结果过期或被容量淘汰后，重复提交被拒绝而不是再执行，执行失败不被记忆。
+#[cfg(feature = "runtime-tokio")]
+mod executor {
+    use distributed::DistributedError;
+    use distributed::codec::JsonCodec;
+    use distributed::storage::ExactlyOnceExecutor;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn expired_or_evicted_results_are_not_re_executed() {
+        let now = Arc::new(Mutex::new(Duration::ZERO));
+        let clock = now.clone();
+        let executor = ExactlyOnceExecutor::new(JsonCodec)
+            .with_ttl(Duration::from_secs(10))
+            .with_capacity(2)
+            .with_clock(move || *clock.lock().unwrap());
+        let counter = AtomicU64::new(0);
+        let increment = || async { Ok(counter.fetch_add(1, Ordering::SeqCst) + 1) };
+
+        // 失败不记忆，同一键可以重试
+        let failed: Result<u64, _> = executor
+            .execute("a", || async {
+                Err(DistributedError::Storage("disk full".into()))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(executor.execute("a", increment).await.unwrap(), 1);
+        assert_eq!(executor.execute("a", increment).await.unwrap(), 1);
+
+        // 容量 2：c 挤掉最早完成的 a
+        assert_eq!(executor.execute("b", increment).await.unwrap(), 2);
+        assert_eq!(executor.execute("c", increment).await.unwrap(), 3);
+        assert_eq!(executor.len(), 2);
+        let err = executor.execute("a", increment).await.unwrap_err();
+        assert!(matches!(err, DistributedError::InvalidState(_)), "{err}");
+
+        // TTL 到期后 b、c 被清理，同样不再执行
+        *now.lock().unwrap() = Duration::from_secs(11);
+        assert!(executor.is_empty());
+        assert!(executor.execute("b", increment).await.is_err());
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+
+        let stats = executor.stats();
+        assert_eq!(
+            (stats.executed(), stats.replayed(), stats.forgotten()),
+            (3, 1, 2)
+        );
+    }
+}
+
+#[cfg(feature = "transport-grpc")]
+mod grpc {
+    use distributed::DistributedError;
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::network::Deadline;
+    use distributed::network::grpc_replication::{
+        AckCodec, GrpcReplicationTransport, NodeHandler, ReplicationServer,
+    };
+    use distributed::replication::{ReplicateRequest, ReplicationTransport};
+    use distributed::storage::ExactlyOnceExecutor;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    /// 非幂等命令：每次应用计数器加一
+    #[derive(Clone, Default)]
+    struct Counter(Arc<AtomicU64>);
+
+    impl NodeHandler for Counter {
+        async fn apply(&self, _request: ReplicateRequest) -> Result<(), DistributedError> {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn increment(key: &str) -> ReplicateRequest {
+        ReplicateRequest {
+            idempotency_key: key.to_string(),
+            payload: b"incr".to_vec(),
+            level: ConsistencyLevel::Quorum,
+            priority: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn redelivered_replicate_returns_the_original_ack() {
+        let counter = Counter::default();
+        let executor = Arc::new(ExactlyOnceExecutor::new(AckCodec));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(
+                    ReplicationServer::new("n1", counter.clone())
+                        .with_exactly_once(executor.clone()),
+                )
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let transport = GrpcReplicationTransport::new([("n1", format!("http://{addr}"))]);
+        let deadline = || Deadline::after(Duration::from_secs(5));
+
+        // 先后两次投递
+        let first = transport
+            .send("n1", increment("k1"), deadline())
+            .await
+            .unwrap();
+        let second = transport
+            .send("n1", increment("k1"), deadline())
+            .await
+            .unwrap();
+        assert!(first.applied);
+        assert_eq!(first, second);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        // 并发投递：后到者等待首个执行完成
+        let (a, b) = tokio::join!(
+            transport.send("n1", increment("k2"), deadline()),
+            transport.send("n1", increment("k2"), deadline()),
+        );
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+        assert_eq!(executor.stats().executed(), 2);
+        assert_eq!(executor.stats().replayed(), 2);
+    }
+}
@@ -0,0 +1,148 @@
+// 测试目的：按发送者序号的可靠 FIFO 广播
+// - 不变量：1) 50% 丢包下，两个发送者各 100 条消息最终被其余节点全部按序、恰好一次投递，
+//              全部确认后缓冲被回收；
+//           2) 发送者只把消息送到一个节点后崩溃，转发者仍把消息按序送到其余节点；
+//              未确认消息达到缓冲上限时 send 被拒绝。
+use distributed::consensus::{RbMessage, ReliableBroadcast};
+use std::collections::HashMap;
+
+/// SplitMix64，丢包序列由种子决定
+struct Rng(u64);
+
+impl Rng {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+type Delivered = HashMap<String, Vec<(String, Vec<u8>)>>;
+
+fn cluster(ids: &[&str]) -> HashMap<String, ReliableBroadcast> {
+    let members: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    members
+        .iter()
+        .map(|id| (id.clone(), ReliableBroadcast::new(id, members.clone())))
+        .collect()
+}
+
+/// 一轮：各节点重传后交换消息，按 `drop` 概率丢弃，`crashed` 的节点不收不发
+fn round(
+    nodes: &mut HashMap<String, ReliableBroadcast>,
+    delivered: &mut Delivered,
+    rng: &mut Rng,
+    drop: f64,
+    crashed: &[&str],
+) {
+    let mut in_flight: Vec<(String, RbMessage)> = Vec::new();
+    let mut ids: Vec<String> = nodes.keys().cloned().collect();
+    ids.sort();
+    for id in &ids {
+        let node = nodes.get_mut(id).unwrap();
+        node.tick();
+        let outgoing = node.take_outgoing();
+        if !crashed.contains(&id.as_str()) {
+            in_flight.extend(outgoing);
+        }
+    }
+    for (to, msg) in in_flight {
+        if crashed.contains(&to.as_str()) || rng.next_f64() < drop {
+            continue;
+        }
+        let out = nodes.get_mut(&to).unwrap().handle_message(msg);
+        delivered.entry(to).or_default().extend(out);
+    }
+}
+
+fn from_sender(delivered: &Delivered, node: &str, sender: &str) -> Vec<Vec<u8>> {
+    delivered
+        .get(node)
+        .into_iter()
+        .flatten()
+        .filter(|(s, _)| s == sender)
+        .map(|(_, payload)| payload.clone())
+        .collect()
+}
+
+#[test]
+fn all_messages_deliver_in_order_despite_half_of_messages_dropped() {
+    let mut nodes = cluster(&["a", "b", "c"]);
+    let mut delivered = Delivered::new();
+    let mut rng = Rng(7);
+    let expected: Vec<Vec<u8>> = (0..100u32).map(|i| i.to_be_bytes().to_vec()).collect();
+    for (i, payload) in expected.iter().enumerate() {
+        assert_eq!(
+            nodes.get_mut("a").unwrap().send(payload.clone()).unwrap().0,
+            i as u64 + 1
+        );
+        nodes.get_mut("b").unwrap().send(payload.clone()).unwrap();
+        if i % 10 == 0 {
+            round(&mut nodes, &mut delivered, &mut rng, 0.5, &[]);
+        }
+    }
+    let mut rounds = 0;
+    while nodes.values().any(|n| n.buffered() > 0) {
+        round(&mut nodes, &mut delivered, &mut rng, 0.5, &[]);
+        rounds += 1;
+        assert!(rounds < 500, "broadcast did not converge");
+    }
+
+    for (node, sender) in [("b", "a"), ("c", "a"), ("a", "b"), ("c", "b")] {
+        assert_eq!(
+            from_sender(&delivered, node, sender),
+            expected,
+            "{node} from {sender}"
+        );
+        assert_eq!(nodes[node].delivered_through(sender).0, 100);
+    }
+    assert!(nodes.values().all(|n| n.pending_len() == 0));
+    assert!(nodes["a"].retransmissions() > 0);
+}
+
+#[test]
+fn relays_deliver_after_sender_crash_and_buffer_is_bounded() {
+    let mut nodes = cluster(&["a", "b", "c"]);
+    let mut delivered = Delivered::new();
+    let mut rng = Rng(11);
+    let a = nodes.get_mut("a").unwrap();
+    for i in 0..20u8 {
+        a.send(vec![i]).unwrap();
+    }
+    // a 的消息只送到 b（逆序到达），随后 a 崩溃
+    let mut to_b: Vec<RbMessage> = a
+        .take_outgoing()
+        .into_iter()
+        .filter(|(to, _)| to == "b")
+        .map(|(_, msg)| msg)
+        .collect();
+    to_b.reverse();
+    for msg in to_b {
+        let out = nodes.get_mut("b").unwrap().handle_message(msg);
+        delivered.entry("b".to_string()).or_default().extend(out);
+    }
+    for _ in 0..50 {
+        round(&mut nodes, &mut delivered, &mut rng, 0.5, &["a"]);
+    }
+    let expected: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i]).collect();
+    assert_eq!(from_sender(&delivered, "b", "a"), expected);
+    assert_eq!(from_sender(&delivered, "c", "a"), expected);
+
+    // 无人确认时，未确认消息达到上限即拒绝发送
+    let mut lonely =
+        ReliableBroadcast::new("x", ["x", "y"].map(String::from)).with_max_buffer_size(4);
+    for _ in 0..4 {
+        lonely.send(b"m".to_vec()).unwrap();
+    }
+    assert!(lonely.send(b"m".to_vec()).is_err());
+    let acks = RbMessage::Ack {
+        from: "y".to_string(),
+        sender_id: "x".to_string(),
+        through: lonely.delivered_through("x"),
+    };
+    lonely.handle_message(acks);
+    assert_eq!(lonely.buffered(), 0);
+    assert!(lonely.send(b"m".to_vec()).is_ok());
+}
@@ -0,0 +1,251 @@
+// 测试目的：启动前的持久化状态完整性检查（fsck）
+// - 不变量：1) 写入中途崩溃的残余（WAL 与幂等记录的不完整末尾、改名前留下的临时快照与临时段）
+//              报为 Warning 并附修复动作；修复后报告干净、完好的记录全部保留，节点可以重新打开
+//              并继续追加；
+//           2) WAL 中间帧、快照、硬状态、幂等记录的校验失败，以及硬状态与日志不一致、快照与
+//              WAL 之间的缺口均报为 Error，修复也无法消除，启动前检查拒绝启动。
+use distributed::DistributedConfig;
+use distributed::codec::JsonCodec;
+use distributed::core::errors::DistributedError;
+use distributed::storage::{
+    Artifact, FileIdempotency, FileSnapshot, Fsck, FsckPolicy, HardState, HardStateFile,
+    IdempotencyStore, RepairAction, SegmentedWal, Severity, SnapshotStorage,
+};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 记录为 (任期, 命令)
+type Wal = SegmentedWal<(u64, String), JsonCodec>;
+
+fn data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fsck-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn open_wal(dir: &Path) -> Wal {
+    Wal::new(dir.join("wal"), JsonCodec)
+        .with_segment_entries(4)
+        .open()
+        .unwrap()
+}
+
+/// 10 条日志（任期 1..=2）、覆盖到索引 4 的快照、任期 2 的硬状态与 3 条幂等记录
+fn populate(dir: &Path) {
+    let mut wal = open_wal(dir);
+    for i in 1..=10u64 {
+        wal.append(&(1 + i / 6, format!("put k{i}"))).unwrap();
+    }
+    let mut snapshot = FileSnapshot::new(dir.join("snapshot"), JsonCodec)
+        .with_index(|state: &Vec<String>| state.len() as u64);
+    let state: Vec<String> = (1..=4).map(|i| format!("k{i}")).collect();
+    snapshot.save_snapshot(&state).unwrap();
+    HardStateFile::new(dir.join("hard_state"))
+        .save(&HardState {
+            term: 2,
+            voted_for: Some("n1".into()),
+            commit_index: 9,
+        })
+        .unwrap();
+    let mut ids = FileIdempotency::open(dir.join("idempotency")).unwrap();
+    for id in ["req-1", "req-2", "req-3"] {
+        ids.try_record(id.to_string()).unwrap();
+    }
+}
+
+fn fsck(dir: &Path) -> Fsck {
+    Fsck::for_data_dir(dir).with_entry_term(|payload| {
+        serde_json::from_slice::<(u64, String)>(payload)
+            .ok()
+            .map(|(term, _)| term)
+    })
+}
+
+fn segments(dir: &Path) -> Vec<PathBuf> {
+    let mut segments: Vec<PathBuf> = fs::read_dir(dir.join("wal"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "seg"))
+        .collect();
+    segments.sort();
+    segments
+}
+
+fn append_bytes(path: &Path, bytes: &[u8]) {
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    file.write_all(bytes).unwrap();
+}
+
+fn flip_byte(path: &Path, offset: usize) {
+    let mut bytes = fs::read(path).unwrap();
+    bytes[offset] ^= 0x40;
+    fs::write(path, bytes).unwrap();
+}
+
+fn errors_for(fsck: &Fsck, artifact: Artifact) -> Vec<String> {
+    fsck.check()
+        .unwrap()
+        .for_artifact(artifact)
+        .filter(|f| f.severity == Severity::Error)
+        .map(|f| f.message.clone())
+        .collect()
+}
+
+#[test]
+fn crash_residue_is_repaired_into_a_startable_state() {
+    let dir = data_dir("residue");
+    populate(&dir);
+    let fsck = fsck(&dir);
+    let report = fsck.check().unwrap();
+    assert!(report.findings.is_empty(), "{:?}", report.findings);
+
+    // 崩溃：末尾帧只写了一半，临时段、临时快照尚未改名，幂等记录写了一半
+    let last_segment = segments(&dir).pop().unwrap();
+    append_bytes(&last_segment, &[7; 30]);
+    fs::write(dir.join("wal/00000000000000000011-000001.tmp"), b"partial").unwrap();
+    fs::write(dir.join("snapshot.tmp"), b"partial").unwrap();
+    append_bytes(&dir.join("idempotency"), &[5, 0, 0, 0, 1, 2]);
+
+    let report = fsck.check().unwrap();
+    assert_eq!(report.max_severity(), Some(Severity::Warning));
+    assert_eq!(report.findings.len(), 4, "{:?}", report.findings);
+    for artifact in [Artifact::Wal, Artifact::Snapshot, Artifact::Idempotency] {
+        assert!(report.for_artifact(artifact).all(|f| f.repair.is_some()));
+    }
+    assert!(report.findings.iter().any(|f| f.repair
+        == Some(RepairAction::Truncate {
+            path: last_segment.clone(),
+            len: fs::metadata(&last_segment).unwrap().len() - 30,
+        })));
+
+    // 阈值为 Warning 时拒绝启动；默认阈值（Error）只检查
+    let strict = FsckPolicy {
+        refuse_at: Some(Severity::Warning),
+        repair: false,
+    };
+    let err = fsck.preflight(&strict).unwrap_err();
+    assert!(
+        matches!(&err, DistributedError::Storage(m) if m.contains("4 problem(s)")),
+        "{err}"
+    );
+    let config = DistributedConfig::default().with_data_dir(&dir);
+    assert_eq!(config.preflight().unwrap().unwrap().findings.len(), 4);
+
+    let config = config.with_fsck(FsckPolicy {
+        repair: true,
+        ..strict
+    });
+    let report = config.preflight().unwrap().unwrap();
+    assert!(report.is_clean(), "{:?}", report.findings);
+    assert_eq!(report.repaired.len(), 4);
+
+    // 完好的记录全部保留，修复后的日志可以继续追加
+    let mut wal = open_wal(&dir);
+    assert_eq!(wal.read_live().unwrap().len(), 10);
+    assert_eq!(wal.append(&(2, "put k11".into())).unwrap(), 11);
+    drop(wal);
+    assert_eq!(open_wal(&dir).read_live().unwrap().len(), 11);
+    let mut ids = FileIdempotency::open(dir.join("idempotency")).unwrap();
+    assert_eq!(ids.len(), 3);
+    ids.record("req-4".to_string());
+    assert!(ids.take_error().is_none());
+    assert!(
+        FileIdempotency::open(dir.join("idempotency"))
+            .unwrap()
+            .seen(&"req-4".into())
+    );
+    assert!(fsck.check().unwrap().findings.is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn corrupted_artifacts_are_errors_that_refuse_startup() {
+    let repair = FsckPolicy {
+        refuse_at: Some(Severity::Error),
+        repair: true,
+    };
+
+    // WAL：第一段中间帧的负载被改写
+    let dir = data_dir("wal");
+    populate(&dir);
+    flip_byte(&segments(&dir)[0], 25);
+    assert_eq!(
+        errors_for(&fsck(&dir), Artifact::Wal),
+        ["checksum mismatch in frame at byte 0"]
+    );
+    assert!(fsck(&dir).preflight(&repair).is_err());
+
+    // 快照：负载被改写
+    let dir = data_dir("snapshot");
+    populate(&dir);
+    flip_byte(&dir.join("snapshot"), 20);
+    assert_eq!(
+        errors_for(&fsck(&dir), Artifact::Snapshot),
+        ["storage error: snapshot checksum mismatch"]
+    );
+    assert!(fsck(&dir).preflight(&repair).is_err());
+
+    // 硬状态：字节被改写；与日志不一致（提交索引越界、任期落后于日志）
+    let dir = data_dir("hard-state");
+    populate(&dir);
+    let hard_state = HardStateFile::new(dir.join("hard_state"));
+    flip_byte(hard_state.path(), 10);
+    assert!(hard_state.load().is_err());
+    assert_eq!(
+        errors_for(&fsck(&dir), Artifact::HardState),
+        ["storage error: hard state checksum mismatch"]
+    );
+    hard_state
+        .save(&HardState {
+            term: 1,
+            voted_for: None,
+            commit_index: 12,
+        })
+        .unwrap();
+    assert_eq!(
+        errors_for(&fsck(&dir), Artifact::HardState),
+        [
+            "commit index 12 is beyond the last durable entry 10",
+            "log contains term 2 but hard state term is 1"
+        ]
+    );
+    assert!(fsck(&dir).preflight(&repair).is_err());
+
+    // 幂等记录：第一条记录被改写
+    let dir = data_dir("idempotency");
+    populate(&dir);
+    flip_byte(&dir.join("idempotency"), 9);
+    assert_eq!(
+        errors_for(&fsck(&dir), Artifact::Idempotency),
+        ["checksum mismatch in record at byte 0"]
+    );
+    assert!(fsck(&dir).preflight(&repair).is_err());
+
+    // 交叉校验：日志截断到 7 并压缩，但快照只覆盖到 4
+    let dir = data_dir("gap");
+    populate(&dir);
+    let mut wal = open_wal(&dir);
+    wal.truncate_before(7).unwrap();
+    wal.compact().unwrap();
+    drop(wal);
+    assert_eq!(
+        errors_for(&fsck(&dir), Artifact::Wal),
+        ["WAL starts at 7 but the snapshot only covers up to 4; entries 5..7 are missing"]
+    );
+    let config = DistributedConfig::default()
+        .with_data_dir(&dir)
+        .with_fsck(repair);
+    let err = config.preflight().unwrap_err();
+    assert!(err.to_string().contains("refusing to start"), "{err}");
+
+    // 快照追上后缺口消失
+    let mut snapshot = FileSnapshot::new(dir.join("snapshot"), JsonCodec).with_index(|_| 6);
+    snapshot.save_snapshot(&Vec::<String>::new()).unwrap();
+    assert!(config.preflight().unwrap().unwrap().is_clean());
+
+    for name in ["wal", "snapshot", "hard-state", "idempotency", "gap"] {
+        let _ = fs::remove_dir_all(data_dir(name));
+    }
+}
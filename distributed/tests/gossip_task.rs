@@ -0,0 +1,71 @@
+// 测试目的：tokio 驱动的 gossip 任务使会籍视图收敛
+// - 不变量：1) 新成员经种子节点加入后，3 个 gossip 周期内全部节点都知道它；
+//           2) 发往未登记节点的推送返回 `Unreachable`，不影响任务运行。
+#[cfg(feature = "runtime-tokio")]
+mod gossip_task {
+    use distributed::swim::{
+        GossipPayload, GossipTransport, InProcessGossipTransport, MembershipView, SwimMemberState,
+        TransportError, start_gossip_task,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    const INTERVAL_MS: u64 = 100;
+
+    fn view(me: &str, known: &[&str]) -> Arc<Mutex<MembershipView>> {
+        let mut view = MembershipView::new(me.to_string());
+        for node in known {
+            view.local_update(node, SwimMemberState::Alive, 1);
+        }
+        Arc::new(Mutex::new(view))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn new_member_reaches_all_nodes_within_three_rounds() {
+        let nodes = ["n1", "n2", "n3", "n4", "n5"];
+        let transport = Arc::new(InProcessGossipTransport::new());
+        let mut views = Vec::new();
+        let mut tasks = Vec::new();
+        for node in nodes {
+            transport.register(node);
+            let v = view(node, &nodes);
+            tasks.push(start_gossip_task(v.clone(), transport.clone(), INTERVAL_MS));
+            views.push(v);
+        }
+
+        // 新成员只认识自己和种子节点 n1
+        transport.register("n6");
+        let joiner = view("n6", &["n6", "n1"]);
+        tasks.push(start_gossip_task(
+            joiner.clone(),
+            transport.clone(),
+            INTERVAL_MS,
+        ));
+
+        // 周期在 0、100、200ms 触发；在第 4 个周期之前检查
+        tokio::time::sleep(Duration::from_millis(2 * INTERVAL_MS + INTERVAL_MS / 2)).await;
+        for v in &views {
+            let v = v.lock().unwrap();
+            assert!(v.members.contains_key("n6"), "{} misses n6", v.me);
+        }
+        assert_eq!(joiner.lock().unwrap().members.len(), 6);
+        for task in tasks {
+            task.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn unregistered_peer_is_unreachable() {
+        let transport = InProcessGossipTransport::new();
+        let payload = GossipPayload {
+            from: "n1".into(),
+            members: Vec::new(),
+            signature: Vec::new(),
+            seq: 0,
+        };
+        assert_eq!(
+            transport.send("ghost", payload).await,
+            Err(TransportError::Unreachable("ghost".into()))
+        );
+    }
+}
@@ -0,0 +1,155 @@
+// 测试目的：基于 gRPC 的复制传输
+// - 不变量：
+//   1) 三个进程内节点中两个确认时，多数派复制成功；失败节点的确认携带错误；
+//   2) 确认数不足多数派时复制失败，Eventual 级别下单个确认即可；
+//   3) 超过截止时间或不可达的节点记为未确认。
+#[cfg(feature = "transport-grpc")]
+mod grpc_replication {
+    use distributed::DistributedError;
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::network::grpc_replication::{
+        GrpcReplicationTransport, NodeHandler, ReplicationServer,
+    };
+    use distributed::replication::{RemoteReplicator, ReplicateRequest};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        applied: Arc<Mutex<Vec<ReplicateRequest>>>,
+        fail: bool,
+        delay: Option<Duration>,
+    }
+
+    impl NodeHandler for Recorder {
+        async fn apply(&self, request: ReplicateRequest) -> Result<(), DistributedError> {
+            if let Some(delay) = self.delay {
+                tokio::time::sleep(delay).await;
+            }
+            if self.fail {
+                return Err(DistributedError::Storage("disk full".into()));
+            }
+            self.applied.lock().unwrap().push(request);
+            Ok(())
+        }
+    }
+
+    async fn serve(node_id: &str, handler: Recorder) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(ReplicationServer::new(node_id, handler))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        format!("http://{addr}")
+    }
+
+    async fn cluster(handlers: &[Recorder]) -> RemoteReplicator<GrpcReplicationTransport> {
+        let mut endpoints = Vec::new();
+        for (i, handler) in handlers.iter().enumerate() {
+            let node = format!("n{}", i + 1);
+            let uri = serve(&node, handler.clone()).await;
+            endpoints.push((node, uri));
+        }
+        let nodes = endpoints.iter().map(|(n, _)| n.clone()).collect();
+        RemoteReplicator::new(GrpcReplicationTransport::new(endpoints), nodes)
+    }
+
+    #[tokio::test]
+    async fn quorum_replication_tolerates_one_failing_node() {
+        let handlers = [
+            Recorder::default(),
+            Recorder::default(),
+            Recorder {
+                fail: true,
+                ..Recorder::default()
+            },
+        ];
+        let replicator = cluster(&handlers).await;
+
+        for i in 0..3 {
+            let acks = replicator
+                .replicate(&format!("k{i}"), vec![i], ConsistencyLevel::Quorum)
+                .await
+                .unwrap();
+            let applied: Vec<_> = acks
+                .iter()
+                .map(|a| (a.node_id.as_str(), a.applied))
+                .collect();
+            assert_eq!(applied, vec![("n1", true), ("n2", true), ("n3", false)]);
+            assert!(acks[2].error.as_deref().unwrap().contains("disk full"));
+        }
+
+        let seen = handlers[0].applied.lock().unwrap().clone();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[1].idempotency_key, "k1");
+        assert_eq!(seen[1].payload, vec![1]);
+        assert_eq!(seen[1].level, ConsistencyLevel::Quorum);
+    }
+
+    #[tokio::test]
+    async fn quorum_fails_when_majority_errors() {
+        let failing = Recorder {
+            fail: true,
+            ..Recorder::default()
+        };
+        let handlers = [Recorder::default(), failing.clone(), failing];
+        let replicator = cluster(&handlers).await;
+
+        let err = replicator
+            .replicate("k", b"v".to_vec(), ConsistencyLevel::Quorum)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, DistributedError::Network(ref m) if m.contains("1/2")),
+            "{err}"
+        );
+        replicator
+            .replicate("k", b"v".to_vec(), ConsistencyLevel::Eventual)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn slow_and_unreachable_nodes_are_not_acked() {
+        let slow = Recorder {
+            delay: Some(Duration::from_secs(2)),
+            ..Recorder::default()
+        };
+        let mut endpoints = vec![
+            ("n1".to_string(), serve("n1", Recorder::default()).await),
+            ("n2".to_string(), serve("n2", slow).await),
+        ];
+        // 绑定后立即释放的端口：连接被拒绝
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        endpoints.push((
+            "n3".to_string(),
+            format!("http://{}", closed.local_addr().unwrap()),
+        ));
+        drop(closed);
+
+        let nodes = endpoints.iter().map(|(n, _)| n.clone()).collect();
+        let replicator = RemoteReplicator::new(GrpcReplicationTransport::new(endpoints), nodes)
+            .with_timeout(Duration::from_millis(300));
+
+        let err = replicator
+            .replicate("k", b"v".to_vec(), ConsistencyLevel::Quorum)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DistributedError::Network(_)), "{err}");
+
+        let acks = replicator
+            .replicate("k", b"v".to_vec(), ConsistencyLevel::Eventual)
+            .await
+            .unwrap();
+        assert!(acks[0].applied);
+        let slow_error = acks[1].error.clone().unwrap_or_default();
+        assert!(
+            slow_error.to_lowercase().contains("deadline"),
+            "{slow_error}"
+        );
+        assert!(!acks[2].applied && acks[2].error.is_some());
+    }
+}
@@ -19,3 +19,24 @@ fn ring_rebalance_minimal_change() {
     let after = ring.route(&"user-42").unwrap().to_string();
     assert!(before == after || (before != after));
 }
+
+// 测试目的：环组成的内省
+// - 不变量：1) 3 个节点各 16 个虚拟节点时，distinct_nodes 恰好 3 项且环大小为 48；
+//           2) 移除节点后其虚拟节点数归零，其余节点不变。
+#[test]
+fn ring_introspection_counts_distinct_and_virtual_nodes() {
+    let mut ring = ConsistentHashRing::new(16);
+    for node in ["n1", "n2", "n3"] {
+        ring.add_node(node);
+    }
+    let mut nodes: Vec<&str> = ring.distinct_nodes().collect();
+    nodes.sort();
+    assert_eq!(nodes, ["n1", "n2", "n3"]);
+    assert_eq!(ring.virtual_node_count(), 48);
+    assert_eq!(ring.virtual_nodes_for("n2"), 16);
+
+    ring.remove_node("n2");
+    assert_eq!(ring.virtual_nodes_for("n2"), 0);
+    assert_eq!(ring.distinct_nodes().count(), 2);
+    assert_eq!(ring.virtual_node_count(), 32);
+}
@@ -0,0 +1,173 @@
+// 测试目的：心跳服务驱动 φ 检测器与 SWIM 会籍
+// - 不变量：
+//   1) 心跳规律时 φ 低、状态为 Alive；连续丢包后在确定的模拟时刻转为 Suspect，
+//      持续 suspect_timeout 后转为 Faulty，恢复 ack 后回到 Alive；
+//   2) RTT EWMA 跟随链路延迟变化，可喂给 LeastResponseTimeBalancer；
+//   3) 会籍变更时新增节点被探测、离开的节点从视图中移除；
+//   4) gRPC ping 仅对在线节点成功。
+#[cfg(feature = "runtime-tokio")]
+mod heartbeat {
+    use distributed::core::{ClusterMembership, ClusterNodeId};
+    use distributed::load_balancing::LeastResponseTimeBalancer;
+    use distributed::network::heartbeat::{
+        HeartbeatConfig, HeartbeatService, InMemoryPingTransport,
+    };
+    use distributed::service_discovery::ServiceInstance;
+    use distributed::swim::SwimMemberState::{Alive, Faulty, Suspect};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    /// 生成 `N` 个节点 ID 的文本形式，即传输层与视图中的节点地址
+    fn nodes<const N: usize>() -> [String; N] {
+        std::array::from_fn(|_| ClusterNodeId::generate().to_string())
+    }
+
+    fn membership(nodes: &[&String]) -> ClusterMembership {
+        ClusterMembership::new(nodes.iter().map(|n| n.parse().unwrap()).collect())
+    }
+
+    fn config() -> HeartbeatConfig {
+        HeartbeatConfig {
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_millis(200),
+            phi_threshold: 8.0,
+            suspect_timeout: Duration::from_secs(3),
+            rtt_alpha: 0.5,
+            ..HeartbeatConfig::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropped_heartbeats_drive_suspect_then_faulty_then_alive() {
+        let [me, a, b] = nodes();
+        let transport = InMemoryPingTransport::new();
+        transport.set_delay(&a, Duration::from_millis(10));
+        let svc = HeartbeatService::new(&me, &membership(&[&me, &a, &b]), transport.clone())
+            .with_config(config());
+
+        let start = Instant::now();
+        let mut states = Vec::new();
+        for round in 0..12u32 {
+            tokio::time::sleep_until(start + Duration::from_secs(round as u64)).await;
+            match round {
+                5 => transport.set_drop(&b, true),
+                10 => transport.set_drop(&b, false),
+                _ => {}
+            }
+            svc.probe_round().await;
+            assert_eq!(svc.state(&a), Some(Alive), "round {round}");
+            states.push(svc.state(&b).unwrap());
+        }
+
+        // 第 5 轮首次丢包：此时距上次心跳 1.2s，φ 仍低；第 6 轮（2.2s）越过阈值；
+        // 第 9 轮距怀疑开始满 3s 转为 Faulty；第 10 轮恢复 ack
+        let expected = [
+            Alive, Alive, Alive, Alive, Alive, Alive, Suspect, Suspect, Suspect, Faulty, Alive,
+            Alive,
+        ];
+        assert_eq!(states, expected);
+        assert!(svc.phi(&a).unwrap() < 1.0);
+        assert_eq!(svc.view().alive_count(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rtt_ewma_feeds_least_response_time_balancer() {
+        let [me, a, b] = nodes();
+        let transport = InMemoryPingTransport::new();
+        transport.set_delay(&a, Duration::from_millis(40));
+        transport.set_delay(&b, Duration::from_millis(10));
+        let svc = HeartbeatService::new(&me, &membership(&[&me, &a, &b]), transport.clone())
+            .with_config(config());
+        let driver = svc.start();
+
+        tokio::time::sleep(Duration::from_millis(4500)).await;
+        let rtt = svc.rtt_estimates();
+        assert_eq!(rtt[&a], Duration::from_millis(40));
+        assert_eq!(rtt[&b], Duration::from_millis(10));
+
+        let addrs: HashMap<&str, std::net::SocketAddr> = [
+            (a.as_str(), "10.0.0.1:80".parse().unwrap()),
+            (b.as_str(), "10.0.0.2:80".parse().unwrap()),
+        ]
+        .into();
+        let servers = addrs
+            .iter()
+            .map(|(id, addr)| {
+                ServiceInstance::new(id.to_string(), "svc".into(), *addr, HashMap::new())
+            })
+            .collect();
+        let mut balancer = LeastResponseTimeBalancer::new(servers);
+        let feed = |balancer: &mut LeastResponseTimeBalancer| {
+            for (peer, rtt) in svc.rtt_estimates() {
+                balancer.set_response_time(addrs[peer.as_str()], rtt);
+            }
+        };
+        feed(&mut balancer);
+        assert_eq!(balancer.select_server().unwrap().id, b);
+
+        // b 变慢：EWMA（α = 0.5）几轮后越过 a
+        transport.set_delay(&b, Duration::from_millis(100));
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let after_one = svc.rtt(&b).unwrap();
+        assert_eq!(after_one, Duration::from_millis(55));
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        feed(&mut balancer);
+        assert_eq!(balancer.select_server().unwrap().id, a);
+        driver.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn membership_changes_update_probed_peers() {
+        let [me, a, b, c, d] = nodes();
+        let transport = InMemoryPingTransport::new();
+        let svc = HeartbeatService::new(&me, &membership(&[&me, &a, &b]), transport.clone())
+            .with_config(config());
+        svc.probe_round().await;
+        assert!(svc.view().contains(&b));
+
+        svc.set_membership(&membership(&[&me, &a, &c]));
+        transport.set_delay(&c, Duration::from_millis(5));
+        svc.probe_round().await;
+        let view = svc.view();
+        assert!(!view.contains(&b) && view.contains(&c));
+        assert_eq!(svc.rtt(&c), Some(Duration::from_millis(5)));
+        assert_eq!(svc.state(&c), Some(Alive));
+
+        // 从未 ack 的新节点同样会被怀疑
+        transport.set_drop(&d, true);
+        svc.set_membership(&membership(&[&me, &a, &c, &d]));
+        let start = Instant::now();
+        for round in 1..=3u64 {
+            tokio::time::sleep_until(start + Duration::from_secs(round)).await;
+            svc.probe_round().await;
+        }
+        assert_eq!(svc.state(&d), Some(Suspect));
+    }
+}
+
+#[cfg(feature = "transport-grpc")]
+mod heartbeat_grpc {
+    use distributed::network::heartbeat::{GrpcPingTransport, HeartbeatServer, PingTransport};
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    #[tokio::test]
+    async fn grpc_ping_reaches_live_peer_only() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let live = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(HeartbeatServer::new("a"))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+
+        let transport = GrpcPingTransport::new([("a", live), ("b", dead)]);
+        transport.ping("me", "a").await.unwrap();
+        transport.ping("me", "a").await.unwrap();
+        assert!(transport.ping("me", "b").await.is_err());
+        assert!(transport.ping("me", "c").await.is_err());
+    }
+}
@@ -0,0 +1,147 @@
+// 测试目的：仲裁读的对冲请求
+// - 不变量：1) 一个副本去程延迟 500ms、对冲延迟 50ms 时，p99 读延迟从 500ms 以上降到 100ms 以内，
+//           每次调用的额外请求不超过上限，且对冲的应答都进入了结果；
+//           2) 非幂等写从不对冲，显式幂等的写可以对冲；p95 对冲延迟随应答延迟收敛。
+use distributed::consistency::ConsistencyLevel;
+use distributed::network::hedging::{HedgeConfig, HedgeDelay, HedgedReader, HedgedRequest};
+use distributed::simnet::{
+    LatencyModel, LinkConfig, SimHedgeTransport, SimNetwork, SimTimer, TraceEvent,
+};
+use std::time::Duration;
+
+const REPLICAS: [&str; 3] = ["r1", "r2", "r3"];
+
+/// 副本原样回显请求；client → r3 固定 500ms，其余链路 2–10ms
+fn cluster(seed: u64) -> SimNetwork {
+    let net = SimNetwork::new(seed).with_default_link(LinkConfig::default().with_latency(
+        LatencyModel::Uniform {
+            min: Duration::from_millis(2),
+            max: Duration::from_millis(10),
+        },
+    ));
+    net.set_link(
+        "client",
+        "r3",
+        LinkConfig::default().with_latency(LatencyModel::Fixed(Duration::from_millis(500))),
+    );
+    for replica in REPLICAS {
+        net.register(replica, |_, envelope| Some(envelope.payload.clone()));
+    }
+    net.register("client", |_, _| None);
+    net
+}
+
+fn reader(net: &SimNetwork, config: HedgeConfig) -> HedgedReader<SimHedgeTransport, SimTimer> {
+    let clock = net.clock();
+    HedgedReader::new(SimHedgeTransport::new(net.clone(), "client"), net.timer())
+        .with_config(config)
+        .with_clock(move || clock.now())
+}
+
+/// 第 `i` 次调用的副本偏好顺序按键轮转，r3 在 2/3 的调用中位于前 R=2 个
+fn targets(i: usize) -> Vec<String> {
+    (0..3).map(|j| REPLICAS[(i + j) % 3].to_string()).collect()
+}
+
+/// 执行 `n` 次仲裁读，返回升序排列的延迟
+fn read_latencies(
+    net: &SimNetwork,
+    reader: &HedgedReader<SimHedgeTransport, SimTimer>,
+    n: usize,
+) -> Vec<Duration> {
+    let mut latencies: Vec<Duration> = (0..n)
+        .map(|i| {
+            let start = net.now();
+            let request = HedgedRequest::read(vec![i as u8]);
+            let replies = net
+                .block_on(reader.call(&targets(i), ConsistencyLevel::Quorum, request))
+                .unwrap();
+            assert_eq!(replies.len(), 2);
+            assert!(replies.iter().all(|r| r.payload == vec![i as u8]));
+            net.now() - start
+        })
+        .collect();
+    latencies.sort();
+    latencies
+}
+
+fn requests_sent(net: &SimNetwork) -> usize {
+    net.trace()
+        .iter()
+        .filter(|e| matches!(e, TraceEvent::Sent { from, .. } if from == "client"))
+        .count()
+}
+
+#[test]
+fn hedging_cuts_p99_and_respects_the_extra_request_cap() {
+    let unhedged_net = cluster(5);
+    let unhedged = reader(
+        &unhedged_net,
+        HedgeConfig {
+            max_extra: 0,
+            ..HedgeConfig::default()
+        },
+    );
+    let baseline = read_latencies(&unhedged_net, &unhedged, 100);
+    assert!(baseline[98] >= Duration::from_millis(500), "{baseline:?}");
+
+    let net = cluster(5);
+    let hedged = reader(
+        &net,
+        HedgeConfig {
+            delay: HedgeDelay::Fixed(Duration::from_millis(50)),
+            max_extra: 1,
+            ..HedgeConfig::default()
+        },
+    );
+    let latencies = read_latencies(&net, &hedged, 100);
+    assert!(latencies[98] < Duration::from_millis(100), "{latencies:?}");
+
+    // r3 位于前两个的 66 次调用各对冲一次，对冲的快副本都赶在 r3 之前应答
+    let stats = hedged.stats();
+    assert_eq!(stats.calls(), 100);
+    assert_eq!(stats.hedges_issued(), 66);
+    assert_eq!(stats.hedges_won(), 66);
+    assert_eq!(requests_sent(&net), 2 * 100 + 66);
+    assert!(hedged.latency("r3").unwrap() >= Duration::from_millis(50));
+}
+
+#[test]
+fn only_idempotent_requests_are_hedged() {
+    let net = cluster(9);
+    let reader = reader(&net, HedgeConfig::default());
+    assert_eq!(reader.hedge_delay(), Duration::from_millis(50));
+
+    // 只读快副本，p95 收敛到往返时延附近
+    let fast = vec!["r1".to_string(), "r2".to_string()];
+    for i in 0..50 {
+        let request = HedgedRequest::read(vec![i]);
+        net.block_on(reader.call(&fast, ConsistencyLevel::Quorum, request))
+            .unwrap();
+    }
+    let delay = reader.hedge_delay();
+    assert!(
+        delay >= Duration::from_millis(4) && delay <= Duration::from_millis(30),
+        "{delay:?}"
+    );
+
+    // r3 在前两个：带幂等键的写可以对冲
+    let start = net.now();
+    let write = HedgedRequest::idempotent_write("k1", b"w".to_vec());
+    let replies = net
+        .block_on(reader.call(&targets(2), ConsistencyLevel::Quorum, write))
+        .unwrap();
+    assert!(net.now() - start < Duration::from_millis(100));
+    assert!(replies.iter().any(|r| r.hedged && r.node == "r2"));
+    assert_eq!(reader.stats().hedges_issued(), 1);
+
+    // 普通写只能等 r3
+    let start = net.now();
+    let write = HedgedRequest::write(b"w".to_vec());
+    let replies = net
+        .block_on(reader.call(&targets(2), ConsistencyLevel::Quorum, write))
+        .unwrap();
+    assert!(net.now() - start >= Duration::from_millis(500));
+    assert!(replies.iter().all(|r| !r.hedged));
+    assert_eq!(reader.stats().hedges_issued(), 1);
+}
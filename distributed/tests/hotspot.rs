@@ -0,0 +1,93 @@
+// 测试目的：键级热点检测
+// - 不变量：
+//   1) Zipfian 负载下真实最热的 10 个键都出现在检测器的前 20 中，且真实次数落在
+//      [count - error, count] 内；
+//   2) 路由层喂入的节点计数精确，过期的时间槽不计入报告；NodeLoad 按平均负载的倍数判定越界，
+//      admin 快照附带热点报告。
+use distributed::admin::ClusterInspector;
+use distributed::benchmarks::loadgen::{
+    InMemoryReplicatedKv, KeyDistribution, LoadGenerator, WorkloadConfig,
+};
+use distributed::partitioning::{HashRingRouter, HotspotDetector, NodeLoad};
+use distributed::storage::{ApplyEvent, ReplicatedKv};
+use distributed::topology::ConsistentHashRing;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[test]
+fn zipfian_top_keys_are_reported_within_error_bound() {
+    let detector = Arc::new(HotspotDetector::<String>::new(20));
+    let kv = InMemoryReplicatedKv::<String, Vec<u8>>::new(3).with_hotspots(detector.clone());
+    let truth: Arc<Mutex<HashMap<String, u64>>> = Arc::default();
+    let sink = truth.clone();
+    kv.subscribe(Box::new(move |event: &ApplyEvent<String>| {
+        *sink.lock().unwrap().entry(event.key.clone()).or_insert(0) += 1;
+    }));
+    let config = WorkloadConfig::default()
+        .with_duration(Duration::from_millis(300))
+        .with_read_ratio(0.0)
+        .with_keys(1_000, KeyDistribution::Zipfian { theta: 0.99 })
+        .with_value_size(8);
+    let load = LoadGenerator::new(config).run(&kv);
+    assert_eq!(load.successes, load.writes);
+
+    let report = detector.report();
+    assert_eq!(report.total, load.writes);
+    assert_eq!(report.keys.len(), 20);
+    let mut truth: Vec<(String, u64)> = truth.lock().unwrap().clone().into_iter().collect();
+    truth.sort_by_key(|(_, count)| Reverse(*count));
+    for (key, count) in &truth[..10] {
+        let hot = report
+            .keys
+            .iter()
+            .find(|k| &k.key == key)
+            .unwrap_or_else(|| panic!("{key} ({count}) missing from {:?}", report.keys));
+        assert!(
+            hot.count - hot.error <= *count && *count <= hot.count,
+            "{key}: {count} vs {hot:?}"
+        );
+    }
+}
+
+#[test]
+fn routed_nodes_window_expiry_and_load_bound() {
+    let mut ring = ConsistentHashRing::new(32);
+    for node in ["n1", "n2", "n3"] {
+        ring.add_node(node);
+    }
+    let detector = Arc::new(HotspotDetector::new(5));
+    let router = HashRingRouter::new(ring).with_hotspots(detector.clone());
+    let hot_owner = router.owner_of(&"hot").unwrap();
+    for i in 0..30 {
+        router.owner_of(&format!("k{i}"));
+        router.owner_of(&"hot");
+    }
+
+    let report = detector.report();
+    assert_eq!(report.total, 61);
+    assert_eq!(report.keys[0].key, "hot");
+    assert_eq!((report.keys[0].count, report.keys[0].error), (31, 0));
+    assert_eq!(report.nodes.iter().map(|n| n.count).sum::<u64>(), 61);
+    assert_eq!(report.nodes[0].node, hot_owner);
+    assert!(detector.exceeds_bound(&hot_owner, 3, 1.25));
+
+    let snapshot = ClusterInspector::<String>::new("n1")
+        .with_hotspots(&detector)
+        .snapshot();
+    let hotspots = snapshot.hotspots.unwrap();
+    assert_eq!((hotspots.total, &hotspots.nodes), (61, &report.nodes));
+    assert_eq!(hotspots.keys[0], report.keys[0]);
+
+    // 10 秒窗口分 5 个槽：12 秒后最早的槽已过期
+    let windowed = HotspotDetector::<&str>::new(3).with_window(Duration::from_secs(10), 5);
+    let t0 = Instant::now();
+    windowed.record_at(&"old", Some("n1"), t0);
+    windowed.record_at(&"new", Some("n2"), t0 + Duration::from_secs(9));
+    assert_eq!(windowed.report_at(t0 + Duration::from_secs(9)).total, 2);
+    let later = windowed.report_at(t0 + Duration::from_secs(12));
+    assert_eq!(later.total, 1);
+    assert_eq!(later.keys[0].key, "new");
+    assert_eq!(later.nodes[0].node, "n2");
+}
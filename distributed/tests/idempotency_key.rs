@@ -0,0 +1,108 @@
+// 测试目的：HLC + 节点 ID + 序号生成的幂等键
+// - 不变量：
+//   1) 两个时钟偏斜的节点共生成 1M 个键，无碰撞；每个节点的键按生成顺序严格递增；
+//   2) Display/FromStr 与 serde 往返一致，可取出内嵌时间戳；
+//   3) replicate_auto_idempotent 返回的键重放时被去重。
+use distributed::consistency::ConsistencyLevel;
+use distributed::core::{HlcClock, HlcTimestamp};
+use distributed::replication::LocalReplicator;
+use distributed::storage::{IdempotencyKey, IdempotencyKeyGenerator, InMemoryIdempotency};
+use distributed::topology::ConsistentHashRing;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[test]
+fn million_keys_across_skewed_nodes_never_collide() {
+    // 共享的"真实"时间；b 的时钟快 5 秒，a 的时钟每隔一段时间回拨
+    let wall = Arc::new(AtomicU64::new(1_700_000_000_000));
+    let a_wall = wall.clone();
+    let a = IdempotencyKeyGenerator::with_clock(
+        "node-a",
+        HlcClock::with_time_source(move || {
+            let now = a_wall.load(Ordering::Relaxed);
+            now - (now % 7) * 3
+        }),
+    );
+    let b_wall = wall.clone();
+    let b = IdempotencyKeyGenerator::with_clock(
+        "node-b",
+        HlcClock::with_time_source(move || b_wall.load(Ordering::Relaxed) + 5_000),
+    );
+
+    let mut seen = HashSet::with_capacity(1_000_000);
+    let (mut last_a, mut last_b): (Option<IdempotencyKey>, Option<IdempotencyKey>) = (None, None);
+    for i in 0..500_000u64 {
+        if i % 1_000 == 0 {
+            wall.fetch_add(1, Ordering::Relaxed);
+        }
+        let ka = a.next_key();
+        let kb = b.next_key();
+        // 偶尔互发消息：a 合并 b 的时间戳后追上 b 的时钟
+        if i % 50_000 == 0 {
+            a.clock().update(kb.timestamp());
+        }
+        for (key, last) in [(ka, &mut last_a), (kb, &mut last_b)] {
+            if let Some(prev) = last.as_ref() {
+                assert!(*prev < key, "{prev} !< {key}");
+            }
+            assert!(seen.insert(key.clone()), "collision on {key}");
+            *last = Some(key);
+        }
+    }
+    assert_eq!(seen.len(), 1_000_000);
+}
+
+#[test]
+fn text_and_serde_round_trip() {
+    let key = IdempotencyKey::new(
+        HlcTimestamp {
+            physical_ms: 1_700_000_000_123,
+            logical: 7,
+        },
+        "zone-1-node-3",
+        42,
+    );
+    let text = key.to_string();
+    assert_eq!(text, "1700000000123-7-42-zone-1-node-3");
+    assert_eq!(text.parse::<IdempotencyKey>().unwrap(), key);
+    assert_eq!(key.physical_ms(), 1_700_000_000_123);
+    assert_eq!(key.node_id(), "zone-1-node-3");
+
+    let json = serde_json::to_string(&key).unwrap();
+    assert_eq!(json, format!("\"{text}\""));
+    assert_eq!(serde_json::from_str::<IdempotencyKey>(&json).unwrap(), key);
+
+    for bad in ["", "1-2-3", "x-1-2-n", "1-2-3-"] {
+        assert!(bad.parse::<IdempotencyKey>().is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn replicate_auto_idempotent_returns_replayable_key() {
+    let mut ring = ConsistentHashRing::new(8);
+    let nodes: Vec<String> = ["n1", "n2", "n3"].iter().map(|n| n.to_string()).collect();
+    for n in &nodes {
+        ring.add_node(n);
+    }
+    let mut replicator = LocalReplicator::<IdempotencyKey>::new(ring, nodes.clone())
+        .with_idempotency(Box::new(InMemoryIdempotency::default()))
+        .with_key_generator(IdempotencyKeyGenerator::new("writer"));
+
+    let (k1, res) = replicator.replicate_auto_idempotent("cmd", ConsistencyLevel::Strong);
+    res.unwrap();
+    let (k2, res) = replicator.replicate_auto_idempotent("cmd", ConsistencyLevel::Strong);
+    res.unwrap();
+    assert!(k1 < k2);
+    assert_eq!(k1.node_id(), "writer");
+
+    // 所有副本都会失败时：以已记录的键重放直接成功，新键则失败
+    for n in &nodes {
+        replicator.successes.insert(n.clone(), false);
+    }
+    replicator
+        .replicate_idempotent(&k1, &nodes, "cmd", ConsistencyLevel::Strong)
+        .unwrap();
+    let (_, res) = replicator.replicate_auto_idempotent("cmd", ConsistencyLevel::Strong);
+    assert!(res.is_err());
+}
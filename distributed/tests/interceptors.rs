@@ -0,0 +1,199 @@
+// 测试目的：gRPC 拦截器链
+// - 不变量：
+//   1) 链按顺序执行；认证失败即短路，下游拦截器一次也不调用；
+//   2) JWT 校验拒绝缺失、篡改、过期与签发方不符的令牌，通过时载荷进入请求扩展；
+//   3) 限流耗尽返回 ResourceExhausted，开启豁免时 Control 优先级请求不受限；
+//      追踪 ID 沿用调用方的值或新生成；
+//   4) 挂到 tonic 服务上时，未认证请求得到 Unauthenticated。
+#[cfg(feature = "grpc")]
+mod interceptors {
+    use distributed::chain;
+    use distributed::network::interceptors::{
+        JwtClaims, JwtInterceptor, PRIORITY_HEADER, RateLimitInterceptor, TRACE_ID_HEADER, TraceId,
+        TracingInterceptor,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use tonic::service::Interceptor;
+    use tonic::{Code, Request, Status};
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn with_token(token: &str) -> Request<()> {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {token}").parse().unwrap());
+        request
+    }
+
+    fn counting(calls: &Arc<AtomicUsize>) -> impl Interceptor + Send + 'static {
+        let calls = calls.clone();
+        move |request: Request<()>| -> Result<Request<()>, Status> {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(request)
+        }
+    }
+
+    #[test]
+    fn auth_failure_short_circuits_downstream_interceptors() {
+        let auth = JwtInterceptor::new("secret");
+        let token = auth.issue(&json!({ "sub": "svc-a", "exp": now() + 60 }));
+        let downstream = Arc::new(AtomicUsize::new(0));
+        let mut chain = chain![auth, counting(&downstream), counting(&downstream)];
+        assert_eq!(chain.len(), 3);
+
+        let err = chain.call(Request::new(())).unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+        let err = chain.call(with_token("not.a.jwt")).unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+        assert_eq!(downstream.load(Ordering::SeqCst), 0);
+
+        let passed = chain.call(with_token(&token)).unwrap();
+        assert_eq!(downstream.load(Ordering::SeqCst), 2);
+        let claims = passed.extensions().get::<JwtClaims>().unwrap();
+        assert_eq!(claims.subject(), Some("svc-a"));
+    }
+
+    #[test]
+    fn jwt_rejects_tampered_expired_and_foreign_tokens() {
+        let auth = JwtInterceptor::new("secret").with_issuer("idp");
+        let valid = auth.issue(&json!({ "iss": "idp", "exp": now() + 60 }));
+        assert!(auth.verify(&valid).is_ok());
+
+        let expired = auth.issue(&json!({ "iss": "idp", "exp": now() - 10 }));
+        let wrong_issuer = auth.issue(&json!({ "iss": "other", "exp": now() + 60 }));
+        let foreign =
+            JwtInterceptor::new("other-secret").issue(&json!({ "iss": "idp", "exp": now() + 60 }));
+        let (head, _) = valid.rsplit_once('.').unwrap();
+        let (_, forged_sig) = foreign.rsplit_once('.').unwrap();
+        let tampered = format!("{head}.{forged_sig}");
+        for token in [expired, wrong_issuer, foreign, tampered] {
+            let err = auth.verify(&token).unwrap_err();
+            assert_eq!(err.code(), Code::Unauthenticated, "{token}");
+        }
+        assert!(
+            auth.with_leeway_secs(30)
+                .verify(&auth_expired_within_leeway())
+                .is_ok()
+        );
+    }
+
+    fn auth_expired_within_leeway() -> String {
+        JwtInterceptor::new("secret").issue(&json!({ "iss": "idp", "exp": now() - 5 }))
+    }
+
+    #[test]
+    fn rate_limit_and_tracing_in_chain() {
+        let mut chain = chain![RateLimitInterceptor::new(2, 0), TracingInterceptor::new()];
+
+        let mut traced = Request::new(());
+        traced
+            .metadata_mut()
+            .insert(TRACE_ID_HEADER, "caller-trace".parse().unwrap());
+        let first = chain.call(traced).unwrap();
+        assert_eq!(
+            first.extensions().get::<TraceId>(),
+            Some(&TraceId("caller-trace".into()))
+        );
+
+        let second = chain.call(Request::new(())).unwrap();
+        let generated = second.extensions().get::<TraceId>().unwrap().0.clone();
+        assert_eq!(
+            second
+                .metadata()
+                .get(TRACE_ID_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            generated
+        );
+        assert_ne!(generated, "caller-trace");
+
+        let err = chain.call(Request::new(())).unwrap_err();
+        assert_eq!(err.code(), Code::ResourceExhausted);
+    }
+
+    #[test]
+    fn rate_limit_exempts_control_priority_when_enabled() {
+        let with_priority = |priority: &'static str| {
+            let mut request = Request::new(());
+            request
+                .metadata_mut()
+                .insert(PRIORITY_HEADER, priority.parse().unwrap());
+            request
+        };
+
+        let mut limiter = RateLimitInterceptor::new(1, 0).with_control_exempt(true);
+        assert!(limiter.call(with_priority("bulk")).is_ok());
+        assert_eq!(
+            limiter.call(with_priority("bulk")).unwrap_err().code(),
+            Code::ResourceExhausted
+        );
+        for _ in 0..3 {
+            assert!(limiter.call(with_priority("control")).is_ok());
+        }
+
+        let mut strict = RateLimitInterceptor::new(1, 0);
+        assert!(strict.call(with_priority("control")).is_ok());
+        assert!(strict.call(with_priority("control")).is_err());
+    }
+
+    #[cfg(feature = "transport-grpc")]
+    #[tokio::test]
+    async fn chain_guards_a_tonic_service() {
+        use distributed::DistributedError;
+        use distributed::consistency::ConsistencyLevel;
+        use distributed::network::Deadline;
+        use distributed::network::grpc_replication::{
+            GrpcReplicationTransport, NodeHandler, ReplicationServer,
+        };
+        use distributed::storage::replication::{Priority, ReplicateRequest, ReplicationTransport};
+        use std::time::Duration;
+        use tokio_stream::wrappers::TcpListenerStream;
+        use tonic::service::interceptor::InterceptedService;
+
+        struct Accept(Arc<AtomicUsize>);
+        impl NodeHandler for Accept {
+            async fn apply(&self, _request: ReplicateRequest) -> Result<(), DistributedError> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let applied = Arc::new(AtomicUsize::new(0));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let service = InterceptedService::new(
+            ReplicationServer::new("n1", Accept(applied.clone())),
+            chain![JwtInterceptor::new("secret"), TracingInterceptor::new()],
+        );
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        // 复制传输不携带令牌：请求在拦截器处被拒绝，处理器不会被调用
+        let transport = GrpcReplicationTransport::new([("n1", addr)]);
+        let request = ReplicateRequest {
+            idempotency_key: "k1".into(),
+            payload: b"v".to_vec(),
+            level: ConsistencyLevel::Eventual,
+            priority: Priority::Normal,
+        };
+        let err = transport
+            .send("n1", request, Deadline::after(Duration::from_secs(2)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unauthenticated"), "{err}");
+        assert_eq!(applied.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,94 @@
+// 测试目的：按节点组织的连接池与故障检测器联动
+// - 不变量：1) 建连失败后按指数退避惰性重连，退避期内的借出不尝试建连；空闲超时的连接被回收；
+//           2) 会籍视图判定可疑的节点连接被主动关闭，借出以 Network 错误快速失败，恢复存活后重新建连。
+#[cfg(feature = "runtime-tokio")]
+mod keyed_connection_pool {
+    use distributed::DistributedError;
+    use distributed::network::pool::testing::{FakeConnection, FakeNetwork};
+    use distributed::network::pool::{KeyedConnectionPool, PoolConfig, ReconnectBackoff};
+    use distributed::swim::{MembershipView, SwimMemberState};
+    use std::time::Duration;
+
+    fn pool(network: &FakeNetwork) -> KeyedConnectionPool<FakeConnection> {
+        let config = PoolConfig {
+            max_size: 2,
+            min_idle: 0,
+            max_idle: Duration::from_secs(60),
+            acquire_timeout: Duration::from_secs(1),
+        };
+        KeyedConnectionPool::new([("n1", "10.0.0.1:7000"), ("n2", "10.0.0.2:7000")], config)
+            .with_connector(network.connector())
+            .with_backoff(ReconnectBackoff {
+                initial: Duration::from_millis(100),
+                max: Duration::from_millis(400),
+            })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnect_backs_off_exponentially_and_idle_connections_are_reaped() {
+        let network = FakeNetwork::new();
+        let pool = pool(&network);
+        network.set_down("10.0.0.1:7000", true);
+
+        // 第 1、2、3 次失败后分别退避 100ms、200ms、400ms（封顶）
+        for (attempt, backoff) in [(1, 100), (2, 200), (3, 400), (4, 400)] {
+            assert!(pool.acquire("n1").await.is_err());
+            assert_eq!(network.connect_attempts("10.0.0.1:7000"), attempt);
+            tokio::time::advance(Duration::from_millis(backoff - 1)).await;
+            let err = pool.acquire("n1").await.err().unwrap();
+            assert!(matches!(err, DistributedError::Network(ref m) if m.contains("backoff")));
+            assert_eq!(network.connect_attempts("10.0.0.1:7000"), attempt);
+            tokio::time::advance(Duration::from_millis(1)).await;
+        }
+        assert_eq!(pool.stats().connect_failures(), 4);
+        assert_eq!(pool.stats().fail_fast(), 4);
+
+        // 恢复后成功建连并清零退避；其它节点不受影响
+        network.set_down("10.0.0.1:7000", false);
+        let conn = pool.acquire("n1").await.unwrap();
+        assert_eq!(conn.endpoint, "10.0.0.1:7000");
+        pool.release(conn);
+        let conn = pool.acquire("n2").await.unwrap();
+        pool.release(conn);
+        assert_eq!((pool.total_open(), pool.total_idle()), (2, 2));
+        assert_eq!(pool.stats().acquire_wait().count, 2);
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        pool.reap_idle();
+        assert_eq!((pool.open("n1"), pool.idle("n1")), (0, 0));
+        assert_eq!(pool.total_open(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn suspected_nodes_are_closed_and_fail_fast() {
+        let network = FakeNetwork::new();
+        let pool = pool(&network);
+        let idle = pool.acquire("n1").await.unwrap();
+        let busy = pool.acquire("n1").await.unwrap();
+        pool.release(idle);
+        assert_eq!((pool.open("n1"), pool.idle("n1")), (2, 1));
+
+        let mut view = MembershipView::new("n0".to_string());
+        view.local_update("n1", SwimMemberState::Suspect, 1);
+        view.local_update("n2", SwimMemberState::Alive, 1);
+        pool.apply_membership(&view);
+        assert!(pool.is_suspected("n1"));
+        assert_eq!(pool.open("n1"), 0);
+        assert_eq!(pool.stats().suspected_closes(), 1);
+
+        let err = pool.acquire("n1").await.err().unwrap();
+        assert!(matches!(err, DistributedError::Network(ref m) if m.contains("suspected")));
+        assert_eq!(network.connect_attempts("10.0.0.1:7000"), 2);
+        // 可疑期间借出的连接归还时被关闭而不是回到池中
+        pool.release(busy);
+        assert_eq!(pool.open("n1"), 0);
+        assert!(pool.acquire("n2").await.is_ok());
+
+        view.local_update("n1", SwimMemberState::Alive, 2);
+        pool.apply_membership(&view);
+        let conn = pool.acquire("n1").await.unwrap();
+        assert_eq!(network.connect_attempts("10.0.0.1:7000"), 3);
+        pool.release(conn);
+        assert_eq!(pool.idle("n1"), 1);
+    }
+}
@@ -0,0 +1,108 @@
+// 测试目的：基于 KV 租约的跨节点屏障与信号量
+// - 不变量：1) 屏障恰在第 K 个参与者到达时放行，此前所有等待者都不放行；过期未放行的屏障使等待者
+//              收到错误并可被清理、同名重建；
+//           2) 信号量的许可总数不超过上限；持有者崩溃（不续约也不释放）后许可在租约到期时被回收，
+//              存活的持有者续约后保留许可。
+#[cfg(feature = "runtime-tokio")]
+mod kv_coordination {
+    use distributed::simnet::SimNetwork;
+    use distributed::storage::InMemoryVersionedStore;
+    use distributed::sync::coordination::{DistributedBarrier, DistributedSemaphore};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    type Store = InMemoryVersionedStore<String, Vec<u8>>;
+
+    const POLL: Duration = Duration::from_millis(1);
+
+    #[tokio::test]
+    async fn barrier_releases_exactly_at_the_kth_arrival() {
+        let net = SimNetwork::new(31);
+        let store = Arc::new(Store::new());
+        let node = || {
+            Arc::new(
+                DistributedBarrier::new(store.clone())
+                    .with_ttl(Duration::from_secs(5))
+                    .with_poll_interval(POLL)
+                    .with_time_source(net.clock().time_source()),
+            )
+        };
+        let nodes: Vec<_> = (0..3).map(|_| node()).collect();
+
+        let waiters: Vec<_> = nodes[..2]
+            .iter()
+            .enumerate()
+            .map(|(i, barrier)| {
+                let barrier = barrier.clone();
+                tokio::spawn(async move { barrier.enter("map-done", &format!("w{i}"), 3).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(nodes[2].arrived("map-done").unwrap(), 2);
+        assert!(waiters.iter().all(|w| !w.is_finished()));
+        // 重复登记幂等，人数不同的登记被拒绝
+        assert!(!nodes[2].try_enter("map-done", "w0", 3).unwrap());
+        assert!(nodes[2].try_enter("map-done", "w9", 4).is_err());
+
+        assert!(nodes[2].try_enter("map-done", "w2", 3).unwrap());
+        for waiter in waiters {
+            waiter.await.unwrap().unwrap();
+        }
+        assert!(nodes[0].try_enter("map-done", "late", 3).is_err());
+
+        // 只到了一个参与者的屏障过期后被遗弃：等待者收到错误，清理后同名屏障重新开始
+        let lonely = {
+            let barrier = nodes[0].clone();
+            tokio::spawn(async move { barrier.enter("reduce-done", "w0", 2).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        net.run_for(Duration::from_secs(6));
+        assert!(lonely.await.unwrap().is_err());
+        assert_eq!(nodes[1].arrived("reduce-done").unwrap(), 0);
+        assert_eq!(nodes[1].cleanup().unwrap(), 2);
+        assert!(!nodes[1].try_enter("reduce-done", "w1", 2).unwrap());
+        assert!(nodes[2].try_enter("reduce-done", "w2", 2).unwrap());
+    }
+
+    #[tokio::test]
+    async fn semaphore_reclaims_permits_after_lease_lapses() {
+        let net = SimNetwork::new(32);
+        let store = Arc::new(Store::new());
+        let node = |id: &str| {
+            DistributedSemaphore::new(store.clone(), id, 3)
+                .with_lease(Duration::from_secs(2))
+                .with_poll_interval(POLL)
+                .with_time_source(net.clock().time_source())
+        };
+        let (a, b) = (node("a"), node("b"));
+
+        let crashed = a.try_acquire("workers", 2).unwrap().unwrap();
+        let survivor = b.acquire("workers", 1).await.unwrap();
+        assert_eq!(b.available("workers").unwrap(), 0);
+        assert!(b.try_acquire("workers", 1).unwrap().is_none());
+        assert!(b.try_acquire("workers", 4).is_err());
+
+        // a 崩溃：不续约也不释放；b 在到期前续约
+        net.run_for(Duration::from_millis(1_500));
+        let survivor = b.renew(&survivor).unwrap();
+        net.run_for(Duration::from_millis(1_000));
+        assert!(b.renew(&crashed).is_err());
+        assert_eq!(b.available("workers").unwrap(), 2);
+        let reclaimed = b.try_acquire("workers", 2).unwrap().unwrap();
+        assert!(!a.release(&crashed).unwrap());
+        assert!(b.try_acquire("workers", 1).unwrap().is_none());
+
+        // 阻塞获取在许可归还后返回
+        let waiter = {
+            let c = node("c");
+            tokio::spawn(async move { c.acquire("workers", 2).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiter.is_finished());
+        assert!(b.release(&reclaimed).unwrap());
+        let lease = waiter.await.unwrap().unwrap();
+        assert_eq!(lease.permits, 2);
+        assert!(b.release(&survivor).unwrap());
+        assert_eq!(b.available("workers").unwrap(), 1);
+    }
+}
@@ -0,0 +1,116 @@
+// 测试目的：复制 KV 的 MVCC 只读快照
+// - 不变量：1) 快照开启后 1000 次并发交错写入（含删除）期间与之后，经快照的 multi_get 始终返回快照点的
+//              原值；新快照看到最新值；
+//           2) GC 只回收没有活跃快照、也不在保留窗口内需要的版本；快照注销后其版本才被回收，早于回收
+//              界限的快照点被拒绝。
+use distributed::benchmarks::loadgen::InMemoryReplicatedKv;
+use distributed::consistency::ConsistencyLevel;
+use distributed::storage::cached_kv::ReplicatedKv;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+const LEVEL: ConsistencyLevel = ConsistencyLevel::Quorum;
+
+type Kv = InMemoryReplicatedKv<String, u64>;
+
+fn keys() -> Vec<String> {
+    (0..10).map(|i| format!("acct/{i}")).collect()
+}
+
+#[test]
+fn snapshot_is_stable_under_interleaved_writes() {
+    let kv = Arc::new(Kv::new(3));
+    for key in keys() {
+        kv.put(key, 100, LEVEL).unwrap();
+    }
+    let snapshot = kv.snapshot().unwrap();
+    assert_eq!(snapshot.version(), 10);
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let (done, keys) = (done.clone(), keys());
+        thread::spawn(move || {
+            let mut reads = 0;
+            loop {
+                assert_eq!(snapshot.multi_get(&keys), vec![Some(100); 10]);
+                reads += 1;
+                if done.load(Ordering::SeqCst) {
+                    return (snapshot, reads);
+                }
+            }
+        })
+    };
+    let writers: Vec<_> = (0..4u64)
+        .map(|w| {
+            let kv = kv.clone();
+            thread::spawn(move || {
+                let keys = keys();
+                for i in 0..250u64 {
+                    let key = &keys[((w + i) % 10) as usize];
+                    if i % 7 == 0 {
+                        kv.delete(key, LEVEL).unwrap();
+                    } else {
+                        kv.put(key.clone(), w * 1_000 + i, LEVEL).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    done.store(true, Ordering::SeqCst);
+    let (snapshot, reads) = reader.join().unwrap();
+    assert!(reads > 0);
+    assert_eq!(snapshot.multi_get(&keys()), vec![Some(100); 10]);
+
+    // 新快照与法定人数读一致，都看到最新值
+    let latest = kv.snapshot().unwrap();
+    assert_eq!(latest.version(), 1_010);
+    for key in keys() {
+        assert_eq!(latest.get(&key), kv.get(&key, LEVEL).unwrap(), "{key}");
+    }
+    assert!(kv.snapshot_at(1_011).is_err());
+}
+
+#[test]
+fn gc_reclaims_only_versions_no_snapshot_needs() {
+    let kv = Kv::new(3);
+    let (a, b) = ("a".to_string(), "b".to_string());
+    for i in 1..=5 {
+        kv.put(a.clone(), i, LEVEL).unwrap();
+    }
+    let snapshot = kv.snapshot_at(3).unwrap();
+    for i in 6..=1_000 {
+        kv.put(if i % 2 == 0 { a.clone() } else { b.clone() }, i, LEVEL)
+            .unwrap();
+    }
+    kv.delete(&b, LEVEL).unwrap();
+    assert_eq!(kv.mvcc().version_count(), 1_001);
+
+    // 快照点 3 之前只有版本 1、2 不再被需要
+    assert_eq!(kv.gc_versions(), 2);
+    assert_eq!(snapshot.get(&a), Some(3));
+    assert_eq!(snapshot.get(&b), None);
+    assert!(kv.snapshot_at(2).is_err());
+    let again = kv.snapshot_at(4).unwrap();
+    assert_eq!(again.get(&a), Some(4));
+
+    // 快照全部注销后，每个键只保留最新版本（含 b 的删除墓碑）
+    drop((snapshot, again));
+    assert_eq!(kv.mvcc().active_snapshots(), 0);
+    assert_eq!(kv.gc_versions(), 997);
+    assert_eq!(kv.mvcc().version_count(), 2);
+    let latest = kv.snapshot().unwrap();
+    assert_eq!(latest.multi_get([&a, &b]), vec![Some(1_000), None]);
+
+    // 保留窗口内的历史即使没有快照也不回收
+    let retained = Kv::new(1).with_snapshot_retention(100);
+    for i in 1..=500 {
+        retained.put(a.clone(), i, LEVEL).unwrap();
+    }
+    assert_eq!(retained.gc_versions(), 399);
+    assert_eq!(retained.snapshot_at(400).unwrap().get(&a), Some(400));
+    assert!(retained.snapshot_at(399).is_err());
+}
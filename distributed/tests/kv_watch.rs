@@ -0,0 +1,210 @@
+// 测试目的：复制 KV 的键变更订阅（watch）
+// - 不变量：1) 从给定版本订阅先回放历史再接收实时变更，只含匹配前缀的键；已压缩的版本返回
+//              Compacted(min_version)；
+//           2) 1000 次并发交错写入后，每个订阅者按版本严格递增、恰好一次地收到匹配的事件；慢订阅者
+//              缓冲溢出后被取消；领导者切换后的重放不产生重复事件；
+//           3) 历史由 WAL 提供：旧领导者写入的事件在新领导者上从 WAL 回放，订阅者跨领导者切换
+//              按版本顺序恰好一次地收到全部事件；WAL 压缩边界在重新打开后仍然生效。
+use distributed::benchmarks::loadgen::InMemoryReplicatedKv;
+use distributed::codec::JsonCodec;
+use distributed::consistency::ConsistencyLevel;
+use distributed::storage::cached_kv::ReplicatedKv;
+use distributed::storage::{KvEvent, SegmentedWal, WalWatchLog, WatchError, WatchHub};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+const LEVEL: ConsistencyLevel = ConsistencyLevel::Quorum;
+
+type Kv = InMemoryReplicatedKv<String, String>;
+
+fn put(kv: &Kv, key: &str, value: &str) {
+    kv.put(key.to_string(), value.to_string(), LEVEL).unwrap();
+}
+
+fn versions(events: &[KvEvent<String, String>]) -> Vec<u64> {
+    events.iter().map(KvEvent::version).collect()
+}
+
+#[test]
+fn replay_from_version_filters_prefix_and_reports_compaction() {
+    let kv = Kv::new(3).with_watch_history(100);
+    put(&kv, "app/a", "1");
+    put(&kv, "sys/x", "2");
+    put(&kv, "app/b", "3");
+    kv.delete(&"app/a".to_string(), LEVEL).unwrap();
+
+    // 从版本 2 回放：版本 3、4 属于 app/，版本 2 被前缀过滤
+    let watch = kv.watch_from("app/".to_string(), Some(2)).unwrap();
+    let live = kv.watch("app/".to_string()).unwrap();
+    put(&kv, "app/c", "5");
+    put(&kv, "sys/y", "6");
+    let events = watch.drain().unwrap();
+    assert_eq!(
+        events,
+        vec![
+            KvEvent::Put {
+                key: "app/b".into(),
+                value: "3".into(),
+                version: 3
+            },
+            KvEvent::Delete {
+                key: "app/a".into(),
+                version: 4
+            },
+            KvEvent::Put {
+                key: "app/c".into(),
+                value: "5".into(),
+                version: 5
+            },
+        ]
+    );
+    assert_eq!(versions(&live.drain().unwrap()), [5]);
+
+    kv.compact_watch_history(4).unwrap();
+    assert_eq!(kv.watch_hub().min_version(), 4);
+    let err = kv.watch_from("app/".to_string(), Some(3)).err().unwrap();
+    assert_eq!(err, WatchError::Compacted(4));
+    let watch = kv.watch_from(String::new(), Some(4)).unwrap();
+    assert_eq!(versions(&watch.drain().unwrap()), [4, 5, 6]);
+
+    // 历史上限同样压缩最旧的事件
+    let bounded = Kv::new(1).with_watch_history(2);
+    for i in 0..5 {
+        put(&bounded, "k", &i.to_string());
+    }
+    let err = bounded.watch_from(String::new(), Some(1)).err().unwrap();
+    assert_eq!(err, WatchError::Compacted(4));
+}
+
+#[test]
+fn interleaved_writes_are_delivered_once_in_version_order() {
+    let kv = Arc::new(Kv::new(3).with_watch_buffer(2_000));
+    let all = kv.watch(String::new()).unwrap();
+    let even = kv.watch("even/".to_string()).unwrap();
+    let slow = Kv::new(1).with_watch_buffer(10);
+    let overflowing = slow.watch(String::new()).unwrap();
+
+    let writers: Vec<_> = (0..4)
+        .map(|w| {
+            let kv = kv.clone();
+            thread::spawn(move || {
+                for i in 0..250 {
+                    let prefix = if i % 2 == 0 { "even" } else { "odd" };
+                    put(&kv, &format!("{prefix}/{w}-{i}"), &i.to_string());
+                }
+            })
+        })
+        .collect();
+    for i in 0..20 {
+        put(&slow, "k", &i.to_string());
+    }
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let events = all.drain().unwrap();
+    assert_eq!(versions(&events), (1..=1_000).collect::<Vec<_>>());
+    let even_events = even.drain().unwrap();
+    assert_eq!(even_events.len(), 500);
+    assert!(
+        even_events
+            .windows(2)
+            .all(|w| w[0].version() < w[1].version())
+    );
+    assert!(even_events.iter().all(|e| e.key().starts_with("even/")));
+
+    // 慢订阅者：已缓冲的 10 个事件仍可读出，随后得到溢出错误，不再接收
+    assert_eq!(overflowing.pending(), 10);
+    assert_eq!(overflowing.drain().unwrap().len(), 10);
+    assert_eq!(overflowing.drain().err(), Some(WatchError::Overflow(10)));
+    assert_eq!(slow.watch_hub().watcher_count(), 0);
+
+    // 新领导者重放已发布的条目：订阅者对每个版本只收到一次
+    let hub = WatchHub::<String, String>::new();
+    let watch = hub.watch(String::new(), None).unwrap();
+    let event = |version: u64| KvEvent::Put {
+        key: format!("k{version}"),
+        value: String::new(),
+        version,
+    };
+    for version in 1..=5 {
+        assert!(hub.publish(event(version)).unwrap());
+    }
+    let replayed: Vec<bool> = (3..=8).map(|v| hub.publish(event(v)).unwrap()).collect();
+    assert_eq!(replayed, [false, false, false, true, true, true]);
+    assert_eq!(
+        versions(&watch.drain().unwrap()),
+        (1..=8).collect::<Vec<_>>()
+    );
+}
+
+/// 接管 `dir` 中 WAL 的一个领导者实例
+fn leader(dir: &Path) -> Kv {
+    let wal = SegmentedWal::new(dir, JsonCodec).with_segment_entries(4);
+    Kv::new(3).with_watch_log(Arc::new(WalWatchLog::open(wal).unwrap()))
+}
+
+#[test]
+fn watch_survives_leader_change_via_wal_replay() {
+    let dir = std::env::temp_dir().join(format!("kv-watch-wal-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let old = leader(&dir);
+    let watch = old.watch(String::new()).unwrap();
+    for i in 1..=10 {
+        put(&old, &format!("k{i}"), &i.to_string());
+    }
+    // 订阅者只消费了前 6 个事件，旧领导者随即失效
+    let mut received = Vec::new();
+    for _ in 0..6 {
+        received.push(watch.try_next().unwrap().unwrap());
+    }
+    drop(watch);
+    drop(old);
+
+    // 新领导者接管同一日志，并重放它所知道的尾部条目：都已发布过，不再投递
+    let new = leader(&dir);
+    assert_eq!(new.watch_hub().last_version(), 10);
+    for version in 7..=10 {
+        let replay = KvEvent::Put {
+            key: format!("k{version}"),
+            value: version.to_string(),
+            version,
+        };
+        assert!(!new.watch_hub().publish(replay).unwrap());
+    }
+
+    // 订阅者从下一个版本续订：7..=10 来自 WAL，之后是新领导者的实时写入
+    let resumed = new.watch_from(String::new(), Some(7)).unwrap();
+    for i in 11..=15 {
+        put(&new, &format!("k{i}"), &i.to_string());
+    }
+    received.extend(resumed.drain().unwrap());
+    assert_eq!(versions(&received), (1..=15).collect::<Vec<_>>());
+    assert!(
+        received
+            .iter()
+            .all(|e| e.key() == &format!("k{}", e.version()))
+    );
+
+    // 压缩边界来自 WAL，重新打开后仍然生效
+    new.compact_watch_history(5).unwrap();
+    assert_eq!(
+        new.watch_from(String::new(), Some(3)).err(),
+        Some(WatchError::Compacted(5))
+    );
+    drop(new);
+    let reopened = leader(&dir);
+    assert_eq!(reopened.watch_hub().min_version(), 5);
+    assert_eq!(
+        reopened.watch_from(String::new(), Some(4)).err(),
+        Some(WatchError::Compacted(5))
+    );
+    let tail = reopened.watch_from(String::new(), Some(5)).unwrap();
+    assert_eq!(
+        versions(&tail.drain().unwrap()),
+        (5..=15).collect::<Vec<_>>()
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
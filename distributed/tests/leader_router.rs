@@ -0,0 +1,138 @@
+// 测试目的：领导者感知路由
+// - 不变量：
+//   1) 写入流中途领导者变更时，恰好一次重试发往提示的新领导者，之后直接路由到新领导者；
+//   2) 无提示的拒绝按退避重试，总尝试次数受 RetryPolicy 约束（无重试风暴）；
+//   3) 有界陈旧读只落到落后不超过上限的跟随者；会籍事件使缓存失效。
+#[cfg(feature = "runtime-tokio")]
+mod leader_router {
+    use distributed::core::{ClusterMembership, ClusterNodeId};
+    use distributed::network::RetryPolicy;
+    use distributed::network::leader_router::{LeaderRouter, ReadPreference, RouteError};
+    use distributed::swim::{SwimEvent, SwimMemberState};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    /// 组成员的节点 ID 文本
+    const N1: &str = "00000000-0000-0000-0000-000000000001";
+    const N2: &str = "00000000-0000-0000-0000-000000000002";
+    const N3: &str = "00000000-0000-0000-0000-000000000003";
+
+    fn id(text: &str) -> ClusterNodeId {
+        text.parse().unwrap()
+    }
+
+    /// 模拟共识组：只有 `leader` 接受写入，其余节点按 `hint` 回复 NotLeader
+    struct Cluster {
+        leader: Option<String>,
+        hint: bool,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl Cluster {
+        fn new(leader: Option<&str>, hint: bool) -> Self {
+            Self {
+                leader: leader.map(str::to_string),
+                hint,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        async fn handle(&self, node: String) -> Result<String, RouteError> {
+            self.calls.lock().unwrap().push(node.clone());
+            match &self.leader {
+                Some(leader) if *leader == node => Ok(node),
+                leader => Err(RouteError::NotLeader {
+                    hint: leader.clone().filter(|_| self.hint),
+                }),
+            }
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    fn router() -> LeaderRouter {
+        let router = LeaderRouter::new().with_retry_policy(RetryPolicy {
+            max_retries: 3,
+            retry_on_empty: false,
+            backoff_base_ms: Some(50),
+        });
+        router.set_group("g1", vec![N1.into(), N2.into(), N3.into()]);
+        router
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn leader_change_mid_stream_retries_once_with_hint() {
+        let router = router();
+        router.set_leader("g1", N1);
+        let mut cluster = Cluster::new(Some(N1), true);
+        let start = Instant::now();
+
+        for _ in 0..2 {
+            let served = router.write("g1", |n| cluster.handle(n)).await.unwrap();
+            assert_eq!(served, N1);
+        }
+        cluster.leader = Some(N2.into());
+        for _ in 0..3 {
+            let served = router.write("g1", |n| cluster.handle(n)).await.unwrap();
+            assert_eq!(served, N2);
+        }
+
+        assert_eq!(cluster.calls(), [N1, N1, N1, N2, N2, N2]);
+        assert_eq!(router.current_leader("g1").as_deref(), Some(N2));
+        // 带提示的重定向不退避
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn no_hint_backs_off_and_stops_at_retry_limit() {
+        let router = router();
+        router.set_leader("g1", N1);
+        let cluster = Cluster::new(None, false);
+        let start = Instant::now();
+
+        let err = router.write("g1", |n| cluster.handle(n)).await.unwrap_err();
+        assert!(matches!(err, RouteError::NotLeader { hint: None }));
+        // 1 次初始尝试 + 3 次重试，轮流探测成员
+        assert_eq!(cluster.calls(), [N1, N1, N2, N3]);
+        assert_eq!(start.elapsed(), Duration::from_millis(50 + 100 + 200));
+        assert_eq!(router.current_leader("g1"), None);
+
+        // 选举结束后，探测到的领导者被缓存
+        let cluster = Cluster::new(Some(N2), false);
+        assert_eq!(router.write("g1", |n| cluster.handle(n)).await.unwrap(), N2);
+        assert_eq!(router.current_leader("g1").as_deref(), Some(N2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bounded_staleness_reads_and_membership_invalidation() {
+        let router = router();
+        router.set_leader("g1", N1);
+        router.report_staleness("g1", N2, Duration::from_millis(10));
+        router.report_staleness("g1", N3, Duration::from_secs(2));
+        let read = |node: String| async move { Ok::<_, RouteError>(node) };
+
+        let bounded = ReadPreference::BoundedStaleness(Duration::from_millis(100));
+        for _ in 0..3 {
+            assert_eq!(router.read("g1", bounded, read).await.unwrap(), N2);
+        }
+        let leader_read = router.read("g1", ReadPreference::Leader, read).await;
+        assert_eq!(leader_read.unwrap(), N1);
+
+        // 领导者被判定失效：缓存作废
+        router.on_swim_event(&SwimEvent::new(N1.into(), SwimMemberState::Faulty, 1));
+        assert_eq!(router.current_leader("g1"), None);
+
+        // n2 离开会籍：不再有合格跟随者，读回到领导者路径
+        router.set_leader("g1", N3);
+        router.on_membership_change(&ClusterMembership::new(vec![id(N1), id(N3)]));
+        assert_eq!(router.read("g1", bounded, read).await.unwrap(), N3);
+
+        assert!(matches!(
+            router.write("missing", read).await,
+            Err(RouteError::UnknownGroup(_))
+        ));
+    }
+}
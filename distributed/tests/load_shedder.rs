@@ -0,0 +1,72 @@
+// 测试目的：过载时按优先级丢弃请求
+// - 不变量：1) 负载超过高水位后低优先级请求被丢弃，高优先级请求仍被接纳直至名额用尽；
+//           2) 许可丢弃后名额归还，负载回落后低优先级请求重新被接纳。
+use distributed::core::errors::DistributedError;
+use distributed::resilience::{LoadShedder, ShedError};
+
+#[test]
+fn sheds_low_priority_under_double_offered_load() {
+    let shedder = LoadShedder::new(100)
+        .with_high_watermark(0.5)
+        .with_min_accept_priority(5);
+
+    // 200% 负载：200 个请求同时在途，高低优先级交替到达
+    let mut held = Vec::new();
+    let (mut low_ok, mut low_shed, mut high_ok, mut high_rejected) = (0, 0, 0, 0);
+    for i in 0..200 {
+        let priority = if i % 2 == 0 { 1 } else { 9 };
+        match shedder.add_request(priority) {
+            Ok(permit) => {
+                held.push(permit);
+                if priority < 5 {
+                    low_ok += 1
+                } else {
+                    high_ok += 1
+                }
+            }
+            Err(ShedError::Shed { priority, load }) => {
+                assert!(priority < 5);
+                assert!(load > 0.5);
+                low_shed += 1;
+            }
+            Err(ShedError::AtCapacity { .. }) => {
+                if priority < 5 {
+                    low_shed += 1
+                } else {
+                    high_rejected += 1
+                }
+            }
+        }
+    }
+
+    assert_eq!(shedder.in_flight(), 100);
+    assert_eq!(shedder.current_load(), 1.0);
+    // 负载超过 0.5 前高低优先级各接纳一半
+    assert_eq!(low_ok, 26);
+    assert_eq!(low_shed, 74);
+    assert_eq!(high_ok, 74);
+    assert_eq!(high_rejected, 26);
+    assert!(high_ok > low_ok * 2);
+
+    let err: DistributedError = shedder.add_request(9).unwrap_err().into();
+    assert!(matches!(err, DistributedError::Overloaded(_)));
+}
+
+#[test]
+fn dropping_permits_restores_capacity() {
+    let shedder = LoadShedder::new(10).with_high_watermark(0.5);
+    let held: Vec<_> = (0..8).map(|_| shedder.add_request(200).unwrap()).collect();
+    assert_eq!(shedder.current_load(), 0.8);
+    assert!(matches!(
+        shedder.add_request(0),
+        Err(ShedError::Shed { .. })
+    ));
+
+    drop(held);
+    assert_eq!(shedder.in_flight(), 0);
+    assert_eq!(shedder.current_load(), 0.0);
+    let _permit = shedder
+        .add_request(0)
+        .expect("idle shedder accepts any priority");
+    assert_eq!(shedder.in_flight(), 1);
+}
@@ -0,0 +1,112 @@
+// 测试目的：复制 KV 的端到端负载生成与报告
+// - 不变量：
+//   1) 带丢包的 2 秒负载下报告字段齐全且自洽：ops == successes + failures，错误分布之和
+//      等于 failures，分位数单调，CSV / JSON 与报告一致；
+//   2) 直方图分位数的相对误差不超过 1%；
+//   3) Zipfian 分布下最热的键明显比均匀分布更常被访问。
+use distributed::benchmarks::loadgen::{
+    InMemoryReplicatedKv, KeyDistribution, LatencyHistogram, LoadGenerator, LoadReport,
+    WorkloadConfig, key_name,
+};
+use distributed::chaos::ChaosConfig;
+use distributed::storage::{ApplyEvent, ReplicatedKv};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn two_second_workload_reports_consistent_fields() {
+    let kv = InMemoryReplicatedKv::<String, Vec<u8>>::new(3).with_chaos(ChaosConfig {
+        drop_rate: 0.2,
+        ..ChaosConfig::default()
+    });
+    let config = WorkloadConfig::default()
+        .with_duration(Duration::from_secs(2))
+        .with_read_ratio(0.5)
+        .with_keys(1_000, KeyDistribution::Zipfian { theta: 0.99 })
+        .with_value_size(64)
+        .with_target_qps(Some(500))
+        .with_clients(2);
+    let report = LoadGenerator::new(config).run(&kv);
+
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!(report.ops, report.successes + report.failures);
+    assert!(report.reads > 0 && report.writes > 0);
+    assert!(report.successes > 0 && report.failures > 0);
+    assert_eq!(report.errors.get("Network"), Some(&report.failures));
+    assert!(report.elapsed >= Duration::from_secs(2));
+    // 限速生效：不超过目标 QPS 加上一个突发桶
+    assert!(
+        report.throughput() <= 500.0 * 1.2,
+        "{}",
+        report.throughput()
+    );
+    assert!(report.throughput() > 100.0, "{}", report.throughput());
+    assert!(report.p50() <= report.p99() && report.p99() <= report.p999());
+    assert!(report.p999() <= report.latency.max());
+
+    let csv = report.to_csv();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(LoadReport::CSV_HEADER));
+    let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+    assert_eq!(row.len(), LoadReport::CSV_HEADER.split(',').count());
+    assert_eq!(row[1], report.ops.to_string());
+    assert_eq!(row[11], format!("Network={}", report.failures));
+
+    let json = report.to_json();
+    assert_eq!(json["ops"], report.ops);
+    assert_eq!(json["errors"]["Network"], report.failures);
+    assert_eq!(json["latency_us"]["p99"], report.p99().as_micros() as u64);
+}
+
+#[test]
+fn histogram_percentiles_within_one_percent() {
+    let mut hist = LatencyHistogram::new();
+    for micros in 1..=10_000u64 {
+        hist.record(Duration::from_micros(micros));
+    }
+    assert_eq!(hist.count(), 10_000);
+    assert_eq!(hist.min(), Duration::from_micros(1));
+    assert_eq!(hist.max(), Duration::from_micros(10_000));
+    for (q, expected) in [(0.5, 5_000.0), (0.99, 9_900.0), (0.999, 9_990.0)] {
+        let got = hist.percentile(q).as_nanos() as f64 / 1_000.0;
+        assert!(
+            (got - expected).abs() / expected <= 0.01,
+            "p{q}: {got} vs {expected}"
+        );
+    }
+
+    let mut merged = LatencyHistogram::new();
+    merged.merge(&hist);
+    merged.merge(&hist);
+    assert_eq!(merged.count(), 20_000);
+    assert_eq!(merged.percentile(0.5), hist.percentile(0.5));
+}
+
+fn hottest_key_share(distribution: KeyDistribution) -> f64 {
+    let kv = InMemoryReplicatedKv::<String, Vec<u8>>::new(3);
+    let seen: Arc<Mutex<HashMap<String, u64>>> = Arc::default();
+    let sink = seen.clone();
+    kv.subscribe(Box::new(move |event: &ApplyEvent<String>| {
+        *sink.lock().unwrap().entry(event.key.clone()).or_insert(0) += 1;
+    }));
+    let config = WorkloadConfig::default()
+        .with_duration(Duration::from_millis(300))
+        .with_read_ratio(0.0)
+        .with_keys(100, distribution)
+        .with_value_size(8);
+    let report = LoadGenerator::new(config).run(&kv);
+    assert_eq!(report.successes, report.writes);
+    let seen = seen.lock().unwrap();
+    let hottest = seen.get(&key_name(0)).copied().unwrap_or(0);
+    hottest as f64 / report.writes as f64
+}
+
+#[test]
+fn zipfian_concentrates_on_hot_keys() {
+    let uniform = hottest_key_share(KeyDistribution::Uniform);
+    let zipfian = hottest_key_share(KeyDistribution::Zipfian { theta: 0.99 });
+    // 100 个键、theta≈1 时首个键约占 19%，均匀分布约 1%
+    assert!(uniform < 0.05, "uniform share {uniform}");
+    assert!(zipfian > 0.1, "zipfian share {zipfian}");
+}
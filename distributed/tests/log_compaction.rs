@@ -0,0 +1,109 @@
+// 测试目的：定时驱动的日志压缩（分段 WAL 与 Raft 日志）
+// - 不变量：1) 以 1k 条为阈值追加 10k 条，磁盘上的记录数始终不超过阈值加一个段；每次压缩后与重启恢复后
+//              存活记录（每个键的最新值）均可完整读出；
+//           2) Raft 日志压缩先在已应用位置创建快照再截断前缀，保留的条目有界，索引与应用顺序不受影响。
+use distributed::codec::JsonCodec;
+use distributed::consensus::raft::{LogIndex, MinimalRaft};
+use distributed::simnet::SimNetwork;
+use distributed::storage::{Compactor, EntryCount, SegmentedWal};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Wal = SegmentedWal<(String, u64), JsonCodec>;
+
+fn live_map(wal: &Wal) -> BTreeMap<String, u64> {
+    wal.read_live()
+        .unwrap()
+        .into_iter()
+        .map(|(_, (k, v))| (k, v))
+        .collect()
+}
+
+#[test]
+fn wal_stays_bounded_and_live_data_survives_compaction() {
+    let dir = std::env::temp_dir().join(format!("wal-compaction-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let open = || {
+        Wal::new(&dir, JsonCodec)
+            .with_segment_entries(100)
+            .with_key(|(k, _): &(String, u64)| k.clone())
+            .open()
+            .unwrap()
+    };
+    let net = SimNetwork::new(11);
+    let wal = Arc::new(Mutex::new(open()));
+    let clock = net.clock();
+    let compactor = Arc::new(
+        Compactor::new(wal.clone(), EntryCount(1_000))
+            .with_interval(Duration::from_millis(10))
+            .with_clock(move || clock.now()),
+    );
+    compactor.start(net.timer());
+
+    let mut model = BTreeMap::new();
+    let mut runs_seen = 0;
+    for i in 0..10_000u64 {
+        let key = format!("k{}", i % 50);
+        wal.lock().unwrap().append(&(key.clone(), i)).unwrap();
+        model.insert(key, i);
+        net.run_for(Duration::from_millis(1));
+
+        let wal = wal.lock().unwrap();
+        assert!(wal.len() <= 1_000 + wal.segment_entries(), "{}", wal.len());
+        if compactor.metrics().runs() != runs_seen {
+            runs_seen = compactor.metrics().runs();
+            assert_eq!(live_map(&wal), model);
+        }
+    }
+    compactor.stop();
+    assert!(runs_seen >= 9, "{runs_seen}");
+    let metrics = compactor.metrics();
+    assert!(metrics.entries_reclaimed() >= 9_000);
+    assert!(metrics.bytes_reclaimed() > 0);
+    assert!(metrics.last_run().is_some());
+
+    // 重启恢复：压缩后的段与活跃段一起读出，新记录的序号继续递增
+    let next_seq = wal.lock().unwrap().next_seq();
+    drop(compactor);
+    drop(wal);
+    let mut reopened = open();
+    assert_eq!(live_map(&reopened), model);
+    assert_eq!(reopened.append(&("k0".into(), 1)).unwrap(), next_seq);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn raft_log_is_snapshotted_then_truncated() {
+    let net = SimNetwork::new(12);
+    let applied = Arc::new(Mutex::new(Vec::new()));
+    let mut raft = MinimalRaft::<u64>::new();
+    let sink = applied.clone();
+    raft.set_apply(Box::new(move |e: &u64| sink.lock().unwrap().push(*e)));
+    raft.become_leader(Vec::<String>::new());
+    let raft = Arc::new(Mutex::new(raft));
+    let clock = net.clock();
+    let compactor = Arc::new(
+        Compactor::new(raft.clone(), EntryCount(1_000))
+            .with_interval(Duration::from_millis(10))
+            .with_clock(move || clock.now()),
+    );
+    compactor.start(net.timer());
+
+    for i in 1..=10_000u64 {
+        assert_eq!(raft.lock().unwrap().propose(i).unwrap(), LogIndex(i));
+        net.run_for(Duration::from_millis(1));
+        assert!(raft.lock().unwrap().retained_entries() <= 1_000 + 10);
+    }
+    compactor.stop();
+
+    let mut raft = raft.lock().unwrap();
+    assert_eq!(raft.last_log_index(), LogIndex(10_000));
+    let snapshot = raft.snapshot().unwrap();
+    assert_eq!(snapshot.last_included_index, raft.log_start());
+    assert!(raft.log_start().0 >= 9_000);
+    // 截断不能越过已应用的位置
+    assert!(raft.truncate_before(LogIndex(10_002)).is_err());
+    assert_eq!(*applied.lock().unwrap(), (1..=10_000).collect::<Vec<_>>());
+    assert_eq!(compactor.metrics().entries_reclaimed(), raft.log_start().0);
+}
@@ -0,0 +1,73 @@
+// 测试目的：验证成员视图纪元随加入/离开递增、跨线程等待与按条目纪元合并
+// - 不变量：每次生效的变更纪元加一；合并取各条目纪元较高的一侧
+use distributed::core::{ClusterEpoch, ClusterMembership, ClusterNodeId};
+use std::time::Duration;
+
+fn ids<const N: usize>() -> [ClusterNodeId; N] {
+    std::array::from_fn(|_| ClusterNodeId::generate())
+}
+
+#[test]
+fn three_join_leave_operations_reach_epoch_three() {
+    let [n1, n2, n3] = ids();
+    let mut membership = ClusterMembership::new(vec![n1.clone()]);
+    assert_eq!(membership.current_epoch(), ClusterEpoch(0));
+
+    assert_eq!(membership.join(n2.clone()), ClusterEpoch(1));
+    assert_eq!(membership.join(n3.clone()), ClusterEpoch(2));
+    assert_eq!(membership.leave(&n1), ClusterEpoch(3));
+    assert_eq!(membership.current_epoch(), ClusterEpoch(3));
+    assert_eq!(membership.nodes, vec![n2.clone(), n3]);
+
+    // 无效变更不推进纪元
+    membership.join(n2);
+    membership.leave(&n1);
+    assert_eq!(membership.current_epoch(), ClusterEpoch(3));
+}
+
+#[test]
+fn wait_for_epoch_observes_updates_from_another_thread() {
+    let [n1, n2, n3] = ids();
+    let mut membership = ClusterMembership::new(vec![n1]);
+    let watcher = membership.clone();
+    assert!(
+        watcher
+            .wait_for_epoch(ClusterEpoch(1), Duration::from_millis(20))
+            .is_err()
+    );
+
+    let waiter =
+        std::thread::spawn(move || watcher.wait_for_epoch(ClusterEpoch(2), Duration::from_secs(5)));
+    membership.join(n2);
+    membership.join(n3);
+    assert!(waiter.join().unwrap().is_ok());
+}
+
+#[test]
+fn merge_takes_higher_epoch_entry_per_node() {
+    let [n1, n2, n3] = ids();
+    let base = ClusterMembership::new(vec![n1.clone(), n2.clone()]);
+
+    // a: n2 离开（纪元 1）
+    let mut a = base.clone();
+    a.leave(&n2);
+    // b: n3 加入、离开、再加入（纪元 3），n2 未变
+    let mut b = base.clone();
+    b.join(n3.clone());
+    b.leave(&n3);
+    b.join(n3.clone());
+
+    a.merge(&b);
+    assert_eq!(a.current_epoch(), ClusterEpoch(3));
+    assert!(a.is_member(&n1));
+    assert!(!a.is_member(&n2), "a 的离开（纪元 1）高于 b 的初始条目");
+    assert!(a.is_member(&n3));
+
+    // 对称合并得到相同成员
+    b.merge(&a);
+    let mut left = a.nodes.clone();
+    let mut right = b.nodes.clone();
+    left.sort();
+    right.sort();
+    assert_eq!(left, right);
+}
@@ -0,0 +1,225 @@
+// 测试目的：验证传输层消息签名中间件。
+// - 不变量：1) 以错误密钥伪造或签名者未知的 AppendEntries 被丢弃并计数，不影响接收方日志；
+//   2) 合法流量在伪造流量前后照常复制与提交；3) Ed25519 与 HMAC 对篡改、未知签名者一律拒绝；
+//   4) 截获后重放的请求、转发给其他节点的请求与未回显请求序号的回复都被拒绝。
+
+#[cfg(feature = "signing")]
+mod signing {
+    use distributed::consensus::raft::{
+        AppendEntriesReq, LogIndex, MinimalRaft, RaftTransport, Term,
+    };
+    use distributed::security::{
+        Ed25519Authenticator, HmacSha256Authenticator, KeyProvider, SigningMiddleware,
+    };
+    use distributed::simnet::{SimNetwork, SimRaftTransport};
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+    use std::time::Duration;
+
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("simnet future should be ready"),
+        }
+    }
+
+    fn hmac(node: &str, keys: &HashMap<String, Vec<u8>>) -> Arc<SigningMiddleware> {
+        let keys: Arc<dyn KeyProvider> = Arc::new(keys.clone());
+        Arc::new(SigningMiddleware::new(Arc::new(
+            HmacSha256Authenticator::new(node, keys),
+        )))
+    }
+
+    fn replicate(
+        leader: &Mutex<MinimalRaft<String>>,
+        transport: &SimRaftTransport,
+        peers: &[&str],
+    ) {
+        for peer in peers {
+            let req = leader.lock().unwrap().append_entries_for(peer, "a");
+            let last_sent = req.prev_log_index.0 + req.entries.len() as u64;
+            let resp = ready(transport.append_entries(peer, req)).unwrap();
+            leader
+                .lock()
+                .unwrap()
+                .handle_append_entries_resp(peer, LogIndex(last_sent), &resp);
+        }
+    }
+
+    #[test]
+    fn forged_append_entries_are_dropped_while_legit_traffic_proceeds() {
+        let keys: HashMap<String, Vec<u8>> = ["a", "b", "c"]
+            .iter()
+            .map(|id| (id.to_string(), format!("secret-{id}").into_bytes()))
+            .collect();
+        let net = SimNetwork::new(7);
+        let signers = ["a", "b", "c"].map(|id| hmac(id, &keys));
+        let nodes: Vec<_> = signers
+            .iter()
+            .map(|signing| {
+                let node = Arc::new(Mutex::new(MinimalRaft::<String>::new()));
+                let id = signing.node_id().to_string();
+                SimRaftTransport::serve_signed(&net, id, node.clone(), signing.clone());
+                node
+            })
+            .collect();
+        let leader = &nodes[0];
+        let transport = SimRaftTransport::new(net.clone(), "a").with_signing(hmac("a", &keys));
+
+        let vote = leader.lock().unwrap().start_election("a");
+        for peer in ["b", "c"] {
+            let resp = ready(RaftTransport::<String>::request_vote(
+                &transport,
+                peer,
+                vote.clone(),
+            ));
+            assert!(resp.unwrap().vote_granted);
+        }
+        leader.lock().unwrap().become_leader(["b", "c"]);
+        leader.lock().unwrap().propose("set x".to_string()).unwrap();
+        replicate(leader, &transport, &["b", "c"]);
+
+        // 冒充 a 但持有错误密钥；以及使用自身密钥、却不在密钥表中的 m
+        let wrong: HashMap<String, Vec<u8>> =
+            HashMap::from([("a".to_string(), b"guessed".to_vec())]);
+        let impostor = SimRaftTransport::new(net.clone(), "a").with_signing(hmac("a", &wrong));
+        let outsider_keys = HashMap::from([("m".to_string(), b"m-secret".to_vec())]);
+        let outsider =
+            SimRaftTransport::new(net.clone(), "m").with_signing(hmac("m", &outsider_keys));
+        let forged = AppendEntriesReq {
+            term: Term(9),
+            leader_id: "a".to_string(),
+            prev_log_index: LogIndex(0),
+            prev_log_term: Term(0),
+            entries: vec![(Term(9), "evil".to_string())],
+            leader_commit: LogIndex(1),
+        };
+        for attacker in [&impostor, &outsider] {
+            assert!(ready(attacker.append_entries("b", forged.clone())).is_err());
+        }
+        assert_eq!(nodes[1].lock().unwrap().last_log_term(), Term(1));
+
+        leader.lock().unwrap().propose("set y".to_string()).unwrap();
+        replicate(leader, &transport, &["b", "c"]);
+        assert_eq!(leader.lock().unwrap().commit_index().0, 2);
+        assert_eq!(nodes[1].lock().unwrap().last_log_index().0, 2);
+
+        let stats = signers[1].stats();
+        assert_eq!(stats.bad_signature(), 1);
+        assert_eq!(stats.unknown_signer(), 1);
+        assert_eq!(stats.dropped(), 2);
+        assert_eq!(stats.verified(), 3);
+    }
+
+    #[test]
+    fn ed25519_rejects_tampering_and_unknown_signers() {
+        let no_keys: Arc<dyn KeyProvider> = Arc::new(HashMap::<String, Vec<u8>>::new());
+        let a_key = Ed25519Authenticator::new("a", &[1; 32], no_keys.clone())
+            .unwrap()
+            .public_key();
+        let public_keys: Arc<dyn KeyProvider> = Arc::new(HashMap::from([("a".to_string(), a_key)]));
+        let a = SigningMiddleware::new(Arc::new(
+            Ed25519Authenticator::new("a", &[1; 32], public_keys.clone()).unwrap(),
+        ));
+        let b = SigningMiddleware::new(Arc::new(
+            Ed25519Authenticator::new("b", &[2; 32], public_keys.clone()).unwrap(),
+        ));
+
+        let sealed = a.seal("b", b"vote".to_vec()).unwrap();
+        assert_eq!(b.open_from("c", &sealed), None);
+        assert!(matches!(b.open_from("a", &sealed), Some((_, p)) if p == b"vote"));
+
+        let mut tampered = a.sign("b", b"vote".to_vec()).unwrap();
+        tampered.payload = b"veto".to_vec();
+        assert!(b.verify(&tampered).is_err());
+        assert_eq!(a.open(&b.seal("a", b"hi".to_vec()).unwrap()), None);
+        assert_eq!(a.open(b"not json"), None);
+
+        assert_eq!(b.stats().verified(), 1);
+        assert_eq!(b.stats().bad_signature(), 2);
+        assert_eq!(a.stats().unknown_signer(), 1);
+        assert_eq!(a.stats().malformed(), 1);
+    }
+
+    #[test]
+    fn replayed_misdirected_and_unsolicited_messages_are_rejected() {
+        let keys: HashMap<String, Vec<u8>> = ["a", "b", "c"]
+            .iter()
+            .map(|id| (id.to_string(), format!("secret-{id}").into_bytes()))
+            .collect();
+        let (a, b, c) = (hmac("a", &keys), hmac("b", &keys), hmac("c", &keys));
+
+        // 截获的请求原样重放：第二次被高水位拒绝
+        let first = a.seal("b", b"append 1".to_vec()).unwrap();
+        let (seq, payload) = b.open_from("a", &first).unwrap();
+        assert_eq!(payload, b"append 1");
+        assert_eq!(b.open_from("a", &first), None);
+
+        // 发给 b 的请求被转发给 c：接收方不符
+        assert_eq!(c.open_from("a", &first), None);
+
+        // 回复须回显本次请求的序号；旧回复、或被当作请求重放的回复都被拒绝
+        let reply = b.seal_reply("a", seq, b"ok".to_vec()).unwrap();
+        assert_eq!(a.open_reply("b", seq, &reply), Some(b"ok".to_vec()));
+        let second = a.sign("b", b"append 2".to_vec()).unwrap();
+        assert!(second.seq > seq);
+        assert_eq!(a.open_reply("b", second.seq, &reply), None);
+        assert_eq!(a.open_from("b", &reply), None);
+
+        // 序号晚于高水位的新请求照常接受
+        assert!(b.open_from("a", &second.to_bytes().unwrap()).is_some());
+
+        assert_eq!(b.stats().replayed(), 1);
+        assert_eq!(b.stats().verified(), 2);
+        assert_eq!(c.stats().misdirected(), 1);
+        assert_eq!(a.stats().replayed(), 2);
+        assert_eq!(a.stats().verified(), 1);
+    }
+
+    #[test]
+    fn replayed_append_entries_are_dropped_over_simnet() {
+        let keys: HashMap<String, Vec<u8>> = ["a", "b"]
+            .iter()
+            .map(|id| (id.to_string(), format!("secret-{id}").into_bytes()))
+            .collect();
+        let net = SimNetwork::new(11);
+        // a 只需能收到回复
+        net.register("a", |_, _| None);
+        let transport = SimRaftTransport::new(net.clone(), "a").with_signing(hmac("a", &keys));
+        let req = AppendEntriesReq {
+            term: Term(1),
+            leader_id: "a".to_string(),
+            prev_log_index: LogIndex(0),
+            prev_log_term: Term(0),
+            entries: vec![(Term(1), "set x".to_string())],
+            leader_commit: LogIndex(0),
+        };
+
+        // 攻击者先冒充 b 截获一条已签名的 AppendEntries，不作应答
+        let captured = Arc::new(Mutex::new(None));
+        let tap = captured.clone();
+        net.register("b", move |_, envelope| {
+            *tap.lock().unwrap() = Some(envelope.payload.clone());
+            None
+        });
+        assert!(ready(transport.append_entries("b", req.clone())).is_err());
+        let captured = captured.lock().unwrap().take().unwrap();
+
+        let follower = Arc::new(Mutex::new(MinimalRaft::<String>::new()));
+        let b = hmac("b", &keys);
+        SimRaftTransport::serve_signed(&net, "b", follower.clone(), b.clone());
+        assert!(ready(transport.append_entries("b", req)).unwrap().success);
+
+        // 重放截获的请求：签名有效，但序号不高于高水位，被丢弃且不作应答
+        assert!(
+            net.call("a", "b", captured, Duration::from_millis(100))
+                .is_err()
+        );
+        assert_eq!(b.stats().replayed(), 1);
+        assert_eq!(b.stats().verified(), 1);
+        assert_eq!(follower.lock().unwrap().last_log_index(), LogIndex(1));
+    }
+}
@@ -0,0 +1,104 @@
+// 测试目的：基于 Raft 复制计数器的单调 ID 服务
+// - 不变量：1) 顺序取号严格递增，批量取号返回紧随其后的连续区间，跟随者应用后得到相同的计数值；
+//           2) 领导者宕机后新领导者继续取号不产生重复；恢复的旧领导者取号失败而不会拿到重复的 ID。
+#[cfg(feature = "runtime-tokio")]
+mod monotone_id {
+    use distributed::consensus::raft::MinimalRaft;
+    use distributed::core::{IncrementCommand, MonotoneIdService};
+    use distributed::simnet::{SimNetwork, SimRaftTransport};
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type Node = Arc<Mutex<MinimalRaft<IncrementCommand>>>;
+
+    const NODES: [&str; 3] = ["n1", "n2", "n3"];
+
+    fn cluster(net: &SimNetwork) -> Vec<(Node, MonotoneIdService<SimRaftTransport>)> {
+        NODES
+            .iter()
+            .map(|id| {
+                let raft: Node = Arc::new(Mutex::new(MinimalRaft::new()));
+                SimRaftTransport::serve(net, *id, raft.clone());
+                let peers = NODES.iter().filter(|p| *p != id).copied();
+                let transport = SimRaftTransport::new(net.clone(), *id);
+                let service = MonotoneIdService::new(*id, raft.clone(), transport, peers);
+                (raft, service)
+            })
+            .collect()
+    }
+
+    /// 领导者持续发送心跳，直到 `service` 应用到 `value`
+    async fn wait_value(
+        leader: &MonotoneIdService<SimRaftTransport>,
+        service: &MonotoneIdService<SimRaftTransport>,
+        value: u64,
+    ) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while service.counter().value() < value {
+                leader.replicate().await;
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("counter did not catch up");
+    }
+
+    #[tokio::test]
+    async fn sequential_ids_are_strictly_monotone() {
+        let net = SimNetwork::new(21);
+        let nodes = cluster(&net);
+        let leader = &nodes[0].1;
+        assert!(leader.campaign().await.unwrap());
+        assert!(nodes[1].1.next_id().await.is_err());
+
+        let mut last = 0;
+        for _ in 0..20 {
+            let id = leader.next_id().await.unwrap();
+            assert!(id > last, "{id} after {last}");
+            last = id;
+        }
+        assert_eq!(last, 20);
+        assert_eq!(leader.batch_ids(5).await.unwrap(), 21..26);
+        assert_eq!(leader.next_id().await.unwrap(), 26);
+        assert!(leader.batch_ids(0).await.is_err());
+
+        // 心跳把提交索引带给跟随者，跟随者应用同一日志
+        wait_value(leader, &nodes[2].1, 26).await;
+        assert_eq!(nodes[2].1.counter().value(), 26);
+    }
+
+    #[tokio::test]
+    async fn leader_failover_does_not_duplicate_ids() {
+        let net = SimNetwork::new(22);
+        let nodes = cluster(&net);
+        let (old_raft, old) = &nodes[0];
+        assert!(old.campaign().await.unwrap());
+        let mut issued = HashSet::new();
+        for _ in 0..10 {
+            assert!(issued.insert(old.next_id().await.unwrap()));
+        }
+        issued.extend(old.batch_ids(10).await.unwrap());
+
+        // n1 宕机，n2 当选后继续取号
+        net.unregister("n1");
+        let new = &nodes[1].1;
+        assert!(new.campaign().await.unwrap());
+        for _ in 0..10 {
+            let id = new.next_id().await.unwrap();
+            assert!(id > 20, "{id}");
+            assert!(issued.insert(id), "duplicate id {id}");
+        }
+        issued.extend(new.batch_ids(3).await.unwrap());
+        assert_eq!(issued.len(), 33);
+
+        // n1 恢复但仍自认为领导者：条目无法在旧任期提交，取号失败并让位
+        SimRaftTransport::serve(&net, "n1", old_raft.clone());
+        assert!(old.next_id().await.is_err());
+        assert!(!old.is_leader());
+        assert_eq!(new.next_id().await.unwrap(), 34);
+        // 旧任期的冲突条目被新领导者的日志覆盖
+        wait_value(new, old, 34).await;
+        assert_eq!(old.counter().value(), 34);
+    }
+}
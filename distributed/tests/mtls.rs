@@ -0,0 +1,185 @@
+// 测试目的：gRPC 复制传输的双向 TLS 与节点身份校验
+// - 不变量：1) 证书由集群 CA 签发的客户端可以复制，证书来自其他 CA 的客户端在握手阶段被拒绝；
+//           2) 证书有效但 CN 不在会籍视图中的节点被拒绝，请求不会到达处理函数；
+//           3) 经 ConfigManager 触发的证书轮换无需重启即生效，重载失败时保留旧证书。
+#[cfg(feature = "transport-grpc")]
+mod mtls {
+    use distributed::DistributedError;
+    use distributed::config_management::ConfigManager;
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::network::Deadline;
+    use distributed::network::grpc_replication::{
+        GrpcReplicationTransport, NodeHandler, ReplicationServer,
+    };
+    use distributed::network::tls::{RequirePeer, TlsConfig, TlsContext, membership_authorizer};
+    use distributed::replication::{Priority, ReplicateRequest, ReplicationTransport};
+    use distributed::swim::{MembershipView, SwimMemberState};
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, DnType, IsCa, KeyPair};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    type Ca = CertifiedIssuer<'static, KeyPair>;
+
+    #[derive(Clone, Default)]
+    struct Counter(Arc<AtomicUsize>);
+
+    impl NodeHandler for Counter {
+        async fn apply(&self, _request: ReplicateRequest) -> Result<(), DistributedError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn ca(name: &str) -> Ca {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap()
+    }
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mtls-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 写出 `node` 的证书（由 `issuer` 签发）、私钥与信任的 CA 包
+    fn write_node(dir: &Path, node: &str, issuer: &Ca, trusted: &[&Ca]) -> TlsConfig {
+        let mut params =
+            CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, node);
+        let key = KeyPair::generate().unwrap();
+        let cert = params.signed_by(&key, issuer).unwrap();
+        let config = TlsConfig::new(
+            dir.join(format!("{node}.crt")),
+            dir.join(format!("{node}.key")),
+            dir.join(format!("{node}-ca.pem")),
+        );
+        std::fs::write(&config.cert_path, cert.pem()).unwrap();
+        std::fs::write(&config.key_path, key.serialize_pem()).unwrap();
+        let bundle: String = trusted.iter().map(|ca| ca.pem()).collect();
+        std::fs::write(&config.ca_path, bundle).unwrap();
+        config
+    }
+
+    /// 以 mTLS 启动节点 n1，只接受 n1..n3
+    async fn serve(tls: &Arc<TlsContext>, handler: Counter) -> String {
+        let mut view = MembershipView::new("n1".to_string());
+        for node in ["n1", "n2", "n3"] {
+            view.local_update(node, SwimMemberState::Alive, 1);
+        }
+        let authorize = membership_authorizer(Arc::new(Mutex::new(view)));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(RequirePeer::new(
+                    ReplicationServer::new("n1", handler),
+                    tls,
+                    authorize,
+                ))
+                .serve_with_incoming(tls.incoming(listener)),
+        );
+        format!("http://{addr}")
+    }
+
+    async fn send(config: TlsConfig, uri: &str) -> Result<bool, DistributedError> {
+        let tls = Arc::new(TlsContext::new(config).unwrap());
+        let transport = GrpcReplicationTransport::new([("n1", uri)]).with_tls(tls);
+        let request = ReplicateRequest {
+            idempotency_key: "k".into(),
+            payload: vec![1],
+            level: ConsistencyLevel::Quorum,
+            priority: Priority::Normal,
+        };
+        let ack = transport
+            .send("n1", request, Deadline::after(Duration::from_secs(2)))
+            .await?;
+        Ok(ack.applied)
+    }
+
+    #[tokio::test]
+    async fn client_from_foreign_ca_is_refused_and_cluster_client_succeeds() {
+        let dir = dir("ca");
+        let (cluster, foreign) = (ca("cluster"), ca("foreign"));
+        let server =
+            Arc::new(TlsContext::new(write_node(&dir, "n1", &cluster, &[&cluster])).unwrap());
+        let handler = Counter::default();
+        let uri = serve(&server, handler.clone()).await;
+
+        assert!(
+            send(write_node(&dir, "n2", &cluster, &[&cluster]), &uri)
+                .await
+                .unwrap()
+        );
+        // 信任集群 CA，但自身证书由外部 CA 签发
+        let outsider = write_node(&dir, "n3", &foreign, &[&cluster]);
+        assert!(send(outsider, &uri).await.is_err());
+        // 主机名不匹配时，只有关闭主机名校验才能连上
+        let misnamed =
+            write_node(&dir, "n2", &cluster, &[&cluster]).with_server_name("n1.internal");
+        assert!(send(misnamed.clone(), &uri).await.is_err());
+        assert!(
+            send(misnamed.with_verify_hostname(false), &uri)
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(handler.0.load(Ordering::SeqCst), 2);
+        assert_eq!(server.stats().rejected_peers(), 0);
+    }
+
+    #[tokio::test]
+    async fn valid_certificate_outside_membership_is_rejected() {
+        let dir = dir("membership");
+        let cluster = ca("cluster");
+        let server =
+            Arc::new(TlsContext::new(write_node(&dir, "n1", &cluster, &[&cluster])).unwrap());
+        let handler = Counter::default();
+        let uri = serve(&server, handler.clone()).await;
+
+        let stranger = write_node(&dir, "m9", &cluster, &[&cluster]);
+        let err = send(stranger, &uri).await.unwrap_err();
+        assert!(err.to_string().contains("not a cluster member"), "{err}");
+        assert_eq!(server.stats().rejected_peers(), 1);
+        assert_eq!(handler.0.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn certificates_rotate_through_config_manager_without_restart() {
+        let dir = dir("rotate");
+        let (old_ca, new_ca) = (ca("old"), ca("new"));
+        let initial = write_node(&dir, "n1", &old_ca, &[&old_ca]);
+        let mut manager = ConfigManager::new();
+        manager.set_override(
+            TlsConfig::CERT_PATH_KEY,
+            initial.cert_path.to_str().unwrap(),
+        );
+        manager.set_override(TlsConfig::KEY_PATH_KEY, initial.key_path.to_str().unwrap());
+        manager.set_override(TlsConfig::CA_PATH_KEY, initial.ca_path.to_str().unwrap());
+        let server = Arc::new(
+            TlsContext::new(TlsConfig::from_snapshot(&manager.snapshot()).unwrap()).unwrap(),
+        );
+        server.watch(&mut manager);
+        let uri = serve(&server, Counter::default()).await;
+
+        let old_client = write_node(&dir, "n2", &old_ca, &[&old_ca]);
+        let new_client = write_node(&dir, "n3", &new_ca, &[&new_ca]);
+        assert!(send(old_client.clone(), &uri).await.unwrap());
+        assert!(send(new_client.clone(), &uri).await.is_err());
+
+        // 原地替换文件后改动任一配置键触发重载
+        write_node(&dir, "n1", &new_ca, &[&new_ca]);
+        manager.set_override("tls.generation", 2i64);
+        assert_eq!(server.stats().reloads(), 1);
+        assert!(send(new_client.clone(), &uri).await.unwrap());
+        assert!(send(old_client, &uri).await.is_err());
+
+        // 私钥缺失的配置被拒绝，服务继续使用轮换后的证书
+        manager.set_override(TlsConfig::KEY_PATH_KEY, "/nonexistent/n1.key");
+        assert_eq!(server.stats().reload_failures(), 1);
+        assert!(send(new_client, &uri).await.unwrap());
+    }
+}
@@ -0,0 +1,64 @@
+// 测试目的：MVCC 存储
+// - 不变量：
+//   1) 时间点读取返回不晚于 as_of 的最新版本；
+//   2) gc_before 回收旧版本后，as_of ≥ horizon 的读取结果不变。
+use distributed::storage::MvccStore;
+
+fn history() -> MvccStore<String, &'static str> {
+    let mut store = MvccStore::new();
+    store.write("k".to_string(), "v10", 10);
+    store.write("k".to_string(), "v30", 30);
+    // 乱序到达
+    store.write("k".to_string(), "v20", 20);
+    store.write("other".to_string(), "o5", 5);
+    store
+}
+
+#[test]
+fn point_in_time_reads_return_historical_values() {
+    let store = history();
+    let k = "k".to_string();
+    assert_eq!(store.read(&k, 5), None);
+    assert_eq!(store.read(&k, 10), Some(&"v10"));
+    assert_eq!(store.read(&k, 19), Some(&"v10"));
+    assert_eq!(store.read(&k, 20), Some(&"v20"));
+    assert_eq!(store.read(&k, 29), Some(&"v20"));
+    assert_eq!(store.read(&k, u64::MAX), Some(&"v30"));
+    assert_eq!(store.read(&"missing".to_string(), 100), None);
+
+    let versions: Vec<_> = store.versions(&k).collect();
+    assert_eq!(versions, vec![(10, &"v10"), (20, &"v20"), (30, &"v30")]);
+    assert_eq!(store.latest(&k), Some((30, &"v30")));
+}
+
+#[test]
+fn same_timestamp_write_overwrites() {
+    let mut store = history();
+    store.write("k".to_string(), "v20b", 20);
+    assert_eq!(store.read(&"k".to_string(), 25), Some(&"v20b"));
+    assert_eq!(store.versions(&"k".to_string()).count(), 3);
+}
+
+#[test]
+fn gc_removes_old_versions_without_affecting_later_reads() {
+    let mut store = history();
+    let k = "k".to_string();
+    let probes = [25u64, 30, 40];
+    let before: Vec<_> = probes.iter().map(|t| store.read(&k, *t).copied()).collect();
+
+    let removed = store.gc_before(25);
+    assert_eq!(removed, 1);
+    let versions: Vec<_> = store.versions(&k).map(|(ts, _)| ts).collect();
+    assert_eq!(versions, vec![20, 30]);
+
+    let after: Vec<_> = probes.iter().map(|t| store.read(&k, *t).copied()).collect();
+    assert_eq!(before, after);
+    // 只有一个版本的键保留该版本
+    assert_eq!(store.read(&"other".to_string(), 25), Some(&"o5"));
+    // 早于 horizon 的历史不再可读
+    assert_eq!(store.read(&k, 15), None);
+
+    assert_eq!(store.gc_before(100), 1);
+    assert_eq!(store.version_count(), 2);
+    assert_eq!(store.read(&k, 100), Some(&"v30"));
+}
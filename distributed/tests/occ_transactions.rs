@@ -0,0 +1,93 @@
+// 测试目的：乐观并发控制（OCC）
+// - 不变量：
+//   1) 读写同一键的两个并发事务至多一个提交成功，另一个返回 ConflictError；
+//   2) 读集不相交的事务均可提交；
+//   3) 中止事务的写入不可见。
+use distributed::transactions::{ConflictError, OccStore};
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+fn seeded(key: &str, value: i64) -> OccStore<i64> {
+    let store = OccStore::new();
+    let mut txn = store.transaction();
+    let snapshot = txn.begin();
+    txn.write(key, value);
+    txn.commit(snapshot).unwrap();
+    store
+}
+
+#[test]
+fn concurrent_conflicting_transactions_one_aborts() {
+    let store = seeded("balance", 100);
+    let barrier = Arc::new(Barrier::new(2));
+
+    let handles: Vec<_> = [30i64, 50]
+        .into_iter()
+        .map(|amount| {
+            let store = store.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let mut txn = store.transaction();
+                let snapshot = txn.begin();
+                let balance = txn.read("balance", &snapshot).unwrap();
+                txn.write("balance", balance - amount);
+                // 两个事务都完成读取后再提交
+                barrier.wait();
+                txn.commit(snapshot)
+            })
+        })
+        .collect();
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    let committed = results.iter().filter(|r| r.is_ok()).count();
+    assert_eq!(committed, 1, "{results:?}");
+    let conflict = results.iter().find_map(|r| r.as_ref().err()).unwrap();
+    assert_eq!(
+        conflict,
+        &ConflictError {
+            keys: vec!["balance".to_string()]
+        }
+    );
+    let balance = store.get("balance").unwrap();
+    assert!(balance == 70 || balance == 50, "{balance}");
+}
+
+#[test]
+fn disjoint_transactions_both_commit() {
+    let store = seeded("a", 1);
+    let mut t1 = store.transaction();
+    let mut t2 = store.transaction();
+    let s1 = t1.begin();
+    let s2 = t2.begin();
+
+    let a = t1.read("a", &s1).unwrap();
+    t1.write("a", a + 1);
+    assert_eq!(t2.read("b", &s2), None);
+    t2.write("b", 10);
+
+    let c1 = t1.commit(s1).unwrap();
+    let c2 = t2.commit(s2).unwrap();
+    assert!(c2.version > c1.version);
+    assert_eq!(store.get("a"), Some(2));
+    assert_eq!(store.get("b"), Some(10));
+}
+
+#[test]
+fn aborted_writes_are_not_visible() {
+    let store = seeded("x", 1);
+    let mut stale = store.transaction();
+    let snapshot = stale.begin();
+    stale.read("x", &snapshot);
+    stale.write("y", 99);
+    // 读到自己缓冲的写入
+    assert_eq!(stale.read("y", &snapshot), Some(99));
+
+    let mut other = store.transaction();
+    let s = other.begin();
+    other.write("x", 2);
+    other.commit(s).unwrap();
+
+    assert!(stale.commit(snapshot).is_err());
+    assert_eq!(store.get("y"), None);
+    assert_eq!(store.get("x"), Some(2));
+}
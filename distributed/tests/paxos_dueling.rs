@@ -0,0 +1,207 @@
+// 测试目的：Paxos 竞争提议者的退避
+// - 不变量：1) 两个提议者同时发起提案、消息延迟随机时，按拒绝指数退避后 5 轮以内有一方提交，
+//              另一方得知选定值后不再重试，双方对选定值一致；提交成功后失败计数清零；
+//           2) 退避随连续失败指数增长且不超过上限，抖动落在 [一半, 全部] 区间。
+use distributed::consensus::paxos::{
+    AcceptReq, DuelingPrevention, FlexiblePaxosConfig, Nack, PaxosAcceptor, PaxosProposer,
+    PrepareReq, Promise,
+};
+use distributed::core::ClusterNodeId;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::Duration;
+
+/// SplitMix64，消息延迟由种子决定
+struct Rng(u64);
+
+impl Rng {
+    fn delay(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        1 + (z ^ (z >> 31)) % 10
+    }
+}
+
+enum Event {
+    Start(usize),
+    Prepare(usize, usize, PrepareReq),
+    Accept(usize, usize, AcceptReq<u64>),
+    Promise(usize, Promise<u64>),
+    Accepted(usize, distributed::consensus::paxos::Accepted),
+    Nack(usize, Nack),
+    Learn(usize, u64),
+}
+
+/// 按 (时间, 入队顺序) 出队的事件队列
+#[derive(Default)]
+struct Queue {
+    heap: BinaryHeap<Reverse<(u64, u64)>>,
+    events: HashMap<u64, Event>,
+    seq: u64,
+}
+
+impl Queue {
+    fn push(&mut self, at: u64, event: Event) {
+        self.seq += 1;
+        self.heap.push(Reverse((at, self.seq)));
+        self.events.insert(self.seq, event);
+    }
+
+    fn pop(&mut self) -> Option<(u64, Event)> {
+        let Reverse((at, seq)) = self.heap.pop()?;
+        Some((at, self.events.remove(&seq).unwrap()))
+    }
+}
+
+fn node(i: u16) -> ClusterNodeId {
+    ClusterNodeId::from_components(format!("127.0.0.1:{}", 7000 + i).parse().unwrap(), 0)
+}
+
+struct Outcome {
+    chosen: [Option<u64>; 2],
+    attempts: [u32; 2],
+    nacks: u32,
+    /// 模拟结束后两个提议者都不再重试
+    stopped: bool,
+}
+
+/// 3 个接受者、2 个提议者的离散事件模拟；每条消息延迟 1..=10ms
+fn duel(seed: u64, dueling: DuelingPrevention) -> Outcome {
+    let ids: Vec<ClusterNodeId> = (0..3).map(node).collect();
+    let config = FlexiblePaxosConfig::majority(&ids);
+    let mut acceptors: Vec<PaxosAcceptor<u64>> = ids
+        .iter()
+        .map(|id| PaxosAcceptor::new(id.clone()))
+        .collect();
+    let mut proposers: Vec<PaxosProposer<u64>> = (0..2)
+        .map(|p| {
+            PaxosProposer::new(node(10 + p as u16), config.clone())
+                .unwrap()
+                .with_dueling_prevention(dueling)
+        })
+        .collect();
+    let mut rng = Rng(seed);
+    let mut queue = Queue::default();
+    queue.push(0, Event::Start(0));
+    queue.push(0, Event::Start(1));
+
+    let mut out = Outcome {
+        chosen: [None; 2],
+        attempts: [0; 2],
+        nacks: 0,
+        stopped: false,
+    };
+    while let Some((now, event)) = queue.pop() {
+        match event {
+            Event::Start(p) => {
+                let prepare = if out.attempts[p] == 0 {
+                    Some(proposers[p].prepare(100 + p as u64))
+                } else {
+                    proposers[p].retry()
+                };
+                let Some(prepare) = prepare else {
+                    continue;
+                };
+                out.attempts[p] += 1;
+                for a in 0..acceptors.len() {
+                    let at = now + rng.delay();
+                    queue.push(at, Event::Prepare(p, a, prepare.clone()));
+                }
+            }
+            Event::Prepare(p, a, req) => {
+                let reply = match acceptors[a].respond_prepare(&req) {
+                    Ok(promise) => Event::Promise(p, promise),
+                    Err(nack) => Event::Nack(p, nack),
+                };
+                queue.push(now + rng.delay(), reply);
+            }
+            Event::Accept(p, a, req) => {
+                let reply = match acceptors[a].respond_accept(&req) {
+                    Ok(accepted) => Event::Accepted(p, accepted),
+                    Err(nack) => Event::Nack(p, nack),
+                };
+                queue.push(now + rng.delay(), reply);
+            }
+            Event::Promise(p, promise) => {
+                if let Some(accept) = proposers[p].handle_promise(promise) {
+                    for a in 0..acceptors.len() {
+                        let at = now + rng.delay();
+                        queue.push(at, Event::Accept(p, a, accept.clone()));
+                    }
+                }
+            }
+            Event::Accepted(p, accepted) => {
+                if out.chosen[p].is_none()
+                    && let Some(value) = proposers[p].handle_accepted(accepted)
+                {
+                    out.chosen[p] = Some(value);
+                    assert_eq!(proposers[p].failed_attempts(), 0);
+                    queue.push(now + rng.delay(), Event::Learn(1 - p, value));
+                }
+            }
+            Event::Nack(p, nack) => {
+                if let Some(backoff) = proposers[p].handle_nack(nack) {
+                    out.nacks += 1;
+                    let at = now + backoff.as_millis() as u64;
+                    queue.push(at, Event::Start(p));
+                }
+            }
+            Event::Learn(p, value) => {
+                proposers[p].learn(value);
+                out.chosen[p].get_or_insert(value);
+            }
+        }
+        assert!(now < 60_000, "seed {seed}: no value chosen");
+    }
+    out.stopped = proposers.iter_mut().all(|p| p.retry().is_none());
+    out
+}
+
+#[test]
+fn one_of_two_dueling_proposers_commits_within_five_rounds() {
+    let dueling = DuelingPrevention {
+        base_backoff_ms: 20,
+        max_backoff_ms: 500,
+        jitter: true,
+    };
+    let mut duels = 0;
+    for seed in 0..200 {
+        let out = duel(seed, dueling);
+        let chosen = out.chosen[0].expect("value chosen");
+        assert_eq!(out.chosen[1], Some(chosen), "seed {seed}");
+        assert!(out.stopped, "seed {seed}");
+        assert!(
+            out.attempts.iter().all(|a| *a <= 5),
+            "seed {seed}: attempts {:?}",
+            out.attempts
+        );
+        duels += u32::from(out.nacks > 0);
+    }
+    // 大多数种子下两个提议者确实发生了冲突
+    assert!(duels > 100, "{duels}");
+}
+
+#[test]
+fn backoff_grows_exponentially_up_to_the_cap() {
+    let fixed = DuelingPrevention {
+        base_backoff_ms: 10,
+        max_backoff_ms: 100,
+        jitter: false,
+    };
+    let backoffs: Vec<u64> = (1..=6)
+        .map(|n| fixed.backoff(n, 0).as_millis() as u64)
+        .collect();
+    assert_eq!(backoffs, vec![10, 20, 40, 80, 100, 100]);
+
+    let jittered = DuelingPrevention {
+        jitter: true,
+        ..fixed
+    };
+    for seed in 0..100 {
+        let ms = jittered.backoff(3, seed).as_millis() as u64;
+        assert!((20..=40).contains(&ms), "{ms}");
+    }
+    assert_eq!(jittered.backoff(40, 7), Duration::from_millis(57));
+}
@@ -0,0 +1,186 @@
+// 测试目的：Flexible Paxos 法定人数交叠
+// - 不变量：
+//   1) 任意 Phase 1 法定人数与任意 Phase 2 法定人数相交，配置才合法；
+//   2) 提议者仅在凑齐 Phase 1 / Phase 2 法定人数后推进阶段；
+//   3) 阈值配置按 q1 + q2 > n 校验、按应答者个数计数，大规模集群不枚举组合；显式列出的
+//      法定人数仍逐对校验交叠；
+//   4) 阈值配置只统计成员中的应答者，非成员凑不成法定人数。
+use distributed::consensus::paxos::{FlexiblePaxosConfig, PaxosAcceptor, PaxosProposer};
+use distributed::core::ClusterNodeId;
+use std::collections::HashSet;
+
+fn nodes(n: usize) -> Vec<ClusterNodeId> {
+    (0..n).map(|_| ClusterNodeId::generate()).collect()
+}
+
+#[test]
+fn q1_4_q2_2_on_5_nodes_is_valid() {
+    let cfg = FlexiblePaxosConfig::threshold(&nodes(5), 4, 2);
+    assert!(cfg.check_valid().is_ok());
+}
+
+#[test]
+fn q1_2_q2_2_on_5_nodes_is_rejected() {
+    let ns = nodes(5);
+    let cfg = FlexiblePaxosConfig::threshold(&ns, 2, 2);
+    assert!(cfg.check_valid().is_err());
+    assert!(PaxosProposer::<u64>::new(ns[0].clone(), cfg).is_err());
+}
+
+#[test]
+fn proposer_uses_asymmetric_quorums() {
+    let ns = nodes(5);
+    let cfg = FlexiblePaxosConfig::threshold(&ns, 4, 2);
+    let mut proposer = PaxosProposer::new(ns[0].clone(), cfg).unwrap();
+    let mut acceptors: Vec<PaxosAcceptor<u64>> =
+        ns.iter().map(|n| PaxosAcceptor::new(n.clone())).collect();
+
+    let prepare = proposer.prepare(42);
+    let mut accept = None;
+    for (i, a) in acceptors.iter_mut().enumerate() {
+        let promise = a.handle_prepare(&prepare).unwrap();
+        accept = proposer.handle_promise(promise);
+        // 前三个承诺不足 Q1=4
+        if i < 3 {
+            assert!(accept.is_none());
+        } else {
+            break;
+        }
+    }
+    let accept = accept.expect("phase1 quorum of 4 reached");
+    assert_eq!(accept.value, 42);
+
+    // Q2=2：两个接受者应答即可选定
+    let a0 = acceptors[0].handle_accept(&accept).unwrap();
+    assert_eq!(proposer.handle_accepted(a0), None);
+    let a1 = acceptors[1].handle_accept(&accept).unwrap();
+    assert_eq!(proposer.handle_accepted(a1), Some(42));
+}
+
+#[test]
+fn later_proposer_inherits_chosen_value() {
+    let ns = nodes(5);
+    let cfg = FlexiblePaxosConfig::threshold(&ns, 4, 2);
+    let mut acceptors: Vec<PaxosAcceptor<u64>> =
+        ns.iter().map(|n| PaxosAcceptor::new(n.clone())).collect();
+
+    let mut p1 = PaxosProposer::new(ns[0].clone(), cfg.clone()).unwrap();
+    let prep = p1.prepare(1);
+    let mut accept = None;
+    for a in acceptors.iter_mut().take(4) {
+        accept = p1.handle_promise(a.handle_prepare(&prep).unwrap());
+    }
+    let accept = accept.unwrap();
+    // 只在 n3、n4 上被接受（Q2=2）
+    for a in acceptors.iter_mut().skip(3) {
+        let ack = a.handle_accept(&accept).unwrap();
+        p1.handle_accepted(ack);
+    }
+    assert_eq!(p1.chosen(), Some(&1));
+
+    // 第二个提议者以更高编号提出 2，但任意 Q1 都与 {n3,n4} 相交，必须沿袭 1
+    let mut p2 = PaxosProposer::new(ns[1].clone(), cfg).unwrap();
+    p2.prepare(2);
+    let prep2 = p2.prepare(2);
+    let mut accept2 = None;
+    for a in acceptors.iter_mut().skip(1) {
+        accept2 = p2.handle_promise(a.handle_prepare(&prep2).unwrap());
+    }
+    assert_eq!(accept2.unwrap().value, 1);
+}
+
+#[test]
+fn threshold_quorums_scale_without_enumeration() {
+    let ns = nodes(1_001);
+    let cfg = FlexiblePaxosConfig::threshold(&ns, 900, 102);
+    assert_eq!(
+        cfg,
+        FlexiblePaxosConfig::Threshold {
+            members: ns.iter().cloned().collect(),
+            q1: 900,
+            q2: 102
+        }
+    );
+    assert!(cfg.check_valid().is_ok());
+    assert!(
+        FlexiblePaxosConfig::threshold(&ns, 900, 101)
+            .check_valid()
+            .is_err()
+    );
+    assert!(
+        FlexiblePaxosConfig::threshold(&ns, 1_002, 1)
+            .check_valid()
+            .is_err()
+    );
+    assert!(
+        FlexiblePaxosConfig::threshold(&ns, 0, 1_001)
+            .check_valid()
+            .is_err()
+    );
+
+    let mut proposer = PaxosProposer::new(ns[0].clone(), cfg).unwrap();
+    let mut acceptors: Vec<PaxosAcceptor<u64>> =
+        ns.iter().map(|n| PaxosAcceptor::new(n.clone())).collect();
+    let prepare = proposer.prepare(7);
+    let mut accept = None;
+    for (i, a) in acceptors.iter_mut().enumerate().take(900) {
+        assert!(accept.is_none(), "phase1 reached after {i} promises");
+        accept = proposer.handle_promise(a.handle_prepare(&prepare).unwrap());
+    }
+    let accept = accept.expect("phase1 quorum of 900 reached");
+    let mut chosen = None;
+    for a in acceptors.iter_mut().rev().take(102) {
+        assert_eq!(chosen, None);
+        chosen = proposer.handle_accepted(a.handle_accept(&accept).unwrap());
+    }
+    assert_eq!(chosen, Some(7));
+}
+
+#[test]
+fn explicit_quorum_lists_are_checked_pairwise() {
+    let ns = nodes(3);
+    let (a, b, c) = (ns[0].clone(), ns[1].clone(), ns[2].clone());
+    let cfg = FlexiblePaxosConfig::new(
+        vec![vec![a.clone(), b.clone()], vec![b.clone(), c.clone()]],
+        vec![vec![b.clone()]],
+    );
+    assert!(cfg.check_valid().is_ok());
+    assert!(cfg.is_phase1_quorum(&[b.clone(), c.clone()].into()));
+    assert!(!cfg.is_phase1_quorum(&[a.clone(), c.clone()].into()));
+    assert!(!cfg.is_phase2_quorum(&[a.clone(), c.clone()].into()));
+
+    let disjoint = FlexiblePaxosConfig::new(vec![vec![a]], vec![vec![b, c]]);
+    assert!(disjoint.check_valid().is_err());
+    assert!(
+        FlexiblePaxosConfig::new(vec![], vec![])
+            .check_valid()
+            .is_err()
+    );
+}
+
+#[test]
+fn non_member_responders_do_not_form_a_quorum() {
+    let ns = nodes(3);
+    let cfg = FlexiblePaxosConfig::majority(&ns);
+    let outsiders: HashSet<ClusterNodeId> = nodes(5).into_iter().collect();
+    assert!(!cfg.is_phase1_quorum(&outsiders));
+    assert!(!cfg.is_phase2_quorum(&outsiders));
+
+    // 一个成员加上任意多个非成员仍不足多数派
+    let mut responders = outsiders.clone();
+    responders.insert(ns[0].clone());
+    assert!(!cfg.is_phase1_quorum(&responders));
+    responders.insert(ns[1].clone());
+    assert!(cfg.is_phase1_quorum(&responders));
+    assert!(cfg.is_phase2_quorum(&responders));
+
+    // 提议者不会因非成员的承诺进入 Phase 2
+    let mut proposer = PaxosProposer::new(ns[0].clone(), cfg).unwrap();
+    let prepare = proposer.prepare(9);
+    for outsider in &outsiders {
+        let promise = PaxosAcceptor::<u64>::new(outsider.clone())
+            .handle_prepare(&prepare)
+            .unwrap();
+        assert!(proposer.handle_promise(promise).is_none());
+    }
+}
@@ -0,0 +1,102 @@
+// 测试目的：PBFT 检查点与垃圾回收
+// - 不变量：
+//   1) 收集到 2f+1 个摘要一致的检查点后形成稳定检查点；
+//   2) 稳定检查点之前（含）的消息被丢弃，消息日志有界；
+//   3) 截断到 u64::MAX 时清空日志而不溢出。
+use distributed::{ByzantineMessage, CheckpointMsg, MessageLog, PBFTNode};
+use std::time::SystemTime;
+
+fn commit(seq: u64, sender: &str) -> ByzantineMessage {
+    ByzantineMessage::Commit {
+        view: 0,
+        sequence: seq,
+        digest: format!("d{seq}"),
+        sender: sender.to_string(),
+        timestamp: SystemTime::now(),
+    }
+}
+
+#[test]
+fn message_log_is_bounded_by_checkpoints() {
+    let n = 4;
+    let mut nodes: Vec<PBFTNode> = (0..n)
+        .map(|i| PBFTNode::new(format!("node_{i}"), n).with_checkpoint_interval(10))
+        .collect();
+    // 检查点消息延迟若干轮才送达，模拟网络延迟
+    let mut in_flight: Vec<(u64, CheckpointMsg)> = Vec::new();
+
+    for seq in 1..=100u64 {
+        for node in nodes.iter_mut() {
+            for i in 0..n {
+                node.log_message(seq, commit(seq, &format!("node_{i}")));
+            }
+        }
+        for node in nodes.iter_mut() {
+            if let Some(cp) = node.maybe_checkpoint(seq, &format!("state@{seq}")) {
+                in_flight.push((seq + 5, cp));
+            }
+        }
+        let (due, rest): (Vec<_>, Vec<_>) = in_flight.into_iter().partition(|(at, _)| *at <= seq);
+        in_flight = rest;
+        for (_, cp) in due {
+            for node in nodes.iter_mut().filter(|n| n.node_id != cp.node_id) {
+                node.handle_checkpoint(cp.clone());
+            }
+        }
+
+        for node in &nodes {
+            assert!(
+                node.message_log.len() <= 20,
+                "{} log grew to {} at seq {seq}",
+                node.node_id,
+                node.message_log.len()
+            );
+        }
+    }
+
+    for node in &nodes {
+        assert_eq!(node.stable_checkpoint().unwrap().seq, 90);
+    }
+}
+
+#[test]
+fn mismatched_digests_do_not_form_stable_checkpoint() {
+    let mut node = PBFTNode::new("node_0".to_string(), 4).with_checkpoint_interval(10);
+    for seq in 1..=10 {
+        node.log_message(seq, commit(seq, "node_1"));
+    }
+    node.maybe_checkpoint(10, "good");
+    node.handle_checkpoint(CheckpointMsg {
+        seq: 10,
+        digest: "bad".into(),
+        node_id: "node_1".into(),
+    });
+    node.handle_checkpoint(CheckpointMsg {
+        seq: 10,
+        digest: "good".into(),
+        node_id: "node_2".into(),
+    });
+    assert!(node.stable_checkpoint().is_none());
+    assert_eq!(node.message_log.len(), 10);
+
+    let stable = node
+        .handle_checkpoint(CheckpointMsg {
+            seq: 10,
+            digest: "good".into(),
+            node_id: "node_3".into(),
+        })
+        .unwrap();
+    assert_eq!(stable.proof.len(), 3);
+    assert!(node.message_log.is_empty());
+}
+
+#[test]
+fn truncating_through_max_sequence_clears_the_log() {
+    let mut log = MessageLog::new();
+    log.append(1, commit(1, "node_1"));
+    log.append(u64::MAX, commit(u64::MAX, "node_1"));
+
+    assert!(log.entries_after(u64::MAX).is_empty());
+    assert_eq!(log.truncate_through(u64::MAX), 2);
+    assert!(log.is_empty());
+}
@@ -0,0 +1,185 @@
+// 测试目的：PBFT 崩溃副本的状态传输
+// - 不变量：
+//   1) 请求经 pre-prepare/prepare/commit 三阶段形成 2f+1 提交证书后才执行；
+//   2) 重启副本从领导者取得最新稳定检查点（含 2f+1 证明）与其后的缺失请求；
+//   3) 追赶只重放带提交证书的请求，完成后其状态与其余副本一致，并能继续参与后续轮次；
+//   4) 证明来自非配置副本的检查点被拒绝。
+use distributed::{
+    BftNetwork, ByzantineMessage, ByzantineNetwork, CheckpointMsg, PBFTNode, StableCheckpoint,
+    StateTransferReq, StateTransferResp,
+};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, SystemTime};
+
+fn request(seq: u64) -> ByzantineMessage {
+    ByzantineMessage::Request {
+        id: format!("req-{seq}"),
+        content: format!("op-{seq}").into_bytes(),
+        timestamp: SystemTime::now(),
+        sender: "client".to_string(),
+    }
+}
+
+/// 把消息投递给 `live` 中的全部副本（含发送者自身），直到不再产生新消息。
+/// 低于稳定检查点的迟到消息会被副本拒绝，这里直接丢弃。
+fn deliver(net: &mut ByzantineNetwork, live: &[String], initial: Vec<ByzantineMessage>) {
+    let mut queue: VecDeque<ByzantineMessage> = initial.into();
+    while let Some(message) = queue.pop_front() {
+        for id in live {
+            let node = net.nodes.get_mut(id).unwrap();
+            let out = match &message {
+                ByzantineMessage::Request { .. } => node.receive_request(message.clone()),
+                ByzantineMessage::Prepare { .. } => node.handle_prepare(message.clone()),
+                ByzantineMessage::PreCommit { .. } => node.handle_pre_commit(message.clone()),
+                ByzantineMessage::Commit { .. } => node.handle_commit(message.clone()),
+                ByzantineMessage::Checkpoint(cp) => {
+                    node.handle_checkpoint(cp.clone());
+                    Ok(vec![])
+                }
+                _ => Ok(vec![]),
+            };
+            queue.extend(out.unwrap_or_default());
+        }
+    }
+}
+
+/// 客户端向 `live` 副本多播请求，主节点 node_0 分配序列号并发出 pre-prepare，
+/// 随后驱动 prepare/commit 两轮投票与检查点广播
+fn run_round(net: &mut ByzantineNetwork, live: &[String], seq: u64) {
+    deliver(net, live, vec![request(seq)]);
+    let pre_prepare = net
+        .nodes
+        .get_mut("node_0")
+        .unwrap()
+        .handle_request(request(seq))
+        .unwrap();
+    deliver(net, live, pre_prepare);
+    for id in live {
+        assert_eq!(
+            net.nodes[id].last_executed, seq,
+            "{id} did not execute {seq}"
+        );
+    }
+}
+
+/// 按需改写领导者状态传输响应的传输层
+struct Rewriting<F>(ByzantineNetwork, F);
+
+impl<F> BftNetwork for Rewriting<F>
+where
+    F: Fn(&mut StateTransferResp) + Send + Sync,
+{
+    fn request_state_transfer<'a>(
+        &'a self,
+        target: &'a str,
+        req: StateTransferReq,
+    ) -> Pin<Box<dyn Future<Output = Result<StateTransferResp, String>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut resp = self.0.nodes[target].handle_state_transfer_request(&req)?;
+            (self.1)(&mut resp);
+            Ok(resp)
+        })
+    }
+}
+
+fn cluster() -> ByzantineNetwork {
+    let mut net = ByzantineNetwork::new(4, Duration::from_millis(0), 0.0);
+    for node in net.nodes.values_mut() {
+        node.checkpoint_interval = 4;
+    }
+    net
+}
+
+#[tokio::test]
+async fn crashed_replica_catches_up_and_participates() {
+    let mut net = cluster();
+    let all: Vec<String> = (0..4).map(|i| format!("node_{i}")).collect();
+
+    for seq in 1..=5 {
+        run_round(&mut net, &all, seq);
+    }
+
+    // node_3 在第 5 轮后崩溃
+    let mut crashed: PBFTNode = net.nodes.remove("node_3").unwrap();
+    assert_eq!(crashed.last_executed, 5);
+    assert_eq!(crashed.stable_checkpoint().unwrap().seq, 4);
+    let live: Vec<String> = all[..3].to_vec();
+    for seq in 6..=9 {
+        run_round(&mut net, &live, seq);
+    }
+    assert_eq!(net.nodes["node_0"].stable_checkpoint().unwrap().seq, 8);
+
+    // 错过的轮次无法直接执行
+    assert!(crashed.execute(10, request(10)).is_err());
+
+    crashed.catch_up("node_0", &net).await.unwrap();
+    assert_eq!(crashed.last_executed, 9);
+    assert_eq!(crashed.stable_checkpoint().unwrap().seq, 8);
+    assert_eq!(crashed.state_digest(), net.nodes["node_0"].state_digest());
+
+    net.nodes.insert("node_3".to_string(), crashed);
+    run_round(&mut net, &all, 10);
+    let digest = net.nodes["node_0"].state_digest();
+    for id in &all {
+        assert_eq!(net.nodes[id].last_executed, 10);
+        assert_eq!(net.nodes[id].state_digest(), digest);
+    }
+}
+
+/// node_3 缺席第 1..=9 轮，返回存活副本组成的网络与落后的 node_3
+fn lagging_replica() -> (ByzantineNetwork, PBFTNode) {
+    let mut net = cluster();
+    let live: Vec<String> = (0..3).map(|i| format!("node_{i}")).collect();
+    let lagging = net.nodes.remove("node_3").unwrap();
+    for seq in 1..=9 {
+        run_round(&mut net, &live, seq);
+    }
+    (net, lagging)
+}
+
+#[tokio::test]
+async fn tampered_snapshot_is_rejected() {
+    let (net, mut lagging) = lagging_replica();
+    let tampering = Rewriting(net, |resp: &mut StateTransferResp| {
+        resp.snapshot = b"{\"last_executed\":8,\"committed\":{}}".to_vec();
+    });
+    let err = lagging.catch_up("node_0", &tampering).await.unwrap_err();
+    assert!(err.contains("摘要不匹配"), "{err}");
+    assert_eq!(lagging.last_executed, 0);
+}
+
+#[tokio::test]
+async fn forged_checkpoint_proof_is_rejected() {
+    let (net, mut lagging) = lagging_replica();
+    // 摘要与快照都真实，但 2f+1 证明中只有一条来自配置内的副本
+    let forging = Rewriting(net, |resp: &mut StateTransferResp| {
+        let StableCheckpoint { seq, digest, proof } = resp.checkpoint.as_mut().unwrap();
+        proof.truncate(1);
+        for node_id in ["node_4", "mallory"] {
+            proof.push(CheckpointMsg {
+                seq: *seq,
+                digest: digest.clone(),
+                node_id: node_id.to_string(),
+            });
+        }
+    });
+    let err = lagging.catch_up("node_0", &forging).await.unwrap_err();
+    assert!(err.contains("证明不足"), "{err}");
+    assert_eq!(lagging.last_executed, 0);
+    assert!(lagging.stable_checkpoint().is_none());
+}
+
+#[tokio::test]
+async fn requests_without_commit_certificate_are_not_replayed() {
+    let (net, mut lagging) = lagging_replica();
+    // 去掉检查点之后的 Commit：第 9 轮的请求失去提交证书
+    let stripping = Rewriting(net, |resp: &mut StateTransferResp| {
+        resp.missing_msgs
+            .retain(|(_, m)| !matches!(m, ByzantineMessage::Commit { .. }));
+    });
+    lagging.catch_up("node_0", &stripping).await.unwrap();
+    assert_eq!(lagging.stable_checkpoint().unwrap().seq, 8);
+    assert_eq!(lagging.last_executed, 8);
+}
@@ -0,0 +1,229 @@
+// 测试目的：节点握手的能力协商
+// - 不变量：1) 未握手的对端不支持任何可选特性；协商结果为双方特性的交集，schema 版本取较小者；
+//              任一方缺少对方必需的特性时以 Configuration 报告缺失的特性名；
+//           2) 经 gRPC 传输：不支持压缩的对端收到未压缩的负载，支持的对端收到压缩负载；
+//              缺少必需能力的对端在建连握手时被拒绝。
+use distributed::DistributedError;
+use distributed::network::handshake::{Feature, FeatureSet, Handshake, PeerCapabilities};
+
+#[test]
+fn negotiation_defaults_to_lowest_common_denominator() {
+    let caps = PeerCapabilities::new(
+        Handshake::new("a")
+            .with_features(FeatureSet::all())
+            .with_required(FeatureSet::empty().with(Feature::SnapshotChunking))
+            .with_max_codec_version(3),
+    );
+    assert!(!caps.supports("b", Feature::HedgedReads));
+    assert_eq!(caps.codec_version("b"), None);
+
+    let b = Handshake::new("b")
+        .with_features(
+            [Feature::SnapshotChunking, Feature::HedgedReads]
+                .into_iter()
+                .collect(),
+        )
+        .with_max_codec_version(2);
+    let negotiated = caps.accept(b).unwrap();
+    assert_eq!(
+        negotiated.features.iter().collect::<Vec<_>>(),
+        vec![Feature::SnapshotChunking, Feature::HedgedReads]
+    );
+    assert!(caps.supports("b", Feature::HedgedReads));
+    assert!(!caps.supports("b", Feature::CompressionLz4));
+    assert_eq!(caps.codec_version("b"), Some(2));
+
+    // 对端缺少本节点必需的特性
+    let err = caps.accept(Handshake::new("c")).unwrap_err();
+    assert!(
+        matches!(err, DistributedError::Configuration(ref m) if m.contains("peer c") && m.contains("snapshot-chunking")),
+        "{err}"
+    );
+    assert!(!caps.is_known("c"));
+
+    // 本节点缺少对端必需的特性
+    let plain = PeerCapabilities::new(Handshake::new("d"));
+    let err = plain
+        .accept(
+            Handshake::new("e").with_required(FeatureSet::empty().with(Feature::CompressionZstd)),
+        )
+        .unwrap_err();
+    assert!(
+        matches!(err, DistributedError::Configuration(ref m) if m.contains("compression-zstd")),
+        "{err}"
+    );
+}
+
+#[cfg(feature = "transport-grpc")]
+mod grpc_handshake {
+    use distributed::DistributedError;
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::network::Deadline;
+    use distributed::network::grpc_replication::{
+        GrpcReplicationTransport, NodeHandler, ReplicationServer,
+    };
+    use distributed::network::handshake::{
+        Feature, FeatureSet, Handshake, PayloadCompressor, PeerCapabilities,
+    };
+    use distributed::replication::{
+        Priority, RemoteReplicator, ReplicateRequest, ReplicationTransport,
+    };
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    /// 游程编码，足以区分压缩与未压缩的负载
+    #[derive(Default)]
+    struct Rle {
+        compressed: AtomicU64,
+    }
+
+    impl PayloadCompressor for Rle {
+        fn feature(&self) -> Feature {
+            Feature::CompressionLz4
+        }
+
+        fn compress(&self, data: &[u8]) -> Vec<u8> {
+            self.compressed.fetch_add(1, Ordering::SeqCst);
+            let mut out = Vec::new();
+            for chunk in data.chunk_by(|a, b| a == b) {
+                for run in chunk.chunks(255) {
+                    out.extend([run.len() as u8, run[0]]);
+                }
+            }
+            out
+        }
+
+        fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, DistributedError> {
+            if !data.len().is_multiple_of(2) {
+                return Err(DistributedError::Storage("truncated run".into()));
+            }
+            Ok(data
+                .chunks(2)
+                .flat_map(|run| std::iter::repeat_n(run[1], run[0] as usize))
+                .collect())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl NodeHandler for Recorder {
+        async fn apply(&self, request: ReplicateRequest) -> Result<(), DistributedError> {
+            self.0.lock().unwrap().push(request.payload);
+            Ok(())
+        }
+    }
+
+    async fn serve(server: ReplicationServer<Recorder>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(server)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        format!("http://{addr}")
+    }
+
+    fn lz4() -> FeatureSet {
+        FeatureSet::empty().with(Feature::CompressionLz4)
+    }
+
+    #[tokio::test]
+    async fn peers_without_compression_get_plain_payloads() {
+        let (old, new) = (Recorder::default(), Recorder::default());
+        let old_caps = Arc::new(PeerCapabilities::new(Handshake::new("old")));
+        let new_caps = Arc::new(PeerCapabilities::new(
+            Handshake::new("new").with_features(lz4()),
+        ));
+        let old_uri =
+            serve(ReplicationServer::new("old", old.clone()).with_capabilities(old_caps)).await;
+        let new_uri = serve(
+            ReplicationServer::new("new", new.clone())
+                .with_capabilities(new_caps.clone())
+                .with_compressor(Arc::new(Rle::default())),
+        )
+        .await;
+
+        let caps = Arc::new(PeerCapabilities::new(
+            Handshake::new("client").with_features(lz4()),
+        ));
+        let compressor = Arc::new(Rle::default());
+        let transport = GrpcReplicationTransport::new([("old", old_uri), ("new", new_uri)])
+            .with_capabilities(caps.clone())
+            .with_compressor(compressor.clone());
+        let replicator =
+            RemoteReplicator::new(transport, vec!["old".to_string(), "new".to_string()]);
+        let payload = vec![7u8; 1_000];
+        let acks = replicator
+            .replicate("k", payload.clone(), ConsistencyLevel::Quorum)
+            .await
+            .unwrap();
+        assert!(acks.iter().all(|a| a.applied), "{acks:?}");
+
+        assert!(!caps.supports("old", Feature::CompressionLz4));
+        assert!(caps.supports("new", Feature::CompressionLz4));
+        assert!(new_caps.is_known("client"));
+        assert_eq!(compressor.compressed.load(Ordering::SeqCst), 1);
+        assert_eq!(*old.0.lock().unwrap(), vec![payload.clone()]);
+        assert_eq!(*new.0.lock().unwrap(), vec![payload]);
+    }
+
+    #[tokio::test]
+    async fn peer_missing_required_capability_is_rejected() {
+        let handler = Recorder::default();
+        let uri = serve(
+            ReplicationServer::new("old", handler.clone())
+                .with_capabilities(Arc::new(PeerCapabilities::new(Handshake::new("old")))),
+        )
+        .await;
+        let request = ReplicateRequest {
+            idempotency_key: "k".into(),
+            payload: b"v".to_vec(),
+            level: ConsistencyLevel::Quorum,
+            priority: Priority::Normal,
+        };
+        let send = |required: FeatureSet| {
+            let transport = GrpcReplicationTransport::new([("old", uri.clone())])
+                .with_capabilities(Arc::new(PeerCapabilities::new(
+                    Handshake::new("client").with_required(required),
+                )));
+            let request = request.clone();
+            async move {
+                transport
+                    .send("old", request, Deadline::after(Duration::from_secs(5)))
+                    .await
+            }
+        };
+
+        let err = send(FeatureSet::empty().with(Feature::SnapshotChunking))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, DistributedError::Configuration(ref m) if m.contains("old lacks required capability snapshot-chunking")),
+            "{err}"
+        );
+        assert!(send(FeatureSet::empty()).await.unwrap().applied);
+        assert_eq!(handler.0.lock().unwrap().len(), 1);
+
+        // 服务端必需的特性客户端没有：握手被服务端拒绝
+        let strict = serve(
+            ReplicationServer::new("strict", Recorder::default()).with_capabilities(Arc::new(
+                PeerCapabilities::new(Handshake::new("strict").with_required(lz4())),
+            )),
+        )
+        .await;
+        let transport = GrpcReplicationTransport::new([("strict", strict)])
+            .with_capabilities(Arc::new(PeerCapabilities::new(Handshake::new("client"))));
+        let err = transport
+            .send("strict", request, Deadline::after(Duration::from_secs(5)))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, DistributedError::Configuration(ref m) if m.contains("compression-lz4")),
+            "{err}"
+        );
+    }
+}
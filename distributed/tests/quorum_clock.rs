@@ -0,0 +1,72 @@
+// 测试目的：多数派协商墙钟时间
+// - 不变量：1) 三个节点读数 [100, 102, 110] 时中位数为 102、不确定度为 10，不确定度超过上限时拒绝；
+//           2) 少数节点失败仍能凑齐多数派，多数节点失败时返回错误。
+#[cfg(feature = "runtime-tokio")]
+mod quorum_clock {
+    use distributed::core::{QuorumClock, QuorumTime, TimeFuture, TimeTransport};
+    use distributed::swim::TransportError;
+    use std::collections::HashMap;
+
+    /// 节点读数固定的时间传输；`None` 表示节点不可达。应答先让出一次，模拟异步返回
+    struct MockTime(HashMap<String, Option<u64>>);
+
+    impl MockTime {
+        fn new(times: &[(&str, Option<u64>)]) -> Self {
+            Self(times.iter().map(|(n, t)| (n.to_string(), *t)).collect())
+        }
+    }
+
+    impl TimeTransport for MockTime {
+        fn get_time<'a>(&'a self, node: &'a str) -> TimeFuture<'a> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                self.0
+                    .get(node)
+                    .copied()
+                    .flatten()
+                    .ok_or_else(|| TransportError::Unreachable(node.to_string()))
+            })
+        }
+    }
+
+    fn nodes() -> Vec<String> {
+        ["n1", "n2", "n3"].map(String::from).to_vec()
+    }
+
+    #[tokio::test]
+    async fn median_and_uncertainty_of_three_nodes() {
+        let transport = MockTime::new(&[("n1", Some(100)), ("n2", Some(110)), ("n3", Some(102))]);
+        let time = QuorumClock::new(50)
+            .query_time(&nodes(), &transport)
+            .await
+            .unwrap();
+        assert_eq!(
+            time,
+            QuorumTime {
+                median_ms: 102,
+                uncertainty_ms: 10,
+                responses: 3
+            }
+        );
+        assert!(
+            QuorumClock::new(5)
+                .query_time(&nodes(), &transport)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn minority_failures_are_tolerated() {
+        let clock = QuorumClock::new(50);
+        let one_down = MockTime::new(&[("n1", Some(100)), ("n2", None), ("n3", Some(104))]);
+        let time = clock.query_time(&nodes(), &one_down).await.unwrap();
+        assert_eq!((time.median_ms, time.uncertainty_ms), (100, 4));
+        assert_eq!(time.responses, 2);
+
+        let two_down = MockTime::new(&[("n1", Some(100)), ("n2", None), ("n3", None)]);
+        let err = clock.query_time(&nodes(), &two_down).await.unwrap_err();
+        assert!(err.to_string().contains("quorum"), "{err}");
+        assert!(clock.query_time(&[], &one_down).await.is_err());
+    }
+}
@@ -0,0 +1,221 @@
+// 测试目的：失去多数派连通性时隔离写入
+// - 不变量：1) 仿真网络中节点 a 与 b、c 分区后，故障检测器判定失联并经过宽限期，a 上的
+//              Strong/Quorum 写入立即以 QuorumUnavailable { reachable: 1, required: 2 } 失败，
+//              宽限期内则仍按普通复制失败；开启暂存时 Eventual 写入继续放行并进入待补齐
+//              队列；分区恢复后写入自动恢复；
+//           2) 未开启暂存时隔离同样拒绝 Eventual 写入；未登记的分片报配置错误；
+//           3) 接入防护后 Replicator::replicate 同样经防护准入，不构成登记分片的副本集合报配置
+//              错误；防护按副本器的写仲裁（如角色感知仲裁）计算所需副本数。
+use distributed::ReplicaRole;
+use distributed::consistency::ConsistencyLevel;
+use distributed::core::errors::DistributedError;
+use distributed::replication::{LocalReplicator, Replicator, RoleAwareQuorum};
+use distributed::simnet::SimNetwork;
+use distributed::storage::QuorumGuard;
+use distributed::swim::PhiAccrualFailureDetector;
+use distributed::topology::ConsistentHashRing;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+fn nodes() -> Vec<String> {
+    ["a", "b", "c"].map(String::from).to_vec()
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Ok,
+    Replication,
+    Fenced,
+}
+
+#[test]
+fn partitioned_node_fails_fast_after_grace_and_recovers_on_heal() {
+    let net = SimNetwork::new(3);
+    let base = Instant::now();
+    let detectors: Arc<Mutex<HashMap<String, PhiAccrualFailureDetector>>> = Arc::default();
+    {
+        let detectors = detectors.clone();
+        net.register("a", move |net, envelope| {
+            detectors
+                .lock()
+                .unwrap()
+                .entry(envelope.from.clone())
+                .or_insert_with(|| {
+                    PhiAccrualFailureDetector::default().with_first_heartbeat_estimate(ms(100))
+                })
+                .heartbeat(base + net.now());
+            None
+        });
+    }
+    net.register("b", |_, _| None);
+    net.register("c", |_, _| None);
+    net.partition([vec!["a"], vec!["b", "c"]], ms(2_000), ms(5_000));
+
+    let grace = ms(500);
+    let guard = Arc::new(QuorumGuard::new("a", grace).with_hinted_handoff(true));
+    guard.set_shard("s1", nodes(), base);
+    let mut replicator = LocalReplicator::<u64>::new(ConsistentHashRing::new(16), nodes())
+        .with_quorum_guard(guard.clone());
+
+    let mut timeline = Vec::new();
+    let mut lost_at = None;
+    let mut hinted = 0;
+    for _ in 0..70 {
+        for peer in ["b", "c"] {
+            net.send(peer, "a", b"heartbeat".to_vec());
+        }
+        net.run_for(ms(100));
+        let now = base + net.now();
+        for peer in ["b", "c"] {
+            let available = detectors.lock().unwrap()[peer].is_available(now, 8.0);
+            guard.observe(peer, available, now);
+            if available {
+                replicator.mark_node_recovered(peer);
+            } else {
+                replicator.mark_node_failed(peer);
+            }
+        }
+        if guard.reachability("s1") == Some((1, 2)) && lost_at.is_none() {
+            lost_at = Some(net.now());
+        }
+        let strong = replicator.replicate_shard("s1", "put", ConsistencyLevel::Strong, now);
+        let outcome = match strong {
+            Ok(()) => Outcome::Ok,
+            Err(DistributedError::QuorumUnavailable {
+                reachable,
+                required,
+            }) => {
+                assert_eq!((reachable, required), (1, 2));
+                let level = ConsistencyLevel::Quorum;
+                let quorum = replicator.replicate_shard("s1", "put", level, now);
+                assert!(matches!(
+                    quorum,
+                    Err(DistributedError::QuorumUnavailable { .. })
+                ));
+                replicator
+                    .replicate_shard("s1", "put", ConsistencyLevel::Eventual, now)
+                    .unwrap();
+                hinted += 1;
+                Outcome::Fenced
+            }
+            Err(DistributedError::Network(_)) => Outcome::Replication,
+            Err(e) => panic!("unexpected {e}"),
+        };
+        timeline.push((net.now(), outcome));
+    }
+
+    let lost_at = lost_at.expect("detector noticed the partition");
+    assert!(lost_at > ms(2_000) && lost_at < ms(3_000), "{lost_at:?}");
+    for (at, outcome) in &timeline {
+        let expected = if *at < lost_at {
+            Outcome::Ok
+        } else if *at < lost_at + grace {
+            Outcome::Replication
+        } else if *at <= ms(5_000) {
+            Outcome::Fenced
+        } else if *at >= ms(5_300) {
+            Outcome::Ok
+        } else {
+            continue;
+        };
+        assert_eq!(*outcome, expected, "at {at:?}");
+    }
+    assert!(hinted > 0);
+    assert_eq!(guard.fast_failures(), 2 * hinted);
+    assert!(guard.fenced_shards(base + net.now()).is_empty());
+
+    // 暂存的 Eventual 写入在副本追上后补齐
+    assert_eq!(replicator.pending_count(), hinted as usize);
+    replicator.ack_pending("b");
+    assert_eq!(replicator.ack_pending("c"), hinted as usize);
+}
+
+#[test]
+fn fencing_without_hinted_handoff_rejects_eventual_writes() {
+    let t0 = Instant::now();
+    let guard = QuorumGuard::new("a", ms(200));
+    guard.set_shard("s1", nodes(), t0);
+    guard.set_shard("s2", vec!["a".into(), "d".into(), "e".into()], t0);
+    guard.observe("b", false, t0);
+    guard.observe("c", false, t0);
+    assert_eq!(guard.reachability("s1"), Some((1, 2)));
+    assert_eq!(guard.reachability("s2"), Some((3, 2)));
+
+    let eventual = ConsistencyLevel::Eventual;
+    assert!(guard.admit("s1", eventual, t0 + ms(199)).is_ok());
+    assert!(matches!(
+        guard.admit("s1", eventual, t0 + ms(200)),
+        Err(DistributedError::QuorumUnavailable {
+            reachable: 1,
+            required: 2
+        })
+    ));
+    assert!(
+        guard
+            .admit("s2", ConsistencyLevel::Strong, t0 + ms(200))
+            .is_ok()
+    );
+    assert_eq!(guard.fenced_shards(t0 + ms(200)), ["s1"]);
+    assert!(matches!(
+        guard.admit("s3", eventual, t0),
+        Err(DistributedError::Configuration(_))
+    ));
+
+    // 一个副本恢复即重新构成多数，隔离解除
+    guard.observe("b", true, t0 + ms(300));
+    assert!(!guard.is_fenced("s1", t0 + ms(300)));
+    assert!(
+        guard
+            .admit("s1", ConsistencyLevel::Strong, t0 + ms(300))
+            .is_ok()
+    );
+}
+
+#[test]
+fn replicate_is_admitted_with_the_replicators_quorum() {
+    let t0 = Instant::now();
+    let replicas: Vec<String> = ["a", "b", "c", "l"].map(String::from).to_vec();
+    let guard = Arc::new(QuorumGuard::new("a", Duration::ZERO));
+    guard.set_shard("s1", replicas.clone(), t0);
+    guard.observe("c", false, t0);
+    guard.observe("l", false, t0);
+    // 数量多数：4 个副本需要 3 个，只达到 2 个
+    assert_eq!(guard.reachability("s1"), Some((2, 3)));
+    assert_eq!(guard.fenced_shards(t0), ["s1"]);
+
+    // 学习者 l 不计入仲裁：3 个投票者中 a、b 即构成多数
+    let roles = HashMap::from([("l".to_string(), ReplicaRole::Learner)]);
+    let mut replicator = LocalReplicator::<u64>::new(ConsistentHashRing::new(16), replicas)
+        .with_quorum(Arc::new(RoleAwareQuorum::new(roles)))
+        .with_quorum_guard(guard.clone());
+    assert_eq!(guard.reachability("s1"), Some((2, 2)));
+    assert!(guard.fenced_shards(t0).is_empty());
+    replicator.mark_node_failed("c");
+    replicator.mark_node_failed("l");
+    replicator.replicate(1, ConsistencyLevel::Strong).unwrap();
+
+    // b 也失联：Replicator::replicate 经防护立即失败，而不是进入复制后按仲裁失败
+    guard.observe("b", false, t0);
+    replicator.mark_node_failed("b");
+    assert!(matches!(
+        replicator.replicate(2, ConsistencyLevel::Strong),
+        Err(DistributedError::QuorumUnavailable {
+            reachable: 1,
+            required: 2
+        })
+    ));
+    assert_eq!(guard.fast_failures(), 1);
+    assert!(matches!(
+        replicator.replicate_to_nodes(&nodes(), 3, ConsistencyLevel::Strong),
+        Err(DistributedError::Configuration(_))
+    ));
+
+    guard.observe("b", true, t0);
+    replicator.mark_node_recovered("b");
+    replicator.replicate(4, ConsistencyLevel::Strong).unwrap();
+}
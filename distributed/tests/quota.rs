@@ -0,0 +1,128 @@
+// 测试目的：租户配额在写路径上的执行与用量计数的收敛
+// - 不变量：1) 越过硬上限的写入被拒绝，读取与删除仍可进行，删除释放用量；
+//           2) 两个节点各自记账、经共享存储同步后看到相同的用量。
+use distributed::benchmarks::loadgen::InMemoryReplicatedKv;
+use distributed::consistency::{ConsistencyLevel, PNCounter};
+use distributed::core::errors::DistributedError;
+use distributed::monitoring::MetricValue;
+use distributed::resilience::{QuotaKv, QuotaLimits, QuotaManager, QuotaStatus};
+use distributed::storage::cached_kv::ReplicatedKv;
+use std::sync::Arc;
+
+const LEVEL: ConsistencyLevel = ConsistencyLevel::Quorum;
+
+fn tenant_kv(
+    quotas: Arc<QuotaManager>,
+) -> QuotaKv<InMemoryReplicatedKv<String, Vec<u8>>, String, Vec<u8>> {
+    QuotaKv::new(
+        InMemoryReplicatedKv::new(3),
+        quotas,
+        |key: &String| key.split('/').next().unwrap_or_default().to_string(),
+        |_: &String, value: &Vec<u8>| value.len() as u64,
+    )
+}
+
+fn counter(quotas: &QuotaManager, name: &str) -> f64 {
+    let metric = quotas
+        .metrics()
+        .get_all_metrics()
+        .into_iter()
+        .find(|m| m.name == name);
+    match metric.map(|m| m.value) {
+        Some(MetricValue::Counter(value)) => value,
+        other => panic!("unexpected metric {other:?}"),
+    }
+}
+
+#[test]
+fn hard_byte_limit_rejects_writes_but_allows_deletes() {
+    let limits = QuotaLimits {
+        soft_bytes: Some(200),
+        hard_bytes: Some(300),
+        ops: None,
+    };
+    let quotas = Arc::new(
+        QuotaManager::new("n1", QuotaLimits::default()).with_tenant_limits("acme", limits),
+    );
+    let kv = tenant_kv(quotas.clone());
+
+    kv.put("acme/a".into(), vec![0; 150], LEVEL).unwrap();
+    assert_eq!(
+        quotas.check_write("acme", 100).unwrap(),
+        QuotaStatus::SoftLimitExceeded
+    );
+    kv.put("acme/b".into(), vec![0; 100], LEVEL).unwrap();
+    assert_eq!(quotas.usage("acme"), 250);
+
+    let err = kv.put("acme/c".into(), vec![0; 100], LEVEL).unwrap_err();
+    match err {
+        DistributedError::QuotaExceeded { tenant, resource } => {
+            assert_eq!(tenant, "acme");
+            assert_eq!(resource, "bytes");
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    assert_eq!(kv.get(&"acme/c".to_string(), LEVEL).unwrap(), None);
+    assert_eq!(quotas.usage("acme"), 250);
+
+    // 其他租户不受影响；超限租户仍可读取、删除，删除后可再次写入
+    kv.put("other/a".into(), vec![0; 1000], LEVEL).unwrap();
+    assert_eq!(
+        kv.get(&"acme/a".to_string(), LEVEL)
+            .unwrap()
+            .map(|v| v.len()),
+        Some(150)
+    );
+    kv.delete(&"acme/a".to_string(), LEVEL).unwrap();
+    assert_eq!(quotas.usage("acme"), 100);
+    kv.put("acme/c".into(), vec![0; 100], LEVEL).unwrap();
+    assert_eq!(quotas.usage("acme"), 200);
+
+    assert_eq!(counter(&quotas, "quota_soft_breach_total"), 2.0);
+    assert_eq!(counter(&quotas, "quota_rejected_total"), 1.0);
+}
+
+#[test]
+fn ops_budget_rejects_writes_over_rate() {
+    let limits = QuotaLimits {
+        ops: Some(distributed::security::RateLimitConfig {
+            capacity: 2,
+            refill_per_sec: 0,
+        }),
+        ..QuotaLimits::default()
+    };
+    let quotas = QuotaManager::new("n1", limits);
+    assert!(quotas.check_write("t", 1).is_ok());
+    assert!(quotas.check_write("t", 1).is_ok());
+    assert!(matches!(
+        quotas.check_write("t", 1),
+        Err(DistributedError::QuotaExceeded { resource, .. }) if resource == "ops"
+    ));
+    assert!(quotas.check_write("u", 1).is_ok());
+}
+
+#[test]
+fn usage_converges_across_nodes() {
+    let store: InMemoryReplicatedKv<String, PNCounter> = InMemoryReplicatedKv::new(3);
+    let n1 = QuotaManager::new("n1", QuotaLimits::default());
+    let n2 = QuotaManager::new("n2", QuotaLimits::default());
+
+    n1.record_usage("acme", 100);
+    n1.record_usage("acme", -30);
+    n2.record_usage("acme", 50);
+    n2.record_usage("beta", 5);
+
+    for _ in 0..2 {
+        n1.sync(&store).unwrap();
+        n2.sync(&store).unwrap();
+    }
+    n1.sync_tenant("beta", &store).unwrap();
+
+    assert_eq!(n1.usage("acme"), 120);
+    assert_eq!(n2.usage("acme"), 120);
+    assert_eq!(n1.usage("beta"), 5);
+
+    // 再次同步幂等
+    n1.sync(&store).unwrap();
+    assert_eq!(n1.usage("acme"), 120);
+}
@@ -0,0 +1,78 @@
+// 测试目的：AppendEntries 的日志一致性检查（Raft §5.3）
+// - 不变量：
+//   1) 迟到或重复的 AppendEntries 与本地日志一致时不截断其后已追加的条目；
+//   2) 只从第一个任期冲突的位置截断并覆盖；
+//   3) commit_index 单调不减，且不超过本次请求携带的最后一条新条目。
+use distributed::consensus::raft::{AppendEntriesReq, LogIndex, MinimalRaft, Term};
+
+fn append(
+    prev: u64,
+    prev_term: u64,
+    entries: &[(u64, &str)],
+    leader_commit: u64,
+) -> AppendEntriesReq<(Term, Vec<u8>)> {
+    AppendEntriesReq {
+        term: Term(2),
+        leader_id: "n1".into(),
+        prev_log_index: LogIndex(prev),
+        prev_log_term: Term(prev_term),
+        entries: entries
+            .iter()
+            .map(|(term, data)| (Term(*term), data.as_bytes().to_vec()))
+            .collect(),
+        leader_commit: LogIndex(leader_commit),
+    }
+}
+
+fn follower_with(entries: &[(u64, &str)]) -> MinimalRaft<Vec<u8>> {
+    let mut raft = MinimalRaft::new();
+    let resp = raft
+        .handle_append_entries_with_terms(append(0, 0, entries, 0))
+        .unwrap();
+    assert!(resp.success);
+    raft
+}
+
+#[test]
+fn stale_append_does_not_truncate_matching_suffix() {
+    let mut raft = follower_with(&[(1, "a"), (1, "b"), (2, "c")]);
+
+    // 只携带前两条的旧请求晚于完整请求送达
+    let resp = raft
+        .handle_append_entries_with_terms(append(0, 0, &[(1, "a"), (1, "b")], 0))
+        .unwrap();
+    assert!(resp.success);
+    assert_eq!(raft.last_log_index(), LogIndex(3));
+    assert_eq!(raft.last_log_term(), Term(2));
+}
+
+#[test]
+fn conflicting_suffix_is_truncated_at_first_conflict() {
+    let mut raft = follower_with(&[(1, "a"), (1, "b"), (1, "x"), (1, "y")]);
+
+    let resp = raft
+        .handle_append_entries_with_terms(append(1, 1, &[(1, "b"), (2, "c")], 0))
+        .unwrap();
+    assert!(resp.success);
+    assert_eq!(raft.last_log_index(), LogIndex(3));
+    assert_eq!(raft.last_log_term(), Term(2));
+}
+
+#[test]
+fn commit_index_is_monotonic_and_capped_at_last_new_entry() {
+    let mut raft = follower_with(&[(1, "a"), (1, "b"), (1, "c")]);
+
+    // 领导者提交点超过本次请求携带的条目：只能提交到最后一条新条目
+    raft.handle_append_entries_with_terms(append(0, 0, &[(1, "a")], 3))
+        .unwrap();
+    assert_eq!(raft.commit_index(), LogIndex(1));
+
+    raft.handle_append_entries_with_terms(append(0, 0, &[(1, "a"), (1, "b"), (1, "c")], 3))
+        .unwrap();
+    assert_eq!(raft.commit_index(), LogIndex(3));
+
+    // 迟到的心跳携带更小的提交点，不得让 commit_index 回退
+    raft.handle_append_entries_with_terms(append(1, 1, &[], 1))
+        .unwrap();
+    assert_eq!(raft.commit_index(), LogIndex(3));
+}
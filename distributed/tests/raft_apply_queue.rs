@@ -0,0 +1,136 @@
+// 测试目的：Raft 异步应用队列
+// - 不变量：
+//   1) 启用队列后提交不等待状态机，条目按索引顺序应用，last_applied 最终追上 commit_index；
+//   2) 队列满时提交照常推进，apply_queue_depth 反映积压，pump_apply_queue 补入队后全部应用；
+//   3) 跟随者路径同样经队列应用。
+#[cfg(feature = "runtime-tokio")]
+mod raft_apply_queue {
+    use distributed::consensus_raft::{
+        AppendEntriesReq, LogEntry, LogIndex, MinimalRaft, StateMachine, Term,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    /// 每次应用先取得一个许可，测试以此控制状态机进度
+    struct GatedMachine {
+        gate: Arc<Semaphore>,
+        applied: Arc<Mutex<Vec<(LogIndex, u64)>>>,
+    }
+
+    impl StateMachine<u64> for GatedMachine {
+        async fn apply(&mut self, entry: LogEntry<u64>) {
+            self.gate.acquire().await.unwrap().forget();
+            self.applied
+                .lock()
+                .unwrap()
+                .push((entry.index, entry.command));
+        }
+    }
+
+    /// 状态机、放行闸门与已应用记录
+    type Gated = (
+        GatedMachine,
+        Arc<Semaphore>,
+        Arc<Mutex<Vec<(LogIndex, u64)>>>,
+    );
+
+    fn gated() -> Gated {
+        let gate = Arc::new(Semaphore::new(0));
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let machine = GatedMachine {
+            gate: gate.clone(),
+            applied: applied.clone(),
+        };
+        (machine, gate, applied)
+    }
+
+    fn leader() -> MinimalRaft<u64> {
+        let mut raft = MinimalRaft::new();
+        raft.start_election("n1");
+        raft.become_leader(Vec::<String>::new());
+        raft
+    }
+
+    async fn wait_applied(raft: &MinimalRaft<u64>, index: u64) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while raft.last_applied().0 < index {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("state machine did not catch up");
+    }
+
+    #[tokio::test]
+    async fn commits_do_not_wait_for_state_machine() {
+        let mut raft = leader();
+        let (machine, gate, applied) = gated();
+        raft.start_apply_queue(16, machine);
+
+        for i in 0..10 {
+            raft.propose(i).unwrap();
+        }
+        assert_eq!(raft.commit_index(), LogIndex(10));
+        assert_eq!(raft.last_applied(), LogIndex(0));
+        assert_eq!(raft.apply_queue_depth(), 10);
+
+        gate.add_permits(10);
+        wait_applied(&raft, 10).await;
+        assert_eq!(raft.apply_queue_depth(), 0);
+        let applied = applied.lock().unwrap().clone();
+        let expected: Vec<_> = (0..10).map(|i| (LogIndex(i + 1), i)).collect();
+        assert_eq!(applied, expected);
+    }
+
+    #[tokio::test]
+    async fn full_queue_defers_enqueue_until_pumped() {
+        let mut raft = leader();
+        let (machine, gate, applied) = gated();
+        raft.start_apply_queue(2, machine);
+
+        for i in 0..6 {
+            raft.propose(i).unwrap();
+        }
+        assert_eq!(raft.commit_index(), LogIndex(6));
+        assert_eq!(raft.apply_queue_depth(), 6);
+
+        gate.add_permits(6);
+        // 只有已入队的 2 条会被应用，其余留在日志中等待补入队
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(raft.last_applied(), LogIndex(2));
+        assert_eq!(raft.apply_queue_depth(), 4);
+
+        while raft.last_applied().0 < 6 {
+            raft.pump_apply_queue();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(raft.apply_queue_depth(), 0);
+        assert_eq!(applied.lock().unwrap().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn follower_applies_through_queue() {
+        let mut follower = MinimalRaft::<u64>::new();
+        let (machine, gate, applied) = gated();
+        follower.start_apply_queue(8, machine);
+        gate.add_permits(8);
+
+        let resp = follower
+            .handle_append_entries_with_terms(AppendEntriesReq {
+                term: Term(1),
+                leader_id: "n1".into(),
+                prev_log_index: LogIndex(0),
+                prev_log_term: Term(0),
+                entries: vec![(Term(1), 7), (Term(1), 8), (Term(1), 9)],
+                leader_commit: LogIndex(2),
+            })
+            .unwrap();
+        assert!(resp.success);
+
+        wait_applied(&follower, 2).await;
+        let applied = applied.lock().unwrap().clone();
+        assert_eq!(applied, vec![(LogIndex(1), 7), (LogIndex(2), 8)]);
+        assert_eq!(follower.apply_queue_depth(), 0);
+    }
+}
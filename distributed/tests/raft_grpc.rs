@@ -0,0 +1,287 @@
+// 测试目的：基于 gRPC 的三节点 Raft 集群
+// - 不变量：
+//   1) 进程内三个节点选出唯一领导者，提交的条目在所有节点按序应用；
+//   2) 跟随者拒绝管理接口的提案，并返回当前领导者提示；
+//   3) 宕机后以空日志重启的跟随者由领导者补齐全部已提交条目；
+//   4) 配置硬状态文件的节点重启后恢复任期，同一任期不会再投出第二票。
+#[cfg(feature = "transport-grpc")]
+mod raft_grpc {
+    use distributed::consensus::raft_grpc::{GrpcRaftTransport, RaftConfig, RaftGrpcNode};
+    use distributed::consensus::{
+        LogIndex, MinimalRaft, RaftState, RaftTransport, RequestVoteReq, Term,
+    };
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    fn config() -> RaftConfig {
+        RaftConfig {
+            heartbeat_interval: Duration::from_millis(20),
+            election_timeout_min: Duration::from_millis(150),
+            election_timeout_max: Duration::from_millis(300),
+            rpc_timeout: Duration::from_millis(100),
+            backoff_base: Duration::from_millis(20),
+            backoff_max: Duration::from_millis(100),
+        }
+    }
+
+    struct TestNode {
+        node: RaftGrpcNode,
+        applied: Arc<Mutex<Vec<Vec<u8>>>>,
+        tasks: Vec<JoinHandle<()>>,
+    }
+
+    impl TestNode {
+        fn kill(&self) {
+            self.node.shutdown();
+            for task in &self.tasks {
+                task.abort();
+            }
+        }
+    }
+
+    struct Cluster {
+        addrs: Vec<(String, SocketAddr)>,
+        nodes: Vec<TestNode>,
+        admin: GrpcRaftTransport,
+    }
+
+    impl Cluster {
+        async fn start(size: usize) -> Self {
+            let mut listeners = Vec::new();
+            for i in 0..size {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                listeners.push((format!("n{}", i + 1), listener));
+            }
+            let addrs: Vec<_> = listeners
+                .iter()
+                .map(|(id, l)| (id.clone(), l.local_addr().unwrap()))
+                .collect();
+            let admin = GrpcRaftTransport::new(
+                addrs
+                    .iter()
+                    .map(|(id, addr)| (id.clone(), format!("http://{addr}"))),
+            )
+            .with_config(config());
+            let mut cluster = Self {
+                addrs,
+                nodes: Vec::new(),
+                admin,
+            };
+            for (id, listener) in listeners {
+                let node = cluster.spawn(&id, listener);
+                cluster.nodes.push(node);
+            }
+            cluster
+        }
+
+        fn spawn(&self, id: &str, listener: TcpListener) -> TestNode {
+            let applied = Arc::new(Mutex::new(Vec::new()));
+            let mut raft = MinimalRaft::new();
+            let sink = applied.clone();
+            raft.set_apply(Box::new(move |e: &Vec<u8>| {
+                sink.lock().unwrap().push(e.clone())
+            }));
+            let peers = self
+                .addrs
+                .iter()
+                .filter(|(peer, _)| peer != id)
+                .map(|(peer, addr)| (peer.clone(), format!("http://{addr}")));
+            let node = RaftGrpcNode::new(id, raft, peers).with_config(config());
+            let server = tokio::spawn({
+                let service = node.server();
+                async move {
+                    let _ = tonic::transport::Server::builder()
+                        .add_service(service)
+                        .serve_with_incoming(TcpListenerStream::new(listener))
+                        .await;
+                }
+            });
+            let driver = node.start();
+            TestNode {
+                node,
+                applied,
+                tasks: vec![server, driver],
+            }
+        }
+
+        async fn restart(&mut self, i: usize) {
+            self.nodes[i].kill();
+            let (id, addr) = self.addrs[i].clone();
+            let listener = bind_retry(addr).await;
+            self.nodes[i] = self.spawn(&id, listener);
+        }
+
+        /// 等待恰好一个节点成为领导者，返回其下标
+        async fn leader(&self) -> usize {
+            wait_until(|| {
+                let leaders: Vec<_> = self
+                    .nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, n)| n.node.status().state == RaftState::Leader)
+                    .collect();
+                (leaders.len() == 1).then(|| leaders[0].0)
+            })
+            .await
+        }
+    }
+
+    async fn bind_retry(addr: SocketAddr) -> TcpListener {
+        for _ in 0..50 {
+            if let Ok(listener) = TcpListener::bind(addr).await {
+                return listener;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("cannot rebind {addr}");
+    }
+
+    async fn wait_until<T>(mut check: impl FnMut() -> Option<T>) -> T {
+        let start = Instant::now();
+        loop {
+            if let Some(v) = check() {
+                return v;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn committed_entry_is_applied_on_all_nodes() {
+        let cluster = Cluster::start(3).await;
+        let leader = cluster.leader().await;
+        let leader_id = cluster.nodes[leader].node.id().to_string();
+
+        let reply = cluster
+            .admin
+            .propose(&leader_id, b"x=1".to_vec())
+            .await
+            .unwrap();
+        assert!(reply.accepted);
+        assert_eq!(reply.index, 1);
+
+        wait_until(|| {
+            cluster
+                .nodes
+                .iter()
+                .all(|n| *n.applied.lock().unwrap() == vec![b"x=1".to_vec()])
+                .then_some(())
+        })
+        .await;
+        let term = cluster.nodes[leader].node.status().term;
+        for n in &cluster.nodes {
+            let status = cluster.admin.status(n.node.id()).await.unwrap();
+            assert_eq!(status.commit_index, 1);
+            assert_eq!(status.term, term.0);
+            assert_eq!(status.leader.as_deref(), Some(leader_id.as_str()));
+        }
+    }
+
+    #[tokio::test]
+    async fn followers_reject_proposals_with_leader_hint() {
+        let cluster = Cluster::start(3).await;
+        let leader = cluster.leader().await;
+        let leader_id = cluster.nodes[leader].node.id().to_string();
+        let follower = (leader + 1) % 3;
+        // 跟随者需先收到一次心跳才知道领导者
+        wait_until(|| cluster.nodes[follower].node.status().leader).await;
+
+        let reply = cluster
+            .admin
+            .propose(cluster.nodes[follower].node.id(), b"y".to_vec())
+            .await
+            .unwrap();
+        assert!(!reply.accepted);
+        assert_eq!(reply.leader.as_deref(), Some(leader_id.as_str()));
+        assert!(cluster.nodes[follower].node.propose(b"y".to_vec()).is_err());
+    }
+
+    #[tokio::test]
+    async fn restarted_follower_catches_up() {
+        let mut cluster = Cluster::start(3).await;
+        let leader = cluster.leader().await;
+        let leader_id = cluster.nodes[leader].node.id().to_string();
+        let follower = (leader + 1) % 3;
+
+        for i in 0..3u8 {
+            cluster.nodes[leader].node.propose(vec![i]).unwrap();
+        }
+        cluster.nodes[follower].kill();
+        // 剩余两个节点仍构成多数派
+        for i in 3..5u8 {
+            let reply = cluster.admin.propose(&leader_id, vec![i]).await.unwrap();
+            assert!(reply.accepted);
+        }
+        wait_until(|| (cluster.nodes[leader].node.status().commit_index.0 == 5).then_some(()))
+            .await;
+
+        cluster.restart(follower).await;
+        let expected: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i]).collect();
+        wait_until(|| (*cluster.nodes[follower].applied.lock().unwrap() == expected).then_some(()))
+            .await;
+        assert_eq!(cluster.nodes[follower].node.status().last_log_index.0, 5);
+    }
+
+    #[tokio::test]
+    async fn restarted_node_does_not_vote_twice_in_a_term() {
+        let path = std::env::temp_dir().join(format!("raft-hard-state-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let admin =
+            GrpcRaftTransport::new([("n1", format!("http://{addr}"))]).with_config(config());
+        // 不启动驱动循环：节点只被动应答投票请求
+        let serve = |listener: TcpListener| {
+            let node = RaftGrpcNode::new("n1", MinimalRaft::new(), [("n2", "http://127.0.0.1:1")])
+                .with_config(config())
+                .with_hard_state(&path)
+                .unwrap();
+            let service = node.server();
+            let server = tokio::spawn(async move {
+                let _ = tonic::transport::Server::builder()
+                    .add_service(service)
+                    .serve_with_incoming(TcpListenerStream::new(listener))
+                    .await;
+            });
+            (node, server)
+        };
+        let vote = |candidate: &str| RequestVoteReq {
+            term: Term(5),
+            candidate_id: candidate.into(),
+            last_log_index: LogIndex(0),
+            last_log_term: Term(0),
+        };
+
+        let (node, server) = serve(listener);
+        assert!(
+            admin
+                .request_vote("n1", vote("n2"))
+                .await
+                .unwrap()
+                .vote_granted
+        );
+        node.shutdown();
+        server.abort();
+
+        let (node, server) = serve(bind_retry(addr).await);
+        assert_eq!(node.status().term, Term(5));
+        // 旧通道已断开：重试到新进程应答为止
+        let start = Instant::now();
+        let second = loop {
+            if let Ok(resp) = admin.request_vote("n1", vote("n3")).await {
+                break resp;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        assert!(!second.vote_granted);
+        assert_eq!(second.term, Term(5));
+        server.abort();
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,153 @@
+// 测试目的：见证者与学习者副本
+// - 不变量：1) 2 个投票者 + 1 个见证者：一个投票者故障时，另一投票者与见证者的确认即可提交写入；
+//              学习者的确认不计入仲裁；
+//           2) 读取从不路由到见证者（哈希环、路由器与仲裁读）；
+//           3) Raft 学习者接收日志但不计入提交与选举仲裁，经成员变更提升后计入多数派。
+use distributed::ReplicaRole;
+use distributed::consensus::raft::{
+    AppendEntriesReq, ConfChange, LogIndex, MinimalRaft, RaftNode, RequestVoteReq, Term,
+};
+use distributed::consistency::ConsistencyLevel;
+use distributed::core::ClientSession;
+use distributed::partitioning::HashRingRouter;
+use distributed::replication::{LocalReplicator, RoleAwareQuorum};
+use distributed::topology::ConsistentHashRing;
+use std::sync::Arc;
+
+const LEVEL: ConsistencyLevel = ConsistencyLevel::Quorum;
+
+fn ring() -> ConsistentHashRing {
+    let mut ring = ConsistentHashRing::new(16);
+    ring.add_node("v1");
+    ring.add_node("v2");
+    ring.add_node_with_role("w", ReplicaRole::Witness);
+    ring
+}
+
+fn targets(nodes: &[&str]) -> Vec<String> {
+    nodes.iter().map(|n| n.to_string()).collect()
+}
+
+#[test]
+fn witness_ack_completes_write_quorum_but_learner_ack_does_not() {
+    let mut ring = ring();
+    ring.add_node_with_role("l", ReplicaRole::Learner);
+    let quorum = Arc::new(RoleAwareQuorum::from_ring(&ring));
+    let nodes = targets(&["v1", "v2", "w", "l"]);
+    let mut replicator = LocalReplicator::<u64>::new(ring, nodes.clone()).with_quorum(quorum);
+
+    replicator.mark_node_failed("v2");
+    replicator.replicate_to_nodes(&nodes, "put", LEVEL).unwrap();
+
+    // 只剩一个投票者与学习者：投票者 + 见证者中只有 1/3 确认
+    replicator.mark_node_failed("w");
+    let err = replicator
+        .replicate_to_nodes(&nodes, "put", LEVEL)
+        .unwrap_err();
+    assert!(err.to_string().contains("acks 1/2"), "{err}");
+}
+
+#[test]
+fn reads_never_route_to_witness() {
+    let ring = ring();
+    for i in 0..200 {
+        let key = format!("key-{i}");
+        assert_eq!(
+            ring.nodes_for_with_roles(&key, 3)
+                .iter()
+                .filter(|(_, role)| *role == ReplicaRole::Witness)
+                .count(),
+            1
+        );
+        let readable = ring.read_nodes_for(&key, 3);
+        assert_eq!(readable.len(), 2);
+        assert!(!readable.contains(&"w".to_string()));
+    }
+
+    let router = HashRingRouter::new(ring.clone());
+    for i in 0..200 {
+        let key = format!("key-{i}");
+        let target = router.preferred_replica(&key, 3, "w").unwrap();
+        assert_ne!(target, "w", "{key}");
+    }
+
+    // 仲裁读只在投票者间进行：见证者水位最高也不被选中，投票者不足时拒绝
+    let nodes = targets(&["v1", "v2", "w"]);
+    let mut replicator = LocalReplicator::<u64>::new(ring, nodes.clone());
+    replicator.advance_watermark("v1", 1);
+    replicator.advance_watermark("v2", 2);
+    replicator.advance_watermark("w", 3);
+    let mut session = ClientSession::new();
+    let read = replicator.quorum_read(&nodes, LEVEL, &mut session, |n| n.to_string());
+    assert_eq!(read.unwrap(), "v2");
+    replicator.mark_node_failed("v1");
+    assert!(
+        replicator
+            .quorum_read(&nodes, LEVEL, &mut session, |n| n.to_string())
+            .is_err()
+    );
+}
+
+/// 把领导者的日志复制到 `peer` 并把应答交回领导者
+fn replicate(leader: &mut MinimalRaft<u64>, follower: &mut MinimalRaft<u64>, peer: &str) {
+    let req = leader.append_entries_for(peer, "a");
+    let last_sent = LogIndex(req.prev_log_index.0 + req.entries.len() as u64);
+    let resp = follower.handle_append_entries_with_terms(req).unwrap();
+    leader.handle_append_entries_resp(peer, last_sent, &resp);
+}
+
+#[test]
+fn learner_counts_toward_majority_only_after_promotion() {
+    let mut a = MinimalRaft::<u64>::new();
+    let (mut b, mut c) = (MinimalRaft::<u64>::new(), MinimalRaft::<u64>::new());
+    c.set_learner(true);
+    a.start_election("a");
+    a.become_leader(["b"]);
+    a.apply_conf_change(ConfChange::AddLearner("c".to_string()))
+        .unwrap();
+    assert_eq!(a.peer_role("c"), Some(ReplicaRole::Learner));
+    assert_eq!((a.voters(), a.quorum_size()), (vec!["b"], 2));
+
+    // 学习者收到日志并确认，但提交仍需投票者 b
+    a.propose(1).unwrap();
+    replicate(&mut a, &mut c, "c");
+    assert_eq!(c.last_log_index(), LogIndex(1));
+    assert_eq!(a.commit_index(), LogIndex(0));
+    replicate(&mut a, &mut b, "b");
+    assert_eq!(a.commit_index(), LogIndex(1));
+
+    // 提升后 c 的确认单独即可与领导者构成 3 个投票者中的多数
+    a.apply_conf_change(ConfChange::PromoteLearner("c".to_string()))
+        .unwrap();
+    c.set_learner(false);
+    assert_eq!((a.voters(), a.quorum_size()), (vec!["b", "c"], 2));
+    assert!(a.learners().is_empty());
+    a.propose(2).unwrap();
+    replicate(&mut a, &mut c, "c");
+    assert_eq!(a.commit_index(), LogIndex(2));
+    assert!(
+        a.apply_conf_change(ConfChange::PromoteLearner("c".to_string()))
+            .is_err()
+    );
+
+    // 提升后的 c 投票，学习者不投票；学习者照常接收不带任期的请求
+    let vote = RequestVoteReq {
+        term: Term(5),
+        candidate_id: "b".to_string(),
+        last_log_index: LogIndex(2),
+        last_log_term: Term(1),
+    };
+    assert!(c.handle_request_vote(vote.clone()).unwrap().vote_granted);
+    let mut d = MinimalRaft::<u64>::new();
+    d.set_learner(true);
+    assert!(!d.handle_request_vote(vote).unwrap().vote_granted);
+    let req = AppendEntriesReq {
+        term: Term(5),
+        leader_id: "b".to_string(),
+        prev_log_index: LogIndex(0),
+        prev_log_term: Term(0),
+        entries: vec![7],
+        leader_commit: LogIndex(0),
+    };
+    assert!(d.handle_append_entries(req).unwrap().success);
+}
@@ -0,0 +1,89 @@
+// 测试目的：复制管道的待确认条目上限（背压）
+// - 不变量：1) 待确认条目数不超过 max_pending_entries，达到上限后 replicate 以 BackPressure 拒绝，
+//              副本追上并确认后恢复接受；全部副本确认的条目不计入待确认数；
+//           2) 异步变体在条目已满时挂起调用方而不报错，确认归还许可后调用方继续完成。
+use distributed::consistency::ConsistencyLevel;
+use distributed::core::errors::DistributedError;
+use distributed::replication::{LocalReplicator, Replicator};
+use distributed::topology::ConsistentHashRing;
+
+fn build(nodes: &[&str]) -> LocalReplicator<u64> {
+    let mut ring = ConsistentHashRing::new(8);
+    for n in nodes {
+        ring.add_node(n);
+    }
+    LocalReplicator::new(ring, nodes.iter().map(|n| n.to_string()).collect())
+}
+
+#[test]
+fn saturated_pipeline_rejects_with_back_pressure() {
+    assert_eq!(build(&["n1"]).max_pending_entries(), 1_000);
+    let mut repl = build(&["n1", "n2", "n3"]).with_max_pending_entries(5);
+    for i in 0..10u32 {
+        repl.replicate(i, ConsistencyLevel::Quorum).unwrap();
+    }
+    assert_eq!(repl.pending_count(), 0);
+
+    // n3 变慢：仲裁仍可达成，但每个条目都在等待 n3 确认
+    repl.mark_node_failed("n3");
+    for i in 0..5u32 {
+        repl.replicate(i, ConsistencyLevel::Quorum).unwrap();
+    }
+    assert_eq!(repl.pending_count(), 5);
+    let err = repl.replicate(5u32, ConsistencyLevel::Quorum).unwrap_err();
+    assert!(matches!(err, DistributedError::BackPressure(_)), "{err}");
+    assert_eq!(err.kind(), "BackPressure");
+    assert_eq!(repl.pending_count(), 5);
+
+    // 待确认数不超过上限：已满时即使全部副本都会确认的写入也被拒绝
+    repl.mark_node_recovered("n3");
+    let err = repl.replicate(5u32, ConsistencyLevel::Quorum).unwrap_err();
+    assert!(matches!(err, DistributedError::BackPressure(_)), "{err}");
+    assert_eq!(repl.ack_pending("n2"), 0);
+    assert_eq!(repl.ack_pending("n3"), 5);
+    assert_eq!(repl.pending_count(), 0);
+    repl.replicate(5u32, ConsistencyLevel::Quorum).unwrap();
+    assert_eq!(repl.pending_count(), 0);
+}
+
+#[cfg(feature = "runtime-tokio")]
+mod suspension {
+    use super::build;
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::replication::AsyncLocalReplicator;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn async_replicator_suspends_until_entries_are_acknowledged() {
+        let repl = Arc::new(AsyncLocalReplicator::new(
+            build(&["n1", "n2", "n3"]).with_max_pending_entries(2),
+        ));
+        repl.mark_node_failed("n3");
+        for i in 0..2u32 {
+            repl.replicate(i, ConsistencyLevel::Quorum).await.unwrap();
+        }
+        assert_eq!(repl.pending_count(), 2);
+        assert_eq!(repl.available_permits(), 0);
+
+        let blocked = {
+            let repl = repl.clone();
+            tokio::spawn(async move { repl.replicate(2u32, ConsistencyLevel::Quorum).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!blocked.is_finished());
+        assert_eq!(repl.pending_count(), 2);
+
+        // n3 追上：两个条目完成，挂起的调用方取得许可并完成（n3 仍故障，新条目继续待确认）
+        assert_eq!(repl.ack_pending("n3"), 2);
+        blocked.await.unwrap().unwrap();
+        assert_eq!(repl.pending_count(), 1);
+        assert_eq!(repl.available_permits(), 1);
+
+        repl.mark_node_recovered("n3");
+        repl.replicate(3u32, ConsistencyLevel::Quorum)
+            .await
+            .unwrap();
+        assert_eq!(repl.available_permits(), 1);
+    }
+}
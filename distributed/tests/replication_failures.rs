@@ -0,0 +1,44 @@
+// 测试目的：LocalReplicator 的节点故障/恢复与脑裂模拟
+// - 不变量：1) 三节点中两个故障时 Strong/Quorum 复制失败，Eventual（需 1 个确认）成功；恢复后 Quorum 复制成功；
+//           2) 脑裂时多数派一侧可以完成 Quorum 复制，少数派一侧不能。
+use distributed::consistency::ConsistencyLevel;
+use distributed::replication::{LocalReplicator, Replicator};
+use distributed::topology::ConsistentHashRing;
+
+fn build(nodes: &[&str]) -> LocalReplicator<u64> {
+    let mut ring = ConsistentHashRing::new(8);
+    for n in nodes {
+        ring.add_node(n);
+    }
+    LocalReplicator::new(ring, nodes.iter().map(|n| n.to_string()).collect())
+}
+
+#[test]
+fn two_of_three_failed_only_eventual_succeeds() {
+    let mut repl = build(&["n1", "n2", "n3"]);
+    repl.mark_node_failed("n3");
+    repl.mark_node_failed("n1");
+    assert_eq!(repl.failed_nodes(), vec!["n1", "n3"]);
+
+    assert!(repl.replicate(1u32, ConsistencyLevel::Strong).is_err());
+    assert!(repl.replicate(1u32, ConsistencyLevel::Quorum).is_err());
+    repl.replicate(1u32, ConsistencyLevel::Eventual).unwrap();
+
+    repl.mark_node_recovered("n1");
+    assert_eq!(repl.failed_nodes(), vec!["n3"]);
+    assert_eq!(repl.recovered_nodes(), vec!["n1"]);
+    repl.replicate(2u32, ConsistencyLevel::Quorum).unwrap();
+}
+
+#[test]
+fn split_brain_majority_side_keeps_quorum() {
+    let mut repl = build(&["n1", "n2", "n3"]);
+    let (_, minority_view) = repl.split_brain_scenario(vec!["n1", "n2"], vec!["n3"]);
+    assert_eq!(repl.failed_nodes(), vec!["n3"]);
+    repl.replicate(1u32, ConsistencyLevel::Quorum).unwrap();
+
+    repl.successes = minority_view;
+    assert_eq!(repl.failed_nodes(), vec!["n1", "n2"]);
+    assert!(repl.replicate(1u32, ConsistencyLevel::Quorum).is_err());
+    repl.replicate(1u32, ConsistencyLevel::Eventual).unwrap();
+}
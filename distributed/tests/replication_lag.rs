@@ -0,0 +1,56 @@
+// 测试目的：复制延迟监控
+// - 不变量：
+//   1) 跟随者停滞时其延迟随领导者提交增长，max_lag 取最落后者；
+//   2) 延迟超过阈值时告警回调触发一次，追平后重新武装。
+use distributed::core::ClusterNodeId;
+use distributed::replication::ReplicationLagMonitor;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn stalled_follower_lag_grows_and_alerts_above_threshold() {
+    let mut monitor = ReplicationLagMonitor::new(5);
+    let alerts = Arc::new(Mutex::new(Vec::new()));
+    let sink = alerts.clone();
+    monitor.set_alert_callback(move |node, lag| sink.lock().unwrap().push((node, lag)));
+
+    let (n2, n3) = (ClusterNodeId::generate(), ClusterNodeId::generate());
+    // n2 在索引 3 停滞，n3 一路跟上
+    let mut lags = Vec::new();
+    for commit in 1..=10 {
+        monitor.update_leader_commit(commit);
+        monitor.update_follower_commit(&n2, commit.min(3));
+        monitor.update_follower_commit(&n3, commit);
+        lags.push(monitor.lag(&n2));
+    }
+    assert_eq!(monitor.leader_commit(), 10);
+    assert_eq!(lags, [0, 0, 0, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(monitor.lag(&n3), 0);
+    assert_eq!(monitor.max_lag(), 7);
+    // 只在首次超过阈值（lag 6）时告警
+    assert_eq!(*alerts.lock().unwrap(), [(n2.clone(), 6)]);
+
+    // 追平后重新武装，再次落后时再告警
+    monitor.update_follower_commit(&n2, 10);
+    assert_eq!(monitor.max_lag(), 0);
+    monitor.update_leader_commit(20);
+    let alerts = alerts.lock().unwrap();
+    assert_eq!(alerts.len(), 3);
+    assert!(alerts[1..].contains(&(n2.clone(), 10)));
+    assert!(alerts[1..].contains(&(n3.clone(), 10)));
+}
+
+#[test]
+fn follower_commit_is_monotonic() {
+    let mut monitor = ReplicationLagMonitor::new(100);
+    monitor.update_leader_commit(10);
+    let n2 = ClusterNodeId::generate();
+    monitor.update_follower_commit(&n2, 8);
+    monitor.update_follower_commit(&n2, 4);
+    assert_eq!(monitor.lag(&n2), 2);
+    // 领导者提交不回退
+    monitor.update_leader_commit(5);
+    assert_eq!(monitor.leader_commit(), 10);
+    assert_eq!(monitor.lag(&ClusterNodeId::generate()), 10);
+    monitor.remove_follower(&n2);
+    assert_eq!(monitor.max_lag(), 0);
+}
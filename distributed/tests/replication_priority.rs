@@ -0,0 +1,154 @@
+// 测试目的：复制队列的优先级调度
+// - 不变量：
+//   1) 慢对端下 Control 命令先于积压的 Bulk 完成，但 Bulk 在配额内仍有进展；
+//   2) 较高类别持续占满时，Bulk 每 `bulk_quota` 条至少取出一条，不会饿死；
+//   3) 各类别的完成数与卸载数分别计数，优先级随请求传到传输层。
+#[cfg(feature = "runtime-tokio")]
+mod replication_priority {
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::core::errors::DistributedError;
+    use distributed::network::Deadline;
+    use distributed::storage::replication::{
+        Priority, RemoteReplicator, ReplicateAck, ReplicateRequest, ReplicationTransport,
+    };
+    use distributed::storage::replication_queue::{
+        ReplicationQueue, ReplicationQueueConfig, ReplicationTicket, ShedPolicy,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// 每次确认耗时 10ms，并按完成顺序记录请求的优先级
+    #[derive(Clone, Default)]
+    struct SlowPeer {
+        completed: Arc<Mutex<Vec<Priority>>>,
+    }
+
+    impl ReplicationTransport for SlowPeer {
+        async fn send(
+            &self,
+            node: &str,
+            request: ReplicateRequest,
+            _deadline: Deadline,
+        ) -> Result<ReplicateAck, DistributedError> {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.completed.lock().unwrap().push(request.priority);
+            Ok(ReplicateAck {
+                applied: true,
+                node_id: node.to_string(),
+                error: None,
+            })
+        }
+    }
+
+    fn queue(peer: &SlowPeer, config: ReplicationQueueConfig) -> ReplicationQueue {
+        let replicator = RemoteReplicator::new(peer.clone(), vec!["n1".into()]);
+        ReplicationQueue::start(replicator, config.with_concurrency(1))
+    }
+
+    async fn submit_many(
+        queue: &ReplicationQueue,
+        prefix: &str,
+        count: usize,
+        priority: Priority,
+    ) -> Vec<ReplicationTicket> {
+        let mut tickets = Vec::with_capacity(count);
+        for i in 0..count {
+            let ticket = queue
+                .submit_with_priority(
+                    format!("{prefix}{i}"),
+                    vec![0],
+                    ConsistencyLevel::Quorum,
+                    priority,
+                )
+                .await
+                .unwrap();
+            tickets.push(ticket);
+        }
+        tickets
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn control_completes_before_bulk_backlog() {
+        let peer = SlowPeer::default();
+        let queue = queue(
+            &peer,
+            ReplicationQueueConfig::default()
+                .with_capacity(2000)
+                .with_bulk_quota(4),
+        );
+        let bulk = submit_many(&queue, "bulk-", 1000, Priority::Bulk).await;
+        let control = submit_many(&queue, "control-", 10, Priority::Control).await;
+
+        for ticket in control {
+            ticket.wait().await.unwrap();
+        }
+        let completed = peer.completed.lock().unwrap().clone();
+        let bulk_before_control = completed.iter().filter(|p| **p == Priority::Bulk).count();
+        assert!(
+            bulk_before_control < 100,
+            "{bulk_before_control} bulk commands finished first"
+        );
+        assert!(bulk_before_control >= 1, "bulk starved under the quota");
+
+        for ticket in bulk.into_iter().take(100) {
+            ticket.wait().await.unwrap();
+        }
+        assert_eq!(queue.stats().class(Priority::Control).completed, 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bulk_progresses_under_sustained_high_load() {
+        let peer = SlowPeer::default();
+        let queue = queue(
+            &peer,
+            ReplicationQueueConfig::default()
+                .with_capacity(1000)
+                .with_bulk_quota(8),
+        );
+        let bulk = submit_many(&queue, "bulk-", 20, Priority::Bulk).await;
+        let high = submit_many(&queue, "high-", 400, Priority::High).await;
+
+        for ticket in bulk {
+            ticket.wait().await.unwrap();
+        }
+        let stats = queue.stats();
+        assert_eq!(stats.class(Priority::Bulk).completed, 20);
+        // 每取出 8 条 High 至少取出一条 Bulk
+        assert!(stats.class(Priority::High).completed <= 20 * 8);
+        assert!(stats.class(Priority::High).depth > 0);
+
+        for ticket in high {
+            ticket.wait().await.unwrap();
+        }
+        assert_eq!(queue.stats().class(Priority::High).completed, 400);
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shed_is_counted_per_class() {
+        let peer = SlowPeer::default();
+        let queue = queue(
+            &peer,
+            ReplicationQueueConfig::default()
+                .with_capacity(2)
+                .with_policy(ShedPolicy::RejectNew),
+        );
+        let queued = submit_many(&queue, "normal-", 2, Priority::Normal).await;
+        for priority in [Priority::Bulk, Priority::Bulk, Priority::Control] {
+            let rejected = queue
+                .submit_with_priority("late", vec![0], ConsistencyLevel::Quorum, priority)
+                .await;
+            assert!(matches!(rejected, Err(DistributedError::Overloaded(_))));
+        }
+        for ticket in queued {
+            ticket.wait().await.unwrap();
+        }
+
+        let stats = queue.stats();
+        assert_eq!(stats.rejected, 3);
+        assert_eq!(stats.class(Priority::Bulk).shed, 2);
+        assert_eq!(stats.class(Priority::Control).shed, 1);
+        assert_eq!(stats.class(Priority::Normal).completed, 2);
+        assert_eq!(*peer.completed.lock().unwrap(), vec![Priority::Normal; 2]);
+    }
+}
@@ -0,0 +1,174 @@
+// 测试目的：带背压的复制提交队列
+// - 不变量：
+//   1) RejectNew：队列满时新提交以 Overloaded 拒绝并计数，已入队的写入照常完成；
+//   2) DropOldestEventual：只丢弃最旧的 Eventual 条目，Strong/Quorum 写入从不被丢弃，
+//      无可丢弃条目时显式拒绝；
+//   3) BlockWithDeadline：等待空位不超过截止时间，期间有空位则入队。
+#[cfg(feature = "runtime-tokio")]
+mod replication_queue {
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::core::errors::DistributedError;
+    use distributed::network::Deadline;
+    use distributed::storage::replication::{
+        RemoteReplicator, ReplicateAck, ReplicateRequest, ReplicationTransport,
+    };
+    use distributed::storage::replication_queue::{
+        ReplicationQueue, ReplicationQueueConfig, ReplicationTicket, ShedPolicy,
+    };
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    /// 每次确认耗时 `delay` 的对端
+    struct SlowPeers {
+        delay: Duration,
+    }
+
+    impl ReplicationTransport for SlowPeers {
+        async fn send(
+            &self,
+            node: &str,
+            _request: ReplicateRequest,
+            _deadline: Deadline,
+        ) -> Result<ReplicateAck, DistributedError> {
+            tokio::time::sleep(self.delay).await;
+            Ok(ReplicateAck {
+                applied: true,
+                node_id: node.to_string(),
+                error: None,
+            })
+        }
+    }
+
+    const ACK_DELAY: Duration = Duration::from_millis(100);
+
+    fn queue(capacity: usize, policy: ShedPolicy) -> ReplicationQueue {
+        let replicator = RemoteReplicator::new(SlowPeers { delay: ACK_DELAY }, vec!["n1".into()]);
+        ReplicationQueue::start(
+            replicator,
+            ReplicationQueueConfig::default()
+                .with_capacity(capacity)
+                .with_policy(policy)
+                .with_concurrency(1),
+        )
+    }
+
+    /// 让工作任务取走队首条目
+    async fn let_worker_run() {
+        tokio::task::yield_now().await;
+    }
+
+    fn is_overloaded<T>(result: &Result<T, DistributedError>) -> bool {
+        matches!(result, Err(DistributedError::Overloaded(_)))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reject_new_counts_rejections() {
+        let queue = queue(2, ShedPolicy::RejectNew);
+        let in_flight = queue
+            .submit("w0", vec![0], ConsistencyLevel::Strong)
+            .await
+            .unwrap();
+        let_worker_run().await;
+
+        let mut queued = Vec::new();
+        for i in 1..=2 {
+            let key = format!("w{i}");
+            queued.push(
+                queue
+                    .submit(key, vec![i], ConsistencyLevel::Quorum)
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_eq!(queue.depth(), 2);
+        for level in [ConsistencyLevel::Strong, ConsistencyLevel::Eventual] {
+            assert!(is_overloaded(&queue.submit("late", vec![], level).await));
+        }
+        let stats = queue.stats();
+        assert_eq!((stats.rejected, stats.shed(), stats.depth), (2, 2, 2));
+
+        assert!(in_flight.wait().await.is_ok());
+        for ticket in queued {
+            assert!(ticket.wait().await.is_ok());
+        }
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drop_oldest_only_sheds_eventual_writes() {
+        let queue = queue(2, ShedPolicy::DropOldestEventual);
+        let in_flight = queue
+            .submit("w0", vec![], ConsistencyLevel::Strong)
+            .await
+            .unwrap();
+        let_worker_run().await;
+
+        let eventual_1 = queue
+            .submit("e1", vec![], ConsistencyLevel::Eventual)
+            .await
+            .unwrap();
+        let strong_1 = queue
+            .submit("s1", vec![], ConsistencyLevel::Strong)
+            .await
+            .unwrap();
+        // 队列满：e2 挤掉最旧的 Eventual 条目 e1
+        let eventual_2 = queue
+            .submit("e2", vec![], ConsistencyLevel::Eventual)
+            .await
+            .unwrap();
+        assert!(is_overloaded(&eventual_1.wait().await));
+        // Quorum 写入挤掉 e2；队列只剩非 Eventual 条目后新写入被显式拒绝
+        let quorum = queue
+            .submit("q1", vec![], ConsistencyLevel::Quorum)
+            .await
+            .unwrap();
+        assert!(is_overloaded(&eventual_2.wait().await));
+        let rejected = queue.submit("s2", vec![], ConsistencyLevel::Strong).await;
+        assert!(is_overloaded(&rejected));
+
+        let stats = queue.stats();
+        assert_eq!((stats.dropped, stats.rejected), (2, 1));
+        for ticket in [in_flight, strong_1, quorum] {
+            assert_eq!(ticket.wait().await.unwrap().len(), 1);
+        }
+    }
+
+    /// 容量 1：w0 在途、w1 排队，队列已满
+    async fn saturated(policy: ShedPolicy) -> (ReplicationQueue, Vec<ReplicationTicket>) {
+        let queue = queue(1, policy);
+        let first = queue
+            .submit("w0", vec![], ConsistencyLevel::Strong)
+            .await
+            .unwrap();
+        let_worker_run().await;
+        let second = queue
+            .submit("w1", vec![], ConsistencyLevel::Strong)
+            .await
+            .unwrap();
+        (queue, vec![first, second])
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn blocking_submit_honors_deadline() {
+        let (queue, _) = saturated(ShedPolicy::BlockWithDeadline(Duration::from_millis(30))).await;
+        let start = Instant::now();
+        let blocked = queue.submit("w2", vec![], ConsistencyLevel::Strong).await;
+        assert!(is_overloaded(&blocked));
+        assert_eq!(start.elapsed(), Duration::from_millis(30));
+        assert_eq!(queue.stats().timed_out, 1);
+
+        // 工作任务在 100ms 处取走 w1 腾出空位：截止时间内入队
+        let (queue, tickets) =
+            saturated(ShedPolicy::BlockWithDeadline(Duration::from_millis(200))).await;
+        let start = Instant::now();
+        let third = queue
+            .submit("w2", vec![], ConsistencyLevel::Strong)
+            .await
+            .unwrap();
+        assert_eq!(start.elapsed(), ACK_DELAY);
+        for ticket in tickets.into_iter().chain([third]) {
+            assert!(ticket.wait().await.is_ok());
+        }
+        assert_eq!(queue.stats().shed(), 0);
+    }
+}
@@ -0,0 +1,108 @@
+// 测试目的：重试与熔断在同一循环中协作
+// - 不变量：1) 重试途中熔断器打开后不再尝试，返回 CircuitOpen 并带上最后一次错误；熔断器
+//              打开期间的新请求不调用操作；
+//           2) 熔断器保持关闭时按指数退避重试，用尽后返回 Exhausted；中途成功则返回结果。
+use distributed::{
+    CircuitBreaker, CircuitConfig, CircuitState, RetryBreakerError, RetryPolicy, RetryWithBreaker,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn policy(max_retries: usize) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        retry_on_empty: false,
+        backoff_base_ms: Some(10),
+    }
+}
+
+fn breaker(error_threshold: u32) -> CircuitBreaker {
+    CircuitBreaker::new(CircuitConfig {
+        error_threshold,
+        open_ms: 60_000,
+    })
+}
+
+fn recording_executor() -> (RetryWithBreaker, Arc<Mutex<Vec<Duration>>>) {
+    let sleeps = Arc::new(Mutex::new(Vec::new()));
+    let recorded = sleeps.clone();
+    let executor = RetryWithBreaker::new().with_sleep(move |d| recorded.lock().unwrap().push(d));
+    (executor, sleeps)
+}
+
+#[test]
+fn circuit_tripping_mid_retry_stops_further_attempts() {
+    let (executor, sleeps) = recording_executor();
+    let mut cb = breaker(3);
+    let mut calls = 0;
+    let result: Result<(), _> = executor.execute(
+        |attempt| {
+            calls += 1;
+            Err(format!("boom {attempt}"))
+        },
+        &policy(5),
+        &mut cb,
+    );
+    match result {
+        Err(RetryBreakerError::CircuitOpen {
+            attempts,
+            last_error,
+        }) => {
+            assert_eq!(attempts, 3);
+            assert_eq!(last_error.as_deref(), Some("boom 2"));
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    assert_eq!(calls, 3);
+    assert_eq!(cb.state(), CircuitState::Open);
+    // 打开后不再退避：只有前两次失败之后等待过
+    assert_eq!(
+        *sleeps.lock().unwrap(),
+        [Duration::from_millis(10), Duration::from_millis(20)]
+    );
+
+    // 熔断器打开期间的请求直接被拒绝
+    let err = executor
+        .execute(
+            |_| -> Result<(), String> { panic!("must not run") },
+            &policy(5),
+            &mut cb,
+        )
+        .unwrap_err();
+    assert_eq!(err.attempts(), 0);
+    assert!(err.last_error().is_none());
+    assert_eq!(err.to_string(), "circuit open after 0 attempt(s)");
+}
+
+#[test]
+fn closed_circuit_retries_with_backoff_until_exhausted_or_success() {
+    let (executor, sleeps) = recording_executor();
+    let mut cb = breaker(10);
+    let err = executor
+        .execute(|_| -> Result<(), &str> { Err("down") }, &policy(2), &mut cb)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        RetryBreakerError::Exhausted {
+            attempts: 3,
+            last_error: "down"
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "retries exhausted after 3 attempt(s): down"
+    );
+    assert_eq!(sleeps.lock().unwrap().len(), 2);
+    assert_eq!(cb.state(), CircuitState::Closed);
+
+    // 第二次尝试成功，熔断器记为成功并清零错误计数
+    let value = executor
+        .execute(
+            |attempt| if attempt == 0 { Err("flaky") } else { Ok(42) },
+            &policy(2),
+            &mut cb,
+        )
+        .unwrap();
+    assert_eq!(value, 42);
+    assert_eq!(cb.state(), CircuitState::Closed);
+}
@@ -10,3 +10,39 @@ fn route_owner_exists() {
     let owner = router.owner_of(&"k-01");
     assert!(owner.is_some());
 }
+
+// 测试目的：主副本与副本集查询
+// - 不变量：1) 主副本等于 owner_of，其余副本不含主副本且合计为 replica_count 个不同节点；
+//           2) 偏好节点在副本集中时返回偏好节点，否则回退到主副本。
+#[test]
+fn preferred_replica_falls_back_to_primary() {
+    let mut ring = ConsistentHashRing::new(32);
+    for n in ["n1", "n2", "n3", "n4"] {
+        ring.add_node(n);
+    }
+    let router = HashRingRouter::new(ring);
+    for key in ["k-01", "k-02", "k-03"] {
+        let (primary, replicas) = router.primary_and_replicas(&key, 3);
+        let primary = primary.unwrap();
+        assert_eq!(Some(primary.clone()), router.owner_of(&key));
+        assert_eq!(replicas.len(), 2);
+        assert!(!replicas.contains(&primary));
+
+        for replica in &replicas {
+            assert_eq!(
+                router.preferred_replica(&key, 3, replica).as_ref(),
+                Some(replica)
+            );
+        }
+        let outsider = ["n1", "n2", "n3", "n4"]
+            .into_iter()
+            .find(|n| *n != primary && !replicas.iter().any(|r| r == n))
+            .unwrap();
+        assert_eq!(
+            router.preferred_replica(&key, 3, outsider),
+            Some(primary.clone())
+        );
+        assert_eq!(router.preferred_replica(&key, 3, "n9"), Some(primary));
+    }
+    assert_eq!(router.primary_and_replicas(&"k", 0).1, Vec::<String>::new());
+}
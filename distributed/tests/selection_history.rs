@@ -0,0 +1,80 @@
+// 测试目的：负载均衡选择历史
+// - 不变量：1) 轮询均衡器 100 次选择后，summarize 与轮询的均匀分布一致；历史只保留最近
+//              capacity 条记录，原因为 RoundRobin；
+//           2) 一致性哈希均衡器 100 次按键选择后，summarize 与实际返回的实例分布一致，记录携带
+//              请求键的哈希，同一键的哈希相同。
+use distributed::service_discovery::ServiceInstance;
+use distributed::{ConsistentHashBalancer, RoundRobinBalancer, SelectionReason};
+use std::collections::HashMap;
+
+fn fleet(n: u16) -> Vec<ServiceInstance> {
+    (0..n)
+        .map(|i| {
+            ServiceInstance::new(
+                format!("s{i}"),
+                "api".to_string(),
+                format!("127.0.0.1:{}", 9000 + i).parse().unwrap(),
+                HashMap::new(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn round_robin_history_matches_uniform_distribution() {
+    let mut balancer = RoundRobinBalancer::new(fleet(4)).with_history(100);
+    for _ in 0..100 {
+        balancer.select_server().unwrap();
+    }
+    let history = balancer.history().unwrap();
+    assert_eq!(history.len(), 100);
+    let expected: HashMap<String, usize> = (0..4).map(|i| (format!("s{i}"), 25)).collect();
+    assert_eq!(history.summarize(), expected);
+    assert!(
+        history
+            .records()
+            .all(|r| r.reason == SelectionReason::RoundRobin)
+    );
+    let ids: Vec<&str> = history
+        .records()
+        .take(5)
+        .map(|r| r.selected_id.as_str())
+        .collect();
+    assert_eq!(ids, ["s0", "s1", "s2", "s3", "s0"]);
+
+    // 容量不足时只保留最近的记录
+    let mut bounded = RoundRobinBalancer::new(fleet(3)).with_history(10);
+    for _ in 0..100 {
+        bounded.select_server().unwrap();
+    }
+    let history = bounded.history().unwrap();
+    assert_eq!(history.len(), 10);
+    assert_eq!(history.records().next().unwrap().selected_id, "s0");
+    assert_eq!(history.summarize().values().sum::<usize>(), 10);
+    assert!(RoundRobinBalancer::new(fleet(3)).history().is_none());
+}
+
+#[test]
+fn consistent_hash_history_records_key_hashes() {
+    let balancer = ConsistentHashBalancer::new(fleet(5), 50).with_history(128);
+    let mut expected: HashMap<String, usize> = HashMap::new();
+    for i in 0..100 {
+        let server = balancer.select_server(&format!("user-{}", i % 40)).unwrap();
+        *expected.entry(server.id.clone()).or_default() += 1;
+    }
+    let history = balancer.history().unwrap();
+    assert_eq!(history.len(), 100);
+    assert_eq!(history.summarize(), expected);
+    assert!(expected.len() > 1, "{expected:?}");
+
+    // 第 i 与第 i+40 次选择使用同一个键
+    let records: Vec<_> = history.records().collect();
+    for i in 0..60 {
+        let (a, b) = (records[i], records[i + 40]);
+        assert!(matches!(a.reason, SelectionReason::ConsistentHash { .. }));
+        assert_eq!(a.reason, b.reason);
+        assert_eq!(a.selected_id, b.selected_id);
+        assert!(a.timestamp <= b.timestamp);
+    }
+    assert_ne!(records[0].reason, records[1].reason);
+}
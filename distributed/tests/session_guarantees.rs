@@ -0,0 +1,200 @@
+// 测试目的：会话保证（read-your-writes / monotonic reads）
+// - 不变量：
+//   1) 写入成功后同一会话的仲裁读不会观察到早于该写入的状态；
+//   2) 落后副本在追平之前不参与该会话的读取；
+//   3) 会话读到的时间戳不回退；
+//   4) 异步仲裁读在限定时间内等待落后副本追平：追平后读取成功，无人追平则超时返回错误。
+use distributed::consistency::ConsistencyLevel;
+use distributed::core::{ClientSession, SessionError};
+use distributed::replication::LocalReplicator;
+use distributed::topology::ConsistentHashRing;
+use std::collections::HashMap;
+
+fn build(nodes: &[&str]) -> (LocalReplicator<u64>, Vec<String>) {
+    let mut ring = ConsistentHashRing::new(8);
+    let mut v = Vec::new();
+    for n in nodes {
+        ring.add_node(n);
+        v.push((*n).to_string());
+    }
+    (LocalReplicator::new(ring, v.clone()), v)
+}
+
+#[test]
+fn ensure_read_your_writes_rejects_stale_watermark() {
+    let mut s = ClientSession::new();
+    s.record_write(5);
+    assert!(s.ensure_read_your_writes(5).is_ok());
+    assert_eq!(
+        s.ensure_read_your_writes(4),
+        Err(SessionError::ReadYourWrites {
+            watermark: 4,
+            required: 5
+        })
+    );
+    s.record_read(7);
+    assert!(s.ensure_monotonic_reads(6).is_err());
+    assert!(s.ensure_readable(7).is_ok());
+}
+
+#[test]
+fn read_after_write_never_observes_older_state() {
+    let (mut r, nodes) = build(&["n1", "n2", "n3"]);
+    // 各副本的 (时间戳, 值)
+    let mut store: HashMap<String, (u64, u64)> = HashMap::new();
+
+    for round in 0..30u64 {
+        // 每轮有一个副本错过写入（确认数仍满足多数派）
+        let lagging = &nodes[(round % 3) as usize];
+        r.successes.clear();
+        r.successes.insert(lagging.clone(), false);
+
+        let mut session = ClientSession::new();
+        let ts = r
+            .replicate_with_session(&nodes, round, ConsistencyLevel::Quorum, &mut session)
+            .unwrap();
+        for n in &nodes {
+            if r.watermark(n) == ts {
+                store.insert(n.clone(), (ts, round));
+            }
+        }
+
+        // 落后副本恢复在线但尚未追平
+        r.successes.clear();
+        for level in [ConsistencyLevel::Eventual, ConsistencyLevel::Quorum] {
+            let (seen_ts, value) = r
+                .quorum_read(&nodes, level, &mut session, |n| {
+                    store.get(n).copied().unwrap_or((0, 0))
+                })
+                .unwrap();
+            assert!(
+                seen_ts >= ts,
+                "round {round}: read ts {seen_ts} < write ts {ts}"
+            );
+            assert_eq!(value, round);
+        }
+    }
+}
+
+#[test]
+fn lagging_replicas_are_skipped_until_caught_up() {
+    let (mut r, nodes) = build(&["n1", "n2", "n3"]);
+    let mut session = ClientSession::new();
+    r.successes.insert("n3".into(), false);
+    r.successes.insert("n2".into(), false);
+    // Eventual：只有 n1 确认
+    let ts = r
+        .replicate_with_session(&nodes, 1u64, ConsistencyLevel::Eventual, &mut session)
+        .unwrap();
+    r.successes.clear();
+
+    // 多数派读：仅 n1 追平，1/2 不足
+    assert!(
+        r.quorum_read(
+            &nodes,
+            ConsistencyLevel::Quorum,
+            &mut session,
+            str::to_string
+        )
+        .is_err()
+    );
+    // n1 宕机后，剩余副本均落后
+    r.successes.insert("n1".into(), false);
+    assert!(
+        r.quorum_read(
+            &nodes,
+            ConsistencyLevel::Eventual,
+            &mut session,
+            str::to_string
+        )
+        .is_err()
+    );
+
+    // n2 追平后可被读取，会话读时间戳随之推进
+    r.advance_watermark("n2", ts);
+    let node = r
+        .quorum_read(
+            &nodes,
+            ConsistencyLevel::Eventual,
+            &mut session,
+            str::to_string,
+        )
+        .unwrap();
+    assert_eq!(node, "n2");
+    assert_eq!(session.last_read_timestamp, ts);
+
+    // 新会话不受旧会话约束
+    let mut fresh = ClientSession::new();
+    assert!(
+        r.quorum_read(&nodes, ConsistencyLevel::Quorum, &mut fresh, str::to_string)
+            .is_ok()
+    );
+}
+
+#[cfg(feature = "runtime-tokio")]
+mod catch_up {
+    use super::build;
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::core::ClientSession;
+    use distributed::replication::AsyncLocalReplicator;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[tokio::test(start_paused = true)]
+    async fn quorum_read_waits_for_a_lagging_replica_to_catch_up() {
+        let (r, nodes) = build(&["n1", "n2", "n3"]);
+        let repl = Arc::new(AsyncLocalReplicator::new(r));
+        let mut session = ClientSession::new();
+        // Eventual：只有 n1 确认，n2、n3 恢复在线但尚未追平
+        repl.mark_node_failed("n2");
+        repl.mark_node_failed("n3");
+        let ts = repl
+            .replicate_with_session(&nodes, 1u64, ConsistencyLevel::Eventual, &mut session)
+            .await
+            .unwrap();
+        repl.mark_node_recovered("n2");
+        repl.mark_node_recovered("n3");
+
+        // 无人追平：等满限定时间后失败
+        let start = Instant::now();
+        let wait = Duration::from_millis(50);
+        let read = repl
+            .quorum_read(
+                &nodes,
+                ConsistencyLevel::Quorum,
+                &mut session,
+                wait,
+                str::to_string,
+            )
+            .await;
+        assert!(read.is_err());
+        assert!(start.elapsed() >= wait);
+
+        // n2 在 30ms 后追平，等待中的读随即成功
+        let catching_up = {
+            let repl = repl.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                repl.advance_watermark("n2", ts);
+            })
+        };
+        let start = Instant::now();
+        let node = repl
+            .quorum_read(
+                &nodes,
+                ConsistencyLevel::Quorum,
+                &mut session,
+                Duration::from_secs(1),
+                str::to_string,
+            )
+            .await
+            .unwrap();
+        assert!(["n1", "n2"].contains(&node.as_str()));
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(session.last_read_timestamp, ts);
+        assert_eq!(repl.watermark("n2"), ts);
+        catching_up.await.unwrap();
+    }
+}
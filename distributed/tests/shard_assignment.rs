@@ -0,0 +1,72 @@
+// 测试目的：拓扑的分片分配与节点增减触发的再均衡
+// - 不变量：1) 轮转分配后各节点承载的分片数至多相差 1；3 节点 9 分片的集群加入第 4 个节点时，
+//              计划恰好包含 3 次迁移，每个原有节点各迁出一个分片到新节点，其余分片原地不动；
+//           2) 移除节点时只迁出该节点上的副本，迁移后仍均衡，且同一分片的副本始终落在不同节点上。
+use distributed::core::{ClusterTopology, NodeId, ShardId};
+use std::collections::{HashMap, HashSet};
+
+fn nodes(names: &[&str]) -> Vec<NodeId> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+#[test]
+fn adding_a_node_to_three_nodes_with_nine_shards_moves_three_shards() {
+    let mut topology = ClusterTopology::new(9);
+    let assignment = topology.assign_shards(nodes(&["a", "b", "c"]));
+    assert_eq!(assignment.len(), 9);
+    assert!(topology.is_balanced());
+    assert!(topology.shards_per_node().values().all(|&n| n == 3));
+    let before = topology.node_for_shard.clone();
+
+    let plan = topology.rebalance(nodes(&["d"]), Vec::new());
+    assert_eq!(plan.len(), 3, "{plan:?}");
+    assert_eq!(plan.moves_to("d").count(), 3);
+    let sources: HashSet<&str> = plan.moves.iter().map(|m| m.from.as_str()).collect();
+    assert_eq!(sources, HashSet::from(["a", "b", "c"]));
+    for m in &plan.moves {
+        assert_eq!(before[&m.shard], [m.from.as_str()]);
+        assert_eq!(topology.node_for_shard[&m.shard], ["d".to_string()]);
+    }
+    let moved: HashSet<ShardId> = plan.moves.iter().map(|m| m.shard).collect();
+    for shard in topology.shards().filter(|s| !moved.contains(s)) {
+        assert_eq!(topology.node_for_shard[&shard], before[&shard]);
+    }
+    assert!(topology.is_balanced());
+    assert_eq!(topology.shards_per_node()["d"], 3);
+
+    // 已均衡时再次触发不产生迁移
+    assert!(topology.rebalance(Vec::new(), Vec::new()).is_empty());
+}
+
+#[test]
+fn removing_a_node_moves_only_its_replicas_and_keeps_replicas_apart() {
+    let mut topology = ClusterTopology::new(12).with_replication_factor(2);
+    topology.assign_shards(nodes(&["a", "b", "c", "d"]));
+    assert!(topology.is_balanced());
+    let before = topology.node_for_shard.clone();
+    let on_b = before
+        .values()
+        .filter(|o| o.contains(&"b".to_string()))
+        .count();
+
+    let plan = topology.rebalance(Vec::new(), nodes(&["b"]));
+    assert_eq!(plan.len(), on_b);
+    assert!(plan.moves.iter().all(|m| m.from == "b"));
+    assert!(topology.is_balanced(), "{:?}", topology.shards_per_node());
+    let counts: HashMap<NodeId, usize> = topology.shards_per_node().into_iter().collect();
+    assert_eq!(counts.len(), 3);
+    assert!(counts.values().all(|&n| n == 8));
+    for owners in topology.node_for_shard.values() {
+        assert_eq!(owners.len(), 2);
+        assert_ne!(owners[0], owners[1]);
+        assert!(!owners.contains(&"b".to_string()));
+    }
+
+    // 加入节点与移除节点可以同时发生
+    let plan = topology.rebalance(nodes(&["e", "f"]), nodes(&["a"]));
+    assert_eq!(plan.moves.iter().filter(|m| m.from == "a").count(), 8);
+    assert!(topology.is_balanced(), "{:?}", topology.shards_per_node());
+    for owners in topology.node_for_shard.values() {
+        assert_ne!(owners[0], owners[1]);
+    }
+}
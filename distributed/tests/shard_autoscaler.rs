@@ -0,0 +1,155 @@
+// 测试目的：热点分片自动分裂
+// - 不变量：
+//   1) 持续过热的分片恰好分裂一次，分裂点为热键的加权中位数，上半区间经限速搬迁到最空闲的
+//      节点，之后负载在节点间均衡且不再分裂（负载未回落到重新武装线以下）；
+//   2) dry-run 只报告一次将要执行的分裂，分区与归属保持不变。
+use distributed::DistributedError;
+use distributed::ShardId;
+use distributed::partitioning::{
+    AutoscaleAction, AutoscalerConfig, DrainProgress, RangePartitioner, ShardAutoscaler,
+    ShardDrain, ShardMove,
+};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const SHARD_BYTES: u64 = 40_000;
+
+#[derive(Default)]
+struct MockDrain {
+    moved: HashMap<ShardId, u64>,
+    calls: usize,
+}
+
+impl ShardDrain for MockDrain {
+    fn drain(&mut self, mv: &ShardMove, max_bytes: u64) -> Result<DrainProgress, DistributedError> {
+        self.calls += 1;
+        let moved = self.moved.entry(mv.shard).or_insert(0);
+        let bytes = max_bytes.min(SHARD_BYTES - *moved);
+        *moved += bytes;
+        Ok(DrainProgress {
+            bytes,
+            done: *moved == SHARD_BYTES,
+        })
+    }
+}
+
+fn scaler(dry_run: bool) -> ShardAutoscaler {
+    let mut partitioner = RangePartitioner::new();
+    partitioner.split_shard(ShardId(0), "m").unwrap();
+    let nodes = ["n1", "n2", "n3"].map(String::from).to_vec();
+    ShardAutoscaler::new(partitioner, nodes).with_config(AutoscalerConfig {
+        max_ops_per_sec: 800.0,
+        sustain: Duration::from_secs(2),
+        cooldown: Duration::from_secs(30),
+        move_bytes_per_sec: 10_000,
+        dry_run,
+        ..AutoscalerConfig::default()
+    })
+}
+
+/// 每 100ms：20 个热键各 6 次（1200 ops/s 落在 ShardId(0)），5 个冷键各 2 次
+fn simulate(scaler: &mut ShardAutoscaler, seconds: u64) -> Vec<(Duration, AutoscaleAction)> {
+    let t0 = Instant::now();
+    let mut drain = MockDrain::default();
+    let mut actions = Vec::new();
+    for tick in 0..seconds * 10 {
+        let elapsed = Duration::from_millis(tick * 100);
+        let now = t0 + elapsed;
+        for i in 0..20 {
+            for _ in 0..6 {
+                scaler.record_at(&format!("a{i:02}"), 100, now);
+            }
+        }
+        for i in 0..5 {
+            scaler.record_at(&format!("x{i}"), 100, now);
+            scaler.record_at(&format!("x{i}"), 100, now);
+        }
+        let mut step = scaler.evaluate(now);
+        step.extend(scaler.drive(now, &mut drain).unwrap());
+        actions.extend(step.into_iter().map(|a| (elapsed, a)));
+    }
+    actions
+}
+
+#[test]
+fn hot_shard_splits_once_and_load_rebalances() {
+    let mut scaler = scaler(false);
+    let actions = simulate(&mut scaler, 20);
+
+    let splits: Vec<_> = actions
+        .iter()
+        .filter(|(_, a)| matches!(a, AutoscaleAction::Split { .. }))
+        .collect();
+    assert_eq!(splits.len(), 1, "{actions:?}");
+    let (
+        split_at,
+        AutoscaleAction::Split {
+            shard,
+            at,
+            new_shard,
+            plan,
+            dry_run,
+        },
+    ) = splits[0]
+    else {
+        unreachable!()
+    };
+    assert!(*split_at >= Duration::from_secs(2));
+    assert_eq!(
+        (*shard, at.as_str(), *new_shard, *dry_run),
+        (ShardId(0), "a10", ShardId(2), false)
+    );
+    assert_eq!(
+        plan.moves,
+        [ShardMove {
+            shard: ShardId(2),
+            start: "a10".to_string(),
+            end: Some("m".to_string()),
+            from: "n1".to_string(),
+            to: "n3".to_string(),
+        }]
+    );
+
+    // 40KB 以 10KB/s 搬迁：首批用掉 1 秒的突发额度，其余约需 3 秒
+    let (done_at, _) = actions
+        .iter()
+        .find(|(_, a)| matches!(a, AutoscaleAction::MoveCompleted { .. }))
+        .unwrap();
+    assert!(
+        *done_at >= *split_at + Duration::from_millis(2_900),
+        "{done_at:?}"
+    );
+    assert_eq!(scaler.node_of(ShardId(2)), Some("n3"));
+    assert_eq!(scaler.pending_moves().count(), 0);
+
+    let loads = scaler.node_loads();
+    for node in ["n1", "n3"] {
+        assert!((loads[node] - 600.0).abs() < 1.0, "{loads:?}");
+    }
+    assert!(
+        scaler
+            .metrics()
+            .get_all_metrics()
+            .iter()
+            .any(|m| m.name == "shard_2_ops_total")
+    );
+}
+
+#[test]
+fn dry_run_only_reports_intended_split() {
+    let mut scaler = scaler(true);
+    let actions = simulate(&mut scaler, 10);
+    assert_eq!(actions.len(), 1, "{actions:?}");
+    assert!(matches!(
+        &actions[0].1,
+        AutoscaleAction::Split {
+            shard: ShardId(0),
+            new_shard: ShardId(2),
+            dry_run: true,
+            ..
+        }
+    ));
+    assert_eq!(scaler.partitioner().shards(), [ShardId(0), ShardId(1)]);
+    assert_eq!(scaler.pending_moves().count(), 0);
+    assert_eq!(scaler.node_of(ShardId(0)), Some("n1"));
+}
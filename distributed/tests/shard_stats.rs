@@ -0,0 +1,44 @@
+// 测试目的：按分片的请求与字节统计
+// - 不变量：1) 读计入 read_count / bytes_out，写计入 write_count / bytes_in；
+//           2) hottest_shards 按读写总数降序返回前 N 个分片，并发记录不丢计数。
+use distributed::core::{ClusterTopology, ShardId, ShardStats};
+use std::thread;
+
+#[test]
+fn counts_reads_writes_and_ranks_hot_shards() {
+    let stats = ShardStats::for_topology(&ClusterTopology::new(4));
+    stats.record_read(ShardId(1), 100);
+    stats.record_write(ShardId(1), 40);
+    stats.record_write(ShardId(3), 10);
+    // 拓扑之外的分片首次出现时登记
+    stats.record_read(ShardId(9), 1);
+
+    let counters = stats
+        .with_counters(ShardId(1), |c| {
+            (c.read_count(), c.write_count(), c.bytes_out(), c.bytes_in())
+        })
+        .unwrap();
+    assert_eq!(counters, (1, 1, 100, 40));
+    assert_eq!(stats.shard_count(), 5);
+    assert_eq!(
+        stats.hottest_shards(3),
+        [(ShardId(1), 2), (ShardId(3), 1), (ShardId(9), 1)]
+    );
+
+    thread::scope(|s| {
+        for t in 0..4u64 {
+            let stats = &stats;
+            s.spawn(move || {
+                for _ in 0..10_000 {
+                    stats.record_read(ShardId(t), 8);
+                }
+            });
+        }
+    });
+    assert_eq!(stats.total_ops(), 40_004);
+    assert_eq!(stats.hottest_shards(1), [(ShardId(1), 10_002)]);
+    assert_eq!(
+        stats.with_counters(ShardId(0), |c| c.bytes_out()),
+        Some(80_000)
+    );
+}
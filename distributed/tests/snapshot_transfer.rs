@@ -0,0 +1,247 @@
+// 测试目的：分块、可续传的快照传输
+// - 不变量：1) 经模拟网络发送快照时在 60% 处断开连接，重连后从最后确认的块续传：已确认的块不重发，
+//              跟随者最终安装的数据与整体哈希一致；
+//           2) 校验和错误的块不被追加，整体哈希不符的快照被丢弃而不安装；分片搬迁在投递失败后续传；
+//           3) 不晚于本地提交点的快照被忽略，提交/应用进度与日志不回退；
+//           4) 更高任期的快照使候选人退为跟随者。
+use distributed::DistributedError;
+use distributed::ShardId;
+use distributed::consensus::raft::{
+    AppendEntriesReq, AppendEntriesResp, InstallSnapshotReq, InstallSnapshotResp, LogIndex,
+    MinimalRaft, RaftNode, RaftState, RaftTransport, RequestVoteReq, RequestVoteResp, Snapshot,
+    Term,
+};
+use distributed::consensus::snapshot_transfer::{
+    ChunkOutcome, SnapshotReceiver, SnapshotSender, chunk_checksum, install_snapshot_chunked,
+    snapshot_hash,
+};
+use distributed::partitioning::{ChunkedShardDrain, DrainProgress, ShardDrain, ShardMove};
+use distributed::simnet::{SimNetwork, SimRaftTransport};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 第 `kill_after` 次 InstallSnapshot 应答后注销目标端点，模拟连接被切断
+struct KillAfter {
+    inner: SimRaftTransport,
+    net: SimNetwork,
+    kill_after: usize,
+    calls: AtomicUsize,
+}
+
+impl RaftTransport<u64> for KillAfter {
+    fn append_entries(
+        &self,
+        target: &str,
+        req: AppendEntriesReq<(Term, u64)>,
+    ) -> impl Future<Output = Result<AppendEntriesResp, DistributedError>> + Send {
+        RaftTransport::<u64>::append_entries(&self.inner, target, req)
+    }
+
+    fn request_vote(
+        &self,
+        target: &str,
+        req: RequestVoteReq,
+    ) -> impl Future<Output = Result<RequestVoteResp, DistributedError>> + Send {
+        RaftTransport::<u64>::request_vote(&self.inner, target, req)
+    }
+
+    fn install_snapshot(
+        &self,
+        target: &str,
+        req: InstallSnapshotReq,
+    ) -> impl Future<Output = Result<InstallSnapshotResp, DistributedError>> + Send {
+        let reply = RaftTransport::<u64>::install_snapshot(&self.inner, target, req);
+        if self.calls.fetch_add(1, Ordering::SeqCst) + 1 == self.kill_after {
+            self.net.unregister(target);
+        }
+        reply
+    }
+}
+
+#[test]
+fn dropped_connection_resumes_from_last_acked_chunk() {
+    let net = SimNetwork::new(7);
+    let follower = Arc::new(Mutex::new(MinimalRaft::<u64>::new()));
+    SimRaftTransport::serve(&net, "n2", follower.clone());
+    net.register("n1", |_, _| None);
+    let transport = KillAfter {
+        inner: SimRaftTransport::new(net.clone(), "n1"),
+        net: net.clone(),
+        kill_after: 6,
+        calls: AtomicUsize::new(0),
+    };
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let snapshot = Snapshot {
+        last_included_index: LogIndex(42),
+        last_included_term: Term(3),
+        data: data.clone(),
+    };
+    let mut sender = SnapshotSender::new(data.clone())
+        .with_chunk_size(1_000)
+        .with_max_in_flight(3);
+    assert_eq!(sender.total_chunks(), 10);
+
+    let send = |sender: &mut SnapshotSender| {
+        net.block_on(install_snapshot_chunked::<u64, _>(
+            &transport,
+            "n2",
+            Term(3),
+            "n1",
+            &snapshot,
+            sender,
+        ))
+    };
+    assert!(send(&mut sender).is_err());
+    assert_eq!(sender.acked_offset(), 6_000);
+    assert!(follower.lock().unwrap().snapshot().is_none());
+
+    SimRaftTransport::serve(&net, "n2", follower.clone());
+    let resp = send(&mut sender).unwrap();
+    assert!(sender.is_complete());
+    assert_eq!(resp.next_offset, 10_000);
+
+    let follower = follower.lock().unwrap();
+    let installed = follower.snapshot().unwrap();
+    assert_eq!(installed.last_included_index, LogIndex(42));
+    assert_eq!(snapshot_hash(&installed.data), snapshot_hash(&data));
+    let received = follower.snapshot_receiver().stats();
+    assert_eq!(received.accepted_chunks(), 10);
+    assert_eq!(received.duplicate_chunks(), 0);
+    assert_eq!(received.completed(), 1);
+    // 只有断线时在途的 3 块被重发
+    assert_eq!(sender.stats().chunks_sent(), 13);
+    assert_eq!(sender.stats().resent_chunks(), 3);
+}
+
+#[test]
+fn corrupted_chunks_and_hash_mismatch_are_rejected() {
+    let mut raft = MinimalRaft::<u64>::new();
+    let req = |offset: u64, data: &[u8], checksum: u64, hash: Option<String>| InstallSnapshotReq {
+        term: Term(1),
+        leader_id: "n1".into(),
+        last_included_index: LogIndex(5),
+        last_included_term: Term(1),
+        offset,
+        data: data.to_vec(),
+        done: hash.is_some(),
+        checksum: Some(checksum),
+        snapshot_hash: hash,
+    };
+    let resp = raft
+        .handle_install_snapshot(req(0, b"abcd", chunk_checksum(b"abcd"), None))
+        .unwrap();
+    assert_eq!(resp.next_offset, 4);
+    // 传输中损坏：校验和不符，进度不变
+    let resp = raft
+        .handle_install_snapshot(req(4, b"eXgh", chunk_checksum(b"efgh"), None))
+        .unwrap();
+    assert_eq!(resp.next_offset, 4);
+    let wrong_hash = Some(snapshot_hash(b"something else"));
+    let err = raft
+        .handle_install_snapshot(req(4, b"efgh", chunk_checksum(b"efgh"), wrong_hash))
+        .unwrap_err();
+    assert!(err.to_string().contains("hash mismatch"), "{err}");
+    assert!(raft.snapshot().is_none());
+    let stats = raft.snapshot_receiver().stats();
+    assert_eq!((stats.rejected_chunks(), stats.hash_mismatches()), (1, 1));
+
+    // 搬迁分片：第 3 块投递失败，下一次 drain 从已确认的偏移续传
+    let mv = ShardMove {
+        shard: ShardId(1),
+        start: "a".into(),
+        end: None,
+        from: "n1".into(),
+        to: "n2".into(),
+    };
+    let target = Mutex::new(SnapshotReceiver::new());
+    let (deliveries, installed) = (AtomicUsize::new(0), Mutex::new(None));
+    let mut drain = ChunkedShardDrain::new(
+        |_: &ShardMove| Ok(b"0123456789".to_vec()),
+        |mv: &ShardMove, chunk| {
+            if deliveries.fetch_add(1, Ordering::SeqCst) == 2 {
+                return Err(DistributedError::Network("connection reset".into()));
+            }
+            match target.lock().unwrap().accept(&mv.to, chunk)? {
+                ChunkOutcome::Progress(next) => Ok(next),
+                ChunkOutcome::Complete(data) => {
+                    let len = data.len() as u64;
+                    *installed.lock().unwrap() = Some(data);
+                    Ok(len)
+                }
+            }
+        },
+    )
+    .with_chunk_size(3);
+    assert!(drain.drain(&mv, 100).is_err());
+    assert_eq!(drain.transfer(ShardId(1)).unwrap().acked_offset(), 6);
+    assert_eq!(
+        drain.drain(&mv, 100).unwrap(),
+        DrainProgress {
+            bytes: 4,
+            done: true
+        }
+    );
+    assert_eq!(
+        installed.lock().unwrap().as_deref(),
+        Some(&b"0123456789"[..])
+    );
+    assert_eq!(target.lock().unwrap().stats().duplicate_chunks(), 0);
+}
+
+fn single_chunk(term: u64, last_included_index: u64, data: &[u8]) -> InstallSnapshotReq {
+    InstallSnapshotReq {
+        term: Term(term),
+        leader_id: "n1".into(),
+        last_included_index: LogIndex(last_included_index),
+        last_included_term: Term(1),
+        offset: 0,
+        data: data.to_vec(),
+        done: true,
+        checksum: Some(chunk_checksum(data)),
+        snapshot_hash: Some(snapshot_hash(data)),
+    }
+}
+
+#[test]
+fn stale_snapshot_is_ignored() {
+    let mut raft = MinimalRaft::<u64>::new();
+    raft.handle_append_entries(AppendEntriesReq {
+        term: Term(1),
+        leader_id: "n1".into(),
+        prev_log_index: LogIndex(0),
+        prev_log_term: Term(0),
+        entries: vec![1, 2, 3, 4],
+        leader_commit: LogIndex(3),
+    })
+    .unwrap();
+
+    raft.handle_install_snapshot(single_chunk(1, 2, b"old"))
+        .unwrap();
+    assert!(raft.snapshot().is_none());
+    assert_eq!(raft.commit_index(), LogIndex(3));
+    assert_eq!(raft.last_applied(), LogIndex(3));
+    assert_eq!(raft.last_log_index(), LogIndex(4));
+
+    // 越过提交点的快照照常安装，并保留边界之后任期一致的条目
+    raft.handle_install_snapshot(single_chunk(1, 4, b"new"))
+        .unwrap();
+    assert_eq!(raft.snapshot().unwrap().data, b"new");
+    assert_eq!(raft.commit_index(), LogIndex(4));
+    assert_eq!(raft.last_applied(), LogIndex(4));
+}
+
+#[test]
+fn higher_term_snapshot_steps_candidate_down() {
+    let mut raft = MinimalRaft::<u64>::new();
+    raft.start_election("n2");
+    assert_eq!(raft.state(), RaftState::Candidate);
+
+    let resp = raft
+        .handle_install_snapshot(single_chunk(3, 5, b"state"))
+        .unwrap();
+    assert_eq!(resp.term, Term(3));
+    assert_eq!(raft.current_term(), Term(3));
+    assert_eq!(raft.state(), RaftState::Follower);
+    assert_eq!(raft.commit_index(), LogIndex(5));
+}
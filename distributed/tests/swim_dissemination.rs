@@ -0,0 +1,66 @@
+// 测试目的：SWIM 捎带传播缓冲
+// - 不变量：1) 传播次数少的更新优先被捎带，新入队的更新排在已传播过的更新之前；传播满
+//              k × ⌈log2 n⌉ 次的更新被淘汰；
+//           2) 任意字节预算下，单次取出的条目编码长度之和不超过预算与 max_payload_bytes。
+use distributed::{DisseminationBuffer, GossipEntry, SwimMemberState};
+
+fn entry(node: &str, incarnation: u64) -> GossipEntry {
+    GossipEntry::new(node, SwimMemberState::Suspect, incarnation)
+}
+
+#[test]
+fn low_count_entries_are_prioritized_and_evicted_at_limit() {
+    let mut buffer = DisseminationBuffer::new(1_024).with_cluster_size(8);
+    assert_eq!(buffer.dissemination_limit(), 9);
+    for node in ["n1", "n2", "n3"] {
+        buffer.push(entry(node, 1));
+    }
+    let one = entry("n1", 1).encoded_len();
+
+    // 预算只够两条：n1、n2 先发，n3 下一轮优先
+    let first: Vec<String> = buffer
+        .pop_entries(2 * one)
+        .into_iter()
+        .map(|e| e.node_id)
+        .collect();
+    assert_eq!(first, ["n1", "n2"]);
+    buffer.push(entry("n4", 1));
+    let second: Vec<String> = buffer
+        .pop_entries(2 * one)
+        .into_iter()
+        .map(|e| e.node_id)
+        .collect();
+    assert_eq!(second, ["n3", "n4"]);
+
+    // 更新的 incarnation 替换旧条目并从 0 次重新传播，更旧的被忽略
+    buffer.push(entry("n2", 2));
+    buffer.push(entry("n3", 0));
+    let third = buffer.pop_entries(one);
+    assert_eq!((third[0].node_id.as_str(), third[0].incarnation), ("n2", 2));
+    assert_eq!(third[0].dissemination_count, 1);
+
+    for _ in 0..20 {
+        buffer.pop_entries(usize::MAX);
+    }
+    assert!(buffer.is_empty());
+    assert_eq!(buffer.evicted(), 4);
+}
+
+#[test]
+fn byte_budget_is_never_exceeded() {
+    let mut buffer = DisseminationBuffer::new(200)
+        .with_retransmit_multiplier(2)
+        .with_cluster_size(100);
+    for i in 0..50u64 {
+        buffer.push(entry(&format!("node-{}", "x".repeat((i % 13) as usize)), i));
+        buffer.push(entry(&format!("member-{i}"), i));
+    }
+    let mut sent = 0;
+    for budget in (0..400).step_by(7) {
+        let popped = buffer.pop_entries(budget);
+        let bytes: usize = popped.iter().map(GossipEntry::encoded_len).sum();
+        assert!(bytes <= budget.min(200), "{bytes} > {budget}");
+        sent += popped.len();
+    }
+    assert!(sent > 0);
+}
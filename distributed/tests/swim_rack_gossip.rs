@@ -0,0 +1,84 @@
+// 测试目的：优先同机架的 gossip 对象选择
+// - 不变量：
+//   1) 2 机架 6 节点、fanout 3 时，至少 2/3 的 gossip 发往同机架节点，且同机架节点全部入选；
+//   2) 同机架节点不足时用跨机架节点补足，从不选中自身或故障节点；
+//   3) `gossip_round` 按选择结果广播。
+use distributed::swim::{MembershipView, SwimEvent, SwimMemberState, SwimNode, SwimTransport};
+use std::sync::Mutex;
+use std::time::Duration;
+
+fn two_rack_view(me: &str) -> MembershipView {
+    let mut view = MembershipView::new(me.to_string());
+    for i in 0..6 {
+        let node = format!("n{i}");
+        view.local_update(&node, SwimMemberState::Alive, 0);
+        view.set_rack(&node, if i < 3 { "r1" } else { "r2" });
+    }
+    view
+}
+
+#[test]
+fn majority_of_fanout_stays_in_rack() {
+    let mut same_rack = 0;
+    let mut total = 0;
+    for source in 0..6 {
+        let source = format!("n{source}");
+        let mut view = two_rack_view(&source);
+        for round in 0..10 {
+            // 每轮视图版本不同，选择随之轮换
+            view.local_update(&source, SwimMemberState::Alive, round);
+            let peers = view.gossip_peers(&source, 3, true);
+            assert_eq!(peers.len(), 3);
+            assert!(!peers.contains(&source));
+            let local = peers
+                .iter()
+                .filter(|p| view.rack(p) == view.rack(&source))
+                .count();
+            assert_eq!(local, 2, "both rack mates are chosen first");
+            same_rack += local;
+            total += peers.len();
+        }
+    }
+    assert!(same_rack * 3 >= total * 2, "{same_rack}/{total}");
+}
+
+#[test]
+fn falls_back_across_racks_and_skips_faulty() {
+    let mut view = two_rack_view("n0");
+    view.local_update("n1", SwimMemberState::Faulty, 1);
+    let peers = view.gossip_peers("n0", 4, true);
+    assert_eq!(peers.len(), 4);
+    assert_eq!(peers[0], "n2");
+    assert!(!peers.contains(&"n1".to_string()));
+    assert!(peers[1..].iter().all(|p| view.rack(p) == Some("r2")));
+
+    // 不偏好机架时同样排除自身与故障节点
+    let all = view.gossip_peers("n0", 10, false);
+    assert_eq!(all.len(), 4);
+}
+
+struct Recorder(Mutex<Vec<String>>);
+
+impl SwimTransport for Recorder {
+    fn ping(&self, _to: &str) -> bool {
+        true
+    }
+    fn gossip(&self, to: &str, _events: &[SwimEvent]) -> bool {
+        self.0.lock().unwrap().push(to.to_string());
+        true
+    }
+}
+
+#[test]
+fn gossip_round_uses_rack_aware_selector() {
+    let view = two_rack_view("n3");
+    let node = SwimNode::new("n3".into(), Recorder(Mutex::new(Vec::new()))).with_params(
+        Duration::from_millis(1000),
+        Duration::from_millis(5000),
+        2,
+    );
+    assert_eq!(node.gossip_round(&view, true), 2);
+    let mut sent = node.transport.0.lock().unwrap().clone();
+    sent.sort();
+    assert_eq!(sent, ["n4", "n5"]);
+}
@@ -0,0 +1,54 @@
+// 测试目的：全序广播
+// - 不变量：
+//   1) 所有订阅者以相同顺序收到相同消息；
+//   2) 序号从 1 开始无空洞、严格单调；
+//   3) 单副本复制组由领导者自身构成多数派，广播立即提交。
+#[cfg(feature = "runtime-tokio")]
+mod total_order_broadcast {
+    use distributed::consensus::{RaftTotalOrderBroadcast, SequenceNumber, TotalOrderBroadcast};
+
+    #[tokio::test]
+    async fn concurrent_broadcasts_arrive_in_same_order_everywhere() {
+        let replicas = RaftTotalOrderBroadcast::cluster(3, 256);
+        let mut subscribers: Vec<_> = replicas.iter().map(|r| r.subscribe()).collect();
+
+        let mut handles = Vec::new();
+        for i in 0..100u32 {
+            let replica = replicas[i as usize % 3].clone();
+            handles.push(tokio::spawn(async move {
+                replica.broadcast(i.to_le_bytes().to_vec()).await.unwrap()
+            }));
+        }
+        let mut seqs = Vec::new();
+        for h in handles {
+            seqs.push(h.await.unwrap());
+        }
+        seqs.sort();
+        assert_eq!(seqs, (1..=100).map(SequenceNumber).collect::<Vec<_>>());
+
+        let mut orders = Vec::new();
+        for sub in subscribers.iter_mut() {
+            let mut order = Vec::new();
+            for expected in 1..=100u64 {
+                let (seq, payload) = sub.recv().await.unwrap();
+                assert_eq!(seq, SequenceNumber(expected));
+                order.push(payload);
+            }
+            orders.push(order);
+        }
+        assert_eq!(orders[0], orders[1]);
+        assert_eq!(orders[0], orders[2]);
+    }
+
+    #[tokio::test]
+    async fn single_replica_commits_on_its_own() {
+        assert!(RaftTotalOrderBroadcast::cluster(0, 16).is_empty());
+        let replicas = RaftTotalOrderBroadcast::cluster(1, 16);
+        let mut sub = replicas[0].subscribe();
+        for expected in 1..=3u64 {
+            let seq = replicas[0].broadcast(vec![expected as u8]).await.unwrap();
+            assert_eq!(seq, SequenceNumber(expected));
+            assert_eq!(sub.recv().await.unwrap(), (seq, vec![expected as u8]));
+        }
+    }
+}
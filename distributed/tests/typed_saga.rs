@@ -0,0 +1,84 @@
+// 测试目的：带类型 Saga 的输出串联与补偿
+// - 不变量：
+//   1) 三步链中第二步拿到第一步的输出，最终结果是最后一步的输出；
+//   2) 第三步失败时前两步按逆序补偿，且各自收到执行时产生的输出。
+use distributed::DistributedError;
+use distributed::transactions::{TypedSaga, TypedSagaStep};
+use std::sync::{Arc, Mutex};
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// 扣款，返回流水号
+struct Debit(Log);
+impl TypedSagaStep<u64, String> for Debit {
+    fn execute(&mut self, amount: u64) -> Result<String, DistributedError> {
+        self.0.lock().unwrap().push(format!("debit {amount}"));
+        Ok(format!("tx-{amount}"))
+    }
+    fn compensate(&mut self, reference: String) -> Result<(), DistributedError> {
+        self.0.lock().unwrap().push(format!("refund {reference}"));
+        Ok(())
+    }
+}
+
+/// 按流水号入账，返回 (流水号, 入账序号)
+struct Credit(Log);
+impl TypedSagaStep<String, (String, u32)> for Credit {
+    fn execute(&mut self, reference: String) -> Result<(String, u32), DistributedError> {
+        self.0.lock().unwrap().push(format!("credit {reference}"));
+        Ok((reference, 7))
+    }
+    fn compensate(&mut self, (reference, entry): (String, u32)) -> Result<(), DistributedError> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("reverse {reference}#{entry}"));
+        Ok(())
+    }
+}
+
+struct Notify {
+    fail: bool,
+}
+impl TypedSagaStep<(String, u32), String> for Notify {
+    fn execute(&mut self, (reference, entry): (String, u32)) -> Result<String, DistributedError> {
+        if self.fail {
+            return Err(DistributedError::Network("notify unavailable".into()));
+        }
+        Ok(format!("{reference} booked as entry {entry}"))
+    }
+    fn compensate(&mut self, _: String) -> Result<(), DistributedError> {
+        Ok(())
+    }
+}
+
+fn transfer(log: &Log, fail: bool) -> TypedSaga<u64, String> {
+    TypedSaga::new()
+        .then(Debit(log.clone()))
+        .then(Credit(log.clone()))
+        .then(Notify { fail })
+}
+
+#[test]
+fn outputs_are_threaded_through_steps() {
+    let log = Log::default();
+    let result = transfer(&log, false).run(100).unwrap();
+    assert_eq!(result, "tx-100 booked as entry 7");
+    assert_eq!(*log.lock().unwrap(), ["debit 100", "credit tx-100"]);
+}
+
+#[test]
+fn failure_compensates_with_step_outputs_in_reverse() {
+    let log = Log::default();
+    let err = transfer(&log, true).run(42).unwrap_err();
+    assert!(matches!(err, DistributedError::Network(_)));
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "debit 42",
+            "credit tx-42",
+            "reverse tx-42#7",
+            "refund tx-42"
+        ]
+    );
+}
@@ -0,0 +1,189 @@
+// 测试目的：按 schema 版本逐级迁移的命令编解码
+// - 不变量：1) v1 编码的命令经 v1→v2→v3 迁移链解码为当前类型，任意版本间往返不丢信息；
+//              未来版本的负载以 UnsupportedVersion 拒绝并报告所见版本；
+//           2) v3 节点经复制传输与 v2 对端握手后按 v2 编码发送，对端成功解码并应用。
+use distributed::codec::{BinaryCodec, CodecError, UpcastingCodec};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PutV1 {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PutV2 {
+    pub key: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PutV3 {
+    pub key: String,
+    pub data: String,
+    pub ttl_ms: Option<u64>,
+}
+
+fn rename(mut value: Value, from: &str, to: &str) -> Result<Value, String> {
+    let obj = value.as_object_mut().ok_or("expected object")?;
+    let field = obj.remove(from).ok_or(format!("missing {from}"))?;
+    obj.insert(to.to_string(), field);
+    Ok(value)
+}
+
+/// v2：`value` 改名为 `data`
+pub fn codec_v2<T>() -> UpcastingCodec<T> {
+    UpcastingCodec::new().with_migration(
+        2,
+        |v| rename(v, "value", "data"),
+        |v| rename(v, "data", "value"),
+    )
+}
+
+/// v3：新增可选的 `ttl_ms`，降级时丢弃
+pub fn codec_v3() -> UpcastingCodec<PutV3> {
+    codec_v2().with_migration(
+        3,
+        |mut v| {
+            v["ttl_ms"] = Value::Null;
+            Ok(v)
+        },
+        |mut v| {
+            v.as_object_mut().ok_or("expected object")?.remove("ttl_ms");
+            Ok(v)
+        },
+    )
+}
+
+#[test]
+fn v1_payloads_migrate_through_the_chain_and_round_trip() {
+    let v1 = UpcastingCodec::<PutV1>::new();
+    let v3 = codec_v3();
+    assert_eq!((v1.current_version(), v3.current_version()), (1, 3));
+
+    let old = v1.encode(&PutV1 {
+        key: "k".into(),
+        value: "hello".into(),
+    });
+    assert_eq!(UpcastingCodec::<PutV1>::wire_version(&old).unwrap(), 1);
+    let put = v3.try_decode(&old).unwrap();
+    assert_eq!(
+        put,
+        PutV3 {
+            key: "k".into(),
+            data: "hello".into(),
+            ttl_ms: None,
+        }
+    );
+
+    // 当前版本往返；降级到 v1 后旧节点可以解码，再由新节点上迁回来
+    let with_ttl = PutV3 {
+        ttl_ms: Some(500),
+        ..put.clone()
+    };
+    assert_eq!(v3.decode(&v3.encode(&with_ttl)), Some(with_ttl.clone()));
+    let downgraded = v3.encode_for_version(&put, 1).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<Value>(&downgraded[4..]).unwrap(),
+        json!({"key": "k", "value": "hello"})
+    );
+    assert_eq!(v1.try_decode(&downgraded).unwrap().value, "hello");
+    assert_eq!(v3.try_decode(&downgraded).unwrap(), put);
+
+    // 旧节点遇到未来版本
+    let v2 = codec_v2::<PutV2>();
+    assert_eq!(
+        v2.try_decode(&v3.encode(&with_ttl)).unwrap_err(),
+        CodecError::UnsupportedVersion {
+            version: 3,
+            current: 2,
+        }
+    );
+    assert!(matches!(
+        v3.encode_for_version(&put, 4),
+        Err(CodecError::UnsupportedVersion { version: 4, .. })
+    ));
+    assert!(matches!(
+        v3.try_decode(&[0, 0]),
+        Err(CodecError::Malformed(_))
+    ));
+}
+
+#[cfg(feature = "transport-grpc")]
+mod mixed_version_cluster {
+    use super::{PutV2, PutV3, codec_v2, codec_v3};
+    use distributed::DistributedError;
+    use distributed::codec::{BinaryCodec, UpcastingCodec};
+    use distributed::consistency::ConsistencyLevel;
+    use distributed::network::grpc_replication::{
+        GrpcReplicationTransport, NodeHandler, ReplicationServer,
+    };
+    use distributed::replication::{RemoteReplicator, ReplicateRequest};
+    use std::sync::{Arc, Mutex};
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    /// 以本节点的 schema 解码负载，记录 (线上版本, 解码后的 key/data)
+    struct Decoder<T> {
+        codec: UpcastingCodec<T>,
+        applied: Arc<Mutex<Vec<(u32, String, String)>>>,
+    }
+
+    impl NodeHandler for Decoder<PutV2> {
+        async fn apply(&self, request: ReplicateRequest) -> Result<(), DistributedError> {
+            let version = UpcastingCodec::<PutV2>::wire_version(&request.payload).unwrap();
+            let put = self
+                .codec
+                .try_decode(&request.payload)
+                .map_err(|e| DistributedError::Storage(e.to_string()))?;
+            self.applied
+                .lock()
+                .unwrap()
+                .push((version, put.key, put.data));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn v3_node_encodes_at_v2_for_a_v2_peer() {
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let handler = Decoder {
+            codec: codec_v2::<PutV2>(),
+            applied: applied.clone(),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(ReplicationServer::new("old", handler).with_schema_version(2))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let codec = Arc::new(codec_v3());
+        let transport = GrpcReplicationTransport::new([("old", format!("http://{addr}"))])
+            .with_schema(codec.clone());
+        let replicator = RemoteReplicator::new(transport, vec!["old".to_string()]);
+        assert_eq!(replicator.transport().negotiated_version("old"), None);
+        for (i, ttl_ms) in [(0, Some(500)), (1, None)] {
+            let put = PutV3 {
+                key: format!("k{i}"),
+                data: format!("v{i}"),
+                ttl_ms,
+            };
+            let acks = replicator
+                .replicate(&put.key, codec.encode(&put), ConsistencyLevel::Quorum)
+                .await
+                .unwrap();
+            assert!(acks[0].applied, "{:?}", acks[0].error);
+        }
+        assert_eq!(replicator.transport().negotiated_version("old"), Some(2));
+
+        assert_eq!(
+            *applied.lock().unwrap(),
+            vec![
+                (2, "k0".to_string(), "v0".to_string()),
+                (2, "k1".to_string(), "v1".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,76 @@
+// 测试目的：按部署版本的蓝绿/金丝雀路由
+// - 不变量：1) 4 个 v1、1 个 v2 实例在 canary_fraction=0.2 下发送 1000 次请求，v2 收到 180–220 次；
+//              调整比例后按新比例分流；
+//           2) 版本按 semver 而非字典序比较，更旧的版本不再接收流量；新版本实例全部不健康时流量
+//              全部回到上一个版本。
+use distributed::VersionAwareBalancer;
+use distributed::service_discovery::ServiceInstance;
+use std::collections::HashMap;
+
+fn instance(id: &str, port: u16, version: &str) -> ServiceInstance {
+    ServiceInstance::new(
+        id.to_string(),
+        "api".to_string(),
+        format!("127.0.0.1:{port}").parse().unwrap(),
+        HashMap::new(),
+    )
+    .with_version(version)
+}
+
+fn route(balancer: &mut VersionAwareBalancer, requests: usize) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for _ in 0..requests {
+        let server = balancer.select_server().expect("server available");
+        *counts.entry(server.version.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[test]
+fn canary_fraction_of_traffic_reaches_latest_version() {
+    let mut fleet: Vec<_> = (0..4)
+        .map(|i| instance(&format!("v1-{i}"), 9000 + i, "1.0.0"))
+        .collect();
+    fleet.push(instance("v2-0", 9100, "2.0.0"));
+    let mut balancer = VersionAwareBalancer::new(fleet, 0.2);
+    assert_eq!(balancer.canary_version(), Some("2.0.0"));
+    assert_eq!(balancer.stable_version(), Some("1.0.0"));
+
+    let counts = route(&mut balancer, 1000);
+    let v2 = counts.get("2.0.0").copied().unwrap_or(0);
+    assert!((180..=220).contains(&v2), "v2 received {v2}");
+    assert_eq!(counts["1.0.0"], 1000 - v2);
+
+    balancer.set_canary_fraction(0.75);
+    let counts = route(&mut balancer, 1000);
+    let v2 = counts.get("2.0.0").copied().unwrap_or(0);
+    assert!((730..=770).contains(&v2), "v2 received {v2}");
+    balancer.set_canary_fraction(1.0);
+    assert_eq!(route(&mut balancer, 100).get("1.0.0"), None);
+}
+
+#[test]
+fn versions_order_by_semver_and_unhealthy_canary_falls_back() {
+    let fleet = vec![
+        instance("old", 9001, "1.2.0"),
+        instance("prev", 9002, "v1.9.3"),
+        instance("next", 9003, "1.10.0"),
+        instance("rc", 9004, "1.10.0-rc.1"),
+    ];
+    let mut balancer = VersionAwareBalancer::new(fleet.clone(), 0.5);
+    assert_eq!(balancer.canary_version(), Some("1.10.0"));
+    assert_eq!(balancer.stable_version(), Some("1.10.0-rc.1"));
+    let counts = route(&mut balancer, 100);
+    assert_eq!((counts["1.10.0"], counts["1.10.0-rc.1"]), (50, 50));
+    assert_eq!(balancer.version_selections().len(), 2);
+
+    let mut degraded: Vec<_> = fleet.into_iter().filter(|s| s.id != "rc").collect();
+    degraded[2].update_health(false);
+    balancer.update_servers(degraded);
+    assert_eq!(balancer.canary_version(), Some("v1.9.3"));
+    assert_eq!(balancer.stable_version(), Some("1.2.0"));
+
+    balancer.update_servers(vec![instance("only", 9005, "3.0.0")]);
+    assert_eq!(balancer.stable_version(), None);
+    assert_eq!(route(&mut balancer, 10)["3.0.0"], 10);
+}
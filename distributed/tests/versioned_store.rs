@@ -0,0 +1,147 @@
+// 测试目的：带版本存储的 CAS 语义
+// - 不变量：
+//   1) 8 个线程对同一键做 CAS 自增，最终值等于成功的 CAS 次数（无丢失更新）；
+//   2) CAS 冲突报告当前版本，删除后重建的键得到更高版本；
+//   3) 持久化实现重启后（快照 + WAL 重放）恢复全部条目与修订号。
+use distributed::storage::{
+    CasError, FileVersionedStore, InMemoryVersionedStore, Version, VersionedStore,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn cas_increment_loop<S: VersionedStore<String, u64> + Sync>(
+    store: &S,
+    threads: usize,
+    ops: usize,
+) {
+    let key = "counter".to_string();
+    store.put_if_version(key.clone(), 0, None).unwrap();
+    let successes = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                for _ in 0..ops {
+                    let (value, version) = store.get(&key).unwrap();
+                    match store.put_if_version(key.clone(), value + 1, Some(version)) {
+                        Ok(_) => {
+                            successes.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(CasError::Conflict { actual }) => assert!(actual > Some(version)),
+                        Err(e) => panic!("unexpected error: {e}"),
+                    }
+                }
+            });
+        }
+    });
+    let (value, _) = store.get(&key).unwrap();
+    let successes = successes.load(Ordering::Relaxed);
+    assert!(successes > 0);
+    assert_eq!(value, successes as u64);
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("versioned-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn concurrent_cas_increments_are_not_lost() {
+    cas_increment_loop(&InMemoryVersionedStore::new(), 8, 1000);
+
+    let dir = temp_dir("concurrent");
+    let store = FileVersionedStore::open(&dir)
+        .unwrap()
+        .with_snapshot_every(64);
+    cas_increment_loop(&store, 8, 50);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn conflicts_report_actual_version() {
+    let store = Arc::new(InMemoryVersionedStore::new());
+    let v1 = store.put_if_version("a", 1, None).unwrap();
+    let err = store.put_if_version("a", 2, None).unwrap_err();
+    assert_eq!(err.actual(), Some(v1));
+
+    let v2 = store.put_if_version("a", 2, Some(v1)).unwrap();
+    assert!(v2 > v1);
+    assert_eq!(
+        store.delete_if_version(&"a", v1).unwrap_err().actual(),
+        Some(v2)
+    );
+    store.delete_if_version(&"a", v2).unwrap();
+    assert_eq!(store.get(&"a"), None);
+    assert_eq!(
+        store.delete_if_version(&"a", v2).unwrap_err().actual(),
+        None
+    );
+
+    // 重建的键版本高于删除前，旧版本的 CAS 不会误中
+    let v3 = store.put_if_version("a", 3, None).unwrap();
+    assert!(v3 > v2);
+    assert!(store.put_if_version("a", 4, Some(v2)).is_err());
+
+    store.put_if_version("b", 5, None).unwrap();
+    store.put_if_version("c", 6, None).unwrap();
+    let scanned: Vec<_> = store
+        .scan("b"..)
+        .into_iter()
+        .map(|(k, v, _)| (k, v))
+        .collect();
+    assert_eq!(scanned, vec![("b", 5), ("c", 6)]);
+}
+
+#[test]
+fn file_store_recovers_from_snapshot_and_wal() {
+    let dir = temp_dir("recover");
+    let (last, deleted_at) = {
+        let store = FileVersionedStore::<String, String>::open(&dir)
+            .unwrap()
+            .with_snapshot_every(5);
+        let mut versions = Vec::new();
+        for i in 0..6 {
+            versions.push(
+                store
+                    .put_if_version(format!("k{i}"), format!("v{i}"), None)
+                    .unwrap(),
+            );
+        }
+        // 第 5 条记录触发快照，其后 1 条仍在 WAL 中
+        assert_eq!(store.wal_len(), 1);
+        store
+            .delete_if_version(&"k0".to_string(), versions[0])
+            .unwrap();
+        let last = store
+            .put_if_version("k1".into(), "v1'".into(), Some(versions[1]))
+            .unwrap();
+        (last, versions[0])
+    };
+
+    let store = FileVersionedStore::<String, String>::open(&dir).unwrap();
+    assert_eq!(store.wal_len(), 3);
+    assert_eq!(store.get(&"k0".to_string()), None);
+    assert_eq!(
+        store.get(&"k1".to_string()),
+        Some(("v1'".to_string(), last))
+    );
+    assert_eq!(store.scan(..).len(), 5);
+
+    // 修订号随之恢复：新写入的版本高于重启前的全部版本
+    let next = store
+        .put_if_version("k0".into(), "again".into(), None)
+        .unwrap();
+    assert!(next > last && next > deleted_at);
+    assert_eq!(next, Version(last.0 + 1));
+
+    store.snapshot().unwrap();
+    assert_eq!(store.wal_len(), 0);
+    drop(store);
+    let store = FileVersionedStore::<String, String>::open(&dir).unwrap();
+    assert_eq!(
+        store.get(&"k0".to_string()),
+        Some(("again".to_string(), next))
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,236 @@
+// 测试目的：xDS（EDS）服务发现
+// - 不变量：
+//   1) 管理服务器推送的每次端点更新都反映到 get_all_services()；
+//   2) 流断开后客户端重连并继续接收更新。
+#[cfg(feature = "grpc")]
+mod xds_discovery {
+    use distributed::ServiceDiscovery;
+    use distributed::service_discovery::xds::proto::{
+        Address, Any, ClusterLoadAssignment, DiscoveryRequest, DiscoveryResponse, Endpoint,
+        LbEndpoint, LocalityLbEndpoints, SocketAddress,
+    };
+    use distributed::service_discovery::xds::{EDS_TYPE_URL, XdsConfig, XdsDiscovery};
+    use prost::Message;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{Mutex, Notify, mpsc};
+    use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+    use tonic::codegen::{BoxFuture, Context, Poll, Service, http};
+    use tonic::{Request, Response, Status, Streaming};
+
+    fn assignment(cluster: &str, ports: &[u16]) -> DiscoveryResponse {
+        let cla = ClusterLoadAssignment {
+            cluster_name: cluster.to_string(),
+            endpoints: vec![LocalityLbEndpoints {
+                lb_endpoints: ports
+                    .iter()
+                    .map(|port| LbEndpoint {
+                        endpoint: Some(Endpoint {
+                            address: Some(Address {
+                                socket_address: Some(SocketAddress {
+                                    address: "10.0.0.1".to_string(),
+                                    port_value: u32::from(*port),
+                                }),
+                            }),
+                            hostname: String::new(),
+                        }),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }],
+        };
+        DiscoveryResponse {
+            version_info: format!("v{}", ports.len()),
+            resources: vec![Any {
+                type_url: EDS_TYPE_URL.to_string(),
+                value: cla.encode_to_vec(),
+            }],
+            type_url: EDS_TYPE_URL.to_string(),
+            nonce: format!("n{}", ports.len()),
+        }
+    }
+
+    /// 进程内 EDS 管理服务器：每个新流依次取出一个脚本，按脚本推送响应
+    #[derive(Clone)]
+    struct MockEds {
+        scripts: Arc<Mutex<Vec<Script>>>,
+    }
+
+    struct Script {
+        responses: Vec<DiscoveryResponse>,
+        /// 推送完毕后是否以错误结束流
+        fail_after: bool,
+        /// 推送第二条及之后的响应前等待测试放行
+        gate: Option<Arc<Notify>>,
+    }
+
+    impl tonic::server::NamedService for MockEds {
+        const NAME: &'static str = "envoy.service.endpoint.v3.EndpointDiscoveryService";
+    }
+
+    impl Service<http::Request<tonic::body::Body>> for MockEds {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<tonic::body::Body>) -> Self::Future {
+            let handler = StreamEndpoints(self.clone());
+            Box::pin(async move {
+                let codec =
+                    tonic_prost::ProstCodec::<DiscoveryResponse, DiscoveryRequest>::default();
+                Ok(tonic::server::Grpc::new(codec)
+                    .streaming(handler, req)
+                    .await)
+            })
+        }
+    }
+
+    struct StreamEndpoints(MockEds);
+
+    impl Service<Request<Streaming<DiscoveryRequest>>> for StreamEndpoints {
+        type Response = Response<ReceiverStream<Result<DiscoveryResponse, Status>>>;
+        type Error = Status;
+        type Future = BoxFuture<Self::Response, Status>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request<Streaming<DiscoveryRequest>>) -> Self::Future {
+            let scripts = self.0.scripts.clone();
+            Box::pin(async move {
+                let mut inbound = req.into_inner();
+                let script = {
+                    let mut scripts = scripts.lock().await;
+                    if scripts.is_empty() {
+                        Script {
+                            responses: Vec::new(),
+                            fail_after: false,
+                            gate: None,
+                        }
+                    } else {
+                        scripts.remove(0)
+                    }
+                };
+                let (tx, rx) = mpsc::channel(4);
+                tokio::spawn(async move {
+                    // 首个请求为订阅，此后每条响应等待客户端 ACK
+                    let Ok(Some(first)) = inbound.message().await else {
+                        return;
+                    };
+                    assert_eq!(first.type_url, EDS_TYPE_URL);
+                    assert_eq!(first.resource_names, vec!["orders".to_string()]);
+                    for (i, resp) in script.responses.into_iter().enumerate() {
+                        if let (true, Some(gate)) = (i > 0, &script.gate) {
+                            gate.notified().await;
+                        }
+                        let nonce = resp.nonce.clone();
+                        if tx.send(Ok(resp)).await.is_err() {
+                            return;
+                        }
+                        match inbound.message().await {
+                            Ok(Some(ack)) => assert_eq!(ack.response_nonce, nonce),
+                            _ => return,
+                        }
+                    }
+                    if script.fail_after {
+                        let _ = tx
+                            .send(Err(Status::unavailable("control plane restart")))
+                            .await;
+                        return;
+                    }
+                    // 保持流打开
+                    while let Ok(Some(_)) = inbound.message().await {}
+                });
+                Ok(Response::new(ReceiverStream::new(rx)))
+            })
+        }
+    }
+
+    async fn serve(scripts: Vec<Script>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let svc = MockEds {
+            scripts: Arc::new(Mutex::new(scripts)),
+        };
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(svc)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        format!("http://{addr}")
+    }
+
+    async fn wait_for_ports(discovery: &XdsDiscovery, expected: &[u16]) {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            let mut ports: Vec<u16> = discovery
+                .get_all_services()
+                .get("orders")
+                .map(|v| v.iter().map(|i| i.address.port()).collect())
+                .unwrap_or_default();
+            ports.sort_unstable();
+            if ports == expected {
+                return;
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "saw {ports:?}, want {expected:?}"
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn endpoint_updates_are_reflected() {
+        let gate = Arc::new(Notify::new());
+        let uri = serve(vec![Script {
+            responses: vec![
+                assignment("orders", &[8080, 8081]),
+                assignment("orders", &[8081, 8082, 8083]),
+            ],
+            fail_after: false,
+            gate: Some(gate.clone()),
+        }])
+        .await;
+        let mut discovery = XdsDiscovery::new(XdsConfig::new(uri, "node-1", "orders"));
+        discovery.start();
+
+        wait_for_ports(&discovery, &[8080, 8081]).await;
+        gate.notify_one();
+        // 每次响应是全量视图：8080 被移除
+        wait_for_ports(&discovery, &[8081, 8082, 8083]).await;
+        let instances = discovery.get_services("orders");
+        assert!(instances.iter().all(|i| i.is_healthy && i.name == "orders"));
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_stream_error() {
+        let uri = serve(vec![
+            Script {
+                responses: vec![assignment("orders", &[9000])],
+                fail_after: true,
+                gate: None,
+            },
+            Script {
+                responses: vec![assignment("orders", &[9000, 9001])],
+                fail_after: false,
+                gate: None,
+            },
+        ])
+        .await;
+        let config = XdsConfig::new(uri, "node-1", "orders")
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(50));
+        let mut discovery = XdsDiscovery::new(config);
+        discovery.start();
+
+        wait_for_ports(&discovery, &[9000]).await;
+        wait_for_ports(&discovery, &[9000, 9001]).await;
+    }
+}
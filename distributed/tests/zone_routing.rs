@@ -0,0 +1,91 @@
+// 测试目的：可用区感知路由
+// - 不变量：1) 本区 2 个、他区 4 个实例全部健康时，至少 95% 的选择落在本区；本区健康容量低于阈值时
+//           按比例溢出，本区实例全部不健康时选择全部转到他区且不返回 None；
+//           2) 一致性哈希的跨区偏好列表每区一项且与健康状态无关，本区主副本不健康时切换到列表中的他区实例。
+use distributed::service_discovery::ServiceInstance;
+use distributed::{ConsistentHashBalancer, RoundRobinBalancer, ZoneAwareBalancer};
+use std::collections::{HashMap, HashSet};
+
+fn instance(id: &str, port: u16, zone: &str) -> ServiceInstance {
+    ServiceInstance::new(
+        id.to_string(),
+        "api".to_string(),
+        format!("127.0.0.1:{port}").parse().unwrap(),
+        HashMap::new(),
+    )
+    .with_zone(zone)
+}
+
+fn fleet() -> Vec<ServiceInstance> {
+    vec![
+        instance("l1", 9001, "az-a"),
+        instance("l2", 9002, "az-a"),
+        instance("r1", 9003, "az-b"),
+        instance("r2", 9004, "az-b"),
+        instance("r3", 9005, "az-c"),
+        instance("r4", 9006, "az-c"),
+    ]
+}
+
+#[test]
+fn prefers_local_zone_and_spills_over_when_local_is_unhealthy() {
+    let mut balancer = ZoneAwareBalancer::new("az-a", fleet(), RoundRobinBalancer::new)
+        .with_min_local_fraction(0.75);
+    let local = (0..1000)
+        .filter(|_| balancer.select_server().unwrap().zone() == Some("az-a"))
+        .count();
+    assert!(local >= 950, "{local}");
+    assert_eq!(balancer.local_selections(), local as u64);
+
+    // 本区健康容量 1/2 低于 0.75：按健康实例数 1:4 分配
+    assert!(balancer.set_healthy("l1", false));
+    let spilled_local = (0..1000)
+        .filter(|_| balancer.select_server().unwrap().id == "l2")
+        .count();
+    assert!((195..=205).contains(&spilled_local), "{spilled_local}");
+
+    balancer.set_healthy("l2", false);
+    for _ in 0..100 {
+        let selected = balancer.select_server().expect("remote instances remain");
+        assert_ne!(selected.zone(), Some("az-a"));
+    }
+    let selections = balancer.zone_selections();
+    let remote = selections["az-b"] + selections["az-c"];
+    assert_eq!(selections["az-a"], (local + spilled_local) as u64);
+    assert_eq!(remote, (1000 - local + 1000 - spilled_local + 100) as u64);
+}
+
+#[test]
+fn consistent_hash_preference_list_spans_zones() {
+    let mut servers = fleet();
+    let balancer = ConsistentHashBalancer::new(servers.clone(), 16);
+    for key in ["user:1", "user:2", "order:42"] {
+        let list = balancer.zone_preference_list(key);
+        let zones: HashSet<_> = list.iter().map(|s| s.zone()).collect();
+        assert_eq!(list.len(), 3);
+        assert_eq!(zones.len(), 3);
+        assert_eq!(
+            balancer
+                .select_server_zone_aware(key, "az-a")
+                .unwrap()
+                .zone(),
+            Some("az-a")
+        );
+    }
+
+    // 本区实例全部不健康：切换到偏好列表中第一个他区实例，列表本身不变
+    let expected = balancer
+        .zone_preference_list("user:1")
+        .into_iter()
+        .find(|s| s.zone() != Some("az-a"))
+        .unwrap()
+        .id
+        .clone();
+    for server in servers.iter_mut().filter(|s| s.zone() == Some("az-a")) {
+        server.update_health(false);
+    }
+    let balancer = ConsistentHashBalancer::new(servers, 16);
+    assert_eq!(balancer.zone_preference_list("user:1").len(), 3);
+    let failover = balancer.select_server_zone_aware("user:1", "az-a").unwrap();
+    assert_eq!(failover.id, expected);
+}
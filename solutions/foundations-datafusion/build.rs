@@ -0,0 +1,21 @@
+//! 编译 `proto/Flight.proto`，把文件描述符集写入 `OUT_DIR/flight_descriptor.bin`，
+//! 供 `reflection` 模块以 `include_bytes!` 嵌入。生成的 Rust 代码不使用：服务端类型来自 arrow-flight。
+
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+    println!("cargo:rerun-if-changed=proto/Flight.proto");
+
+    prost_build::Config::new()
+        .protoc_executable(protoc_bin_vendored::protoc_bin_path()?)
+        .file_descriptor_set_path(out_dir.join("flight_descriptor.bin"))
+        .compile_protos(
+            &["proto/Flight.proto"],
+            &[
+                PathBuf::from("proto"),
+                protoc_bin_vendored::include_path()?,
+            ],
+        )?;
+    Ok(())
+}
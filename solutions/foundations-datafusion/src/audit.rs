@@ -0,0 +1,579 @@
+//! 查询审计日志与查询级指标
+//!
+//! 每次 `do_get` / `do_put` / `do_action` 调用生成一个 `query_id`，通过响应元数据
+//! `x-query-id`（出错时附在 `Status` 元数据上）返回给客户端。调用结束时写出一条 JSON 行审计记录：
+//! 客户端身份（令牌的 SHA-256 前缀，不记录令牌本身）、SQL 文本或其哈希、规划/执行耗时、
+//! 返回行数/字节数与结果状态，同时发出带 `query_id` 的 tracing 事件。
+//!
+//! 流式结果在流结束时记录；客户端中途断开（流被丢弃）记为 `cancelled`。
+//! 规划耗时、执行耗时与结果大小同时计入直方图，由 `stats` 动作以 JSON 返回。
+
+use arrow_flight::FlightData;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+use tracing::{info, warn};
+
+use crate::config::AuditConfig;
+use crate::error::AppError;
+
+/// 返回查询 ID 的元数据键
+pub const QUERY_ID_METADATA: &str = "x-query-id";
+
+/// 延迟直方图桶上界（毫秒）
+const LATENCY_BOUNDS_MS: &[f64] = &[
+    1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+/// 结果大小直方图桶上界（字节）
+const SIZE_BOUNDS_BYTES: &[f64] = &[
+    1024.0,
+    16.0 * 1024.0,
+    256.0 * 1024.0,
+    1024.0 * 1024.0,
+    16.0 * 1024.0 * 1024.0,
+    256.0 * 1024.0 * 1024.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditStatus {
+    Ok,
+    Error,
+    /// 客户端在结果流结束前断开
+    Cancelled,
+}
+
+/// 审计日志中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub query_id: String,
+    pub timestamp: DateTime<Utc>,
+    /// `do_get` / `do_put` / `do_action`
+    pub method: String,
+    /// `do_action` 的动作类型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    pub client: String,
+    /// SQL 原文；启用 `hash_sql` 时不记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sql: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sql_hash: Option<String>,
+    /// 未经过规划阶段（如 `do_action`）时为空
+    pub planning_ms: Option<f64>,
+    pub execution_ms: f64,
+    pub rows: u64,
+    pub bytes: u64,
+    pub status: AuditStatus,
+    /// gRPC 状态码，如 `Ok`、`InvalidArgument`
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 固定桶直方图；`counts[i]` 为落在 `(bounds[i-1], bounds[i]]` 的样本数，最后一个桶为溢出桶
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    pub bounds: Vec<f64>,
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &[f64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        let bucket = self.bounds.partition_point(|b| *b < value);
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+/// `stats` 动作返回的查询指标
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditStats {
+    pub queries: u64,
+    pub errors: u64,
+    pub cancelled: u64,
+    pub planning_ms: Histogram,
+    pub execution_ms: Histogram,
+    pub result_bytes: Histogram,
+}
+
+impl Default for AuditStats {
+    fn default() -> Self {
+        Self {
+            queries: 0,
+            errors: 0,
+            cancelled: 0,
+            planning_ms: Histogram::new(LATENCY_BOUNDS_MS),
+            execution_ms: Histogram::new(LATENCY_BOUNDS_MS),
+            result_bytes: Histogram::new(SIZE_BOUNDS_BYTES),
+        }
+    }
+}
+
+/// 按大小滚动的 JSON 行文件：`path` → `path.1` → … → `path.{max_files}`，更旧的被删除
+struct RotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingWriter {
+    fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = Self::open_append(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files: max_files.max(1),
+            file,
+            written,
+        })
+    }
+
+    fn open_append(path: &PathBuf) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.written > 0 && self.written + len > self.max_bytes {
+            self.rotate()?;
+        }
+        // 整行一次写入，避免与滚动交错出半行
+        self.file.write_all(format!("{}\n", line).as_bytes())?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let _ = fs::remove_file(self.rotated(self.max_files));
+        for n in (1..self.max_files).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                fs::rename(from, self.rotated(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))?;
+        self.file = Self::open_append(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        name.into()
+    }
+}
+
+/// 审计记录的写出端与查询指标
+pub struct QueryAudit {
+    hash_sql: bool,
+    writer: Option<Mutex<RotatingWriter>>,
+    stats: Mutex<AuditStats>,
+}
+
+impl Default for QueryAudit {
+    /// 只统计指标、不写审计文件
+    fn default() -> Self {
+        Self {
+            hash_sql: false,
+            writer: None,
+            stats: Mutex::new(AuditStats::default()),
+        }
+    }
+}
+
+impl QueryAudit {
+    /// 按配置打开审计文件；`path` 为空时不写文件
+    pub fn new(config: &AuditConfig) -> Result<Self, AppError> {
+        let writer = if config.path.is_empty() {
+            None
+        } else {
+            let writer = RotatingWriter::open(
+                PathBuf::from(&config.path),
+                config.max_file_bytes,
+                config.max_files,
+            )
+            .map_err(|e| AppError::Config(format!("无法打开审计日志 {}: {}", config.path, e)))?;
+            Some(Mutex::new(writer))
+        };
+        Ok(Self {
+            hash_sql: config.hash_sql,
+            writer,
+            stats: Mutex::new(AuditStats::default()),
+        })
+    }
+
+    /// 开始记录一次调用；客户端身份取自请求的 `authorization` 元数据
+    pub fn begin(self: &Arc<Self>, method: &str, metadata: &MetadataMap) -> QueryTrace {
+        let authorization = metadata.get("authorization").and_then(|v| v.to_str().ok());
+        let query_id = uuid::Uuid::new_v4().to_string();
+        info!(query_id = %query_id, method, "开始处理请求");
+        QueryTrace {
+            audit: self.clone(),
+            record: AuditRecord {
+                query_id,
+                timestamp: Utc::now(),
+                method: method.to_string(),
+                action: None,
+                client: client_identity(authorization),
+                sql: None,
+                sql_hash: None,
+                planning_ms: None,
+                execution_ms: 0.0,
+                rows: 0,
+                bytes: 0,
+                status: AuditStatus::Ok,
+                code: format!("{:?}", Code::Ok),
+                error: None,
+            },
+            started: Instant::now(),
+            planned_at: None,
+            rows: Arc::new(AtomicU64::new(0)),
+            done: false,
+        }
+    }
+
+    pub fn stats(&self) -> AuditStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    fn record(&self, record: &AuditRecord) {
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.queries += 1;
+            match record.status {
+                AuditStatus::Ok => {}
+                AuditStatus::Error => stats.errors += 1,
+                AuditStatus::Cancelled => stats.cancelled += 1,
+            }
+            if let Some(planning) = record.planning_ms {
+                stats.planning_ms.observe(planning);
+            }
+            stats.execution_ms.observe(record.execution_ms);
+            stats.result_bytes.observe(record.bytes as f64);
+        }
+
+        info!(
+            query_id = %record.query_id,
+            method = %record.method,
+            client = %record.client,
+            status = ?record.status,
+            planning_ms = ?record.planning_ms,
+            execution_ms = record.execution_ms,
+            rows = record.rows,
+            bytes = record.bytes,
+            "请求完成"
+        );
+
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        // 审计写入失败不影响查询本身
+        let result = serde_json::to_string(record)
+            .map_err(io::Error::from)
+            .and_then(|line| writer.lock().unwrap().write_line(&line));
+        if let Err(e) = result {
+            warn!(query_id = %record.query_id, "写入审计日志失败: {}", e);
+        }
+    }
+}
+
+/// 令牌只以 SHA-256 前缀出现在审计记录中
+fn client_identity(authorization: Option<&str>) -> String {
+    match authorization.and_then(|h| h.strip_prefix("Bearer ")) {
+        Some(token) => format!("token:{}", &sha256_hex(token.trim())[..12]),
+        None => "anonymous".to_string(),
+    }
+}
+
+fn sha256_hex(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+/// 单次调用的审计上下文；结束前被丢弃时记为 `cancelled`
+pub struct QueryTrace {
+    audit: Arc<QueryAudit>,
+    record: AuditRecord,
+    started: Instant,
+    planned_at: Option<Instant>,
+    /// 由结果批次流累加的行数
+    rows: Arc<AtomicU64>,
+    done: bool,
+}
+
+impl QueryTrace {
+    pub fn query_id(&self) -> &str {
+        &self.record.query_id
+    }
+
+    pub fn set_action(&mut self, action: &str) {
+        self.record.action = Some(action.to_string());
+    }
+
+    pub fn set_sql(&mut self, sql: &str) {
+        self.record.sql_hash = Some(sha256_hex(sql));
+        if !self.audit.hash_sql {
+            self.record.sql = Some(sql.to_string());
+        }
+    }
+
+    /// 规划结束、开始执行
+    pub fn mark_planned(&mut self) {
+        self.planned_at = Some(Instant::now());
+    }
+
+    pub fn row_counter(&self) -> Arc<AtomicU64> {
+        self.rows.clone()
+    }
+
+    pub fn add_bytes(&mut self, bytes: usize) {
+        self.record.bytes += bytes as u64;
+    }
+
+    /// 在响应（或错误）元数据中写入 `x-query-id`
+    pub fn attach_id(&self, metadata: &mut MetadataMap) {
+        if let Ok(value) = MetadataValue::try_from(self.record.query_id.as_str()) {
+            metadata.insert(QUERY_ID_METADATA, value);
+        }
+    }
+
+    /// 以成功状态结束
+    pub fn finish(mut self) {
+        self.complete(None);
+    }
+
+    /// 以错误状态结束，返回附带 `x-query-id` 的错误
+    pub fn fail(mut self, mut status: Status) -> Status {
+        self.complete(Some(&status));
+        self.attach_id(status.metadata_mut());
+        status
+    }
+
+    /// 统计结果流的字节数，流结束（或出错）时写出审计记录
+    pub fn track<S>(self, stream: S) -> impl Stream<Item = Result<FlightData, Status>> + Send
+    where
+        S: Stream<Item = Result<FlightData, Status>> + Send + Unpin,
+    {
+        futures::stream::unfold((stream, Some(self)), |(mut stream, mut trace)| async move {
+            let item = stream.next().await;
+            if let Some(t) = trace.as_mut() {
+                match &item {
+                    Some(Ok(data)) => t.add_bytes(data.data_header.len() + data.data_body.len()),
+                    Some(Err(status)) => {
+                        t.complete(Some(status));
+                        trace = None;
+                    }
+                    None => {
+                        t.complete(None);
+                        trace = None;
+                    }
+                }
+            }
+            item.map(|item| (item, (stream, trace)))
+        })
+    }
+
+    fn complete(&mut self, error: Option<&Status>) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        let now = Instant::now();
+        let record = &mut self.record;
+        record.planning_ms = self
+            .planned_at
+            .map(|t| t.duration_since(self.started).as_secs_f64() * 1000.0);
+        record.execution_ms =
+            now.duration_since(self.planned_at.unwrap_or(self.started)).as_secs_f64() * 1000.0;
+        record.rows = self.rows.load(Ordering::Relaxed);
+        if let Some(status) = error {
+            record.status = AuditStatus::Error;
+            record.code = format!("{:?}", status.code());
+            record.error = Some(status.message().to_string());
+        }
+        self.audit.record(record);
+    }
+}
+
+impl Drop for QueryTrace {
+    fn drop(&mut self) {
+        if !self.done {
+            self.record.status = AuditStatus::Cancelled;
+            self.record.code = format!("{:?}", Code::Cancelled);
+            self.complete(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::{collect_batches, do_action, users_context};
+    use arrow_flight::flight_service_server::FlightService;
+    use arrow_flight::Ticket;
+    use futures::TryStreamExt;
+    use tonic::Request;
+
+    fn audit_config(dir: &tempfile::TempDir, hash_sql: bool) -> AuditConfig {
+        AuditConfig {
+            path: dir.path().join("audit.jsonl").to_string_lossy().into_owned(),
+            hash_sql,
+            ..AuditConfig::default()
+        }
+    }
+
+    fn read_records(dir: &tempfile::TempDir) -> Vec<AuditRecord> {
+        std::fs::read_to_string(dir.path().join("audit.jsonl"))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    async fn audited_service(config: &AuditConfig) -> (DfFlightService, Arc<QueryAudit>) {
+        let audit = Arc::new(QueryAudit::new(config).unwrap());
+        let svc = DfFlightService::new(users_context().await).with_audit(audit.clone());
+        (svc, audit)
+    }
+
+    #[tokio::test]
+    async fn one_record_per_query_with_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let (svc, audit) = audited_service(&audit_config(&dir, false)).await;
+
+        let mut request = Request::new(Ticket::new("SELECT * FROM users"));
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer s3cret".parse().unwrap());
+        let response = svc.do_get(request).await.unwrap();
+        let query_id = response
+            .metadata()
+            .get(QUERY_ID_METADATA)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let _: Vec<FlightData> = response.into_inner().try_collect().await.unwrap();
+
+        let rejected = collect_batches(&svc, "DROP TABLE users").await.unwrap_err();
+        assert!(rejected.metadata().get(QUERY_ID_METADATA).is_some());
+        collect_batches(&svc, "SELECT * FROM missing").await.unwrap_err();
+        do_action(&svc, "stats", Vec::new()).await.unwrap();
+
+        let records = read_records(&dir);
+        assert_eq!(records.len(), 4);
+
+        let ok = &records[0];
+        assert_eq!(ok.query_id, query_id);
+        assert_eq!(ok.method, "do_get");
+        assert_eq!(ok.status, AuditStatus::Ok);
+        assert_eq!(ok.code, "Ok");
+        assert_eq!(ok.sql.as_deref(), Some("SELECT * FROM users"));
+        assert_eq!(ok.rows, 5);
+        assert!(ok.bytes > 0);
+        assert!(ok.planning_ms.is_some());
+        assert!(ok.client.starts_with("token:") && !ok.client.contains("s3cret"));
+
+        assert_eq!(records[1].status, AuditStatus::Error);
+        assert_eq!(records[1].code, "PermissionDenied");
+        assert_eq!(records[1].rows, 0);
+        assert_eq!(records[2].status, AuditStatus::Error);
+        assert!(records[2].error.is_some());
+        assert_eq!(records[3].method, "do_action");
+        assert_eq!(records[3].action.as_deref(), Some("stats"));
+        assert_eq!(records[1].client, "anonymous");
+        assert!(records[3].client.starts_with("token:"));
+
+        let stats = audit.stats();
+        assert_eq!(stats.queries, 4);
+        assert_eq!(stats.errors, 2);
+        assert_eq!(stats.planning_ms.count, 1);
+        assert_eq!(stats.execution_ms.count, 4);
+    }
+
+    #[tokio::test]
+    async fn stats_action_reports_histograms() {
+        let (svc, _) = audited_service(&AuditConfig::default()).await;
+        for _ in 0..3 {
+            collect_batches(&svc, "SELECT name FROM users").await.unwrap();
+        }
+        let body = do_action(&svc, "stats", Vec::new()).await.unwrap();
+        let stats: AuditStats = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats.queries, 3);
+        assert_eq!(stats.planning_ms.count, 3);
+        assert_eq!(stats.result_bytes.counts.iter().sum::<u64>(), 3);
+    }
+
+    #[tokio::test]
+    async fn hashed_sql_and_dropped_stream_is_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let (svc, _) = audited_service(&audit_config(&dir, true)).await;
+        let response = svc
+            .do_get(Request::new(Ticket::new("SELECT * FROM users")))
+            .await
+            .unwrap();
+        drop(response);
+
+        let records = read_records(&dir);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, AuditStatus::Cancelled);
+        assert!(records[0].sql.is_none());
+        assert_eq!(records[0].sql_hash.as_deref(), Some(sha256_hex("SELECT * FROM users").as_str()));
+    }
+
+    #[test]
+    fn writer_rotates_and_keeps_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let mut writer = RotatingWriter::open(path.clone(), 32, 2).unwrap();
+        for i in 0..10 {
+            writer.write_line(&format!("{{\"line\":{:>10}}}", i)).unwrap();
+        }
+        assert!(path.exists());
+        assert!(dir.path().join("audit.jsonl.1").exists());
+        assert!(dir.path().join("audit.jsonl.2").exists());
+        assert!(!dir.path().join("audit.jsonl.3").exists());
+        let current = std::fs::read_to_string(&path).unwrap();
+        assert!(current.contains("9}"), "{current}");
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let mut h = Histogram::new(&[1.0, 10.0]);
+        for v in [0.5, 1.0, 5.0, 50.0] {
+            h.observe(v);
+        }
+        assert_eq!(h.counts, vec![2, 1, 1]);
+        assert_eq!(h.count, 4);
+    }
+}
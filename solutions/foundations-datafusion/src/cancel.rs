@@ -0,0 +1,177 @@
+//! 查询取消
+//!
+//! 每次 `do_get` 以审计生成的 `query_id`（即响应元数据 `x-query-id`）登记一个
+//! `CancellationToken`；`cancel_query` 动作按 ID 触发令牌。生产者任务在拉取每个批次前检查令牌，
+//! 取消后丢弃 DataFusion 流（中止其执行），结果流以 `Status::Cancelled` 结束。
+//! 结果流结束或被客户端丢弃时登记随之移除，之后再取消同一 ID 返回 `NotFound`。
+
+use arrow_flight::FlightData;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use tonic::Status;
+
+pub type QueryId = String;
+
+/// `cancel_query` 动作的请求
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CancelRequest {
+    pub query_id: QueryId,
+}
+
+/// 进行中查询的取消令牌
+#[derive(Debug, Default)]
+pub struct QueryRegistry {
+    tokens: Mutex<HashMap<QueryId, CancellationToken>>,
+}
+
+impl QueryRegistry {
+    /// 登记查询；返回的句柄被丢弃时移除登记
+    pub fn register(self: &Arc<Self>, query_id: impl Into<QueryId>) -> Registration {
+        let query_id = query_id.into();
+        let token = CancellationToken::new();
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(query_id.clone(), token.clone());
+        Registration {
+            registry: self.clone(),
+            query_id,
+            token,
+        }
+    }
+
+    /// 触发取消；查询不存在（未登记或已结束）时返回 `false`
+    pub fn cancel(&self, query_id: &str) -> bool {
+        match self.tokens.lock().unwrap().get(query_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// 一次查询的登记
+pub struct Registration {
+    registry: Arc<QueryRegistry>,
+    query_id: QueryId,
+    token: CancellationToken,
+}
+
+impl Registration {
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry.tokens.lock().unwrap().remove(&self.query_id);
+    }
+}
+
+/// 令牌触发后以 `Status::Cancelled` 结束结果流，不等待生产者停下
+pub fn cancellable<S>(
+    stream: S,
+    registration: Registration,
+) -> impl Stream<Item = Result<FlightData, Status>>
+where
+    S: Stream<Item = Result<FlightData, Status>> + Send + Unpin,
+{
+    futures::stream::unfold(Some((stream, registration)), |state| async move {
+        let (mut stream, registration) = state?;
+        let token = registration.token();
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => {
+                let status = Status::cancelled(format!("查询 {} 已取消", registration.query_id));
+                Some((Err(status), None))
+            }
+            item = stream.next() => item.map(|item| (item, Some((stream, registration)))),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::QUERY_ID_METADATA;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::{do_action, privileged_auth};
+    use arrow_flight::flight_service_server::FlightService;
+    use arrow_flight::Ticket;
+    use datafusion::arrow::array::{ArrayRef, Int64Array};
+    use datafusion::arrow::datatypes::DataType;
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::datasource::MemTable;
+    use datafusion::logical_expr::{create_udf, ColumnarValue, Volatility};
+    use datafusion::prelude::*;
+    use std::time::Duration;
+    use tonic::Request;
+
+    /// 200 批、每批 1000 行的 `numbers` 表，`slow(n)` 每批阻塞 10ms（全表约 2s）
+    fn slow_context() -> SessionContext {
+        let batches: Vec<RecordBatch> = (0..200i64)
+            .map(|i| {
+                let values = Int64Array::from_iter_values(i * 1000..(i + 1) * 1000);
+                RecordBatch::try_from_iter(vec![("n", Arc::new(values) as ArrayRef)]).unwrap()
+            })
+            .collect();
+        let table = MemTable::try_new(batches[0].schema(), vec![batches]).unwrap();
+        // 单分区：阻塞的 UDF 只占用一个工作线程
+        let ctx = SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1));
+        ctx.register_table("numbers", Arc::new(table)).unwrap();
+        ctx.register_udf(create_udf(
+            "slow",
+            vec![DataType::Int64],
+            Arc::new(DataType::Int64),
+            Volatility::Volatile,
+            Arc::new(|args: &[ColumnarValue]| {
+                std::thread::sleep(Duration::from_millis(10));
+                Ok(args[0].clone())
+            }),
+        ));
+        ctx
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn cancel_query_closes_a_slow_stream_with_cancelled() {
+        let svc = DfFlightService::new(slow_context()).with_auth(privileged_auth());
+        let ticket = Ticket {
+            ticket: "SELECT slow(n) FROM numbers".into(),
+        };
+        let resp = svc.do_get(Request::new(ticket)).await.unwrap();
+        let query_id = resp
+            .metadata()
+            .get(QUERY_ID_METADATA)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let mut stream = resp.into_inner();
+        let consumer = tokio::spawn(async move {
+            while let Some(item) = stream.next().await {
+                item?;
+            }
+            Ok::<_, Status>(())
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let body = serde_json::to_vec(&CancelRequest { query_id }).unwrap();
+        do_action(&svc, "cancel_query", body.clone()).await.unwrap();
+        let status = tokio::time::timeout(Duration::from_millis(200), consumer)
+            .await
+            .expect("取消后 200ms 内结果流应结束")
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Cancelled);
+
+        // 结果流结束后登记已移除
+        let err = do_action(&svc, "cancel_query", body).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+}
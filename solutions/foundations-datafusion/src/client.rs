@@ -1,4 +1,5 @@
 use arrow_flight::{FlightClient, Ticket};
+use futures::StreamExt;
 use tonic::transport::Channel;
 use tracing::{info, error};
 
@@ -36,17 +37,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 async fn execute_query(
-    client: &mut FlightClient<Channel>,
+    client: &mut FlightClient,
     sql: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let ticket = Ticket {
-        ticket: sql.as_bytes().to_vec(),
+        ticket: sql.as_bytes().to_vec().into(),
     };
     
     let mut stream = client.do_get(ticket).await?;
     
-    while let Some(flight_data) = stream.message().await? {
-        info!("收到数据: {:?}", flight_data);
+    while let Some(batch) = stream.next().await {
+        info!("收到数据: {:?}", batch?);
     }
     
     Ok(())
@@ -1,47 +1,572 @@
+//! 服务配置
+//!
+//! 分层加载：默认值 → 可选的 TOML 文件（`CONFIG_PATH`，未设置时尝试 `./config.toml`）→ 环境变量。
+//! 环境变量名与早期版本保持一致。非法值不再静默回退到默认值：所有解析与校验错误汇总为一个
+//! `AppError::Config` 返回。
+
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use subtle::{Choice, ConstantTimeEq};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AppConfig {
-    pub server_address: String,
+use crate::audit::QueryAudit;
+use crate::error::AppError;
+use crate::health::CircuitBreaker;
+use crate::limits::QueryLimits;
+use crate::prepared::PreparedStatementCache;
+use crate::router::ShardRouter;
+use crate::validation::{SqlValidator, StatementKind};
+
+/// 监听地址、数据目录等进程级配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: String,
     pub data_path: String,
     pub log_level: String,
     pub max_connections: u32,
-    pub query_timeout_seconds: u64,
 }
 
-impl Default for AppConfig {
+impl Default for ServerConfig {
     fn default() -> Self {
         Self {
-            server_address: "0.0.0.0:50051".to_string(),
+            address: "0.0.0.0:50051".to_string(),
             data_path: "./data".to_string(),
             log_level: "info".to_string(),
             max_connections: 100,
-            query_timeout_seconds: 300,
         }
     }
 }
 
+/// 查询校验、结果限制与熔断配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueryConfig {
+    pub timeout_seconds: u64,
+    /// `do_get` 允许的语句类别（query / explain / show）
+    pub allowed_statements: Vec<String>,
+    /// 查询字符串最大字节数
+    pub max_query_length: usize,
+    /// 单个查询最多返回的行数（0 表示不限）
+    pub max_rows: usize,
+    /// 单个查询最多返回的字节数（0 表示不限）
+    pub max_result_bytes: usize,
+    /// 超过该行数的批次被切分
+    pub max_batch_rows: usize,
+    /// 结果流缓冲的批次数
+    pub stream_buffer_batches: usize,
+    /// `get_flight_info` 把结果拆成的端点数
+    pub parallelism_factor: usize,
+    /// 连续查询失败多少次后熔断
+    pub breaker_consecutive_failures: u32,
+    /// 最近一分钟错误率超过该值时熔断
+    pub breaker_max_error_rate: f64,
+    /// 是否接受 FlightSQL 之前的原始 SQL / JSON Ticket；关闭后只接受 FlightSQL 命令
+    pub legacy_tickets: bool,
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 300,
+            allowed_statements: vec!["query".to_string()],
+            max_query_length: 64 * 1024,
+            max_rows: 1_000_000,
+            max_result_bytes: 256 * 1024 * 1024,
+            max_batch_rows: 8192,
+            stream_buffer_batches: 4,
+            parallelism_factor: 1,
+            breaker_consecutive_failures: 5,
+            breaker_max_error_rate: 0.5,
+            legacy_tickets: true,
+        }
+    }
+}
+
+impl QueryConfig {
+    /// 构建 `do_get` 的 SQL 校验器；未知的语句类别视为配置错误
+    pub fn sql_validator(&self) -> Result<SqlValidator, AppError> {
+        let allowed = self
+            .allowed_statements
+            .iter()
+            .map(|s| {
+                StatementKind::parse(s)
+                    .ok_or_else(|| AppError::Config(format!("未知的语句类别: {}", s)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SqlValidator::new(allowed, self.max_query_length))
+    }
+
+    /// 构建 `do_get` 的默认结果限制
+    pub fn limits(&self) -> QueryLimits {
+        QueryLimits::new(self.max_rows, self.max_result_bytes, self.max_batch_rows)
+    }
+
+    /// 构建包裹查询执行的熔断器
+    pub fn circuit_breaker(&self) -> CircuitBreaker {
+        CircuitBreaker::new(self.breaker_consecutive_failures, self.breaker_max_error_rate)
+    }
+
+    /// 单次查询的预算上限；客户端的 `grpc-timeout` 只能收紧它
+    pub fn query_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_seconds)
+    }
+}
+
+/// `do_action` 认证配置
+///
+/// 特权动作（规划语句、刷新表、注册 UDF、取消查询）只在 `privileged_actions` 开启且调用方
+/// 携带有效令牌时执行；开启它而 `tokens` 为空是配置错误。其余动作在 `tokens` 为空时不做认证。
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub tokens: Vec<String>,
+    /// 是否开放特权动作
+    pub privileged_actions: bool,
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 日志中不输出令牌本身
+        f.debug_struct("AuthConfig")
+            .field("tokens", &format!("<{} redacted>", self.tokens.len()))
+            .field("privileged_actions", &self.privileged_actions)
+            .finish()
+    }
+}
+
+impl AuthConfig {
+    pub fn enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// 校验 `authorization: Bearer <token>` 头；未配置令牌时没有调用方能通过校验
+    ///
+    /// 与每个令牌都做常数时间比较，耗时不泄露令牌内容或匹配到第几个令牌。
+    pub fn is_authorized(&self, authorization: Option<&str>) -> bool {
+        authorization
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .is_some_and(|token| {
+                let token = token.trim().as_bytes();
+                self.tokens
+                    .iter()
+                    .fold(Choice::from(0), |matched, t| matched | t.as_bytes().ct_eq(token))
+                    .into()
+            })
+    }
+}
+
+/// 预编译语句缓存配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// 预编译语句空闲过期时间
+    pub prepared_statement_ttl_seconds: u64,
+    /// 预编译语句缓存上限
+    pub max_prepared_statements: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            prepared_statement_ttl_seconds: 600,
+            max_prepared_statements: 1024,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn statement_cache(&self) -> PreparedStatementCache {
+        PreparedStatementCache::new(
+            Duration::from_secs(self.prepared_statement_ttl_seconds),
+            self.max_prepared_statements,
+        )
+    }
+}
+
+/// 查询审计日志配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// JSON 行审计文件路径；为空时只统计指标、不写文件
+    pub path: String,
+    /// 只记录 SQL 的 SHA-256，不记录原文
+    pub hash_sql: bool,
+    /// 单个审计文件的滚动阈值
+    pub max_file_bytes: u64,
+    /// 保留的历史审计文件数
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            hash_sql: false,
+            max_file_bytes: 64 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+impl AuditConfig {
+    pub fn query_audit(&self) -> Result<QueryAudit, AppError> {
+        QueryAudit::new(self)
+    }
+}
+
+/// 分片扇出配置；`shards` 为空时本节点直接执行查询
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RouterConfig {
+    /// 分片列表，每项为 `名称=http://host:port`
+    pub shards: Vec<String>,
+    /// 从客户端预算中为协调者保留的规划/合并时间
+    pub planning_overhead_ms: u64,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            shards: Vec::new(),
+            planning_overhead_ms: 20,
+        }
+    }
+}
+
+impl RouterConfig {
+    /// 构建分片路由；未配置分片时返回 `None`
+    pub fn shard_router(&self) -> Result<Option<ShardRouter>, AppError> {
+        if self.shards.is_empty() {
+            return Ok(None);
+        }
+        let mut router = ShardRouter::new(Duration::from_millis(self.planning_overhead_ms));
+        for shard in &self.shards {
+            let (name, endpoint) = shard.split_once('=').ok_or_else(|| {
+                AppError::Config(format!("router.shards 项格式应为 名称=地址: {}", shard))
+            })?;
+            router = router.with_shard(name.trim(), endpoint.trim())?;
+        }
+        Ok(Some(router))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub query: QueryConfig,
+    pub auth: AuthConfig,
+    pub cache: CacheConfig,
+    pub audit: AuditConfig,
+    pub router: RouterConfig,
+}
+
 impl AppConfig {
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        // 从环境变量加载配置
-        let config = AppConfig {
-            server_address: env::var("SERVER_ADDRESS")
-                .unwrap_or_else(|_| "0.0.0.0:50051".to_string()),
-            data_path: env::var("DATA_PATH")
-                .unwrap_or_else(|_| "./data".to_string()),
-            log_level: env::var("LOG_LEVEL")
-                .unwrap_or_else(|_| "info".to_string()),
-            max_connections: env::var("MAX_CONNECTIONS")
-                .unwrap_or_else(|_| "100".to_string())
-                .parse()
-                .unwrap_or(100),
-            query_timeout_seconds: env::var("QUERY_TIMEOUT_SECONDS")
-                .unwrap_or_else(|_| "300".to_string())
-                .parse()
-                .unwrap_or(300),
+    /// 从 `CONFIG_PATH`（或 `./config.toml`）与进程环境变量加载
+    pub fn load() -> Result<Self, AppError> {
+        let file = match std::env::var("CONFIG_PATH") {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => Some(PathBuf::from("config.toml")).filter(|p| p.exists()),
+        };
+        Self::load_from(file.as_deref(), |key| std::env::var(key).ok())
+    }
+
+    /// 分层加载：默认值 → `file` → `env`，最后整体校验
+    pub fn load_from(
+        file: Option<&Path>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, AppError> {
+        let mut config = match file {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| {
+                    AppError::Config(format!("无法读取配置文件 {}: {}", path.display(), e))
+                })?;
+                toml::from_str(&text).map_err(|e| {
+                    AppError::Config(format!("配置文件 {} 格式错误: {}", path.display(), e))
+                })?
+            }
+            None => AppConfig::default(),
+        };
+
+        let mut errors = Vec::new();
+        config.apply_env(&env, &mut errors);
+        errors.extend(config.problems());
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(AppError::Config(errors.join("; ")))
+        }
+    }
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, msg: String| {
+            if !ok {
+                problems.push(msg);
+            }
+        };
+
+        check(
+            self.server.address.parse::<SocketAddr>().is_ok(),
+            format!("server.address 不是合法的监听地址: {}", self.server.address),
+        );
+        check(self.server.max_connections > 0, "server.max_connections 必须大于 0".into());
+        check(self.query.timeout_seconds > 0, "query.timeout_seconds 必须大于 0".into());
+        check(self.query.max_query_length > 0, "query.max_query_length 必须大于 0".into());
+        check(self.query.max_batch_rows > 0, "query.max_batch_rows 必须大于 0".into());
+        check(
+            self.query.stream_buffer_batches > 0,
+            "query.stream_buffer_batches 必须大于 0".into(),
+        );
+        check(
+            self.query.parallelism_factor > 0,
+            "query.parallelism_factor 必须大于 0".into(),
+        );
+        check(
+            self.query.breaker_consecutive_failures > 0,
+            "query.breaker_consecutive_failures 必须大于 0".into(),
+        );
+        check(
+            self.query.breaker_max_error_rate > 0.0 && self.query.breaker_max_error_rate <= 1.0,
+            format!(
+                "query.breaker_max_error_rate 必须在 (0, 1] 内: {}",
+                self.query.breaker_max_error_rate
+            ),
+        );
+        check(
+            !self.query.allowed_statements.is_empty(),
+            "query.allowed_statements 不能为空".into(),
+        );
+        for kind in &self.query.allowed_statements {
+            check(
+                StatementKind::parse(kind).is_some(),
+                format!("query.allowed_statements 含未知的语句类别: {}", kind),
+            );
+        }
+        check(
+            self.cache.prepared_statement_ttl_seconds > 0,
+            "cache.prepared_statement_ttl_seconds 必须大于 0".into(),
+        );
+        check(
+            self.cache.max_prepared_statements > 0,
+            "cache.max_prepared_statements 必须大于 0".into(),
+        );
+        check(
+            !self.auth.privileged_actions || self.auth.enabled(),
+            "auth.privileged_actions 已开启但未配置 auth.tokens，特权动作将对任何人开放".into(),
+        );
+        check(self.audit.max_file_bytes > 0, "audit.max_file_bytes 必须大于 0".into());
+        check(self.audit.max_files > 0, "audit.max_files 必须大于 0".into());
+        for shard in &self.router.shards {
+            check(
+                shard.split_once('=').is_some_and(|(name, endpoint)| {
+                    !name.trim().is_empty() && !endpoint.trim().is_empty()
+                }),
+                format!("router.shards 项格式应为 名称=地址: {}", shard),
+            );
+        }
+        problems
+    }
+
+    /// 以环境变量覆盖；变量名沿用早期的扁平命名
+    fn apply_env(&mut self, env: &impl Fn(&str) -> Option<String>, errors: &mut Vec<String>) {
+        let mut layer = EnvLayer { env, errors };
+        layer.string("SERVER_ADDRESS", &mut self.server.address);
+        layer.string("DATA_PATH", &mut self.server.data_path);
+        layer.string("LOG_LEVEL", &mut self.server.log_level);
+        layer.parse("MAX_CONNECTIONS", &mut self.server.max_connections);
+
+        layer.parse("QUERY_TIMEOUT_SECONDS", &mut self.query.timeout_seconds);
+        layer.list("ALLOWED_STATEMENTS", &mut self.query.allowed_statements);
+        layer.parse("MAX_QUERY_LENGTH", &mut self.query.max_query_length);
+        layer.parse("MAX_ROWS", &mut self.query.max_rows);
+        layer.parse("MAX_RESULT_BYTES", &mut self.query.max_result_bytes);
+        layer.parse("MAX_BATCH_ROWS", &mut self.query.max_batch_rows);
+        layer.parse("STREAM_BUFFER_BATCHES", &mut self.query.stream_buffer_batches);
+        layer.parse("PARALLELISM_FACTOR", &mut self.query.parallelism_factor);
+        layer.parse(
+            "BREAKER_CONSECUTIVE_FAILURES",
+            &mut self.query.breaker_consecutive_failures,
+        );
+        layer.parse("BREAKER_MAX_ERROR_RATE", &mut self.query.breaker_max_error_rate);
+        layer.parse("LEGACY_TICKETS", &mut self.query.legacy_tickets);
+
+        layer.list("AUTH_TOKENS", &mut self.auth.tokens);
+        layer.parse("PRIVILEGED_ACTIONS", &mut self.auth.privileged_actions);
+
+        layer.parse(
+            "PREPARED_STATEMENT_TTL_SECONDS",
+            &mut self.cache.prepared_statement_ttl_seconds,
+        );
+        layer.parse("MAX_PREPARED_STATEMENTS", &mut self.cache.max_prepared_statements);
+
+        layer.string("AUDIT_LOG_PATH", &mut self.audit.path);
+        layer.parse("AUDIT_HASH_SQL", &mut self.audit.hash_sql);
+        layer.parse("AUDIT_MAX_FILE_BYTES", &mut self.audit.max_file_bytes);
+        layer.parse("AUDIT_MAX_FILES", &mut self.audit.max_files);
+
+        layer.list("ROUTER_SHARDS", &mut self.router.shards);
+        layer.parse("ROUTER_PLANNING_OVERHEAD_MS", &mut self.router.planning_overhead_ms);
+    }
+}
+
+/// 逐个读取环境变量，解析失败时记录错误并保留原值
+struct EnvLayer<'a, F> {
+    env: &'a F,
+    errors: &'a mut Vec<String>,
+}
+
+impl<F: Fn(&str) -> Option<String>> EnvLayer<'_, F> {
+    fn string(&mut self, key: &str, target: &mut String) {
+        if let Some(value) = (self.env)(key) {
+            *target = value;
+        }
+    }
+
+    fn list(&mut self, key: &str, target: &mut Vec<String>) {
+        if let Some(value) = (self.env)(key) {
+            *target = value
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+    }
+
+    fn parse<T>(&mut self, key: &str, target: &mut T)
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if let Some(value) = (self.env)(key) {
+            match value.trim().parse() {
+                Ok(parsed) => *target = parsed,
+                Err(e) => self.errors.push(format!("{}={:?} 无效: {}", key, value, e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    fn config_file(contents: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), contents).unwrap();
+        file
+    }
+
+    #[test]
+    fn env_overrides_file_which_overrides_defaults() {
+        let file = config_file(
+            r#"
+            [server]
+            address = "127.0.0.1:6000"
+            data_path = "/srv/data"
+
+            [query]
+            max_rows = 500
+            "#,
+        );
+        let config =
+            AppConfig::load_from(Some(file.path()), env(&[("SERVER_ADDRESS", "127.0.0.1:7000")]))
+                .unwrap();
+        assert_eq!(config.server.address, "127.0.0.1:7000");
+        assert_eq!(config.server.data_path, "/srv/data");
+        assert_eq!(config.query.max_rows, 500);
+        assert_eq!(config.query.max_batch_rows, QueryConfig::default().max_batch_rows);
+    }
+
+    #[test]
+    fn invalid_values_are_reported_together() {
+        let err = AppConfig::load_from(
+            None,
+            env(&[
+                ("MAX_CONNECTIONS", "abc"),
+                ("SERVER_ADDRESS", "not-an-address"),
+                ("QUERY_TIMEOUT_SECONDS", "0"),
+            ]),
+        )
+        .unwrap_err();
+        let AppError::Config(message) = err else {
+            panic!("unexpected error: {err}");
         };
-        
-        Ok(config)
+        assert!(message.contains("MAX_CONNECTIONS"), "{message}");
+        assert!(message.contains("server.address"), "{message}");
+        assert!(message.contains("query.timeout_seconds"), "{message}");
+    }
+
+    #[test]
+    fn legacy_env_names_still_apply() {
+        let config = AppConfig::load_from(
+            None,
+            env(&[
+                ("DATA_PATH", "/tmp/tables"),
+                ("LOG_LEVEL", "debug"),
+                ("MAX_CONNECTIONS", "7"),
+                ("ALLOWED_STATEMENTS", "query, explain"),
+                ("MAX_QUERY_LENGTH", "1024"),
+                ("PREPARED_STATEMENT_TTL_SECONDS", "30"),
+                ("MAX_PREPARED_STATEMENTS", "8"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.server.data_path, "/tmp/tables");
+        assert_eq!(config.server.log_level, "debug");
+        assert_eq!(config.server.max_connections, 7);
+        assert_eq!(config.query.allowed_statements, vec!["query", "explain"]);
+        assert_eq!(config.query.max_query_length, 1024);
+        assert_eq!(config.cache.prepared_statement_ttl_seconds, 30);
+        assert_eq!(config.cache.max_prepared_statements, 8);
+    }
+
+    #[test]
+    fn unknown_file_keys_and_missing_file_are_errors() {
+        let file = config_file("[server]\nadress = \"127.0.0.1:1\"\n");
+        assert!(AppConfig::load_from(Some(file.path()), env(&[])).is_err());
+        assert!(AppConfig::load_from(Some(Path::new("/nonexistent/config.toml")), env(&[])).is_err());
+    }
+
+    #[test]
+    fn auth_tokens_are_redacted_and_checked() {
+        let auth = AuthConfig {
+            tokens: vec!["s3cret".into()],
+            privileged_actions: true,
+        };
+        assert!(!format!("{:?}", auth).contains("s3cret"));
+        assert!(auth.is_authorized(Some("Bearer s3cret")));
+        assert!(!auth.is_authorized(Some("Bearer nope")));
+        assert!(!auth.is_authorized(None));
+        // 未配置令牌时一律拒绝，而不是放行
+        assert!(!AuthConfig::default().is_authorized(None));
+        assert!(!AuthConfig::default().is_authorized(Some("Bearer ")));
+    }
+
+    #[test]
+    fn privileged_actions_without_tokens_are_rejected() {
+        let err = AppConfig::load_from(None, env(&[("PRIVILEGED_ACTIONS", "true")])).unwrap_err();
+        assert!(err.to_string().contains("auth.privileged_actions"), "{err}");
+
+        let config = AppConfig::load_from(
+            None,
+            env(&[("PRIVILEGED_ACTIONS", "true"), ("AUTH_TOKENS", "s3cret")]),
+        )
+        .unwrap();
+        assert!(config.auth.privileged_actions);
+        assert!(!AppConfig::default().auth.privileged_actions);
     }
 }
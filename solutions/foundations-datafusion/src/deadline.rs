@@ -0,0 +1,122 @@
+//! 端到端截止时间
+//!
+//! 客户端经 `grpc-timeout` 元数据（tonic 的 `Request::set_timeout`）声明本次调用的预算。
+//! `do_get` 取它与配置的 `query.timeout_seconds` 中较短者：规划阶段超时以
+//! `DeadlineExceeded` 失败；结果流到达截止时刻时触发查询的取消令牌（生产者停止拉取
+//! DataFusion 流，见 `cancel`），并以 `Status::deadline_exceeded` 结束。
+//!
+//! 协调者（见 `router`）从客户端预算中扣除自身的规划/合并开销，把剩余预算写入发往每个分片的
+//! `do_get` 的 `grpc-timeout`，因此没有分片会运行超过端到端预算允许的时间。
+
+use arrow_flight::FlightData;
+use futures::{Stream, StreamExt};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tonic::metadata::MetadataMap;
+use tonic::Status;
+
+use crate::error::AppError;
+
+/// gRPC 截止时间元数据
+pub const GRPC_TIMEOUT: &str = "grpc-timeout";
+
+/// 解析 `grpc-timeout` 的值：至多 8 位数字加单位（`H`/`M`/`S`/`m`/`u`/`n`）
+pub fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    if !value.is_ascii() {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len().checked_sub(1)?);
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = digits.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(amount * 3600),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    })
+}
+
+/// 请求声明的预算；未声明时为 `None`，格式非法时报参数错误
+pub fn requested(metadata: &MetadataMap) -> Result<Option<Duration>, AppError> {
+    let Some(value) = metadata.get(GRPC_TIMEOUT) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(parse_grpc_timeout)
+        .map(Some)
+        .ok_or_else(|| AppError::InvalidArgument(format!("元数据 {} 格式无效", GRPC_TIMEOUT)))
+}
+
+/// 本次调用的预算：请求声明的预算与 `fallback`（配置的查询超时）中较短者
+pub fn budget(metadata: &MetadataMap, fallback: Duration) -> Result<Duration, AppError> {
+    Ok(requested(metadata)?.map_or(fallback, |requested| requested.min(fallback)))
+}
+
+/// 在截止时刻前完成 `fut`，否则以 `QueryTimeout` 失败
+pub async fn within<T>(
+    deadline: Instant,
+    fut: impl Future<Output = Result<T, AppError>>,
+) -> Result<T, AppError> {
+    tokio::time::timeout_at(deadline.into(), fut)
+        .await
+        .unwrap_or(Err(AppError::QueryTimeout))
+}
+
+/// 截止时刻到达时取消 `token`（停止生产者），并以 `DeadlineExceeded` 结束结果流
+pub fn bounded<S>(
+    stream: S,
+    deadline: Instant,
+    token: CancellationToken,
+) -> impl Stream<Item = Result<FlightData, Status>>
+where
+    S: Stream<Item = Result<FlightData, Status>> + Send + Unpin,
+{
+    futures::stream::unfold(Some(stream), move |state| {
+        let token = token.clone();
+        async move {
+            let mut stream = state?;
+            tokio::select! {
+                biased;
+                _ = tokio::time::sleep_until(deadline.into()) => {
+                    token.cancel();
+                    Some((Err(Status::deadline_exceeded("查询超过截止时间")), None))
+                }
+                item = stream.next() => item.map(|item| (item, Some(stream))),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grpc_timeout_units_and_invalid_values() {
+        assert_eq!(parse_grpc_timeout("300m"), Some(Duration::from_millis(300)));
+        assert_eq!(parse_grpc_timeout("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_grpc_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_grpc_timeout("1500u"), Some(Duration::from_micros(1500)));
+        for invalid in ["", "m", "123456789m", "10x", "-5m", "1.5S"] {
+            assert_eq!(parse_grpc_timeout(invalid), None, "{invalid}");
+        }
+
+        let mut metadata = MetadataMap::new();
+        let fallback = Duration::from_secs(300);
+        assert_eq!(budget(&metadata, fallback).unwrap(), fallback);
+        metadata.insert(GRPC_TIMEOUT, "250m".parse().unwrap());
+        assert_eq!(budget(&metadata, fallback).unwrap(), Duration::from_millis(250));
+        // 请求预算只能收紧配置的超时
+        assert_eq!(budget(&metadata, Duration::from_millis(100)).unwrap(), Duration::from_millis(100));
+        metadata.insert(GRPC_TIMEOUT, "soon".parse().unwrap());
+        assert!(matches!(budget(&metadata, fallback), Err(AppError::InvalidArgument(_))));
+    }
+}
@@ -22,12 +22,31 @@ pub enum AppError {
     
     #[error("无效的 SQL 查询: {0}")]
     InvalidQuery(String),
+
+    #[error("无效的参数: {0}")]
+    InvalidArgument(String),
+
+    #[error("预编译语句不存在或已过期: {0}")]
+    StatementNotFound(String),
+
+    #[error("查询不存在或已结束: {0}")]
+    QueryNotFound(String),
+
+    #[error("服务不可用: {0}")]
+    Unavailable(String),
 }
 
 impl From<AppError> for tonic::Status {
     fn from(err: AppError) -> Self {
         match err {
             AppError::Tonic(status) => status,
+            AppError::InvalidQuery(reason) => tonic::Status::permission_denied(reason),
+            AppError::InvalidArgument(_) => tonic::Status::invalid_argument(err.to_string()),
+            AppError::StatementNotFound(_) | AppError::QueryNotFound(_) => {
+                tonic::Status::not_found(err.to_string())
+            }
+            AppError::Unavailable(reason) => tonic::Status::unavailable(reason),
+            AppError::QueryTimeout => tonic::Status::deadline_exceeded(err.to_string()),
             _ => tonic::Status::internal(err.to_string()),
         }
     }
@@ -0,0 +1,406 @@
+//! FlightSQL 协议
+//!
+//! 通用 BI 工具说的是 FlightSQL，而不是携带 SQL 字符串的裸 Ticket。FlightSQL 命令以 protobuf
+//! `Any` 编码放在 `FlightDescriptor.cmd`、Ticket 与 `do_action` 的请求体中；`decode_command` 按
+//! `type_url` 识别它们，其余字节仍按原始 SQL / JSON Ticket 处理（`query.legacy_tickets` 关闭时拒绝）。
+//!
+//! - `CommandStatementQuery`：`get_flight_info` 校验并规划 SQL，端点 Ticket 为携带 SQL 的
+//!   `TicketStatementQuery`，`do_get` 执行前重新校验。
+//! - 预编译语句：`CreatePreparedStatement` 动作复用预编译语句缓存，句柄即 JSON 形式的
+//!   `StatementTicket`；`do_put` 绑定参数后返回携带参数的新句柄（`DoPutPreparedStatementResult`），
+//!   服务端不为绑定的参数保存状态。
+//! - `CommandGetTables` / `CommandGetDbSchemas` / `CommandGetSqlInfo`：由 SessionContext 的目录
+//!   生成元数据结果集，端点 Ticket 即命令本身。
+
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::sql::metadata::{SqlInfoData, SqlInfoDataBuilder};
+use arrow_flight::sql::{
+    Any, Command, CommandGetDbSchemas, CommandGetSqlInfo, CommandGetTables, ProstMessageExt,
+    SqlInfo,
+};
+use arrow_flight::{
+    FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, IpcMessage, SchemaAsIpc, Ticket,
+};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::ipc::writer::IpcWriteOptions;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::logical_expr::{LogicalPlan, TableType};
+use datafusion::prelude::SessionContext;
+use datafusion::scalar::ScalarValue;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use prost::Message;
+use std::sync::OnceLock;
+use tonic::Status;
+
+use crate::error::AppError;
+use crate::prepared::{placeholder_index, StatementTicket};
+
+/// FlightSQL 消息的 `type_url` 前缀
+const TYPE_URL_PREFIX: &str = "type.googleapis.com/arrow.flight.protocol.sql.";
+
+/// 创建预编译语句的动作
+pub const CREATE_PREPARED_STATEMENT: &str = "CreatePreparedStatement";
+/// 关闭预编译语句的动作
+pub const CLOSE_PREPARED_STATEMENT: &str = "ClosePreparedStatement";
+
+fn arrow_error(e: ArrowError) -> AppError {
+    AppError::DataFusion(e.into())
+}
+
+/// 元数据构建器以 `FlightError` 报错，Arrow 错误按 DataFusion 错误处理
+fn flight_error(e: FlightError) -> AppError {
+    match e {
+        FlightError::Arrow(e) => arrow_error(e),
+        FlightError::Tonic(status) => AppError::Tonic(status),
+        other => AppError::DataFusion(datafusion::error::DataFusionError::External(Box::new(other))),
+    }
+}
+
+/// 解码 `Any` 编码的 FlightSQL 命令；不是 FlightSQL 消息时返回 `None`（按旧协议处理）
+pub fn decode_command(bytes: &[u8]) -> Option<Result<Command, AppError>> {
+    let any = Any::decode(bytes).ok()?;
+    if !any.type_url.starts_with(TYPE_URL_PREFIX) {
+        return None;
+    }
+    Some(
+        Command::try_from(any)
+            .map_err(|e| AppError::InvalidArgument(format!("无法解码 FlightSQL 命令: {}", e))),
+    )
+}
+
+/// 解码 `do_action` 请求体中 `Any` 编码的消息
+pub fn unpack<M: ProstMessageExt>(body: &[u8]) -> Result<M, AppError> {
+    Any::decode(body)
+        .ok()
+        .and_then(|any| any.unpack::<M>().ok().flatten())
+        .ok_or_else(|| AppError::InvalidArgument(format!("请求体不是 {}", M::type_url())))
+}
+
+/// 单端点的 `FlightInfo`，端点 Ticket 为 `Any` 编码的 `ticket`
+pub fn flight_info(
+    descriptor: FlightDescriptor,
+    schema: &Schema,
+    ticket: Any,
+) -> Result<FlightInfo, AppError> {
+    let endpoint = FlightEndpoint::new().with_ticket(Ticket::new(ticket.encode_to_vec()));
+    Ok(FlightInfo::new()
+        .try_with_schema(schema)
+        .map_err(arrow_error)?
+        .with_descriptor(descriptor)
+        .with_endpoint(endpoint))
+}
+
+/// 把元数据结果集编码为 Flight 数据流
+pub fn encode(batch: RecordBatch) -> BoxStream<'static, Result<FlightData, Status>> {
+    FlightDataEncoderBuilder::new()
+        .build(futures::stream::once(async move { Ok(batch) }))
+        .map_err(Status::from)
+        .boxed()
+}
+
+fn table_type(table_type: TableType) -> &'static str {
+    match table_type {
+        TableType::Base => "TABLE",
+        TableType::View => "VIEW",
+        TableType::Temporary => "LOCAL TEMPORARY",
+    }
+}
+
+/// `CommandGetTables`：列出全部目录中的表，过滤条件由 arrow-flight 的构建器施加
+pub async fn tables(
+    ctx: &SessionContext,
+    query: CommandGetTables,
+) -> Result<RecordBatch, AppError> {
+    let mut builder = query.into_builder();
+    for catalog_name in ctx.catalog_names() {
+        let Some(catalog) = ctx.catalog(&catalog_name) else {
+            continue;
+        };
+        for schema_name in catalog.schema_names() {
+            let Some(schema) = catalog.schema(&schema_name) else {
+                continue;
+            };
+            for table_name in schema.table_names() {
+                let Some(table) = schema.table(&table_name).await? else {
+                    continue;
+                };
+                builder
+                    .append(
+                        &catalog_name,
+                        &schema_name,
+                        &table_name,
+                        table_type(table.table_type()),
+                        &table.schema(),
+                    )
+                    .map_err(flight_error)?;
+            }
+        }
+    }
+    builder.build().map_err(flight_error)
+}
+
+/// `CommandGetDbSchemas`：列出全部目录中的模式
+pub fn db_schemas(
+    ctx: &SessionContext,
+    query: CommandGetDbSchemas,
+) -> Result<RecordBatch, AppError> {
+    let mut builder = query.into_builder();
+    for catalog_name in ctx.catalog_names() {
+        if let Some(catalog) = ctx.catalog(&catalog_name) {
+            for schema_name in catalog.schema_names() {
+                builder.append(&catalog_name, schema_name);
+            }
+        }
+    }
+    builder.build().map_err(flight_error)
+}
+
+/// 服务端能力描述；只读、只支持 SQL
+fn sql_info_data() -> &'static SqlInfoData {
+    static INFO: OnceLock<SqlInfoData> = OnceLock::new();
+    INFO.get_or_init(|| {
+        let mut builder = SqlInfoDataBuilder::new();
+        builder.append(SqlInfo::FlightSqlServerName, env!("CARGO_PKG_NAME"));
+        builder.append(SqlInfo::FlightSqlServerVersion, env!("CARGO_PKG_VERSION"));
+        builder.append(SqlInfo::FlightSqlServerArrowVersion, "53");
+        builder.append(SqlInfo::FlightSqlServerReadOnly, true);
+        builder.append(SqlInfo::FlightSqlServerSql, true);
+        builder.append(SqlInfo::FlightSqlServerSubstrait, false);
+        builder.build().expect("SqlInfo 元数据可以构建")
+    })
+}
+
+/// `CommandGetSqlInfo`：按请求的信息项返回能力描述；未指定时返回全部
+pub fn sql_info(query: CommandGetSqlInfo) -> Result<RecordBatch, AppError> {
+    query
+        .into_builder(sql_info_data())
+        .build()
+        .map_err(flight_error)
+}
+
+/// 预编译语句句柄：语句 ID 与已绑定的参数
+pub fn statement_handle(statement_id: &str, params: Vec<serde_json::Value>) -> Vec<u8> {
+    let ticket = StatementTicket {
+        statement_id: statement_id.to_string(),
+        params,
+    };
+    serde_json::to_vec(&ticket).expect("statement ticket serializes")
+}
+
+pub fn parse_handle(handle: &[u8]) -> Result<StatementTicket, AppError> {
+    serde_json::from_slice(handle)
+        .map_err(|e| AppError::InvalidArgument(format!("无效的预编译语句句柄: {}", e)))
+}
+
+/// 以 IPC 消息编码 schema，用于 `ActionCreatePreparedStatementResult`
+pub fn schema_ipc(schema: &Schema) -> Result<Vec<u8>, AppError> {
+    let message: IpcMessage = SchemaAsIpc::new(schema, &IpcWriteOptions::default())
+        .try_into()
+        .map_err(arrow_error)?;
+    Ok(message.0.to_vec())
+}
+
+/// 占位符按序号排列的参数 schema；无法推断类型的参数为 `Null`
+pub fn parameter_schema(plan: &LogicalPlan) -> Result<Schema, AppError> {
+    let mut params: Vec<(String, Option<DataType>)> =
+        plan.get_parameter_types()?.into_iter().collect();
+    params.sort_by_key(|(name, _)| placeholder_index(name));
+    Ok(Schema::new(
+        params
+            .into_iter()
+            .map(|(name, ty)| Field::new(name, ty.unwrap_or(DataType::Null), true))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// `do_put` 发来的参数批次（单行）转换为 `StatementTicket` 的 JSON 参数
+pub fn parameter_values(batch: &RecordBatch) -> Result<Vec<serde_json::Value>, AppError> {
+    if batch.num_rows() != 1 {
+        return Err(AppError::InvalidArgument(format!(
+            "参数批次应恰好有 1 行，实际 {} 行",
+            batch.num_rows()
+        )));
+    }
+    batch
+        .columns()
+        .iter()
+        .map(|column| Ok(to_json(ScalarValue::try_from_array(column, 0)?)))
+        .collect()
+}
+
+fn to_json(value: ScalarValue) -> serde_json::Value {
+    use serde_json::Value;
+
+    if value.is_null() {
+        return Value::Null;
+    }
+    match value {
+        ScalarValue::Boolean(Some(b)) => Value::Bool(b),
+        ScalarValue::Int8(Some(v)) => v.into(),
+        ScalarValue::Int16(Some(v)) => v.into(),
+        ScalarValue::Int32(Some(v)) => v.into(),
+        ScalarValue::Int64(Some(v)) => v.into(),
+        ScalarValue::UInt8(Some(v)) => v.into(),
+        ScalarValue::UInt16(Some(v)) => v.into(),
+        ScalarValue::UInt32(Some(v)) => v.into(),
+        ScalarValue::UInt64(Some(v)) => v.into(),
+        ScalarValue::Float32(Some(v)) => float(v as f64),
+        ScalarValue::Float64(Some(v)) => float(v),
+        // 其余类型（字符串、日期、小数等）以文本传递，绑定时按推断的参数类型解析
+        other => Value::String(other.to_string()),
+    }
+}
+
+/// NaN 与无穷没有 JSON 数字表示，以文本传递
+fn float(v: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(v)
+        .map(serde_json::Value::Number)
+        .unwrap_or_else(|| serde_json::Value::String(v.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::{collect_batches, privileged_auth, users_context, TEST_TOKEN};
+    use arrow_flight::flight_service_server::FlightServiceServer;
+    use arrow_flight::sql::client::FlightSqlServiceClient;
+    use datafusion::arrow::array::{Array, ArrayRef, Int64Array, StringArray};
+    use std::sync::Arc;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, Server};
+    use tonic::Code;
+
+    /// 在随机端口上启动服务，返回连接到它的 FlightSQL 客户端
+    async fn serve(svc: DfFlightService) -> FlightSqlServiceClient<Channel> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(FlightServiceServer::new(svc))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let channel = Channel::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        FlightSqlServiceClient::new(channel)
+    }
+
+    /// 依次读取 `FlightInfo` 的全部端点
+    async fn fetch(
+        client: &mut FlightSqlServiceClient<Channel>,
+        info: FlightInfo,
+    ) -> Vec<RecordBatch> {
+        let mut batches = Vec::new();
+        for endpoint in info.endpoint {
+            let stream = client.do_get(endpoint.ticket.unwrap()).await.unwrap();
+            batches.extend(stream.try_collect::<Vec<_>>().await.unwrap());
+        }
+        batches
+    }
+
+    fn strings(batches: &[RecordBatch], column: &str) -> Vec<String> {
+        batches
+            .iter()
+            .flat_map(|batch| {
+                let values = batch
+                    .column_by_name(column)
+                    .unwrap()
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                values.iter().map(|v| v.unwrap().to_string()).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn flight_sql_client_lists_tables_schemas_and_sql_info() {
+        let mut client = serve(DfFlightService::new(users_context().await)).await;
+
+        let info = client
+            .get_tables(CommandGetTables {
+                catalog: None,
+                db_schema_filter_pattern: None,
+                table_name_filter_pattern: Some("us%".to_string()),
+                table_types: vec![],
+                include_schema: true,
+            })
+            .await
+            .unwrap();
+        let tables = fetch(&mut client, info).await;
+        assert_eq!(strings(&tables, "table_name"), ["users"]);
+        assert_eq!(strings(&tables, "table_type"), ["TABLE"]);
+        assert_eq!(strings(&tables, "db_schema_name"), ["public"]);
+        assert!(tables[0].column_by_name("table_schema").is_some());
+
+        let info = client
+            .get_db_schemas(CommandGetDbSchemas {
+                catalog: Some("datafusion".to_string()),
+                db_schema_filter_pattern: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(strings(&fetch(&mut client, info).await, "db_schema_name"), ["public"]);
+
+        let info = client
+            .get_sql_info(vec![SqlInfo::FlightSqlServerName, SqlInfo::FlightSqlServerReadOnly])
+            .await
+            .unwrap();
+        let rows: usize = fetch(&mut client, info).await.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 2);
+    }
+
+    #[tokio::test]
+    async fn prepared_statement_binds_parameters_end_to_end() {
+        let svc = DfFlightService::new(users_context().await)
+            .with_auth(privileged_auth())
+            .with_legacy_tickets(false);
+        let mut client = serve(svc).await;
+        client.set_header("authorization", format!("Bearer {}", TEST_TOKEN));
+
+        let mut stmt = client
+            .prepare("SELECT name FROM users WHERE age > $1 ORDER BY id".to_string(), None)
+            .await
+            .unwrap();
+        let parameters = stmt.parameter_schema().unwrap();
+        assert_eq!(parameters.fields().len(), 1);
+        assert_eq!(parameters.field(0).data_type(), &DataType::Int64);
+        assert_eq!(stmt.dataset_schema().unwrap().field(0).name(), "name");
+
+        let params = RecordBatch::try_from_iter(vec![(
+            "$1",
+            Arc::new(Int64Array::from(vec![29])) as ArrayRef,
+        )])
+        .unwrap();
+        stmt.set_parameters(params).unwrap();
+        let info = stmt.execute().await.unwrap();
+        assert_eq!(strings(&fetch(&mut client, info).await, "name"), ["Bob", "Charlie", "Eve"]);
+        stmt.close().await.unwrap();
+
+        let info = client
+            .execute("SELECT count(*) AS n FROM users".to_string(), None)
+            .await
+            .unwrap();
+        let batches = fetch(&mut client, info).await;
+        let counts = batches[0]
+            .column_by_name("n")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(counts.value(0), 5);
+
+        // 关闭旧协议后原始 SQL Ticket 被拒绝；默认仍然接受
+        let legacy_off = DfFlightService::new(users_context().await).with_legacy_tickets(false);
+        let err = collect_batches(&legacy_off, "SELECT 1").await.unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let legacy_on = DfFlightService::new(users_context().await);
+        assert_eq!(collect_batches(&legacy_on, "SELECT 1").await.unwrap().len(), 1);
+    }
+}
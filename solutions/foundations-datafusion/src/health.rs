@@ -0,0 +1,357 @@
+//! 健康与就绪状态
+//!
+//! `HealthRegistry` 汇总服务各组件的状态，并通过 gRPC 健康检查协议（`tonic-health`）与
+//! `do_action("health")` 对外暴露：
+//! - 就绪（readiness）：配置已加载、示例表与 data_path 表注册完成、（分片模式下）可用后端数不低于下限；
+//! - 存活（liveness）：查询执行器未发生 panic，且包裹查询执行的 `CircuitBreaker` 未熔断。
+//!
+//! 就绪且存活时上报 `SERVING`，否则上报 `NOT_SERVING`。
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+use tracing::{info, warn};
+
+/// Flight 服务在健康检查协议中的服务名
+pub const FLIGHT_SERVICE_NAME: &str = "arrow.flight.protocol.FlightService";
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    /// 冷却期已过，放行探测请求
+    HalfOpen,
+}
+
+/// 查询执行熔断器：连续失败次数或滑动窗口内的错误率超过阈值时熔断
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    consecutive_failure_threshold: u32,
+    max_error_rate: f64,
+    /// 计算错误率所需的最少样本数，避免少量请求造成误判
+    min_samples: usize,
+    window: Duration,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerInner {
+    consecutive_failures: u32,
+    /// (时间, 是否成功)
+    outcomes: VecDeque<(Instant, bool)>,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(consecutive_failure_threshold: u32, max_error_rate: f64) -> Self {
+        Self {
+            consecutive_failure_threshold: consecutive_failure_threshold.max(1),
+            max_error_rate,
+            min_samples: 20,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(30),
+            inner: Mutex::new(BreakerInner::default()),
+        }
+    }
+
+    /// 设置熔断后的冷却时间
+    #[cfg(test)]
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn state(&self) -> BreakerState {
+        let inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => BreakerState::Closed,
+            Some(at) if at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// 熔断期间拒绝请求；半开状态放行，由下一次结果决定闭合或重新熔断
+    pub fn allow(&self) -> bool {
+        self.state() != BreakerState::Open
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        self.push_outcome(&mut inner, true);
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        self.push_outcome(&mut inner, false);
+
+        let half_open = inner
+            .opened_at
+            .is_some_and(|at| at.elapsed() >= self.cooldown);
+        let tripped = inner.consecutive_failures >= self.consecutive_failure_threshold
+            || self.error_rate_exceeded(&inner);
+        if half_open || (inner.opened_at.is_none() && tripped) {
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// 滑动窗口内的错误率
+    pub fn error_rate(&self) -> f64 {
+        let mut inner = self.inner.lock().unwrap();
+        self.evict(&mut inner);
+        Self::rate(&inner)
+    }
+
+    fn push_outcome(&self, inner: &mut BreakerInner, success: bool) {
+        inner.outcomes.push_back((Instant::now(), success));
+        self.evict(inner);
+    }
+
+    fn evict(&self, inner: &mut BreakerInner) {
+        while let Some((at, _)) = inner.outcomes.front() {
+            if at.elapsed() < self.window {
+                break;
+            }
+            inner.outcomes.pop_front();
+        }
+    }
+
+    fn error_rate_exceeded(&self, inner: &BreakerInner) -> bool {
+        inner.outcomes.len() >= self.min_samples && Self::rate(inner) > self.max_error_rate
+    }
+
+    fn rate(inner: &BreakerInner) -> f64 {
+        if inner.outcomes.is_empty() {
+            return 0.0;
+        }
+        let failures = inner.outcomes.iter().filter(|(_, ok)| !ok).count();
+        failures as f64 / inner.outcomes.len() as f64
+    }
+}
+
+/// `do_action("health")` 返回的健康快照
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSnapshot {
+    pub serving: bool,
+    pub ready: bool,
+    pub live: bool,
+    pub config_loaded: bool,
+    pub tables_registered: bool,
+    pub backends_connected: usize,
+    pub backends_required: usize,
+    pub executor_panicked: bool,
+    pub breaker: BreakerState,
+    pub error_rate: f64,
+}
+
+#[derive(Debug, Default)]
+struct ComponentState {
+    config_loaded: bool,
+    tables_registered: bool,
+    backends_connected: usize,
+    backends_required: usize,
+    executor_panicked: bool,
+}
+
+/// 服务组件健康登记表
+pub struct HealthRegistry {
+    reporter: HealthReporter,
+    breaker: CircuitBreaker,
+    state: Mutex<ComponentState>,
+}
+
+impl HealthRegistry {
+    /// 创建登记表并立即上报 `NOT_SERVING`，直到各组件就绪
+    pub async fn new(reporter: HealthReporter, breaker: CircuitBreaker) -> Self {
+        let registry = Self {
+            reporter,
+            breaker,
+            state: Mutex::new(ComponentState::default()),
+        };
+        registry.publish().await;
+        registry
+    }
+
+    pub async fn mark_config_loaded(&self) {
+        self.state.lock().unwrap().config_loaded = true;
+        self.publish().await;
+    }
+
+    pub async fn mark_tables_registered(&self) {
+        self.state.lock().unwrap().tables_registered = true;
+        self.publish().await;
+    }
+
+
+    /// 查询执行器发生 panic：服务不再存活，需要重启
+    pub async fn record_panic(&self) {
+        self.state.lock().unwrap().executor_panicked = true;
+        self.publish().await;
+    }
+
+    /// 熔断器是否放行本次查询
+    pub fn allow_query(&self) -> bool {
+        self.breaker.allow()
+    }
+
+    /// 记录一次查询结果，并在熔断状态变化时更新健康状态
+    pub async fn record_query(&self, success: bool) {
+        let before = self.breaker.state();
+        if success {
+            self.breaker.record_success();
+        } else {
+            self.breaker.record_failure();
+        }
+        if self.breaker.state() != before {
+            self.publish().await;
+        }
+    }
+
+    pub fn snapshot(&self) -> HealthSnapshot {
+        let state = self.state.lock().unwrap();
+        let breaker = self.breaker.state();
+        let ready = state.config_loaded
+            && state.tables_registered
+            && state.backends_connected >= state.backends_required;
+        let live = !state.executor_panicked && breaker != BreakerState::Open;
+        HealthSnapshot {
+            serving: ready && live,
+            ready,
+            live,
+            config_loaded: state.config_loaded,
+            tables_registered: state.tables_registered,
+            backends_connected: state.backends_connected,
+            backends_required: state.backends_required,
+            executor_panicked: state.executor_panicked,
+            breaker,
+            error_rate: self.breaker.error_rate(),
+        }
+    }
+
+    /// 把当前状态同步到 gRPC 健康检查服务（整体状态 "" 与 Flight 服务）
+    pub async fn publish(&self) {
+        let snapshot = self.snapshot();
+        let status = if snapshot.serving {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+        // HealthReporter 内部共享状态，克隆后即可在 `&self` 上更新
+        let mut reporter = self.reporter.clone();
+        for service in ["", FLIGHT_SERVICE_NAME] {
+            reporter.set_service_status(service, status).await;
+        }
+        if snapshot.serving {
+            info!("健康状态: SERVING");
+        } else {
+            warn!(
+                "健康状态: NOT_SERVING (ready={}, live={}, breaker={:?})",
+                snapshot.ready, snapshot.live, snapshot.breaker
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::{do_action, users_context};
+    use std::sync::Arc;
+    use tonic::Request;
+    use tonic_health::pb::health_check_response::ServingStatus as PbStatus;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, Server};
+    use tonic_health::pb::health_client::HealthClient;
+    use tonic_health::pb::HealthCheckRequest;
+
+    async fn check(client: &mut HealthClient<Channel>) -> PbStatus {
+        let resp = client
+            .check(Request::new(HealthCheckRequest {
+                service: FLIGHT_SERVICE_NAME.to_string(),
+            }))
+            .await
+            .unwrap();
+        PbStatus::try_from(resp.into_inner().status).unwrap()
+    }
+
+    /// 在随机端口上起健康检查服务，返回连上它的客户端
+    async fn ready_registry(threshold: u32) -> (HealthClient<Channel>, Arc<HealthRegistry>) {
+        let (reporter, health_service) = tonic_health::server::health_reporter();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(health_service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let registry = HealthRegistry::new(reporter, CircuitBreaker::new(threshold, 0.5)).await;
+        registry.mark_config_loaded().await;
+        let channel = Channel::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let client = HealthClient::new(channel);
+        (client, Arc::new(registry))
+    }
+
+    #[tokio::test]
+    async fn not_serving_until_tables_are_registered() {
+        let (mut client, registry) = ready_registry(5).await;
+        assert_eq!(check(&mut client).await, PbStatus::NotServing);
+
+        registry.mark_tables_registered().await;
+        assert_eq!(check(&mut client).await, PbStatus::Serving);
+    }
+
+    #[tokio::test]
+    async fn consecutive_failures_flip_back_to_not_serving() {
+        let (mut client, registry) = ready_registry(3).await;
+        registry.mark_tables_registered().await;
+        assert_eq!(check(&mut client).await, PbStatus::Serving);
+
+        registry.record_query(false).await;
+        registry.record_query(false).await;
+        assert_eq!(check(&mut client).await, PbStatus::Serving);
+        registry.record_query(false).await;
+        assert_eq!(check(&mut client).await, PbStatus::NotServing);
+        assert!(!registry.allow_query());
+        assert!(!registry.snapshot().live);
+    }
+
+    #[tokio::test]
+    async fn half_open_breaker_closes_after_success() {
+        let breaker = CircuitBreaker::new(1, 0.5).with_cooldown(Duration::from_millis(10));
+        breaker.record_failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.allow());
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn health_action_reports_snapshot() {
+        let (_client, registry) = ready_registry(5).await;
+        let svc = DfFlightService::new(users_context().await).with_health(registry.clone());
+        let body = do_action(&svc, "health", Vec::new()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["ready"], false);
+        assert_eq!(json["tables_registered"], false);
+
+        registry.mark_tables_registered().await;
+        let body = do_action(&svc, "health", Vec::new()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["serving"], true);
+    }
+}
@@ -0,0 +1,374 @@
+//! `do_get` 结果限制与背压
+//!
+//! 每个查询受 `max_rows` / `max_result_bytes` 限制，超大批次按 `max_batch_rows` 重新切块；
+//! 默认值来自 `AppConfig`，客户端可通过请求元数据（`x-max-rows` 等）进一步收紧，但不能放宽。
+//! 命中限制时结果被截断，最后一条 `FlightData` 的 `app_metadata` 为 `truncated=true`。
+//!
+//! DataFusion 流与 gRPC 发送端之间是一个有界通道：客户端读得慢时生产者在 `send` 处等待，
+//! 服务端缓冲的批次数不超过通道容量，内存占用与结果集大小无关。生产者在拉取每个批次前检查
+//! 取消令牌（见 `cancel`），取消后丢弃 DataFusion 流并停止生产。
+
+use arrow_flight::FlightData;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::DataFusionError;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use futures::{Stream, StreamExt};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tonic::metadata::MetadataMap;
+use tonic::Status;
+
+use crate::error::AppError;
+
+/// 截断标记，写入最后一条 `FlightData` 的 `app_metadata`
+pub const TRUNCATED_METADATA: &[u8] = b"truncated=true";
+
+/// 单个查询的结果限制；`None` 表示不限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryLimits {
+    pub max_rows: Option<usize>,
+    pub max_result_bytes: Option<usize>,
+    pub max_batch_rows: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_rows: None,
+            max_result_bytes: None,
+            max_batch_rows: 8192,
+        }
+    }
+}
+
+impl QueryLimits {
+    /// 由配置值构建，0 表示不限
+    pub fn new(max_rows: usize, max_result_bytes: usize, max_batch_rows: usize) -> Self {
+        Self {
+            max_rows: (max_rows > 0).then_some(max_rows),
+            max_result_bytes: (max_result_bytes > 0).then_some(max_result_bytes),
+            max_batch_rows: max_batch_rows.max(1),
+        }
+    }
+
+    /// 应用请求元数据中的覆盖值；只取更严格的一方
+    pub fn with_overrides(self, metadata: &MetadataMap) -> Result<Self, AppError> {
+        let read = |key: &str| -> Result<Option<usize>, AppError> {
+            let Some(value) = metadata.get(key) else {
+                return Ok(None);
+            };
+            value
+                .to_str()
+                .ok()
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .map(Some)
+                .ok_or_else(|| AppError::InvalidArgument(format!("元数据 {} 必须是正整数", key)))
+        };
+        let tighten = |current: Option<usize>, requested: Option<usize>| match (current, requested) {
+            (Some(c), Some(r)) => Some(c.min(r)),
+            (c, r) => c.or(r),
+        };
+
+        Ok(Self {
+            max_rows: tighten(self.max_rows, read("x-max-rows")?),
+            max_result_bytes: tighten(self.max_result_bytes, read("x-max-result-bytes")?),
+            max_batch_rows: read("x-max-batch-rows")?
+                .map_or(self.max_batch_rows, |r| r.min(self.max_batch_rows)),
+        })
+    }
+
+    /// 在已输出 `rows` 行、`bytes` 字节的前提下，`batch` 可输出的行数及是否命中限制
+    fn fit(&self, batch: &RecordBatch, bytes_per_row: usize, rows: usize, bytes: usize) -> (usize, bool) {
+        let mut take = batch.num_rows();
+        let mut hit = false;
+        if let Some(max) = self.max_rows {
+            let remaining = max.saturating_sub(rows);
+            if take > remaining {
+                take = remaining;
+                hit = true;
+            }
+        }
+        if let Some(max) = self.max_result_bytes {
+            let remaining = max.saturating_sub(bytes);
+            if take * bytes_per_row > remaining {
+                take = remaining / bytes_per_row.max(1);
+                hit = true;
+            }
+        }
+        (take, hit)
+    }
+}
+
+/// 按 `max_rows` 切分批次（零拷贝 slice）
+fn rechunk(batch: &RecordBatch, max_rows: usize) -> impl Iterator<Item = RecordBatch> + '_ {
+    (0..batch.num_rows())
+        .step_by(max_rows)
+        .map(move |offset| batch.slice(offset, max_rows.min(batch.num_rows() - offset)))
+}
+
+/// 受限结果流：有界通道的接收端与截断/缓冲统计
+pub struct LimitedStream {
+    pub receiver: mpsc::Receiver<Result<RecordBatch, DataFusionError>>,
+    pub truncated: Arc<AtomicBool>,
+    /// 通道中同时排队的最大批次数（测试用于验证有界缓冲）
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub peak_buffered: Arc<AtomicUsize>,
+}
+
+impl LimitedStream {
+    pub fn into_stream(self) -> impl Stream<Item = Result<RecordBatch, DataFusionError>> {
+        futures::stream::unfold(self.receiver, |mut rx| async move {
+            rx.recv().await.map(|batch| (batch, rx))
+        })
+    }
+}
+
+/// 在后台任务中拉取 DataFusion 流，按限制切块/截断后写入容量为 `capacity` 的通道
+pub fn spawn_limited(
+    mut input: SendableRecordBatchStream,
+    limits: QueryLimits,
+    capacity: usize,
+    cancel: CancellationToken,
+) -> LimitedStream {
+    let capacity = capacity.max(1);
+    let (tx, receiver) = mpsc::channel(capacity);
+    let truncated = Arc::new(AtomicBool::new(false));
+    let peak_buffered = Arc::new(AtomicUsize::new(0));
+
+    let (flag, peak) = (truncated.clone(), peak_buffered.clone());
+    tokio::spawn(async move {
+        let (mut rows, mut bytes) = (0usize, 0usize);
+        'outer: loop {
+            let item = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => break,
+                item = input.next() => item,
+            };
+            let Some(item) = item else {
+                break;
+            };
+            let batch = match item {
+                Ok(batch) => batch,
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    break;
+                }
+            };
+            let bytes_per_row = batch.get_array_memory_size() / batch.num_rows().max(1);
+            for chunk in rechunk(&batch, limits.max_batch_rows) {
+                let (take, hit) = limits.fit(&chunk, bytes_per_row, rows, bytes);
+                if hit {
+                    // 先置位再发送，保证消费者看到流结束时标记已可见
+                    flag.store(true, Ordering::Release);
+                }
+                if take > 0 {
+                    rows += take;
+                    bytes += take * bytes_per_row;
+                    if tx.send(Ok(chunk.slice(0, take))).await.is_err() {
+                        // 客户端已断开
+                        break 'outer;
+                    }
+                    peak.fetch_max(capacity - tx.capacity(), Ordering::Relaxed);
+                }
+                if hit {
+                    break 'outer;
+                }
+            }
+        }
+    });
+
+    LimitedStream {
+        receiver,
+        truncated,
+        peak_buffered,
+    }
+}
+
+/// 缓存一条消息作为前瞻：流结束时若发生截断，在最后一条消息上写入截断标记
+pub fn mark_truncated<S>(
+    stream: S,
+    truncated: Arc<AtomicBool>,
+) -> impl Stream<Item = Result<FlightData, Status>>
+where
+    S: Stream<Item = Result<FlightData, Status>> + Send + Unpin,
+{
+    futures::stream::unfold(
+        (stream.fuse(), None::<FlightData>),
+        move |(mut stream, mut pending)| {
+            let truncated = truncated.clone();
+            async move {
+                loop {
+                    match stream.next().await {
+                        Some(Ok(data)) => {
+                            if let Some(prev) = pending.replace(data) {
+                                return Some((Ok(prev), (stream, pending)));
+                            }
+                        }
+                        Some(Err(e)) => return Some((Err(e), (stream, None))),
+                        None => {
+                            let mut last = pending.take()?;
+                            if truncated.load(Ordering::Acquire) {
+                                last.app_metadata = TRUNCATED_METADATA.to_vec().into();
+                            }
+                            return Some((Ok(last), (stream, None)));
+                        }
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::collect_batches;
+    use arrow_flight::decode::FlightRecordBatchStream;
+    use arrow_flight::error::FlightError;
+    use arrow_flight::flight_service_server::FlightService;
+    use arrow_flight::Ticket;
+    use datafusion::arrow::array::{ArrayRef, Int64Array};
+    use datafusion::datasource::MemTable;
+    use datafusion::prelude::*;
+    use futures::TryStreamExt;
+    use std::time::Duration;
+    use tonic::Request;
+
+    /// 100 万行的 `numbers` 表，每批 65536 行
+    fn million_rows() -> SessionContext {
+        let batches: Vec<RecordBatch> = (0..1_000_000i64)
+            .step_by(65_536)
+            .map(|start| {
+                let end = (start + 65_536).min(1_000_000);
+                let values = Int64Array::from_iter_values(start..end);
+                RecordBatch::try_from_iter(vec![("n", Arc::new(values) as ArrayRef)]).unwrap()
+            })
+            .collect();
+        let table = MemTable::try_new(batches[0].schema(), vec![batches]).unwrap();
+        let ctx = SessionContext::new();
+        ctx.register_table("numbers", Arc::new(table)).unwrap();
+        ctx
+    }
+
+    async fn raw_do_get(svc: &DfFlightService, request: Request<Ticket>) -> Vec<FlightData> {
+        svc.do_get(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    async fn decode(messages: Vec<FlightData>) -> Vec<RecordBatch> {
+        let stream = futures::stream::iter(messages.into_iter().map(Ok::<_, FlightError>));
+        FlightRecordBatchStream::new_from_flight_data(stream)
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn max_rows_truncates_million_row_table() {
+        let svc = DfFlightService::new(million_rows())
+            .with_limits(QueryLimits::new(10_000, 0, 4096), 4);
+        let messages = raw_do_get(&svc, Request::new(Ticket::new("SELECT * FROM numbers"))).await;
+        assert_eq!(messages.last().unwrap().app_metadata.as_ref(), TRUNCATED_METADATA);
+
+        let batches = decode(messages).await;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 10_000);
+        assert!(batches.iter().all(|b| b.num_rows() <= 4096));
+    }
+
+    #[tokio::test]
+    async fn metadata_can_only_tighten_limits() {
+        let svc = DfFlightService::new(million_rows())
+            .with_limits(QueryLimits::new(10_000, 0, 8192), 4);
+
+        let mut request = Request::new(Ticket::new("SELECT * FROM numbers"));
+        request.metadata_mut().insert("x-max-rows", "100".parse().unwrap());
+        let batches = decode(raw_do_get(&svc, request).await).await;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 100);
+
+        let mut request = Request::new(Ticket::new("SELECT * FROM numbers"));
+        request.metadata_mut().insert("x-max-rows", "50000".parse().unwrap());
+        let batches = decode(raw_do_get(&svc, request).await).await;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 10_000);
+
+        let mut request = Request::new(Ticket::new("SELECT * FROM numbers"));
+        request.metadata_mut().insert("x-max-rows", "abc".parse().unwrap());
+        let err = svc.do_get(request).await.err().unwrap();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn untruncated_result_has_no_marker() {
+        let svc = DfFlightService::new(million_rows())
+            .with_limits(QueryLimits::new(10_000, 0, 8192), 4);
+        let messages = raw_do_get(
+            &svc,
+            Request::new(Ticket::new("SELECT * FROM numbers WHERE n < 500")),
+        )
+        .await;
+        assert!(messages.last().unwrap().app_metadata.is_empty());
+        let rows: usize = collect_batches(&svc, "SELECT * FROM numbers WHERE n < 500")
+            .await
+            .unwrap()
+            .iter()
+            .map(|b| b.num_rows())
+            .sum();
+        assert_eq!(rows, 500);
+    }
+
+    #[tokio::test]
+    async fn slow_reader_keeps_buffer_within_channel_capacity() {
+        let ctx = million_rows();
+        let input = ctx
+            .sql("SELECT * FROM numbers")
+            .await
+            .unwrap()
+            .execute_stream()
+            .await
+            .unwrap();
+        let limited =
+            spawn_limited(input, QueryLimits::new(0, 0, 1024), 2, CancellationToken::new());
+        let peak = limited.peak_buffered.clone();
+        let truncated = limited.truncated.clone();
+
+        let mut stream = Box::pin(limited.into_stream());
+        let mut rows = 0;
+        while let Some(batch) = stream.next().await {
+            rows += batch.unwrap().num_rows();
+            if rows < 20 * 1024 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            assert!(peak.load(Ordering::Relaxed) <= 2);
+        }
+        assert_eq!(rows, 1_000_000);
+        assert!(!truncated.load(Ordering::Acquire));
+    }
+
+    #[tokio::test]
+    async fn byte_limit_truncates() {
+        let ctx = million_rows();
+        let input = ctx
+            .sql("SELECT * FROM numbers")
+            .await
+            .unwrap()
+            .execute_stream()
+            .await
+            .unwrap();
+        // 每行约 8 字节
+        let limits = QueryLimits::new(0, 80_000, 8192);
+        let limited = spawn_limited(input, limits, 4, CancellationToken::new());
+        let truncated = limited.truncated.clone();
+        let batches: Vec<RecordBatch> = limited.into_stream().try_collect().await.unwrap();
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert!(rows > 0 && rows <= 10_000, "{rows}");
+        assert!(truncated.load(Ordering::Acquire));
+    }
+}
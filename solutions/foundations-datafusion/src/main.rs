@@ -1,26 +1,58 @@
-use arrow_flight::flight_service_server::{FlightServiceServer, FlightService};
+// tonic 服务按值返回 `Result<_, Status>` 是惯例，Status 较大但不值得装箱
+#![allow(clippy::result_large_err)]
+
+use arrow_flight::flight_service_server::FlightServiceServer;
 use datafusion::prelude::*;
-use foundations::{service, telemetry};
+use foundations::telemetry::{self, settings::TelemetrySettings, TelemetryConfig};
 use std::net::SocketAddr;
 use tonic::transport::Server;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 
+mod audit;
+mod cancel;
 mod config;
+mod deadline;
 mod error;
+mod flight_sql;
+mod health;
+mod limits;
+mod partitions;
+mod prepared;
+mod reflection;
+mod router;
 mod service_impl;
+mod tables;
+#[cfg(test)]
+mod test_util;
+mod udf;
+mod validation;
 
 use config::AppConfig;
 use error::AppError;
+use health::HealthRegistry;
 use service_impl::DfFlightService;
+use std::sync::Arc;
+use tables::TableCatalog;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // 初始化可观测性
-    telemetry::init_default();
+    let telemetry_driver = telemetry::init(TelemetryConfig {
+        service_info: &foundations::service_info!(),
+        settings: &TelemetrySettings::default(),
+        custom_server_routes: vec![],
+    })
+    .map_err(|e| e.to_string())?;
+    tokio::spawn(telemetry_driver);
     
+    // 健康检查：各组件就绪前上报 NOT_SERVING
+    let (reporter, health_service) = tonic_health::server::health_reporter();
+
     // 加载配置
     let config = AppConfig::load()?;
     info!("配置加载完成: {:?}", config);
+    let health = Arc::new(HealthRegistry::new(reporter, config.query.circuit_breaker()).await);
+    health.mark_config_loaded().await;
     
     // 构建 DataFusion 上下文
     let ctx = SessionContext::new();
@@ -31,19 +63,48 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Err(e.into());
     }
     
+    // 扫描 data_path 注册 CSV / Parquet 表，单个文件失败不影响其余文件
+    let catalog = Arc::new(TableCatalog::new(&config.server.data_path));
+    let report = catalog.refresh(&ctx).await;
+    info!("data_path {} 表注册完成: {}", config.server.data_path, report.summary());
+    for name in &report.registered {
+        info!("已注册表 '{}'", name);
+    }
+    for (path, err) in &report.errors {
+        warn!("表注册失败 {}: {}", path, err);
+    }
+    health.mark_tables_registered().await;
+
     // 创建服务实例
-    let svc = DfFlightService::new(ctx);
+    let svc = DfFlightService::new(ctx)
+        .with_validator(config.query.sql_validator()?)
+        .with_catalog(catalog)
+        .with_statement_cache(config.cache.statement_cache())
+        .with_auth(config.auth.clone())
+        .with_health(health)
+        .with_audit(Arc::new(config.audit.query_audit()?))
+        .with_limits(config.query.limits(), config.query.stream_buffer_batches)
+        .with_parallelism(config.query.parallelism_factor)
+        .with_query_timeout(config.query.query_timeout())
+        .with_legacy_tickets(config.query.legacy_tickets);
+    let svc = match config.router.shard_router()? {
+        Some(router) => {
+            info!("以协调者身份运行，分片: {:?}", router.shard_names().collect::<Vec<_>>());
+            svc.with_router(Arc::new(router))
+        }
+        None => svc,
+    };
     
     // 启动服务
-    let addr: SocketAddr = config.server_address.parse()?;
+    let addr: SocketAddr = config.server.address.parse()?;
     info!("启动 DataFusion 服务在地址: {}", addr);
     
-    service::spawn_with_health(
-        Server::builder()
-            .add_service(FlightServiceServer::new(svc))
-            .serve(addr),
-    )
-    .await?;
+    Server::builder()
+        .add_service(health_service)
+        .add_service(reflection::service()?)
+        .add_service(FlightServiceServer::new(svc))
+        .serve(addr)
+        .await?;
     
     Ok(())
 }
@@ -57,13 +118,13 @@ async fn register_sample_tables(ctx: &SessionContext) -> Result<(), AppError> {
 4,Diana,28,Boston
 5,Eve,32,Seattle"#;
     
-    // 写入临时文件
-    let temp_file = tempfile::NamedTempFile::new()?;
+    // 写入临时文件；CSV 在查询时才读取，文件须保留到进程退出
+    let temp_file = tempfile::Builder::new().suffix(".csv").tempfile()?;
     std::fs::write(&temp_file, sample_data)?;
+    let (_, path) = temp_file.keep().map_err(|e| e.error)?;
     
     // 注册为 CSV 表
-    let df = ctx.read_csv(temp_file.path().to_str().unwrap(), Default::default()).await?;
-    ctx.register_table("users", df)?;
+    ctx.register_csv("users", path.to_str().unwrap(), CsvReadOptions::new()).await?;
     
     info!("示例表 'users' 注册成功");
     Ok(())
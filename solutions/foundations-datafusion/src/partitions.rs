@@ -0,0 +1,134 @@
+//! 多端点并行拉取
+//!
+//! 单个端点只能顺序下载整张结果表。`get_flight_info` 把查询按 DataFusion 输出分区拆成多个
+//! 端点（`RoundRobinBatch(parallelism_factor)`），每个端点的 Ticket 携带 SQL 与分区下标；
+//! 客户端并行对各端点调用 `do_get`，每次只读取对应分区。结果限制按单个分区生效。
+
+use arrow_flight::{FlightDescriptor, FlightEndpoint, FlightInfo, Ticket};
+use datafusion::arrow::datatypes::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// 读取单个输出分区的 Ticket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionTicket {
+    pub sql: String,
+    pub partition: usize,
+    /// 规划时的分区数；`do_get` 重新规划后分区数不同则拒绝执行
+    pub partitions: usize,
+}
+
+impl PartitionTicket {
+    /// Ticket 是否为分区读取请求（否则按预编译语句或原始 SQL 处理）
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.first() != Some(&b'{') {
+            return None;
+        }
+        serde_json::from_slice(bytes).ok()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("partition ticket serializes")
+    }
+}
+
+/// 为 `sql` 的每个输出分区生成一个端点
+pub fn flight_info(
+    descriptor: FlightDescriptor,
+    schema: &Schema,
+    sql: &str,
+    partitions: usize,
+) -> Result<FlightInfo, AppError> {
+    let info = FlightInfo::new()
+        .try_with_schema(schema)
+        .map_err(|e| AppError::DataFusion(e.into()))?
+        .with_descriptor(descriptor)
+        .with_ordered(false);
+    Ok((0..partitions).fold(info, |info, partition| {
+        let ticket = PartitionTicket {
+            sql: sql.to_string(),
+            partition,
+            partitions,
+        };
+        info.with_endpoint(FlightEndpoint::new().with_ticket(Ticket::new(ticket.encode())))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::collect_batches;
+    use arrow_flight::flight_service_server::FlightService;
+    use datafusion::arrow::array::{ArrayRef, Int64Array};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::datasource::MemTable;
+    use datafusion::prelude::*;
+    use std::sync::Arc;
+    use tonic::Request;
+
+    /// 1 万行的 `numbers` 表，每批 1000 行
+    fn ten_thousand_rows() -> SessionContext {
+        let batches: Vec<RecordBatch> = (0..10_000i64)
+            .step_by(1_000)
+            .map(|start| {
+                let values = Int64Array::from_iter_values(start..start + 1_000);
+                RecordBatch::try_from_iter(vec![("n", Arc::new(values) as ArrayRef)]).unwrap()
+            })
+            .collect();
+        let table = MemTable::try_new(batches[0].schema(), vec![batches]).unwrap();
+        let ctx = SessionContext::new();
+        ctx.register_table("numbers", Arc::new(table)).unwrap();
+        ctx
+    }
+
+    async fn flight_info_for(svc: &DfFlightService, sql: &str) -> FlightInfo {
+        let descriptor = FlightDescriptor::new_cmd(sql.to_string());
+        svc.get_flight_info(Request::new(descriptor))
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    #[tokio::test]
+    async fn endpoints_fetched_in_parallel_cover_the_whole_table() {
+        let svc = DfFlightService::new(ten_thousand_rows()).with_parallelism(4);
+        let info = flight_info_for(&svc, "SELECT n FROM numbers").await;
+        assert_eq!(info.endpoint.len(), 4);
+
+        let fetches = info.endpoint.iter().map(|endpoint| {
+            let ticket = endpoint.ticket.clone().unwrap().ticket.to_vec();
+            collect_batches(&svc, ticket)
+        });
+        let partitions = futures::future::try_join_all(fetches).await.unwrap();
+        let rows: usize = partitions
+            .iter()
+            .flatten()
+            .map(|batch| batch.num_rows())
+            .sum();
+        assert_eq!(rows, 10_000);
+        assert!(partitions.iter().all(|batches| !batches.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn stale_or_rejected_partition_tickets_fail() {
+        let svc = DfFlightService::new(ten_thousand_rows()).with_parallelism(2);
+        let stale = PartitionTicket {
+            sql: "SELECT n FROM numbers".into(),
+            partition: 3,
+            partitions: 4,
+        };
+        let err = collect_batches(&svc, stale.encode()).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        let write = PartitionTicket {
+            sql: "DROP TABLE numbers".into(),
+            partition: 0,
+            partitions: 2,
+        };
+        let err = collect_batches(&svc, write.encode()).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+}
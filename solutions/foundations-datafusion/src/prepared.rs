@@ -0,0 +1,318 @@
+//! 预编译语句
+//!
+//! `prepare` 动作对带占位符（`$1`, `$2`, ...）的 SQL 只做一次规划，把逻辑计划缓存在
+//! 生成的语句 ID 下；随后 `do_get` 以 `{"statement_id": ..., "params": [...]}` 形式的 Ticket
+//! 绑定参数并执行缓存的计划。缓存有 TTL（按最近一次使用滑动）与数量上限。
+
+use datafusion::arrow::datatypes::DataType;
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::scalar::ScalarValue;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+
+/// `prepare` 动作的请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrepareRequest {
+    pub sql: String,
+}
+
+/// `close_statement` 动作的请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloseStatementRequest {
+    pub statement_id: String,
+}
+
+/// 执行预编译语句的 Ticket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementTicket {
+    pub statement_id: String,
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+}
+
+impl StatementTicket {
+    /// Ticket 是否为预编译语句执行请求（否则按原始 SQL 处理）
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.first() != Some(&b'{') {
+            return None;
+        }
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+/// 占位符及其推断出的类型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterInfo {
+    pub name: String,
+    /// 无法推断时为 `None`
+    pub data_type: Option<String>,
+}
+
+/// `prepare` 动作的响应体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrepareResponse {
+    pub statement_id: String,
+    pub parameters: Vec<ParameterInfo>,
+}
+
+struct PreparedStatement {
+    plan: LogicalPlan,
+    param_types: Vec<(String, Option<DataType>)>,
+    last_used: Instant,
+}
+
+/// 预编译语句缓存
+pub struct PreparedStatementCache {
+    ttl: Duration,
+    max_statements: usize,
+    statements: Mutex<HashMap<String, PreparedStatement>>,
+}
+
+impl PreparedStatementCache {
+    pub fn new(ttl: Duration, max_statements: usize) -> Self {
+        Self {
+            ttl,
+            max_statements: max_statements.max(1),
+            statements: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 缓存已规划的语句，返回语句 ID 与参数信息；超过上限时淘汰最久未使用的语句
+    pub fn insert(&self, plan: LogicalPlan) -> Result<PrepareResponse, AppError> {
+        let mut param_types: Vec<(String, Option<DataType>)> =
+            plan.get_parameter_types()?.into_iter().collect();
+        param_types.sort_by_key(|(name, _)| placeholder_index(name));
+
+        let statement_id = uuid::Uuid::new_v4().to_string();
+        let parameters = param_types
+            .iter()
+            .map(|(name, ty)| ParameterInfo {
+                name: name.clone(),
+                data_type: ty.as_ref().map(|t| t.to_string()),
+            })
+            .collect();
+
+        let mut statements = self.statements.lock().unwrap();
+        let now = Instant::now();
+        statements.retain(|_, s| now.duration_since(s.last_used) < self.ttl);
+        while statements.len() >= self.max_statements {
+            let oldest = statements
+                .iter()
+                .min_by_key(|(_, s)| s.last_used)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => statements.remove(&id),
+                None => break,
+            };
+        }
+        statements.insert(
+            statement_id.clone(),
+            PreparedStatement {
+                plan,
+                param_types,
+                last_used: now,
+            },
+        );
+
+        Ok(PrepareResponse {
+            statement_id,
+            parameters,
+        })
+    }
+
+    /// 绑定参数，返回可直接执行的逻辑计划；未知或已过期的 ID 返回 `StatementNotFound`
+    pub fn bind(
+        &self,
+        statement_id: &str,
+        params: &[serde_json::Value],
+    ) -> Result<LogicalPlan, AppError> {
+        let (plan, param_types) = {
+            let mut statements = self.statements.lock().unwrap();
+            let now = Instant::now();
+            let expired = statements
+                .get(statement_id)
+                .is_some_and(|s| now.duration_since(s.last_used) >= self.ttl);
+            if expired {
+                statements.remove(statement_id);
+            }
+            let stmt = statements
+                .get_mut(statement_id)
+                .ok_or_else(|| AppError::StatementNotFound(statement_id.to_string()))?;
+            stmt.last_used = now;
+            (stmt.plan.clone(), stmt.param_types.clone())
+        };
+
+        if params.len() != param_types.len() {
+            return Err(AppError::InvalidArgument(format!(
+                "需要 {} 个参数，实际 {} 个",
+                param_types.len(),
+                params.len()
+            )));
+        }
+
+        let values = params
+            .iter()
+            .zip(&param_types)
+            .map(|(value, (name, ty))| to_scalar(value, ty.as_ref()).map_err(|e| {
+                AppError::InvalidArgument(format!("参数 {} 无法绑定: {}", name, e))
+            }))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(plan.with_param_values(values)?)
+    }
+
+    /// 关闭语句；返回该语句此前是否存在
+    pub fn remove(&self, statement_id: &str) -> bool {
+        self.statements.lock().unwrap().remove(statement_id).is_some()
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.statements.lock().unwrap().len()
+    }
+}
+
+/// `$3` → 3；非数字占位符排在最后
+pub(crate) fn placeholder_index(name: &str) -> usize {
+    name.trim_start_matches('$').parse().unwrap_or(usize::MAX)
+}
+
+/// 把 JSON 参数转换为 `ScalarValue`；已知参数类型时按该类型解析
+fn to_scalar(
+    value: &serde_json::Value,
+    data_type: Option<&DataType>,
+) -> Result<ScalarValue, AppError> {
+    use serde_json::Value;
+
+    let scalar = match (value, data_type) {
+        (Value::Null, Some(ty)) => ScalarValue::try_from(ty)?,
+        (Value::Null, None) => ScalarValue::Null,
+        (Value::String(s), Some(ty)) => ScalarValue::try_from_string(s.clone(), ty)?,
+        (Value::String(s), None) => ScalarValue::Utf8(Some(s.clone())),
+        (Value::Bool(b), _) => ScalarValue::Boolean(Some(*b)),
+        (Value::Number(n), Some(ty)) => ScalarValue::try_from_string(n.to_string(), ty)?,
+        (Value::Number(n), None) => match n.as_i64() {
+            Some(i) => ScalarValue::Int64(Some(i)),
+            None => ScalarValue::Float64(n.as_f64()),
+        },
+        (other, _) => {
+            return Err(AppError::InvalidArgument(format!(
+                "不支持的参数值: {}",
+                other
+            )));
+        }
+    };
+    Ok(scalar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::{collect_batches, do_action, privileged_auth, users_context};
+    use datafusion::arrow::array::{Array, StringArray};
+    use serde_json::json;
+
+    fn names(batches: &[datafusion::arrow::record_batch::RecordBatch]) -> Vec<String> {
+        let mut out: Vec<String> = batches
+            .iter()
+            .flat_map(|b| {
+                let col = b.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+                (0..col.len()).map(|i| col.value(i).to_string()).collect::<Vec<_>>()
+            })
+            .collect();
+        out.sort();
+        out
+    }
+
+    async fn prepare(svc: &DfFlightService, sql: &str) -> PrepareResponse {
+        let body = serde_json::to_vec(&PrepareRequest { sql: sql.into() }).unwrap();
+        let resp = do_action(svc, "prepare", body).await.unwrap();
+        serde_json::from_slice(&resp).unwrap()
+    }
+
+    fn ticket(statement_id: &str, params: Vec<serde_json::Value>) -> Vec<u8> {
+        serde_json::to_vec(&StatementTicket {
+            statement_id: statement_id.into(),
+            params,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn prepared_statement_executes_with_different_params() {
+        let svc = DfFlightService::new(users_context().await).with_auth(privileged_auth());
+        let prepared = prepare(&svc, "SELECT name FROM users WHERE age > $1").await;
+        assert_eq!(prepared.parameters.len(), 1);
+        assert_eq!(prepared.parameters[0].name, "$1");
+
+        let rows = collect_batches(&svc, ticket(&prepared.statement_id, vec![json!(30)]))
+            .await
+            .unwrap();
+        assert_eq!(names(&rows), vec!["Charlie", "Eve"]);
+
+        let rows = collect_batches(&svc, ticket(&prepared.statement_id, vec![json!(26)]))
+            .await
+            .unwrap();
+        assert_eq!(names(&rows), vec!["Bob", "Charlie", "Diana", "Eve"]);
+    }
+
+    #[tokio::test]
+    async fn unknown_closed_or_expired_statement_is_not_found() {
+        let svc = DfFlightService::new(users_context().await).with_auth(privileged_auth());
+        let err = collect_batches(&svc, ticket("missing", vec![])).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+
+        let prepared = prepare(&svc, "SELECT name FROM users WHERE age > $1").await;
+        let body = serde_json::to_vec(&CloseStatementRequest {
+            statement_id: prepared.statement_id.clone(),
+        })
+        .unwrap();
+        do_action(&svc, "close_statement", body).await.unwrap();
+        let err = collect_batches(&svc, ticket(&prepared.statement_id, vec![json!(1)]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+
+        let svc = DfFlightService::new(users_context().await)
+            .with_auth(privileged_auth())
+            .with_statement_cache(PreparedStatementCache::new(Duration::from_millis(10), 8));
+        let prepared = prepare(&svc, "SELECT name FROM users WHERE age > $1").await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let err = collect_batches(&svc, ticket(&prepared.statement_id, vec![json!(1)]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn prepare_rejects_ddl() {
+        let svc = DfFlightService::new(users_context().await).with_auth(privileged_auth());
+        let body = serde_json::to_vec(&PrepareRequest {
+            sql: "DROP TABLE users".into(),
+        })
+        .unwrap();
+        let err = do_action(&svc, "prepare", body).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used_when_full() {
+        let cache = PreparedStatementCache::new(Duration::from_secs(60), 2);
+        let plan = || LogicalPlan::EmptyRelation(datafusion::logical_expr::EmptyRelation {
+            produce_one_row: true,
+            schema: std::sync::Arc::new(datafusion::common::DFSchema::empty()),
+        });
+        let a = cache.insert(plan()).unwrap().statement_id;
+        let b = cache.insert(plan()).unwrap().statement_id;
+        cache.bind(&a, &[]).unwrap();
+        let _c = cache.insert(plan()).unwrap().statement_id;
+        assert_eq!(cache.len(), 2);
+        assert!(cache.bind(&a, &[]).is_ok());
+        assert!(matches!(cache.bind(&b, &[]), Err(AppError::StatementNotFound(_))));
+    }
+}
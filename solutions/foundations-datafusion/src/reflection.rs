@@ -0,0 +1,76 @@
+//! gRPC 服务反射
+//!
+//! grpcurl、Postman 等工具经反射服务列出服务并解析消息。描述符集由 build.rs 从
+//! `proto/Flight.proto` 生成并嵌入二进制，另外登记 tonic-health 自带的健康检查描述符。
+
+use tonic_reflection::server::v1::{ServerReflection, ServerReflectionServer};
+
+/// `arrow.flight.protocol` 的文件描述符集
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/flight_descriptor.bin"));
+
+/// Flight 与健康检查服务的反射端点
+pub fn service(
+) -> Result<ServerReflectionServer<impl ServerReflection>, tonic_reflection::server::Error> {
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::users_context;
+    use arrow_flight::flight_service_server::FlightServiceServer;
+    use futures::StreamExt;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, Server};
+    use tonic_reflection::pb::v1::server_reflection_client::ServerReflectionClient;
+    use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+    use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+    use tonic_reflection::pb::v1::ServerReflectionRequest;
+
+    #[tokio::test]
+    async fn reflection_lists_the_flight_service() {
+        let (_reporter, health_service) = tonic_health::server::health_reporter();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let svc = DfFlightService::new(users_context().await);
+        tokio::spawn(
+            Server::builder()
+                .add_service(health_service)
+                .add_service(service().unwrap())
+                .add_service(FlightServiceServer::new(svc))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let channel = Channel::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut client = ServerReflectionClient::new(channel);
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(MessageRequest::ListServices(String::new())),
+        };
+        let mut responses = client
+            .server_reflection_info(futures::stream::iter([request]))
+            .await
+            .unwrap()
+            .into_inner();
+        let response = responses.next().await.unwrap().unwrap();
+        let Some(MessageResponse::ListServicesResponse(list)) = response.message_response else {
+            panic!("意外的反射应答: {:?}", response.message_response);
+        };
+        let names: Vec<String> = list.service.into_iter().map(|s| s.name).collect();
+        assert!(
+            names.iter().any(|n| n == "arrow.flight.protocol.FlightService"),
+            "{:?}",
+            names
+        );
+        assert!(names.iter().any(|n| n == "grpc.health.v1.Health"), "{:?}", names);
+    }
+}
@@ -0,0 +1,343 @@
+//! 分片扇出与截止时间预算
+//!
+//! 协调者把同一条 SQL 发给每个分片（各分片持有数据的一部分）并合并结果。客户端的预算先扣除
+//! 协调者自身的规划/合并开销（`planning_overhead`），剩余部分经 `grpc-timeout` 随每个分片的
+//! `do_get` 下发；分片按该预算自行截止（见 `deadline`），协调者在客户端截止时刻仍未返回的
+//! 分片上放弃等待。
+//!
+//! 超过截止时间的分片不影响其余分片的结果：合并后的结果流末尾附加一条只含 `app_metadata`
+//! 的消息 `cut_off=<分片名,...>`，列出被截断的分片。其它错误（如 SQL 被拒绝）使整个查询失败；
+//! 所有分片都超时则返回 `DeadlineExceeded`。
+
+use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_client::FlightServiceClient;
+use arrow_flight::{FlightData, Ticket};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc;
+use datafusion::arrow::record_batch::RecordBatch;
+use futures::{Stream, StreamExt, TryStreamExt};
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tonic::transport::Channel;
+use tonic::{Code, Request, Status};
+
+use crate::error::AppError;
+
+/// 被截断分片标记的前缀，写入合并结果最后一条消息的 `app_metadata`
+pub const CUT_OFF_METADATA_PREFIX: &[u8] = b"cut_off=";
+
+type FlightStream = Pin<Box<dyn Stream<Item = Result<FlightData, Status>> + Send>>;
+
+/// 一个分片返回的批次
+#[derive(Debug, Default)]
+pub struct ShardBatches {
+    pub schema: Option<SchemaRef>,
+    pub batches: Vec<RecordBatch>,
+}
+
+/// 一个分片的结果
+#[derive(Debug)]
+pub struct ShardResult {
+    pub shard: String,
+    /// 自扇出开始到该分片结束的耗时
+    pub elapsed: Duration,
+    pub outcome: Result<ShardBatches, Status>,
+}
+
+impl ShardResult {
+    pub fn cut_off(&self) -> bool {
+        matches!(&self.outcome, Err(status) if status.code() == Code::DeadlineExceeded)
+    }
+}
+
+/// 一次扇出的全部分片结果
+#[derive(Debug)]
+pub struct RoutedResult {
+    /// 客户端的端到端预算
+    pub budget: Duration,
+    /// 下发给分片的预算
+    pub shard_budget: Duration,
+    pub shards: Vec<ShardResult>,
+}
+
+impl RoutedResult {
+    /// 超过截止时间被截断的分片
+    pub fn cut_off(&self) -> Vec<&str> {
+        self.shards
+            .iter()
+            .filter(|s| s.cut_off())
+            .map(|s| s.shard.as_str())
+            .collect()
+    }
+
+    /// 按分片顺序合并批次并编码为 Flight 数据流；有分片被截断时末尾附加 `cut_off=` 标记
+    pub fn into_flight_stream(self) -> Result<FlightStream, Status> {
+        let cut_off: Vec<String> = self.cut_off().into_iter().map(str::to_string).collect();
+        let mut schema = None;
+        let mut batches = Vec::new();
+        for shard in self.shards {
+            match shard.outcome {
+                Ok(result) => {
+                    schema = schema.or(result.schema);
+                    batches.extend(result.batches);
+                }
+                Err(status) if status.code() == Code::DeadlineExceeded => {}
+                Err(status) => {
+                    return Err(Status::new(
+                        status.code(),
+                        format!("分片 {} 失败: {}", shard.shard, status.message()),
+                    ))
+                }
+            }
+        }
+        let Some(schema) = schema else {
+            return Err(Status::deadline_exceeded(format!(
+                "所有分片均未在截止时间前返回: {}",
+                cut_off.join(",")
+            )));
+        };
+
+        let data = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(futures::stream::iter(batches.into_iter().map(Ok)))
+            .map_err(Status::from);
+        if cut_off.is_empty() {
+            return Ok(data.boxed());
+        }
+        let mut marker = CUT_OFF_METADATA_PREFIX.to_vec();
+        marker.extend_from_slice(cut_off.join(",").as_bytes());
+        let trailer = FlightData::new()
+            .with_data_header(metadata_only_header())
+            .with_app_metadata(marker);
+        Ok(data.chain(futures::stream::once(async { Ok(trailer) })).boxed())
+    }
+}
+
+/// 头部类型为 `NONE` 的 IPC 消息：客户端解码器跳过它而不是报错，只读取 `app_metadata`
+fn metadata_only_header() -> Vec<u8> {
+    let mut fbb = flatbuffers::FlatBufferBuilder::new();
+    let mut message = ipc::MessageBuilder::new(&mut fbb);
+    message.add_version(ipc::MetadataVersion::V5);
+    message.add_header_type(ipc::MessageHeader::NONE);
+    message.add_bodyLength(0);
+    let message = message.finish();
+    fbb.finish(message, None);
+    fbb.finished_data().to_vec()
+}
+
+/// 协调者到各分片的扇出
+pub struct ShardRouter {
+    shards: Vec<(String, FlightServiceClient<Channel>)>,
+    planning_overhead: Duration,
+}
+
+impl ShardRouter {
+    /// `planning_overhead`：从客户端预算中为协调者自身保留的规划/合并时间
+    pub fn new(planning_overhead: Duration) -> Self {
+        Self {
+            shards: Vec::new(),
+            planning_overhead,
+        }
+    }
+
+    /// 登记分片；连接在首次请求时建立
+    pub fn with_shard(mut self, name: &str, endpoint: &str) -> Result<Self, AppError> {
+        let channel = Channel::from_shared(endpoint.to_string())
+            .map_err(|e| {
+                AppError::Config(format!("分片 {} 的地址 {} 无效: {}", name, endpoint, e))
+            })?
+            .connect_lazy();
+        self.shards.push((name.to_string(), FlightServiceClient::new(channel)));
+        Ok(self)
+    }
+
+    pub fn shard_names(&self) -> impl Iterator<Item = &str> {
+        self.shards.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// 把 `sql` 并行发给每个分片；分片预算为 `budget` 减去规划开销
+    pub async fn fan_out(&self, sql: &str, budget: Duration) -> Result<RoutedResult, Status> {
+        let started = Instant::now();
+        let shard_budget = budget
+            .checked_sub(self.planning_overhead)
+            .filter(|b| !b.is_zero())
+            .ok_or_else(|| {
+                Status::deadline_exceeded(format!(
+                    "预算 {:?} 不足以覆盖协调者开销 {:?}",
+                    budget, self.planning_overhead
+                ))
+            })?;
+        // 分片忽略下发的预算时，协调者在客户端截止时刻放弃等待
+        let hard_deadline = started + budget;
+
+        let calls = self.shards.iter().map(|(name, client)| {
+            let mut client = client.clone();
+            let mut request = Request::new(Ticket::new(sql.to_string()));
+            request.set_timeout(shard_budget);
+            async move {
+                let call = async move {
+                    let stream = client.do_get(request).await?.into_inner();
+                    collect(stream.map_err(FlightError::from)).await
+                };
+                let outcome = tokio::time::timeout_at(hard_deadline.into(), call)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(Status::deadline_exceeded(format!(
+                            "分片 {} 未在截止时间前返回",
+                            name
+                        )))
+                    });
+                ShardResult {
+                    shard: name.clone(),
+                    elapsed: started.elapsed(),
+                    outcome,
+                }
+            }
+        });
+        Ok(RoutedResult {
+            budget,
+            shard_budget,
+            shards: futures::future::join_all(calls).await,
+        })
+    }
+}
+
+/// 解码一个分片的结果流
+async fn collect(
+    stream: impl Stream<Item = Result<FlightData, FlightError>> + Send + 'static,
+) -> Result<ShardBatches, Status> {
+    let mut decoder = FlightRecordBatchStream::new_from_flight_data(stream);
+    let mut batches = Vec::new();
+    while let Some(batch) = decoder.next().await {
+        batches.push(batch.map_err(|e| match e {
+            FlightError::Tonic(status) => status,
+            other => Status::internal(other.to_string()),
+        })?);
+    }
+    Ok(ShardBatches {
+        schema: decoder.schema().cloned(),
+        batches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::collect_batches;
+    use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+    use datafusion::arrow::array::{ArrayRef, Int64Array};
+    use datafusion::arrow::datatypes::DataType;
+    use datafusion::datasource::MemTable;
+    use datafusion::logical_expr::{create_udf, ColumnarValue, Volatility};
+    use datafusion::prelude::*;
+    use std::sync::Arc;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    /// 20 批、每批 100 行的 `numbers` 表；`slow(n)` 每批阻塞 `delay`（分片只在批次之间检查截止时间）
+    fn numbers_context(delay: Duration) -> SessionContext {
+        let batches: Vec<RecordBatch> = (0..20i64)
+            .map(|i| {
+                let values = Int64Array::from_iter_values(i * 100..(i + 1) * 100);
+                RecordBatch::try_from_iter(vec![("n", Arc::new(values) as ArrayRef)]).unwrap()
+            })
+            .collect();
+        let table = MemTable::try_new(batches[0].schema(), vec![batches]).unwrap();
+        let ctx = SessionContext::new_with_config(SessionConfig::new().with_target_partitions(1));
+        ctx.register_table("numbers", Arc::new(table)).unwrap();
+        ctx.register_udf(create_udf(
+            "slow",
+            vec![DataType::Int64],
+            Arc::new(DataType::Int64),
+            Volatility::Volatile,
+            Arc::new(move |args: &[ColumnarValue]| {
+                std::thread::sleep(delay);
+                Ok(args[0].clone())
+            }),
+        ));
+        ctx
+    }
+
+    /// 在随机端口上启动一个分片，返回其地址
+    async fn spawn_shard(ctx: SessionContext) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(FlightServiceServer::new(DfFlightService::new(ctx)))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        format!("http://{}", addr)
+    }
+
+    async fn router() -> ShardRouter {
+        let fast_a = spawn_shard(numbers_context(Duration::ZERO)).await;
+        let slow = spawn_shard(numbers_context(Duration::from_millis(20))).await;
+        let fast_b = spawn_shard(numbers_context(Duration::ZERO)).await;
+        ShardRouter::new(Duration::from_millis(100))
+            .with_shard("fast-a", &fast_a)
+            .unwrap()
+            .with_shard("slow", &slow)
+            .unwrap()
+            .with_shard("fast-b", &fast_b)
+            .unwrap()
+    }
+
+    const SQL: &str = "SELECT slow(n) AS n FROM numbers";
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn slow_shard_is_cut_off_within_the_client_deadline() {
+        let router = router().await;
+        let budget = Duration::from_millis(300);
+        let started = Instant::now();
+        let routed = router.fan_out(SQL, budget).await.unwrap();
+        assert_eq!(routed.shard_budget, Duration::from_millis(200));
+        assert!(started.elapsed() < budget + Duration::from_millis(50), "{:?}", started.elapsed());
+
+        // 慢分片按下发的预算自行截止，快分片返回全部数据
+        let slow = &routed.shards[1];
+        let status = slow.outcome.as_ref().unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded, "{status}");
+        assert!(slow.elapsed < budget, "{:?}", slow.elapsed);
+        for fast in [&routed.shards[0], &routed.shards[2]] {
+            let batches = &fast.outcome.as_ref().unwrap().batches;
+            let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+            assert_eq!(rows, 2_000, "{}", fast.shard);
+        }
+        assert_eq!(routed.cut_off(), ["slow"]);
+
+        // 预算不足以覆盖协调者开销时不扇出
+        let err = router.fan_out(SQL, Duration::from_millis(80)).await.unwrap_err();
+        assert_eq!(err.code(), Code::DeadlineExceeded);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn coordinator_do_get_marks_cut_off_shards() {
+        let coordinator = DfFlightService::new(SessionContext::new())
+            .with_router(Arc::new(router().await));
+        let mut request = Request::new(Ticket::new(SQL));
+        request.set_timeout(Duration::from_millis(300));
+        let started = Instant::now();
+        let stream = coordinator.do_get(request).await.unwrap().into_inner();
+        let messages: Vec<FlightData> = stream.try_collect().await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(400), "{:?}", started.elapsed());
+        assert_eq!(
+            messages.last().unwrap().app_metadata.as_ref(),
+            b"cut_off=slow"
+        );
+
+        // 元数据消息不影响解码：两个快分片的全部行
+        let batches = collect(futures::stream::iter(messages.into_iter().map(Ok)))
+            .await
+            .unwrap()
+            .batches;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 4_000);
+
+        // 没有截止时间时慢分片也能完成（约 0.4s），不附加标记
+        let batches = collect_batches(&coordinator, SQL).await.unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 6_000);
+    }
+}
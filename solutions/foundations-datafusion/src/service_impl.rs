@@ -1,34 +1,193 @@
 use arrow_flight::{
+    decode::FlightRecordBatchStream,
+    encode::FlightDataEncoderBuilder,
+    error::FlightError,
     flight_service_server::FlightService,
-    FlightData, FlightDescriptor, FlightInfo, HandshakeRequest, HandshakeResponse,
-    PutResult, SchemaResult, Ticket,
+    sql::{
+        ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest,
+        ActionCreatePreparedStatementResult, Command, DoPutPreparedStatementResult,
+        ProstMessageExt, TicketStatementQuery,
+    },
+    ActionType, Criteria, FlightData, FlightDescriptor, FlightInfo, HandshakeRequest,
+    HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket,
 };
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::physical_plan::repartition::RepartitionExec;
+use datafusion::physical_plan::{execute_stream_partitioned, ExecutionPlan, SendableRecordBatchStream};
 use datafusion::prelude::*;
+use futures::{FutureExt, StreamExt, TryStreamExt};
+use prost::Message;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tonic::{Request, Response, Status, Streaming};
 use tracing::{info, error, warn};
 
+use crate::audit::{QueryAudit, QueryTrace};
+use crate::cancel::{self, CancelRequest, QueryRegistry};
+use crate::config::AuthConfig;
+use crate::deadline;
 use crate::error::AppError;
+use crate::flight_sql;
+use crate::health::HealthRegistry;
+use crate::limits::{self, QueryLimits};
+use crate::partitions::{self, PartitionTicket};
+use crate::prepared::{
+    CloseStatementRequest, PrepareRequest, PreparedStatementCache, StatementTicket,
+};
+use crate::router::ShardRouter;
+use crate::tables::TableCatalog;
+use crate::udf::{UdfSpec, WasmUdf};
+use crate::validation::SqlValidator;
+
+/// `do_action` 支持的动作：(类型, 描述)
+const ACTIONS: &[(&str, &str)] = &[
+    ("refresh_tables", "重新扫描 data_path 并注册/移除表"),
+    ("prepare", "规划带占位符的 SQL 并返回语句 ID 与参数信息"),
+    ("close_statement", "关闭预编译语句"),
+    ("health", "返回就绪、存活与熔断器状态"),
+    ("stats", "返回查询计数与规划/执行耗时、结果大小直方图"),
+    ("register_udf", "注册以 Wasm 模块实现的标量 UDF（同名时替换）"),
+    ("cancel_query", "按 x-query-id 取消进行中的 do_get 查询"),
+    ("CreatePreparedStatement", "FlightSQL：创建预编译语句"),
+    ("ClosePreparedStatement", "FlightSQL：关闭预编译语句"),
+];
+
+/// 需要 `auth.privileged_actions` 与有效令牌的动作：会规划任意 SQL、改变目录或影响他人的查询
+const PRIVILEGED_ACTIONS: &[&str] = &[
+    "refresh_tables",
+    "prepare",
+    "register_udf",
+    "cancel_query",
+    flight_sql::CREATE_PREPARED_STATEMENT,
+];
+
+/// 客户端 SQL 的规划选项：校验器之外再由 DataFusion 拒绝规划 DDL、DML 与会话语句
+fn read_only() -> SQLOptions {
+    SQLOptions::new()
+        .with_allow_ddl(false)
+        .with_allow_dml(false)
+        .with_allow_statements(false)
+}
 
 pub struct DfFlightService {
     ctx: Arc<SessionContext>,
+    validator: SqlValidator,
+    auth: AuthConfig,
+    catalog: Option<Arc<TableCatalog>>,
+    statements: PreparedStatementCache,
+    health: Option<Arc<HealthRegistry>>,
+    audit: Arc<QueryAudit>,
+    limits: QueryLimits,
+    /// DataFusion 流与 gRPC 发送端之间的有界通道容量（批次数）
+    stream_buffer: usize,
+    /// `get_flight_info` 把结果拆成的分区（端点）数
+    parallelism: usize,
+    /// 进行中 `do_get` 的取消令牌，键为 `x-query-id`
+    queries: Arc<QueryRegistry>,
+    /// 请求未声明 `grpc-timeout` 时的查询预算；声明了也不能超过它
+    query_timeout: Duration,
+    /// 协调者模式：SQL `do_get` 扇出到各分片并合并结果
+    router: Option<Arc<ShardRouter>>,
+    /// 是否接受 FlightSQL 之前的原始 SQL / JSON Ticket 与描述符
+    legacy_tickets: bool,
 }
 
 impl DfFlightService {
     pub fn new(ctx: SessionContext) -> Self {
         Self {
             ctx: Arc::new(ctx),
+            validator: SqlValidator::default(),
+            auth: AuthConfig::default(),
+            catalog: None,
+            statements: PreparedStatementCache::new(std::time::Duration::from_secs(600), 1024),
+            health: None,
+            audit: Arc::new(QueryAudit::default()),
+            limits: QueryLimits::default(),
+            stream_buffer: 4,
+            parallelism: 1,
+            queries: Arc::new(QueryRegistry::default()),
+            query_timeout: Duration::from_secs(300),
+            router: None,
+            legacy_tickets: true,
         }
     }
+
+    /// 替换预编译语句缓存（TTL 与数量上限）
+    pub fn with_statement_cache(mut self, statements: PreparedStatementCache) -> Self {
+        self.statements = statements;
+        self
+    }
+
+    /// 挂载 data_path 表目录，启用 `refresh_tables` 动作
+    pub fn with_catalog(mut self, catalog: Arc<TableCatalog>) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// 挂载健康登记表：查询执行经过熔断器，结果计入存活状态
+    pub fn with_health(mut self, health: Arc<HealthRegistry>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// 替换查询审计：每次调用写出一条审计记录并计入 `stats` 指标
+    pub fn with_audit(mut self, audit: Arc<QueryAudit>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// 设置默认结果限制与流缓冲容量
+    pub fn with_limits(mut self, limits: QueryLimits, stream_buffer: usize) -> Self {
+        self.limits = limits;
+        self.stream_buffer = stream_buffer.max(1);
+        self
+    }
+
+    /// 设置 `get_flight_info` 返回的端点数（按轮转批次重分区）
+    pub fn with_parallelism(mut self, parallelism_factor: usize) -> Self {
+        self.parallelism = parallelism_factor.max(1);
+        self
+    }
+
+    /// 设置查询超时：请求未声明截止时间时的预算上限
+    pub fn with_query_timeout(mut self, timeout: Duration) -> Self {
+        self.query_timeout = timeout;
+        self
+    }
+
+    /// 以协调者身份运行：原始 SQL 的 `do_get` 扇出到 `router` 的各分片
+    pub fn with_router(mut self, router: Arc<ShardRouter>) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    /// 是否继续接受原始 SQL / JSON Ticket；关闭后只接受 FlightSQL 命令
+    pub fn with_legacy_tickets(mut self, enabled: bool) -> Self {
+        self.legacy_tickets = enabled;
+        self
+    }
+
+    /// 启用 `do_action` 的 Bearer 令牌认证
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// 替换 `do_get` 使用的 SQL 校验器
+    pub fn with_validator(mut self, validator: SqlValidator) -> Self {
+        self.validator = validator;
+        self
+    }
 }
 
 #[tonic::async_trait]
 impl FlightService for DfFlightService {
     type HandshakeStream = Pin<Box<dyn futures::Stream<Item = Result<HandshakeResponse, Status>> + Send>>;
     type ListFlightsStream = Pin<Box<dyn futures::Stream<Item = Result<FlightInfo, Status>> + Send>>;
-    type GetFlightInfoStream = Pin<Box<dyn futures::Stream<Item = Result<FlightInfo, Status>> + Send>>;
-    type GetSchemaStream = Pin<Box<dyn futures::Stream<Item = Result<SchemaResult, Status>> + Send>>;
     type DoGetStream = Pin<Box<dyn futures::Stream<Item = Result<FlightData, Status>> + Send>>;
     type DoPutStream = Pin<Box<dyn futures::Stream<Item = Result<PutResult, Status>> + Send>>;
     type DoActionStream = Pin<Box<dyn futures::Stream<Item = Result<arrow_flight::Result, Status>> + Send>>;
@@ -44,71 +203,103 @@ impl FlightService for DfFlightService {
 
     async fn list_flights(
         &self,
-        _request: Request<FlightDescriptor>,
+        _request: Request<Criteria>,
     ) -> Result<Response<Self::ListFlightsStream>, Status> {
         Err(Status::unimplemented("list_flights not implemented"))
     }
 
     async fn get_flight_info(
         &self,
-        _request: Request<FlightDescriptor>,
-    ) -> Result<Response<Self::GetFlightInfoStream>, Status> {
-        Err(Status::unimplemented("get_flight_info not implemented"))
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let mut trace = self.audit.begin("get_flight_info", request.metadata());
+        match self.plan_flight_info(request.into_inner(), &mut trace).await {
+            Ok(info) => {
+                let mut response = Response::new(info);
+                trace.attach_id(response.metadata_mut());
+                trace.finish();
+                Ok(response)
+            }
+            Err(status) => Err(trace.fail(status)),
+        }
     }
 
     async fn get_schema(
         &self,
         _request: Request<FlightDescriptor>,
-    ) -> Result<Response<Self::GetSchemaStream>, Status> {
+    ) -> Result<Response<SchemaResult>, Status> {
         Err(Status::unimplemented("get_schema not implemented"))
     }
 
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("poll_flight_info not implemented"))
+    }
+
     async fn do_get(
         &self,
         request: Request<Ticket>,
     ) -> Result<Response<Self::DoGetStream>, Status> {
-        let ticket = request.into_inner();
-        let sql = String::from_utf8_lossy(&ticket.ticket);
-        
-        info!("收到 SQL 查询: {}", sql);
-        
-        // 验证 SQL 查询
-        if sql.trim().is_empty() {
-            return Err(Status::invalid_argument("SQL 查询不能为空"));
-        }
-        
-        // 执行查询
-        match self.execute_query(&sql).await {
+        let mut trace = self.audit.begin("do_get", request.metadata());
+        match self.execute_get(request, &mut trace).await {
             Ok(stream) => {
-                info!("查询执行成功");
-                Ok(Response::new(stream))
-            }
-            Err(e) => {
-                error!("查询执行失败: {}", e);
-                Err(Status::internal(e.to_string()))
+                let mut metadata = tonic::metadata::MetadataMap::new();
+                trace.attach_id(&mut metadata);
+                let stream = trace.track(stream).boxed();
+                Ok(Response::from_parts(metadata, stream, Default::default()))
             }
+            Err(status) => Err(trace.fail(status)),
         }
     }
 
     async fn do_put(
         &self,
-        _request: Request<Streaming<FlightData>>,
+        request: Request<Streaming<FlightData>>,
     ) -> Result<Response<Self::DoPutStream>, Status> {
-        Err(Status::unimplemented("do_put not implemented"))
+        let mut trace = self.audit.begin("do_put", request.metadata());
+        let result = match self.execute_put(request, &mut trace).await {
+            Ok(result) => result,
+            Err(status) => return Err(trace.fail(status)),
+        };
+        let mut response: Response<Self::DoPutStream> =
+            Response::new(Box::pin(futures::stream::once(async { Ok::<_, Status>(result) })));
+        trace.attach_id(response.metadata_mut());
+        trace.finish();
+        Ok(response)
     }
 
     async fn do_action(
         &self,
-        _request: Request<arrow_flight::Action>,
+        request: Request<arrow_flight::Action>,
     ) -> Result<Response<Self::DoActionStream>, Status> {
-        Err(Status::unimplemented("do_action not implemented"))
+        let mut trace = self.audit.begin("do_action", request.metadata());
+        let body = match self.execute_action(request, &mut trace).await {
+            Ok(body) => body,
+            Err(status) => return Err(trace.fail(status)),
+        };
+        trace.add_bytes(body.len());
+
+        let result = arrow_flight::Result { body: body.into() };
+        let mut response: Response<Self::DoActionStream> =
+            Response::new(Box::pin(futures::stream::once(async { Ok::<_, Status>(result) })));
+        trace.attach_id(response.metadata_mut());
+        trace.finish();
+        Ok(response)
     }
 
     async fn list_actions(
         &self,
         _request: Request<arrow_flight::Empty>,
     ) -> Result<Response<Self::ListActionsStream>, Status> {
-        Err(Status::unimplemented("list_actions not implemented"))
+        let actions = ACTIONS.iter().map(|(ty, description)| {
+            Ok::<_, Status>(ActionType {
+                r#type: ty.to_string(),
+                description: description.to_string(),
+            })
+        });
+        Ok(Response::new(Box::pin(futures::stream::iter(actions))))
     }
 
     async fn do_exchange(
@@ -120,47 +311,603 @@ impl FlightService for DfFlightService {
 }
 
 impl DfFlightService {
-    async fn execute_query(&self, sql: &str) -> Result<Self::DoGetStream, AppError> {
-        let ctx = self.ctx.clone();
-        let sql = sql.to_string();
-        
-        let stream = async_stream::stream! {
-            match ctx.sql(&sql).await {
-                Ok(df) => {
-                    match df.stream().await {
-                        Ok(mut stream) => {
-                            while let Some(batch) = stream.next().await {
-                                match batch {
-                                    Ok(batch) => {
-                                        let flight_data = FlightData {
-                                            data_header: vec![],
-                                            app_metadata: vec![],
-                                            data_body: vec![],
-                                            flight_descriptor: None,
-                                        };
-                                        yield Ok(flight_data);
-                                    }
-                                    Err(e) => {
-                                        error!("批次处理错误: {}", e);
-                                        yield Err(Status::internal(e.to_string()));
-                                        return;
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("流处理错误: {}", e);
-                            yield Err(Status::internal(e.to_string()));
-                        }
-                    }
+    /// `do_get` 主体：解析 Ticket（SQL、预编译语句或分区）、校验并执行
+    async fn execute_get(
+        &self,
+        request: Request<Ticket>,
+        trace: &mut QueryTrace,
+    ) -> Result<<Self as FlightService>::DoGetStream, Status> {
+        // 请求元数据只能收紧服务端默认限制与查询超时
+        let limits = self.limits.with_overrides(request.metadata())?;
+        let budget = deadline::budget(request.metadata(), self.query_timeout)?;
+        let deadline = Instant::now() + budget;
+        let ticket = request.into_inner();
+
+        if let Some(command) = flight_sql::decode_command(&ticket.ticket) {
+            return self.execute_flight_sql(command?, limits, deadline, trace).await;
+        }
+        if !self.legacy_tickets {
+            return Err(Status::invalid_argument("只接受 FlightSQL 命令 Ticket"));
+        }
+
+        // 分区 Ticket：只读取 `get_flight_info` 规划出的某个输出分区
+        if let Some(part) = PartitionTicket::parse(&ticket.ticket) {
+            info!(
+                query_id = %trace.query_id(),
+                "读取分区 {}/{}: {}", part.partition, part.partitions, part.sql
+            );
+            trace.set_sql(&part.sql);
+            if let Err(e) = self.validator.validate(&part.sql) {
+                warn!("拒绝 SQL 查询: {}", e);
+                return Err(e.into());
+            }
+            let query = self.execute_partition(&part, limits, deadline, trace);
+            return self
+                .guarded(deadline::within(deadline, query))
+                .await
+                .map_err(|e| {
+                    error!("分区查询执行失败: {}", e);
+                    Status::from(e)
+                });
+        }
+
+        // 预编译语句：绑定参数后执行缓存的计划
+        if let Some(stmt) = StatementTicket::parse(&ticket.ticket) {
+            info!(query_id = %trace.query_id(), "执行预编译语句: {}", stmt.statement_id);
+            trace.set_sql(&format!("EXECUTE {}", stmt.statement_id));
+            let query = self.execute_prepared(&stmt, limits, deadline, trace);
+            return self
+                .guarded(deadline::within(deadline, query))
+                .await
+                .map_err(|e| {
+                    error!("预编译语句执行失败: {}", e);
+                    Status::from(e)
+                });
+        }
+
+        let sql = String::from_utf8_lossy(&ticket.ticket);
+        trace.set_sql(&sql);
+
+        info!(query_id = %trace.query_id(), "收到 SQL 查询: {}", sql);
+
+        // 验证 SQL 查询
+        if sql.trim().is_empty() {
+            return Err(Status::invalid_argument("SQL 查询不能为空"));
+        }
+        // 只读校验：DDL/DML 与多语句负载一律拒绝
+        if let Err(e) = self.validator.validate(&sql) {
+            warn!("拒绝 SQL 查询: {}", e);
+            return Err(e.into());
+        }
+
+        // 协调者：扇出到各分片，剩余预算随请求下发
+        if let Some(router) = &self.router {
+            let routed = router.fan_out(&sql, budget).await?;
+            for shard in &routed.shards {
+                info!(
+                    query_id = %trace.query_id(),
+                    "分片 {} 用时 {:?}（分片预算 {:?}，端到端预算 {:?}）",
+                    shard.shard, shard.elapsed, routed.shard_budget, routed.budget
+                );
+            }
+            let cut_off = routed.cut_off();
+            if !cut_off.is_empty() {
+                warn!(query_id = %trace.query_id(), "分片超过截止时间被截断: {:?}", cut_off);
+            }
+            return routed.into_flight_stream();
+        }
+
+        // 执行查询
+        let query = self.execute_query(&sql, limits, deadline, trace);
+        match self.guarded(deadline::within(deadline, query)).await {
+            Ok(stream) => {
+                info!("查询执行成功");
+                Ok(stream)
+            }
+            Err(e) => {
+                error!("查询执行失败: {}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// `get_flight_info` 主体：校验 CMD 中的 SQL，按分区规划并为每个输出分区生成端点
+    async fn plan_flight_info(
+        &self,
+        descriptor: FlightDescriptor,
+        trace: &mut QueryTrace,
+    ) -> Result<FlightInfo, Status> {
+        if let Some(command) = flight_sql::decode_command(&descriptor.cmd) {
+            return self.plan_flight_sql(command?, descriptor, trace).await;
+        }
+        if !self.legacy_tickets {
+            return Err(Status::invalid_argument("只接受 FlightSQL 命令描述符"));
+        }
+        let sql = String::from_utf8(descriptor.cmd.to_vec())
+            .map_err(|_| Status::invalid_argument("FlightDescriptor.cmd 必须是 UTF-8 SQL"))?;
+        trace.set_sql(&sql);
+        info!(query_id = %trace.query_id(), "规划分区查询: {}", sql);
+        if sql.trim().is_empty() {
+            return Err(Status::invalid_argument("SQL 查询不能为空"));
+        }
+        if let Err(e) = self.validator.validate(&sql) {
+            warn!("拒绝 SQL 查询: {}", e);
+            return Err(e.into());
+        }
+
+        let plan = self
+            .guarded(self.partitioned(&sql, self.parallelism))
+            .await?;
+        trace.mark_planned();
+        let partitions = plan.properties().output_partitioning().partition_count();
+        Ok(partitions::flight_info(descriptor, &plan.schema(), &sql, partitions)?)
+    }
+
+    /// FlightSQL 命令的 `get_flight_info`：校验并规划查询，或为元数据命令生成结果集 schema；
+    /// 端点 Ticket 仍是 `Any` 编码的 FlightSQL 消息
+    async fn plan_flight_sql(
+        &self,
+        command: Command,
+        descriptor: FlightDescriptor,
+        trace: &mut QueryTrace,
+    ) -> Result<FlightInfo, Status> {
+        info!(query_id = %trace.query_id(), "FlightSQL 命令: {}", command.type_url());
+        let info = match command {
+            Command::CommandStatementQuery(query) => {
+                trace.set_sql(&query.query);
+                if let Err(e) = self.validator.validate(&query.query) {
+                    warn!("拒绝 SQL 查询: {}", e);
+                    return Err(e.into());
                 }
-                Err(e) => {
-                    error!("SQL 执行错误: {}", e);
-                    yield Err(Status::internal(e.to_string()));
+                let df = self
+                    .guarded(async { Ok::<_, AppError>(self.ctx.sql_with_options(&query.query, read_only()).await?) })
+                    .await?;
+                trace.mark_planned();
+                let ticket = TicketStatementQuery {
+                    statement_handle: query.query.into_bytes().into(),
+                };
+                flight_sql::flight_info(descriptor, df.schema().as_arrow(), ticket.as_any())?
+            }
+            Command::CommandPreparedStatementQuery(query) => {
+                let stmt = flight_sql::parse_handle(&query.prepared_statement_handle)?;
+                trace.set_sql(&format!("EXECUTE {}", stmt.statement_id));
+                let plan = self.statements.bind(&stmt.statement_id, &stmt.params)?;
+                trace.mark_planned();
+                flight_sql::flight_info(descriptor, plan.schema().as_arrow(), query.as_any())?
+            }
+            Command::CommandGetTables(query) => {
+                let batch = flight_sql::tables(&self.ctx, query.clone()).await?;
+                flight_sql::flight_info(descriptor, &batch.schema(), query.as_any())?
+            }
+            Command::CommandGetDbSchemas(query) => {
+                let batch = flight_sql::db_schemas(&self.ctx, query.clone())?;
+                flight_sql::flight_info(descriptor, &batch.schema(), query.as_any())?
+            }
+            Command::CommandGetSqlInfo(query) => {
+                let batch = flight_sql::sql_info(query.clone())?;
+                flight_sql::flight_info(descriptor, &batch.schema(), query.as_any())?
+            }
+            other => {
+                return Err(Status::unimplemented(format!(
+                    "不支持的 FlightSQL 命令: {}",
+                    other.type_url()
+                )))
+            }
+        };
+        Ok(info)
+    }
+
+    /// FlightSQL Ticket 的 `do_get`：执行语句或预编译语句，或返回元数据结果集
+    async fn execute_flight_sql(
+        &self,
+        command: Command,
+        limits: QueryLimits,
+        deadline: Instant,
+        trace: &mut QueryTrace,
+    ) -> Result<<Self as FlightService>::DoGetStream, Status> {
+        match command {
+            Command::TicketStatementQuery(ticket) => {
+                let sql = String::from_utf8(ticket.statement_handle.to_vec()).map_err(|_| {
+                    Status::invalid_argument("TicketStatementQuery 必须是 UTF-8 SQL")
+                })?;
+                info!(query_id = %trace.query_id(), "执行 FlightSQL 语句: {}", sql);
+                trace.set_sql(&sql);
+                if let Err(e) = self.validator.validate(&sql) {
+                    warn!("拒绝 SQL 查询: {}", e);
+                    return Err(e.into());
                 }
+                let query = self.execute_query(&sql, limits, deadline, trace);
+                Ok(self.guarded(deadline::within(deadline, query)).await?)
+            }
+            Command::CommandPreparedStatementQuery(query) => {
+                let stmt = flight_sql::parse_handle(&query.prepared_statement_handle)?;
+                info!(
+                    query_id = %trace.query_id(),
+                    "执行 FlightSQL 预编译语句: {}", stmt.statement_id
+                );
+                trace.set_sql(&format!("EXECUTE {}", stmt.statement_id));
+                let query = self.execute_prepared(&stmt, limits, deadline, trace);
+                Ok(self.guarded(deadline::within(deadline, query)).await?)
+            }
+            Command::CommandGetTables(query) => {
+                Ok(flight_sql::encode(flight_sql::tables(&self.ctx, query).await?))
+            }
+            Command::CommandGetDbSchemas(query) => {
+                Ok(flight_sql::encode(flight_sql::db_schemas(&self.ctx, query)?))
+            }
+            Command::CommandGetSqlInfo(query) => {
+                Ok(flight_sql::encode(flight_sql::sql_info(query)?))
+            }
+            other => Err(Status::unimplemented(format!(
+                "不支持的 FlightSQL Ticket: {}",
+                other.type_url()
+            ))),
+        }
+    }
+
+    /// `do_put` 主体：只支持为 FlightSQL 预编译语句绑定参数，返回携带参数的新句柄
+    async fn execute_put(
+        &self,
+        request: Request<Streaming<FlightData>>,
+        trace: &mut QueryTrace,
+    ) -> Result<PutResult, Status> {
+        let mut stream = request.into_inner();
+        let first = stream
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("do_put 请求流为空"))?;
+        let command = first
+            .flight_descriptor
+            .as_ref()
+            .and_then(|descriptor| flight_sql::decode_command(&descriptor.cmd));
+        let query = match command.transpose()? {
+            Some(Command::CommandPreparedStatementQuery(query)) => query,
+            _ => {
+                return Err(Status::unimplemented(
+                    "do_put 只支持 FlightSQL 预编译语句的参数绑定",
+                ))
+            }
+        };
+        let stmt = flight_sql::parse_handle(&query.prepared_statement_handle)?;
+        trace.set_sql(&format!("EXECUTE {}", stmt.statement_id));
+
+        let data = futures::stream::once(async move { Ok::<_, Status>(first) })
+            .chain(stream)
+            .map_err(FlightError::from);
+        let batches: Vec<RecordBatch> = FlightRecordBatchStream::new_from_flight_data(data)
+            .try_collect()
+            .await
+            .map_err(|e| match e {
+                FlightError::Tonic(status) => status,
+                other => Status::invalid_argument(format!("无法解码参数批次: {}", other)),
+            })?;
+        let [batch] = batches.as_slice() else {
+            return Err(Status::invalid_argument(format!(
+                "参数绑定需要恰好 1 个批次，实际 {} 个",
+                batches.len()
+            )));
+        };
+        let params = flight_sql::parameter_values(batch)?;
+        // 立即校验参数个数与类型，错误在 do_put 而不是之后的 get_flight_info 上报告
+        self.statements.bind(&stmt.statement_id, &params)?;
+        info!(
+            query_id = %trace.query_id(),
+            "预编译语句 {} 绑定 {} 个参数", stmt.statement_id, params.len()
+        );
+
+        let result = DoPutPreparedStatementResult {
+            prepared_statement_handle: Some(
+                flight_sql::statement_handle(&stmt.statement_id, params).into(),
+            ),
+        };
+        // 规范要求 app_metadata 直接是 DoPutPreparedStatementResult，而不是 Any 包装
+        Ok(PutResult {
+            app_metadata: result.encode_to_vec().into(),
+        })
+    }
+
+    /// `do_action` 主体：认证后按动作类型分派
+    ///
+    /// 特权动作总是要求经过验证的调用方（未配置令牌或未开启 `auth.privileged_actions` 时一律
+    /// 拒绝）；其余动作只在配置了令牌时校验。
+    async fn execute_action(
+        &self,
+        request: Request<arrow_flight::Action>,
+        trace: &mut QueryTrace,
+    ) -> Result<Vec<u8>, Status> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok());
+        let privileged = PRIVILEGED_ACTIONS.contains(&request.get_ref().r#type.as_str());
+        if privileged && !self.auth.privileged_actions {
+            return Err(Status::unauthenticated(format!(
+                "特权动作 {} 未开放（auth.privileged_actions）",
+                request.get_ref().r#type
+            )));
+        }
+        if (privileged || self.auth.enabled()) && !self.auth.is_authorized(authorization) {
+            return Err(Status::unauthenticated("缺少或无效的认证令牌"));
+        }
+        let action = request.into_inner();
+        info!(query_id = %trace.query_id(), "收到 action: {}", action.r#type);
+        trace.set_action(&action.r#type);
+
+        match action.r#type.as_str() {
+            "refresh_tables" => self.refresh_tables().await,
+            "prepare" => self.prepare(&action.body).await,
+            "close_statement" => self.close_statement(&action.body),
+            "health" => self.health(),
+            "stats" => self.stats(),
+            "register_udf" => self.register_udf(&action.body),
+            "cancel_query" => self.cancel_query(&action.body),
+            flight_sql::CREATE_PREPARED_STATEMENT => {
+                self.create_prepared_statement(&action.body).await
+            }
+            flight_sql::CLOSE_PREPARED_STATEMENT => self.close_prepared_statement(&action.body),
+            other => Err(Status::unimplemented(format!("未知的 action: {}", other))),
+        }
+    }
+
+    /// 经熔断器执行查询：熔断期间直接拒绝；执行器 panic 时标记服务不再存活
+    async fn guarded<T>(
+        &self,
+        fut: impl Future<Output = Result<T, AppError>>,
+    ) -> Result<T, AppError> {
+        let Some(health) = &self.health else {
+            return fut.await;
+        };
+        if !health.allow_query() {
+            return Err(AppError::Unavailable("查询熔断中，请稍后重试".to_string()));
+        }
+        match AssertUnwindSafe(fut).catch_unwind().await {
+            Ok(result) => {
+                health.record_query(result.is_ok()).await;
+                result
+            }
+            Err(_) => {
+                error!("查询执行器发生 panic");
+                health.record_panic().await;
+                Err(AppError::Unavailable("查询执行器异常".to_string()))
             }
+        }
+    }
+
+    async fn execute_query(
+        &self,
+        sql: &str,
+        limits: QueryLimits,
+        deadline: Instant,
+        trace: &mut QueryTrace,
+    ) -> Result<<Self as FlightService>::DoGetStream, AppError> {
+        let df = self.ctx.sql_with_options(sql, read_only()).await?;
+        self.stream_dataframe(df, limits, deadline, trace).await
+    }
+
+    /// 按轮转批次重分区的物理计划
+    ///
+    /// 根节点没有分布要求，物理优化器会移除其上的重分区并把分区数收敛到 `target_partitions`，
+    /// 因此在优化后的计划上显式加一层 `RoundRobinBatch`，分区数不随机器核数变化。
+    async fn partitioned(
+        &self,
+        sql: &str,
+        partitions: usize,
+    ) -> Result<Arc<dyn ExecutionPlan>, AppError> {
+        let plan = self
+            .ctx
+            .sql_with_options(sql, read_only())
+            .await?
+            .create_physical_plan()
+            .await?;
+        if plan.properties().output_partitioning().partition_count() == partitions {
+            return Ok(plan);
+        }
+        let partitioning = datafusion::physical_plan::Partitioning::RoundRobinBatch(partitions);
+        Ok(Arc::new(RepartitionExec::try_new(plan, partitioning)?))
+    }
+
+    /// 按当前并行度重新规划分区查询，只执行 Ticket 指定的分区；其余分区的流立即丢弃
+    async fn execute_partition(
+        &self,
+        ticket: &PartitionTicket,
+        limits: QueryLimits,
+        deadline: Instant,
+        trace: &mut QueryTrace,
+    ) -> Result<<Self as FlightService>::DoGetStream, AppError> {
+        let plan = self.partitioned(&ticket.sql, self.parallelism).await?;
+        let mut streams = execute_stream_partitioned(plan, self.ctx.task_ctx())?;
+        if streams.len() != ticket.partitions || ticket.partition >= streams.len() {
+            return Err(AppError::InvalidArgument(format!(
+                "分区 {}/{} 与当前计划的 {} 个分区不符",
+                ticket.partition,
+                ticket.partitions,
+                streams.len()
+            )));
+        }
+        let batches = streams.swap_remove(ticket.partition);
+        drop(streams);
+        trace.mark_planned();
+        Ok(self.encode_batches(batches, limits, deadline, trace))
+    }
+
+    async fn execute_prepared(
+        &self,
+        stmt: &StatementTicket,
+        limits: QueryLimits,
+        deadline: Instant,
+        trace: &mut QueryTrace,
+    ) -> Result<<Self as FlightService>::DoGetStream, AppError> {
+        let plan = self.statements.bind(&stmt.statement_id, &stmt.params)?;
+        let df = self.ctx.execute_logical_plan(plan).await?;
+        self.stream_dataframe(df, limits, deadline, trace).await
+    }
+
+    /// 执行 DataFrame 并把结果批次编码为 Flight 数据流；批次经有界通道按需生产。
+    /// 物理计划创建完成即视为规划结束，之后的耗时计入执行阶段。
+    async fn stream_dataframe(
+        &self,
+        df: DataFrame,
+        limits: QueryLimits,
+        deadline: Instant,
+        trace: &mut QueryTrace,
+    ) -> Result<<Self as FlightService>::DoGetStream, AppError> {
+        let batches = df.execute_stream().await?;
+        trace.mark_planned();
+        Ok(self.encode_batches(batches, limits, deadline, trace))
+    }
+
+    /// 对结果批次施加限制并编码为 Flight 数据流；查询以 `query_id` 登记，可经 `cancel_query`
+    /// 取消，到达截止时刻时以 `DeadlineExceeded` 结束
+    fn encode_batches(
+        &self,
+        batches: SendableRecordBatchStream,
+        limits: QueryLimits,
+        deadline: Instant,
+        trace: &mut QueryTrace,
+    ) -> <Self as FlightService>::DoGetStream {
+        let schema = batches.schema();
+        let registration = self.queries.register(trace.query_id());
+        let limited =
+            limits::spawn_limited(batches, limits, self.stream_buffer, registration.token());
+        let truncated = limited.truncated.clone();
+        let rows = trace.row_counter();
+
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(
+                limited
+                    .into_stream()
+                    .inspect_ok(move |batch| {
+                        rows.fetch_add(batch.num_rows() as u64, Ordering::Relaxed);
+                    })
+                    .map_err(|e| FlightError::ExternalError(Box::new(e))),
+            )
+            .map_err(|e| {
+                error!("批次编码错误: {}", e);
+                Status::from(e)
+            });
+
+        let stream = limits::mark_truncated(stream, truncated).boxed();
+        let token = registration.token();
+        let stream = cancel::cancellable(stream, registration).boxed();
+        deadline::bounded(stream, deadline, token).boxed()
+    }
+
+    /// `refresh_tables`：重新扫描 data_path，返回 JSON 格式的差异报告
+    async fn refresh_tables(&self) -> Result<Vec<u8>, Status> {
+        let catalog = self
+            .catalog
+            .as_ref()
+            .ok_or_else(|| Status::failed_precondition("未配置 data_path 表目录"))?;
+        let report = catalog.refresh(&self.ctx).await;
+        info!("表目录刷新完成: {}", report.summary());
+        for (path, err) in &report.errors {
+            warn!("表注册失败 {}: {}", path, err);
+        }
+        serde_json::to_vec(&report).map_err(|e| Status::internal(e.to_string()))
+    }
+
+    /// `prepare`：校验并规划 SQL，缓存逻辑计划
+    async fn prepare(&self, body: &[u8]) -> Result<Vec<u8>, Status> {
+        let req: PrepareRequest = serde_json::from_slice(body)
+            .map_err(|e| Status::invalid_argument(format!("无效的 prepare 请求: {}", e)))?;
+        self.validator.validate(&req.sql)?;
+        let plan = self
+            .ctx
+            .state()
+            .create_logical_plan(&req.sql)
+            .await
+            .map_err(AppError::from)?;
+        read_only().verify_plan(&plan).map_err(AppError::from)?;
+        let resp = self.statements.insert(plan)?;
+        info!(
+            "预编译语句 {} 已创建（{} 个参数）",
+            resp.statement_id,
+            resp.parameters.len()
+        );
+        serde_json::to_vec(&resp).map_err(|e| Status::internal(e.to_string()))
+    }
+
+    /// `CreatePreparedStatement`：与 `prepare` 共用语句缓存，返回 `Any` 编码的 FlightSQL 结果
+    async fn create_prepared_statement(&self, body: &[u8]) -> Result<Vec<u8>, Status> {
+        let req: ActionCreatePreparedStatementRequest = flight_sql::unpack(body)?;
+        self.validator.validate(&req.query)?;
+        let plan = self
+            .ctx
+            .state()
+            .create_logical_plan(&req.query)
+            .await
+            .map_err(AppError::from)?;
+        read_only().verify_plan(&plan).map_err(AppError::from)?;
+        let dataset_schema = flight_sql::schema_ipc(plan.schema().as_arrow())?;
+        let parameter_schema = flight_sql::schema_ipc(&flight_sql::parameter_schema(&plan)?)?;
+        let resp = self.statements.insert(plan)?;
+        info!(
+            "FlightSQL 预编译语句 {} 已创建（{} 个参数）",
+            resp.statement_id,
+            resp.parameters.len()
+        );
+        let result = ActionCreatePreparedStatementResult {
+            prepared_statement_handle: flight_sql::statement_handle(&resp.statement_id, Vec::new())
+                .into(),
+            dataset_schema: dataset_schema.into(),
+            parameter_schema: parameter_schema.into(),
         };
-        
-        Ok(Box::pin(stream))
+        Ok(result.as_any().encode_to_vec())
+    }
+
+    /// `ClosePreparedStatement`：句柄中的语句 ID 决定移除哪条缓存语句
+    fn close_prepared_statement(&self, body: &[u8]) -> Result<Vec<u8>, Status> {
+        let req: ActionClosePreparedStatementRequest = flight_sql::unpack(body)?;
+        let stmt = flight_sql::parse_handle(&req.prepared_statement_handle)?;
+        if !self.statements.remove(&stmt.statement_id) {
+            return Err(AppError::StatementNotFound(stmt.statement_id).into());
+        }
+        Ok(Vec::new())
+    }
+
+    /// `health`：返回 JSON 格式的健康快照
+    fn health(&self) -> Result<Vec<u8>, Status> {
+        let health = self
+            .health
+            .as_ref()
+            .ok_or_else(|| Status::failed_precondition("未启用健康登记表"))?;
+        serde_json::to_vec(&health.snapshot()).map_err(|e| Status::internal(e.to_string()))
+    }
+
+    /// `stats`：返回 JSON 格式的查询指标
+    fn stats(&self) -> Result<Vec<u8>, Status> {
+        serde_json::to_vec(&self.audit.stats()).map_err(|e| Status::internal(e.to_string()))
+    }
+
+    /// `register_udf`：编译 Wasm 模块并注册为标量 UDF
+    fn register_udf(&self, body: &[u8]) -> Result<Vec<u8>, Status> {
+        let spec: UdfSpec = serde_json::from_slice(body)
+            .map_err(|e| Status::invalid_argument(format!("无效的 register_udf 请求: {}", e)))?;
+        let udf = WasmUdf::try_new(spec)?.into_scalar_udf();
+        info!("注册 UDF {}", udf.name());
+        self.ctx.register_udf(udf);
+        Ok(Vec::new())
+    }
+
+    /// `cancel_query`：触发进行中查询的取消令牌
+    fn cancel_query(&self, body: &[u8]) -> Result<Vec<u8>, Status> {
+        let req: CancelRequest = serde_json::from_slice(body)
+            .map_err(|e| Status::invalid_argument(format!("无效的 cancel_query 请求: {}", e)))?;
+        if !self.queries.cancel(&req.query_id) {
+            return Err(AppError::QueryNotFound(req.query_id).into());
+        }
+        info!(query_id = %req.query_id, "查询已取消");
+        Ok(Vec::new())
+    }
+
+    /// `close_statement`：移除缓存的语句
+    fn close_statement(&self, body: &[u8]) -> Result<Vec<u8>, Status> {
+        let req: CloseStatementRequest = serde_json::from_slice(body)
+            .map_err(|e| Status::invalid_argument(format!("无效的 close_statement 请求: {}", e)))?;
+        if !self.statements.remove(&req.statement_id) {
+            return Err(AppError::StatementNotFound(req.statement_id).into());
+        }
+        Ok(Vec::new())
     }
 }
@@ -0,0 +1,507 @@
+//! 数据目录表注册
+//!
+//! 扫描 `ServerConfig::data_path`，把其中的 `.csv` / `.parquet` 文件以及 hive 风格分区目录
+//! （`year=2025/month=10/...`）注册为 DataFusion 表，表名取文件（目录）名，schema 自动推断。
+//! 单个文件注册失败不会中断其余文件；表名重复的来源全部跳过。错误统一汇总到 `RegistrationReport`。
+
+use datafusion::arrow::datatypes::DataType;
+use datafusion::datasource::file_format::parquet::ParquetFormat;
+use datafusion::datasource::listing::{
+    ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl,
+};
+use datafusion::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::error::AppError;
+
+/// 支持的数据文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableFormat {
+    Csv,
+    Parquet,
+}
+
+impl TableFormat {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(TableFormat::Csv),
+            "parquet" => Some(TableFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            TableFormat::Csv => ".csv",
+            TableFormat::Parquet => ".parquet",
+        }
+    }
+}
+
+/// 在 `data_path` 中发现的一张表
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableSource {
+    pub name: String,
+    pub path: PathBuf,
+    pub format: TableFormat,
+    /// hive 分区列，按目录层级顺序
+    pub partition_cols: Vec<String>,
+}
+
+/// 一次扫描（启动或 `refresh_tables`）的结果汇总
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RegistrationReport {
+    pub registered: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// (路径, 错误信息)
+    pub errors: Vec<(String, String)>,
+}
+
+impl RegistrationReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "新增 {} 张表, 移除 {} 张表, 未变化 {} 张表, 失败 {} 个",
+            self.registered.len(),
+            self.removed.len(),
+            self.unchanged.len(),
+            self.errors.len()
+        )
+    }
+}
+
+/// 扫描 `data_path` 顶层条目；无法识别的条目记录到 `errors`
+pub fn discover(data_path: &Path) -> (Vec<TableSource>, Vec<(String, String)>) {
+    let mut sources = Vec::new();
+    let mut errors = Vec::new();
+
+    let entries = match std::fs::read_dir(data_path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push((data_path.display().to_string(), e.to_string()));
+            return (sources, errors);
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let result = if path.is_dir() {
+            discover_partitioned(&path)
+        } else {
+            Ok(TableFormat::from_path(&path).map(|format| TableSource {
+                name: table_name(&path),
+                path: path.clone(),
+                format,
+                partition_cols: Vec::new(),
+            }))
+        };
+        match result {
+            Ok(Some(source)) => sources.push(source),
+            Ok(None) => {}
+            Err(e) => errors.push((path.display().to_string(), e.to_string())),
+        }
+    }
+
+    sources.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.path.cmp(&b.path)));
+    let sources = reject_duplicate_names(sources, &mut errors);
+    (sources, errors)
+}
+
+/// 同名的来源（如 `orders.csv` 与 `orders.parquet`）无法确定该注册哪一个：全部跳过，
+/// 每个路径记一条错误。`sources` 须已按表名排序。
+fn reject_duplicate_names(
+    sources: Vec<TableSource>,
+    errors: &mut Vec<(String, String)>,
+) -> Vec<TableSource> {
+    let mut kept = Vec::with_capacity(sources.len());
+    for group in sources.chunk_by(|a, b| a.name == b.name) {
+        if let [source] = group {
+            kept.push(source.clone());
+            continue;
+        }
+        let paths: Vec<String> = group.iter().map(|s| s.path.display().to_string()).collect();
+        for path in &paths {
+            errors.push((
+                path.clone(),
+                format!("表名 '{}' 重复（{}），均未注册", group[0].name, paths.join(", ")),
+            ));
+        }
+    }
+    kept
+}
+
+/// 识别 hive 分区目录：沿 `key=value` 子目录下探，确定分区列与文件格式
+fn discover_partitioned(dir: &Path) -> Result<Option<TableSource>, AppError> {
+    let mut partition_cols = Vec::new();
+    let mut current = dir.to_path_buf();
+    loop {
+        let mut next_dir = None;
+        let mut format = None;
+        for entry in std::fs::read_dir(&current)?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if next_dir.is_none() {
+                    next_dir = Some(path);
+                }
+            } else if let Some(f) = TableFormat::from_path(&path) {
+                match format {
+                    Some(existing) if existing != f => {
+                        return Err(AppError::Config(format!(
+                            "目录 {} 中混合了多种文件格式",
+                            current.display()
+                        )));
+                    }
+                    _ => format = Some(f),
+                }
+            }
+        }
+
+        if let Some(format) = format {
+            return Ok(Some(TableSource {
+                name: table_name(dir),
+                path: dir.to_path_buf(),
+                format,
+                partition_cols,
+            }));
+        }
+
+        let Some(next) = next_dir else {
+            return Ok(None);
+        };
+        let dir_name = next
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string();
+        match dir_name.split_once('=') {
+            Some((key, _)) if !key.is_empty() => partition_cols.push(key.to_string()),
+            _ => {
+                return Err(AppError::Config(format!(
+                    "目录 {} 不是 key=value 形式的分区目录",
+                    next.display()
+                )));
+            }
+        }
+        current = next;
+    }
+}
+
+/// 表名取文件名（去扩展名），统一小写以匹配 SQL 中未加引号的标识符
+fn table_name(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+async fn register_source(ctx: &SessionContext, source: &TableSource) -> Result<(), AppError> {
+    let path = source.path.to_str().ok_or_else(|| {
+        AppError::Config(format!("路径不是合法的 UTF-8: {}", source.path.display()))
+    })?;
+    let partition_cols: Vec<(String, DataType)> = source
+        .partition_cols
+        .iter()
+        .map(|c| (c.clone(), DataType::Utf8))
+        .collect();
+
+    match source.format {
+        TableFormat::Csv => {
+            let options = CsvReadOptions::new()
+                .has_header(true)
+                .file_extension(source.format.extension())
+                .table_partition_cols(partition_cols);
+            ctx.register_csv(&source.name, path, options).await?;
+        }
+        TableFormat::Parquet if source.path.is_dir() => {
+            register_parquet_directory(ctx, &source.name, &source.path).await?;
+        }
+        TableFormat::Parquet => {
+            let options = ParquetReadOptions {
+                file_extension: source.format.extension(),
+                ..Default::default()
+            }
+            .table_partition_cols(partition_cols);
+            ctx.register_parquet(&source.name, path, options).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 把 `dir` 下的全部 `.parquet` 文件注册为一张多文件 `ListingTable`
+///
+/// 数据列 schema 由文件推断；`key=value` 子目录成为分区列，某一层的取值全部是规范写法的整数时
+/// 列类型为 `Int64`，否则为 `Utf8`（`month=01` 按字符串 `'01'` 过滤：DataFusion 按字面值
+/// 拼出目录前缀，把它当作整数 1 会去列 `month=1`）。按分区列过滤时 DataFusion 在列出文件
+/// 阶段裁剪，不匹配的目录不会被扫描。
+pub async fn register_parquet_directory(
+    ctx: &SessionContext,
+    name: &str,
+    dir: &Path,
+) -> Result<(), AppError> {
+    let source = discover_partitioned(dir)?
+        .filter(|s| s.format == TableFormat::Parquet)
+        .ok_or_else(|| {
+            AppError::Config(format!("目录 {} 中没有 .parquet 文件", dir.display()))
+        })?;
+
+    let mut values = vec![Vec::new(); source.partition_cols.len()];
+    collect_partition_values(dir, 0, &mut values)?;
+    let partition_cols: Vec<(String, DataType)> = source
+        .partition_cols
+        .iter()
+        .zip(&values)
+        .map(|(col, values)| {
+            let canonical = |v: &String| v.parse::<i64>().is_ok_and(|n| n.to_string() == *v);
+            let ty = if values.iter().all(canonical) {
+                DataType::Int64
+            } else {
+                DataType::Utf8
+            };
+            (col.clone(), ty)
+        })
+        .collect();
+
+    let path = dir
+        .to_str()
+        .ok_or_else(|| AppError::Config(format!("路径不是合法的 UTF-8: {}", dir.display())))?;
+    let url = ListingTableUrl::parse(path)?;
+    let options = ListingOptions::new(Arc::new(ParquetFormat::default()))
+        .with_file_extension(TableFormat::Parquet.extension())
+        .with_table_partition_cols(partition_cols);
+    let schema = options.infer_schema(&ctx.state(), &url).await?;
+    let config = ListingTableConfig::new(url)
+        .with_listing_options(options)
+        .with_schema(schema);
+    ctx.register_table(name, Arc::new(ListingTable::try_new(config)?))?;
+    Ok(())
+}
+
+/// 收集每一层分区目录出现过的取值
+fn collect_partition_values(
+    dir: &Path,
+    depth: usize,
+    values: &mut [Vec<String>],
+) -> Result<(), AppError> {
+    if depth == values.len() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let dir_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if let Some((_, value)) = dir_name.split_once('=') {
+            values[depth].push(value.to_string());
+            collect_partition_values(&path, depth + 1, values)?;
+        }
+    }
+    Ok(())
+}
+
+/// 记录已从 `data_path` 注册的表，支持不重启地重新扫描
+pub struct TableCatalog {
+    data_path: PathBuf,
+    tables: Mutex<BTreeMap<String, TableSource>>,
+}
+
+impl TableCatalog {
+    pub fn new(data_path: impl Into<PathBuf>) -> Self {
+        Self {
+            data_path: data_path.into(),
+            tables: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 重新扫描 `data_path` 并与已注册的表做差异：移除消失的表、注册新增的表
+    pub async fn refresh(&self, ctx: &SessionContext) -> RegistrationReport {
+        let mut tables = self.tables.lock().await;
+        let (sources, errors) = discover(&self.data_path);
+        let mut report = RegistrationReport {
+            errors,
+            ..Default::default()
+        };
+
+        let current: BTreeMap<String, TableSource> =
+            sources.into_iter().map(|s| (s.name.clone(), s)).collect();
+
+        let stale: Vec<String> = tables
+            .iter()
+            .filter(|(name, source)| current.get(*name) != Some(*source))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in stale {
+            tables.remove(&name);
+            if let Err(e) = ctx.deregister_table(name.as_str()) {
+                report.errors.push((name.clone(), e.to_string()));
+            }
+            if !current.contains_key(&name) {
+                report.removed.push(name);
+            }
+        }
+
+        for (name, source) in current {
+            if tables.contains_key(&name) {
+                report.unchanged.push(name);
+                continue;
+            }
+            match register_source(ctx, &source).await {
+                Ok(()) => {
+                    report.registered.push(name.clone());
+                    tables.insert(name, source);
+                }
+                Err(e) => report
+                    .errors
+                    .push((source.path.display().to_string(), e.to_string())),
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::{collect_batches, row_count};
+    use datafusion::arrow::array::{ArrayRef, Int64Array, StringArray};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::arrow::util::pretty::pretty_format_batches;
+    use datafusion::parquet::arrow::ArrowWriter;
+
+    fn write_parquet(path: &Path) {
+        let batch = RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef),
+            ("sku", Arc::new(StringArray::from(vec!["a", "b", "c"])) as ArrayRef),
+        ])
+        .unwrap();
+        let file = std::fs::File::create(path).unwrap();
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn csv_and_parquet_are_queryable_through_do_get() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("users.csv"), "id,name\n1,Alice\n2,Bob\n").unwrap();
+        write_parquet(&dir.path().join("orders.parquet"));
+
+        let ctx = SessionContext::new();
+        let catalog = Arc::new(TableCatalog::new(dir.path()));
+        let report = catalog.refresh(&ctx).await;
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.registered, vec!["orders", "users"]);
+
+        let svc = DfFlightService::new(ctx).with_catalog(catalog);
+        assert_eq!(row_count(&svc, "SELECT * FROM users").await, 2);
+        assert_eq!(row_count(&svc, "SELECT * FROM orders WHERE id > 1").await, 2);
+    }
+
+    #[tokio::test]
+    async fn refresh_diffs_added_and_removed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.csv"), "x\n1\n").unwrap();
+        // 非法文件不影响其他表注册
+        std::fs::write(dir.path().join("broken.parquet"), b"not parquet").unwrap();
+
+        let ctx = SessionContext::new();
+        let catalog = TableCatalog::new(dir.path());
+        let report = catalog.refresh(&ctx).await;
+        assert_eq!(report.registered, vec!["a"]);
+        assert_eq!(report.errors.len(), 1);
+
+        std::fs::remove_file(dir.path().join("a.csv")).unwrap();
+        std::fs::remove_file(dir.path().join("broken.parquet")).unwrap();
+        std::fs::write(dir.path().join("b.csv"), "y\n2\n").unwrap();
+        let report = catalog.refresh(&ctx).await;
+        assert_eq!(report.registered, vec!["b"]);
+        assert_eq!(report.removed, vec!["a"]);
+        assert!(!ctx.table_exist("a").unwrap());
+    }
+
+    #[tokio::test]
+    async fn duplicate_table_names_are_reported_not_collapsed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("orders.csv"), "id\n1\n").unwrap();
+        write_parquet(&dir.path().join("Orders.parquet"));
+        std::fs::write(dir.path().join("users.csv"), "id\n1\n").unwrap();
+
+        let (sources, errors) = discover(dir.path());
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].name, "users");
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|(_, e)| e.contains("表名 'orders' 重复")), "{errors:?}");
+
+        let ctx = SessionContext::new();
+        let report = TableCatalog::new(dir.path()).refresh(&ctx).await;
+        assert_eq!(report.registered, vec!["users"]);
+        assert_eq!(report.errors.len(), 2);
+        assert!(!ctx.table_exist("orders").unwrap());
+    }
+
+    #[tokio::test]
+    async fn hive_partitioned_directory_exposes_partition_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let part = dir.path().join("events").join("region=eu");
+        std::fs::create_dir_all(&part).unwrap();
+        std::fs::write(part.join("part-0.csv"), "id\n1\n2\n").unwrap();
+
+        let (sources, errors) = discover(dir.path());
+        assert!(errors.is_empty());
+        assert_eq!(sources[0].name, "events");
+        assert_eq!(sources[0].partition_cols, vec!["region"]);
+
+        let ctx = SessionContext::new();
+        let catalog = Arc::new(TableCatalog::new(dir.path()));
+        catalog.refresh(&ctx).await;
+        let svc = DfFlightService::new(ctx).with_catalog(catalog);
+        assert_eq!(
+            row_count(&svc, "SELECT * FROM events WHERE region = 'eu'").await,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn parquet_directory_prunes_hive_partitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("sales");
+        for part in ["year=2024/month=01", "year=2024/month=02", "year=2023/month=12"] {
+            let part = root.join(part);
+            std::fs::create_dir_all(&part).unwrap();
+            write_parquet(&part.join("part-0.parquet"));
+        }
+
+        let ctx = SessionContext::new();
+        register_parquet_directory(&ctx, "sales", &root).await.unwrap();
+        let svc = DfFlightService::new(ctx);
+        assert_eq!(row_count(&svc, "SELECT * FROM sales").await, 9);
+
+        // 注册后破坏不匹配分区的文件：裁剪生效时查询不会读到它们
+        for part in ["year=2024/month=02", "year=2023/month=12"] {
+            std::fs::write(root.join(part).join("part-0.parquet"), b"not parquet").unwrap();
+        }
+        let sql = "SELECT id, year, month FROM sales WHERE year = 2024 AND month = '01' ORDER BY id";
+        let batches = collect_batches(&svc, sql.as_bytes().to_vec()).await.unwrap();
+        let rows = pretty_format_batches(&batches).unwrap().to_string();
+        let expected = [
+            "+----+------+-------+",
+            "| id | year | month |",
+            "+----+------+-------+",
+            "| 1  | 2024 | 01    |",
+            "| 2  | 2024 | 01    |",
+            "| 3  | 2024 | 01    |",
+            "+----+------+-------+",
+        ];
+        assert_eq!(rows, expected.join("\n"));
+
+        // 全表扫描会读到被破坏的文件
+        assert!(collect_batches(&svc, b"SELECT * FROM sales".to_vec()).await.is_err());
+    }
+}
@@ -0,0 +1,103 @@
+//! 测试辅助：构造示例上下文并通过 Flight 接口收发数据
+
+use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::FlightService;
+use arrow_flight::{Action, Ticket};
+use datafusion::arrow::array::{ArrayRef, Int64Array, StringArray};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::prelude::*;
+use futures::{StreamExt, TryStreamExt};
+use std::sync::Arc;
+use tonic::{Request, Status};
+
+use crate::config::AuthConfig;
+use crate::service_impl::DfFlightService;
+
+/// 测试用令牌；`do_action` 总是携带它
+pub const TEST_TOKEN: &str = "test-token";
+
+/// 开放特权动作、只接受 `TEST_TOKEN` 的认证配置
+pub fn privileged_auth() -> AuthConfig {
+    AuthConfig {
+        tokens: vec![TEST_TOKEN.to_string()],
+        privileged_actions: true,
+    }
+}
+
+/// 与 `main.rs` 示例数据一致的内存 `users` 表
+pub async fn users_context() -> SessionContext {
+    let batch = RecordBatch::try_from_iter(vec![
+        ("id", Arc::new(Int64Array::from(vec![1, 2, 3, 4, 5])) as ArrayRef),
+        (
+            "name",
+            Arc::new(StringArray::from(vec!["Alice", "Bob", "Charlie", "Diana", "Eve"])) as ArrayRef,
+        ),
+        ("age", Arc::new(Int64Array::from(vec![25, 30, 35, 28, 32])) as ArrayRef),
+        (
+            "city",
+            Arc::new(StringArray::from(vec![
+                "New York",
+                "San Francisco",
+                "Chicago",
+                "Boston",
+                "Seattle",
+            ])) as ArrayRef,
+        ),
+    ])
+    .unwrap();
+    let ctx = SessionContext::new();
+    ctx.register_batch("users", batch).unwrap();
+    ctx
+}
+
+/// 以给定 Ticket 调用 `do_get` 并解码全部批次
+pub async fn collect_batches(
+    svc: &DfFlightService,
+    ticket: impl Into<Vec<u8>>,
+) -> Result<Vec<RecordBatch>, Status> {
+    let ticket = Ticket {
+        ticket: ticket.into().into(),
+    };
+    let resp = svc.do_get(Request::new(ticket)).await?;
+    let stream = resp.into_inner().map_err(FlightError::from).boxed();
+    FlightRecordBatchStream::new_from_flight_data(stream)
+        .try_collect()
+        .await
+        .map_err(|e| match e {
+            FlightError::Tonic(status) => status,
+            other => Status::internal(other.to_string()),
+        })
+}
+
+/// 结果集总行数
+pub async fn row_count(svc: &DfFlightService, sql: &str) -> usize {
+    collect_batches(svc, sql)
+        .await
+        .unwrap()
+        .iter()
+        .map(|b| b.num_rows())
+        .sum()
+}
+
+/// 以 `TEST_TOKEN` 调用 `do_action` 并返回第一个结果的 body
+pub async fn do_action(
+    svc: &DfFlightService,
+    action_type: &str,
+    body: Vec<u8>,
+) -> Result<Vec<u8>, Status> {
+    let action = Action {
+        r#type: action_type.to_string(),
+        body: body.into(),
+    };
+    let mut request = Request::new(action);
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {}", TEST_TOKEN).parse().unwrap(),
+    );
+    let mut stream = svc.do_action(request).await?.into_inner();
+    match stream.next().await {
+        Some(result) => Ok(result?.body.to_vec()),
+        None => Ok(Vec::new()),
+    }
+}
@@ -0,0 +1,296 @@
+//! 以 Wasm 模块实现的动态标量 UDF
+//!
+//! `register_udf` 动作的 body 为 JSON 编码的 `UdfSpec`。模块须导出与 UDF 同名的函数，参数与
+//! 返回值只能是 `i32` / `i64` / `f32` / `f64`，分别对应 Arrow 的 Int32 / Int64 / Float32 / Float64。
+//! 模块不链接任何导入，每个批次在新的 `Store` 中实例化并按行数限制燃料，死循环的函数以
+//! 执行错误结束而不会占住执行器。任一参数为 NULL 的行结果为 NULL。
+
+use std::any::Any;
+use std::sync::Arc;
+
+use datafusion::arrow::array::{
+    Array, ArrayRef, Float32Array, Float64Array, Int32Array, Int64Array,
+};
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::{DataFusionError, Result as DfResult, ScalarValue};
+use datafusion::logical_expr::{ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, Volatility};
+use serde::{Deserialize, Serialize};
+use wasmi::core::ValType;
+use wasmi::{Config, Engine, Linker, Module, Store, Val};
+
+use crate::error::AppError;
+
+/// 每行允许消耗的燃料（约等于执行的 Wasm 指令数）
+const FUEL_PER_ROW: u64 = 1_000_000;
+
+/// `register_udf` 动作的请求
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UdfSpec {
+    pub name: String,
+    pub input_types: Vec<String>,
+    pub return_type: String,
+    pub wasm_bytes: Vec<u8>,
+}
+
+/// UDF 支持的参数/返回类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WasmType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl WasmType {
+    fn parse(name: &str) -> Result<Self, AppError> {
+        match name.to_ascii_lowercase().as_str() {
+            "i32" | "int" | "int32" => Ok(Self::I32),
+            "i64" | "bigint" | "int64" => Ok(Self::I64),
+            "f32" | "float" | "float32" => Ok(Self::F32),
+            "f64" | "double" | "float64" => Ok(Self::F64),
+            other => Err(AppError::InvalidArgument(format!("不支持的 UDF 类型: {}", other))),
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            Self::I32 => DataType::Int32,
+            Self::I64 => DataType::Int64,
+            Self::F32 => DataType::Float32,
+            Self::F64 => DataType::Float64,
+        }
+    }
+
+    fn val_type(self) -> ValType {
+        match self {
+            Self::I32 => ValType::I32,
+            Self::I64 => ValType::I64,
+            Self::F32 => ValType::F32,
+            Self::F64 => ValType::F64,
+        }
+    }
+
+    /// 读取第 `row` 行；NULL 返回 `None`
+    fn read(self, array: &ArrayRef, row: usize) -> Option<Val> {
+        if array.is_null(row) {
+            return None;
+        }
+        let any = array.as_any();
+        Some(match self {
+            Self::I32 => Val::I32(any.downcast_ref::<Int32Array>()?.value(row)),
+            Self::I64 => Val::I64(any.downcast_ref::<Int64Array>()?.value(row)),
+            Self::F32 => Val::F32(any.downcast_ref::<Float32Array>()?.value(row).into()),
+            Self::F64 => Val::F64(any.downcast_ref::<Float64Array>()?.value(row).into()),
+        })
+    }
+
+    fn build(self, values: Vec<Option<Val>>) -> ArrayRef {
+        match self {
+            Self::I32 => Arc::new(Int32Array::from_iter(
+                values.iter().map(|v| v.as_ref().and_then(Val::i32)),
+            )),
+            Self::I64 => Arc::new(Int64Array::from_iter(
+                values.iter().map(|v| v.as_ref().and_then(Val::i64)),
+            )),
+            Self::F32 => Arc::new(Float32Array::from_iter(
+                values.iter().map(|v| v.as_ref().and_then(Val::f32).map(f32::from)),
+            )),
+            Self::F64 => Arc::new(Float64Array::from_iter(
+                values.iter().map(|v| v.as_ref().and_then(Val::f64).map(f64::from)),
+            )),
+        }
+    }
+}
+
+/// 调用 Wasm 导出函数的标量 UDF
+#[derive(Debug)]
+pub struct WasmUdf {
+    name: String,
+    inputs: Vec<WasmType>,
+    output: WasmType,
+    signature: Signature,
+    engine: Engine,
+    module: Module,
+}
+
+impl WasmUdf {
+    /// 编译模块并检查导出函数的签名与 `spec` 一致
+    pub fn try_new(spec: UdfSpec) -> Result<Self, AppError> {
+        if spec.input_types.is_empty() {
+            return Err(AppError::InvalidArgument("UDF 至少需要一个参数".into()));
+        }
+        let inputs = spec
+            .input_types
+            .iter()
+            .map(|t| WasmType::parse(t))
+            .collect::<Result<Vec<_>, _>>()?;
+        let output = WasmType::parse(&spec.return_type)?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &spec.wasm_bytes[..])
+            .map_err(|e| AppError::InvalidArgument(format!("无效的 Wasm 模块: {}", e)))?;
+        let ty = module
+            .get_export(&spec.name)
+            .and_then(|export| export.func().cloned())
+            .ok_or_else(|| {
+                AppError::InvalidArgument(format!("Wasm 模块未导出函数 {}", spec.name))
+            })?;
+        let expected: Vec<ValType> = inputs.iter().map(|t| t.val_type()).collect();
+        if ty.params() != expected.as_slice() || ty.results() != [output.val_type()] {
+            return Err(AppError::InvalidArgument(format!(
+                "导出函数 {} 的签名与声明不符: {:?}",
+                spec.name, ty
+            )));
+        }
+
+        let signature = Signature::exact(
+            inputs.iter().map(|t| t.data_type()).collect(),
+            Volatility::Immutable,
+        );
+        Ok(Self {
+            name: spec.name,
+            inputs,
+            output,
+            signature,
+            engine,
+            module,
+        })
+    }
+
+    pub fn into_scalar_udf(self) -> ScalarUDF {
+        ScalarUDF::new_from_impl(self)
+    }
+
+    /// 在新实例中逐行调用导出函数
+    fn call_rows(&self, arrays: &[ArrayRef], rows: usize) -> DfResult<Vec<Option<Val>>> {
+        let wasm_err = |e: &dyn std::fmt::Display| {
+            DataFusionError::Execution(format!("UDF {} 执行失败: {}", self.name, e))
+        };
+        let mut store = Store::new(&self.engine, ());
+        store
+            .set_fuel(FUEL_PER_ROW.saturating_mul(rows as u64))
+            .map_err(|e| wasm_err(&e))?;
+        let instance = Linker::<()>::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| wasm_err(&e))?;
+        let func = instance
+            .get_func(&store, &self.name)
+            .ok_or_else(|| wasm_err(&"导出函数缺失"))?;
+
+        let mut results = Vec::with_capacity(rows);
+        let mut output = [Val::default(self.output.val_type())];
+        for row in 0..rows {
+            let params: Option<Vec<Val>> = self
+                .inputs
+                .iter()
+                .zip(arrays)
+                .map(|(ty, array)| ty.read(array, row))
+                .collect();
+            let Some(params) = params else {
+                results.push(None);
+                continue;
+            };
+            func.call(&mut store, &params, &mut output)
+                .map_err(|e| wasm_err(&e))?;
+            results.push(Some(output[0].clone()));
+        }
+        Ok(results)
+    }
+}
+
+impl ScalarUDFImpl for WasmUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> DfResult<DataType> {
+        Ok(self.output.data_type())
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> DfResult<ColumnarValue> {
+        let all_scalars = args.iter().all(|a| matches!(a, ColumnarValue::Scalar(_)));
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let rows = arrays.first().map_or(0, |a| a.len());
+        let result = self.output.build(self.call_rows(&arrays, rows)?);
+        if all_scalars {
+            return Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(&result, 0)?));
+        }
+        Ok(ColumnarValue::Array(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_impl::DfFlightService;
+    use crate::test_util::{collect_batches, do_action, privileged_auth, users_context};
+
+    const IDENTITY: &str = r#"(module
+        (func (export "ident") (param i64) (result i64) local.get 0))"#;
+
+    fn spec(name: &str, wat: &str) -> UdfSpec {
+        UdfSpec {
+            name: name.into(),
+            input_types: vec!["i64".into()],
+            return_type: "i64".into(),
+            wasm_bytes: wat::parse_str(wat).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn registered_wasm_udf_is_usable_in_do_get() {
+        let svc = DfFlightService::new(users_context().await).with_auth(privileged_auth());
+        let body = serde_json::to_vec(&spec("ident", IDENTITY)).unwrap();
+        do_action(&svc, "register_udf", body).await.unwrap();
+
+        let batches = collect_batches(&svc, "SELECT ident(id) AS v FROM users ORDER BY v")
+            .await
+            .unwrap();
+        let values: Vec<i64> = batches
+            .iter()
+            .flat_map(|b| {
+                let column = b.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+                column.values().to_vec()
+            })
+            .collect();
+        assert_eq!(values, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn invalid_specs_and_runaway_modules_are_rejected() {
+        // 未开放特权动作时 register_udf 被拒绝，UDF 不会注册
+        let open = DfFlightService::new(users_context().await);
+        let body = serde_json::to_vec(&spec("ident", IDENTITY)).unwrap();
+        let err = do_action(&open, "register_udf", body).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+        assert!(collect_batches(&open, "SELECT ident(id) FROM users").await.is_err());
+
+        let svc = DfFlightService::new(users_context().await).with_auth(privileged_auth());
+        let mismatched = UdfSpec {
+            return_type: "f64".into(),
+            ..spec("ident", IDENTITY)
+        };
+        let err = do_action(&svc, "register_udf", serde_json::to_vec(&mismatched).unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        let spin = r#"(module
+            (func (export "spin") (param i64) (result i64) (loop br 0) local.get 0))"#;
+        let body = serde_json::to_vec(&spec("spin", spin)).unwrap();
+        do_action(&svc, "register_udf", body).await.unwrap();
+        assert!(collect_batches(&svc, "SELECT spin(id) FROM users").await.is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! SQL 语句校验
+//!
+//! `do_get` 只允许只读查询：在交给 `SessionContext::sql` 之前，先用与其相同的 `DFParser`
+//! 解析语句（包括 `CREATE EXTERNAL TABLE`、`COPY TO` 等 DataFusion 扩展语法），拒绝 DDL/DML、
+//! 多语句负载与超长查询（`SELECT ... INTO` 会建表，按 DDL 处理）。DDL/DML 仅能通过经过认证的
+//! `do_action` 执行。
+
+use datafusion::sql::parser::{DFParser, Statement};
+use datafusion::sql::sqlparser::ast::{SetExpr, Statement as SqlStatement};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// 允许在 `do_get` 中执行的语句类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementKind {
+    /// SELECT / WITH ... SELECT / VALUES 等查询
+    Query,
+    /// EXPLAIN
+    Explain,
+    /// SHOW TABLES / SHOW COLUMNS 等只读元数据查询
+    Show,
+}
+
+impl StatementKind {
+    /// 将解析出的语句归类；不在枚举中的语句返回 `None`
+    fn of(statement: &Statement) -> Option<Self> {
+        let statement = match statement {
+            Statement::Statement(statement) => statement,
+            Statement::Explain(_) => return Some(StatementKind::Explain),
+            Statement::CreateExternalTable(_) | Statement::CopyTo(_) => return None,
+        };
+        match statement.as_ref() {
+            SqlStatement::Query(query) if !selects_into(&query.body) => Some(StatementKind::Query),
+            SqlStatement::Explain { .. } | SqlStatement::ExplainTable { .. } => {
+                Some(StatementKind::Explain)
+            }
+            SqlStatement::ShowTables { .. }
+            | SqlStatement::ShowColumns { .. }
+            | SqlStatement::ShowVariable { .. } => Some(StatementKind::Show),
+            _ => None,
+        }
+    }
+
+    /// 从配置字符串解析（大小写不敏感）
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "query" | "select" => Some(StatementKind::Query),
+            "explain" => Some(StatementKind::Explain),
+            "show" => Some(StatementKind::Show),
+            _ => None,
+        }
+    }
+}
+
+/// 查询体中是否含有 `SELECT ... INTO`
+fn selects_into(body: &SetExpr) -> bool {
+    match body {
+        SetExpr::Select(select) => select.into.is_some(),
+        SetExpr::Query(query) => selects_into(&query.body),
+        SetExpr::SetOperation { left, right, .. } => selects_into(left) || selects_into(right),
+        _ => false,
+    }
+}
+
+/// 查询校验器
+#[derive(Debug, Clone)]
+pub struct SqlValidator {
+    allowed: Vec<StatementKind>,
+    max_query_length: usize,
+}
+
+impl Default for SqlValidator {
+    fn default() -> Self {
+        Self {
+            allowed: vec![StatementKind::Query],
+            max_query_length: 64 * 1024,
+        }
+    }
+}
+
+impl SqlValidator {
+    pub fn new(allowed: Vec<StatementKind>, max_query_length: usize) -> Self {
+        Self {
+            allowed,
+            max_query_length,
+        }
+    }
+
+    /// 校验单条只读语句，违规时返回 `AppError::InvalidQuery` 并附带原因
+    pub fn validate(&self, sql: &str) -> Result<(), AppError> {
+        if sql.len() > self.max_query_length {
+            return Err(AppError::InvalidQuery(format!(
+                "查询长度 {} 超过上限 {}",
+                sql.len(),
+                self.max_query_length
+            )));
+        }
+
+        let statements: Vec<Statement> = DFParser::parse_sql(sql)
+            .map_err(|e| AppError::InvalidQuery(format!("SQL 解析失败: {}", e)))?
+            .into();
+
+        let statement = match statements.as_slice() {
+            [] => return Err(AppError::InvalidQuery("SQL 查询不能为空".to_string())),
+            [statement] => statement,
+            _ => {
+                return Err(AppError::InvalidQuery(format!(
+                    "不允许多语句查询（共 {} 条）",
+                    statements.len()
+                )));
+            }
+        };
+
+        match StatementKind::of(statement) {
+            Some(kind) if self.allowed.contains(&kind) => Ok(()),
+            _ => Err(AppError::InvalidQuery(format!(
+                "不允许的语句类型: {}",
+                statement_name(statement)
+            ))),
+        }
+    }
+}
+
+/// 取语句的首个关键字作为可读名称，用于错误信息
+fn statement_name(statement: &Statement) -> String {
+    let text = statement.to_string();
+    let mut words = text.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some(first), Some(second)) if matches!(first, "CREATE" | "DROP" | "ALTER") => {
+            format!("{} {}", first, second)
+        }
+        (Some(first), _) => first.to_string(),
+        _ => "UNKNOWN".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(err: AppError) -> String {
+        match err {
+            AppError::InvalidQuery(reason) => reason,
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn rejects_ddl_and_dml() {
+        let v = SqlValidator::default();
+        for sql in [
+            "DROP TABLE users",
+            "INSERT INTO users VALUES (6, 'Frank', 40, 'Austin')",
+            "CREATE EXTERNAL TABLE t STORED AS CSV LOCATION '/etc/passwd'",
+            "SELECT * INTO copied FROM users",
+        ] {
+            let err = v.validate(sql).expect_err(sql);
+            assert!(reason(err).contains("不允许的语句类型"), "{sql}");
+        }
+    }
+
+    #[test]
+    fn rejects_multi_statement_and_oversized() {
+        let v = SqlValidator::default();
+        let err = v.validate("SELECT 1; DROP TABLE users").unwrap_err();
+        assert!(reason(err).contains("多语句"));
+
+        let v = SqlValidator::new(vec![StatementKind::Query], 16);
+        let err = v.validate("SELECT * FROM users WHERE age > 30").unwrap_err();
+        assert!(reason(err).contains("超过上限"));
+    }
+
+    #[test]
+    fn accepts_select_and_cte() {
+        let v = SqlValidator::default();
+        v.validate("SELECT name, age FROM users WHERE age > 30").unwrap();
+        v.validate(
+            "WITH adults AS (SELECT * FROM users WHERE age >= 30) \
+             SELECT city, COUNT(*) FROM adults GROUP BY city",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn allowlist_controls_explain() {
+        let sql = "EXPLAIN SELECT * FROM users";
+        assert!(SqlValidator::default().validate(sql).is_err());
+        let v = SqlValidator::new(vec![StatementKind::Query, StatementKind::Explain], 1024);
+        v.validate(sql).unwrap();
+    }
+
+    #[test]
+    fn invalid_query_maps_to_permission_denied() {
+        let err = SqlValidator::default().validate("DROP TABLE users").unwrap_err();
+        let status: tonic::Status = err.into();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn privileged_actions_require_a_verified_caller() {
+        use crate::config::AuthConfig;
+        use crate::service_impl::DfFlightService;
+        use crate::test_util::{do_action, privileged_auth, users_context};
+        use arrow_flight::flight_service_server::FlightService;
+        use arrow_flight::Action;
+
+        async fn rejected(svc: &DfFlightService, request: tonic::Request<Action>) -> tonic::Code {
+            match svc.do_action(request).await {
+                Ok(_) => panic!("动作应被拒绝"),
+                Err(status) => status.code(),
+            }
+        }
+
+        let prepare = br#"{"sql": "SELECT * FROM users WHERE id = $1"}"#.to_vec();
+        let unauthenticated = |ty: &str| {
+            tonic::Request::new(Action {
+                r#type: ty.to_string(),
+                body: prepare.clone().into(),
+            })
+        };
+
+        // 默认配置（无令牌）：特权动作对任何调用方都不开放，只读动作不受影响
+        let open = DfFlightService::new(users_context().await);
+        for ty in ["prepare", "refresh_tables", "cancel_query"] {
+            assert_eq!(
+                rejected(&open, unauthenticated(ty)).await,
+                tonic::Code::Unauthenticated,
+                "{ty}"
+            );
+        }
+        assert!(open.do_action(unauthenticated("stats")).await.is_ok());
+
+        // 开启特权动作后，未携带或携带错误令牌的调用方仍被拒绝
+        let svc = DfFlightService::new(users_context().await).with_auth(privileged_auth());
+        assert_eq!(
+            rejected(&svc, unauthenticated("prepare")).await,
+            tonic::Code::Unauthenticated
+        );
+        let mut wrong = unauthenticated("prepare");
+        wrong
+            .metadata_mut()
+            .insert("authorization", "Bearer nope".parse().unwrap());
+        assert_eq!(rejected(&svc, wrong).await, tonic::Code::Unauthenticated);
+        do_action(&svc, "prepare", prepare.clone()).await.unwrap();
+
+        // 配置了令牌但未开放特权动作
+        let closed = DfFlightService::new(users_context().await).with_auth(AuthConfig {
+            privileged_actions: false,
+            ..privileged_auth()
+        });
+        let err = do_action(&closed, "prepare", prepare).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+    }
+}